@@ -4,11 +4,14 @@ use nezha_staking_lib::{
     accounts as ac,
     error::StakingError,
     fixed_point::FPUSDC,
-    francium::constants as fr_consts,
-    instruction::{self, CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput, WithdrawVault},
+    francium::{accounts as fr_accounts, constants as fr_consts},
+    instruction::{
+        self, CreateEpochWinnersMetaArgs, FranciumRewardsToken, TierWinnersMetaInput, WinnerInput, WithdrawVault,
+    },
     state::*,
 };
-use solana_client::rpc_client::RpcClient;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{rpc_client::RpcClient, rpc_response::RpcKeyedAccount};
 use solana_sdk::{
     borsh0_10::try_from_slice_unchecked,
     compute_budget,
@@ -464,6 +467,15 @@ fn main() {
                 WithdrawVault::Insurance,
             );
         }
+        "francium-withdraw-rewards" => {
+            for rewards_token in [FranciumRewardsToken::RewardsToken, FranciumRewardsToken::RewardsTokenB] {
+                francium_withdraw_rewards(&rpc, &staking_program_id, &admin_kp, rewards_token);
+            }
+        }
+        "dump-state" => {
+            let output = args.get(2).map(String::as_str).unwrap_or("snapshot.json");
+            dump_state(&rpc, &staking_program_id, &usdc_mint_pubkey, output);
+        }
         _ => {
             eprintln!("error: invalid command");
         }
@@ -491,6 +503,50 @@ fn withdraw_vault(rpc: &RpcClient, program_id: &Pubkey, admin_kp: &Keypair, usdc
     println!("Post Admin balance: {}", get_usdc_balance(rpc, &admin_usdc));
 }
 
+fn francium_withdraw_rewards(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    admin_kp: &Keypair,
+    rewards_token: FranciumRewardsToken,
+) {
+    let mints = fr_consts::get_mints();
+    let mint = rewards_token.mint(&mints);
+    let francium_authority = ac::francium_authority(program_id).pubkey;
+    let rewards_ata = get_associated_token_address(&francium_authority, &mint);
+    let balance = get_token_amount(rpc, &rewards_ata);
+
+    println!("Harvested rewards ({:?}) {}", rewards_token, balance);
+    if balance == 0 {
+        return;
+    }
+
+    let latest_data = rpc.get_account_data(&ac::latest_epoch(program_id).pubkey).unwrap();
+    let super_admin = LatestEpoch::try_from_slice(&latest_data).unwrap().pubkeys.super_admin;
+    let admin_pubkey = admin_kp.pubkey();
+    let treasury_ata = get_or_create_ata(&rpc, &admin_kp, &super_admin, &mint);
+
+    let ix = instruction::francium_withdraw_rewards(
+        &program_id,
+        &admin_pubkey,
+        &super_admin,
+        rewards_token,
+        balance,
+        &mints,
+    );
+    send_txs(&rpc, &admin_kp, &[ix]);
+
+    println!(
+        "Post Treasury balance ({:?}) {}",
+        rewards_token,
+        get_token_amount(rpc, &treasury_ata)
+    );
+}
+
+fn get_token_amount(rpc: &RpcClient, token_account: &Pubkey) -> u64 {
+    let account_data = rpc.get_account_data(token_account).unwrap();
+    spl_token::state::Account::unpack(&account_data).unwrap().amount
+}
+
 fn get_usdc_balance(rpc: &RpcClient, usdc_account: &Pubkey) -> FPUSDC {
     let account_data = rpc.get_account_data(usdc_account).unwrap();
     let account = spl_token::state::Account::unpack(&account_data).unwrap();
@@ -607,6 +663,63 @@ fn confirm_tx(rpc: &RpcClient, tx_id: Signature) {
     println!();
 }
 
+/// Export all the program accounts, vaults and Francium pool accounts in the snapshot format read
+/// by `nezha_testing::snapshot::Snapshot`.
+fn dump_state(rpc: &RpcClient, staking_program_id: &Pubkey, usdc_mint: &Pubkey, output: &str) {
+    let mut accounts = rpc.get_program_accounts(staking_program_id).unwrap();
+    println!("Program accounts: {}", accounts.len());
+
+    let mints = fr_consts::get_mints();
+    let francium_authority = ac::francium_authority(staking_program_id).pubkey;
+    let mut pubkeys = vec![
+        *usdc_mint,
+        ac::vault_authority(staking_program_id).pubkey,
+        ac::deposit_vault(staking_program_id).pubkey,
+        ac::pending_deposit_vault(staking_program_id).pubkey,
+        ac::treasury_vault(staking_program_id).pubkey,
+        ac::insurance_vault(staking_program_id).pubkey,
+        francium_authority,
+        fr_accounts::share_token_ata(&francium_authority, &mints),
+        fr_accounts::rewards_token_ata(&francium_authority, &mints),
+        fr_accounts::rewards_token_b_ata(&francium_authority, &mints),
+        fr_accounts::farming_info(&francium_authority, &mints),
+        fr_consts::LENDING_MARKET_INFO,
+        fr_consts::LENDING_MARKET_AUTHORITY,
+        fr_consts::LENDING_POOL_INFO,
+        fr_consts::LENDING_POOL_USDC_ACCOUNT,
+        fr_consts::FARMING_POOL,
+        fr_consts::FARMING_POOL_AUTHORITY,
+        fr_consts::FARMING_POOL_SHARE_TOKEN_ACCOUNT,
+        fr_consts::FARMING_POOL_REWARDS_TOKEN_ACCOUNT,
+        fr_consts::FARMING_POOL_REWARDS_TOKEN_B_ACCOUNT,
+        mints.share_token_mint,
+        mints.rewards_token_mint,
+        mints.rewards_token_b_mint,
+    ];
+    pubkeys.extend((1..=3).map(|tier| ac::prize_vault(staking_program_id, tier).pubkey));
+
+    // getMultipleAccounts accepts at most 100 accounts.
+    for chunk in pubkeys.chunks(100) {
+        let chunk_accounts = rpc.get_multiple_accounts(chunk).unwrap();
+        for (pubkey, account) in chunk.iter().zip(chunk_accounts) {
+            match account {
+                Some(account) => accounts.push((*pubkey, account)),
+                None => println!("Account not found: {pubkey}"),
+            }
+        }
+    }
+
+    let keyed_accounts: Vec<RpcKeyedAccount> = accounts
+        .iter()
+        .map(|(pubkey, account)| RpcKeyedAccount {
+            pubkey: pubkey.to_string(),
+            account: UiAccount::encode(pubkey, account, UiAccountEncoding::Base64, None, None),
+        })
+        .collect();
+    fs::write(output, serde_json::to_string_pretty(&keyed_accounts).unwrap()).unwrap();
+    println!("Wrote {} accounts to {output}", keyed_accounts.len());
+}
+
 fn show_epoch(rpc: &RpcClient, epoch_pubkey: &Pubkey) {
     let epoch_data = rpc.get_account_data(&epoch_pubkey).unwrap();
     let epoch: Result<Epoch, _> = try_from_slice_unchecked(&epoch_data);
@@ -154,6 +154,8 @@ pub enum InvalidConstant {
     RentSysvar = 20,
     #[error("Clock Sysvar")]
     ClockSysvar,
+    #[error("SlotHashes Sysvar")]
+    SlotHashesSysvar,
 }
 
 // The following conversion functions can be used for mapping a ProgramError to appropriate variant
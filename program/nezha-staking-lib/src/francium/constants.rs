@@ -33,28 +33,6 @@ const _MINTS: Mints = Mints {
     rewards_token_b_mint: static_pubkey!("EgiD69Uhf8t13CRPKz1btmtHj7SogeEjyPHfnT4d13XN"),
 };
 
-#[cfg(target_os = "solana")]
 pub fn get_mints() -> Mints {
     _MINTS.clone()
 }
-
-#[cfg(not(target_os = "solana"))]
-use std::cell::RefCell;
-
-#[cfg(not(target_os = "solana"))]
-thread_local! {
-    static MINTS: RefCell<Mints> = RefCell::new(_MINTS.clone());
-}
-
-#[cfg(not(target_os = "solana"))]
-pub fn get_mints() -> Mints {
-    MINTS.with(|mints| mints.borrow().clone())
-}
-
-#[cfg(not(target_os = "solana"))]
-pub fn set_mints(mints_: Mints) {
-    MINTS.with(|mints| {
-        let mut refm_ = mints.borrow_mut();
-        *refm_ = mints_;
-    });
-}
@@ -5,7 +5,7 @@ use solana_program::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     system_program,
-    sysvar::{clock, rent},
+    sysvar::{clock, rent, slot_hashes},
 };
 use spl_associated_token_account::get_associated_token_address;
 
@@ -121,6 +121,7 @@ pub fn cancel_stake_update(
 
 /// Admin: Create a new epoch.
 /// `YieldSplitCfg` defines how the yield of this epoch should be split.
+/// The slot hashes sysvar is optional for the program, the creation slot of the epoch is not recorded without it.
 pub fn create_epoch(
     program_id: &Pubkey,
     admin: &Pubkey,
@@ -140,6 +141,7 @@ pub fn create_epoch(
             [writable] ac::latest_epoch(program_id).pubkey,
             [] solana_program::system_program::id(),
             [] rent::id(),
+            [] slot_hashes::id(),
         ],
     )
 }
@@ -403,6 +405,33 @@ pub fn withdraw_vault(
     )
 }
 
+/// Admin: Withdraw the Francium farming rewards harvested by `francium_withdraw` to the treasury.
+/// The rewards are not USDC, so they're not part of the epoch returns. They are kept in the francium authority's
+/// rewards token accounts until they're sent to the super admin's rewards token account, which needs to exist.
+pub fn francium_withdraw_rewards(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    super_admin: &Pubkey,
+    rewards_token: FranciumRewardsToken,
+    amount: u64,
+    mints: &fr_consts::Mints,
+) -> Instruction {
+    let francium_authority = ac::francium_authority(program_id).pubkey;
+    Instruction::new_with_borsh(
+        program_id.clone(),
+        &StakingInstruction::FranciumWithdrawRewards { rewards_token, amount },
+        accounts![
+            [signer writable] admin.clone(),
+            [] francium_authority,
+            [] ac::latest_epoch(program_id).pubkey,
+            [writable] get_associated_token_address(&francium_authority, &rewards_token.mint(mints)),
+            [writable] get_associated_token_address(super_admin, &rewards_token.mint(mints)),
+            //
+            [] spl_token::id(),
+        ],
+    )
+}
+
 /// Admin: Complete and process the stake update.
 /// Currently, this is only allowed in the `Running` state of epochs.
 /// A background process is supposed to monitor the active stake update requests and complete them
@@ -3,6 +3,7 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::pubkey::Pubkey;
 
 use crate::accounts as ac;
+use crate::francium::constants::Mints;
 
 use crate::state::*;
 
@@ -74,6 +75,10 @@ pub enum StakingInstruction {
     RotateKey {
         key_type: RotateKeyType,
     },
+    FranciumWithdrawRewards {
+        rewards_token: FranciumRewardsToken,
+        amount: u64,
+    },
 }
 
 #[repr(C)]
@@ -123,3 +128,20 @@ pub enum RotateKeyType {
     Admin,
     Investor,
 }
+
+/// Francium farming rewards tokens harvested on `FranciumWithdraw`.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone, Copy)]
+pub enum FranciumRewardsToken {
+    RewardsToken,
+    RewardsTokenB,
+}
+
+impl FranciumRewardsToken {
+    pub fn mint(self, mints: &Mints) -> Pubkey {
+        match self {
+            FranciumRewardsToken::RewardsToken => mints.rewards_token_mint,
+            FranciumRewardsToken::RewardsTokenB => mints.rewards_token_b_mint,
+        }
+    }
+}
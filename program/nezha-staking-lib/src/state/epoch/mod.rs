@@ -1,5 +1,7 @@
 //! State of an epoch.
 
+use std::io::{Error, ErrorKind, Read, Result as IoResult, Write};
+
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::program_pack::IsInitialized;
 
@@ -13,8 +15,12 @@ mod tickets;
 pub use tickets::*;
 
 /// State of an epoch.
+///
+/// Borsh (de)serialization is implemented by hand so that `creation_slot` stays a trailing, optional field. Epoch
+/// accounts created before it was added were allocated at the old `EPOCH_LEN`: they are read with `creation_slot` as
+/// `None`, and a `None` is not written at all, so they keep fitting in their account.
 #[repr(C)]
-#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Epoch {
     pub account_type: AccountType,
     pub contract_version: ContractVersion,
@@ -34,6 +40,9 @@ pub struct Epoch {
     pub draw_enabled: Option<bool>,
     /// set after epoch ends
     pub end_at: Option<i64>,
+    /// set when creating the epoch, `None` for epochs created before it was recorded.
+    /// Must remain the last field, see [`Epoch`].
+    pub creation_slot: Option<CreationSlot>,
 }
 
 /// See the source of [`crate::processor::investment::withdraw`] and
@@ -80,8 +89,77 @@ pub struct Returns {
     pub tier3_prize: FPUSDC,
 }
 
+/// The most recent slot hash when the epoch was created. Tickets of the epoch are derived from it.
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub struct CreationSlot {
+    pub slot: u64,
+    pub hash: [u8; 32],
+}
+
 //
 
+impl BorshSerialize for Epoch {
+    fn serialize<W: Write>(&self, writer: &mut W) -> IoResult<()> {
+        self.account_type.serialize(writer)?;
+        self.contract_version.serialize(writer)?;
+        self.is_initialized.serialize(writer)?;
+        self.index.serialize(writer)?;
+        self.status.serialize(writer)?;
+        self.yield_split_cfg.serialize(writer)?;
+        self.start_at.serialize(writer)?;
+        self.expected_end_at.serialize(writer)?;
+        self.tickets_info.serialize(writer)?;
+        self.total_invested.serialize(writer)?;
+        self.returns.serialize(writer)?;
+        self.draw_enabled.serialize(writer)?;
+        self.end_at.serialize(writer)?;
+        // Not written when absent, so that epochs created before this field keep their length
+        if let Some(creation_slot) = &self.creation_slot {
+            1u8.serialize(writer)?;
+            creation_slot.serialize(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl BorshDeserialize for Epoch {
+    fn deserialize_reader<R: Read>(reader: &mut R) -> IoResult<Self> {
+        Ok(Self {
+            account_type: BorshDeserialize::deserialize_reader(reader)?,
+            contract_version: BorshDeserialize::deserialize_reader(reader)?,
+            is_initialized: BorshDeserialize::deserialize_reader(reader)?,
+            index: BorshDeserialize::deserialize_reader(reader)?,
+            status: BorshDeserialize::deserialize_reader(reader)?,
+            yield_split_cfg: BorshDeserialize::deserialize_reader(reader)?,
+            start_at: BorshDeserialize::deserialize_reader(reader)?,
+            expected_end_at: BorshDeserialize::deserialize_reader(reader)?,
+            tickets_info: BorshDeserialize::deserialize_reader(reader)?,
+            total_invested: BorshDeserialize::deserialize_reader(reader)?,
+            returns: BorshDeserialize::deserialize_reader(reader)?,
+            draw_enabled: BorshDeserialize::deserialize_reader(reader)?,
+            end_at: BorshDeserialize::deserialize_reader(reader)?,
+            creation_slot: deserialize_trailing_option(reader)?,
+        })
+    }
+}
+
+/// Like `Option<T>`, but the end of the data or the zero padding of the account also mean `None`.
+fn deserialize_trailing_option<R: Read, T: BorshDeserialize>(reader: &mut R) -> IoResult<Option<T>> {
+    let mut tag = [0u8; 1];
+    if reader.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    match tag[0] {
+        0 => Ok(None),
+        1 => Ok(Some(T::deserialize_reader(reader)?)),
+        tag => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid Option representation: {}", tag),
+        )),
+    }
+}
+
 impl HasAccountType for Epoch {
     fn account_type() -> AccountType {
         AccountType::Epoch
@@ -114,6 +192,7 @@ impl Epoch {
         option_max_len(1) +                         // draw_enabled: Option<bool>,
         //
         option_max_len(8) +                         // end_at: Option<i64>,
+        option_max_len(CreationSlot::max_len()) +   // creation_slot: Option<CreationSlot>,
         0 // (this line is for formatting)
     }
 }
@@ -137,6 +216,14 @@ impl InsuranceCfg {
     }
 }
 
+impl CreationSlot {
+    pub const fn max_len() -> usize {
+        8 +     // slot: u64,
+        32 +    // hash: [u8; 32],
+        0 //
+    }
+}
+
 impl Returns {
     pub const fn max_len() -> usize {
         FPUSDC::max_len() +     // total: FPUSDC,
@@ -151,13 +238,11 @@ impl Returns {
 
 //
 
-#[test]
-fn epoch_len() {
+#[cfg(test)]
+fn max_len_epoch() -> Epoch {
     use crate::fixed_point::FixedPoint;
-    use crate::state::EPOCH_LEN;
 
-    let mut v = Vec::new();
-    let e = Epoch {
+    Epoch {
         account_type: AccountType::Epoch,
         contract_version: ContractVersion::V1,
         is_initialized: true,
@@ -195,7 +280,45 @@ fn epoch_len() {
         draw_enabled: Some(true),
         //
         end_at: Some(0),
-    };
+        creation_slot: Some(CreationSlot { slot: 0, hash: [0; 32] }),
+    }
+}
+
+#[test]
+fn epoch_len() {
+    use crate::state::EPOCH_LEN;
+
+    let mut v = Vec::new();
+    let e = max_len_epoch();
     e.serialize(&mut v).unwrap();
     assert_eq!(v.len(), EPOCH_LEN);
 }
+
+#[test]
+fn epoch_without_creation_slot() {
+    use crate::state::EPOCH_LEN;
+    use solana_program::borsh0_10::try_from_slice_unchecked;
+
+    // Epoch accounts created before `creation_slot` was added
+    const OLD_EPOCH_LEN: usize = EPOCH_LEN - option_max_len(CreationSlot::max_len());
+
+    let e = Epoch {
+        creation_slot: None,
+        ..max_len_epoch()
+    };
+    let mut v = Vec::new();
+    e.serialize(&mut v).unwrap();
+    assert_eq!(v.len(), OLD_EPOCH_LEN);
+    assert_eq!(try_from_slice_unchecked::<Epoch>(&v).unwrap(), e);
+
+    // Zero padded account
+    let mut padded = v.clone();
+    padded.resize(EPOCH_LEN, 0);
+    assert_eq!(try_from_slice_unchecked::<Epoch>(&padded).unwrap(), e);
+
+    // New epochs keep their creation slot
+    let e = max_len_epoch();
+    let mut v = Vec::new();
+    e.serialize(&mut v).unwrap();
+    assert_eq!(try_from_slice_unchecked::<Epoch>(&v).unwrap(), e);
+}
@@ -6,6 +6,7 @@ use solana_program::{
     entrypoint::ProgramResult,
     msg,
     program::{invoke, invoke_signed},
+    program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
     system_instruction, sysvar,
@@ -21,6 +22,8 @@ use crate::{
     francium::accounts as fr_accounts,
     francium::constants as fr_consts,
     francium::instruction as fr_ixns,
+    instruction::FranciumRewardsToken,
+    solana,
     state::{LatestEpoch, TicketsInfo},
     utils::*,
 };
@@ -616,6 +619,11 @@ pub fn process_francium_withdraw<'a>(program_id: &Pubkey, accounts: &[AccountInf
     let rewards_amount = farming_user.staked_amount;
     msg!("Unstaking rewards {}", amount_to_ui_amount(rewards_amount, 6));
 
+    // Unstaking harvests the pending farming rewards into the rewards token ATAs.
+    // They aren't USDC, so they're not part of the returns. `FranciumWithdrawRewards` sends them to the treasury.
+    let rewards_token_balance_before = token_balance(rewards_token_ata)?;
+    let rewards_token_b_balance_before = token_balance(rewards_token_b_ata)?;
+
     invoke_signed(
         &fr_ixns::unstake_from_farming_pool(
             &lending_rewards_program.key,
@@ -652,6 +660,11 @@ pub fn process_francium_withdraw<'a>(program_id: &Pubkey, accounts: &[AccountInf
     )
     .map_err(StakingError::francium_farming_error)?;
 
+    let harvested_rewards = token_balance(rewards_token_ata)?.saturating_sub(rewards_token_balance_before);
+    let harvested_rewards_b = token_balance(rewards_token_b_ata)?.saturating_sub(rewards_token_b_balance_before);
+    msg!("Harvested rewards token {}", harvested_rewards);
+    msg!("Harvested rewards token B {}", harvested_rewards_b);
+
     let share_token_balance = {
         let share_token_ata_account = spl_token::state::Account::unpack(&share_token_ata.try_borrow_data()?)?;
 
@@ -722,3 +735,69 @@ pub fn process_francium_withdraw<'a>(program_id: &Pubkey, accounts: &[AccountInf
     )?;
     Ok(())
 }
+
+/// Withdraw the farming rewards harvested by `process_francium_withdraw` to the treasury: the super admin's ATA of the
+/// rewards token.
+pub fn process_francium_withdraw_rewards<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    rewards_token: FranciumRewardsToken,
+    amount: u64,
+) -> ProgramResult {
+    msg!("Ixn: Francium withdraw rewards: {:?}", rewards_token);
+
+    let account_info_iter = &mut accounts.iter();
+
+    load_accounts!(
+        account_info_iter,
+        //
+        admin,
+        francium_authority,
+        latest_epoch,
+        rewards_token_ata,
+        treasury_rewards_token_ata,
+        //
+        token_program,
+    );
+
+    ac::latest_epoch(program_id).verify(latest_epoch)?;
+    let latest_epoch = LatestEpoch::try_from_slice(&latest_epoch.data.borrow())?;
+
+    check_admin(admin, &latest_epoch)?;
+
+    let francium_authority_pda = ac::francium_authority(program_id);
+    francium_authority_pda.verify(francium_authority)?;
+
+    let mints = fr_consts::get_mints();
+    check_ata_account(
+        "rewards_token_ata",
+        rewards_token_ata.key,
+        francium_authority.key,
+        &rewards_token.mint(&mints),
+    )?;
+    check_ata_account(
+        "treasury_rewards_token_ata",
+        treasury_rewards_token_ata.key,
+        &latest_epoch.pubkeys.super_admin,
+        &rewards_token.mint(&mints),
+    )?;
+
+    check_token_program(token_program)?;
+
+    // End Of Checks
+
+    msg!("Transferring {}", amount);
+    solana::token_transfer(
+        token_program,
+        rewards_token_ata,
+        treasury_rewards_token_ata,
+        francium_authority,
+        Some(&francium_authority_pda.seeds()),
+        amount,
+    )?;
+    Ok(())
+}
+
+fn token_balance(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(spl_token::state::Account::unpack(&token_account.try_borrow_data()?)?.amount)
+}
@@ -78,6 +78,9 @@ pub fn process_instruction<'a>(program_id: &Pubkey, accounts: &[AccountInfo<'a>]
             winners_input,
         } => winners::process_publish_winners(program_id, accounts, page_index, winners_input),
         StakingInstruction::RotateKey { key_type } => process_rotate_key(program_id, accounts, key_type),
+        StakingInstruction::FranciumWithdrawRewards { rewards_token, amount } => {
+            investment::francium::process_francium_withdraw_rewards(program_id, accounts, rewards_token, amount)
+        }
         StakingInstruction::Removed1
         | StakingInstruction::Removed2
         | StakingInstruction::Removed3
@@ -230,8 +233,13 @@ pub fn process_create_epoch<'a>(
         system_program_info,
         rent_info
     );
+    // Optional, so that clients built before the creation slot was recorded can still create epochs
+    let slot_hashes_info = account_info_iter.next();
 
     check_rent_sysvar(rent_info)?;
+    if let Some(slot_hashes_info) = slot_hashes_info {
+        check_slot_hashes_sysvar(slot_hashes_info)?;
+    }
     check_system_program(system_program_info)?;
 
     ac::latest_epoch(program_id).verify(latest_epoch_info)?;
@@ -305,6 +313,17 @@ pub fn process_create_epoch<'a>(
     let index = latest_epoch.index + 1;
     msg!("Next epoch {}", index);
 
+    let creation_slot = match slot_hashes_info {
+        Some(slot_hashes_info) => {
+            let (slot, hash) = solana::sysvar_latest_slot_hash(slot_hashes_info)?;
+            Some(CreationSlot { slot, hash })
+        }
+        None => {
+            msg!("No slot hashes sysvar, creation slot not recorded");
+            None
+        }
+    };
+
     let epoch_pda = ac::epoch(program_id, index);
     epoch_pda.verify(epoch_info)?;
 
@@ -336,6 +355,7 @@ pub fn process_create_epoch<'a>(
         draw_enabled: None,
         //
         end_at: None,
+        creation_slot,
     };
 
     let mut epoch_data = epoch_info.try_borrow_mut_data()?;
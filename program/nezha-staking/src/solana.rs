@@ -81,3 +81,16 @@ pub fn token_transfer<'a>(
 pub fn sysvar_clock() -> Result<Clock, ProgramError> {
     clock::Clock::get()
 }
+
+/// The most recent entry of the SlotHashes sysvar.
+/// The sysvar is too large to deserialize, so only its first entry is read: a u64 length followed by
+/// (slot: u64, hash: [u8; 32]) entries, most recent first.
+pub fn sysvar_latest_slot_hash(slot_hashes_info: &AccountInfo) -> Result<(u64, [u8; 32]), ProgramError> {
+    let data = slot_hashes_info.try_borrow_data()?;
+    if data.len() < 48 || data[..8] == [0; 8] {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let slot = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let hash = data[16..48].try_into().unwrap();
+    Ok((slot, hash))
+}
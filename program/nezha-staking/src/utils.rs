@@ -37,6 +37,13 @@ pub fn check_clock_sysvar(account: &AccountInfo) -> Result<(), InvalidConstant>
     Ok(())
 }
 
+pub fn check_slot_hashes_sysvar(account: &AccountInfo) -> Result<(), InvalidConstant> {
+    if *account.key != solana_program::sysvar::slot_hashes::id() {
+        return Err(InvalidConstant::SlotHashesSysvar);
+    }
+    Ok(())
+}
+
 pub fn check_super_admin(account: &AccountInfo, latest_epoch: &LatestEpoch) -> Result<(), StakingError> {
     if !account.is_signer {
         return Err(StakingError::MissingSignature(SignatureType::SuperAdmin));
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use crate::accounts::Accounts;
 
 pub fn build_account_names_map(accounts: &Accounts) -> HashMap<Pubkey, String> {
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint);
     let program_id = &accounts.program_id;
 
     HashMap::from([
@@ -16,7 +16,7 @@ pub fn build_account_names_map(accounts: &Accounts) -> HashMap<Pubkey, String> {
         (accounts.owner.pubkey(), "owner".into()),
         (owner_usdc, "owner_usdc".into()),
         (accounts.investor.pubkey(), "investor".into()),
-        (accounts.usdc_mint.pubkey(), "usdc_mint".into()),
+        (accounts.usdc_mint, "usdc_mint".into()),
         (accounts.random1.pubkey(), "random1".into()),
         (accounts.random2.pubkey(), "random2".into()),
         (ac::vault_authority(program_id).pubkey, "vault_authority".into()),
@@ -8,7 +8,7 @@ pub struct Accounts {
     pub admin: Keypair,
     pub owner: Keypair,
     pub investor: Keypair,
-    pub usdc_mint: Keypair,
+    pub usdc_mint: Pubkey,
     pub random1: Keypair,
     pub random2: Keypair,
     pub nezha_vrf_program_id: Pubkey,
@@ -22,7 +22,7 @@ impl Accounts {
             admin: Keypair::new(),
             owner: Keypair::new(),
             investor: Keypair::new(),
-            usdc_mint: Keypair::new(),
+            usdc_mint: Pubkey::new_unique(),
             random1: Keypair::new(),
             random2: Keypair::new(),
             nezha_vrf_program_id: Pubkey::new_unique(),
@@ -9,7 +9,6 @@ use nezha_staking_lib::state::TicketsInfo;
 use nezha_testing::solana_test_runtime::Account;
 use nezha_vrf_lib::state::NezhaVrfRequest;
 use nezha_vrf_lib::state::NezhaVrfRequestStatus;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::accounts::Accounts;
 use anyhow::Context;
@@ -22,7 +21,7 @@ use nezha_staking_lib::{
     state::{LatestEpoch, Stake, YieldSplitCfg, MAX_NUM_WINNERS_PER_PAGE},
 };
 use solana_program::borsh0_10::try_from_slice_unchecked;
-use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction};
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey, system_instruction};
 use solana_sdk::{signature::Keypair, signer::Signer};
 use spl_associated_token_account::get_associated_token_address;
 use spl_associated_token_account::instruction::create_associated_token_account;
@@ -61,6 +60,58 @@ pub async fn create_mint(
         .await
 }
 
+/// Like `create_mint`, for addresses we don't have the keypair of, e.g. the mainnet mints hardcoded in the program.
+pub async fn set_mint(mint: &Pubkey, manager: &Pubkey, processor: &mut dyn SolanaTestRuntime) -> Result<()> {
+    let rent = processor.get_rent().await?;
+    let mint_state = spl_token::state::Mint {
+        mint_authority: COption::Some(*manager),
+        supply: 0,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(mint_state, &mut data)?;
+    processor.set_account(
+        mint,
+        &Account {
+            lamports: rent.minimum_balance(spl_token::state::Mint::LEN),
+            owner: spl_token::id(),
+            data,
+        },
+    );
+    Ok(())
+}
+
+/// Token account holding `amount` at an address we don't have the keypair of.
+pub async fn set_token_account(
+    address: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    amount: u64,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    let rent = processor.get_rent().await?;
+    let token_account = spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(token_account, &mut data)?;
+    processor.set_account(
+        address,
+        &Account {
+            lamports: rent.minimum_balance(spl_token::state::Account::LEN),
+            owner: spl_token::id(),
+            data,
+        },
+    );
+    Ok(())
+}
+
 pub async fn init(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<()> {
     processor
         .send_ixns(
@@ -69,7 +120,7 @@ pub async fn init(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) ->
                 &accounts.super_admin.pubkey(),
                 &accounts.admin.pubkey(),
                 &accounts.investor.pubkey(),
-                &accounts.usdc_mint.pubkey(),
+                &accounts.usdc_mint,
                 &accounts.nezha_vrf_program_id,
             )],
             &[&accounts.super_admin],
@@ -106,11 +157,7 @@ pub async fn create_epoch(
 ) -> Result<()> {
     let latest_epoch: LatestEpoch = get_latest_epoch(accounts, processor).await?;
     let index = latest_epoch.index + 1;
-    let expected_end_at = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs() as i64
-        + 60 * 60 * 24;
+    let expected_end_at = processor.get_clock().await?.unix_timestamp + 60 * 60 * 24;
 
     processor
         .send_ixns(
@@ -138,19 +185,29 @@ pub async fn request_stake_update(
     accounts: &Accounts,
     processor: &mut dyn SolanaTestRuntime,
 ) -> Result<()> {
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    request_stake_update_for(&accounts.owner, op, amount, accounts, processor).await
+}
+
+pub async fn request_stake_update_for(
+    owner: &Keypair,
+    op: StakeUpdateOp,
+    amount: FPUSDC,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    let owner_usdc = get_associated_token_address(&owner.pubkey(), &accounts.usdc_mint);
     processor
         .send_ixns(
             &[instruction::request_stake_update(
                 &accounts.program_id,
-                &accounts.owner.pubkey(),
+                &owner.pubkey(),
                 &owner_usdc,
                 match op {
                     StakeUpdateOp::Deposit => amount.as_usdc_i64(),
                     StakeUpdateOp::Withdraw => -amount.as_usdc_i64(),
                 },
             )],
-            &[&accounts.owner],
+            &[owner],
         )
         .await
 }
@@ -160,13 +217,23 @@ pub async fn approve_stake_update(
     processor: &mut dyn SolanaTestRuntime,
     op: StakeUpdateOp,
     amount: FPUSDC,
+) -> Result<()> {
+    approve_stake_update_for(&accounts.owner.pubkey(), accounts, processor, op, amount).await
+}
+
+pub async fn approve_stake_update_for(
+    owner: &Pubkey,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+    op: StakeUpdateOp,
+    amount: FPUSDC,
 ) -> Result<()> {
     processor
         .send_ixns(
             &[instruction::approve_stake_update(
                 &accounts.program_id,
                 &accounts.admin.pubkey(),
-                &accounts.owner.pubkey(),
+                owner,
                 match op {
                     StakeUpdateOp::Deposit => amount.as_usdc_i64(),
                     StakeUpdateOp::Withdraw => -amount.as_usdc_i64(),
@@ -178,13 +245,21 @@ pub async fn approve_stake_update(
 }
 
 pub async fn complete_stake_update(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<()> {
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    complete_stake_update_for(&accounts.owner.pubkey(), accounts, processor).await
+}
+
+pub async fn complete_stake_update_for(
+    owner: &Pubkey,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    let owner_usdc = get_associated_token_address(owner, &accounts.usdc_mint);
     processor
         .send_ixns(
             &[instruction::complete_stake_update(
                 &accounts.program_id,
                 &accounts.admin.pubkey(),
-                &accounts.owner.pubkey(),
+                owner,
                 &owner_usdc,
             )],
             &[&accounts.admin],
@@ -198,20 +273,30 @@ pub async fn cancel_stake_update(
     op: StakeUpdateOp,
     amount: FPUSDC,
 ) -> Result<()> {
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    cancel_stake_update_for(&accounts.owner, accounts, processor, op, amount).await
+}
+
+pub async fn cancel_stake_update_for(
+    owner: &Keypair,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+    op: StakeUpdateOp,
+    amount: FPUSDC,
+) -> Result<()> {
+    let owner_usdc = get_associated_token_address(&owner.pubkey(), &accounts.usdc_mint);
     processor
         .send_ixns(
             &[instruction::cancel_stake_update(
                 &accounts.program_id,
                 None,
-                &accounts.owner.pubkey(),
+                &owner.pubkey(),
                 &owner_usdc,
                 match op {
                     StakeUpdateOp::Deposit => amount.as_usdc_i64(),
                     StakeUpdateOp::Withdraw => -amount.as_usdc_i64(),
                 },
             )],
-            &[&accounts.owner],
+            &[owner],
         )
         .await
 }
@@ -222,7 +307,7 @@ pub async fn cancel_stake_update_by_admin(
     op: StakeUpdateOp,
     amount: FPUSDC,
 ) -> Result<()> {
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint);
     processor
         .send_ixns(
             &[instruction::cancel_stake_update(
@@ -245,7 +330,7 @@ pub async fn yield_withdraw_by_investor(
     accounts: &Accounts,
     processor: &mut dyn SolanaTestRuntime,
 ) -> Result<()> {
-    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint.pubkey());
+    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint);
     let epoch_index = get_latest_epoch(accounts, processor).await?.index;
     processor
         .send_ixns(
@@ -266,7 +351,7 @@ pub async fn yield_deposit_by_investor(
     accounts: &Accounts,
     processor: &mut dyn SolanaTestRuntime,
 ) -> Result<()> {
-    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint.pubkey());
+    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint);
     let epoch_index = get_latest_epoch(accounts, processor).await?.index;
 
     processor
@@ -388,12 +473,33 @@ pub async fn claim_winning(
     tier: u8,
     accounts: &Accounts,
     processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    claim_winning_for(
+        &accounts.owner.pubkey(),
+        epoch_index,
+        page,
+        winner_index,
+        tier,
+        accounts,
+        processor,
+    )
+    .await
+}
+
+pub async fn claim_winning_for(
+    owner: &Pubkey,
+    epoch_index: u64,
+    page: u32,
+    winner_index: u32,
+    tier: u8,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
 ) -> Result<()> {
     processor
         .send_ixns(
             &[instruction::claim_winning(
                 &accounts.program_id,
-                &accounts.owner.pubkey(),
+                owner,
                 epoch_index,
                 page,
                 winner_index,
@@ -405,7 +511,7 @@ pub async fn claim_winning(
 }
 
 pub async fn fund_jackpot(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<()> {
-    let admin_usdc = get_associated_token_address(&accounts.admin.pubkey(), &accounts.usdc_mint.pubkey());
+    let admin_usdc = get_associated_token_address(&accounts.admin.pubkey(), &accounts.usdc_mint);
     let epoch_index = get_latest_epoch(accounts, processor).await?.index;
     processor
         .send_ixns(
@@ -529,7 +635,15 @@ pub async fn get_epoch_pubkey(accounts: &Accounts, processor: &mut dyn SolanaTes
 }
 
 pub async fn get_owner_stake_balance(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<FPUSDC> {
-    let stake_pubkey = ac::stake(&accounts.program_id, &accounts.owner.pubkey()).pubkey;
+    get_stake_balance(&accounts.owner.pubkey(), accounts, processor).await
+}
+
+pub async fn get_stake_balance(
+    owner: &Pubkey,
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<FPUSDC> {
+    let stake_pubkey = ac::stake(&accounts.program_id, owner).pubkey;
     let stake: Option<Stake> = get_optional_data(stake_pubkey, processor).await?;
     if let Some(stake) = stake {
         let latest_epoch = get_latest_epoch(accounts, processor).await?;
@@ -544,12 +658,12 @@ pub async fn get_owner_stake_balance(accounts: &Accounts, processor: &mut dyn So
 }
 
 pub async fn get_owner_usdc_balance(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<FPUSDC> {
-    let ata = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
+    let ata = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint);
     get_usdc_balance_by_account(&ata, processor).await
 }
 
 pub async fn get_investor_usdc_balance(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<FPUSDC> {
-    let ata = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint.pubkey());
+    let ata = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint);
     get_usdc_balance_by_account(&ata, processor).await
 }
 
@@ -1,11 +1,12 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::{accounts::Accounts, setup::setup_test_runtime};
+use crate::{accounts::Accounts, actions::get_data, setup::setup_test_runtime};
 use anyhow::Result;
 use nezha_staking_lib::{
+    accounts as ac,
     fixed_point::{test_utils::fp, FPUSDC},
     instruction,
-    state::{InsuranceCfg, YieldSplitCfg},
+    state::{Epoch, InsuranceCfg, YieldSplitCfg},
 };
 use solana_program_test::tokio;
 use solana_sdk::signer::Signer;
@@ -54,6 +55,40 @@ async fn works() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn without_slot_hashes() -> Result<()> {
+    let accounts = Accounts::new();
+    let mut runtime = setup_test_runtime(&accounts).await?;
+
+    let epoch_index = 1;
+    let yield_split_cfg = YieldSplitCfg {
+        jackpot: fp("100_000.0"),
+        insurance: InsuranceCfg {
+            premium: fp("3.0"),
+            probability: fp("0.000001"),
+        },
+        treasury_ratio: fp("0.5"),
+        tier2_prize_share: 2,
+        tier3_prize_share: 1,
+    };
+    let mut create_epoch_ixn = instruction::create_epoch(
+        &accounts.program_id,
+        &accounts.admin.pubkey(),
+        epoch_index,
+        one_day_from_now(),
+        yield_split_cfg,
+    );
+    // Clients built before the creation slot was recorded don't pass the slot hashes sysvar
+    create_epoch_ixn.accounts.pop();
+
+    runtime.send_ixns(&[create_epoch_ixn], &[&accounts.admin]).await?;
+
+    let epoch: Epoch = get_data(ac::epoch(&accounts.program_id, epoch_index).pubkey, runtime.as_mut()).await?;
+    assert_eq!(epoch.creation_slot, None);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn with_invalid_values() -> Result<()> {
     let accounts = Accounts::new();
@@ -200,3 +235,37 @@ async fn with_invalid_values() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn cant_create_after_expected_end() -> Result<()> {
+    let accounts = Accounts::new();
+    let mut runtime = setup_test_runtime(&accounts).await?;
+
+    let expected_end_at = runtime.get_clock().await?.unix_timestamp + 60 * 60;
+    let yield_split_cfg = YieldSplitCfg {
+        jackpot: fp("100_000.0"),
+        insurance: InsuranceCfg {
+            premium: fp("3.0"),
+            probability: fp("0.000001"),
+        },
+        treasury_ratio: fp("0.5"),
+        tier2_prize_share: 2,
+        tier3_prize_share: 1,
+    };
+    let create_epoch_ixn = instruction::create_epoch(
+        &accounts.program_id,
+        &accounts.admin.pubkey(),
+        1,
+        expected_end_at,
+        yield_split_cfg,
+    );
+
+    runtime.warp_to_timestamp(expected_end_at).await?;
+    let res = runtime.send_ixns(&[create_epoch_ixn.clone()], &[&accounts.admin]).await;
+    assert!(res.is_err());
+
+    runtime.warp_to_timestamp(expected_end_at - 60).await?;
+    runtime.send_ixns(&[create_epoch_ixn], &[&accounts.admin]).await?;
+
+    Ok(())
+}
@@ -31,8 +31,8 @@ async fn test_end_to_end_with_account_mutations() -> Result<()> {
     let accounts = Accounts::new();
     let account_names = account_names::build_account_names_map(&accounts);
 
-    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint.pubkey());
-    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint.pubkey());
+    let owner_usdc = get_associated_token_address(&accounts.owner.pubkey(), &accounts.usdc_mint);
+    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint);
 
     let mut processor = setup_test_runtime_without_init(&accounts).await?;
 
@@ -44,7 +44,6 @@ async fn test_end_to_end_with_account_mutations() -> Result<()> {
         &accounts.admin,
         &accounts.owner,
         &accounts.investor,
-        &accounts.usdc_mint,
         &accounts.random1,
         &accounts.random2,
     ];
@@ -101,7 +100,7 @@ async fn test_end_to_end_with_account_mutations() -> Result<()> {
                 &accounts.super_admin.pubkey(),
                 &accounts.admin.pubkey(),
                 &accounts.investor.pubkey(),
-                &accounts.usdc_mint.pubkey(),
+                &accounts.usdc_mint,
                 &accounts.nezha_vrf_program_id,
             ),
             signers: vec![&accounts.super_admin],
@@ -1,75 +1,69 @@
 use crate::{
     accounts::Accounts,
-    actions::{self, create_mint, random_tickets_info, random_yield_split_cfg},
+    actions::{self, random_tickets_info, random_yield_split_cfg, set_mint, set_token_account},
+    processors,
     setup::setup_test_runtime_without_init,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use nezha_staking_lib::{
     fixed_point::test_utils::fp,
-    francium::constants::{set_mints, Mints},
-    instruction,
+    francium::accounts as fr_accounts,
+    francium::constants::{self as fr_consts, get_mints, Mints},
+    instruction::{self, FranciumRewardsToken},
+    state::Epoch,
 };
+use solana_program::pubkey::Pubkey;
 use solana_program_test::tokio;
-use solana_sdk::{signature::Keypair, signer::Signer};
+use solana_sdk::signer::Signer;
+use spl_associated_token_account::get_associated_token_address;
 
 use nezha_testing::solana_test_runtime::SolanaTestRuntime;
 
-struct FranciumAccounts {
-    share_token_mint: Keypair,
-    rewards_token_mint: Keypair,
-    rewards_token_b_mint: Keypair,
-}
+/// Liquidity the mocked lending pool starts with. It pays the returns on top of the deposits.
+const LENDING_POOL_LIQUIDITY: u64 = 1_000 * 1_000_000;
+/// Rewards the mocked farming pool starts with.
+const FARMING_POOL_REWARDS: u64 = 1_000 * 1_000_000;
 
-impl FranciumAccounts {
-    fn new() -> Self {
-        Self {
-            share_token_mint: Keypair::new(),
-            rewards_token_mint: Keypair::new(),
-            rewards_token_b_mint: Keypair::new(),
-        }
-    }
-}
+/// The BPF program only knows the mainnet francium mints, so both runtimes use them.
+/// We don't have their keypairs, so they're set directly instead of going through `create_mint`.
+async fn setup() -> Result<(Accounts, Mints, Box<dyn SolanaTestRuntime + Send + Sync>)> {
+    let mints = get_mints();
+    let mut accounts = Accounts::new();
+    accounts.usdc_mint = mints.usdc_mint;
 
-fn get_mints(accounts: &Accounts, francium_accounts: &FranciumAccounts) -> Mints {
-    Mints {
-        usdc_mint: accounts.usdc_mint.pubkey(),
-        share_token_mint: francium_accounts.share_token_mint.pubkey(),
-        rewards_token_mint: francium_accounts.rewards_token_mint.pubkey(),
-        rewards_token_b_mint: francium_accounts.rewards_token_b_mint.pubkey(),
-    }
-}
+    let mut processor = setup_test_runtime_without_init(&accounts).await?;
 
-async fn create_mints(
-    accounts: &Accounts,
-    francium_accounts: &FranciumAccounts,
-    processor: &mut (dyn SolanaTestRuntime + Send + Sync),
-) -> Result<()> {
-    for mint in &[
-        &francium_accounts.share_token_mint,
-        &francium_accounts.rewards_token_mint,
-        &francium_accounts.rewards_token_b_mint,
+    let admin = accounts.admin.pubkey();
+    for mint in [
+        &mints.share_token_mint,
+        &mints.rewards_token_mint,
+        &mints.rewards_token_b_mint,
     ] {
-        create_mint(mint, &accounts.admin.pubkey(), None, processor).await?;
+        set_mint(mint, &admin, processor.as_mut()).await?;
     }
-    Ok(())
-}
-
-#[tokio::test]
-async fn happy_path() -> Result<()> {
-    // We can't test francium using bpf tests because we don't have their code nor blobs.
-    if cfg!(feature = "test-bpf") {
-        return Ok(());
+    for (token_account, mint, owner, amount) in [
+        (
+            &fr_consts::LENDING_POOL_USDC_ACCOUNT,
+            &mints.usdc_mint,
+            &fr_consts::LENDING_MARKET_AUTHORITY,
+            LENDING_POOL_LIQUIDITY,
+        ),
+        (
+            &fr_consts::FARMING_POOL_REWARDS_TOKEN_ACCOUNT,
+            &mints.rewards_token_mint,
+            &fr_consts::FARMING_POOL_AUTHORITY,
+            FARMING_POOL_REWARDS,
+        ),
+        (
+            &fr_consts::FARMING_POOL_REWARDS_TOKEN_B_ACCOUNT,
+            &mints.rewards_token_b_mint,
+            &fr_consts::FARMING_POOL_AUTHORITY,
+            FARMING_POOL_REWARDS,
+        ),
+    ] {
+        set_token_account(token_account, mint, owner, amount, processor.as_mut()).await?;
     }
 
-    let accounts = Accounts::new();
-    let francium_accounts = FranciumAccounts::new();
-    let mints = get_mints(&accounts, &francium_accounts);
-    set_mints(mints.clone());
-
-    let mut processor = setup_test_runtime_without_init(&accounts).await?;
-
-    create_mints(&accounts, &francium_accounts, processor.as_mut()).await?;
-
     actions::init(&accounts, processor.as_mut()).await?;
     francium_init(&accounts, &mints, processor.as_mut()).await?;
     actions::create_epoch(&accounts, random_yield_split_cfg(), processor.as_mut()).await?;
@@ -90,12 +84,166 @@ async fn happy_path() -> Result<()> {
     .await?;
     actions::complete_stake_update(&accounts, processor.as_mut()).await?;
 
+    Ok((accounts, mints, processor))
+}
+
+#[tokio::test]
+async fn happy_path() -> Result<()> {
+    let (accounts, mints, mut processor) = setup().await?;
+
     francium_invest(1, 1, &accounts, &mints, processor.as_mut()).await?;
     francium_withdraw(1, &accounts, &mints, processor.as_mut()).await?;
 
     Ok(())
 }
 
+// The francium mocks run natively, so the budgets cover our side of the CPIs, not francium's own execution.
+// The rest of the transaction compute unit limit is left to francium.
+
+/// `FranciumInvest` is sent without a compute unit limit, so it gets the default 200k.
+const FRANCIUM_INVEST_COMPUTE_BUDGET: u64 = 140_000;
+/// `FranciumWithdraw` is sent with a 300k compute unit limit. The BPF tests run with 250k.
+const FRANCIUM_WITHDRAW_COMPUTE_BUDGET: u64 = 200_000;
+/// Staking -> Francium -> Token.
+const FRANCIUM_INVOKE_DEPTH: usize = 3;
+
+#[tokio::test]
+async fn francium_within_compute_budget() -> Result<()> {
+    processors::francium::set_farming_rewards(5_000_000, 7_000_000);
+    let (accounts, mints, mut processor) = setup().await?;
+
+    let invest_profile = processor
+        .send_ixns_with_profile(
+            &[instruction::francium_invest(
+                &accounts.program_id,
+                &accounts.admin.pubkey(),
+                1,
+                random_tickets_info(1),
+                &mints,
+            )],
+            &[&accounts.admin],
+        )
+        .await?;
+    let withdraw_profile = processor
+        .send_ixns_with_profile(
+            &[instruction::francium_withdraw(
+                &accounts.program_id,
+                &accounts.admin.pubkey(),
+                1,
+                &mints,
+            )],
+            &[&accounts.admin],
+        )
+        .await?;
+
+    for (name, profile, budget) in [
+        ("Invest", invest_profile, FRANCIUM_INVEST_COMPUTE_BUDGET),
+        ("Withdraw", withdraw_profile, FRANCIUM_WITHDRAW_COMPUTE_BUDGET),
+    ] {
+        profile.ensure_compute_units_within(budget).context(name)?;
+        profile
+            .ensure_invoke_depth_within(FRANCIUM_INVOKE_DEPTH)
+            .context(name)?;
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn harvest_rewards() -> Result<()> {
+    processors::francium::set_farming_rewards(5_000_000, 7_000_000);
+    let (accounts, mints, mut processor) = setup().await?;
+
+    francium_invest(1, 1, &accounts, &mints, processor.as_mut()).await?;
+    francium_withdraw(1, &accounts, &mints, processor.as_mut()).await?;
+
+    // Rewards are held by the francium authority. They aren't USDC, so the returns are only the USDC withdrawn.
+    let francium_authority = nezha_staking_lib::accounts::francium_authority(&accounts.program_id).pubkey;
+    let rewards_token_ata = fr_accounts::rewards_token_ata(&francium_authority, &mints);
+    let rewards_token_b_ata = fr_accounts::rewards_token_b_ata(&francium_authority, &mints);
+    assert_eq!(token_balance(&rewards_token_ata, processor.as_mut()).await?, 5_000_000);
+    assert_eq!(
+        token_balance(&rewards_token_b_ata, processor.as_mut()).await?,
+        7_000_000
+    );
+
+    let epoch_pubkey = actions::get_epoch_pubkey(&accounts, processor.as_mut()).await?;
+    let epoch: Epoch = actions::get_data(epoch_pubkey, processor.as_mut()).await?;
+    assert_eq!(epoch.total_invested, Some(fp("100.0")));
+    assert_eq!(epoch.returns.unwrap().total, fp("120.0"));
+
+    // Withdraw to the treasury
+
+    let super_admin = accounts.super_admin.pubkey();
+    actions::create_token_account(&super_admin, &mints.rewards_token_mint, processor.as_mut()).await?;
+    actions::create_token_account(&super_admin, &mints.rewards_token_b_mint, processor.as_mut()).await?;
+    let treasury_rewards_token_ata = get_associated_token_address(&super_admin, &mints.rewards_token_mint);
+    let treasury_rewards_token_b_ata = get_associated_token_address(&super_admin, &mints.rewards_token_b_mint);
+
+    // Can't withdraw more than harvested
+    let res = francium_withdraw_rewards(
+        FranciumRewardsToken::RewardsToken,
+        5_000_001,
+        &accounts,
+        &mints,
+        processor.as_mut(),
+    )
+    .await;
+    assert!(res.is_err());
+
+    // Can't withdraw anywhere else, even to the admin
+    let admin = accounts.admin.pubkey();
+    actions::create_token_account(&admin, &mints.rewards_token_mint, processor.as_mut()).await?;
+    let admin_rewards_token_ata = get_associated_token_address(&admin, &mints.rewards_token_mint);
+    let mut ixn = instruction::francium_withdraw_rewards(
+        &accounts.program_id,
+        &admin,
+        &super_admin,
+        FranciumRewardsToken::RewardsToken,
+        5_000_000,
+        &mints,
+    );
+    ixn.accounts[4].pubkey = admin_rewards_token_ata;
+    let res = processor.send_ixns(&[ixn], &[&accounts.admin]).await;
+    assert!(res.is_err());
+
+    francium_withdraw_rewards(
+        FranciumRewardsToken::RewardsToken,
+        5_000_000,
+        &accounts,
+        &mints,
+        processor.as_mut(),
+    )
+    .await?;
+    francium_withdraw_rewards(
+        FranciumRewardsToken::RewardsTokenB,
+        7_000_000,
+        &accounts,
+        &mints,
+        processor.as_mut(),
+    )
+    .await?;
+
+    assert_eq!(token_balance(&rewards_token_ata, processor.as_mut()).await?, 0);
+    assert_eq!(token_balance(&rewards_token_b_ata, processor.as_mut()).await?, 0);
+    assert_eq!(
+        token_balance(&treasury_rewards_token_ata, processor.as_mut()).await?,
+        5_000_000
+    );
+    assert_eq!(
+        token_balance(&treasury_rewards_token_b_ata, processor.as_mut()).await?,
+        7_000_000
+    );
+    assert_eq!(token_balance(&admin_rewards_token_ata, processor.as_mut()).await?, 0);
+
+    Ok(())
+}
+
+async fn token_balance(account: &Pubkey, processor: &mut (dyn SolanaTestRuntime + Send + Sync)) -> Result<u64> {
+    let account: spl_token::state::Account = actions::get_data_packed(*account, processor).await?;
+    Ok(account.amount)
+}
+
 pub async fn francium_init(
     accounts: &Accounts,
     mints: &Mints,
@@ -152,3 +300,25 @@ pub async fn francium_withdraw(
         )
         .await
 }
+
+pub async fn francium_withdraw_rewards(
+    rewards_token: FranciumRewardsToken,
+    amount: u64,
+    accounts: &Accounts,
+    mints: &Mints,
+    processor: &mut (dyn SolanaTestRuntime + Send + Sync),
+) -> Result<()> {
+    processor
+        .send_ixns(
+            &[instruction::francium_withdraw_rewards(
+                &accounts.program_id,
+                &accounts.admin.pubkey(),
+                &accounts.super_admin.pubkey(),
+                rewards_token,
+                amount,
+                mints,
+            )],
+            &[&accounts.admin],
+        )
+        .await
+}
@@ -7,8 +7,10 @@ mod create_epoch;
 mod end_to_end_account_mutations;
 mod francium;
 mod init;
+mod model_based;
 mod returns;
 mod rotate_key;
 mod setup;
+mod snapshot;
 mod stake_update;
 mod winners;
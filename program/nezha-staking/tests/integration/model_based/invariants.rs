@@ -0,0 +1,236 @@
+//! Invariants checked after every step of the model based tests.
+
+use anyhow::{ensure, Context, Result};
+use nezha_staking_lib::{
+    accounts as ac,
+    fixed_point::FPUSDC,
+    state::{CumulativeReturnRate, EpochStatus, Ratio, Returns, Stake, StakeUpdateRequest, StakeUpdateState},
+};
+use solana_sdk::{signature::Keypair, signer::Signer};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{accounts::Accounts, actions::*};
+use nezha_testing::solana_test_runtime::SolanaTestRuntime;
+
+use super::model::{Model, RequestState};
+
+fn abs_diff(a: u64, b: u64) -> u64 {
+    a.max(b) - a.min(b)
+}
+
+pub async fn check_invariants(
+    model: &Model,
+    users: &[&Keypair],
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    let program_id = &accounts.program_id;
+    let latest_epoch = get_latest_epoch(accounts, processor).await?;
+
+    ensure!(
+        latest_epoch.index == model.epoch_index,
+        "Epoch index: program {} != model {}",
+        latest_epoch.index,
+        model.epoch_index
+    );
+    ensure!(
+        latest_epoch.status == model.status,
+        "Epoch status: program {:?} != model {:?}",
+        latest_epoch.status,
+        model.status
+    );
+
+    // Stakes and stake update requests.
+
+    let mut total_stake = 0u64;
+    for (i, (user, user_model)) in users.iter().zip(&model.users).enumerate() {
+        let stake: Option<Stake> = get_optional_data(ac::stake(program_id, &user.pubkey()).pubkey, processor).await?;
+        ensure!(
+            stake.is_some() == user_model.stake.is_some(),
+            "User {i}: stake exists: program {} != model {}",
+            stake.is_some(),
+            user_model.stake.is_some()
+        );
+        if let (Some(stake), Some(expected)) = (stake, user_model.stake) {
+            let balance: FPUSDC = stake
+                .balance
+                .get_amount(latest_epoch.cumulative_return_rate)
+                .with_context(|| format!("User {i}: stake balance overflow"))?
+                .change_precision();
+            let balance = balance.as_usdc();
+            ensure!(
+                abs_diff(balance, expected) <= model.dust(),
+                "User {i}: stake balance: program {balance} != model {expected}"
+            );
+            total_stake += balance;
+        }
+
+        let request: Option<StakeUpdateRequest> =
+            get_optional_data(ac::stake_update_request(program_id, &user.pubkey()).pubkey, processor).await?;
+        match (request, user_model.request) {
+            (None, None) => {}
+            (Some(request), Some(expected)) => {
+                ensure!(
+                    request.amount == expected.amount,
+                    "User {i}: request amount: program {} != model {}",
+                    request.amount,
+                    expected.amount
+                );
+                let expected_state = match expected.state {
+                    RequestState::PendingApproval => StakeUpdateState::PendingApproval,
+                    RequestState::Queued => StakeUpdateState::Queued,
+                };
+                ensure!(
+                    request.state == expected_state,
+                    "User {i}: request state: program {:?} != model {:?}",
+                    request.state,
+                    expected.state
+                );
+            }
+            (request, expected) => {
+                anyhow::bail!("User {i}: request: program {request:?} != model {expected:?}");
+            }
+        }
+
+        let wallet = get_usdc_balance_by_account(
+            &get_associated_token_address(&user.pubkey(), &accounts.usdc_mint),
+            processor,
+        )
+        .await?
+        .as_usdc();
+        ensure!(
+            abs_diff(wallet, user_model.wallet) <= model.dust(),
+            "User {i}: wallet: program {wallet} != model {}",
+            user_model.wallet
+        );
+    }
+
+    // Vaults.
+
+    let deposit_vault = get_usdc_balance_by_account(&ac::deposit_vault(program_id), processor)
+        .await?
+        .as_usdc();
+    if model.status == EpochStatus::Yielding {
+        // Everything is with the investor. Claims are not made while yielding.
+        ensure!(deposit_vault == 0, "Deposit vault not empty while yielding: {deposit_vault}");
+    } else {
+        // Stake balances are rounded down, so the vault can hold a little more than the stakes,
+        // never less.
+        ensure!(
+            deposit_vault >= total_stake && deposit_vault - total_stake <= model.dust() * model.num_stakes(),
+            "Deposit vault {deposit_vault} doesn't match the sum of stakes {total_stake}"
+        );
+    }
+
+    let pending_deposit_vault = get_usdc_balance_by_account(&ac::pending_deposit_vault(program_id), processor)
+        .await?
+        .as_usdc();
+    ensure!(
+        pending_deposit_vault == model.total_pending_deposits(),
+        "Pending deposit vault {pending_deposit_vault} != pending deposits {}",
+        model.total_pending_deposits()
+    );
+
+    for (tier, pending_funds) in [
+        (2, latest_epoch.pending_funds.tier2_prize),
+        (3, latest_epoch.pending_funds.tier3_prize),
+    ] {
+        let prize_vault = get_usdc_balance_by_account(&ac::prize_vault(program_id, tier), processor)
+            .await?
+            .as_usdc();
+        let expected = pending_funds.as_usdc() + model.total_unclaimed_prizes(tier);
+        ensure!(
+            prize_vault == expected,
+            "Tier {tier} prize vault {prize_vault} != pending funds {} + unclaimed prizes {}",
+            pending_funds,
+            model.total_unclaimed_prizes(tier)
+        );
+    }
+
+    let insurance_vault = get_usdc_balance_by_account(&ac::insurance_vault(program_id), processor)
+        .await?
+        .as_usdc();
+    ensure!(
+        insurance_vault == model.insurance,
+        "Insurance vault {insurance_vault} != total insurance paid {}",
+        model.insurance
+    );
+    ensure!(
+        latest_epoch.pending_funds.insurance.as_usdc() <= insurance_vault,
+        "Pending insurance {} is more than insurance vault {insurance_vault}",
+        latest_epoch.pending_funds.insurance
+    );
+
+    let treasury_vault = get_usdc_balance_by_account(&ac::treasury_vault(program_id), processor)
+        .await?
+        .as_usdc();
+    ensure!(
+        treasury_vault == model.treasury,
+        "Treasury vault {treasury_vault} != total treasury paid {}",
+        model.treasury
+    );
+
+    Ok(())
+}
+
+/// Checks done when the investor returns the funds.
+/// The cumulative return rate must stay the same on gains, and shrink by exactly
+/// `return_amount / total_invested` on losses. Every returned USDC must be accounted for.
+pub fn check_returns(
+    rate_before: CumulativeReturnRate,
+    rate_after: CumulativeReturnRate,
+    total_invested: u64,
+    return_amount: u64,
+    returns: &Returns,
+) -> Result<()> {
+    ensure!(
+        rate_after <= rate_before,
+        "Cumulative return rate increased: {:?} -> {:?}",
+        rate_before,
+        rate_after
+    );
+
+    if return_amount < total_invested {
+        let expected = rate_before
+            .checked_mul(Ratio {
+                numerator: FPUSDC::from_usdc(return_amount),
+                denominator: FPUSDC::from_usdc(total_invested),
+            })
+            .context("Cumulative return rate overflow")?;
+        ensure!(
+            rate_after == expected,
+            "Cumulative return rate after a loss: {:?} != {:?}",
+            rate_after,
+            expected
+        );
+    } else {
+        ensure!(
+            rate_after == rate_before,
+            "Cumulative return rate changed without a loss: {:?} -> {:?}",
+            rate_before,
+            rate_after
+        );
+    }
+
+    ensure!(
+        returns.total.as_usdc() == return_amount,
+        "Returns total {} != return amount {return_amount}",
+        returns.total
+    );
+    ensure!(
+        returns.deposit_back.as_usdc() == return_amount.min(total_invested),
+        "Deposit back {} != min(return amount {return_amount}, invested {total_invested})",
+        returns.deposit_back
+    );
+    let distributed = returns.deposit_back.as_usdc()
+        + returns.insurance.as_usdc()
+        + returns.treasury.as_usdc()
+        + returns.tier2_prize.as_usdc()
+        + returns.tier3_prize.as_usdc();
+    ensure!(
+        distributed == return_amount,
+        "Distributed {distributed} != return amount {return_amount}: {returns:?}"
+    );
+
+    Ok(())
+}
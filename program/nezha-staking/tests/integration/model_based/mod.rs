@@ -0,0 +1,400 @@
+//! Model based tests.
+//!
+//! Random sequences of deposits, withdrawals, cancellations, epochs with random returns
+//! (including losses), winners and claims are run against the program and a simple reference
+//! model ([`model::Model`]). After every step the program state is checked against the model and
+//! a set of invariants ([`invariants`]).
+//!
+//! Environment variables:
+//! - `NEZHA_MODEL_SEED`: Run only the given seed. Use this to reproduce a failure.
+//! - `NEZHA_MODEL_CASES`: Number of seeds to run. Defaults to 8.
+//! - `NEZHA_MODEL_STEPS`: Number of steps per seed. Defaults to 100.
+
+mod invariants;
+mod model;
+
+use anyhow::{bail, ensure, Context, Result};
+use nezha_staking_lib::{
+    accounts as ac,
+    fixed_point::FPUSDC,
+    instruction::{CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput},
+    state::{Epoch, EpochStatus, EpochWinnersPage, MAX_NUM_WINNERS_PER_PAGE},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+use crate::{accounts::Accounts, actions::*, setup::*};
+use nezha_testing::solana_test_runtime::SolanaTestRuntime;
+
+use invariants::{check_invariants, check_returns};
+use model::{Model, Op, PrizeModel, RequestModel, RequestState};
+
+const DEFAULT_NUM_CASES: u64 = 8;
+const DEFAULT_NUM_STEPS: u64 = 100;
+
+/// Enough to pay for the yield of any number of epochs the driver runs.
+const INVESTOR_USDC: u64 = 1_000_000;
+
+#[tokio::test]
+async fn test_random_sequences_hold_invariants() -> Result<()> {
+    let num_steps = env_u64("NEZHA_MODEL_STEPS").unwrap_or(DEFAULT_NUM_STEPS);
+    let seeds: Vec<u64> = match env_u64("NEZHA_MODEL_SEED") {
+        Some(seed) => vec![seed],
+        None => (0..env_u64("NEZHA_MODEL_CASES").unwrap_or(DEFAULT_NUM_CASES)).collect(),
+    };
+
+    for seed in seeds {
+        run(seed, num_steps)
+            .await
+            .with_context(|| format!("Model based test failed. Reproduce with NEZHA_MODEL_SEED={seed}"))?;
+    }
+
+    Ok(())
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.parse().unwrap_or_else(|_| panic!("{name} should be a number")))
+}
+
+async fn run(seed: u64, num_steps: u64) -> Result<()> {
+    let accounts = Accounts::new();
+    let mut processor = setup_test_runtime(&accounts).await?;
+    mint_tokens(
+        &accounts.investor.pubkey(),
+        INVESTOR_USDC * 1_000_000,
+        &accounts.usdc_mint,
+        &accounts.admin,
+        processor.as_mut(),
+    )
+    .await
+    .context("Mint investor USDC")?;
+
+    let users = [&accounts.owner, &accounts.random1, &accounts.random2];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut model = Model::new();
+
+    for step in 0..num_steps {
+        let op = model.generate_op(&mut rng);
+        apply(&op, &mut model, &users, &accounts, processor.as_mut())
+            .await
+            .with_context(|| format!("Step {step}: {op:?}"))?;
+        check_invariants(&model, &users, &accounts, processor.as_mut())
+            .await
+            .with_context(|| format!("Invariant violated after step {step}: {op:?}"))?;
+    }
+
+    Ok(())
+}
+
+/// Whether an operation is expected to succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Ok,
+    Err,
+    /// The outcome depends on rounding dust the model doesn't track. The model follows whatever
+    /// the program did.
+    Either,
+}
+
+impl Expect {
+    fn from_bool(ok: bool) -> Self {
+        if ok {
+            Expect::Ok
+        } else {
+            Expect::Err
+        }
+    }
+
+    /// Returns whether the operation succeeded.
+    fn check(self, res: Result<()>) -> Result<bool> {
+        match (self, res) {
+            (Expect::Ok, Err(e)) => Err(e.context("Expected to succeed")),
+            (Expect::Err, Ok(())) => bail!("Expected to fail, but succeeded"),
+            (_, res) => Ok(res.is_ok()),
+        }
+    }
+}
+
+async fn apply(
+    op: &Op,
+    model: &mut Model,
+    users: &[&Keypair],
+    accounts: &Accounts,
+    processor: &mut dyn SolanaTestRuntime,
+) -> Result<()> {
+    match op.clone() {
+        Op::CreateEpoch => {
+            let expect = Expect::from_bool(model.status == EpochStatus::Ended);
+            let res = create_epoch(accounts, random_yield_split_cfg(), processor).await;
+            if expect.check(res)? {
+                model.epoch_index += 1;
+                model.status = EpochStatus::Running;
+                model.total_invested = 0;
+            }
+        }
+        Op::RequestDeposit { user, amount } => {
+            let user_model = &mut model.users[user];
+            let expect = Expect::from_bool(user_model.request.is_none() && user_model.wallet >= amount);
+            let res = request_stake_update_for(
+                users[user],
+                StakeUpdateOp::Deposit,
+                FPUSDC::from_usdc(amount),
+                accounts,
+                processor,
+            )
+            .await;
+            if expect.check(res)? {
+                user_model.wallet -= amount;
+                user_model.request = Some(RequestModel {
+                    amount: amount as i64,
+                    state: RequestState::PendingApproval,
+                });
+            }
+        }
+        Op::RequestWithdraw { user, amount } => {
+            let dust = model.dust();
+            let user_model = &mut model.users[user];
+            let balance = user_model.stake.unwrap_or_default();
+            let expect = if user_model.request.is_some() || user_model.stake.is_none() || amount > balance + dust {
+                Expect::Err
+            } else if amount + dust <= balance {
+                Expect::Ok
+            } else {
+                Expect::Either
+            };
+            let res = request_stake_update_for(
+                users[user],
+                StakeUpdateOp::Withdraw,
+                FPUSDC::from_usdc(amount),
+                accounts,
+                processor,
+            )
+            .await;
+            if expect.check(res)? {
+                user_model.request = Some(RequestModel {
+                    amount: -(amount as i64),
+                    state: RequestState::Queued,
+                });
+            }
+        }
+        Op::Approve { user } => {
+            let user_model = &mut model.users[user];
+            let (op, amount) = request_op_and_amount(user_model.request);
+            let expect = Expect::from_bool(matches!(
+                user_model.request,
+                Some(RequestModel {
+                    state: RequestState::PendingApproval,
+                    ..
+                })
+            ));
+            let res = approve_stake_update_for(&users[user].pubkey(), accounts, processor, op, amount).await;
+            if expect.check(res)? {
+                user_model.request.as_mut().unwrap().state = RequestState::Queued;
+            }
+        }
+        Op::Complete { user } => {
+            let user_model = &mut model.users[user];
+            let expect = Expect::from_bool(
+                model.status == EpochStatus::Running
+                    && matches!(
+                        user_model.request,
+                        Some(RequestModel {
+                            state: RequestState::Queued,
+                            ..
+                        })
+                    ),
+            );
+            let res = complete_stake_update_for(&users[user].pubkey(), accounts, processor).await;
+            if expect.check(res)? {
+                let request = user_model.request.take().unwrap();
+                let balance = user_model.stake.unwrap_or_default();
+                if request.amount > 0 {
+                    user_model.stake = Some(balance + request.amount as u64);
+                } else {
+                    // The program caps the withdrawal to the balance.
+                    let amount = request.amount.unsigned_abs().min(balance);
+                    user_model.stake = Some(balance - amount);
+                    user_model.wallet += amount;
+                }
+            }
+        }
+        Op::Cancel { user } => {
+            let user_model = &mut model.users[user];
+            let (op, amount) = request_op_and_amount(user_model.request);
+            // Cancelling a request that doesn't exist is a no-op.
+            let res = cancel_stake_update_for(users[user], accounts, processor, op, amount).await;
+            Expect::Ok.check(res)?;
+            if let Some(request) = user_model.request.take() {
+                if request.amount > 0 {
+                    user_model.wallet += request.amount as u64;
+                }
+            }
+        }
+        Op::EnterInvestment { num_tickets } => {
+            let expect = Expect::from_bool(model.status == EpochStatus::Running);
+            let res = yield_withdraw_by_investor(num_tickets, accounts, processor).await;
+            if expect.check(res)? {
+                let epoch: Epoch = get_data(get_epoch_pubkey(accounts, processor).await?, processor).await?;
+                model.total_invested = epoch.total_invested.context("total_invested not set")?.as_usdc();
+                model.status = EpochStatus::Yielding;
+            }
+        }
+        Op::ReturnInvestment { return_bps } => {
+            let expect = Expect::from_bool(model.status == EpochStatus::Yielding);
+            let total_invested = model.total_invested;
+            let return_amount = if total_invested == 0 {
+                0
+            } else {
+                (total_invested as u128 * return_bps as u128 / 10_000).max(1) as u64
+            };
+            let rate_before = get_latest_epoch(accounts, processor).await?.cumulative_return_rate;
+
+            let res = yield_deposit_by_investor(FPUSDC::from_usdc(return_amount), accounts, processor).await;
+            if expect.check(res)? {
+                let latest_epoch = get_latest_epoch(accounts, processor).await?;
+                let epoch: Epoch = get_data(latest_epoch.epoch, processor).await?;
+                let returns = epoch.returns.context("returns not set")?;
+                check_returns(
+                    rate_before,
+                    latest_epoch.cumulative_return_rate,
+                    total_invested,
+                    return_amount,
+                    &returns,
+                )?;
+
+                if return_amount < total_invested {
+                    for user_model in &mut model.users {
+                        if let Some(stake) = &mut user_model.stake {
+                            *stake = (*stake as u128 * return_amount as u128 / total_invested as u128) as u64;
+                        }
+                    }
+                }
+                model.insurance += returns.insurance.as_usdc();
+                model.treasury += returns.treasury.as_usdc();
+                model.status = if epoch.draw_enabled.context("draw_enabled not set")? {
+                    EpochStatus::Finalising
+                } else {
+                    EpochStatus::Ended
+                };
+            }
+        }
+        Op::PublishWinners { tier2, tier3 } => {
+            let expect = Expect::from_bool(model.status == EpochStatus::Finalising);
+            set_winning_combination(model.epoch_index, [0u8; 6], accounts, processor).await?;
+
+            let tier_meta = |winners: &[(usize, u32)]| TierWinnersMetaInput {
+                total_num_winners: winners.len() as u32,
+                total_num_winning_tickets: winners.iter().map(|(_, n)| n).sum(),
+            };
+            let meta_args = CreateEpochWinnersMetaArgs {
+                tier1_meta: tier_meta(&[]),
+                tier2_meta: tier_meta(&tier2),
+                tier3_meta: tier_meta(&tier3),
+            };
+            let mut winners = Vec::new();
+            for (tier, tier_winners) in [(2u8, &tier2), (3u8, &tier3)] {
+                for (user, num_winning_tickets) in tier_winners.iter() {
+                    winners.push((
+                        *user,
+                        WinnerInput {
+                            index: winners.len() as u32,
+                            address: users[*user].pubkey(),
+                            tier,
+                            num_winning_tickets: *num_winning_tickets,
+                        },
+                    ));
+                }
+            }
+            let winners_input: Vec<WinnerInput> = winners.iter().map(|(_, w)| w.clone()).collect();
+
+            let res = publish_epoch_winners(&meta_args, &winners_input, accounts, processor).await;
+            if expect.check(res)? {
+                for (user, winner) in winners {
+                    let page = winner.index / MAX_NUM_WINNERS_PER_PAGE as u32;
+                    let epoch_winners_page: EpochWinnersPage = get_data(
+                        ac::epoch_winners_page(&accounts.program_id, model.epoch_index, page).pubkey,
+                        processor,
+                    )
+                    .await?;
+                    let prize = epoch_winners_page
+                        .winners
+                        .iter()
+                        .find(|w| w.index == winner.index)
+                        .with_context(|| format!("Winner {} not found in page {page}", winner.index))?
+                        .prize;
+                    model.users[user].unclaimed_prizes.push(PrizeModel {
+                        epoch_index: model.epoch_index,
+                        page,
+                        winner_index: winner.index,
+                        tier: winner.tier,
+                        amount: prize.as_usdc(),
+                    });
+                }
+                model.status = EpochStatus::Ended;
+            }
+        }
+        Op::Claim { user } => {
+            // Claims made while yielding put the prize into the deposit vault after it was
+            // invested, and it doesn't go through the returns calculation. Skip them so that the
+            // deposit vault invariant stays exact.
+            if model.status == EpochStatus::Yielding {
+                return Ok(());
+            }
+
+            let (prize, expect) = if let Some(prize) = model.users[user].unclaimed_prizes.first() {
+                (*prize, Expect::Ok)
+            } else if let Some(prize) = model.users[user].claimed_prizes.last() {
+                // Claiming twice
+                (*prize, Expect::Err)
+            } else if let Some(prize) = model.users.iter().flat_map(|u| u.unclaimed_prizes.iter()).next() {
+                // Claiming someone else's prize
+                (*prize, Expect::Err)
+            } else {
+                return Ok(());
+            };
+
+            let res = claim_winning_for(
+                &users[user].pubkey(),
+                prize.epoch_index,
+                prize.page,
+                prize.winner_index,
+                prize.tier,
+                accounts,
+                processor,
+            )
+            .await;
+            if expect.check(res)? {
+                let user_model = &mut model.users[user];
+                user_model.unclaimed_prizes.remove(0);
+                user_model.claimed_prizes.push(prize);
+                let stake = user_model.stake.as_mut().context("Prize claimed without a stake")?;
+                *stake += prize.amount;
+            }
+        }
+    }
+
+    for (i, user_model) in model.users.iter().enumerate() {
+        ensure!(
+            user_model.stake.is_some() || user_model.unclaimed_prizes.is_empty(),
+            "User {i} has prizes but no stake"
+        );
+    }
+
+    Ok(())
+}
+
+/// Arguments to approve or cancel the given request. Some valid looking values are returned when
+/// there is no request.
+fn request_op_and_amount(request: Option<RequestModel>) -> (StakeUpdateOp, FPUSDC) {
+    match request {
+        Some(request) if request.amount < 0 => (
+            StakeUpdateOp::Withdraw,
+            FPUSDC::from_usdc(request.amount.unsigned_abs()),
+        ),
+        Some(request) => (StakeUpdateOp::Deposit, FPUSDC::from_usdc(request.amount as u64)),
+        None => (StakeUpdateOp::Deposit, FPUSDC::from_usdc(1_000_000)),
+    }
+}
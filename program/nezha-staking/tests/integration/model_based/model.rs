@@ -0,0 +1,196 @@
+//! Reference model of the staking program.
+//!
+//! The model is deliberately simpler than the program: balances are plain `u64`s in USDC
+//! precision and losses are applied to every balance directly instead of going through the
+//! `CumulativeReturnRate`. The invariants compare the two and allow for rounding dust.
+
+use nezha_staking_lib::state::EpochStatus;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+
+use crate::setup::USDC_TO_MINT;
+
+/// Number of wallets (owner, random1, random2) the driver stakes with.
+pub const NUM_USERS: usize = 3;
+
+/// Upper limit of a single deposit, in USDC precision.
+const MAX_DEPOSIT: u64 = 200 * 1_000_000;
+
+/// Rounding dust allowed per stake account per epoch, in USDC precision.
+const DUST_PER_EPOCH: u64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestState {
+    PendingApproval,
+    Queued,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequestModel {
+    /// Negative means withdrawal, positive means deposit.
+    pub amount: i64,
+    pub state: RequestState,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PrizeModel {
+    pub epoch_index: u64,
+    pub page: u32,
+    pub winner_index: u32,
+    pub tier: u8,
+    pub amount: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserModel {
+    /// USDC in the user's token account.
+    pub wallet: u64,
+    /// `None` till the first deposit is completed and the stake account is created.
+    pub stake: Option<u64>,
+    pub request: Option<RequestModel>,
+    pub unclaimed_prizes: Vec<PrizeModel>,
+    pub claimed_prizes: Vec<PrizeModel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    pub epoch_index: u64,
+    pub status: EpochStatus,
+    pub users: Vec<UserModel>,
+    /// Amount moved out of the deposit vault when entering investment.
+    pub total_invested: u64,
+    /// Sum of all insurance payments.
+    pub insurance: u64,
+    /// Sum of all treasury payments.
+    pub treasury: u64,
+}
+
+/// Operations the driver can perform.
+/// The driver generates both valid and invalid operations. Whether an operation is expected to
+/// succeed is decided by the model at the time it's applied.
+#[derive(Debug, Clone)]
+pub enum Op {
+    CreateEpoch,
+    RequestDeposit { user: usize, amount: u64 },
+    RequestWithdraw { user: usize, amount: u64 },
+    Approve { user: usize },
+    Complete { user: usize },
+    Cancel { user: usize },
+    EnterInvestment { num_tickets: u64 },
+    /// Return `return_bps / 10_000` times the invested amount.
+    ReturnInvestment { return_bps: u64 },
+    PublishWinners { tier2: Vec<(usize, u32)>, tier3: Vec<(usize, u32)> },
+    Claim { user: usize },
+}
+
+impl Model {
+    pub fn new() -> Self {
+        Self {
+            epoch_index: 0,
+            status: EpochStatus::Ended,
+            users: vec![
+                UserModel {
+                    wallet: USDC_TO_MINT * 1_000_000,
+                    stake: None,
+                    request: None,
+                    unclaimed_prizes: Vec::new(),
+                    claimed_prizes: Vec::new(),
+                };
+                NUM_USERS
+            ],
+            total_invested: 0,
+            insurance: 0,
+            treasury: 0,
+        }
+    }
+
+    /// Max difference allowed between the model balance and the program balance of a stake.
+    pub fn dust(&self) -> u64 {
+        DUST_PER_EPOCH * (self.epoch_index + 1)
+    }
+
+    pub fn num_stakes(&self) -> u64 {
+        self.users.iter().filter(|u| u.stake.is_some()).count() as u64
+    }
+
+    /// Sum of the deposits which are sitting in the pending deposit vault.
+    pub fn total_pending_deposits(&self) -> u64 {
+        self.users
+            .iter()
+            .filter_map(|u| u.request)
+            .filter(|r| r.amount > 0)
+            .map(|r| r.amount as u64)
+            .sum()
+    }
+
+    pub fn total_unclaimed_prizes(&self, tier: u8) -> u64 {
+        self.users
+            .iter()
+            .flat_map(|u| u.unclaimed_prizes.iter())
+            .filter(|p| p.tier == tier)
+            .map(|p| p.amount)
+            .sum()
+    }
+
+    /// Generate the next operation. Biased towards operations that are valid in the current
+    /// state, but invalid operations are generated too.
+    pub fn generate_op(&self, rng: &mut StdRng) -> Op {
+        let user = rng.gen_range(0, NUM_USERS);
+        let user_model = &self.users[user];
+
+        let epoch_op = match self.status {
+            EpochStatus::Ended => Op::CreateEpoch,
+            EpochStatus::Running => Op::EnterInvestment {
+                num_tickets: rng.gen_range(1, 4),
+            },
+            // Losses are as likely as gains.
+            EpochStatus::Yielding => Op::ReturnInvestment {
+                return_bps: rng.gen_range(5_000, 15_001),
+            },
+            EpochStatus::Finalising => {
+                let mut tier2 = Vec::new();
+                let mut tier3 = Vec::new();
+                for (i, u) in self.users.iter().enumerate() {
+                    // Stake account is needed to claim the prize
+                    if u.stake.is_none() {
+                        continue;
+                    }
+                    match rng.gen_range(0, 3) {
+                        0 => tier2.push((i, rng.gen_range(1, 4))),
+                        1 => tier3.push((i, rng.gen_range(1, 4))),
+                        _ => {}
+                    }
+                }
+                Op::PublishWinners { tier2, tier3 }
+            }
+        };
+
+        // Move the epoch forward every so often so that all the epoch states get visited.
+        if self.status == EpochStatus::Finalising || rng.gen_bool(0.2) {
+            return epoch_op;
+        }
+
+        let balance = user_model.stake.unwrap_or_default();
+        let mut candidates = vec![
+            Op::RequestDeposit {
+                user,
+                amount: rng.gen_range(1, user_model.wallet.min(MAX_DEPOSIT).max(1) + 1),
+            },
+            Op::Approve { user },
+            Op::Complete { user },
+            Op::Cancel { user },
+            Op::Claim { user },
+        ];
+        if balance > self.dust() {
+            candidates.push(Op::RequestWithdraw {
+                user,
+                amount: rng.gen_range(1, balance - self.dust() + 1),
+            });
+        }
+        // Withdrawing more than the balance should always fail.
+        candidates.push(Op::RequestWithdraw {
+            user,
+            amount: balance + self.dust() + 1_000_000,
+        });
+        candidates.choose(rng).unwrap().clone()
+    }
+}
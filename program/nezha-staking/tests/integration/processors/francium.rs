@@ -1,8 +1,8 @@
 use francium_lending_pool::instruction::LendingInstruction;
 use francium_lending_rewards_pool::{instruction::FarmingInstructions, state::farming_user::FarmingUser};
-use nezha_staking_lib::fixed_point::FPUSDC;
+use nezha_staking_lib::{fixed_point::FPUSDC, francium::constants as fr_consts};
 use nezha_utils::load_accounts;
-use solana_program::program::invoke_signed;
+use solana_program::program::{invoke, invoke_signed};
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
 use solana_program::sysvar::Sysvar;
@@ -11,15 +11,24 @@ use solana_program::{msg, system_instruction};
 use std::cell::RefCell;
 use std::thread::LocalKey;
 
+const LENDING_MARKET_AUTHORITY_BUMP: u8 = 255;
+const FARMING_POOL_AUTHORITY_BUMP: u8 = 252;
+
 thread_local! {
     static FRANCIUM_RETURN_RATE: std::cell::RefCell<FPUSDC>  = RefCell::new(FPUSDC::zero());
     static FRANCIUM_DEPOSIT: std::cell::RefCell<FPUSDC>  = RefCell::new(FPUSDC::zero());
+    static FRANCIUM_FARMING_REWARDS: std::cell::RefCell<(u64, u64)>  = RefCell::new((0, 0));
 }
 
 pub fn init(return_rate: FPUSDC) {
     set_thread_local_refcell(&FRANCIUM_RETURN_RATE, return_rate);
 }
 
+/// Rewards token and rewards token B amounts paid out on every `UnStake`.
+pub fn set_farming_rewards(rewards: u64, rewards_b: u64) {
+    set_thread_local_refcell(&FRANCIUM_FARMING_REWARDS, (rewards, rewards_b));
+}
+
 pub fn process_francium_lending(_program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
     let ixn = LendingInstruction::unpack(input)?;
     match ixn {
@@ -28,17 +37,79 @@ pub fn process_francium_lending(_program_id: &Pubkey, accounts: &[AccountInfo],
         }
         LendingInstruction::DepositToLendingPool { liquidity_amount } => {
             msg!("Fr Ixn: DepositToLendingPool");
+            let accounts_iter = &mut accounts.iter();
+            load_accounts!(
+                accounts_iter,
+                //
+                user_usdc_token_info,
+                _user_share_token_info,
+                _lending_pool_info,
+                lending_pool_usdc_token_info,
+                _share_token_mint_info,
+                _lending_market_info,
+                _lending_market_authority_info,
+                user_info,
+                _sysvar_clock_info,
+                token_program_info,
+            );
+            invoke(
+                &spl_token::instruction::transfer(
+                    token_program_info.key,
+                    user_usdc_token_info.key,
+                    lending_pool_usdc_token_info.key,
+                    user_info.key,
+                    &[],
+                    liquidity_amount,
+                )?,
+                &[
+                    user_usdc_token_info.clone(),
+                    lending_pool_usdc_token_info.clone(),
+                    user_info.clone(),
+                    token_program_info.clone(),
+                ],
+            )?;
             deposit(FPUSDC::from_usdc(liquidity_amount));
         }
         LendingInstruction::WithdrawFromLendingPool { share_amount: _ } => {
             msg!("Fr Ixn: WithdrawFromLendingPool");
-            let user_usdc_token_info = &accounts[1];
+            let accounts_iter = &mut accounts.iter();
+            load_accounts!(
+                accounts_iter,
+                //
+                _user_share_token_info,
+                user_usdc_token_info,
+                _lending_pool_info,
+                _share_token_mint_info,
+                lending_pool_usdc_token_info,
+                _lending_market_info,
+                lending_market_authority_info,
+                _user_info,
+                _sysvar_clock_info,
+                token_program_info,
+            );
             let amount = get_deposit();
             withdraw(amount);
-            let mut token_account =
-                spl_token::state::Account::unpack(&user_usdc_token_info.try_borrow_mut_data().unwrap())?;
-            token_account.amount += amount.as_usdc();
-            spl_token::state::Account::pack(token_account, &mut user_usdc_token_info.try_borrow_mut_data().unwrap())?;
+            // The lending market authority is the PDA of the lending market. It owns the pool's token accounts.
+            invoke_signed(
+                &spl_token::instruction::transfer(
+                    token_program_info.key,
+                    lending_pool_usdc_token_info.key,
+                    user_usdc_token_info.key,
+                    lending_market_authority_info.key,
+                    &[],
+                    amount.as_usdc(),
+                )?,
+                &[
+                    lending_pool_usdc_token_info.clone(),
+                    user_usdc_token_info.clone(),
+                    lending_market_authority_info.clone(),
+                    token_program_info.clone(),
+                ],
+                &[&[
+                    fr_consts::LENDING_MARKET_INFO.as_ref(),
+                    &[LENDING_MARKET_AUTHORITY_BUMP],
+                ]],
+            )?;
         }
         _ => unimplemented!(),
     }
@@ -121,16 +192,42 @@ pub fn process_francium_rewards(program_id: &Pubkey, accounts: &[AccountInfo], i
                 _user_info,
                 _user_farming_info,
                 _user_stake_token_info,
-                _user_rewards_info,
-                _user_rewards_b_info,
+                user_rewards_info,
+                user_rewards_b_info,
                 _farming_pool_info,
-                _farming_pool_authority_info,
+                farming_pool_authority_info,
                 _farming_pool_stake_token_info,
-                _farming_pool_rewards_token_info,
-                _farming_pool_rewards_b_token_info,
-                _token_program_info,
+                farming_pool_rewards_token_info,
+                farming_pool_rewards_b_token_info,
+                token_program_info,
                 _sysvar_clock_info,
             );
+            let (rewards, rewards_b) = get_thread_local_refcell(&FRANCIUM_FARMING_REWARDS);
+            // The farming pool authority is the PDA of the farming pool. It owns the pool's token accounts.
+            let farming_pool_authority_seeds: &[&[u8]] =
+                &[fr_consts::FARMING_POOL.as_ref(), &[FARMING_POOL_AUTHORITY_BUMP]];
+            for (source_info, destination_info, amount) in [
+                (farming_pool_rewards_token_info, user_rewards_info, rewards),
+                (farming_pool_rewards_b_token_info, user_rewards_b_info, rewards_b),
+            ] {
+                invoke_signed(
+                    &spl_token::instruction::transfer(
+                        token_program_info.key,
+                        source_info.key,
+                        destination_info.key,
+                        farming_pool_authority_info.key,
+                        &[],
+                        amount,
+                    )?,
+                    &[
+                        source_info.clone(),
+                        destination_info.clone(),
+                        farming_pool_authority_info.clone(),
+                        token_program_info.clone(),
+                    ],
+                    &[farming_pool_authority_seeds],
+                )?;
+            }
         }
         _ => unimplemented!(),
     }
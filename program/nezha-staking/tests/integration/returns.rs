@@ -789,8 +789,8 @@ async fn test_withdraw_vault() -> Result<()> {
     end_epoch(winners, &accounts, processor.as_mut()).await?;
 
     let destination_owner = Pubkey::new_unique();
-    let destination_ata = get_associated_token_address(&destination_owner, &accounts.usdc_mint.pubkey());
-    create_token_account(&destination_owner, &accounts.usdc_mint.pubkey(), processor.as_mut()).await?;
+    let destination_ata = get_associated_token_address(&destination_owner, &accounts.usdc_mint);
+    create_token_account(&destination_owner, &accounts.usdc_mint, processor.as_mut()).await?;
 
     assert_eq!(
         fp(100.0),
@@ -880,7 +880,7 @@ async fn incorrect_epoch_index() -> Result<()> {
         complete_stake_update(&accounts, processor.as_mut()).await?;
     }
 
-    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint.pubkey());
+    let investor_usdc = get_associated_token_address(&accounts.investor.pubkey(), &accounts.usdc_mint);
 
     let res = processor
         .send_ixns(
@@ -938,3 +938,60 @@ async fn incorrect_epoch_index() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn end_at_follows_the_clock() -> Result<()> {
+    let (accounts, mut processor) = setup().await?;
+
+    progress_epoch_till(EpochStatus::Yielding, &accounts, processor.as_mut()).await?;
+
+    let before = processor.get_clock().await?;
+    // An hour worth of slots.
+    processor.warp_slots(9_000).await?;
+    let clock = processor.get_clock().await?;
+    assert_eq!(clock.slot, before.slot + 9_000);
+    assert!(clock.unix_timestamp >= before.unix_timestamp + 60 * 60);
+
+    yield_deposit_by_investor(usdc("200"), &accounts, processor.as_mut()).await?;
+
+    let epoch: Epoch = get_data(
+        get_epoch_pubkey(&accounts, processor.as_mut()).await?,
+        processor.as_mut(),
+    )
+    .await?;
+    let end_at = epoch.end_at.expect("end_at should be set");
+    assert!(end_at >= clock.unix_timestamp, "{end_at} < {}", clock.unix_timestamp);
+    assert!(
+        end_at - clock.unix_timestamp < 60,
+        "{end_at} - {} >= 60",
+        clock.unix_timestamp
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn creation_slot_follows_the_clock() -> Result<()> {
+    let (accounts, mut processor) = setup().await?;
+
+    processor.warp_slots(100).await?;
+    let clock = processor.get_clock().await?;
+
+    progress_epoch_till(EpochStatus::Running, &accounts, processor.as_mut()).await?;
+
+    let epoch: Epoch = get_data(
+        get_epoch_pubkey(&accounts, processor.as_mut()).await?,
+        processor.as_mut(),
+    )
+    .await?;
+    let creation_slot = epoch.creation_slot.expect("creation_slot should be set");
+    assert!(
+        creation_slot.slot >= clock.slot - 1,
+        "{} < {}",
+        creation_slot.slot,
+        clock.slot - 1
+    );
+    assert_ne!(creation_slot.hash, [0; 32]);
+
+    Ok(())
+}
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
 use anyhow::{Context, Result};
+use borsh::BorshDeserialize;
 use nezha_staking_lib::{
     error::StakingError,
     fixed_point::test_utils::{fp, usdc},
     francium,
-    instruction::{CreateEpochWinnersMetaArgs, TierWinnersMetaInput},
+    instruction::{CreateEpochWinnersMetaArgs, StakingInstruction, TierWinnersMetaInput},
     state::EpochStatus,
 };
 use solana_sdk::signer::Signer;
@@ -14,36 +15,46 @@ use crate::{account_names, accounts::Accounts, actions::*, processors};
 
 use nezha_testing::{
     solana_emulator::ProcessorFn,
-    solana_test_runtime::{self, ErrorFn, SolanaTestRuntime, TestRuntimeType},
+    solana_test_runtime::{
+        self,
+        profile::{self, InstructionNameFn},
+        ErrorFn, SolanaTestRuntime, TestRuntimeType,
+    },
 };
 
 pub const SOLS_TO_MINT: u64 = 100;
 pub const USDC_TO_MINT: u64 = 1000;
 
 pub async fn new_test_runtime(accounts: &Accounts) -> Result<Box<dyn SolanaTestRuntime + Send + Sync + 'static>> {
+    processors::francium::init(fp(1.2));
+    let francium_processors = [
+        (
+            francium::constants::LENDING_PROGRAM_ID,
+            processors::francium::process_francium_lending as ProcessorFn,
+        ),
+        (
+            francium::constants::LENDING_REWARDS_PROGRAM_ID,
+            processors::francium::process_francium_rewards,
+        ),
+    ];
+
     let runtime_type = if cfg!(feature = "test-bpf") {
+        let nezha_staking_instruction_name_fn: InstructionNameFn = |data| {
+            StakingInstruction::try_from_slice(data)
+                .ok()
+                .map(|ixn| profile::variant_name(&ixn))
+        };
         TestRuntimeType::BPF {
             program_name: "nezha_staking".into(),
             program_id: accounts.program_id,
+            instruction_names: HashMap::from([(accounts.program_id, nezha_staking_instruction_name_fn)]),
+            // We don't have the francium blobs. Their mocks run natively.
+            builtins: HashMap::from(francium_processors),
         }
     } else {
-        processors::francium::init(fp(1.2));
-
         let account_names = account_names::build_account_names_map(accounts);
-        let processors = HashMap::from([
-            (
-                accounts.program_id,
-                nezha_staking::processor::process_instruction as ProcessorFn,
-            ),
-            (
-                francium::constants::LENDING_PROGRAM_ID,
-                processors::francium::process_francium_lending,
-            ),
-            (
-                francium::constants::LENDING_REWARDS_PROGRAM_ID,
-                processors::francium::process_francium_rewards,
-            ),
-        ]);
+        let mut processors = HashMap::from(francium_processors);
+        processors.insert(accounts.program_id, nezha_staking::processor::process_instruction);
         TestRuntimeType::Emulated {
             processors,
             account_names,
@@ -67,7 +78,7 @@ pub async fn setup_test_runtime(accounts: &Accounts) -> Result<Box<dyn SolanaTes
 pub async fn setup_test_runtime_without_init(accounts: &Accounts) -> Result<Box<dyn SolanaTestRuntime + Send + Sync>> {
     let mut runtime = new_test_runtime(&accounts).await?;
 
-    create_mint(&accounts.usdc_mint, &accounts.admin.pubkey(), None, runtime.as_mut())
+    set_mint(&accounts.usdc_mint, &accounts.admin.pubkey(), runtime.as_mut())
         .await
         .context("Create USDC mint")?;
     for actor in [
@@ -81,13 +92,13 @@ pub async fn setup_test_runtime_without_init(accounts: &Accounts) -> Result<Box<
         mint_sols(&actor.pubkey(), SOLS_TO_MINT, runtime.as_mut())
             .await
             .context("Mint SOLs")?;
-        create_token_account(&actor.pubkey(), &accounts.usdc_mint.pubkey(), runtime.as_mut())
+        create_token_account(&actor.pubkey(), &accounts.usdc_mint, runtime.as_mut())
             .await
             .context("Create USDC ATA")?;
         mint_tokens(
             &actor.pubkey(),
             USDC_TO_MINT * 1_000_000,
-            &accounts.usdc_mint.pubkey(),
+            &accounts.usdc_mint,
             &accounts.admin,
             runtime.as_mut(),
         )
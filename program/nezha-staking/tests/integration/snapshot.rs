@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use nezha_staking_lib::{accounts as ac, fixed_point::test_utils::usdc, state::StakeUpdateRequest};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::signer::Signer;
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{accounts::Accounts, actions::*, setup::*};
+
+use nezha_testing::{snapshot::Snapshot, solana_test_runtime::SolanaTestRuntime};
+
+fn staking_state_pubkeys(accounts: &Accounts, epoch_index: u64) -> Vec<Pubkey> {
+    let program_id = &accounts.program_id;
+    let mut pubkeys = vec![
+        accounts.usdc_mint,
+        ac::latest_epoch(program_id).pubkey,
+        ac::epoch(program_id, epoch_index).pubkey,
+        ac::deposit_vault(program_id).pubkey,
+        ac::pending_deposit_vault(program_id).pubkey,
+        ac::treasury_vault(program_id).pubkey,
+        ac::insurance_vault(program_id).pubkey,
+    ];
+    pubkeys.extend((1..=3).map(|tier| ac::prize_vault(program_id, tier).pubkey));
+    for actor in [
+        &accounts.owner,
+        &accounts.admin,
+        &accounts.super_admin,
+        &accounts.investor,
+    ] {
+        pubkeys.push(actor.pubkey());
+        pubkeys.push(get_associated_token_address(&actor.pubkey(), &accounts.usdc_mint));
+        pubkeys.push(ac::stake(program_id, &actor.pubkey()).pubkey);
+        pubkeys.push(ac::stake_update_request(program_id, &actor.pubkey()).pubkey);
+    }
+    pubkeys
+}
+
+async fn capture(accounts: &Accounts, processor: &mut dyn SolanaTestRuntime) -> Result<String> {
+    let epoch_index = get_latest_epoch(accounts, processor).await?.index;
+    let snapshot = Snapshot::capture(processor, &staking_state_pubkeys(accounts, epoch_index)).await?;
+    snapshot.to_json()
+}
+
+#[tokio::test]
+async fn resume_from_snapshot() -> Result<()> {
+    let accounts = Accounts::new();
+
+    let json = {
+        let mut processor = setup_test_runtime(&accounts).await?;
+        create_epoch(&accounts, random_yield_split_cfg(), processor.as_mut()).await?;
+        request_stake_update(StakeUpdateOp::Deposit, usdc("100"), &accounts, processor.as_mut()).await?;
+        approve_stake_update(&accounts, processor.as_mut(), StakeUpdateOp::Deposit, usdc("100")).await?;
+        capture(&accounts, processor.as_mut()).await?
+    };
+
+    // A fresh runtime with nothing but the snapshot.
+    let mut processor = new_test_runtime(&accounts).await?;
+    Snapshot::from_json(&json)
+        .context("Parse snapshot")?
+        .load_into(processor.as_mut());
+
+    let stake_update_request: StakeUpdateRequest = get_data(
+        ac::stake_update_request(&accounts.program_id, &accounts.owner.pubkey()).pubkey,
+        processor.as_mut(),
+    )
+    .await?;
+    assert_eq!(stake_update_request.amount, usdc("100").as_usdc_i64());
+
+    complete_stake_update(&accounts, processor.as_mut())
+        .await
+        .context("Complete stake update after loading the snapshot")?;
+    assert_eq!(
+        get_owner_stake_balance(&accounts, processor.as_mut()).await?,
+        usdc("100")
+    );
+    assert_eq!(
+        get_owner_usdc_balance(&accounts, processor.as_mut()).await?,
+        usdc("900")
+    );
+
+    Ok(())
+}
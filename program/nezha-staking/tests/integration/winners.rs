@@ -1,11 +1,12 @@
 use anyhow::{Context, Result};
 use nezha_staking_lib::{
     fixed_point::test_utils::fp,
-    instruction::{CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput},
+    instruction::{self, CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput},
     state::MAX_NUM_WINNERS_PER_PAGE,
 };
 use solana_program::pubkey::Pubkey;
 use solana_program_test::tokio;
+use solana_sdk::signer::Signer;
 
 use crate::{
     accounts::Accounts,
@@ -298,3 +299,55 @@ async fn cant_change_winners_once_published() -> Result<()> {
 
     Ok(())
 }
+
+/// Compute units a full page of winners may use.
+/// The BPF tests run with a 250k compute unit limit. Leave some headroom below that.
+const PUBLISH_WINNERS_PAGE_COMPUTE_BUDGET: u64 = 200_000;
+
+#[tokio::test]
+async fn publish_winners_within_compute_budget() -> Result<()> {
+    let (accounts, mut processor) = setup().await?;
+
+    progress_epoch(&accounts, processor.as_mut()).await?;
+
+    let winners = generate_winners(10, 1, 10, 2, 10, 3);
+
+    let meta_args = CreateEpochWinnersMetaArgs {
+        tier1_meta: TierWinnersMetaInput {
+            total_num_winners: 10,
+            total_num_winning_tickets: 10 * 1,
+        },
+        tier2_meta: TierWinnersMetaInput {
+            total_num_winners: 10,
+            total_num_winning_tickets: 10 * 2,
+        },
+        tier3_meta: TierWinnersMetaInput {
+            total_num_winners: 10,
+            total_num_winning_tickets: 10 * 3,
+        },
+    };
+    create_epoch_winners_meta(&meta_args, &accounts, processor.as_mut()).await?;
+
+    let epoch_index = get_latest_epoch(&accounts, processor.as_mut()).await?.index;
+    for (i, chunk) in winners.chunks(MAX_NUM_WINNERS_PER_PAGE).enumerate() {
+        let profile = processor
+            .send_ixns_with_profile(
+                &[instruction::publish_winners(
+                    &accounts.program_id,
+                    &accounts.admin.pubkey(),
+                    epoch_index,
+                    i as u32,
+                    chunk.to_vec(),
+                    &accounts.nezha_vrf_program_id,
+                )],
+                &[&accounts.admin],
+            )
+            .await
+            .with_context(|| format!("Page {i}"))?;
+        profile
+            .ensure_compute_units_within(PUBLISH_WINNERS_PAGE_COMPUTE_BUDGET)
+            .with_context(|| format!("Page {i}"))?;
+    }
+
+    Ok(())
+}
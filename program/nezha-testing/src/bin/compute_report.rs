@@ -0,0 +1,18 @@
+//! Summarise the compute report written by the BPF integration tests.
+//! See `nezha_testing::solana_test_runtime::profile`.
+
+use std::{fs::File, io::BufReader};
+
+use anyhow::{Context, Result};
+use nezha_testing::solana_test_runtime::profile::{ComputeReport, COMPUTE_REPORT_ENV};
+
+fn main() -> Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var(COMPUTE_REPORT_ENV).ok())
+        .with_context(|| format!("Usage: compute_report <path>. Or set {}", COMPUTE_REPORT_ENV))?;
+    let file = File::open(&path).with_context(|| format!("Failed to open {}", path))?;
+    let report = ComputeReport::from_reader(BufReader::new(file))?;
+    print!("{}", report);
+    Ok(())
+}
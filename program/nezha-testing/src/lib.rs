@@ -1,3 +1,4 @@
 pub mod mutations;
+pub mod snapshot;
 pub mod solana_emulator;
 pub mod solana_test_runtime;
@@ -0,0 +1,194 @@
+//! Account snapshots.
+//!
+//! A snapshot is a JSON array of accounts in the format used by `solana account --output json`
+//! and `getProgramAccounts`:
+//!
+//! ```json
+//! [{ "pubkey": "...", "account": { "lamports": 1, "data": ["...", "base64"], "owner": "...", ... } }]
+//! ```
+//!
+//! `data` can be encoded as `base64`, `base64+zstd` or `base58`. Snapshots are loaded into a
+//! [`SolanaTestRuntime`] to reproduce the on-chain state locally.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_program::pubkey::Pubkey;
+
+use crate::solana_test_runtime::{Account, SolanaTestRuntime};
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotAccount {
+    pubkey: String,
+    account: UiAccount,
+}
+
+/// Accounts captured at some point in time.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub accounts: BTreeMap<Pubkey, Account>,
+}
+
+impl Snapshot {
+    pub fn insert(&mut self, pubkey: Pubkey, account: Account) {
+        self.accounts.insert(pubkey, account);
+    }
+
+    /// Parses a snapshot. A single account object, as written by `solana account --output json`,
+    /// is accepted as well.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json).context("Invalid JSON")?;
+        let snapshot_accounts: Vec<SnapshotAccount> = if value.is_array() {
+            serde_json::from_value(value)?
+        } else {
+            vec![serde_json::from_value(value)?]
+        };
+
+        let mut snapshot = Self::default();
+        for snapshot_account in snapshot_accounts {
+            let pubkey = Pubkey::from_str(&snapshot_account.pubkey)
+                .with_context(|| format!("Invalid pubkey: {}", snapshot_account.pubkey))?;
+            let account: solana_sdk::account::Account = snapshot_account
+                .account
+                .decode()
+                .ok_or_else(|| anyhow!("Couldn't decode account data: {}", pubkey))?;
+            snapshot.insert(
+                pubkey,
+                Account {
+                    lamports: account.lamports,
+                    owner: account.owner,
+                    data: account.data,
+                },
+            );
+        }
+        Ok(snapshot)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let snapshot_accounts: Vec<SnapshotAccount> = self
+            .accounts
+            .iter()
+            .map(|(pubkey, account)| SnapshotAccount {
+                pubkey: pubkey.to_string(),
+                account: UiAccount::encode(
+                    pubkey,
+                    &solana_sdk::account::Account {
+                        lamports: account.lamports,
+                        data: account.data.clone(),
+                        owner: account.owner,
+                        executable: false,
+                        rent_epoch: 0,
+                    },
+                    UiAccountEncoding::Base64,
+                    None,
+                    None,
+                ),
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&snapshot_accounts)?)
+    }
+
+    /// Load a snapshot file, or every `.json` file in a directory.
+    /// Later files override the accounts of earlier ones, in file name order.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            let json = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            return Self::from_json(&json).with_context(|| format!("Invalid snapshot: {}", path.display()));
+        }
+
+        let mut paths: Vec<_> = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|p| p.extension().map_or(false, |ext| ext == "json"));
+        paths.sort();
+
+        let mut snapshot = Self::default();
+        for path in paths {
+            snapshot.accounts.extend(Self::load(&path)?.accounts);
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Read the given accounts from the runtime. Accounts that don't exist are skipped.
+    pub async fn capture(runtime: &mut dyn SolanaTestRuntime, pubkeys: &[Pubkey]) -> Result<Self> {
+        let mut snapshot = Self::default();
+        for pubkey in pubkeys {
+            if let Some(account) = runtime.get_account(*pubkey).await? {
+                snapshot.insert(*pubkey, account);
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Write all the accounts into the runtime, replacing existing ones.
+    pub fn load_into(&self, runtime: &mut dyn SolanaTestRuntime) {
+        for (pubkey, account) in &self.accounts {
+            runtime.set_account(pubkey, account);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut snapshot = Snapshot::default();
+        snapshot.insert(
+            Pubkey::new_unique(),
+            Account {
+                lamports: 10,
+                owner: Pubkey::new_unique(),
+                data: vec![1, 2, 3],
+            },
+        );
+        snapshot.insert(
+            Pubkey::new_unique(),
+            Account {
+                lamports: 20,
+                owner: Pubkey::new_unique(),
+                data: vec![],
+            },
+        );
+
+        let parsed = Snapshot::from_json(&snapshot.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.accounts.len(), 2);
+        for (pubkey, account) in &snapshot.accounts {
+            let parsed_account = &parsed.accounts[pubkey];
+            assert_eq!(parsed_account.lamports, account.lamports);
+            assert_eq!(parsed_account.owner, account.owner);
+            assert_eq!(parsed_account.data, account.data);
+        }
+    }
+
+    #[test]
+    fn test_single_account() {
+        let json = r#"{
+            "pubkey": "11111111111111111111111111111112",
+            "account": {
+                "lamports": 5,
+                "data": ["AQID", "base64"],
+                "owner": "11111111111111111111111111111111",
+                "executable": false,
+                "rentEpoch": 0,
+                "space": 3
+            }
+        }"#;
+        let snapshot = Snapshot::from_json(json).unwrap();
+        let account = &snapshot.accounts[&Pubkey::from_str("11111111111111111111111111111112").unwrap()];
+        assert_eq!(account.lamports, 5);
+        assert_eq!(account.data, vec![1, 2, 3]);
+    }
+}
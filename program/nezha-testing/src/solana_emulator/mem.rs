@@ -4,13 +4,28 @@ use std::{
 };
 
 use solana_program::{
-    clock::Clock, program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent, system_program, sysvar,
+    clock::{Clock, DEFAULT_MS_PER_SLOT},
+    hash::hashv,
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    slot_hashes::SlotHashes,
+    system_program, sysvar,
 };
 
 thread_local! {
     pub static MEM: RefCell<Mem> = RefCell::new(Mem { accounts: Vec::new() });
     pub static MEM_TXN: RefCell<Mem> = RefCell::new(Mem { accounts: Vec::new() });
     static MEM_INIT: Once = Once::new();
+    static CLOCK_WARP: RefCell<ClockWarp> = RefCell::new(ClockWarp::default());
+}
+
+/// How far the clock has been moved from the wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+struct ClockWarp {
+    slots: u64,
+    seconds: i64,
 }
 
 pub struct Mem {
@@ -105,6 +120,13 @@ fn _init() {
         };
         s.accounts.push((sysvar::clock::id(), Arc::new(account)));
 
+        let account = Account {
+            lamports: uc(1),
+            data: uc(get_slot_hashes_bytes()),
+            owner: uc(system_program::id()),
+        };
+        s.accounts.push((sysvar::slot_hashes::id(), Arc::new(account)));
+
         let rent = RENT;
         let rent_bytes = bincode::serialize(&rent).unwrap();
         let account = Account {
@@ -173,14 +195,55 @@ pub const RENT: Rent = Rent {
 };
 
 pub fn get_clock() -> Clock {
+    let warp = CLOCK_WARP.with(|w| *w.borrow());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
     Clock {
-        slot: 1,
+        slot: 1 + warp.slots,
         epoch_start_timestamp: 1,
         epoch: 1,
         leader_schedule_epoch: 1,
-        unix_timestamp: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as _,
+        unix_timestamp: now + warp.seconds,
     }
 }
+
+/// The SlotHashes sysvar with a single entry for the slot before the current one.
+fn get_slot_hashes_bytes() -> Vec<u8> {
+    let slot = get_clock().slot - 1;
+    let slot_hashes = SlotHashes::new(&[(slot, hashv(&[&slot.to_le_bytes()]))]);
+    bincode::serialize(&slot_hashes).unwrap()
+}
+
+/// Move the clock to the given timestamp. The clock keeps ticking with the wall clock from there.
+pub fn warp_to_timestamp(unix_timestamp: i64) {
+    let current = get_clock().unix_timestamp;
+    CLOCK_WARP.with(|w| w.borrow_mut().seconds += unix_timestamp - current);
+    update_clock_sysvar();
+}
+
+/// Move the clock forward by `slots`. The timestamp moves forward by the default slot duration.
+pub fn warp_slots(slots: u64) {
+    CLOCK_WARP.with(|w| {
+        let mut w = w.borrow_mut();
+        w.slots += slots;
+        w.seconds += (slots * DEFAULT_MS_PER_SLOT / 1000) as i64;
+    });
+    update_clock_sysvar();
+}
+
+/// Programs can read the clock from the sysvar account instead of the syscall.
+/// The SlotHashes sysvar follows the clock's slot.
+fn update_clock_sysvar() {
+    let clock_bytes = bincode::serialize(&get_clock()).unwrap();
+    begin_txn();
+    new_account_with_data(sysvar::clock::id(), &clock_bytes, 1, system_program::id());
+    new_account_with_data(
+        sysvar::slot_hashes::id(),
+        &get_slot_hashes_bytes(),
+        1,
+        system_program::id(),
+    );
+    commit_txn();
+}
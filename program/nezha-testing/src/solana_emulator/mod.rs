@@ -40,7 +40,10 @@ pub fn init() {
     syscall_stubs::init();
 }
 
-pub use mem::{begin_txn, commit_txn, get_account, new_account, new_account_with_data, remove_closed_accounts, RENT};
+pub use mem::{
+    begin_txn, commit_txn, get_account, get_clock, new_account, new_account_with_data, remove_closed_accounts,
+    warp_slots, warp_to_timestamp, RENT,
+};
 
 pub fn invoke(ixns: &[Instruction], signers: &[&Keypair], payer: &Keypair) -> Result<()> {
     mem::begin_txn();
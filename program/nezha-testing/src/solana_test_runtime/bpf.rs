@@ -1,21 +1,42 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use solana_program::account_info::AccountInfo;
 use solana_program::bpf_loader_upgradeable;
+use solana_program::clock::{Clock, DEFAULT_MS_PER_SLOT};
+use solana_program::entrypoint::ProgramResult;
 use solana_program::instruction::InstructionError;
+use solana_program::program_error::ProgramError;
 use solana_program::{instruction::Instruction, pubkey::Pubkey, rent::Rent};
-use solana_program_test::{BanksClientError, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext};
 use solana_sdk::account::AccountSharedData;
 use solana_sdk::transaction::TransactionError;
 use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
 
+use super::profile::{self, InstructionNameFn, InstructionProfile, TransactionProfile};
 use super::{Account, ErrorFn, SolanaTestRuntime};
+use crate::solana_emulator::ProcessorFn;
+
+thread_local! {
+    static BUILTINS: RefCell<HashMap<Pubkey, ProcessorFn>> = RefCell::new(HashMap::new());
+}
+
+/// `processor!` only takes a function, so all the builtins go through this one.
+fn process_builtin(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    let processor = BUILTINS
+        .with(|builtins| builtins.borrow().get(program_id).copied())
+        .ok_or(ProgramError::IncorrectProgramId)?;
+    processor(program_id, accounts, input)
+}
 
 pub struct BPFTestRuntime {
     ptc: ProgramTestContext,
     /// Program ID -> ErrorFn
     errors: HashMap<Pubkey, ErrorFn>,
+    /// Program ID -> InstructionNameFn
+    instruction_names: HashMap<Pubkey, InstructionNameFn>,
 }
 
 impl BPFTestRuntime {
@@ -23,12 +44,29 @@ impl BPFTestRuntime {
         program_name: &str,
         program_id: &Pubkey,
         errors: HashMap<Pubkey, ErrorFn>,
+        instruction_names: HashMap<Pubkey, InstructionNameFn>,
+        builtins: HashMap<Pubkey, ProcessorFn>,
         program_ids: &[Pubkey],
     ) -> Result<Self> {
-        let mut program_test = ProgramTest::new(program_name, *program_id, None);
+        let mut program_test = ProgramTest::default();
+        // Otherwise the builtins are looked up as BPF blobs when running with `cargo test-sbf`
+        program_test.prefer_bpf(false);
+        program_test.add_program(program_name, *program_id, None);
+        for builtin_program_id in builtins.keys() {
+            program_test.add_program(
+                &builtin_program_id.to_string(),
+                *builtin_program_id,
+                processor!(process_builtin),
+            );
+        }
+        BUILTINS.with(|b| *b.borrow_mut() = builtins);
         program_test.set_compute_max_units(250_000);
         let ptc = program_test.start_with_context().await;
-        let mut self_ = Self { ptc, errors };
+        let mut self_ = Self {
+            ptc,
+            errors,
+            instruction_names,
+        };
         for program_id_ in program_ids {
             if program_id_ == program_id {
                 continue;
@@ -49,26 +87,62 @@ impl BPFTestRuntime {
 #[async_trait]
 impl SolanaTestRuntime for BPFTestRuntime {
     async fn send_ixns(&mut self, ixns: &[Instruction], signers: &[&Keypair]) -> Result<()> {
+        self.send_ixns_with_profile(ixns, signers).await?;
+        Ok(())
+    }
+
+    async fn send_ixns_with_profile(
+        &mut self,
+        ixns: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionProfile> {
         let mut signers: Vec<_> = signers.into();
         signers.push(&self.ptc.payer);
         let last_blockhash = self.ptc.banks_client.get_latest_blockhash().await?;
         let blockhash = self.ptc.banks_client.get_new_latest_blockhash(&last_blockhash).await?;
         let tx = Transaction::new_signed_with_payer(ixns, Some(&self.ptc.payer.pubkey()), &signers, blockhash);
 
-        let res = self.ptc.banks_client.process_transaction(tx).await;
-
-        if let Err(e) = res {
-            let err = match e {
+        let res = self
+            .ptc
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await
+            .map_err(|e| match e {
                 BanksClientError::TransactionError(txn_error)
                 | BanksClientError::SimulationError { err: txn_error, .. } => {
                     transaction_error_to_anyhow(ixns, &self.errors, txn_error)
                 }
                 _ => anyhow::Error::from(e),
-            };
-            return Err(err);
+            })?;
+
+        if let Err(txn_error) = res.result {
+            return Err(transaction_error_to_anyhow(ixns, &self.errors, txn_error));
         }
 
-        Ok(())
+        let logs = res.metadata.map(|m| m.log_messages).unwrap_or_default();
+        let mut measurements = profile::parse_logs(&logs).into_iter();
+        let instructions = ixns
+            .iter()
+            .map(|ixn| {
+                let (compute_units, max_invoke_depth) = match measurements.next() {
+                    Some((compute_units, max_invoke_depth)) => (compute_units, Some(max_invoke_depth)),
+                    None => (None, None),
+                };
+                InstructionProfile {
+                    program_id: ixn.program_id,
+                    name: self
+                        .instruction_names
+                        .get(&ixn.program_id)
+                        .and_then(|name_fn| name_fn(&ixn.data)),
+                    compute_units,
+                    max_invoke_depth,
+                }
+            })
+            .collect();
+        let profile = TransactionProfile { instructions };
+        profile::append_to_report(&profile)?;
+
+        Ok(profile)
     }
 
     async fn get_rent(&mut self) -> Result<Rent> {
@@ -103,6 +177,39 @@ impl SolanaTestRuntime for BPFTestRuntime {
         account_shared_data.set_data(account.data.clone());
         self.ptc.set_account(address, &account_shared_data)
     }
+
+    async fn get_clock(&mut self) -> Result<Clock> {
+        self.ptc
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .with_context(|| "Couldn't get clock sysvar from ptc")
+    }
+
+    async fn warp_to_timestamp(&mut self, unix_timestamp: i64) -> Result<()> {
+        let clock = self.get_clock().await?;
+        self.ptc.set_sysvar(&Clock {
+            unix_timestamp,
+            ..clock
+        });
+        Ok(())
+    }
+
+    async fn warp_slots(&mut self, slots: u64) -> Result<()> {
+        if slots == 0 {
+            return Ok(());
+        }
+        let clock = self.get_clock().await?;
+        self.ptc
+            .warp_to_slot(clock.slot + slots)
+            .map_err(|e| anyhow!("Failed to warp to slot {}: {:?}", clock.slot + slots, e))?;
+        // The warped bank doesn't move the timestamp. Keep it in line with the slots.
+        self.ptc.set_sysvar(&Clock {
+            unix_timestamp: clock.unix_timestamp + (slots * DEFAULT_MS_PER_SLOT / 1000) as i64,
+            ..self.get_clock().await?
+        });
+        Ok(())
+    }
 }
 
 fn get_error(errors: &HashMap<Pubkey, ErrorFn>, program_id: &Pubkey, error_code: u32) -> String {
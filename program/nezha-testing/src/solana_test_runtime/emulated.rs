@@ -1,10 +1,13 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use solana_program::{bpf_loader_upgradeable, instruction::Instruction, pubkey::Pubkey, rent::Rent, system_program};
+use solana_program::{
+    bpf_loader_upgradeable, clock::Clock, instruction::Instruction, pubkey::Pubkey, rent::Rent, system_program,
+};
 use solana_sdk::{signature::Keypair, signer::Signer};
 
 use crate::solana_emulator;
 
+use super::profile::{InstructionProfile, TransactionProfile};
 use super::{Account, SolanaTestRuntime};
 
 pub struct EmulatedTestRuntime {
@@ -30,6 +33,24 @@ impl SolanaTestRuntime for EmulatedTestRuntime {
     async fn send_ixns(&mut self, ixns: &[Instruction], signers: &[&Keypair]) -> Result<()> {
         solana_emulator::invoke(ixns, signers, &self.payer)
     }
+    async fn send_ixns_with_profile(
+        &mut self,
+        ixns: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionProfile> {
+        solana_emulator::invoke(ixns, signers, &self.payer)?;
+        // Nothing is metered in the emulator.
+        let instructions = ixns
+            .iter()
+            .map(|ixn| InstructionProfile {
+                program_id: ixn.program_id,
+                name: None,
+                compute_units: None,
+                max_invoke_depth: None,
+            })
+            .collect();
+        Ok(TransactionProfile { instructions })
+    }
     async fn get_rent(&mut self) -> Result<Rent> {
         Ok(solana_emulator::RENT)
     }
@@ -55,4 +76,18 @@ impl SolanaTestRuntime for EmulatedTestRuntime {
         solana_emulator::new_account_with_data(*address, &account.data, account.lamports, account.owner);
         solana_emulator::commit_txn();
     }
+
+    async fn get_clock(&mut self) -> Result<Clock> {
+        Ok(solana_emulator::get_clock())
+    }
+
+    async fn warp_to_timestamp(&mut self, unix_timestamp: i64) -> Result<()> {
+        solana_emulator::warp_to_timestamp(unix_timestamp);
+        Ok(())
+    }
+
+    async fn warp_slots(&mut self, slots: u64) -> Result<()> {
+        solana_emulator::warp_slots(slots);
+        Ok(())
+    }
 }
@@ -1,16 +1,19 @@
 pub mod bpf;
 pub mod emulated;
+pub mod profile;
 
 use std::collections::HashMap;
 use std::fmt::Display;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use solana_program::{instruction::Instruction, pubkey::Pubkey, rent::Rent};
+use solana_program::{clock::Clock, instruction::Instruction, pubkey::Pubkey, rent::Rent};
 use solana_sdk::signature::Keypair;
 
 use crate::solana_emulator::{self, ProcessorFn};
 
+use self::profile::{InstructionNameFn, TransactionProfile};
+
 /// Function that converts error code to corresponding concrete error
 pub type ErrorFn = fn(u32) -> Option<Box<dyn Display + Send + Sync + 'static>>;
 
@@ -24,10 +27,21 @@ pub struct Account {
 #[async_trait]
 pub trait SolanaTestRuntime {
     async fn send_ixns(&mut self, ixns: &[Instruction], signers: &[&Keypair]) -> Result<()>;
+    /// Same as `send_ixns`, but also returns the resources used by each instruction.
+    async fn send_ixns_with_profile(
+        &mut self,
+        ixns: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<TransactionProfile>;
     async fn get_rent(&mut self) -> Result<Rent>;
     fn get_payer(&mut self) -> &Keypair;
     async fn get_account(&mut self, account: Pubkey) -> Result<Option<Account>>;
     fn set_account(&mut self, address: &Pubkey, account: &Account);
+    async fn get_clock(&mut self) -> Result<Clock>;
+    /// Move the clock to the given unix timestamp.
+    async fn warp_to_timestamp(&mut self, unix_timestamp: i64) -> Result<()>;
+    /// Move the clock forward by `slots` slots.
+    async fn warp_slots(&mut self, slots: u64) -> Result<()>;
 }
 
 pub enum TestRuntimeType {
@@ -38,6 +52,10 @@ pub enum TestRuntimeType {
     BPF {
         program_name: String,
         program_id: Pubkey,
+        /// Used to label instructions in the compute profile.
+        instruction_names: HashMap<Pubkey, InstructionNameFn>,
+        /// Programs we don't have blobs of, run natively. Their CPIs are metered, their own execution isn't.
+        builtins: HashMap<Pubkey, ProcessorFn>,
     },
 }
 
@@ -64,8 +82,18 @@ pub async fn new_test_runtime(
         TestRuntimeType::BPF {
             program_name,
             program_id,
+            instruction_names,
+            builtins,
         } => {
-            let runtime = bpf::BPFTestRuntime::new(&program_name, &program_id, errors, &extra_program_ids).await?;
+            let runtime = bpf::BPFTestRuntime::new(
+                &program_name,
+                &program_id,
+                errors,
+                instruction_names,
+                builtins,
+                &extra_program_ids,
+            )
+            .await?;
             Ok(Box::new(runtime))
         }
     }
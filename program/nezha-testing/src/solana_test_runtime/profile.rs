@@ -0,0 +1,328 @@
+//! Compute unit profiling.
+//!
+//! The BPF runtime reports the compute units consumed and the deepest CPI invoke depth reached by
+//! every top level instruction. The emulated runtime doesn't meter anything, so the values are
+//! `None` there and budget checks pass trivially.
+//!
+//! The invoke depth is not stack usage. The SBF VM doesn't expose how much of each 4KB stack frame
+//! was used, so nothing here measures it. Stack frame overflows surface as "Access violation in
+//! stack frame" errors, which is why the deepest CPI paths need their own BPF tests.
+//!
+//! Set `NEZHA_COMPUTE_REPORT=<path>` when running the BPF tests to append every profiled
+//! instruction to `<path>`, and summarise the file with:
+//!
+//! ```sh
+//! cargo run -p nezha-testing --bin compute_report -- <path>
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Display};
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, Context, Result};
+use solana_program::pubkey::Pubkey;
+
+/// Environment variable holding the path of the compute report file.
+pub const COMPUTE_REPORT_ENV: &str = "NEZHA_COMPUTE_REPORT";
+
+/// Function that returns a readable name for the instruction data of a program.
+pub type InstructionNameFn = fn(&[u8]) -> Option<String>;
+
+/// Name of the enum variant from its `Debug` representation.
+/// Useful for implementing [`InstructionNameFn`] for borsh instruction enums.
+pub fn variant_name<T: Debug>(value: &T) -> String {
+    let debug = format!("{:?}", value);
+    debug
+        .split(|c: char| c == ' ' || c == '(' || c == '{')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Resources used by a top level instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionProfile {
+    pub program_id: Pubkey,
+    /// `None` if the program didn't register an [`InstructionNameFn`].
+    pub name: Option<String>,
+    /// `None` if the runtime doesn't meter compute units.
+    pub compute_units: Option<u64>,
+    /// Deepest invoke depth reached. 1 means no CPIs.
+    /// `None` if the runtime doesn't track it.
+    pub max_invoke_depth: Option<usize>,
+}
+
+impl InstructionProfile {
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self.program_id.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionProfile {
+    pub instructions: Vec<InstructionProfile>,
+}
+
+impl TransactionProfile {
+    /// `None` if the runtime doesn't meter compute units.
+    pub fn total_compute_units(&self) -> Option<u64> {
+        self.instructions.iter().map(|ixn| ixn.compute_units).sum()
+    }
+
+    /// Fails if any instruction consumed more than `max_compute_units`.
+    /// Passes if the runtime doesn't meter compute units.
+    pub fn ensure_compute_units_within(&self, max_compute_units: u64) -> Result<()> {
+        for (i, ixn) in self.instructions.iter().enumerate() {
+            if let Some(compute_units) = ixn.compute_units {
+                if compute_units > max_compute_units {
+                    bail!(
+                        "Instruction [{}] {} consumed {} compute units. Budget is {}",
+                        i,
+                        ixn.label(),
+                        compute_units,
+                        max_compute_units
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if any instruction went deeper than `max_invoke_depth` invocations.
+    /// Passes if the runtime doesn't track the invoke depth.
+    pub fn ensure_invoke_depth_within(&self, max_invoke_depth: usize) -> Result<()> {
+        for (i, ixn) in self.instructions.iter().enumerate() {
+            if let Some(invoke_depth) = ixn.max_invoke_depth {
+                if invoke_depth > max_invoke_depth {
+                    bail!(
+                        "Instruction [{}] {} reached invoke depth {}. Budget is {}",
+                        i,
+                        ixn.label(),
+                        invoke_depth,
+                        max_invoke_depth
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Extract the compute units and max invoke depth of every top level instruction from the
+/// transaction logs.
+///
+/// Relevant log lines look like:
+/// ```text
+/// Program <program_id> invoke [<invoke_depth>]
+/// Program <program_id> consumed <n> of <m> compute units
+/// Program <program_id> success
+/// Program <program_id> failed: <error>
+/// ```
+pub fn parse_logs(logs: &[String]) -> Vec<(Option<u64>, usize)> {
+    let mut instructions: Vec<(Option<u64>, usize)> = Vec::new();
+    let mut invoke_depth = 0;
+
+    for log in logs {
+        let rest = match log.strip_prefix("Program ") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        if rest.starts_with("log:") || rest.starts_with("data:") || rest.starts_with("return:") {
+            continue;
+        }
+
+        let mut words = rest.split_whitespace().skip(1);
+        match words.next() {
+            Some("invoke") => {
+                let depth = words
+                    .next()
+                    .and_then(|d| d.trim_start_matches('[').trim_end_matches(']').parse().ok());
+                invoke_depth = match depth {
+                    Some(depth) => depth,
+                    None => continue,
+                };
+                if invoke_depth == 1 {
+                    instructions.push((None, 1));
+                } else if let Some(ixn) = instructions.last_mut() {
+                    ixn.1 = ixn.1.max(invoke_depth);
+                }
+            }
+            Some("consumed") if invoke_depth == 1 => {
+                let compute_units = words.next().and_then(|n| n.parse().ok());
+                if let Some(ixn) = instructions.last_mut() {
+                    ixn.0 = compute_units;
+                }
+            }
+            Some("success") | Some("failed:") => {
+                invoke_depth = invoke_depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    instructions
+}
+
+/// Append the instructions to the compute report file, if [`COMPUTE_REPORT_ENV`] is set.
+pub fn append_to_report(profile: &TransactionProfile) -> Result<()> {
+    let path = match std::env::var(COMPUTE_REPORT_ENV) {
+        Ok(path) => path,
+        Err(_) => return Ok(()),
+    };
+
+    let mut lines = String::new();
+    for ixn in &profile.instructions {
+        let compute_units = match ixn.compute_units {
+            Some(compute_units) => compute_units,
+            None => continue,
+        };
+        lines.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            ixn.program_id,
+            ixn.name.as_deref().unwrap_or("-"),
+            compute_units,
+            ixn.max_invoke_depth.unwrap_or(0),
+        ));
+    }
+
+    // Tests run in parallel. A single write per transaction keeps the lines intact.
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .with_context(|| format!("Failed to write compute report: {}", path))
+}
+
+#[derive(Clone, Debug, Default)]
+struct ReportEntry {
+    count: u64,
+    total_compute_units: u64,
+    min_compute_units: u64,
+    max_compute_units: u64,
+    max_invoke_depth: usize,
+}
+
+/// Summary of a compute report file, per instruction.
+#[derive(Clone, Debug, Default)]
+pub struct ComputeReport {
+    /// (Program ID, Instruction name) -> Entry
+    entries: BTreeMap<(String, String), ReportEntry>,
+}
+
+impl ComputeReport {
+    pub fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut report = Self::default();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 4 {
+                bail!("Line {}: Expected 4 fields. Got {}", i + 1, fields.len());
+            }
+            let compute_units: u64 = fields[2]
+                .parse()
+                .with_context(|| format!("Line {}: Invalid compute units", i + 1))?;
+            let invoke_depth: usize = fields[3]
+                .parse()
+                .with_context(|| format!("Line {}: Invalid invoke depth", i + 1))?;
+
+            let entry = report
+                .entries
+                .entry((fields[0].to_string(), fields[1].to_string()))
+                .or_insert_with(|| ReportEntry {
+                    min_compute_units: u64::MAX,
+                    ..Default::default()
+                });
+            entry.count += 1;
+            entry.total_compute_units += compute_units;
+            entry.min_compute_units = entry.min_compute_units.min(compute_units);
+            entry.max_compute_units = entry.max_compute_units.max(compute_units);
+            entry.max_invoke_depth = entry.max_invoke_depth.max(invoke_depth);
+        }
+        Ok(report)
+    }
+}
+
+impl Display for ComputeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<44} {:<32} {:>8} {:>10} {:>10} {:>10} {:>6}",
+            "Program", "Instruction", "Count", "Min CU", "Avg CU", "Max CU", "Depth"
+        )?;
+        for ((program_id, name), entry) in &self.entries {
+            writeln!(
+                f,
+                "{:<44} {:<32} {:>8} {:>10} {:>10} {:>10} {:>6}",
+                program_id,
+                name,
+                entry.count,
+                entry.min_compute_units,
+                entry.total_compute_units / entry.count,
+                entry.max_compute_units,
+                entry.max_invoke_depth,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_logs() {
+        let logs: Vec<String> = [
+            "Program Stake11111111111111111111111111111111111111 invoke [1]",
+            "Program log: Instruction: invoke [1]",
+            "Program Token11111111111111111111111111111111111111 invoke [2]",
+            "Program Token11111111111111111111111111111111111111 consumed 4000 of 190000 compute units",
+            "Program Token11111111111111111111111111111111111111 success",
+            "Program Stake11111111111111111111111111111111111111 consumed 20000 of 200000 compute units",
+            "Program Stake11111111111111111111111111111111111111 success",
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Program 11111111111111111111111111111111 success",
+            "Program Stake11111111111111111111111111111111111111 invoke [1]",
+            "Program Stake11111111111111111111111111111111111111 consumed 1500 of 180000 compute units",
+            "Program Stake11111111111111111111111111111111111111 failed: custom program error: 0x1",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(parse_logs(&logs), vec![(Some(20000), 2), (None, 1), (Some(1500), 1)]);
+    }
+
+    #[test]
+    fn test_report() {
+        let file = "A\tDeposit\t100\t1\nA\tDeposit\t300\t2\nB\t-\t50\t1\n";
+        let report = ComputeReport::from_reader(file.as_bytes()).unwrap();
+        let deposit = &report.entries[&("A".to_string(), "Deposit".to_string())];
+        assert_eq!(deposit.count, 2);
+        assert_eq!(deposit.min_compute_units, 100);
+        assert_eq!(deposit.max_compute_units, 300);
+        assert_eq!(deposit.max_invoke_depth, 2);
+        assert_eq!(report.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_variant_name() {
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        enum Ixn {
+            Init,
+            Deposit(u64),
+            Withdraw { amount: u64 },
+        }
+        assert_eq!(variant_name(&Ixn::Init), "Init");
+        assert_eq!(variant_name(&Ixn::Deposit(1)), "Deposit");
+        assert_eq!(variant_name(&Ixn::Withdraw { amount: 1 }), "Withdraw");
+    }
+}
@@ -96,3 +96,25 @@ async fn allow_retrying_stuck_vrf_request() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn request_timestamps_follow_the_clock() -> Result<()> {
+    let accounts = Accounts::new();
+    let mut processor = setup_test_runtime(&accounts).await?;
+
+    actions::set_epoch_index_and_status(1, EpochStatus::Finalising, &accounts, processor.as_mut()).await?;
+
+    let request_start = processor.get_clock().await?.unix_timestamp + 60 * 60;
+    processor.warp_to_timestamp(request_start).await?;
+    actions::request_vrf(1, &accounts, processor.as_mut()).await?;
+
+    let request_end = request_start + 60 * 60;
+    processor.warp_to_timestamp(request_end).await?;
+    actions::consume_vrf(1, &accounts, processor.as_mut()).await?;
+
+    let vrf_request: NezhaVrfRequest = actions::get_vrf_request(1, &accounts, processor.as_mut()).await?;
+    assert!((vrf_request.request_start - request_start).abs() < 60);
+    assert!((vrf_request.request_end.unwrap() - request_end).abs() < 60);
+
+    Ok(())
+}
@@ -2,7 +2,8 @@ use std::collections::HashMap;
 
 use anchor_lang::Discriminator;
 use anyhow::{Context, Result};
-use nezha_vrf_lib::error::NezhaVrfError;
+use borsh::BorshDeserialize;
+use nezha_vrf_lib::{error::NezhaVrfError, instruction::NezhaVrfInstruction};
 use solana_program::system_program;
 use solana_sdk::signer::Signer;
 use switchboard_v2::OracleQueueAccountData;
@@ -11,16 +12,27 @@ use crate::{account_names, accounts::Accounts, actions::*, processors};
 
 use nezha_testing::{
     solana_emulator::ProcessorFn,
-    solana_test_runtime::{self, Account, ErrorFn, SolanaTestRuntime, TestRuntimeType},
+    solana_test_runtime::{
+        self,
+        profile::{self, InstructionNameFn},
+        Account, ErrorFn, SolanaTestRuntime, TestRuntimeType,
+    },
 };
 
 pub const SOLS_TO_MINT: u64 = 100;
 
 pub async fn new_test_runtime(accounts: &Accounts) -> Result<Box<dyn SolanaTestRuntime + Send + Sync + 'static>> {
     let runtime_type = if cfg!(feature = "test-bpf") {
+        let nezha_vrf_instruction_name_fn: InstructionNameFn = |data| {
+            NezhaVrfInstruction::try_from_slice(data)
+                .ok()
+                .map(|ixn| profile::variant_name(&ixn))
+        };
         TestRuntimeType::BPF {
             program_name: "nezha_vrf".into(),
             program_id: accounts.program_id,
+            instruction_names: HashMap::from([(accounts.program_id, nezha_vrf_instruction_name_fn)]),
+            builtins: HashMap::new(),
         }
     } else {
         let account_names = account_names::build_account_names_map(accounts);
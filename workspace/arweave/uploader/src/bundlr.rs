@@ -11,7 +11,6 @@ use {
         pubkey::Pubkey,
         signature::{Keypair, Signature, Signer},
     },
-    std::mem::size_of_val,
     thiserror::Error,
 };
 
@@ -146,13 +145,23 @@ impl BundlrClient {
 #[async_trait]
 impl UploaderClient for BundlrClient {
     async fn upload_bytes<T: AsRef<[u8]> + Send>(&self, data_with_media_type: DataWithMediaType<T>) -> Result<String> {
+        self.upload_bytes_with_tags(data_with_media_type, Vec::new()).await
+    }
+
+    async fn upload_bytes_with_tags<T: AsRef<[u8]> + Send>(
+        &self,
+        data_with_media_type: DataWithMediaType<T>,
+        tags: Vec<Tag>,
+    ) -> Result<String> {
         let data = data_with_media_type.bytes();
-        let bytes = size_of_val(&data);
+        let bytes = data.len();
 
-        let tags = vec![Tag {
+        let tags = std::iter::once(Tag {
             name: CONTENT_TYPE.to_string(),
             value: data_with_media_type.media_type.to_string(),
-        }];
+        })
+        .chain(tags)
+        .collect();
 
         let data_item = DataItem::create(data, tags, &self.keypair)?;
         let tx = data_item.into_inner();
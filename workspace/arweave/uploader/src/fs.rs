@@ -0,0 +1,74 @@
+use {
+    crate::{data::DataWithMediaType, tag::Tag, UploaderClient},
+    anyhow::{Context, Result},
+    async_trait::async_trait,
+    reqwest::header::CONTENT_TYPE,
+    ring::digest::{digest, SHA256},
+    serde::Serialize,
+    std::path::PathBuf,
+};
+
+/// Uploader writing to a local directory instead of Arweave, for tests and local development.
+///
+/// The data is written to `<dir>/<id>` and its tags to `<dir>/<id>.tags.json`. The id is the URL-safe base64 SHA-256
+/// of the data, so it has the length of an Arweave transaction id and uploading the same data twice is idempotent.
+pub struct FsUploaderClient {
+    dir: PathBuf,
+}
+
+impl FsUploaderClient {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(id)
+    }
+
+    pub fn read(&self, id: &str) -> Result<Vec<u8>> {
+        std::fs::read(self.path(id)).with_context(|| format!("Upload {id} not found"))
+    }
+
+    pub fn tags(&self, id: &str) -> Result<Vec<Tag>> {
+        let tags = std::fs::read(self.path(&format!("{id}.tags.json")))
+            .with_context(|| format!("Tags of upload {id} not found"))?;
+        Ok(serde_json::from_slice(&tags)?)
+    }
+}
+
+#[async_trait]
+impl UploaderClient for FsUploaderClient {
+    async fn upload_bytes<T: AsRef<[u8]> + Send>(&self, data_with_media_type: DataWithMediaType<T>) -> Result<String> {
+        self.upload_bytes_with_tags(data_with_media_type, Vec::new()).await
+    }
+
+    async fn upload_bytes_with_tags<T: AsRef<[u8]> + Send>(
+        &self,
+        data_with_media_type: DataWithMediaType<T>,
+        tags: Vec<Tag>,
+    ) -> Result<String> {
+        let data = data_with_media_type.bytes();
+        let id = base64::encode_config(digest(&SHA256, &data), base64::URL_SAFE_NO_PAD);
+
+        let tags: Vec<Tag> = std::iter::once(Tag {
+            name: CONTENT_TYPE.to_string(),
+            value: data_with_media_type.media_type.to_string(),
+        })
+        .chain(tags)
+        .collect();
+
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path(&id), &data)?;
+        std::fs::write(self.path(&format!("{id}.tags.json")), serde_json::to_vec(&tags)?)?;
+        Ok(id)
+    }
+
+    async fn upload_json<T: Serialize + Sync>(&self, data: &T) -> Result<String> {
+        let data = serde_json::to_vec(data)?;
+        let data_with_media_type = DataWithMediaType {
+            data,
+            media_type: mime::APPLICATION_JSON,
+        };
+        self.upload_bytes(data_with_media_type).await
+    }
+}
@@ -2,11 +2,14 @@ pub mod bundlr;
 pub mod data;
 mod dataitem;
 mod deephash;
+pub mod fs;
 mod tag;
 
 pub use crate::{
     bundlr::{BundlrClient, BundlrConfig},
     data::DataWithMediaType,
+    fs::FsUploaderClient,
+    tag::Tag,
 };
 
 use {anyhow::Result, async_trait::async_trait, serde::Serialize};
@@ -14,5 +17,11 @@ use {anyhow::Result, async_trait::async_trait, serde::Serialize};
 #[async_trait]
 pub trait UploaderClient {
     async fn upload_bytes<T: AsRef<[u8]> + Send>(&self, data_with_media_type: DataWithMediaType<T>) -> Result<String>;
+    /// Uploads the data with `tags` following its Content-Type tag. Returns the id of the upload.
+    async fn upload_bytes_with_tags<T: AsRef<[u8]> + Send>(
+        &self,
+        data_with_media_type: DataWithMediaType<T>,
+        tags: Vec<Tag>,
+    ) -> Result<String>;
     async fn upload_json<S: Serialize + Sync>(&self, data: &S) -> Result<String>;
 }
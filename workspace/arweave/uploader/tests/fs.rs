@@ -0,0 +1,52 @@
+use {
+    anyhow::Result,
+    arweave_uploader::{DataWithMediaType, FsUploaderClient, Tag, UploaderClient},
+    rand::distributions::{Alphanumeric, DistString},
+    serde_json::{json, Value},
+};
+
+fn client() -> FsUploaderClient {
+    let dir = Alphanumeric.sample_string(&mut rand::thread_rng(), 10);
+    FsUploaderClient::new(std::env::temp_dir().join(format!("arweave-uploader-{dir}")))
+}
+
+#[tokio::test]
+async fn test_upload_bytes_with_tags() -> Result<()> {
+    let client = client();
+
+    let string = Alphanumeric.sample_string(&mut rand::thread_rng(), 10);
+    let data_with_media_type = DataWithMediaType {
+        data: string.clone(),
+        media_type: mime::TEXT_PLAIN,
+    };
+    let tags = vec![Tag {
+        name: "Epoch-Index".to_string(),
+        value: "1".to_string(),
+    }];
+    let id = client.upload_bytes_with_tags(data_with_media_type, tags).await?;
+    // Same length as an Arweave transaction id
+    assert_eq!(id.len(), 43);
+    assert_eq!(client.read(&id)?, string.as_bytes());
+
+    let tags = client.tags(&id)?;
+    assert_eq!(tags.len(), 2);
+    assert_eq!(tags[0].name, "content-type");
+    assert_eq!(tags[0].value, mime::TEXT_PLAIN.to_string());
+    assert_eq!(tags[1].name, "Epoch-Index");
+    assert_eq!(tags[1].value, "1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_json() -> Result<()> {
+    let client = client();
+
+    let json = json!({
+        "string": Alphanumeric.sample_string(&mut rand::thread_rng(), 10),
+    });
+    let id = client.upload_json(&json).await?;
+    let uploaded: Value = serde_json::from_slice(&client.read(&id)?)?;
+    assert_eq!(uploaded, json);
+    assert_eq!(client.upload_json(&json).await?, id);
+    Ok(())
+}
@@ -0,0 +1,255 @@
+//! Authentication and authorization.
+//!
+//! Requests carry an HS256 signed JWT in the `Authorization: Bearer <token>` header.
+//! Service tokens (indexers and operators) are generated with the `gen-token` binary.
+//! Users get a session token by signing a login challenge with their wallet (Sign-In with Solana).
+//!
+//! The verified [`Identity`] is attached to every GraphQL request, and privileged fields are
+//! protected with [`RoleGuard`]. User scoped queries resolve their wallet with [`resolve_wallet`].
+
+mod models;
+mod rate_limit;
+pub mod services;
+
+use anyhow::{anyhow, Result};
+use async_graphql::{Context, Guard};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+use crate::WalletAddr;
+
+pub use models::*;
+pub use rate_limit::*;
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Background indexers. Drive the epoch lifecycle and the stake updates.
+    Indexer,
+    /// Humans operating the lottery.
+    Operator,
+    /// End users. Can only act on their own wallet.
+    User,
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "indexer" => Ok(Role::Indexer),
+            "operator" => Ok(Role::Operator),
+            "user" => Ok(Role::User),
+            _ => Err(anyhow!("Invalid role: {}", s)),
+        }
+    }
+}
+
+/// Roles allowed to run the privileged mutations.
+pub const SERVICE_ROLES: &[Role] = &[Role::Indexer, Role::Operator];
+
+/// Roles allowed to resolve manual reviews.
+pub const OPERATOR_ROLES: &[Role] = &[Role::Operator];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Claims {
+    /// Service name or user wallet address.
+    pub sub: String,
+    pub role: Role,
+    /// Expiry as a unix timestamp.
+    pub exp: i64,
+}
+
+/// Who is making the request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Identity {
+    Anonymous,
+    Authenticated(Claims),
+}
+
+/// Issues and verifies tokens.
+#[derive(Clone)]
+pub struct Auth {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    user_token_ttl: Duration,
+}
+
+impl Auth {
+    pub fn new(secret: &[u8], user_token_ttl: Duration) -> Self {
+        Self {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            user_token_ttl,
+        }
+    }
+
+    pub fn issue(&self, sub: &str, role: Role, expires_at: DateTime<Utc>) -> Result<String> {
+        let claims = Claims {
+            sub: sub.to_owned(),
+            role,
+            exp: expires_at.timestamp(),
+        };
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &self.encoding_key,
+        )?)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+        let data = jsonwebtoken::decode::<Claims>(token, &self.decoding_key, &validation)?;
+        Ok(data.claims)
+    }
+
+    /// Identity of a request from its `Authorization` header value.
+    pub fn identify(&self, authorization: Option<&str>) -> Result<Identity> {
+        let authorization = match authorization {
+            Some(authorization) => authorization,
+            None => return Ok(Identity::Anonymous),
+        };
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .ok_or_else(|| anyhow!("Expected a Bearer token"))?;
+        Ok(Identity::Authenticated(self.verify(token.trim())?))
+    }
+
+    /// Issue a session token for a wallet that signed in.
+    pub fn issue_session(&self, wallet: &Pubkey, now: DateTime<Utc>) -> Result<AuthToken> {
+        let expires_at = now + self.user_token_ttl;
+        Ok(AuthToken {
+            token: self.issue(&wallet.to_string(), Role::User, expires_at)?,
+            expires_at,
+        })
+    }
+}
+
+/// Allows the field only for the given roles.
+pub struct RoleGuard {
+    roles: &'static [Role],
+}
+
+impl RoleGuard {
+    pub fn new(roles: &'static [Role]) -> Self {
+        Self { roles }
+    }
+}
+
+#[async_trait]
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        match ctx.data_opt::<Identity>() {
+            Some(Identity::Authenticated(claims)) if self.roles.contains(&claims.role) => Ok(()),
+            Some(Identity::Authenticated(_)) => Err("Forbidden".into()),
+            _ => Err("Unauthenticated".into()),
+        }
+    }
+}
+
+/// Subject of the token of an authenticated request: a service name or user wallet.
+pub fn subject(ctx: &Context<'_>) -> async_graphql::Result<String> {
+    match ctx.data_opt::<Identity>() {
+        Some(Identity::Authenticated(claims)) => Ok(claims.sub.clone()),
+        _ => Err("Unauthenticated".into()),
+    }
+}
+
+/// Allows service roles, and users acting on their own wallet.
+pub fn ensure_wallet_access(ctx: &Context<'_>, wallet: &WalletAddr) -> async_graphql::Result<()> {
+    match ctx.data_opt::<Identity>() {
+        Some(Identity::Authenticated(claims)) if SERVICE_ROLES.contains(&claims.role) => Ok(()),
+        Some(Identity::Authenticated(claims)) if claims.role == Role::User && claims.sub == wallet.0 => Ok(()),
+        Some(Identity::Authenticated(_)) => Err("Forbidden".into()),
+        _ => Err("Unauthenticated".into()),
+    }
+}
+
+/// Wallet of a user scoped query.
+///
+/// Defaults to the signed in wallet. Querying another wallet is public, but rate limited per client,
+/// unless it's done by a service.
+pub fn resolve_wallet(ctx: &Context<'_>, wallet: Option<WalletAddr>) -> async_graphql::Result<Pubkey> {
+    let claims = match ctx.data_opt::<Identity>() {
+        Some(Identity::Authenticated(claims)) => Some(claims),
+        _ => None,
+    };
+
+    let wallet = match (wallet, claims) {
+        (Some(wallet), _) => wallet,
+        (None, Some(claims)) if claims.role == Role::User => WalletAddr(claims.sub.clone()),
+        (None, _) => return Err("Wallet is required when not signed in".into()),
+    };
+
+    let is_own_wallet = claims.map_or(false, |claims| claims.role == Role::User && claims.sub == wallet.0);
+    let is_service = claims.map_or(false, |claims| SERVICE_ROLES.contains(&claims.role));
+    if !is_own_wallet && !is_service {
+        if let (Some(rate_limiter), Some(client)) = (ctx.data_opt::<RateLimiter>(), ctx.data_opt::<ClientAddr>()) {
+            rate_limiter
+                .check(&client.0)
+                .map_err(|_| "Rate limit exceeded. Sign in to query your own wallet without limits")?;
+        }
+    }
+
+    Ok(Pubkey::from_str(&wallet.0)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    fn auth() -> Auth {
+        Auth::new(b"secret", Duration::hours(1))
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let auth = auth();
+        let token = auth
+            .issue("indexer_deposits", Role::Indexer, Utc::now() + Duration::minutes(1))
+            .unwrap();
+        let claims = auth.verify(&token).unwrap();
+        assert_eq!(claims.sub, "indexer_deposits");
+        assert_eq!(claims.role, Role::Indexer);
+
+        let other = Auth::new(b"other secret", Duration::hours(1));
+        assert!(other.verify(&token).is_err());
+
+        let expired = auth
+            .issue("indexer_deposits", Role::Indexer, Utc::now() - Duration::minutes(1))
+            .unwrap();
+        assert!(auth.verify(&expired).is_err());
+    }
+
+    #[test]
+    fn test_identify() {
+        let auth = auth();
+        assert_eq!(auth.identify(None).unwrap(), Identity::Anonymous);
+        assert!(auth.identify(Some("Basic abc")).is_err());
+        assert!(auth.identify(Some("Bearer abc")).is_err());
+
+        let token = auth
+            .issue("ops", Role::Operator, Utc::now() + Duration::minutes(1))
+            .unwrap();
+        match auth.identify(Some(&format!("Bearer {}", token))).unwrap() {
+            Identity::Authenticated(claims) => assert_eq!(claims.role, Role::Operator),
+            Identity::Anonymous => panic!("Expected an authenticated identity"),
+        }
+    }
+
+    #[test]
+    fn test_issue_session() {
+        let auth = auth();
+        let wallet = Keypair::new().pubkey();
+        let session = auth.issue_session(&wallet, Utc::now()).unwrap();
+        let claims = auth.verify(&session.token).unwrap();
+        assert_eq!(claims.sub, wallet.to_string());
+        assert_eq!(claims.role, Role::User);
+    }
+}
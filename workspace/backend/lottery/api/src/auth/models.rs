@@ -0,0 +1,16 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+
+#[derive(SimpleObject, Debug)]
+pub struct AuthToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct LoginChallenge {
+    /// The message to sign.
+    pub message: String,
+    pub nonce: String,
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Address of the client making the request.
+#[derive(Clone, Debug)]
+pub struct ClientAddr(pub String);
+
+/// Networks of the proxies trusted to forward the client address in `X-Forwarded-For`, parsed from comma separated
+/// addresses or CIDR blocks, e.g. `10.0.0.0/8,127.0.0.1`.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl FromStr for TrustedProxies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut networks = Vec::new();
+        for network in s.split(',').map(str::trim).filter(|network| !network.is_empty()) {
+            let (addr, prefix_len) = network.split_once('/').unwrap_or((network, ""));
+            let addr = IpAddr::from_str(addr).map_err(|e| format!("Invalid trusted proxy {}: {}", network, e))?;
+            let max_prefix_len = if addr.is_ipv4() { 32 } else { 128 };
+            let prefix_len = match prefix_len {
+                "" => max_prefix_len,
+                prefix_len => prefix_len
+                    .parse()
+                    .ok()
+                    .filter(|prefix_len| *prefix_len <= max_prefix_len)
+                    .ok_or_else(|| format!("Invalid prefix length of trusted proxy {}", network))?,
+            };
+            networks.push((addr, prefix_len));
+        }
+        Ok(Self(networks))
+    }
+}
+
+impl TrustedProxies {
+    fn contains(&self, addr: IpAddr) -> bool {
+        self.0.iter().any(|(network, prefix_len)| match (network, addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix_len as u32).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix_len as u32).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        })
+    }
+
+    /// Address of the client: the peer, unless it's a trusted proxy. Each proxy appends the address it got the
+    /// request from to `X-Forwarded-For`, so the client is the last one that isn't a trusted proxy. Anything before it
+    /// is set by the client itself.
+    pub fn client_addr(&self, peer: Option<IpAddr>, forwarded_for: &[&str]) -> ClientAddr {
+        let mut client = peer;
+        if client.map_or(false, |addr| self.contains(addr)) {
+            let forwarded = forwarded_for
+                .iter()
+                .flat_map(|header| header.split(','))
+                .map(|addr| IpAddr::from_str(addr.trim()).ok())
+                .collect::<Vec<_>>();
+            for addr in forwarded.into_iter().rev() {
+                // An address that can't be parsed can't be traced any further
+                client = addr;
+                if !addr.map_or(false, |addr| self.contains(addr)) {
+                    break;
+                }
+            }
+        }
+        ClientAddr(client.map(|addr| addr.to_string()).unwrap_or_default())
+    }
+}
+
+/// Fixed window rate limiter, per client.
+///
+/// The state is kept in memory, so every API instance limits separately.
+pub struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    clients: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, client: &str) -> async_graphql::Result<()> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: &str, now: Instant) -> async_graphql::Result<()> {
+        let mut clients = self.clients.lock().unwrap();
+        let window = self.window;
+        clients.retain(|_, (window_start, _)| now.duration_since(*window_start) < window);
+
+        let (_, count) = clients.entry(client.to_owned()).or_insert((now, 0));
+        if *count >= self.max_requests {
+            return Err("Rate limit exceeded".into());
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_err());
+        assert!(limiter.check_at("b", now).is_ok());
+
+        let later = now + Duration::from_secs(60);
+        assert!(limiter.check_at("a", later).is_ok());
+    }
+
+    #[test]
+    fn test_client_addr() {
+        let proxies: TrustedProxies = "10.0.0.0/8, ::1".parse().unwrap();
+        let client_addr = |peer: &str, forwarded_for: &[&str]| proxies.client_addr(peer.parse().ok(), forwarded_for).0;
+
+        // Only trusted proxies can forward the address
+        assert_eq!(client_addr("1.2.3.4", &["5.6.7.8"]), "1.2.3.4");
+        assert_eq!(client_addr("10.1.2.3", &[]), "10.1.2.3");
+        assert_eq!(client_addr("10.1.2.3", &["5.6.7.8"]), "5.6.7.8");
+        assert_eq!(client_addr("::1", &["5.6.7.8"]), "5.6.7.8");
+        // Addresses set by the client are skipped
+        assert_eq!(client_addr("10.1.2.3", &["9.9.9.9, 5.6.7.8"]), "5.6.7.8");
+        assert_eq!(client_addr("10.1.2.3", &["9.9.9.9", "5.6.7.8, 10.0.0.1"]), "5.6.7.8");
+        assert_eq!(client_addr("10.1.2.3", &["5.6.7.8, unknown"]), "");
+
+        assert!(TrustedProxies::default().client_addr(None, &["5.6.7.8"]).0.is_empty());
+        assert!("10.0.0.0/33".parse::<TrustedProxies>().is_err());
+        assert!("proxy".parse::<TrustedProxies>().is_err());
+    }
+}
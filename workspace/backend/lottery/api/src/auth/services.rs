@@ -0,0 +1,46 @@
+use std::str::FromStr;
+
+use super::{models::*, Auth, ClientAddr, RateLimiter};
+use crate::WalletAddr;
+use async_graphql::{Context, FieldResult, Object};
+use chrono::Utc;
+use service::sign_in::SignInService;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+#[derive(Default)]
+pub struct AuthMutation;
+
+#[Object]
+impl AuthMutation {
+    /// Start signing in. The wallet signs the returned message and passes the signature to `login`.
+    pub async fn login_challenge<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<LoginChallenge> {
+        // Every challenge is stored until it expires
+        if let (Some(rate_limiter), Some(client)) = (ctx.data_opt::<RateLimiter>(), ctx.data_opt::<ClientAddr>()) {
+            rate_limiter.check(&client.0)?;
+        }
+        let sign_in_service = ctx.data::<Box<dyn SignInService>>()?;
+        let wallet = Pubkey::from_str(&wallet.0)?;
+        let challenge = sign_in_service.challenge(&wallet).await?;
+        Ok(LoginChallenge {
+            message: sign_in_service.message(&challenge),
+            nonce: challenge.nonce,
+            expires_at: challenge.expires_at,
+        })
+    }
+
+    /// Exchange a wallet signature of the login challenge message for a session token.
+    pub async fn login<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: WalletAddr,
+        nonce: String,
+        signature: String,
+    ) -> FieldResult<AuthToken> {
+        let sign_in_service = ctx.data::<Box<dyn SignInService>>()?;
+        let auth = ctx.data::<Auth>()?;
+        let wallet = Pubkey::from_str(&wallet.0)?;
+        let signature = Signature::from_str(&signature)?;
+        sign_in_service.verify(&wallet, &nonce, &signature).await?;
+        Ok(auth.issue_session(&wallet, Utc::now())?)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::WalletAddr;
+use anyhow::{anyhow, Result};
+use async_graphql::{Enum, InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use service::model::campaign;
+use uuid::Uuid;
+
+/// A promotion granting bonus sequences to the eligible wallets of every epoch from `startEpoch` to `endEpoch`.
+#[derive(SimpleObject, Debug)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub name: String,
+    pub start_epoch: u64,
+    /// Last epoch of the campaign, inclusive. Open ended if null.
+    pub end_epoch: Option<u64>,
+    pub min_stake: Option<String>,
+    /// Only wallets that didn't play any draw before.
+    pub new_users_only: bool,
+    /// Draws the wallet must have played before the epoch.
+    pub min_draws_played: Option<u64>,
+    /// Only wallets that were referred by another wallet.
+    pub referred_only: bool,
+    pub reward_kind: CampaignRewardKind,
+    /// Number of sequences for `CONSTANT`, amount staked per sequence for `PER_STAKE`, percentage of the normal
+    /// sequences for `MULTIPLIER`.
+    pub reward_value: String,
+    /// Most sequences a wallet is granted over the whole campaign.
+    pub max_sequences_per_wallet: Option<u32>,
+    pub active: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CampaignRewardKind {
+    Constant,
+    PerStake,
+    Multiplier,
+}
+
+impl From<campaign::Campaign> for Campaign {
+    fn from(campaign: campaign::Campaign) -> Self {
+        let reward_kind = match campaign.reward {
+            campaign::CampaignReward::Constant(_) => CampaignRewardKind::Constant,
+            campaign::CampaignReward::PerStake { .. } => CampaignRewardKind::PerStake,
+            campaign::CampaignReward::Multiplier { .. } => CampaignRewardKind::Multiplier,
+        };
+        Self {
+            id: campaign.id,
+            name: campaign.name,
+            start_epoch: campaign.start_epoch,
+            end_epoch: campaign.end_epoch,
+            min_stake: campaign.eligibility.min_stake.map(|min_stake| min_stake.to_string()),
+            new_users_only: campaign.eligibility.new_users_only,
+            min_draws_played: campaign.eligibility.min_draws_played,
+            referred_only: campaign.eligibility.referred_only,
+            reward_kind,
+            reward_value: campaign.reward.value(),
+            max_sequences_per_wallet: campaign.max_sequences_per_wallet,
+            active: campaign.active,
+            created_by: campaign.created_by,
+            created_at: campaign.created_at,
+        }
+    }
+}
+
+#[derive(InputObject, Debug)]
+pub struct CampaignInput {
+    pub name: String,
+    pub start_epoch: u64,
+    pub end_epoch: Option<u64>,
+    pub min_stake: Option<String>,
+    #[graphql(default)]
+    pub new_users_only: bool,
+    pub min_draws_played: Option<u64>,
+    #[graphql(default)]
+    pub referred_only: bool,
+    pub reward_kind: CampaignRewardKind,
+    pub reward_value: String,
+    pub max_sequences_per_wallet: Option<u32>,
+}
+
+impl TryFrom<CampaignInput> for campaign::NewCampaign {
+    type Error = anyhow::Error;
+
+    fn try_from(input: CampaignInput) -> Result<Self> {
+        let reward_type = match input.reward_kind {
+            CampaignRewardKind::Constant => "constant",
+            CampaignRewardKind::PerStake => "per_stake",
+            CampaignRewardKind::Multiplier => "multiplier",
+        };
+        Ok(Self {
+            name: input.name,
+            start_epoch: input.start_epoch,
+            end_epoch: input.end_epoch,
+            eligibility: campaign::CampaignEligibility {
+                min_stake: input
+                    .min_stake
+                    .map(|min_stake| min_stake.parse().map_err(|e| anyhow!("Can't parse min stake: {}", e)))
+                    .transpose()?,
+                new_users_only: input.new_users_only,
+                min_draws_played: input.min_draws_played,
+                referred_only: input.referred_only,
+            },
+            reward: campaign::CampaignReward::parse(reward_type, &input.reward_value)
+                .map_err(|e| anyhow!("Can't parse reward value {}: {}", input.reward_value, e))?,
+            max_sequences_per_wallet: input.max_sequences_per_wallet,
+        })
+    }
+}
+
+/// Sequences a campaign granted a wallet for an epoch.
+#[derive(SimpleObject, Debug)]
+pub struct CampaignGrant {
+    pub campaign_id: Uuid,
+    pub wallet: WalletAddr,
+    pub epoch_index: u64,
+    pub num_sequences: u32,
+    pub granted_at: DateTime<Utc>,
+}
+
+impl From<campaign::CampaignGrant> for CampaignGrant {
+    fn from(grant: campaign::CampaignGrant) -> Self {
+        Self {
+            campaign_id: grant.campaign_id,
+            wallet: grant.wallet.into(),
+            epoch_index: grant.epoch_index,
+            num_sequences: grant.num_sequences,
+            granted_at: grant.granted_at,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct Referral {
+    pub wallet: WalletAddr,
+    pub referrer: WalletAddr,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<campaign::Referral> for Referral {
+    fn from(referral: campaign::Referral) -> Self {
+        Self {
+            wallet: referral.wallet.into(),
+            referrer: referral.referrer.into(),
+            created_at: referral.created_at,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use super::models::*;
+use crate::auth::{ensure_wallet_access, resolve_wallet, subject, RoleGuard, OPERATOR_ROLES};
+use crate::WalletAddr;
+use async_graphql::{Context, FieldResult, Object};
+use service::{campaign::CampaignService, model::campaign::NewCampaign};
+use uuid::Uuid;
+
+#[derive(Default)]
+pub struct CampaignsQuery;
+
+#[Object]
+impl CampaignsQuery {
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn campaigns(&self, ctx: &Context<'_>) -> FieldResult<Vec<Campaign>> {
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let campaigns = service.campaigns().await?;
+        Ok(campaigns.into_iter().map(Into::into).collect())
+    }
+
+    /// Wallets the campaign granted sequences, and how many.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn campaign_grants(
+        &self,
+        ctx: &Context<'_>,
+        campaign_id: Uuid,
+        limit: usize,
+        offset: usize,
+    ) -> FieldResult<Vec<CampaignGrant>> {
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let grants = service.grants_by_campaign(&campaign_id, limit, offset).await?;
+        Ok(grants.into_iter().map(Into::into).collect())
+    }
+
+    /// Campaign bonus sequences granted to the wallet.
+    pub async fn campaign_grants_by_wallet(
+        &self,
+        ctx: &Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<Vec<CampaignGrant>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let grants = service.grants_by_wallet(&wallet).await?;
+        Ok(grants.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn referral(&self, ctx: &Context<'_>, wallet: Option<WalletAddr>) -> FieldResult<Option<Referral>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        Ok(service.referral(&wallet).await?.map(Into::into))
+    }
+}
+
+#[derive(Default)]
+pub struct CampaignMutation;
+
+#[Object]
+impl CampaignMutation {
+    /// Starts granting bonus sequences to the eligible wallets from `startEpoch`.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn create_campaign(&self, ctx: &Context<'_>, campaign: CampaignInput) -> FieldResult<Campaign> {
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let campaign = service
+            .create_campaign(NewCampaign::try_from(campaign)?, &subject(ctx)?)
+            .await?;
+        Ok(campaign.into())
+    }
+
+    /// Pauses or resumes a campaign. Sequences it granted already stay on the tickets.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn set_campaign_active(&self, ctx: &Context<'_>, id: Uuid, active: bool) -> FieldResult<Campaign> {
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let campaign = service.set_active(&id, active).await?;
+        Ok(campaign.into())
+    }
+
+    /// Records that the wallet was referred by `referrer`, which must have played a draw. A wallet is referred at most once.
+    pub async fn register_referral(
+        &self,
+        ctx: &Context<'_>,
+        wallet: WalletAddr,
+        referrer: WalletAddr,
+    ) -> FieldResult<Referral> {
+        ensure_wallet_access(ctx, &wallet)?;
+        let service = ctx.data::<Box<dyn CampaignService>>()?;
+        let referral = service
+            .register_referral(&wallet.try_into()?, &referrer.try_into()?)
+            .await?;
+        Ok(referral.into())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    epochs::models::{EpochStatus, WinningCombination},
+    tickets::models::SequenceType,
+};
+use async_graphql::{ComplexObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use service::model::draw_stats;
+
+/// Aggregates of the tickets and prizes of an epoch.
+#[derive(SimpleObject, Debug)]
+#[graphql(complex)]
+pub struct EpochStats {
+    pub epoch_index: u64,
+    /// Status of the epoch when the stats were computed. Stats are final once it's `ENDED`.
+    pub status: EpochStatus,
+    pub num_players: u64,
+    pub sequences_by_type: Vec<SequenceTypeCount>,
+    /// How often each of the first 5 numbers was picked, over all positions.
+    pub main_number_frequencies: Vec<NumberFrequency>,
+    /// How often each last number was picked.
+    pub last_number_frequencies: Vec<NumberFrequency>,
+    #[graphql(skip)]
+    pub shared_prefixes: Vec<PrefixCount>,
+    pub prizes_by_tier: Vec<TierPrizes>,
+    pub computed_at: DateTime<Utc>,
+}
+
+#[ComplexObject]
+impl EpochStats {
+    /// Prefixes of 4 and 5 numbers shared by more than one ticket, the most shared first, up to 100.
+    async fn shared_prefixes(
+        &self,
+        #[graphql(default = 50)] limit: usize,
+        #[graphql(default = 0)] offset: usize,
+    ) -> Vec<PrefixCount> {
+        self.shared_prefixes.iter().skip(offset).take(limit).cloned().collect()
+    }
+}
+
+impl From<draw_stats::EpochStats> for EpochStats {
+    fn from(stats: draw_stats::EpochStats) -> Self {
+        Self {
+            epoch_index: stats.epoch_index,
+            status: stats.status.into(),
+            num_players: stats.num_players,
+            sequences_by_type: stats.sequences_by_type.into_iter().map(Into::into).collect(),
+            main_number_frequencies: stats.main_number_frequencies.into_iter().map(Into::into).collect(),
+            last_number_frequencies: stats.last_number_frequencies.into_iter().map(Into::into).collect(),
+            shared_prefixes: stats.shared_prefixes.into_iter().map(Into::into).collect(),
+            prizes_by_tier: stats.prizes_by_tier.into_iter().map(Into::into).collect(),
+            computed_at: stats.computed_at,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct SequenceTypeCount {
+    pub sequence_type: SequenceType,
+    pub num_sequences: u64,
+}
+
+impl From<draw_stats::SequenceTypeCount> for SequenceTypeCount {
+    fn from(count: draw_stats::SequenceTypeCount) -> Self {
+        Self {
+            sequence_type: count.sequence_type.into(),
+            num_sequences: count.num_sequences,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct NumberFrequency {
+    pub number: u8,
+    pub num_sequences: u64,
+}
+
+impl From<draw_stats::NumberFrequency> for NumberFrequency {
+    fn from(frequency: draw_stats::NumberFrequency) -> Self {
+        Self {
+            number: frequency.number,
+            num_sequences: frequency.num_sequences,
+        }
+    }
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+pub struct PrefixCount {
+    pub prefix: Vec<u8>,
+    pub num_tickets: u64,
+    pub num_sequences: u64,
+}
+
+impl From<draw_stats::PrefixCount> for PrefixCount {
+    fn from(count: draw_stats::PrefixCount) -> Self {
+        Self {
+            prefix: count.prefix,
+            num_tickets: count.num_tickets,
+            num_sequences: count.num_sequences,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct TierPrizes {
+    pub tier: u8,
+    pub num_winners: u64,
+    pub total_amount: String,
+}
+
+impl From<draw_stats::TierPrizes> for TierPrizes {
+    fn from(prizes: draw_stats::TierPrizes) -> Self {
+        Self {
+            tier: prizes.tier,
+            num_winners: prizes.num_winners,
+            total_amount: prizes.total_amount.to_string(),
+        }
+    }
+}
+
+impl From<draw_stats::WinningCombination> for WinningCombination {
+    fn from(winning_combination: draw_stats::WinningCombination) -> Self {
+        Self {
+            epoch_index: winning_combination.epoch_index,
+            winning_combination: winning_combination.combination,
+        }
+    }
+}
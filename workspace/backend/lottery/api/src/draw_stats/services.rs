@@ -0,0 +1,43 @@
+use super::models::*;
+use crate::auth::{RoleGuard, OPERATOR_ROLES};
+use crate::epochs::models::WinningCombination;
+use async_graphql::{Context, FieldResult, Object};
+use service::draw_stats::DrawStatsService;
+
+#[derive(Default)]
+pub struct DrawStatsQuery;
+
+#[Object]
+impl DrawStatsQuery {
+    /// Null if the epoch doesn't exist.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn epoch_stats(&self, ctx: &Context<'_>, epoch_index: u64) -> FieldResult<Option<EpochStats>> {
+        let service = ctx.data::<Box<dyn DrawStatsService>>()?;
+        Ok(service.epoch_stats(epoch_index).await?.map(Into::into))
+    }
+
+    /// Winning combinations of the past epochs, the most recent first.
+    pub async fn winning_combinations(
+        &self,
+        ctx: &Context<'_>,
+        limit: usize,
+        offset: usize,
+    ) -> FieldResult<Vec<WinningCombination>> {
+        let service = ctx.data::<Box<dyn DrawStatsService>>()?;
+        let winning_combinations = service.winning_combinations(limit, offset).await?;
+        Ok(winning_combinations.into_iter().map(Into::into).collect())
+    }
+}
+
+#[derive(Default)]
+pub struct DrawStatsMutation;
+
+#[Object]
+impl DrawStatsMutation {
+    /// Computes the stats of the epoch again, ignoring the cached ones.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn refresh_epoch_stats(&self, ctx: &Context<'_>, epoch_index: u64) -> FieldResult<Option<EpochStats>> {
+        let service = ctx.data::<Box<dyn DrawStatsService>>()?;
+        Ok(service.refresh_epoch_stats(epoch_index).await?.map(Into::into))
+    }
+}
@@ -3,8 +3,9 @@ use async_graphql::{ComplexObject, Context, Enum, FieldResult, SimpleObject};
 use chrono::{DateTime, Utc};
 use service::{
     epoch::EpochManager,
-    model::{epoch, winner},
+    model::{epoch, epoch_audit, epoch_job, winner},
 };
+use uuid::Uuid;
 
 /// Represents a prize where the user is part of the winners, the Epoch is a pubkey, which can be used to retrieve
 /// the epoch as well. This is a slow operation so the pubkey is returned to avoid unnecessary operations.
@@ -47,6 +48,12 @@ pub struct Epoch {
     pub(crate) draw_enabled: DrawEnabled,
 }
 
+#[derive(SimpleObject, Debug)]
+pub struct WinningCombination {
+    pub epoch_index: u64,
+    pub winning_combination: [u8; 6],
+}
+
 #[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
 pub enum EpochStatus {
     Running,
@@ -182,3 +189,172 @@ impl From<Investor> for service::model::epoch::Investor {
         }
     }
 }
+
+/// Outcome of simulating an epoch operation. Only the first transaction is simulated.
+#[derive(SimpleObject, Debug)]
+pub struct Simulation {
+    pub num_transactions: usize,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    pub account_diffs: Vec<AccountDiff>,
+    pub vault_transfers: Vec<VaultTransfer>,
+}
+
+impl From<service::solana::Simulation> for Simulation {
+    fn from(simulation: service::solana::Simulation) -> Self {
+        Self {
+            num_transactions: simulation.num_transactions,
+            error: simulation.error,
+            logs: simulation.logs,
+            units_consumed: simulation.units_consumed,
+            account_diffs: simulation.account_diffs.into_iter().map(Into::into).collect(),
+            vault_transfers: simulation.vault_transfers.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Decoded account before and after the transaction. None if the account doesn't exist.
+#[derive(SimpleObject, Debug)]
+pub struct AccountDiff {
+    pub pubkey: WalletAddr,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+impl From<service::solana::AccountDiff> for AccountDiff {
+    fn from(diff: service::solana::AccountDiff) -> Self {
+        Self {
+            pubkey: diff.pubkey.into(),
+            before: diff.before,
+            after: diff.after,
+        }
+    }
+}
+
+/// Token balance change. Amounts are in the mint's base units.
+#[derive(SimpleObject, Debug)]
+pub struct VaultTransfer {
+    pub account: WalletAddr,
+    pub vault: Option<String>,
+    pub mint: WalletAddr,
+    pub before: String,
+    pub after: String,
+    pub change: String,
+}
+
+impl From<service::solana::VaultTransfer> for VaultTransfer {
+    fn from(transfer: service::solana::VaultTransfer) -> Self {
+        let change = transfer.change();
+        Self {
+            account: transfer.account.into(),
+            vault: transfer.vault,
+            mint: transfer.mint.into(),
+            before: transfer.before.to_string(),
+            after: transfer.after.to_string(),
+            change: change.to_string(),
+        }
+    }
+}
+
+/// Epoch operation sent or simulated by an operator.
+#[derive(SimpleObject, Debug)]
+pub struct EpochAuditLog {
+    pub id: Uuid,
+    pub operator: String,
+    pub operation: String,
+    /// Parameters as JSON.
+    pub params: String,
+    pub dry_run: bool,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<epoch_audit::EpochAuditLog> for EpochAuditLog {
+    fn from(entry: epoch_audit::EpochAuditLog) -> Self {
+        Self {
+            id: entry.id,
+            operator: entry.operator,
+            operation: entry.operation,
+            params: entry.params.to_string(),
+            dry_run: entry.dry_run,
+            signature: entry.signature,
+            error: entry.error,
+            created_at: entry.created_at,
+        }
+    }
+}
+
+#[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
+pub enum EpochJobCommand {
+    CreateEpoch,
+    EnterInvestment,
+    ExitInvestment,
+    PublishWinningCombination,
+    PublishWinners,
+    FundJackpot,
+}
+
+impl From<epoch_job::EpochJobCommand> for EpochJobCommand {
+    fn from(command: epoch_job::EpochJobCommand) -> Self {
+        match command {
+            epoch_job::EpochJobCommand::CreateEpoch => Self::CreateEpoch,
+            epoch_job::EpochJobCommand::EnterInvestment => Self::EnterInvestment,
+            epoch_job::EpochJobCommand::ExitInvestment => Self::ExitInvestment,
+            epoch_job::EpochJobCommand::PublishWinningCombination => Self::PublishWinningCombination,
+            epoch_job::EpochJobCommand::PublishWinners => Self::PublishWinners,
+            epoch_job::EpochJobCommand::FundJackpot => Self::FundJackpot,
+        }
+    }
+}
+
+#[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
+pub enum EpochJobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl From<epoch_job::EpochJobStatus> for EpochJobStatus {
+    fn from(status: epoch_job::EpochJobStatus) -> Self {
+        match status {
+            epoch_job::EpochJobStatus::Pending => Self::Pending,
+            epoch_job::EpochJobStatus::Running => Self::Running,
+            epoch_job::EpochJobStatus::Succeeded => Self::Succeeded,
+            epoch_job::EpochJobStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+/// Step of the epoch lifecycle run by the epoch indexer.
+/// A failed attempt is retried at nextAttemptAt. A job which gave up after too many failed attempts is failed.
+#[derive(SimpleObject, Debug)]
+pub struct EpochJob {
+    pub epoch_index: u64,
+    pub command: EpochJobCommand,
+    pub status: EpochJobStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub due_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<epoch_job::EpochJob> for EpochJob {
+    fn from(job: epoch_job::EpochJob) -> Self {
+        Self {
+            epoch_index: job.epoch_index,
+            command: job.command.into(),
+            status: job.status.into(),
+            attempts: job.attempts,
+            last_error: job.last_error,
+            due_at: job.due_at,
+            next_attempt_at: job.next_attempt_at,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        }
+    }
+}
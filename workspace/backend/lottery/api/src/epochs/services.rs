@@ -1,16 +1,17 @@
 use std::{ops::Add, str::FromStr};
 
+use crate::auth::{resolve_wallet, subject, RoleGuard, OPERATOR_ROLES, SERVICE_ROLES};
 use crate::WalletAddr;
 
 use super::{input::*, models::*};
-use async_graphql::{Context, FieldResult, Object};
+use async_graphql::{Context, FieldResult, Object, Subscription};
 use chrono::{Duration, Utc};
+use futures::{future, Stream, StreamExt};
 use service::{
     epoch::{EpochManager, FPUSDC},
-    model::epoch::UseCache,
-    tickets::TicketService,
+    events::{Event, EventBus},
+    model::{epoch::UseCache, epoch_audit::EpochOperation},
 };
-use solana_sdk::pubkey::Pubkey;
 
 #[derive(Default)]
 pub struct EpochsQuery;
@@ -62,11 +63,14 @@ impl EpochsQuery {
         }
     }
 
-    pub async fn wallet_prizes<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<Vec<UserPrize>> {
+    pub async fn wallet_prizes<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<Vec<UserPrize>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn EpochManager>>()?;
 
-        let wallet = Pubkey::from_str(&wallet.0)?;
-
         let prizes = service
             .wallet_prizes(&wallet)
             .await?
@@ -83,6 +87,32 @@ impl EpochsQuery {
 
         Ok(prizes)
     }
+
+    /// Epoch operations sent or simulated by operators, most recent first.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn epoch_audit_log<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        #[graphql(default = 50)] limit: usize,
+        #[graphql(default = 0)] offset: usize,
+    ) -> FieldResult<Vec<EpochAuditLog>> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+        let entries = epoch_service.audit_log(limit, offset).await?;
+        Ok(entries.into_iter().map(Into::into).collect())
+    }
+
+    /// Jobs of the epoch indexer, most recently updated first.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn epoch_jobs<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        #[graphql(default = 50)] limit: usize,
+        #[graphql(default = 0)] offset: usize,
+    ) -> FieldResult<Vec<EpochJob>> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+        let jobs = epoch_service.jobs(limit, offset).await?;
+        Ok(jobs.into_iter().map(Into::into).collect())
+    }
 }
 
 #[derive(Default)]
@@ -90,6 +120,7 @@ pub struct EpochMutation;
 
 #[Object]
 impl EpochMutation {
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn create_epoch<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -104,23 +135,31 @@ impl EpochMutation {
 
         let yield_split_cfg = make_yield_split_cfg(prizes, yield_split_cfg)?;
 
-        Ok(epoch_service.create_epoch(expected_end, yield_split_cfg).await?.into())
+        Ok(epoch_service
+            .create_epoch(expected_end, yield_split_cfg, &subject(ctx)?)
+            .await?
+            .into())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn publish_winners<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<Epoch> {
         let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
-        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
 
-        let winners = ticket_service.calculate_winners().await?;
+        let winners = epoch_service.calculate_winners().await?;
 
-        Ok(epoch_service.publish_winners(winners).await?.into())
+        Ok(epoch_service.publish_winners(winners, &subject(ctx)?).await?.into())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn enter_investment<'a>(&self, ctx: &'a Context<'_>, investor: Investor) -> FieldResult<Epoch> {
         let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
-        Ok(epoch_service.enter_investment(investor.into()).await?.into())
+        Ok(epoch_service
+            .enter_investment(investor.into(), &subject(ctx)?)
+            .await?
+            .into())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn exit_investment<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -130,14 +169,15 @@ impl EpochMutation {
         let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
         let return_amount = return_amount.as_deref().map(FPUSDC::from_str).transpose()?;
         Ok(epoch_service
-            .exit_investment(investor.into(), return_amount)
+            .exit_investment(investor.into(), return_amount, &subject(ctx)?)
             .await?
             .into())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn fund_jackpot<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<Epoch> {
         let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
-        epoch_service.fund_jackpot().await?;
+        epoch_service.fund_jackpot(&subject(ctx)?).await?;
 
         // Just to statisfy the graphql requirement that we must return something.
         let latest_epoch = epoch_service
@@ -147,6 +187,76 @@ impl EpochMutation {
         Ok(latest_epoch.into())
     }
 
+    /// Simulates create_epoch without sending it.
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
+    pub async fn dry_run_create_epoch<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        prizes: PrizesInput,
+        expected_duration_minutes: u32,
+        yield_split_cfg: YieldSplitCfgInput,
+    ) -> FieldResult<Simulation> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+
+        let expected_duration = Duration::minutes(expected_duration_minutes as i64);
+        let operation = EpochOperation::CreateEpoch {
+            expected_end_date: Utc::now().add(expected_duration),
+            yield_split_cfg: make_yield_split_cfg(prizes, yield_split_cfg)?,
+        };
+        Ok(epoch_service.dry_run(operation, &subject(ctx)?).await?.into())
+    }
+
+    /// Simulates publish_winners with the winners calculated from the current tickets.
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
+    pub async fn dry_run_publish_winners<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<Simulation> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+
+        let winners = epoch_service.calculate_winners().await?;
+        let operation = EpochOperation::PublishWinners { winners };
+        Ok(epoch_service.dry_run(operation, &subject(ctx)?).await?.into())
+    }
+
+    /// Simulates enter_investment without sending it.
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
+    pub async fn dry_run_enter_investment<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        investor: Investor,
+    ) -> FieldResult<Simulation> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+        let operation = EpochOperation::EnterInvestment {
+            investor: investor.into(),
+        };
+        Ok(epoch_service.dry_run(operation, &subject(ctx)?).await?.into())
+    }
+
+    /// Simulates exit_investment without sending it.
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
+    pub async fn dry_run_exit_investment<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        investor: Investor,
+        return_amount: Option<String>,
+    ) -> FieldResult<Simulation> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+        let operation = EpochOperation::ExitInvestment {
+            investor: investor.into(),
+            return_amount: return_amount.as_deref().map(FPUSDC::from_str).transpose()?,
+        };
+        Ok(epoch_service.dry_run(operation, &subject(ctx)?).await?.into())
+    }
+
+    /// Simulates fund_jackpot without sending it.
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
+    pub async fn dry_run_fund_jackpot<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<Simulation> {
+        let epoch_service = ctx.data::<Box<dyn EpochManager>>()?;
+        Ok(epoch_service
+            .dry_run(EpochOperation::FundJackpot, &subject(ctx)?)
+            .await?
+            .into())
+    }
+
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn publish_winning_combination<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -171,3 +281,39 @@ impl EpochMutation {
         Ok(epoch)
     }
 }
+
+#[derive(Default)]
+pub struct EpochsSubscription;
+
+#[Subscription]
+impl EpochsSubscription {
+    /// The latest epoch, whenever its status changes.
+    async fn epoch_status<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<impl Stream<Item = Epoch>> {
+        let events = ctx.data::<EventBus>()?;
+        Ok(events.subscribe().filter_map(|event| {
+            future::ready(match event {
+                Event::EpochStatusChanged(epoch) => Some(epoch.into()),
+                _ => None,
+            })
+        }))
+    }
+
+    async fn winning_combination<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+    ) -> FieldResult<impl Stream<Item = WinningCombination>> {
+        let events = ctx.data::<EventBus>()?;
+        Ok(events.subscribe().filter_map(|event| {
+            future::ready(match event {
+                Event::WinningCombinationPublished {
+                    epoch_index,
+                    winning_combination,
+                } => Some(WinningCombination {
+                    epoch_index,
+                    winning_combination,
+                }),
+                _ => None,
+            })
+        }))
+    }
+}
@@ -0,0 +1,28 @@
+//! Generate a service token.
+//!
+//! ```sh
+//! AUTH_JWT_SECRET=... cargo run --bin gen-token -- <indexer|operator> <name> [valid_days]
+//! ```
+use anyhow::{anyhow, Result};
+use api::auth::{Auth, Role};
+use chrono::{Duration, Utc};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 3 {
+        return Err(anyhow!("Usage: gen-token <indexer|operator> <name> [valid_days]"));
+    }
+    let role: Role = args[1].parse()?;
+    if role == Role::User {
+        return Err(anyhow!("User tokens are issued by the login mutation"));
+    }
+    let name = &args[2];
+    let valid_days: i64 = args.get(3).map(|x| x.parse()).transpose()?.unwrap_or(365);
+
+    let secret = std::env::var("AUTH_JWT_SECRET").map_err(|_| anyhow!("AUTH_JWT_SECRET is not set"))?;
+    // The user token ttl doesn't matter here
+    let auth = Auth::new(secret.as_bytes(), Duration::zero());
+    let token = auth.issue(name, role, Utc::now() + Duration::days(valid_days))?;
+    println!("{}", token);
+    Ok(())
+}
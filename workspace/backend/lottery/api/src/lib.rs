@@ -1,3 +1,6 @@
+pub mod auth;
+mod campaigns;
+mod draw_stats;
 mod epochs;
 mod health_check;
 pub mod schema;
@@ -5,24 +8,91 @@ mod tickets;
 mod types;
 mod users;
 
-use crate::{health_check::health_check_handler, schema::*};
-use actix_web::{dev::Server, guard, middleware, web, App, HttpResponse, HttpServer};
+use crate::{
+    auth::{Auth, ClientAddr, Identity, RateLimiter, TrustedProxies},
+    health_check::health_check_handler,
+    schema::*,
+};
+use actix_web::{dev::Server, guard, middleware, web, App, HttpRequest, HttpResponse, HttpServer};
 use anyhow::Result;
 use async_graphql::{
     http::{playground_source, GraphQLPlaygroundConfig},
-    EmptySubscription, Schema,
+    Data, Schema, ServerError,
 };
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
 use log::info;
 use service::{
-    epoch::EpochManager, faucet::FaucetService, health_check::ServiceHealthCheck, prize::PrizeService,
-    stake::StakeService, tickets::TicketService, transaction::UserTransactionService,
+    campaign::CampaignService, draw_stats::DrawStatsService, epoch::EpochManager, events::EventBus,
+    faucet::FaucetService, health_check::ServiceHealthCheck, notification::NotificationPreferencesService,
+    prize::PrizeService, screening::StakeUpdateReviewService, sign_in::SignInService, stake::StakeService,
+    tickets::TicketService, transaction::UserTransactionService,
 };
 use std::{net::TcpListener, sync::Arc};
 pub use types::*;
 
-async fn index(schema: web::Data<NezhaSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+async fn index(
+    schema: web::Data<NezhaSchema>,
+    auth: web::Data<Auth>,
+    trusted_proxies: web::Data<TrustedProxies>,
+    http_req: HttpRequest,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    let authorization = http_req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let identity: Identity = match auth.identify(authorization) {
+        Ok(identity) => identity,
+        Err(e) => {
+            let error = ServerError::new(format!("Invalid authorization: {}", e), None);
+            return async_graphql::Response::from_errors(vec![error]).into();
+        }
+    };
+    schema
+        .execute(
+            req.into_inner()
+                .data(identity)
+                .data(client_addr(&http_req, &trusted_proxies)),
+        )
+        .await
+        .into()
+}
+
+/// Subscriptions over WebSocket.
+///
+/// Browsers can't set headers on WebSocket requests, so the token is passed as `Authorization` in the
+/// connection init payload instead.
+async fn index_ws(
+    schema: web::Data<NezhaSchema>,
+    auth: web::Data<Auth>,
+    trusted_proxies: web::Data<TrustedProxies>,
+    http_req: HttpRequest,
+    payload: web::Payload,
+) -> actix_web::Result<HttpResponse> {
+    let auth = auth.into_inner();
+    let mut data = Data::default();
+    data.insert(client_addr(&http_req, &trusted_proxies));
+    GraphQLSubscription::new(Schema::clone(&*schema))
+        .with_data(data)
+        .on_connection_init(move |init_payload| async move {
+            let authorization = init_payload.get("Authorization").and_then(|value| value.as_str());
+            let identity = auth
+                .identify(authorization)
+                .map_err(|e| format!("Invalid authorization: {}", e))?;
+            let mut data = Data::default();
+            data.insert(identity);
+            Ok(data)
+        })
+        .start(&http_req, payload)
+}
+
+fn client_addr(http_req: &HttpRequest, trusted_proxies: &TrustedProxies) -> ClientAddr {
+    let forwarded_for = http_req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>();
+    trusted_proxies.client_addr(http_req.peer_addr().map(|addr| addr.ip()), &forwarded_for)
 }
 
 async fn index_playground() -> actix_web::Result<HttpResponse> {
@@ -37,19 +107,35 @@ pub fn load_schema(
     user_transaction_service: Box<dyn UserTransactionService>,
     faucet_service: Box<dyn FaucetService>,
     prize_service: Box<dyn PrizeService>,
-) -> Schema<Query, Mutation, EmptySubscription> {
-    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+    sign_in_service: Box<dyn SignInService>,
+    stake_update_review_service: Box<dyn StakeUpdateReviewService>,
+    notification_preferences_service: Box<dyn NotificationPreferencesService>,
+    campaign_service: Box<dyn CampaignService>,
+    draw_stats_service: Box<dyn DrawStatsService>,
+    auth: Auth,
+    rate_limiter: RateLimiter,
+    events: EventBus,
+) -> NezhaSchema {
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
         .data(epoch_service)
         .data(stake_service)
         .data(user_transaction_service)
         .data(ticket_service)
         .data(faucet_service)
         .data(prize_service)
+        .data(sign_in_service)
+        .data(stake_update_review_service)
+        .data(notification_preferences_service)
+        .data(campaign_service)
+        .data(draw_stats_service)
+        .data(auth)
+        .data(rate_limiter)
+        .data(events)
         .finish()
 }
 
 pub fn sdl_export() -> String {
-    Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
         .finish()
         .sdl()
 }
@@ -62,6 +148,15 @@ pub async fn run(
     user_transaction_service: Box<dyn UserTransactionService>,
     faucet_service: Box<dyn FaucetService>,
     prize_service: Box<dyn PrizeService>,
+    sign_in_service: Box<dyn SignInService>,
+    stake_update_review_service: Box<dyn StakeUpdateReviewService>,
+    notification_preferences_service: Box<dyn NotificationPreferencesService>,
+    campaign_service: Box<dyn CampaignService>,
+    draw_stats_service: Box<dyn DrawStatsService>,
+    auth: Auth,
+    rate_limiter: RateLimiter,
+    trusted_proxies: TrustedProxies,
+    events: EventBus,
     service_health_check: Arc<ServiceHealthCheck>,
     git_version: String,
 ) -> Result<Server, std::io::Error> {
@@ -72,7 +167,17 @@ pub async fn run(
         user_transaction_service,
         faucet_service,
         prize_service,
+        sign_in_service,
+        stake_update_review_service,
+        notification_preferences_service,
+        campaign_service,
+        draw_stats_service,
+        auth.clone(),
+        rate_limiter,
+        events,
     );
+    let auth = web::Data::new(auth);
+    let trusted_proxies = web::Data::new(trusted_proxies);
     let health_check_data = web::Data::from(service_health_check);
     let git_version = web::Data::from(Arc::new(git_version));
     info!("{}", schema.sdl());
@@ -80,8 +185,16 @@ pub async fn run(
     let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(schema.clone()))
+            .app_data(auth.clone())
+            .app_data(trusted_proxies.clone())
             .wrap(middleware::Logger::default())
             .service(web::resource("/").guard(guard::Post()).to(index))
+            .service(
+                web::resource("/")
+                    .guard(guard::Get())
+                    .guard(guard::Header("upgrade", "websocket"))
+                    .to(index_ws),
+            )
             .service(web::resource("/").guard(guard::Get()).to(index_playground))
             .service(
                 web::scope("/health_check")
@@ -1,31 +1,57 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use api::auth::{Auth, RateLimiter, TrustedProxies};
+use arweave_uploader::{BundlrClient, BundlrConfig, FsUploaderClient};
 use envconfig::Envconfig;
 use git_version::git_version;
 use log::info;
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
+use service::campaign::{CampaignService, DefaultCampaignService};
+use service::draw_stats::{DefaultDrawStatsService, DrawStatsService};
 use service::epoch::EpochRepository;
 use service::epoch::{service::EpochService, EpochManager};
+use service::events::EventBus;
 use service::faucet::SolanaFaucetService;
 use service::health_check::ServiceHealthCheck;
+use service::notification::{DefaultNotificationPreferencesService, NotificationPreferencesService};
 use service::prize::PrizeServiceImpl;
-use service::solana::rpc::SolanaRpcReal;
+use service::screening::{
+    DefaultStakeUpdateReviewService, ManualReviewScreener, SanctionsListScreener, ScreenerChain,
+    StakeUpdateReviewService, StakeUpdateScreener, VelocityScreener,
+};
+use service::sign_in::{DefaultSignInService, SignInService};
+use service::solana::rpc::{SolanaRpcMirrored, SolanaRpcReal};
 use service::solana::solana_impl::SolanaImpl;
 use service::solana::{SwitchboardConfiguration, VrfConfiguration, FPUSDC};
 use service::stake::DefaultStakeService;
 use service::stake::StakeService;
 use service::tickets::bonus::{BonusInfo, BonusSequenceCount, DefaultBonusInfoService};
-use service::tickets::{ConstantTicketPriceCalculator, DefaultTicketService, TicketRepository, TicketService};
+use service::tickets::{
+    ArweaveManifestPublisher, ConstantTicketPriceCalculator, DefaultTicketService, LoyaltyTicketPriceCalculator,
+    ManifestPublisher, PriceTier, RecordedManifestPublisher, TicketAllocation, TicketGeneration, TicketPriceCalculator,
+    TicketRepository, TicketService, TieredTicketPriceCalculator, YieldIndexedTicketPriceCalculator,
+};
 use service::transaction::{UserTransactionRepository, UserTransactionService, UserTransactionServiceImpl};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signer::keypair::read_keypair};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{net::TcpListener, str::FromStr};
+use store::campaigns::PostgresCampaignRepository;
+use store::draw_stats::PostgresDrawStatsRepository;
+use store::epoch_audit::PostgresEpochAuditRepository;
+use store::epoch_jobs::PostgresEpochJobRepository;
+use store::epoch_seeds::PostgresEpochSeedRepository;
 use store::epochs::PostgresEpochRepository;
+use store::events::PostgresEventRelay;
 use store::faucet::PostgresFaucetRepository;
 use store::health_check::DbHealthCheck;
+use store::login_challenges::PostgresLoginChallengeRepository;
+use store::notifications::PostgresNotificationPreferencesRepository;
 use store::prizes::PostgresPrizeRepository;
+use store::program_accounts::PostgresProgramAccountRepository;
+use store::published_manifests::PostgresPublishedManifestRepository;
+use store::stake_update_reviews::PostgresStakeUpdateReviewRepository;
 use store::transactions::PostgresUserTransactionRepository;
 use store::{stake_update::PostgresStakeUpdateRepository, tickets::PostgresTicketRepository, DbConfig};
 
@@ -84,6 +110,95 @@ pub struct AppConfig {
 
     #[envconfig(from = "SWITCHBOARD_CONFIG", default = "fake")]
     pub switchboard_configuration: String,
+
+    #[envconfig(from = "AUTH_JWT_SECRET")]
+    pub auth_jwt_secret: String,
+
+    #[envconfig(from = "AUTH_USER_TOKEN_TTL_MINUTES", default = "1440")]
+    pub auth_user_token_ttl_minutes: i64,
+
+    #[envconfig(from = "SIWS_DOMAIN", default = "nezha")]
+    pub siws_domain: String,
+
+    #[envconfig(from = "SIWS_CHALLENGE_TTL_SECONDS", default = "300")]
+    pub siws_challenge_ttl_seconds: i64,
+
+    #[envconfig(from = "PUBLIC_QUERY_RATE_LIMIT_PER_MINUTE", default = "60")]
+    pub public_query_rate_limit_per_minute: u32,
+
+    /// Proxies, e.g. the ingress, allowed to set the client address in `X-Forwarded-For`.
+    #[envconfig(from = "TRUSTED_PROXIES", default = "")]
+    pub trusted_proxies: TrustedProxies,
+
+    /// OFAC-style CSV of sanctioned addresses. Sanctions screening is off when unset.
+    #[envconfig(from = "SANCTIONS_LIST_PATH")]
+    pub sanctions_list_path: Option<String>,
+
+    #[envconfig(from = "VELOCITY_WINDOW_HOURS", default = "24")]
+    pub velocity_window_hours: i64,
+
+    #[envconfig(from = "VELOCITY_MAX_REQUESTS", default = "10")]
+    pub velocity_max_requests: usize,
+
+    #[envconfig(from = "VELOCITY_MAX_AMOUNT", default = "100000")]
+    pub velocity_max_amount: String,
+
+    /// Program account scans of read only services are served from the account mirror if it was checked within this
+    /// time, and its subscription seen alive within `ACCOUNT_MIRROR_MAX_SUBSCRIPTION_SILENCE_SECONDS`.
+    #[envconfig(from = "ACCOUNT_MIRROR_MAX_STALENESS_SECONDS", default = "900")]
+    pub account_mirror_max_staleness_seconds: i64,
+
+    #[envconfig(from = "ACCOUNT_MIRROR_MAX_SUBSCRIPTION_SILENCE_SECONDS", default = "60")]
+    pub account_mirror_max_subscription_silence_seconds: i64,
+
+    /// `random` draws ticket sequences from the server RNG, `deterministic` derives them from public on-chain data so
+    /// that users can verify them.
+    #[envconfig(from = "TICKET_GENERATION", default = "random")]
+    pub ticket_generation: String,
+
+    /// `snapshot` sizes tickets for the stake balance when they are generated, `time-weighted` for the balance
+    /// averaged over the epoch from the indexed deposits and withdrawals.
+    #[envconfig(from = "TICKET_ALLOCATION", default = "time-weighted")]
+    pub ticket_allocation: String,
+
+    /// Where epoch ticket manifests are uploaded: `bundlr` uploads to Arweave paying with the admin keypair, `fs`
+    /// writes them to `MANIFEST_DIR` for local development.
+    #[envconfig(from = "MANIFEST_UPLOADER", default = "bundlr")]
+    pub manifest_uploader: String,
+
+    #[envconfig(from = "MANIFEST_DIR", default = "manifests")]
+    pub manifest_dir: String,
+
+    #[envconfig(from = "BUNDLR_URL", default = "https://node1.bundlr.network")]
+    pub bundlr_url: String,
+
+    /// How tickets are priced: `constant` at `TICKET_PRICE`, `tiered` by `TICKET_PRICE_TIERS`, `loyalty` discounts
+    /// `TICKET_PRICE` by the draws the wallet played, `yield` targets `TICKET_TARGET_PRIZE_PER_TICKET` of prizes per
+    /// ticket from the previous epoch's returns.
+    #[envconfig(from = "TICKET_PRICING", default = "constant")]
+    pub ticket_pricing: String,
+
+    #[envconfig(from = "TICKET_PRICE", default = "25.0")]
+    pub ticket_price: String,
+
+    /// Comma separated `from:price` pairs, e.g. `0:25,1000:20,10000:15`.
+    #[envconfig(from = "TICKET_PRICE_TIERS", default = "0:25")]
+    pub ticket_price_tiers: String,
+
+    #[envconfig(from = "TICKET_LOYALTY_DISCOUNT_PER_DRAW", default = "0.01")]
+    pub ticket_loyalty_discount_per_draw: String,
+
+    #[envconfig(from = "TICKET_LOYALTY_MAX_DISCOUNT", default = "0.2")]
+    pub ticket_loyalty_max_discount: String,
+
+    #[envconfig(from = "TICKET_TARGET_PRIZE_PER_TICKET", default = "0.05")]
+    pub ticket_target_prize_per_ticket: String,
+
+    #[envconfig(from = "TICKET_MIN_PRICE", default = "5.0")]
+    pub ticket_min_price: String,
+
+    #[envconfig(from = "TICKET_MAX_PRICE", default = "50.0")]
+    pub ticket_max_price: String,
 }
 
 impl AppConfig {
@@ -101,17 +216,43 @@ async fn main() -> Result<()> {
 
     let config: AppConfig = AppConfig::init_from_env()?;
 
-    let solana = new_solana(&config).await;
-
     let db_pool = store::connect(&db_config).await;
+
+    let solana = new_solana(&config).await;
+    // The mirror may lag behind the chain, so only services that send no transactions read from it
+    let mirrored_solana = new_mirrored_solana(&config, &solana, db_pool.clone());
     let listener = TcpListener::bind(config.connection_string())?;
 
     let rng = Arc::new(Mutex::new(ChaChaRng::from_entropy()));
+    // Subscribers may be connected to any replica, so events are relayed to the others
+    let event_relay = PostgresEventRelay::new(db_pool.clone());
+    let relayed_events = event_relay.listen(db_config.clone(), Duration::from_secs(5));
+    let events = EventBus::default().with_relay(Box::new(event_relay));
+    tokio::spawn({
+        let events = events.clone();
+        let epoch_repository = PostgresEpochRepository::new(db_pool.clone());
+        let ticket_repository = PostgresTicketRepository::new(db_pool.clone());
+        async move {
+            events
+                .receive_relayed(relayed_events, &epoch_repository, &ticket_repository)
+                .await
+        }
+    });
     let stake_update_repo = PostgresStakeUpdateRepository::new(db_pool.clone());
+    let stake_update_review_repo = PostgresStakeUpdateReviewRepository::new(db_pool.clone());
+    let screener = ManualReviewScreener::new(
+        new_screener(&config, db_pool.clone())?,
+        Box::new(stake_update_review_repo.clone()),
+    );
     let stake_service: Box<dyn StakeService> = Box::new(DefaultStakeService::new(
         Box::new(solana.clone()),
         Box::new(stake_update_repo.clone()),
+        Box::new(screener),
+        events.clone(),
     ));
+    let stake_update_review_service: Box<dyn StakeUpdateReviewService> = Box::new(
+        DefaultStakeUpdateReviewService::new(Box::new(solana.clone()), Box::new(stake_update_review_repo)),
+    );
     let user_transaction_repository: Box<dyn UserTransactionRepository> = Box::new(
         PostgresUserTransactionRepository::new(db_pool.clone(), config.transaction_max_query_limit),
     );
@@ -123,33 +264,15 @@ async fn main() -> Result<()> {
         Box::new(solana.clone()),
         epoch_repository,
         ticket_repository,
+        new_ticket_service(&config, &solana, db_pool.clone(), rng.clone(), events.clone())?,
+        new_manifest_publisher(&config, db_pool.clone())?,
+        Box::new(PostgresEpochAuditRepository::new(db_pool.clone())),
+        Box::new(PostgresEpochJobRepository::new(db_pool.clone())),
+        events.clone(),
     ));
-
-    let ticket_price_calc = ConstantTicketPriceCalculator::new("25.0".parse().unwrap());
-    let ticket_repository: Box<dyn TicketRepository> = Box::new(PostgresTicketRepository::new(db_pool.clone()));
-    let sub_seq_count = if config.signup_bonus_sequence_count.chars().last() == Some('X') {
-        let mul = config.signup_bonus_sequence_count[..config.signup_bonus_sequence_count.len() - 1].parse::<f32>()?;
-        let min_count = config.signup_bonus_min_sequence_count;
-        BonusSequenceCount::Multiplier { mul, min_count }
-    } else {
-        let count = config.signup_bonus_sequence_count.parse::<u32>()?;
-        BonusSequenceCount::Constant(count)
-    };
-
-    let bonus_info_service = Box::new(DefaultBonusInfoService::new(BonusInfo {
-        sub_seq_count,
-        sub_seq_min_stake: config
-            .signup_bonus_sequence_min_stake
-            .parse()
-            .map_err(|e: String| anyhow!(e))?,
-    }));
-    let ticket_service: Box<dyn TicketService> = Box::new(DefaultTicketService::new(
-        rng,
-        Box::new(solana.clone()),
-        ticket_repository,
-        Box::new(ticket_price_calc),
-        bonus_info_service,
-    ));
+    // The winners published by the epoch mutations are calculated by the epoch service's own ticket service
+    let ticket_service = new_ticket_service(&config, &mirrored_solana, db_pool.clone(), rng, events.clone())?;
+    let campaign_repository = PostgresCampaignRepository::new(db_pool.clone());
     let faucet_repository = PostgresFaucetRepository::new(db_pool.clone());
     let faucet_retry_time_limit = chrono::Duration::seconds(config.faucet_retry_limit_seconds);
     let faucet_mint_amount = FPUSDC::from(config.faucet_mint_amount);
@@ -164,6 +287,33 @@ async fn main() -> Result<()> {
     let prize_repository = PostgresPrizeRepository::new(db_pool.clone(), config.prize_max_query_limit);
     let prize_service = Box::new(PrizeServiceImpl::new(Box::new(prize_repository)));
 
+    let login_challenge_repository = PostgresLoginChallengeRepository::new(db_pool.clone());
+    let sign_in_service: Box<dyn SignInService> = Box::new(DefaultSignInService::new(
+        Box::new(login_challenge_repository),
+        config.siws_domain.clone(),
+        chrono::Duration::seconds(config.siws_challenge_ttl_seconds),
+    ));
+
+    let notification_preferences_service: Box<dyn NotificationPreferencesService> =
+        Box::new(DefaultNotificationPreferencesService::new(Box::new(
+            PostgresNotificationPreferencesRepository::new(db_pool.clone()),
+        )));
+
+    let campaign_service: Box<dyn CampaignService> = Box::new(DefaultCampaignService::new(
+        Box::new(campaign_repository),
+        Box::new(PostgresTicketRepository::new(db_pool.clone())),
+    ));
+
+    let draw_stats_service: Box<dyn DrawStatsService> = Box::new(DefaultDrawStatsService::new(Box::new(
+        PostgresDrawStatsRepository::new(db_pool.clone()),
+    )));
+
+    let auth = Auth::new(
+        config.auth_jwt_secret.as_bytes(),
+        chrono::Duration::minutes(config.auth_user_token_ttl_minutes),
+    );
+    let rate_limiter = RateLimiter::new(config.public_query_rate_limit_per_minute, Duration::from_secs(60));
+
     let git_version: &str = git_version!(args = ["--abbrev=40", "--always"]);
 
     let db_health_check = DbHealthCheck::new(db_pool.clone(), Duration::from_secs(20));
@@ -179,6 +329,15 @@ async fn main() -> Result<()> {
         user_transaction_service,
         faucet_service,
         prize_service,
+        sign_in_service,
+        stake_update_review_service,
+        notification_preferences_service,
+        campaign_service,
+        draw_stats_service,
+        auth,
+        rate_limiter,
+        config.trusted_proxies,
+        events,
         service_health_check,
         git_version.to_string(),
     )
@@ -188,6 +347,152 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+fn new_screener(config: &AppConfig, db_pool: store::Pool) -> Result<Box<dyn StakeUpdateScreener>> {
+    let mut screeners: Vec<Box<dyn StakeUpdateScreener>> = Vec::new();
+    if let Some(path) = &config.sanctions_list_path {
+        screeners.push(Box::new(SanctionsListScreener::from_csv_file(path)?));
+    }
+    screeners.push(Box::new(VelocityScreener::new(
+        Box::new(PostgresUserTransactionRepository::new(
+            db_pool,
+            config.transaction_max_query_limit,
+        )),
+        chrono::Duration::hours(config.velocity_window_hours),
+        config.velocity_max_requests,
+        config.velocity_max_amount.parse().map_err(|e: String| anyhow!(e))?,
+    )));
+    Ok(Box::new(ScreenerChain::new(screeners)))
+}
+
+fn new_manifest_publisher(config: &AppConfig, db_pool: store::Pool) -> Result<Box<dyn ManifestPublisher>> {
+    let app_name = "nezha".to_string();
+    let publisher: Box<dyn ManifestPublisher> = match config.manifest_uploader.as_str() {
+        "bundlr" => {
+            let mut bytes = &config.admin_keypair.as_bytes()[..];
+            let keypair = read_keypair(&mut bytes).map_err(|e| anyhow!("unable to read admin keypair: {}", e))?;
+            let uploader = BundlrClient::new(BundlrConfig {
+                bundlr_url: config.bundlr_url.clone(),
+                keypair,
+                api_client: reqwest::Client::new(),
+                solana_client: RpcClient::new(config.solana_http_rpc_url.clone()),
+            });
+            Box::new(ArweaveManifestPublisher::new(uploader, app_name))
+        }
+        "fs" => Box::new(ArweaveManifestPublisher::new(
+            FsUploaderClient::new(&config.manifest_dir),
+            app_name,
+        )),
+        other => bail!("Invalid MANIFEST_UPLOADER: {}", other),
+    };
+    Ok(Box::new(RecordedManifestPublisher::new(
+        publisher,
+        Box::new(PostgresPublishedManifestRepository::new(db_pool)),
+    )))
+}
+
+fn new_ticket_service(
+    config: &AppConfig,
+    solana: &SolanaImpl,
+    db_pool: store::Pool,
+    rng: Arc<Mutex<ChaChaRng>>,
+    events: EventBus,
+) -> Result<Box<dyn TicketService>> {
+    let ticket_price_calc = new_ticket_price_calculator(config, solana, db_pool.clone())?;
+    let ticket_repository: Box<dyn TicketRepository> = Box::new(PostgresTicketRepository::new(db_pool.clone()));
+    let sub_seq_count = if config.signup_bonus_sequence_count.chars().last() == Some('X') {
+        let mul = config.signup_bonus_sequence_count[..config.signup_bonus_sequence_count.len() - 1].parse::<f32>()?;
+        let min_count = config.signup_bonus_min_sequence_count;
+        BonusSequenceCount::Multiplier { mul, min_count }
+    } else {
+        let count = config.signup_bonus_sequence_count.parse::<u32>()?;
+        BonusSequenceCount::Constant(count)
+    };
+
+    let bonus_info_service = Box::new(DefaultBonusInfoService::new(BonusInfo {
+        sub_seq_count,
+        sub_seq_min_stake: config
+            .signup_bonus_sequence_min_stake
+            .parse()
+            .map_err(|e: String| anyhow!(e))?,
+    }));
+    let ticket_generation = match config.ticket_generation.as_str() {
+        "random" => TicketGeneration::Random,
+        "deterministic" => TicketGeneration::Deterministic(Box::new(PostgresEpochSeedRepository::new(db_pool.clone()))),
+        other => bail!("Invalid TICKET_GENERATION: {}", other),
+    };
+    let ticket_allocation = match config.ticket_allocation.as_str() {
+        "snapshot" => TicketAllocation::Snapshot,
+        "time-weighted" => TicketAllocation::TimeWeighted(Box::new(PostgresUserTransactionRepository::new(
+            db_pool.clone(),
+            config.transaction_max_query_limit,
+        ))),
+        other => bail!("Invalid TICKET_ALLOCATION: {}", other),
+    };
+    Ok(Box::new(DefaultTicketService::new(
+        rng,
+        Box::new(solana.clone()),
+        ticket_repository,
+        ticket_price_calc,
+        bonus_info_service,
+        events,
+        ticket_generation,
+        ticket_allocation,
+        Box::new(DefaultCampaignService::new(
+            Box::new(PostgresCampaignRepository::new(db_pool.clone())),
+            Box::new(PostgresTicketRepository::new(db_pool)),
+        )),
+    )))
+}
+
+fn parse_usdc(name: &str, value: &str) -> Result<FPUSDC> {
+    value.parse().map_err(|e: String| anyhow!("Invalid {}: {}", name, e))
+}
+
+fn new_ticket_price_calculator(
+    config: &AppConfig,
+    solana: &SolanaImpl,
+    db_pool: store::Pool,
+) -> Result<Box<dyn TicketPriceCalculator>> {
+    let price = parse_usdc("TICKET_PRICE", &config.ticket_price)?;
+    Ok(match config.ticket_pricing.as_str() {
+        "constant" => Box::new(ConstantTicketPriceCalculator::new(price)),
+        "tiered" => {
+            let tiers = config
+                .ticket_price_tiers
+                .split(',')
+                .map(|tier| {
+                    let (from, price) = tier
+                        .split_once(':')
+                        .ok_or_else(|| anyhow!("Invalid TICKET_PRICE_TIERS: {}", config.ticket_price_tiers))?;
+                    Ok(PriceTier {
+                        from: parse_usdc("TICKET_PRICE_TIERS", from.trim())?,
+                        price: parse_usdc("TICKET_PRICE_TIERS", price.trim())?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Box::new(TieredTicketPriceCalculator::new(tiers)?)
+        }
+        "loyalty" => Box::new(LoyaltyTicketPriceCalculator::new(
+            Box::new(solana.clone()),
+            Box::new(PostgresTicketRepository::new(db_pool)),
+            price,
+            parse_usdc(
+                "TICKET_LOYALTY_DISCOUNT_PER_DRAW",
+                &config.ticket_loyalty_discount_per_draw,
+            )?,
+            parse_usdc("TICKET_LOYALTY_MAX_DISCOUNT", &config.ticket_loyalty_max_discount)?,
+        )?),
+        "yield" => Box::new(YieldIndexedTicketPriceCalculator::new(
+            Box::new(solana.clone()),
+            price,
+            parse_usdc("TICKET_TARGET_PRIZE_PER_TICKET", &config.ticket_target_prize_per_ticket)?,
+            parse_usdc("TICKET_MIN_PRICE", &config.ticket_min_price)?,
+            parse_usdc("TICKET_MAX_PRICE", &config.ticket_max_price)?,
+        )?),
+        other => bail!("Invalid TICKET_PRICING: {}", other),
+    })
+}
+
 async fn new_solana(config: &AppConfig) -> SolanaImpl {
     let mut bytes = &config.admin_keypair.as_bytes()[..];
     let admin_keypair = Arc::new(read_keypair(&mut bytes).expect("unable to read admin keypair"));
@@ -224,3 +529,16 @@ async fn new_solana(config: &AppConfig) -> SolanaImpl {
 
     solana
 }
+
+fn new_mirrored_solana(config: &AppConfig, solana: &SolanaImpl, db_pool: store::Pool) -> SolanaImpl {
+    SolanaImpl {
+        rpc_client: Arc::new(SolanaRpcMirrored::new(
+            solana.rpc_client.clone(),
+            solana.program_id,
+            Box::new(PostgresProgramAccountRepository::new(db_pool)),
+            chrono::Duration::seconds(config.account_mirror_max_staleness_seconds),
+            chrono::Duration::seconds(config.account_mirror_max_subscription_silence_seconds),
+        )),
+        ..solana.clone()
+    }
+}
@@ -1,10 +1,23 @@
-use crate::{epochs::services::*, tickets::services::*, users::services::*};
-use async_graphql::{EmptySubscription, MergedObject, Schema};
+use crate::{
+    auth::services::*, campaigns::services::*, draw_stats::services::*, epochs::services::*, tickets::services::*,
+    users::services::*,
+};
+use async_graphql::{MergedObject, MergedSubscription, Schema};
 
 #[derive(MergedObject, Default)]
-pub struct Query(EpochsQuery, UsersQuery, TicketsQuery);
+pub struct Query(EpochsQuery, UsersQuery, TicketsQuery, CampaignsQuery, DrawStatsQuery);
 
 #[derive(MergedObject, Default)]
-pub struct Mutation(EpochMutation, UserMutation, TicketMutation);
+pub struct Mutation(
+    EpochMutation,
+    UserMutation,
+    TicketMutation,
+    CampaignMutation,
+    DrawStatsMutation,
+    AuthMutation,
+);
 
-pub type NezhaSchema = Schema<Query, Mutation, EmptySubscription>;
+#[derive(MergedSubscription, Default)]
+pub struct Subscription(EpochsSubscription, UsersSubscription, TicketsSubscription);
+
+pub type NezhaSchema = Schema<Query, Mutation, Subscription>;
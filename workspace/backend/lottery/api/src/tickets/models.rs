@@ -1,13 +1,15 @@
 use crate::WalletAddr;
 use async_graphql::{Enum, InputObject, SimpleObject};
 use itertools::Itertools;
-use service::tickets;
+use service::{epoch::FPUSDC, tickets};
 
 #[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
 pub enum SequenceType {
     Normal,
     SignUpBonus,
     AirdropBonus,
+    UserPicked,
+    CampaignBonus,
 }
 
 impl From<tickets::SequenceType> for SequenceType {
@@ -16,6 +18,8 @@ impl From<tickets::SequenceType> for SequenceType {
             tickets::SequenceType::Normal => Self::Normal,
             tickets::SequenceType::SignUpBonus => Self::SignUpBonus,
             tickets::SequenceType::AirdropBonus => Self::AirdropBonus,
+            tickets::SequenceType::UserPicked => Self::UserPicked,
+            tickets::SequenceType::CampaignBonus => Self::CampaignBonus,
         }
     }
 }
@@ -58,6 +62,80 @@ impl From<tickets::Ticket> for Ticket {
     }
 }
 
+/// A ticket's sequences reproduced from public inputs. The seed is the hash of the block the epoch account was created
+/// in, see `service::tickets::TicketSeed` for how sequences are derived from it.
+#[derive(SimpleObject, Debug)]
+pub struct TicketVerification {
+    pub(crate) ticket: Ticket,
+    pub(crate) epoch_pubkey: String,
+    pub(crate) slot: u64,
+    pub(crate) slot_hash: String,
+    /// Derived sequences reproduced from the seed, the wallet, the ticket balance and the user's picks.
+    pub(crate) sequences: Vec<Sequence>,
+    /// Whether they are the derived sequences of the ticket.
+    pub(crate) verified: bool,
+}
+
+impl From<tickets::TicketVerification> for TicketVerification {
+    fn from(verification: tickets::TicketVerification) -> Self {
+        Self {
+            ticket: verification.ticket.into(),
+            epoch_pubkey: verification.epoch_seed.epoch_pubkey.to_string(),
+            slot: verification.epoch_seed.slot,
+            slot_hash: verification.epoch_seed.slot_hash.to_string(),
+            sequences: verification.sequences.into_iter().map(Sequence::from).collect_vec(),
+            verified: verification.verified,
+        }
+    }
+}
+
+/// Chances of a wallet's ticket in the draw of the latest epoch, and the prize it can expect.
+#[derive(SimpleObject, Debug)]
+pub struct TicketOdds {
+    pub(crate) wallet: WalletAddr,
+    pub(crate) epoch_index: u64,
+    pub(crate) tiers: Vec<TierOdds>,
+    pub(crate) expected_prize: String,
+    /// Whether the yield of the epoch was returned. Until then, the tier 2 and 3 prize pools only hold the prizes
+    /// carried over from previous epochs.
+    pub(crate) final_prize_pools: bool,
+}
+
+impl From<tickets::TicketOdds> for TicketOdds {
+    fn from(odds: tickets::TicketOdds) -> Self {
+        Self {
+            wallet: WalletAddr(odds.wallet.to_string()),
+            epoch_index: odds.epoch_index,
+            tiers: odds.tiers.into_iter().map(TierOdds::from).collect_vec(),
+            expected_prize: odds.expected_prize.to_string(),
+            final_prize_pools: odds.final_prize_pools,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct TierOdds {
+    pub(crate) tier: u8,
+    /// Winning combinations for which the ticket wins the tier.
+    pub(crate) num_winning_combinations: u64,
+    pub(crate) probability: f64,
+    pub(crate) prize_pool: String,
+    /// Prize of the tier averaged over all winning combinations, after sharing it with the other winners.
+    pub(crate) expected_prize: String,
+}
+
+impl From<tickets::TierOdds> for TierOdds {
+    fn from(odds: tickets::TierOdds) -> Self {
+        Self {
+            tier: odds.tier,
+            num_winning_combinations: odds.num_winning_combinations,
+            probability: odds.probability,
+            prize_pool: odds.prize_pool.to_string(),
+            expected_prize: odds.expected_prize.to_string(),
+        }
+    }
+}
+
 #[derive(InputObject, Debug)]
 pub struct WalletRisqId {
     pub(crate) wallet: WalletAddr,
@@ -88,3 +166,97 @@ impl From<service::model::ticket::TicketsWithCount> for TicketsWithCount {
         }
     }
 }
+
+#[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
+pub enum TicketPricingKind {
+    Constant,
+    Tiered,
+    Loyalty,
+    YieldIndexed,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct PriceTier {
+    pub(crate) from: String,
+    pub(crate) price: String,
+}
+
+/// Active ticket pricing policy. Parameters of other policies are null.
+#[derive(SimpleObject, Debug)]
+pub struct TicketPricing {
+    pub(crate) kind: TicketPricingKind,
+    /// Price of the first ticket without discounts.
+    pub(crate) price: String,
+    /// Price of the part of a balance from `from` up to the next tier.
+    pub(crate) tiers: Vec<PriceTier>,
+    /// Discount per draw played, as a ratio of the price.
+    pub(crate) discount_per_draw: Option<String>,
+    pub(crate) max_discount: Option<String>,
+    /// Prizes per ticket the price targets, based on the previous epoch's returns.
+    pub(crate) target_prize_per_ticket: Option<String>,
+    pub(crate) min_price: Option<String>,
+    pub(crate) max_price: Option<String>,
+}
+
+impl From<tickets::TicketPricingPolicy> for TicketPricing {
+    fn from(policy: tickets::TicketPricingPolicy) -> Self {
+        let pricing = |kind, price: FPUSDC| Self {
+            kind,
+            price: price.to_string(),
+            tiers: vec![],
+            discount_per_draw: None,
+            max_discount: None,
+            target_prize_per_ticket: None,
+            min_price: None,
+            max_price: None,
+        };
+        match policy {
+            tickets::TicketPricingPolicy::Constant { price } => pricing(TicketPricingKind::Constant, price),
+            tickets::TicketPricingPolicy::Tiered { tiers } => Self {
+                tiers: tiers
+                    .iter()
+                    .map(|tier| PriceTier {
+                        from: tier.from.to_string(),
+                        price: tier.price.to_string(),
+                    })
+                    .collect(),
+                ..pricing(TicketPricingKind::Tiered, tiers[0].price)
+            },
+            tickets::TicketPricingPolicy::Loyalty {
+                price,
+                discount_per_draw,
+                max_discount,
+            } => Self {
+                discount_per_draw: Some(discount_per_draw.to_string()),
+                max_discount: Some(max_discount.to_string()),
+                ..pricing(TicketPricingKind::Loyalty, price)
+            },
+            tickets::TicketPricingPolicy::YieldIndexed {
+                price,
+                target_prize_per_ticket,
+                min_price,
+                max_price,
+            } => Self {
+                target_prize_per_ticket: Some(target_prize_per_ticket.to_string()),
+                min_price: Some(min_price.to_string()),
+                max_price: Some(max_price.to_string()),
+                ..pricing(TicketPricingKind::YieldIndexed, price)
+            },
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct TicketQuote {
+    pub(crate) sequences_count: u32,
+    pub(crate) price_per_ticket: String,
+}
+
+impl From<tickets::TicketPrice> for TicketQuote {
+    fn from(price: tickets::TicketPrice) -> Self {
+        Self {
+            sequences_count: price.sequences_count,
+            price_per_ticket: price.price_per_ticket.to_string(),
+        }
+    }
+}
@@ -1,9 +1,15 @@
 use super::models::*;
+use crate::auth::{ensure_wallet_access, resolve_wallet, RoleGuard, SERVICE_ROLES};
 use crate::WalletAddr;
 use anyhow::anyhow;
-use async_graphql::{Context, ErrorExtensions, FieldResult, Object};
+use async_graphql::{Context, ErrorExtensions, FieldResult, Object, Subscription};
+use futures::{future, Stream, StreamExt};
 use itertools::Itertools;
-use service::{epoch::FPUSDC, tickets::TicketService};
+use service::{
+    epoch::FPUSDC,
+    events::{Event, EventBus},
+    tickets::TicketService,
+};
 
 #[derive(Default)]
 pub struct TicketsQuery;
@@ -13,23 +19,72 @@ impl TicketsQuery {
     pub async fn ticket<'a>(
         &self,
         ctx: &'a Context<'_>,
-        wallet: WalletAddr,
+        wallet: Option<WalletAddr>,
         epoch_index: u64,
     ) -> FieldResult<Option<Ticket>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
 
         Ok(ticket_service
-            .read_ticket_by_wallet_and_epoch_index(&wallet.try_into()?, epoch_index)
+            .read_ticket_by_wallet_and_epoch_index(&wallet, epoch_index)
             .await?
             .map(|ticket| ticket.into()))
     }
 
+    /// Reproduces the wallet's ticket from public inputs. Only available when tickets are generated deterministically.
+    pub async fn ticket_verification<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+        epoch_index: u64,
+    ) -> FieldResult<Option<TicketVerification>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
+
+        Ok(ticket_service
+            .verify_ticket(&wallet, epoch_index)
+            .await?
+            .map(|verification| verification.into()))
+    }
+
+    /// Exact odds of the wallet's ticket in the draw of the latest epoch, accounting for the holders of the same prefixes
+    /// it would share prizes with.
+    pub async fn ticket_odds<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<Option<TicketOdds>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
+
+        Ok(ticket_service.ticket_odds(&wallet).await?.map(|odds| odds.into()))
+    }
+
     pub async fn ticket_price<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<String> {
         let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
         let price = ticket_service.ticket_price().await?;
         Ok(price.to_string())
     }
 
+    pub async fn ticket_pricing<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<TicketPricing> {
+        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
+        Ok(ticket_service.ticket_pricing_policy().await?.into())
+    }
+
+    /// Number of sequences the wallet's ticket has for a staked amount, e.g. its stake after a deposit.
+    pub async fn ticket_quote<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+        amount: String,
+    ) -> FieldResult<TicketQuote> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
+        let amount = amount.parse::<FPUSDC>().map_err(|e| anyhow!(e))?;
+
+        Ok(ticket_service.calculate_ticket_price(&wallet, amount).await?.into())
+    }
+
     pub async fn tickets_by_epoch_index_and_prefix<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -55,20 +110,6 @@ impl TicketsQuery {
             .collect())
     }
 
-    pub async fn calculate_optimal_winning_combination(&self, ctx: &Context<'_>) -> FieldResult<Option<[u8; 6]>> {
-        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
-
-        let sequence = ticket_service.calculate_optimal_winning_combination().await?;
-        Ok(sequence.map(|seq| seq))
-    }
-
-    pub async fn random_winning_combination(&self, ctx: &Context<'_>) -> FieldResult<Option<[u8; 6]>> {
-        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
-
-        let sequence = ticket_service.random_winning_combination().await?;
-        Ok(sequence.map(|seq| seq))
-    }
-
     pub async fn num_signup_bonus_sequences<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -83,10 +124,15 @@ impl TicketsQuery {
             .await?)
     }
 
-    pub async fn draws_played_by_wallet<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<u64> {
+    pub async fn draws_played_by_wallet<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<u64> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
 
-        Ok(ticket_service.draws_played_by_wallet(&wallet.try_into()?).await?)
+        Ok(ticket_service.draws_played_by_wallet(&wallet).await?)
     }
 }
 
@@ -96,6 +142,7 @@ pub struct TicketMutation;
 #[Object]
 impl TicketMutation {
     pub async fn generate_ticket(&self, ctx: &Context<'_>, wallet: WalletAddr) -> FieldResult<Ticket> {
+        ensure_wallet_access(ctx, &wallet)?;
         let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
 
         Ok(ticket_service
@@ -104,6 +151,24 @@ impl TicketMutation {
             .into())
     }
 
+    /// Replaces the generated sequences of the wallet's upcoming ticket with its own picks, up to the number of
+    /// sequences the stake is entitled to. The remainder is generated.
+    pub async fn submit_user_picks(
+        &self,
+        ctx: &Context<'_>,
+        wallet: WalletAddr,
+        picks: Vec<[u8; 6]>,
+    ) -> FieldResult<Ticket> {
+        ensure_wallet_access(ctx, &wallet)?;
+        let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
+
+        Ok(ticket_service
+            .submit_user_picks(&wallet.try_into()?, &picks)
+            .await?
+            .into())
+    }
+
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn generate_tickets_for_all(&self, ctx: &Context<'_>) -> FieldResult<Vec<Ticket>> {
         let ticket_service = ctx.data::<Box<dyn TicketService>>()?;
         let tickets = ticket_service.generate_tickets_for_all().await?;
@@ -120,6 +185,7 @@ impl TicketMutation {
             .collect())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn update_arweave_url(
         &self,
         ctx: &Context<'_>,
@@ -135,6 +201,7 @@ impl TicketMutation {
             .map(|ticket| ticket.into()))
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn update_risq_ids(
         &self,
         ctx: &Context<'_>,
@@ -157,6 +224,28 @@ impl TicketMutation {
     }
 }
 
+#[derive(Default)]
+pub struct TicketsSubscription;
+
+#[Subscription]
+impl TicketsSubscription {
+    /// The wallet's ticket, whenever its sequences have been generated.
+    async fn ticket_generated<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<impl Stream<Item = Ticket>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let events = ctx.data::<EventBus>()?;
+        Ok(events.subscribe().filter_map(move |event| {
+            future::ready(match event {
+                Event::TicketGenerated(ticket) if ticket.wallet == wallet => Some(ticket.into()),
+                _ => None,
+            })
+        }))
+    }
+}
+
 fn bail_if_any_fail<T>(
     results: impl Iterator<Item = anyhow::Result<T>>,
     msg: &'static str,
@@ -1,7 +1,8 @@
 use crate::{TransactionId, WalletAddr};
-use async_graphql::{Enum, SimpleObject};
+use async_graphql::{Enum, InputObject, SimpleObject};
 use chrono::{DateTime, Utc};
 use service::model;
+use uuid::Uuid;
 
 /// Macro to generate From impls in both direction for GraphQL and Service enums
 macro_rules! same_enum {
@@ -34,6 +35,14 @@ macro_rules! same_enum {
     }
 }
 
+// Me
+
+/// Private data of the signed in user.
+#[derive(SimpleObject, Debug)]
+pub struct Me {
+    pub wallet: WalletAddr,
+}
+
 // Balance
 
 #[derive(SimpleObject, Debug)]
@@ -93,6 +102,48 @@ impl From<model::stake_update::StakeUpdate> for StakeUpdate {
     }
 }
 
+// StakeUpdateReview
+
+/// A stake update request that was flagged by screening and waits for an operator.
+#[derive(SimpleObject, Debug)]
+pub struct StakeUpdateReview {
+    pub id: Uuid,
+    pub wallet: WalletAddr,
+    pub amount: String,
+    pub type_: StakeUpdateType,
+    pub reason: String,
+    pub status: StakeUpdateReviewStatus,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+same_enum! {
+    #[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+    pub enum StakeUpdateReviewStatus: model::stake_update_review::StakeUpdateReviewStatus {
+        Pending,
+        Approved,
+        Rejected,
+        Stale
+    }
+}
+
+impl From<model::stake_update_review::StakeUpdateReview> for StakeUpdateReview {
+    fn from(review: model::stake_update_review::StakeUpdateReview) -> Self {
+        Self {
+            id: review.id,
+            wallet: review.wallet.into(),
+            amount: review.amount.to_string(),
+            type_: review.type_.into(),
+            reason: review.reason,
+            status: review.status.into(),
+            created_at: review.created_at,
+            reviewed_by: review.reviewed_by,
+            reviewed_at: review.reviewed_at,
+        }
+    }
+}
+
 // DepositAttempt - This one should not be used by the UI, only by the indexers
 
 #[derive(SimpleObject, Debug)]
@@ -166,6 +217,8 @@ pub struct Transaction {
     pub mint: WalletAddr,
     pub time: Option<DateTime<Utc>>,
     pub transaction_type: TransactionType,
+    /// `false` while the transaction may still be rolled back.
+    pub finalized: bool,
 }
 
 impl From<service::model::transaction::Transaction> for Transaction {
@@ -177,6 +230,7 @@ impl From<service::model::transaction::Transaction> for Transaction {
             mint: WalletAddr::from(transaction.mint),
             time: transaction.time,
             transaction_type: transaction.transaction_type.into(),
+            finalized: transaction.finalized,
         }
     }
 }
@@ -209,3 +263,60 @@ impl From<service::model::prize::Prize> for Prize {
         }
     }
 }
+
+/// A prize the wallet won in a published draw.
+#[derive(SimpleObject, Debug)]
+pub struct PrizeAvailable {
+    pub wallet: WalletAddr,
+    pub epoch_index: u64,
+    pub tier: u8,
+}
+
+// NotificationPreferences
+
+/// Where and about what the user wants to be notified.
+#[derive(SimpleObject, Debug)]
+pub struct NotificationPreferences {
+    pub wallet: WalletAddr,
+    pub email: Option<String>,
+    /// Receives signed POST requests.
+    pub webhook_url: Option<String>,
+    /// Signs the requests to the webhook. Only returned by the update that generated it.
+    pub webhook_secret: Option<String>,
+    pub kinds: Vec<NotificationKind>,
+    pub updated_at: DateTime<Utc>,
+}
+
+same_enum! {
+    #[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+    pub enum NotificationKind: model::notification::NotificationKind {
+        PrizeWon,
+        DepositCompleted,
+        WithdrawalCompleted,
+        EpochEnded
+    }
+}
+
+impl From<model::notification::NotificationPreferences> for NotificationPreferences {
+    fn from(preferences: model::notification::NotificationPreferences) -> Self {
+        Self {
+            wallet: preferences.wallet.into(),
+            email: preferences.email,
+            webhook_url: preferences.webhook_url,
+            webhook_secret: None,
+            kinds: preferences.kinds.into_iter().map(Into::into).collect(),
+            updated_at: preferences.updated_at,
+        }
+    }
+}
+
+/// Channels left empty are turned off.
+#[derive(InputObject, Debug)]
+pub struct NotificationPreferencesInput {
+    pub email: Option<String>,
+    pub webhook_url: Option<String>,
+    pub kinds: Vec<NotificationKind>,
+    /// Generates a new secret for the webhook. A new webhook URL always gets one.
+    #[graphql(default)]
+    pub rotate_webhook_secret: bool,
+}
@@ -1,13 +1,37 @@
 use super::models::*;
+use crate::auth::{
+    ensure_wallet_access, resolve_wallet, subject, Identity, Role, RoleGuard, OPERATOR_ROLES, SERVICE_ROLES,
+};
 use crate::{TransactionId, WalletAddr};
-use async_graphql::{Context, FieldResult, Object};
-use service::{faucet::FaucetService, prize::PrizeService, stake::StakeService, transaction::UserTransactionService};
+use async_graphql::{Context, ErrorExtensions, FieldResult, Object, Subscription};
+use futures::{future, Stream, StreamExt};
+use service::{
+    events::{Event, EventBus},
+    faucet::FaucetService,
+    model::stake_update_review::{ScreeningError, FLAGGED_FOR_REVIEW_CODE},
+    notification::NotificationPreferencesService,
+    prize::PrizeService,
+    screening::StakeUpdateReviewService,
+    stake::StakeService,
+    transaction::UserTransactionService,
+};
+use uuid::Uuid;
 
 #[derive(Default)]
 pub struct UsersQuery;
 
 #[Object]
 impl UsersQuery {
+    /// The signed in user.
+    pub async fn me<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<Me> {
+        match ctx.data_opt::<Identity>() {
+            Some(Identity::Authenticated(claims)) if claims.role == Role::User => Ok(Me {
+                wallet: WalletAddr(claims.sub.clone()),
+            }),
+            _ => Err("Not signed in".into()),
+        }
+    }
+
     pub async fn balances<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<Vec<Balance>> {
         let service = ctx.data::<Box<dyn StakeService>>()?;
 
@@ -34,11 +58,12 @@ impl UsersQuery {
     pub async fn stake_updates_by_wallet<'a>(
         &self,
         ctx: &'a Context<'_>,
-        wallet: WalletAddr,
+        wallet: Option<WalletAddr>,
     ) -> FieldResult<Vec<StakeUpdate>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn StakeService>>()?;
 
-        let stake_updates = service.stake_updates_by_wallet(&wallet.0).await?;
+        let stake_updates = service.stake_updates_by_wallet(&wallet.to_string()).await?;
         let stake_updates = stake_updates.into_iter().map(Into::into).collect();
 
         Ok(stake_updates)
@@ -71,12 +96,13 @@ impl UsersQuery {
     pub async fn transactions_by_wallet<'a>(
         &self,
         ctx: &'a Context<'_>,
-        wallet: WalletAddr,
+        wallet: Option<WalletAddr>,
         limit: usize,
         offset: usize,
     ) -> FieldResult<Vec<Transaction>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn UserTransactionService>>()?;
-        let transactions = service.by_wallet(&wallet.try_into()?, limit, offset).await?;
+        let transactions = service.by_wallet(&wallet, limit, offset).await?;
         Ok(transactions.into_iter().map(Into::into).collect())
     }
 
@@ -95,41 +121,75 @@ impl UsersQuery {
     pub async fn transactions_by_wallet_and_type<'a>(
         &self,
         ctx: &'a Context<'_>,
-        wallet: WalletAddr,
+        wallet: Option<WalletAddr>,
         transaction_type: TransactionType,
         limit: usize,
         offset: usize,
     ) -> FieldResult<Vec<Transaction>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn UserTransactionService>>()?;
         let txns = service
-            .by_wallet_and_type(&wallet.try_into()?, transaction_type.into(), limit, offset)
+            .by_wallet_and_type(&wallet, transaction_type.into(), limit, offset)
             .await?;
         Ok(txns.into_iter().map(Into::into).collect())
     }
 
-    pub async fn total_deposit_by_wallet<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<String> {
+    pub async fn total_deposit_by_wallet<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<String> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn UserTransactionService>>()?;
-        let total = service.total_deposit_by_wallet(&wallet.try_into()?).await?;
+        let total = service.total_deposit_by_wallet(&wallet).await?;
         Ok(total.to_string())
     }
 
     pub async fn prizes_by_wallet(
         &self,
         ctx: &Context<'_>,
-        wallet: WalletAddr,
+        wallet: Option<WalletAddr>,
         limit: usize,
         offset: usize,
     ) -> FieldResult<Vec<Prize>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn PrizeService>>()?;
-        let prizes = service.by_wallet(&wallet.try_into()?, limit, offset).await?;
+        let prizes = service.by_wallet(&wallet, limit, offset).await?;
         Ok(prizes.into_iter().map(Into::into).collect())
     }
 
-    pub async fn total_prize_by_wallet(&self, ctx: &Context<'_>, wallet: WalletAddr) -> FieldResult<String> {
+    pub async fn total_prize_by_wallet(&self, ctx: &Context<'_>, wallet: Option<WalletAddr>) -> FieldResult<String> {
+        let wallet = resolve_wallet(ctx, wallet)?;
         let service = ctx.data::<Box<dyn PrizeService>>()?;
-        let total = service.total_prize_by_wallet(&wallet.try_into()?).await?;
+        let total = service.total_prize_by_wallet(&wallet).await?;
         Ok(total.to_string())
     }
+
+    /// Notification preferences of the wallet, if it set any.
+    pub async fn notification_preferences(
+        &self,
+        ctx: &Context<'_>,
+        wallet: WalletAddr,
+    ) -> FieldResult<Option<NotificationPreferences>> {
+        ensure_wallet_access(ctx, &wallet)?;
+        let service = ctx.data::<Box<dyn NotificationPreferencesService>>()?;
+        let preferences = service.preferences(&wallet.try_into()?).await?;
+        Ok(preferences.map(Into::into))
+    }
+
+    /// Stake update requests flagged by screening.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn stake_update_reviews(
+        &self,
+        ctx: &Context<'_>,
+        status: StakeUpdateReviewStatus,
+        limit: usize,
+        offset: usize,
+    ) -> FieldResult<Vec<StakeUpdateReview>> {
+        let service = ctx.data::<Box<dyn StakeUpdateReviewService>>()?;
+        let reviews = service.reviews(status.into(), limit, offset).await?;
+        Ok(reviews.into_iter().map(Into::into).collect())
+    }
 }
 
 #[derive(Default)]
@@ -137,12 +197,21 @@ pub struct UserMutation;
 
 #[Object]
 impl UserMutation {
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn approve_stake_update<'a>(&self, ctx: &'a Context<'_>, wallet: WalletAddr) -> FieldResult<StakeUpdate> {
         let service = ctx.data::<Box<dyn StakeService>>()?;
-        let approved = service.approve_stake_update(&wallet.0).await?;
+        let approved = service.approve_stake_update(&wallet.0).await.map_err(|error| {
+            let flagged = matches!(error.downcast_ref(), Some(ScreeningError::Flagged { .. }));
+            error.extend_with(|_, extensions| {
+                if flagged {
+                    extensions.set("code", FLAGGED_FOR_REVIEW_CODE);
+                }
+            })
+        })?;
         Ok(approved.into())
     }
 
+    #[graphql(guard = "RoleGuard::new(SERVICE_ROLES)")]
     pub async fn complete_stake_update<'a>(
         &self,
         ctx: &'a Context<'_>,
@@ -153,12 +222,94 @@ impl UserMutation {
         Ok(approved.into())
     }
 
+    /// Approves a flagged stake update request on chain.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn approve_stake_update_review(&self, ctx: &Context<'_>, id: Uuid) -> FieldResult<StakeUpdateReview> {
+        let service = ctx.data::<Box<dyn StakeUpdateReviewService>>()?;
+        let review = service.approve(&id, &subject(ctx)?).await?;
+        Ok(review.into())
+    }
+
+    /// Cancels a flagged stake update request on chain.
+    #[graphql(guard = "RoleGuard::new(OPERATOR_ROLES)")]
+    pub async fn reject_stake_update_review(&self, ctx: &Context<'_>, id: Uuid) -> FieldResult<StakeUpdateReview> {
+        let service = ctx.data::<Box<dyn StakeUpdateReviewService>>()?;
+        let review = service.reject(&id, &subject(ctx)?).await?;
+        Ok(review.into())
+    }
+
+    /// Updates the notification preferences of the signed in wallet.
+    pub async fn update_notification_preferences(
+        &self,
+        ctx: &Context<'_>,
+        preferences: NotificationPreferencesInput,
+    ) -> FieldResult<NotificationPreferences> {
+        let wallet = resolve_wallet(ctx, None)?;
+        let service = ctx.data::<Box<dyn NotificationPreferencesService>>()?;
+        let updated = service
+            .update_preferences(
+                &wallet,
+                preferences.email,
+                preferences.webhook_url,
+                preferences.kinds.into_iter().map(Into::into).collect(),
+                preferences.rotate_webhook_secret,
+            )
+            .await?;
+        Ok(NotificationPreferences {
+            webhook_secret: updated.new_webhook_secret,
+            ..NotificationPreferences::from(updated.preferences)
+        })
+    }
+
     pub async fn mint_devnet_usdc<'a>(
         &self,
         ctx: &'a Context<'_>,
         wallet: WalletAddr,
     ) -> FieldResult<LatestMintTransaction> {
+        ensure_wallet_access(ctx, &wallet)?;
         let service = ctx.data::<Box<dyn FaucetService>>()?;
         Ok(service.mint_devnet_usdc(&wallet.try_into()?).await?.into())
     }
 }
+
+#[derive(Default)]
+pub struct UsersSubscription;
+
+#[Subscription]
+impl UsersSubscription {
+    /// State changes of the stake updates of the signed in wallet.
+    async fn stake_updates<'a>(&self, ctx: &'a Context<'_>) -> FieldResult<impl Stream<Item = StakeUpdate>> {
+        let wallet = resolve_wallet(ctx, None)?;
+        let events = ctx.data::<EventBus>()?;
+        Ok(events.subscribe().filter_map(move |event| {
+            future::ready(match event {
+                Event::StakeUpdateChanged(stake_update) if stake_update.owner == wallet => Some(stake_update.into()),
+                _ => None,
+            })
+        }))
+    }
+
+    /// Prizes the wallet won, as soon as the winners are published.
+    async fn prizes_available<'a>(
+        &self,
+        ctx: &'a Context<'_>,
+        wallet: Option<WalletAddr>,
+    ) -> FieldResult<impl Stream<Item = PrizeAvailable>> {
+        let wallet = resolve_wallet(ctx, wallet)?;
+        let events = ctx.data::<EventBus>()?;
+        Ok(events.subscribe().filter_map(move |event| {
+            future::ready(match event {
+                Event::PrizeAvailable {
+                    wallet: winner,
+                    epoch_index,
+                    tier,
+                } if winner == wallet => Some(PrizeAvailable {
+                    wallet: winner.into(),
+                    epoch_index,
+                    tier,
+                }),
+                _ => None,
+            })
+        }))
+    }
+}
@@ -1,163 +1,190 @@
-// mod common;
-
-// use crate::common::{
-//     epochs::InMemoryEpochService,
-//     stake::{InMemoryDepositRepository, InMemoryStakeRepository, InMemoryStakeService},
-//     tickets::InMemoryTicketRepository,
-// };
-// use anyhow::Result;
-// use chrono::DateTime;
-// use rand::{prelude::StdRng, SeedableRng};
-// use serde_json::{json, Value};
-// use service::{
-//     epoch::EpochManager,
-//     model::epoch::Epoch,
-//     stake::{DepositRepository, Stake, StakeRepository, StakeService},
-//     tickets::{ConstantTicketPriceCalculator, DefaultTicketService, Ticket, TicketRepository, TicketService},
-// };
-// use solana_sdk::pubkey::Pubkey;
-// use std::{error::Error, str::FromStr};
-// use test_case::test_case;
-
-// /// Tests integration of *service layer* and *api layer*.
-// ///
-// /// # Test cases
-// /// 1. Lists all epochs
-// #[test_case(r#"
-// query {
-//   epochs {
-//     index
-//     start
-//     end
-//     prize {
-//       tier
-//       amount
-//     }
-//     canCollect
-//   }
-// }
-// "#, json!(
-// {
-//   "epochs": [{
-//     "index": 0,
-//     "start": "2022-01-01T00:00:00+00:00",
-//     "end": "2022-01-07T00:00:00+00:00",
-//     "prize": [],
-//     "canCollect": false
-//   }]
-// }
-// ))]
-
-// /// 2. Gets a single epoch
-// #[test_case(r#"
-// query {
-//   epoch(index: 0) {
-//     index
-//     start
-//     end
-//     prize { tier amount winners }
-//     canCollect
-//   }
-// }
-// "#, json!(
-// {
-//   "epoch": {
-//     "index": 0,
-//     "start": "2022-01-01T00:00:00+00:00",
-//     "end": "2022-01-07T00:00:00+00:00",
-//     "prize": [],
-//     "canCollect": false
-//   }
-// }
-// ))]
-
-// /// 3. Lists tickets of wallet and epoch
-// #[test_case(r#"
-// query {
-//   ticket(wallet: "11111111111111111111111111111111", epochIndex: 0) {
-//     epochIndex
-//     arweaveUrl
-//     sequences
-//   }
-// }
-// "#, json!(
-// {
-//   "ticket": {
-//     "epochIndex": 0,
-//     "arweaveUrl": None::<String>,
-//     "sequences": [],
-//   }
-// }
-// ))]
-
-// /// 4. Lists balances of wallet
-// #[test_case(r#"
-// query {
-//   balances(wallet: "11111111111111111111111111111111") { amount currency }
-// }
-// "#, json!(
-// {
-//   "balances": [{
-//     "amount": "0",
-//     "currency": "USDC",
-//   }]
-// }
-// ))]
-// #[tokio::test]
-// async fn test_integration_of_schema_with_service_layer(
-//     query: &str,
-//     expected_response_data: Value,
-// ) -> Result<(), Box<dyn Error>> {
-//     let mut epoch_service = InMemoryEpochService::default();
-//     let epoch = Epoch {
-//         id: Default::default(),
-//         index: 0,
-//         start: DateTime::from_str("2022-01-01T00:00:00Z")?,
-//         end: DateTime::from_str("2022-01-07T00:00:00Z")?,
-//         prize: serde_json::from_str("[]")?,
-//         can_collect: false,
-//     };
-//     epoch_service.add(epoch);
-
-//     let mut ticket_repository = InMemoryTicketRepository::default();
-//     let ticket = Ticket {
-//         wallet: Pubkey::default().to_string(),
-//         epoch_index: 0,
-//         arweave_url: None,
-//         sequences: vec![],
-//     };
-//     ticket_repository.add(ticket);
-
-//     let mut stake_repository = InMemoryStakeRepository::default();
-//     let stake = Stake {
-//         is_initialized: false,
-//         owner: Default::default(),
-//         amount: 0,
-//         effective_epoch_index: 1,
-//     };
-//     stake_repository.add(stake);
-
-//     let rng = StdRng::from_entropy();
-//     let ticket_repository: Box<dyn TicketRepository> = Box::new(ticket_repository);
-//     let ticket_price_calc = ConstantTicketPriceCalculator::new(5);
-//     let ticket_service: Box<dyn TicketService> = Box::new(DefaultTicketService::new(
-//         rng,
-//         ticket_repository,
-//         Box::new(ticket_price_calc),
-//         Box::new(stake_repository.clone()),
-//     ));
-//     let stake_repository: Box<dyn StakeRepository> = Box::new(stake_repository);
-//     let deposit_repository = InMemoryDepositRepository::default();
-//     let deposit_repository: Box<dyn DepositRepository> = Box::new(deposit_repository);
-//     let stake_service: Box<dyn StakeService> =
-//         Box::new(InMemoryStakeService::new(stake_repository, deposit_repository));
-//     let epoch_service: Box<dyn EpochManager> = Box::new(epoch_service);
-//     let ctx = common::setup();
-
-//     let schema = api::load_schema(ctx, epoch_service, ticket_service, stake_service);
-
-//     let actual_response_data = schema.execute(query).await.data.into_json()?;
-
-//     assert_eq!(expected_response_data, actual_response_data);
-//     Ok(())
-// }
+//! Tests the guards of the privileged mutations and signing in.
+//!
+//! Requests that pass the guards fail when the resolver looks up its service, so an error that isn't an auth error
+//! means the request was allowed.
+
+mod common;
+
+use api::{
+    auth::{Auth, ClientAddr, Identity, RateLimiter, Role},
+    schema::{Mutation, Query, Subscription},
+};
+use async_graphql::{Request, Schema};
+use common::{error_message, identity, is_auth_error, is_rate_limited, schema, sign_in_schema, WALLET};
+use serde_json::json;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use test_case::test_case;
+
+#[test_case("mutation { publishWinners { index } }")]
+#[test_case("mutation { fundJackpot { index } }")]
+#[test_case("mutation { enterInvestment(investor: FAKE) { index } }")]
+#[test_case("mutation { exitInvestment(investor: FAKE) { index } }")]
+#[test_case(r#"mutation { approveStakeUpdate(wallet: "nzhq61BXwvKc2dSRKWH9M7danj1GUG3fVUunhkyBYJh") { amount } }"#)]
+#[test_case("mutation { generateTicketsForAll { epochIndex } }")]
+#[test_case("mutation { updateRisqIds(epochIndex: 1, risqIds: []) { epochIndex } }")]
+#[tokio::test]
+async fn test_privileged_mutations(query: &str) {
+    let anonymous = error_message(query, Identity::Anonymous).await;
+    assert_eq!(anonymous.as_deref(), Some("Unauthenticated"));
+
+    let user = error_message(query, identity(WALLET, Role::User)).await;
+    assert_eq!(user.as_deref(), Some("Forbidden"));
+
+    for role in [Role::Indexer, Role::Operator] {
+        let service = error_message(query, identity("service", role)).await;
+        assert!(!is_auth_error(&service), "{:?} was rejected: {:?}", role, service);
+    }
+}
+
+#[tokio::test]
+async fn test_user_can_only_act_on_own_wallet() {
+    let query = format!(
+        r#"mutation {{ generateTicket(wallet: "{}") {{ epochIndex }} }}"#,
+        WALLET
+    );
+
+    let anonymous = error_message(&query, Identity::Anonymous).await;
+    assert_eq!(anonymous.as_deref(), Some("Unauthenticated"));
+
+    let other_user = error_message(&query, identity(&Keypair::new().pubkey().to_string(), Role::User)).await;
+    assert_eq!(other_user.as_deref(), Some("Forbidden"));
+
+    let owner = error_message(&query, identity(WALLET, Role::User)).await;
+    assert!(!is_auth_error(&owner), "Owner was rejected: {:?}", owner);
+
+    let indexer = error_message(&query, identity("indexer_deposits", Role::Indexer)).await;
+    assert!(!is_auth_error(&indexer), "Indexer was rejected: {:?}", indexer);
+}
+
+#[tokio::test]
+async fn test_login() {
+    let schema = sign_in_schema();
+    let keypair = Keypair::new();
+    let wallet = keypair.pubkey();
+
+    let query = format!(
+        r#"mutation {{ loginChallenge(wallet: "{}") {{ message nonce }} }}"#,
+        wallet
+    );
+    let response = schema.execute(Request::new(query)).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let data = response.data.into_json().unwrap();
+    let message = data["loginChallenge"]["message"].as_str().unwrap().to_owned();
+    let nonce = data["loginChallenge"]["nonce"].as_str().unwrap().to_owned();
+    assert!(message.contains(&wallet.to_string()));
+    assert!(message.contains(&nonce));
+
+    let login = |signature: Signature| {
+        format!(
+            r#"mutation {{ login(wallet: "{}", nonce: "{}", signature: "{}") {{ token }} }}"#,
+            wallet, nonce, signature
+        )
+    };
+
+    // Wrong signer
+    let signature = Keypair::new().sign_message(message.as_bytes());
+    let response = schema.execute(Request::new(login(signature))).await;
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(response.data.into_json().unwrap(), json!(null));
+
+    let signature = keypair.sign_message(message.as_bytes());
+    let response = schema.execute(Request::new(login(signature))).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+    let data = response.data.into_json().unwrap();
+    let token = data["login"]["token"].as_str().unwrap();
+    let claims = Auth::new(b"secret", Duration::hours(1)).verify(token).unwrap();
+    assert_eq!(claims.sub, wallet.to_string());
+    assert_eq!(claims.role, Role::User);
+
+    // Nonces can't be replayed
+    let response = schema.execute(Request::new(login(signature))).await;
+    assert_eq!(response.errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_login_challenge_is_rate_limited() {
+    let schema = sign_in_schema();
+    let query = format!(r#"mutation {{ loginChallenge(wallet: "{}") {{ nonce }} }}"#, WALLET);
+    let execute = |client: &str| schema.execute(Request::new(&query).data(ClientAddr(client.to_owned())));
+
+    for _ in 0..2 {
+        let response = execute("127.0.0.1").await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+    let response = execute("127.0.0.1").await;
+    assert!(is_rate_limited(&response), "{:?}", response.errors);
+
+    let response = execute("127.0.0.2").await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+}
+
+#[tokio::test]
+async fn test_me() {
+    let response = schema()
+        .execute(Request::new("{ me { wallet } }").data(Identity::Anonymous))
+        .await;
+    assert_eq!(response.errors.len(), 1);
+
+    let response = schema()
+        .execute(Request::new("{ me { wallet } }").data(identity(WALLET, Role::User)))
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({ "me": { "wallet": WALLET } })
+    );
+}
+
+#[tokio::test]
+async fn test_wallet_of_user_scoped_queries() {
+    let schema = Schema::build(Query::default(), Mutation::default(), Subscription::default())
+        .data(RateLimiter::new(2, std::time::Duration::from_secs(60)))
+        .finish();
+    let execute = |query: &str, identity: Identity| {
+        schema.execute(
+            Request::new(query)
+                .data(identity)
+                .data(ClientAddr("127.0.0.1".to_owned())),
+        )
+    };
+
+    let response = execute("{ drawsPlayedByWallet }", Identity::Anonymous).await;
+    assert_eq!(
+        response.errors.first().map(|e| e.message.as_str()),
+        Some("Wallet is required when not signed in")
+    );
+
+    // Own wallet isn't rate limited. The request fails later on the missing ticket service.
+    let query = format!(r#"{{ drawsPlayedByWallet(wallet: "{}") }}"#, WALLET);
+    for _ in 0..3 {
+        let response = execute("{ drawsPlayedByWallet }", identity(WALLET, Role::User)).await;
+        assert!(!is_rate_limited(&response), "{:?}", response.errors);
+        let response = execute(&query, identity(WALLET, Role::User)).await;
+        assert!(!is_rate_limited(&response), "{:?}", response.errors);
+    }
+
+    // Other wallets are
+    for _ in 0..2 {
+        let response = execute(&query, Identity::Anonymous).await;
+        assert!(!is_rate_limited(&response), "{:?}", response.errors);
+    }
+    let response = execute(&query, Identity::Anonymous).await;
+    assert!(is_rate_limited(&response), "{:?}", response.errors);
+}
+
+#[tokio::test]
+async fn test_update_notification_preferences_of_signed_in_wallet() {
+    let query = r#"mutation { updateNotificationPreferences(preferences: { kinds: [PRIZE_WON] }) { wallet } }"#;
+
+    let anonymous = error_message(query, Identity::Anonymous).await;
+    assert_eq!(anonymous.as_deref(), Some("Wallet is required when not signed in"));
+
+    let service = error_message(query, identity("indexer_transactions", Role::Indexer)).await;
+    assert_eq!(service.as_deref(), Some("Wallet is required when not signed in"));
+
+    // Fails later on the missing preferences service
+    let user = error_message(query, identity(WALLET, Role::User)).await;
+    assert!(!is_auth_error(&user), "User was rejected: {:?}", user);
+    assert_ne!(user.as_deref(), Some("Wallet is required when not signed in"));
+}
@@ -2,36 +2,57 @@
 //!
 //! Common mocks, stubs, fakes and dummy data belong here.
 //!
-use std::{fs, str::FromStr, sync::Arc};
+use api::{
+    auth::{Auth, Claims, Identity, RateLimiter, Role},
+    schema::{Mutation, NezhaSchema, Query, Subscription},
+};
+use async_graphql::{Request, Schema};
+use chrono::{Duration, Utc};
+use service::sign_in::{DefaultSignInService, InMemoryLoginChallengeRepository, SignInService};
 
-use service::stake::SolanaProgramContext;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
+pub const WALLET: &str = "nzhq61BXwvKc2dSRKWH9M7danj1GUG3fVUunhkyBYJh";
 
-pub mod epochs;
-pub mod stake;
-pub mod tickets;
+/// Schema without any services.
+pub fn schema() -> NezhaSchema {
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
+        .data(Auth::new(b"secret", Duration::hours(1)))
+        .finish()
+}
+
+/// Schema signing in against an in memory store of login challenges, allowing 2 challenges per client and minute.
+pub fn sign_in_schema() -> NezhaSchema {
+    let sign_in_service: Box<dyn SignInService> = Box::new(DefaultSignInService::new(
+        Box::new(InMemoryLoginChallengeRepository::default()),
+        "nezha".to_owned(),
+        Duration::minutes(5),
+    ));
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
+        .data(Auth::new(b"secret", Duration::hours(1)))
+        .data(sign_in_service)
+        .data(RateLimiter::new(2, std::time::Duration::from_secs(60)))
+        .finish()
+}
 
-const SOLANA_DEVNET_URL: &str = "https://api.devnet.solana.com";
-const STAKE_PROGRAM_ID: &str = "545Nd9jw9DPiYZRQgzKqfKSEUb1djMCEbrPeSTG7mCFE";
-const USDC_MINT: &str = "7tWUTDppUCLm482XrHqZK5mqChepjVdWw6xAkGXRBLeC";
-const SPL_TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
-const ATA_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub fn identity(sub: &str, role: Role) -> Identity {
+    Identity::Authenticated(Claims {
+        sub: sub.to_owned(),
+        role,
+        exp: (Utc::now() + Duration::hours(1)).timestamp(),
+    })
+}
 
-pub fn setup() -> Arc<SolanaProgramContext> {
-    let keypair_path = fs::canonicalize("./tests/keys/nzhq61BXwvKc2dSRKWH9M7danj1GUG3fVUunhkyBYJh.json")
-        .expect("unable to find admin key path");
+pub async fn error_message(query: &str, identity: Identity) -> Option<String> {
+    let response = schema().execute(Request::new(query).data(identity)).await;
+    response.errors.first().map(|e| e.message.clone())
+}
 
-    let rpc_client = RpcClient::new(SOLANA_DEVNET_URL.into());
-    let stake_program_id = Pubkey::from_str(STAKE_PROGRAM_ID).expect("unable to parse stake program id");
-    let admin_keypair = read_keypair_file(keypair_path).expect("failed to read keypair file");
+pub fn is_auth_error(message: &Option<String>) -> bool {
+    matches!(message.as_deref(), Some("Unauthenticated") | Some("Forbidden"))
+}
 
-    Arc::new(SolanaProgramContext::new(
-        Arc::new(rpc_client),
-        stake_program_id,
-        Pubkey::from_str(SPL_TOKEN_PROGRAM_ID).unwrap(),
-        Pubkey::from_str(ATA_PROGRAM_ID).unwrap(),
-        Pubkey::from_str(USDC_MINT).unwrap(),
-        Arc::new(admin_keypair),
-    ))
+pub fn is_rate_limited(response: &async_graphql::Response) -> bool {
+    response
+        .errors
+        .first()
+        .map_or(false, |e| e.message.starts_with("Rate limit exceeded"))
 }
@@ -0,0 +1,89 @@
+use api::{
+    auth::{Claims, Identity, Role},
+    schema::{Mutation, NezhaSchema, Query, Subscription},
+};
+use async_graphql::{Request, Schema};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use serde_json::json;
+use service::events::{Event, EventBus};
+use solana_sdk::pubkey::Pubkey;
+
+fn schema(events: EventBus) -> NezhaSchema {
+    Schema::build(Query::default(), Mutation::default(), Subscription::default())
+        .data(events)
+        .finish()
+}
+
+#[tokio::test]
+async fn test_winning_combination() {
+    let events = EventBus::default();
+    let schema = schema(events.clone());
+    let mut stream = schema.execute_stream("subscription { winningCombination { epochIndex winningCombination } }");
+
+    // Let the subscription start before publishing
+    let next = tokio::spawn(async move { stream.next().await });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    events.publish(Event::WinningCombinationPublished {
+        epoch_index: 3,
+        winning_combination: [1, 2, 3, 4, 5, 6],
+    });
+
+    let response = next.await.unwrap().unwrap();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({ "winningCombination": { "epochIndex": 3, "winningCombination": [1, 2, 3, 4, 5, 6] } })
+    );
+}
+
+#[tokio::test]
+async fn test_prizes_available_for_signed_in_wallet() {
+    let events = EventBus::default();
+    let schema = schema(events.clone());
+    let wallet = Pubkey::new_unique();
+    let identity = Identity::Authenticated(Claims {
+        sub: wallet.to_string(),
+        role: Role::User,
+        exp: (Utc::now() + Duration::hours(1)).timestamp(),
+    });
+    let mut stream = schema
+        .execute_stream(Request::new("subscription { prizesAvailable { wallet epochIndex tier } }").data(identity));
+
+    let next = tokio::spawn(async move { stream.next().await });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    // Other wallets are filtered out
+    events.publish(Event::PrizeAvailable {
+        wallet: Pubkey::new_unique(),
+        epoch_index: 3,
+        tier: 1,
+    });
+    events.publish(Event::PrizeAvailable {
+        wallet,
+        epoch_index: 3,
+        tier: 2,
+    });
+
+    let response = next.await.unwrap().unwrap();
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap(),
+        json!({ "prizesAvailable": { "wallet": wallet.to_string(), "epochIndex": 3, "tier": 2 } })
+    );
+}
+
+#[tokio::test]
+async fn test_wallet_subscription_requires_wallet_when_anonymous() {
+    let schema = schema(EventBus::default());
+    let mut stream = schema.execute_stream(Request::new("subscription { ticketGenerated { epochIndex } }"));
+    let response = stream.next().await.unwrap();
+    assert_eq!(response.errors[0].message, "Wallet is required when not signed in");
+}
+
+#[tokio::test]
+async fn test_stake_updates_require_sign_in() {
+    let schema = schema(EventBus::default());
+    let mut stream = schema.execute_stream(Request::new("subscription { stakeUpdates { amount } }"));
+    let response = stream.next().await.unwrap();
+    assert_eq!(response.errors[0].message, "Wallet is required when not signed in");
+}
@@ -0,0 +1,150 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures::StreamExt;
+use service::{account_mirror::AccountMirror, model::program_account::MirroredAccountType, solana::rpc::SolanaRpc};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+/// Keeps the account mirror up to date with the staking program accounts.
+pub struct AccountIndexer {
+    rpc_client: Arc<RpcClient>,
+    solana_rpc: Arc<dyn SolanaRpc>,
+    rpc_ws_url: String,
+    program_id: Pubkey,
+    mirror: AccountMirror,
+    check_interval: Duration,
+    heartbeat_interval: Duration,
+    retry_delay: Duration,
+}
+
+impl AccountIndexer {
+    pub fn new(
+        rpc_client: Arc<RpcClient>,
+        solana_rpc: Arc<dyn SolanaRpc>,
+        rpc_ws_url: String,
+        program_id: Pubkey,
+        mirror: AccountMirror,
+        check_interval: Duration,
+        heartbeat_interval: Duration,
+        retry_delay: Duration,
+    ) -> Self {
+        Self {
+            rpc_client,
+            solana_rpc,
+            rpc_ws_url,
+            program_id,
+            mirror,
+            check_interval,
+            heartbeat_interval,
+            retry_delay,
+        }
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        tokio::join!(self.subscribe_loop(), self.check_loop());
+        Ok(())
+    }
+
+    async fn subscribe_loop(&self) {
+        loop {
+            if let Err(e) = self.subscribe().await {
+                log::error!("Account subscription error: {}", e);
+            }
+            tokio::time::sleep(self.retry_delay).await;
+        }
+    }
+
+    async fn check_loop(&self) {
+        loop {
+            tokio::time::sleep(self.check_interval).await;
+            if let Err(e) = self.check().await {
+                log::error!("Error while checking the account mirror: {}", e);
+            }
+        }
+    }
+
+    /// Applies the account updates pushed over the websocket until the connection drops. Records a heartbeat every
+    /// `heartbeat_interval` meanwhile, the mirror isn't served once they stop.
+    async fn subscribe(&self) -> Result<()> {
+        let pubsub_client = PubsubClient::new(&self.rpc_ws_url)
+            .await
+            .with_context(|| format!("Failed to connect to RPC Websocket {}", self.rpc_ws_url))?;
+        let config = RpcProgramAccountsConfig {
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+            with_context: Some(true),
+            ..Default::default()
+        };
+        let (mut stream, _unsubscribe) = pubsub_client
+            .program_subscribe(&self.program_id, Some(config))
+            .await
+            .context("Failed to subscribe to program accounts")?;
+        log::info!("Subscribed to the accounts of {}", self.program_id);
+
+        // Catches up on the updates missed while disconnected
+        self.check().await?;
+
+        let mut heartbeat = tokio::time::interval(self.heartbeat_interval);
+        loop {
+            let update = tokio::select! {
+                update = stream.next() => match update {
+                    Some(update) => update,
+                    None => break,
+                },
+                _ = heartbeat.tick() => {
+                    self.mirror.subscribed(Utc::now()).await?;
+                    continue;
+                }
+            };
+            let slot = update.context.slot;
+            let pubkey = Pubkey::from_str(&update.value.pubkey)?;
+            let account: Account = match update.value.account.decode() {
+                Some(account) => account,
+                None => {
+                    log::error!("Failed to decode account {}", pubkey);
+                    continue;
+                }
+            };
+            self.mirror
+                .apply(&pubkey, &account, slot)
+                .await
+                .with_context(|| format!("Failed to mirror account {}", pubkey))?;
+        }
+        log::warn!("Account subscription ended");
+        Ok(())
+    }
+
+    /// Reconciles the mirror with a full scan of the program accounts.
+    pub async fn check(&self) -> Result<()> {
+        let mut out_of_sync = 0;
+        let mut slot = 0;
+        for account_type in MirroredAccountType::ALL {
+            let accounts = self
+                .solana_rpc
+                .get_program_accounts_by_type(&self.program_id, account_type.into())
+                .await?;
+            // Taken after scanning, so that updates older than the scanned accounts don't replace them
+            slot = self
+                .rpc_client
+                .get_slot_with_commitment(CommitmentConfig::confirmed())
+                .await?;
+            out_of_sync += self.mirror.reconcile(account_type, &accounts, slot).await?;
+        }
+        self.mirror.checked(Utc::now()).await?;
+        log::info!(
+            "Checked the account mirror at slot {}, {} account(s) were out of sync",
+            slot,
+            out_of_sync
+        );
+        Ok(())
+    }
+}
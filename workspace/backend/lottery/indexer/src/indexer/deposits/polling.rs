@@ -8,7 +8,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 
-use crate::nezha_api::{EpochStatus, NezhaAPI, StakeUpdateRequestState};
+use crate::nezha_api::{EpochStatus, FlaggedForReview, NezhaAPI, StakeUpdateRequestState};
 
 pub struct AcceptDeposits {
     pub nezha_api: Arc<dyn NezhaAPI + Send + Sync>,
@@ -47,7 +47,13 @@ impl AcceptDeposits {
                     if deposit.state == StakeUpdateRequestState::PendingApproval {
                         let res = nezha_api.approve_stake_update(&deposit.owner).await;
                         if let Err(e) = res {
-                            log::error!("Failed to approve deposits of {}: {}", deposit.owner, e);
+                            // Flagged requests wait for an operator in the review queue, and are polled until then
+                            if e.is::<FlaggedForReview>() {
+                                log::debug!("{}", e);
+                            } else {
+                                log::error!("Failed to approve deposits of {}: {}", deposit.owner, e);
+                            }
+                            return;
                         } else {
                             log::info!("Approved deposit for: {}", deposit.owner);
                         }
@@ -8,8 +8,18 @@ use nezha_staking::{
     fixed_point::{self, test_utils::fp},
     instruction,
 };
-use rand::Rng;
-use service::solana::SwitchboardConfiguration;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use service::{
+    epoch::EpochJobRepository,
+    model::{
+        epoch_job::{EpochJobCommand, EpochJobStatus},
+        scenario::{Scenario, ScenarioEpoch},
+    },
+    scenario::resolve_draw,
+    solana::SwitchboardConfiguration,
+    tickets::TicketRepository,
+};
 use solana_sdk::signer::Signer;
 use spl_associated_token_account::get_associated_token_address;
 use thiserror::Error;
@@ -37,6 +47,23 @@ pub enum EpochCommand {
     FundJackpot,
 }
 
+impl From<EpochCommand> for EpochJobCommand {
+    fn from(command: EpochCommand) -> Self {
+        match command {
+            EpochCommand::CreateEpoch => EpochJobCommand::CreateEpoch,
+            EpochCommand::EnterInvestment => EpochJobCommand::EnterInvestment,
+            EpochCommand::ExitInvestment => EpochJobCommand::ExitInvestment,
+            EpochCommand::PublishWinningCombination => EpochJobCommand::PublishWinningCombination,
+            EpochCommand::PublishWinners => EpochJobCommand::PublishWinners,
+            EpochCommand::FundJackpot => EpochJobCommand::FundJackpot,
+        }
+    }
+}
+
+/// Key of the advisory lock held by the epoch indexer that runs the jobs.
+/// Other replicas wait until it's released.
+pub const EPOCH_INDEXER_LOCK_KEY: i64 = 0x6e657a6861;
+
 pub trait NextCommand {
     fn next_command(&self) -> EpochCommand;
 }
@@ -64,6 +91,15 @@ impl NextCommand for Option<Epoch> {
     }
 }
 
+/// Epoch index a command applies to. Creating an epoch applies to the index of the new epoch.
+pub fn command_epoch_index(latest_epoch: &Option<Epoch>, command: EpochCommand) -> u64 {
+    match (latest_epoch, command) {
+        (None, _) => 1,
+        (Some(epoch), EpochCommand::CreateEpoch) => epoch.index + 1,
+        (Some(epoch), _) => epoch.index,
+    }
+}
+
 pub fn jackpot_funded(epoch_winners: &Option<EpochWinners>) -> bool {
     match &epoch_winners {
         Some(winners) => winners.jackpot_claimable || winners.tier1_meta.total_num_winners == 0,
@@ -73,6 +109,7 @@ pub fn jackpot_funded(epoch_winners: &Option<EpochWinners>) -> bool {
 
 #[derive(Debug)]
 pub struct EpochJob {
+    pub epoch_index: u64,
     pub command: EpochCommand,
     pub due_time: DateTime<Utc>,
 }
@@ -83,6 +120,9 @@ pub struct EpochJobSchedulerConfig {
     pub exit_investment_offset_seconds: i64,
     pub publish_winning_combination_offset_seconds: i64,
     pub publish_winners_offset_seconds: i64,
+    pub retry_base_delay_seconds: i64,
+    pub retry_max_delay_seconds: i64,
+    pub max_attempts: u32,
 }
 
 pub struct EpochJobScheduler {
@@ -98,6 +138,12 @@ pub struct EpochJobScheduler {
     pub publish_winners_offset: Duration,
     /// the duration offset from epoch start to funding the prizes
     pub fund_prizes_offset: Duration,
+    /// the delay before retrying a failed job, doubled on every failed attempt
+    pub retry_base_delay: Duration,
+    /// the longest delay before retrying a failed job
+    pub retry_max_delay: Duration,
+    /// the attempts after which a failing job is given up
+    pub max_attempts: u32,
 }
 
 impl EpochJobScheduler {
@@ -128,6 +174,12 @@ impl EpochJobScheduler {
 
         Ok(due_time)
     }
+
+    /// Delay before the next attempt of a job that failed `attempts` times.
+    pub fn retry_delay(&self, attempts: u32) -> Duration {
+        let factor = 2i32.pow(attempts.saturating_sub(1).min(30));
+        (self.retry_base_delay * factor).min(self.retry_max_delay)
+    }
 }
 
 impl TryFrom<EpochJobSchedulerConfig> for EpochJobScheduler {
@@ -153,6 +205,9 @@ impl TryFrom<EpochJobSchedulerConfig> for EpochJobScheduler {
             publish_winning_combination_offset,
             publish_winners_offset,
             fund_prizes_offset,
+            retry_base_delay: Duration::seconds(config.retry_base_delay_seconds),
+            retry_max_delay: Duration::seconds(config.retry_max_delay_seconds),
+            max_attempts: config.max_attempts.max(1),
         })
     }
 }
@@ -184,15 +239,19 @@ pub enum EpochIndexerError {
     Overflow,
 }
 
-#[derive(Debug)]
 pub enum WinningCombinationSource {
-    GuaranteedJackpot,
-    Optimal,
     Random,
+    /// Yields and draws of the epochs played from a scenario, so that QA can reproduce jackpots, draws without
+    /// winners and losses. Epochs the scenario doesn't cover are drawn randomly.
+    Scenario {
+        scenario: Scenario,
+        tickets: Box<dyn TicketRepository>,
+    },
 }
 
 pub struct EpochIndexer<T: Rng> {
     pub scheduler: EpochJobScheduler,
+    pub jobs: Box<dyn EpochJobRepository>,
     pub nezha_api: Box<dyn NezhaAPI + Send + Sync>,
     pub context: Arc<SolanaProgramContext>,
     pub prizes: TieredPrizes,
@@ -208,6 +267,7 @@ pub struct EpochIndexer<T: Rng> {
 impl<T: Rng> EpochIndexer<T> {
     pub fn new(
         scheduler: EpochJobScheduler,
+        jobs: Box<dyn EpochJobRepository>,
         nezha_api: Box<dyn NezhaAPI + Send + Sync>,
         context: Arc<SolanaProgramContext>,
         prizes: TieredPrizes,
@@ -221,6 +281,7 @@ impl<T: Rng> EpochIndexer<T> {
     ) -> Self {
         Self {
             scheduler,
+            jobs,
             nezha_api,
             context,
             prizes,
@@ -234,26 +295,83 @@ impl<T: Rng> EpochIndexer<T> {
         }
     }
 
+    /// Runs the jobs as they come due. Errors, of the api or of the job repository, are logged and the next job is
+    /// looked up again after `retry_base_delay`.
     pub async fn run_loop(&mut self) -> Result<()> {
         loop {
-            let job = self.next_job().await?;
-            self.wait_for_job(&job).await?;
-            if let Err(e) = self.run_job(&job).await {
-                log::error!("Error running job ({:?}): {}", job, e);
+            if let Err(e) = self.run_next_job().await {
+                log::error!("Failed to run the next epoch job: {}", e);
+                tokio::time::sleep(self.scheduler.retry_base_delay.to_std()?).await;
+            }
+        }
+    }
+
+    async fn run_next_job(&mut self) -> Result<()> {
+        match self.next_job().await? {
+            Some(job) => {
+                self.wait_for_job(&job).await?;
+                self.process_job(&job).await
+            }
+            // Checked again later, in case an operator ran the step
+            None => {
+                tokio::time::sleep(self.scheduler.retry_max_delay.to_std()?).await;
+                Ok(())
             }
         }
     }
 
-    pub async fn next_job(&self) -> Result<EpochJob> {
+    /// Persists the next job, or reads it back with its retry time if it has been attempted before.
+    /// Returns `None` if the job was given up.
+    pub async fn next_job(&self) -> Result<Option<EpochJob>> {
         let latest_epoch = self.nezha_api.get_latest_epoch().await?;
         match &latest_epoch {
             None => log::info!("No epochs yet"),
             Some(epoch) => log::info!("Latest epoch: {epoch:?}"),
         }
         let command = latest_epoch.next_command();
-        log::info!("Next command: {command:?}");
+        let epoch_index = command_epoch_index(&latest_epoch, command);
+        log::info!("Next command: {command:?} for epoch {epoch_index}");
         let due_time = self.scheduler.due_time(&command)?;
-        Ok(EpochJob { command, due_time })
+        let stored = self.jobs.enqueue(epoch_index, command.into(), due_time).await?;
+        if stored.status == EpochJobStatus::Failed {
+            log::error!(
+                "Gave up on {command:?} for epoch {epoch_index} after {} attempts, waiting for an operator: {}",
+                stored.attempts,
+                stored.last_error.unwrap_or_default()
+            );
+            return Ok(None);
+        }
+        Ok(Some(EpochJob {
+            epoch_index,
+            command,
+            due_time: due_time.max(stored.next_attempt_at),
+        }))
+    }
+
+    /// Runs the job and records the outcome. Failed jobs are retried with exponential backoff, until
+    /// `max_attempts` were made.
+    pub async fn process_job(&mut self, job: &EpochJob) -> Result<()> {
+        let command = job.command.into();
+        let started = self.jobs.start(job.epoch_index, command).await?;
+        let recorded = match self.run_job(job).await {
+            Ok(()) => self.jobs.succeed(job.epoch_index, command).await,
+            Err(e) if started.attempts >= self.scheduler.max_attempts => {
+                log::error!("Gave up on job ({:?}) after {} attempts: {}", job, started.attempts, e);
+                self.jobs.fail(job.epoch_index, command, &e.to_string(), None).await
+            }
+            Err(e) => {
+                log::error!("Error running job ({:?}), attempt {}: {}", job, started.attempts, e);
+                let next_attempt_at = Utc::now() + self.scheduler.retry_delay(started.attempts);
+                self.jobs
+                    .fail(job.epoch_index, command, &e.to_string(), Some(next_attempt_at))
+                    .await
+            }
+        };
+        // The job is looked up again from the latest epoch, so the outcome not being recorded only costs an attempt
+        if let Err(e) = recorded {
+            log::error!("Failed to record the outcome of job ({:?}): {}", job, e);
+        }
+        Ok(())
     }
 
     pub async fn wait_for_job(&self, job: &EpochJob) -> Result<()> {
@@ -267,7 +385,16 @@ impl<T: Rng> EpochIndexer<T> {
         Ok(())
     }
 
+    /// Runs the command of the job, unless the latest epoch shows it has already been done.
+    /// Jobs interrupted by a restart are run again, so every step has to be safe to repeat.
     pub async fn run_job(&mut self, job: &EpochJob) -> Result<()> {
+        let latest_epoch = self.nezha_api.get_latest_epoch().await?;
+        let command = latest_epoch.next_command();
+        if command != job.command || command_epoch_index(&latest_epoch, command) != job.epoch_index {
+            log::info!("Skipping job ({:?}), the epoch has moved on", job);
+            return Ok(());
+        }
+
         match job.command {
             EpochCommand::CreateEpoch => {
                 // create epoch
@@ -341,14 +468,17 @@ impl<T: Rng> EpochIndexer<T> {
 
     pub async fn exit_investment(&self) -> Result<()> {
         if let Investor::Fake = self.investor {
-            let mut rng = rand::thread_rng();
-            let yield_percent = rng.gen_range(self.yield_range.clone());
-
-            let total_invested = self
+            let latest_epoch = self
                 .nezha_api
                 .get_latest_epoch()
                 .await?
-                .ok_or_else(|| EpochIndexerError::CouldNotReadLatestEpoch)?
+                .ok_or_else(|| EpochIndexerError::CouldNotReadLatestEpoch)?;
+            let yield_percent = match self.scenario_epoch(latest_epoch.index) {
+                Some(epoch) => epoch.yield_percent,
+                None => rand::thread_rng().gen_range(self.yield_range.clone()),
+            };
+
+            let total_invested = latest_epoch
                 .total_value_locked
                 .ok_or_else(|| EpochIndexerError::TotalValueLockedNotSet)?;
 
@@ -373,18 +503,32 @@ impl<T: Rng> EpochIndexer<T> {
     pub async fn publish_winning_combination(&mut self) -> Result<()> {
         match self.switchboard_cfg {
             SwitchboardConfiguration::Fake => {
-                let winning_combination = match self.winning_combination_source {
-                    // These two need to read from the GraphQL API because they need to be calculated
-                    WinningCombinationSource::GuaranteedJackpot => self.nezha_api.random_winning_combination().await?,
-                    WinningCombinationSource::Optimal => self.nezha_api.calculate_optimal_winning_combination().await?,
-
-                    // This one can be generated from within the indexer
-                    WinningCombinationSource::Random => Some(self.sequence_generator.generate_sequence()),
+                let winning_combination = match &self.winning_combination_source {
+                    WinningCombinationSource::Scenario { scenario, tickets } => {
+                        let epoch_index = self
+                            .nezha_api
+                            .get_latest_epoch()
+                            .await?
+                            .ok_or_else(|| EpochIndexerError::CouldNotReadLatestEpoch)?
+                            .index;
+                        match scenario.epoch(epoch_index) {
+                            Some(epoch) => {
+                                // Seeded by epoch, so that a draw is the same when it's retried
+                                let mut rng = ChaCha20Rng::seed_from_u64(scenario.seed);
+                                rng.set_stream(epoch_index);
+                                let tickets = tickets.by_epoch_index(epoch_index).await?;
+                                Some(resolve_draw(&mut rng, &epoch.draw, epoch_index, &tickets)?)
+                            }
+                            None => None,
+                        }
+                    }
+                    WinningCombinationSource::Random => None,
                 };
                 let winning_combination = match winning_combination {
                     Some(winning_combination) => winning_combination,
                     None => self.sequence_generator.generate_sequence(),
                 };
+                info!("Publishing winning combination {:?}", winning_combination);
                 self.nezha_api.publish_winning_combination(winning_combination).await?;
             }
             SwitchboardConfiguration::Devnet | SwitchboardConfiguration::Mainnet => {
@@ -396,6 +540,13 @@ impl<T: Rng> EpochIndexer<T> {
         Ok(())
     }
 
+    fn scenario_epoch(&self, epoch_index: u64) -> Option<&ScenarioEpoch> {
+        match &self.winning_combination_source {
+            WinningCombinationSource::Scenario { scenario, .. } => scenario.epoch(epoch_index),
+            WinningCombinationSource::Random => None,
+        }
+    }
+
     pub async fn artkai_finish_epoch(&self) -> Result<()> {
         let latest_epoch = self
             .nezha_api
@@ -461,7 +612,13 @@ impl<T: Rng> EpochIndexer<T> {
 #[cfg(test)]
 mod tests {
     use async_trait::async_trait;
-    use rand::thread_rng;
+    use nezha_staking::fixed_point::test_utils::usdc;
+    use rand::{rngs::ThreadRng, thread_rng};
+    use service::{
+        epoch::InMemoryEpochJobRepository,
+        model::scenario::ScenarioWallet,
+        tickets::{validate_sequence, InMemoryTicketRepository, Sequence, SequenceType, Ticket},
+    };
     use solana_client::nonblocking::rpc_client::RpcClient;
     use solana_sdk::{pubkey::Pubkey, signature::Keypair};
 
@@ -487,6 +644,9 @@ mod tests {
             exit_investment_offset_seconds: 2,
             publish_winning_combination_offset_seconds: 3,
             publish_winners_offset_seconds: 4,
+            retry_base_delay_seconds: 1,
+            retry_max_delay_seconds: 1,
+            max_attempts: 3,
         };
 
         let scheduler = EpochJobScheduler::try_from(config)?;
@@ -529,6 +689,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_retry_delay() -> Result<()> {
+        let config = EpochJobSchedulerConfig {
+            start_schedule_string: "0 0 * * * *".into(),
+            enter_investment_offset_seconds: 1,
+            exit_investment_offset_seconds: 2,
+            publish_winning_combination_offset_seconds: 3,
+            publish_winners_offset_seconds: 4,
+            retry_base_delay_seconds: 30,
+            retry_max_delay_seconds: 300,
+            max_attempts: 3,
+        };
+        let scheduler = EpochJobScheduler::try_from(config)?;
+
+        assert_eq!(scheduler.retry_delay(1), Duration::seconds(30));
+        assert_eq!(scheduler.retry_delay(2), Duration::seconds(60));
+        assert_eq!(scheduler.retry_delay(4), Duration::seconds(240));
+        assert_eq!(scheduler.retry_delay(5), Duration::seconds(300));
+        assert_eq!(scheduler.retry_delay(u32::MAX), Duration::seconds(300));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_wait_for_next_job() -> Result<()> {
         let epoch_length_seconds = 6;
@@ -545,6 +727,9 @@ mod tests {
             publish_winning_combination_offset: publish_winning_combination_offset.clone(),
             publish_winners_offset: publish_winners_offset.clone(),
             fund_prizes_offset: fund_prizes_offset.clone(),
+            retry_base_delay: Duration::seconds(1),
+            retry_max_delay: Duration::seconds(1),
+            max_attempts: 3,
         };
 
         let nezha_api = Box::new(NezhaApiImpl::new());
@@ -575,6 +760,7 @@ mod tests {
         let sequence_generator = SequenceGenerator::new(thread_rng());
         let indexer = EpochIndexer::new(
             scheduler,
+            Box::new(InMemoryEpochJobRepository::default()),
             nezha_api,
             context,
             prizes,
@@ -590,26 +776,32 @@ mod tests {
         let start_time = start_schedule.upcoming(Utc).next().unwrap();
         let jobs = vec![
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::CreateEpoch,
                 due_time: start_time,
             },
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::EnterInvestment,
                 due_time: start_time + enter_investment_offset,
             },
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::ExitInvestment,
                 due_time: start_time + exit_investment_offset,
             },
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::PublishWinningCombination,
                 due_time: start_time + publish_winning_combination_offset,
             },
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::PublishWinners,
                 due_time: start_time + publish_winners_offset,
             },
             EpochJob {
+                epoch_index: 1,
                 command: EpochCommand::FundJackpot,
                 due_time: start_time + fund_prizes_offset,
             },
@@ -628,4 +820,171 @@ mod tests {
         }
         Ok(())
     }
+
+    fn scenario_indexer(
+        nezha_api: NezhaApiImpl,
+        scenario: Scenario,
+        tickets: InMemoryTicketRepository,
+    ) -> Result<EpochIndexer<ThreadRng>> {
+        test_indexer(
+            nezha_api,
+            WinningCombinationSource::Scenario {
+                scenario,
+                tickets: Box::new(tickets),
+            },
+        )
+    }
+
+    fn test_indexer(
+        nezha_api: NezhaApiImpl,
+        winning_combination_source: WinningCombinationSource,
+    ) -> Result<EpochIndexer<ThreadRng>> {
+        let scheduler = EpochJobScheduler::try_from(EpochJobSchedulerConfig {
+            start_schedule_string: "0 0 * * * *".into(),
+            enter_investment_offset_seconds: 1,
+            exit_investment_offset_seconds: 2,
+            publish_winning_combination_offset_seconds: 3,
+            publish_winners_offset_seconds: 4,
+            retry_base_delay_seconds: 1,
+            retry_max_delay_seconds: 1,
+            max_attempts: 3,
+        })?;
+        let context = Arc::new(SolanaProgramContext::new(
+            Arc::new(RpcClient::new("".to_string())),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Arc::new(Keypair::new()),
+            Arc::new(Keypair::new()),
+        ));
+        Ok(EpochIndexer::new(
+            scheduler,
+            Box::new(InMemoryEpochJobRepository::default()),
+            Box::new(nezha_api),
+            context,
+            TieredPrizes {
+                tier1: "1000".into(),
+                tier2_yield_share: 7,
+                tier3_yield_share: 3,
+            },
+            YieldSplitCfg {
+                insurance_premium: "10".into(),
+                insurance_jackpot: "1000".into(),
+                insurance_probability: "0.1".into(),
+                treasury_ratio: "0.5".into(),
+            },
+            0.0..5.0,
+            Box::new(MockArtkaiClient),
+            SequenceGenerator::new(thread_rng()),
+            winning_combination_source,
+            Investor::Fake,
+            SwitchboardConfiguration::Fake,
+        ))
+    }
+
+    fn set_latest_epoch(nezha_api: &NezhaApiImpl, index: u64, status: EpochStatus) {
+        *nezha_api.latest_epoch.lock().unwrap() = Some(Epoch {
+            index,
+            pubkey: Pubkey::new_unique(),
+            prizes: TieredPrizes {
+                tier1: "1000".into(),
+                tier2_yield_share: 7,
+                tier3_yield_share: 3,
+            },
+            status,
+            total_value_locked: Some(usdc("100")),
+            winning_combination: None,
+            winners: None,
+            expected_end_date: Utc::now(),
+            draw_enabled: DrawEnabled::Waiting,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_give_up_failing_job() -> Result<()> {
+        let nezha_api = NezhaApiImpl::new();
+        let mut indexer = test_indexer(nezha_api.clone(), WinningCombinationSource::Random)?;
+
+        // The jackpot can't be funded without the winners
+        set_latest_epoch(&nezha_api, 3, EpochStatus::Ended);
+        for attempt in 1..=indexer.scheduler.max_attempts {
+            let job = indexer.next_job().await?.expect("the job is retried");
+            assert_eq!(job.command, EpochCommand::FundJackpot);
+            indexer.process_job(&job).await?;
+
+            let stored = indexer.jobs.recent(1, 0).await?.remove(0);
+            assert_eq!(stored.attempts, attempt);
+            assert!(stored.last_error.is_some());
+        }
+
+        let stored = indexer.jobs.recent(1, 0).await?.remove(0);
+        assert_eq!(stored.status, EpochJobStatus::Failed);
+        assert!(indexer.next_job().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scenario_yields_and_draws() -> Result<()> {
+        let scenario = Scenario::from_json(
+            r#"{
+                "seed": 42,
+                "first_epoch_index": 3,
+                "ticket_price": "25",
+                "yield_split_cfg": {
+                    "jackpot": "1000",
+                    "insurance_premium": "1",
+                    "insurance_probability": "0.0001",
+                    "treasury_ratio": "0.5",
+                    "tier2_prize_share": 7,
+                    "tier3_prize_share": 3
+                },
+                "epochs": [
+                    { "yield_percent": -20, "draw": { "kind": "winner", "tier": 1, "wallet": "alice" } }
+                ]
+            }"#,
+        )?;
+        let alice_sequence = [1, 2, 3, 4, 5, 6];
+        let mut tickets = InMemoryTicketRepository::new(0);
+        for (wallet, nums) in [("alice", alice_sequence), ("bob", [7, 8, 9, 10, 11, 2])] {
+            tickets.add(Ticket {
+                wallet: ScenarioWallet(wallet.into()).pubkey(),
+                epoch_index: 3,
+                arweave_url: None,
+                sequences: vec![Sequence {
+                    nums,
+                    sequence_type: SequenceType::Normal,
+                }],
+                balance: "100".into(),
+                price: "25".into(),
+                risq_id: None,
+            });
+        }
+        let nezha_api = NezhaApiImpl::new();
+        let mut indexer = scenario_indexer(nezha_api.clone(), scenario, tickets)?;
+
+        set_latest_epoch(&nezha_api, 3, EpochStatus::Yielding);
+        indexer.exit_investment().await?;
+        assert_eq!(*nezha_api.return_amounts.lock().unwrap(), vec![Some(usdc("80"))]);
+
+        // A retried draw gives the same combination
+        set_latest_epoch(&nezha_api, 3, EpochStatus::Finalising);
+        indexer.publish_winning_combination().await?;
+        indexer.publish_winning_combination().await?;
+        assert_eq!(
+            *nezha_api.winning_combinations.lock().unwrap(),
+            vec![alice_sequence, alice_sequence]
+        );
+
+        // Epochs the scenario doesn't cover are played randomly
+        set_latest_epoch(&nezha_api, 4, EpochStatus::Yielding);
+        indexer.exit_investment().await?;
+        let return_amount = nezha_api.return_amounts.lock().unwrap()[1].unwrap();
+        assert!(
+            usdc("100") <= return_amount && return_amount <= usdc("105"),
+            "{return_amount}"
+        );
+        set_latest_epoch(&nezha_api, 4, EpochStatus::Finalising);
+        indexer.publish_winning_combination().await?;
+        validate_sequence(&nezha_api.winning_combinations.lock().unwrap()[2])?;
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+pub mod accounts;
 pub mod deposits;
 pub mod epoch;
 pub mod risq;
@@ -3,6 +3,7 @@ use std::{str::FromStr, sync::Arc, time::Duration};
 use anyhow::{Context, Result};
 use borsh::BorshDeserialize;
 use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
 use nezha_staking::{
     accounts as ac,
     fixed_point::FPUSDC,
@@ -11,19 +12,24 @@ use nezha_staking::{
 };
 use service::{
     model::{
+        notification::NotificationEvent,
         prize::Prize,
         transaction::{Transaction, TransactionId, TransactionType},
     },
+    notification::Notifier,
     prize::PrizeRepository,
     transaction::{TransactionHistoryRepository, UserTransactionRepository},
 };
-use solana_client::{nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig, rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
 use solana_sdk::{
     borsh::try_from_slice_unchecked, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
 };
 use solana_transaction_status::{
-    option_serializer::OptionSerializer, EncodedTransaction, UiCompiledInstruction, UiInnerInstructions, UiInstruction,
-    UiMessage, UiRawMessage, UiTransactionEncoding,
+    option_serializer::OptionSerializer, EncodedTransaction, TransactionConfirmationStatus, UiCompiledInstruction,
+    UiInnerInstructions, UiInstruction, UiMessage, UiRawMessage, UiTransactionEncoding,
 };
 
 use crate::indexer::util::SolanaProgramContext;
@@ -31,6 +37,8 @@ use crate::indexer::util::SolanaProgramContext;
 pub enum TransactionItem {
     User(Transaction),
     Prize(Prize),
+    /// Sent once the other items are stored.
+    Notification(NotificationEvent),
     Others,
 }
 
@@ -39,9 +47,12 @@ pub struct PollingIndexer {
     context: Arc<SolanaProgramContext>,
     retry_delay: Duration,
     batch_size: usize,
+    /// How many transactions are fetched at once.
+    fetch_concurrency: usize,
     transaction_history_repository: Box<dyn TransactionHistoryRepository>,
     user_transaction_repository: Box<dyn UserTransactionRepository>,
     prize_repository: Box<dyn PrizeRepository>,
+    notifier: Notifier,
 }
 
 impl PollingIndexer {
@@ -50,18 +61,22 @@ impl PollingIndexer {
         context: Arc<SolanaProgramContext>,
         retry_delay: Duration,
         batch_size: usize,
+        fetch_concurrency: usize,
         transaction_history_repository: Box<dyn TransactionHistoryRepository>,
         user_transaction_repository: Box<dyn UserTransactionRepository>,
         prize_repository: Box<dyn PrizeRepository>,
+        notifier: Notifier,
     ) -> Self {
         Self {
             rpc_client,
             context,
             retry_delay,
             batch_size,
+            fetch_concurrency,
             transaction_history_repository,
             user_transaction_repository,
             prize_repository,
+            notifier,
         }
     }
 
@@ -71,26 +86,150 @@ impl PollingIndexer {
             if let Err(e) = res {
                 log::error!("Error while indexing transactions: {}", e);
             }
+            let res = self.run_confirmed().await;
+            if let Err(e) = res {
+                log::error!("Error while indexing confirmed transactions: {}", e);
+            }
             tokio::time::sleep(self.retry_delay).await;
         }
     }
 
+    /// Indexes the finalized transactions since the last saved one.
     pub async fn run(&self) -> Result<()> {
-        let last_saved = self
-            .transaction_history_repository
+        let last_saved = self.last_saved().await?;
+        // Taken before listing the signatures, so that every transaction finalized up to it is seen
+        let finalized_slot = self
+            .rpc_client
+            .get_slot_with_commitment(CommitmentConfig::finalized())
+            .await?;
+        let signatures = self.signatures(last_saved, CommitmentConfig::finalized()).await?;
+        if !signatures.is_empty() {
+            log::info!("Found {} transaction(s) since last saved", signatures.len());
+        }
+
+        let mut parsed_transactions = self
+            .fetch_transactions(signatures, CommitmentConfig::finalized())
+            .await?;
+        self.store_transactions(&mut parsed_transactions, true).await?;
+
+        // The finalized ones were marked as such while storing, the others were on a dropped fork
+        let removed = self
+            .user_transaction_repository
+            .remove_unfinalized(finalized_slot)
+            .await?;
+        if removed > 0 {
+            log::info!("Removed {} transaction(s) that weren't finalized", removed);
+        }
+
+        Ok(())
+    }
+
+    /// Indexes the user transactions confirmed but not yet finalized, so that users see them sooner.
+    /// They are stored unfinalized until `run` sees them finalized, or removes them if their fork is dropped.
+    pub async fn run_confirmed(&self) -> Result<()> {
+        let last_saved = self.last_saved().await?;
+        let signatures = self
+            .signatures(last_saved, CommitmentConfig::confirmed())
+            .await?
+            .into_iter()
+            .filter(|signature| signature.confirmation_status != Some(TransactionConfirmationStatus::Finalized))
+            .collect::<Vec<_>>();
+        if signatures.is_empty() {
+            return Ok(());
+        }
+        log::info!("Found {} unfinalized transaction(s)", signatures.len());
+
+        let user_transactions = self
+            .fetch_transactions(signatures, CommitmentConfig::confirmed())
+            .await?
+            .into_iter()
+            .flat_map(|(_, transaction_items)| transaction_items)
+            .filter_map(|transaction_item| match transaction_item {
+                TransactionItem::User(transaction) => Some(transaction),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for batch in user_transactions.chunks(self.batch_size) {
+            self.user_transaction_repository.store_transactions(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-indexes the finalized transactions from `start_slot` to `end_slot`, inclusive, newest first and a page of
+    /// signatures at a time. Passing the `before` signature logged after a page resumes an interrupted backfill below it.
+    /// Running it again over the same slots is harmless: the items are upserted, the transactions aren't recorded as
+    /// indexed and no notifications are sent.
+    pub async fn backfill(&self, start_slot: u64, end_slot: u64, mut before: Option<Signature>) -> Result<()> {
+        let mut count = 0;
+        loop {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: None,
+                commitment: Some(CommitmentConfig::finalized()),
+            };
+            let txs = self
+                .rpc_client
+                .get_signatures_for_address_with_config(&self.context.staking_program_id, config)
+                .await?;
+            let oldest = match txs.last() {
+                Some(tx) => tx.clone(),
+                None => break,
+            };
+            let signatures = txs
+                .into_iter()
+                .filter(|tx| (start_slot..=end_slot).contains(&tx.slot))
+                .collect::<Vec<_>>();
+            count += signatures.len();
+
+            let mut parsed_transactions = self
+                .fetch_transactions(signatures, CommitmentConfig::finalized())
+                .await?;
+            self.store_transactions(&mut parsed_transactions, false).await?;
+            log::info!(
+                "Backfilled down to slot {}, resume with `backfill {} {} {}`",
+                oldest.slot,
+                start_slot,
+                end_slot,
+                oldest.signature
+            );
+
+            if oldest.slot < start_slot {
+                break;
+            }
+            before = Some(Signature::from_str(&oldest.signature)?);
+        }
+        log::info!(
+            "Backfilled {} transaction(s) between slots {} and {}",
+            count,
+            start_slot,
+            end_slot
+        );
+        Ok(())
+    }
+
+    async fn last_saved(&self) -> Result<Option<Signature>> {
+        self.transaction_history_repository
             .last_saved()
             .await?
             .map(Signature::try_from)
-            .transpose()?;
+            .transpose()
+    }
 
+    /// Signatures of the staking program transactions since `until`, newest first.
+    async fn signatures(
+        &self,
+        until: Option<Signature>,
+        commitment: CommitmentConfig,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
         let mut before = None;
-        let mut transactions = Vec::new();
+        let mut signatures = Vec::new();
         loop {
             let config = GetConfirmedSignaturesForAddress2Config {
                 before,
-                until: last_saved,
+                until,
                 limit: None,
-                commitment: Some(CommitmentConfig::finalized()),
+                commitment: Some(commitment),
             };
 
             let txs = self
@@ -100,274 +239,370 @@ impl PollingIndexer {
             match txs.last() {
                 Some(tx) => {
                     before = Some(Signature::from_str(&tx.signature)?);
-                    transactions.extend(txs);
+                    signatures.extend(txs);
                 }
                 None => break,
             }
         }
+        Ok(signatures)
+    }
 
-        if !transactions.is_empty() {
-            log::info!("Found {} transaction(s) since last saved", transactions.len());
-        }
-
-        let mut parsed_transactions = Vec::new();
-
-        for transaction in transactions {
-            log::info!("Parsing transaction {}", transaction.signature);
-            let mut transaction_items = Vec::new();
+    /// Fetches and parses up to `fetch_concurrency` transactions at once, keeping their order.
+    async fn fetch_transactions(
+        &self,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+        commitment: CommitmentConfig,
+    ) -> Result<Vec<(TransactionId, Vec<TransactionItem>)>> {
+        let signatures = signatures.into_iter().filter(|transaction| {
             if transaction.err.is_some() {
                 log::info!("Skipping errorful transaction {} ", transaction.signature);
-                continue;
             }
-            let transaction_id = Signature::from_str(&transaction.signature)?;
+            transaction.err.is_none()
+        });
+        futures::stream::iter(signatures)
+            .map(|transaction| async move {
+                log::info!("Parsing transaction {}", transaction.signature);
+                let transaction_id = Signature::from_str(&transaction.signature)?;
+                let transaction_items = self.parse_transaction(&transaction_id, commitment).await?;
+                Ok::<_, anyhow::Error>((TransactionId::from(transaction_id), transaction_items))
+            })
+            .buffered(self.fetch_concurrency)
+            .try_collect()
+            .await
+    }
 
-            let transaction = self
-                .rpc_client
-                .get_transaction(&transaction_id, UiTransactionEncoding::Json)
-                .await?;
+    async fn parse_transaction(
+        &self,
+        transaction_id: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<Vec<TransactionItem>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Json),
+            commitment: Some(commitment),
+            max_supported_transaction_version: None,
+        };
+        let transaction = self
+            .rpc_client
+            .get_transaction_with_config(transaction_id, config)
+            .await?;
 
-            let transaction_time = transaction
+        let inner_instructions: Vec<UiInnerInstructions> = match transaction.transaction.meta {
+            Some(meta) => match meta.inner_instructions {
+                OptionSerializer::Some(ixs) => ixs,
+                OptionSerializer::None | OptionSerializer::Skip => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        let message = match transaction.transaction.transaction {
+            EncodedTransaction::Json(ui_transaction) => match ui_transaction.message {
+                UiMessage::Raw(message) => message,
+                _ => {
+                    log::error!("Unexpected transaction encoding");
+                    return Ok(Vec::new());
+                }
+            },
+            _ => {
+                log::error!("Unexpected transaction encoding");
+                return Ok(Vec::new());
+            }
+        };
+        let info = TransactionInfo {
+            transaction_id: TransactionId::from(*transaction_id),
+            message: &message,
+            inner_instructions: &inner_instructions,
+            slot: transaction.slot,
+            time: transaction
                 .block_time
-                .map(|secs| DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(secs, 0), Utc));
-            let inner_instructions: Option<Vec<UiInnerInstructions>> = match transaction.transaction.meta {
-                Some(meta) => match meta.inner_instructions {
-                    OptionSerializer::Some(ixs) => Some(ixs),
-                    OptionSerializer::None | OptionSerializer::Skip => None,
-                },
-                _ => None,
-            };
-            if let EncodedTransaction::Json(ui_transaction) = transaction.transaction.transaction {
-                if let UiMessage::Raw(message) = ui_transaction.message {
-                    for (index, ui_instruction) in message.instructions.iter().enumerate() {
-                        if self.context.staking_program_id
-                            == Pubkey::from_str(&message.account_keys[ui_instruction.program_id_index as usize])?
-                        {
-                            let data = bs58::decode(&ui_instruction.data).into_vec()?;
-                            let instruction = StakingInstruction::try_from_slice(&data)?;
-                            let transaction_id = TransactionId::from(transaction_id);
-                            match instruction {
-                                StakingInstruction::RequestStakeUpdate { amount } => {
-                                    let wallet = get_instruction_account(0, ui_instruction, &message)?;
-                                    let transaction = Transaction {
-                                        transaction_id: transaction_id.clone(),
-                                        instruction_index: index as u8,
-                                        wallet,
-                                        amount: FPUSDC::from_usdc(amount.abs() as u64),
-                                        mint: self.context.usdc_mint_pubkey,
-                                        time: transaction_time,
-                                        transaction_type: if amount < 0 {
-                                            TransactionType::WithdrawAttempt
-                                        } else {
-                                            TransactionType::DepositAttempt
-                                        },
-                                    };
-                                    transaction_items.push(TransactionItem::User(transaction));
-                                }
-                                StakingInstruction::ApproveStakeUpdate { amount } => {
-                                    let wallet = get_instruction_account(1, ui_instruction, &message)?;
-                                    let transaction = Transaction {
-                                        transaction_id: transaction_id.clone(),
-                                        instruction_index: index as u8,
-                                        wallet,
-                                        amount: FPUSDC::from_usdc(amount.abs() as u64),
-                                        mint: self.context.usdc_mint_pubkey,
-                                        time: transaction_time,
-                                        transaction_type: if amount < 0 {
-                                            TransactionType::WithdrawApproved
-                                        } else {
-                                            TransactionType::DepositApproved
-                                        },
-                                    };
-                                    transaction_items.push(TransactionItem::User(transaction));
-                                }
-                                StakingInstruction::CompleteStakeUpdate => {
-                                    let transfer = get_token_transfer(
-                                        &inner_instructions.as_ref().context("Can't find inner instructions")?,
-                                        index as u8,
-                                        &message,
-                                    )?
-                                    .context("Could not find amount for cancel stake update")?;
-
-                                    let transaction_type = if transfer.destination
-                                        == *ac::deposit_vault(&self.context.staking_program_id)
-                                    {
-                                        TransactionType::DepositCompleted
-                                    } else {
-                                        TransactionType::WithdrawCompleted
-                                    };
-
-                                    let wallet = get_instruction_account(1, ui_instruction, &message)?;
-                                    let transaction = Transaction {
-                                        transaction_id: transaction_id.clone(),
-                                        instruction_index: index as u8,
-                                        wallet,
-                                        amount: FPUSDC::from_usdc(transfer.amount),
-                                        mint: self.context.usdc_mint_pubkey,
-                                        time: transaction_time,
-                                        transaction_type,
-                                    };
-                                    transaction_items.push(TransactionItem::User(transaction));
-                                }
-                                StakingInstruction::CancelStakeUpdate { amount } => {
-                                    let wallet = get_instruction_account(1, ui_instruction, &message)?;
-                                    let transaction = Transaction {
-                                        transaction_id: transaction_id.clone(),
-                                        instruction_index: index as u8,
-                                        wallet,
-                                        amount: FPUSDC::from_usdc(amount.abs() as u64),
-                                        mint: self.context.usdc_mint_pubkey,
-                                        time: transaction_time,
-                                        transaction_type: if amount < 0 {
-                                            TransactionType::WithdrawCancelled
-                                        } else {
-                                            TransactionType::DepositCancelled
-                                        },
-                                    };
-                                    transaction_items.push(TransactionItem::User(transaction));
-                                }
-                                StakingInstruction::ClaimWinning {
-                                    epoch_index,
-                                    winner_index,
-                                    tier,
-                                    page,
-                                    ..
-                                } => {
-                                    let transfer = get_token_transfer(
-                                        inner_instructions.as_ref().context("Can't find inner instructions")?,
-                                        index as u8,
-                                        &message,
-                                    )?
-                                    .context("Could not find amount for claim winning")?;
-                                    let amount = FPUSDC::from_usdc(transfer.amount);
-
-                                    let wallet = get_instruction_account(0, ui_instruction, &message)?;
-                                    let mint = self.context.usdc_mint_pubkey;
-                                    let transaction = Transaction {
-                                        transaction_id: transaction_id.clone(),
-                                        instruction_index: index as u8,
-                                        wallet,
-                                        amount,
-                                        mint,
-                                        time: transaction_time,
-                                        transaction_type: TransactionType::Claim,
-                                    };
-                                    transaction_items.push(TransactionItem::User(transaction));
-
-                                    let epoch_winners_meta: EpochWinnersMeta =
-                                        get_instruction_account_data(1, ui_instruction, &message, &self.rpc_client)
-                                            .await?;
-
-                                    let epoch_winners_page: EpochWinnersPage =
-                                        get_instruction_account_data(2, ui_instruction, &message, &self.rpc_client)
-                                            .await?;
-
-                                    let claimable = match tier {
-                                        1 => epoch_winners_meta.jackpot_claimable,
-                                        2 | 3 => true,
-                                        _ => unreachable!(),
-                                    };
-
-                                    let winner_index_in_page = winner_index - page * MAX_NUM_WINNERS_PER_PAGE as u32;
-                                    let winner: &Winner = &epoch_winners_page.winners[winner_index_in_page as usize];
-
-                                    let prize = Prize {
-                                        wallet,
-                                        epoch_index,
-                                        page,
-                                        winner_index,
-                                        tier,
-                                        amount,
-                                        claimable,
-                                        claimed: winner.claimed,
-                                    };
-                                    transaction_items.push(TransactionItem::Prize(prize));
-                                }
-                                StakingInstruction::PublishWinners { .. } | StakingInstruction::FundJackpot { .. } => {
-                                    let meta_account_index = match instruction {
-                                        StakingInstruction::PublishWinners { .. } => 3,
-                                        StakingInstruction::FundJackpot { .. } => 3,
-                                        _ => unreachable!(),
-                                    };
-
-                                    let epoch_winners_meta: EpochWinnersMeta = get_instruction_account_data(
-                                        meta_account_index,
-                                        ui_instruction,
-                                        &message,
-                                        &self.rpc_client,
-                                    )
-                                    .await?;
-                                    if epoch_winners_meta.status == WinnerProcessingStatus::Completed {
-                                        let pages = 0..epoch_winners_meta.total_num_pages;
-                                        let page_pubkeys = pages
-                                            .clone()
-                                            .map(|page_index| {
-                                                nezha_staking::accounts::epoch_winners_page(
-                                                    &self.context.staking_program_id,
-                                                    epoch_winners_meta.epoch_index,
-                                                    page_index,
-                                                )
-                                                .pubkey
-                                            })
-                                            .collect::<Vec<_>>();
-                                        let page_accounts =
-                                            self.rpc_client.get_multiple_accounts(&page_pubkeys).await?;
-                                        for (page, page_account) in pages.zip(page_accounts) {
-                                            let page_account = match page_account {
-                                                Some(page_account) => page_account,
-                                                None => {
-                                                    log::error!("Epoch winner page account is None. Page {}", page);
-                                                    continue;
-                                                }
-                                            };
-                                            let epoch_winners_page =
-                                                try_from_slice_unchecked::<EpochWinnersPage>(&page_account.data)?;
-                                            for (winner_index, winner) in epoch_winners_page.winners.iter().enumerate()
-                                            {
-                                                let claimable = match winner.tier {
-                                                    1 => epoch_winners_meta.jackpot_claimable,
-                                                    2 | 3 => true,
-                                                    _ => unreachable!(),
-                                                };
-                                                let prize = Prize {
-                                                    wallet: winner.address,
-                                                    epoch_index: epoch_winners_meta.epoch_index,
-                                                    page,
-                                                    winner_index: winner_index as _,
-                                                    tier: winner.tier,
-                                                    amount: winner.prize,
-                                                    claimable,
-                                                    claimed: winner.claimed,
-                                                };
-                                                transaction_items.push(TransactionItem::Prize(prize));
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
+                .map(|secs| DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(secs, 0), Utc)),
+            finalized: commitment.is_finalized(),
+        };
+
+        let mut transaction_items = Vec::new();
+        for instruction in self.staking_instructions(&message, &inner_instructions)? {
+            self.parse_instruction(&info, &instruction, &mut transaction_items)
+                .await?;
+        }
+        Ok(transaction_items)
+    }
+
+    /// The staking instructions of the transaction, including those invoked through CPI, in execution order.
+    fn staking_instructions<'a>(
+        &self,
+        message: &'a UiRawMessage,
+        inner_instructions: &'a [UiInnerInstructions],
+    ) -> Result<Vec<StakingInstructionRef<'a>>> {
+        let is_staking_program = |instruction: &UiCompiledInstruction| -> Result<bool> {
+            let program_id = message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+                .context("Can't get the program id of the instruction")?;
+            Ok(self.context.staking_program_id == Pubkey::from_str(program_id)?)
+        };
+
+        let mut instructions = Vec::new();
+        for (index, instruction) in message.instructions.iter().enumerate() {
+            if is_staking_program(instruction)? {
+                instructions.push(StakingInstructionRef {
+                    index: index as u8,
+                    inner_index: None,
+                    stack_height: Some(1),
+                    instruction,
+                });
+            }
+            let inner = inner_instructions.iter().find(|ix| ix.index == index as u8);
+            for (inner_index, instruction) in inner.iter().flat_map(|ix| ix.instructions.iter().enumerate()) {
+                if let UiInstruction::Compiled(instruction) = instruction {
+                    if is_staking_program(instruction)? {
+                        instructions.push(StakingInstructionRef {
+                            index: index as u8,
+                            inner_index: Some(inner_index as u8),
+                            stack_height: instruction.stack_height,
+                            instruction,
+                        });
                     }
-                } else {
-                    log::error!("Unexpected transaction encoding");
                 }
             }
-            parsed_transactions.push((TransactionId::from(transaction_id), transaction_items));
         }
+        Ok(instructions)
+    }
+
+    async fn parse_instruction(
+        &self,
+        info: &TransactionInfo<'_>,
+        staking_instruction: &StakingInstructionRef<'_>,
+        transaction_items: &mut Vec<TransactionItem>,
+    ) -> Result<()> {
+        let ui_instruction = staking_instruction.instruction;
+        let message = info.message;
+        let data = bs58::decode(&ui_instruction.data).into_vec()?;
+        let instruction = StakingInstruction::try_from_slice(&data)?;
+        match instruction {
+            StakingInstruction::RequestStakeUpdate { amount } => {
+                let wallet = get_instruction_account(0, ui_instruction, message)?;
+                let transaction = info.user_transaction(
+                    staking_instruction,
+                    wallet,
+                    FPUSDC::from_usdc(amount.abs() as u64),
+                    self.context.usdc_mint_pubkey,
+                    if amount < 0 {
+                        TransactionType::WithdrawAttempt
+                    } else {
+                        TransactionType::DepositAttempt
+                    },
+                );
+                transaction_items.push(TransactionItem::User(transaction));
+            }
+            StakingInstruction::ApproveStakeUpdate { amount } => {
+                let wallet = get_instruction_account(1, ui_instruction, message)?;
+                let transaction = info.user_transaction(
+                    staking_instruction,
+                    wallet,
+                    FPUSDC::from_usdc(amount.abs() as u64),
+                    self.context.usdc_mint_pubkey,
+                    if amount < 0 {
+                        TransactionType::WithdrawApproved
+                    } else {
+                        TransactionType::DepositApproved
+                    },
+                );
+                transaction_items.push(TransactionItem::User(transaction));
+            }
+            StakingInstruction::CompleteStakeUpdate => {
+                let transfer = get_token_transfer(info.inner_instructions, staking_instruction, message)?
+                    .context("Could not find amount for cancel stake update")?;
+
+                let transaction_type = if transfer.destination == *ac::deposit_vault(&self.context.staking_program_id) {
+                    TransactionType::DepositCompleted
+                } else {
+                    TransactionType::WithdrawCompleted
+                };
+
+                let wallet = get_instruction_account(1, ui_instruction, message)?;
+                let amount = FPUSDC::from_usdc(transfer.amount);
+                let transaction_id = info.transaction_id.clone();
+                let instruction_index = staking_instruction.index;
+                let notification = if transaction_type == TransactionType::DepositCompleted {
+                    NotificationEvent::DepositCompleted {
+                        wallet,
+                        amount,
+                        transaction_id,
+                        instruction_index,
+                    }
+                } else {
+                    NotificationEvent::WithdrawalCompleted {
+                        wallet,
+                        amount,
+                        transaction_id,
+                        instruction_index,
+                    }
+                };
+                let transaction = info.user_transaction(
+                    staking_instruction,
+                    wallet,
+                    amount,
+                    self.context.usdc_mint_pubkey,
+                    transaction_type,
+                );
+                transaction_items.push(TransactionItem::User(transaction));
+                if info.finalized {
+                    transaction_items.push(TransactionItem::Notification(notification));
+                }
+            }
+            StakingInstruction::CancelStakeUpdate { amount } => {
+                let wallet = get_instruction_account(1, ui_instruction, message)?;
+                let transaction = info.user_transaction(
+                    staking_instruction,
+                    wallet,
+                    FPUSDC::from_usdc(amount.abs() as u64),
+                    self.context.usdc_mint_pubkey,
+                    if amount < 0 {
+                        TransactionType::WithdrawCancelled
+                    } else {
+                        TransactionType::DepositCancelled
+                    },
+                );
+                transaction_items.push(TransactionItem::User(transaction));
+            }
+            StakingInstruction::ClaimWinning {
+                epoch_index,
+                winner_index,
+                tier,
+                page,
+                ..
+            } => {
+                let transfer = get_token_transfer(info.inner_instructions, staking_instruction, message)?
+                    .context("Could not find amount for claim winning")?;
+                let amount = FPUSDC::from_usdc(transfer.amount);
+
+                let wallet = get_instruction_account(0, ui_instruction, message)?;
+                let mint = self.context.usdc_mint_pubkey;
+                let transaction =
+                    info.user_transaction(staking_instruction, wallet, amount, mint, TransactionType::Claim);
+                transaction_items.push(TransactionItem::User(transaction));
+
+                // The accounts are read at finalized commitment, prizes wait for the transaction to be finalized
+                if !info.finalized {
+                    return Ok(());
+                }
+
+                let epoch_winners_meta: EpochWinnersMeta =
+                    get_instruction_account_data(1, ui_instruction, message, &self.rpc_client).await?;
+
+                let epoch_winners_page: EpochWinnersPage =
+                    get_instruction_account_data(2, ui_instruction, message, &self.rpc_client).await?;
+
+                let claimable = match tier {
+                    1 => epoch_winners_meta.jackpot_claimable,
+                    2 | 3 => true,
+                    _ => unreachable!(),
+                };
+
+                let winner_index_in_page = winner_index - page * MAX_NUM_WINNERS_PER_PAGE as u32;
+                let winner: &Winner = &epoch_winners_page.winners[winner_index_in_page as usize];
+
+                let prize = Prize {
+                    wallet,
+                    epoch_index,
+                    page,
+                    winner_index,
+                    tier,
+                    amount,
+                    claimable,
+                    claimed: winner.claimed,
+                };
+                transaction_items.push(TransactionItem::Prize(prize));
+            }
+            StakingInstruction::PublishWinners { .. } | StakingInstruction::FundJackpot { .. } => {
+                if !info.finalized {
+                    return Ok(());
+                }
 
-        self.store_transactions(&mut parsed_transactions).await?;
+                let meta_account_index = match instruction {
+                    StakingInstruction::PublishWinners { .. } => 3,
+                    StakingInstruction::FundJackpot { .. } => 3,
+                    _ => unreachable!(),
+                };
 
+                let epoch_winners_meta: EpochWinnersMeta =
+                    get_instruction_account_data(meta_account_index, ui_instruction, message, &self.rpc_client).await?;
+                if epoch_winners_meta.status == WinnerProcessingStatus::Completed {
+                    // Funding the jackpot only changes whether the prizes are claimable
+                    let winners_published = matches!(instruction, StakingInstruction::PublishWinners { .. });
+                    if winners_published {
+                        transaction_items.push(TransactionItem::Notification(NotificationEvent::EpochEnded {
+                            epoch_index: epoch_winners_meta.epoch_index,
+                        }));
+                    }
+                    let pages = 0..epoch_winners_meta.total_num_pages;
+                    let page_pubkeys = pages
+                        .clone()
+                        .map(|page_index| {
+                            nezha_staking::accounts::epoch_winners_page(
+                                &self.context.staking_program_id,
+                                epoch_winners_meta.epoch_index,
+                                page_index,
+                            )
+                            .pubkey
+                        })
+                        .collect::<Vec<_>>();
+                    let page_accounts = self.rpc_client.get_multiple_accounts(&page_pubkeys).await?;
+                    for (page, page_account) in pages.zip(page_accounts) {
+                        let page_account = match page_account {
+                            Some(page_account) => page_account,
+                            None => {
+                                log::error!("Epoch winner page account is None. Page {}", page);
+                                continue;
+                            }
+                        };
+                        let epoch_winners_page = try_from_slice_unchecked::<EpochWinnersPage>(&page_account.data)?;
+                        for (winner_index, winner) in epoch_winners_page.winners.iter().enumerate() {
+                            let claimable = match winner.tier {
+                                1 => epoch_winners_meta.jackpot_claimable,
+                                2 | 3 => true,
+                                _ => unreachable!(),
+                            };
+                            let prize = Prize {
+                                wallet: winner.address,
+                                epoch_index: epoch_winners_meta.epoch_index,
+                                page,
+                                winner_index: winner_index as _,
+                                tier: winner.tier,
+                                amount: winner.prize,
+                                claimable,
+                                claimed: winner.claimed,
+                            };
+                            if winners_published {
+                                transaction_items.push(TransactionItem::Notification(NotificationEvent::PrizeWon {
+                                    wallet: prize.wallet,
+                                    epoch_index: prize.epoch_index,
+                                    page: prize.page,
+                                    winner_index: prize.winner_index,
+                                    tier: prize.tier,
+                                    amount: prize.amount,
+                                }));
+                            }
+                            transaction_items.push(TransactionItem::Prize(prize));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
         Ok(())
     }
 
+    /// `live` indexing notifies users and records the transactions as indexed, backfills don't.
     async fn store_transactions(
         &self,
         parsed_transactions: &mut Vec<(TransactionId, Vec<TransactionItem>)>,
+        live: bool,
     ) -> Result<()> {
         while !parsed_transactions.is_empty() {
             let mut count = 0;
             let mut transaction_ids = Vec::new();
             let mut prizes_batch = Vec::new();
             let mut user_tx_batch = Vec::new();
+            let mut notifications = Vec::new();
             while let Some((transaction_id, transaction_items)) = parsed_transactions.pop() {
                 transaction_ids.push(transaction_id);
                 for transaction_item in transaction_items {
@@ -380,6 +615,7 @@ impl PollingIndexer {
                             log::info!("Storing prize: {:?}", prize);
                             prizes_batch.push(prize);
                         }
+                        TransactionItem::Notification(notification) => notifications.push(notification),
                         _ => {}
                     };
                 }
@@ -392,6 +628,14 @@ impl PollingIndexer {
                 self.prize_repository.upsert_prizes(&prizes_batch),
                 self.user_transaction_repository.store_transactions(&user_tx_batch)
             )?;
+            if !live {
+                continue;
+            }
+            // Queued before the transactions are marked as saved, so that a failure is retried. Deliveries are
+            // deduplicated, and sent by the notification worker.
+            for notification in &notifications {
+                self.notifier.notify(notification).await?;
+            }
             self.transaction_history_repository
                 .save_transaction_ids(&transaction_ids)
                 .await?;
@@ -400,6 +644,51 @@ impl PollingIndexer {
     }
 }
 
+/// A staking instruction, either at the top level of the transaction or invoked through CPI.
+struct StakingInstructionRef<'a> {
+    /// Index of the top level instruction.
+    index: u8,
+    /// Position among the inner instructions of `index` when invoked through CPI.
+    inner_index: Option<u8>,
+    /// 1 at the top level. Older RPC nodes don't return it for inner instructions.
+    stack_height: Option<u32>,
+    instruction: &'a UiCompiledInstruction,
+}
+
+/// What the instructions of a transaction share.
+struct TransactionInfo<'a> {
+    transaction_id: TransactionId,
+    message: &'a UiRawMessage,
+    inner_instructions: &'a [UiInnerInstructions],
+    slot: u64,
+    time: Option<DateTime<Utc>>,
+    finalized: bool,
+}
+
+impl TransactionInfo<'_> {
+    fn user_transaction(
+        &self,
+        instruction: &StakingInstructionRef,
+        wallet: Pubkey,
+        amount: FPUSDC,
+        mint: Pubkey,
+        transaction_type: TransactionType,
+    ) -> Transaction {
+        Transaction {
+            transaction_id: self.transaction_id.clone(),
+            instruction_index: instruction.index,
+            wallet,
+            amount,
+            mint,
+            time: self.time,
+            transaction_type,
+            inner_instruction_index: instruction.inner_index,
+            slot: Some(self.slot),
+            finalized: self.finalized,
+        }
+    }
+}
+
 #[allow(unused)]
 struct TokenTransfer {
     source: Pubkey,
@@ -407,37 +696,60 @@ struct TokenTransfer {
     amount: u64,
 }
 
+/// The first token transfer made by the staking instruction itself, rather than by the programs it invokes.
 fn get_token_transfer(
     inner_instructions_list: &[UiInnerInstructions],
-    index: u8,
+    staking_instruction: &StakingInstructionRef,
     message: &UiRawMessage,
 ) -> Result<Option<TokenTransfer>> {
-    if let Some(inner_instructions) = inner_instructions_list.iter().find(|ix| ix.index == index) {
-        for instruction in &inner_instructions.instructions {
-            let instruction = if let UiInstruction::Compiled(instruction) = instruction {
-                instruction
-            } else {
-                continue;
-            };
+    let inner_instructions = match inner_instructions_list
+        .iter()
+        .find(|ix| ix.index == staking_instruction.index)
+    {
+        Some(inner_instructions) => inner_instructions,
+        None => return Ok(None),
+    };
 
-            let data = bs58::decode(&instruction.data).into_vec()?;
+    // Instructions invoked through CPI make their transfers after their own position
+    let first = staking_instruction.inner_index.map_or(0, |index| index as usize + 1);
+    for instruction in inner_instructions.instructions.iter().skip(first) {
+        let instruction = if let UiInstruction::Compiled(instruction) = instruction {
+            instruction
+        } else {
+            continue;
+        };
 
-            let token_instruction = if let Ok(instruction) = spl_token::instruction::TokenInstruction::unpack(&data) {
-                instruction
-            } else {
+        if let (Some(height), Some(instruction_height)) = (staking_instruction.stack_height, instruction.stack_height) {
+            // The staking instruction returned, what follows was invoked by another one
+            if instruction_height <= height {
+                break;
+            }
+            // Invoked by a program the staking instruction invoked
+            if instruction_height > height + 1 {
                 continue;
-            };
+            }
+        }
 
-            if let spl_token::instruction::TokenInstruction::Transfer { amount } = token_instruction {
-                let source = get_instruction_account(0, instruction, message)?;
-                let destination = get_instruction_account(1, instruction, message)?;
+        let program_id = message
+            .account_keys
+            .get(instruction.program_id_index as usize)
+            .context("Can't get the program id of the instruction")?;
+        if Pubkey::from_str(program_id)? != spl_token::id() {
+            continue;
+        }
 
-                return Ok(Some(TokenTransfer {
-                    source,
-                    destination,
-                    amount,
-                }));
-            }
+        let data = bs58::decode(&instruction.data).into_vec()?;
+        if let Ok(spl_token::instruction::TokenInstruction::Transfer { amount }) =
+            spl_token::instruction::TokenInstruction::unpack(&data)
+        {
+            let source = get_instruction_account(0, instruction, message)?;
+            let destination = get_instruction_account(1, instruction, message)?;
+
+            return Ok(Some(TokenTransfer {
+                source,
+                destination,
+                amount,
+            }));
         }
     }
 
@@ -471,3 +783,486 @@ pub async fn get_instruction_account_data<T: BorshDeserialize>(
     let account = try_from_slice_unchecked(&account_data)?;
     Ok(account)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            RwLock,
+        },
+    };
+
+    use async_trait::async_trait;
+    use borsh::BorshSerialize;
+    use service::notification::{InMemoryNotificationDeliveryRepository, InMemoryNotificationPreferencesRepository};
+    use solana_client::{
+        client_error::Result as ClientResult,
+        rpc_client::RpcClientConfig,
+        rpc_request::RpcRequest,
+        rpc_sender::{RpcSender, RpcTransportStats},
+    };
+    use solana_sdk::{message::MessageHeader, signature::Keypair};
+    use solana_transaction_status::{
+        EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta, TransactionStatusMeta,
+        UiTransaction, UiTransactionStatusMeta,
+    };
+
+    use super::*;
+
+    const WALLET: u8 = 0;
+    const STAKING_PROGRAM: u8 = 1;
+    const TOKEN_PROGRAM: u8 = 2;
+    const OTHER_PROGRAM: u8 = 3;
+    const DEPOSIT_VAULT: u8 = 4;
+    const SOURCE: u8 = 5;
+
+    /// Serves the transactions of the staking program, newest first, `page_size` signatures at a time.
+    struct FakeRpc {
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+        transactions: HashMap<String, EncodedConfirmedTransactionWithStatusMeta>,
+        finalized_slot: u64,
+        page_size: usize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    struct FakeRpcSender(Arc<FakeRpc>);
+
+    #[async_trait]
+    impl RpcSender for FakeRpcSender {
+        async fn send(&self, request: RpcRequest, params: serde_json::Value) -> ClientResult<serde_json::Value> {
+            let rpc = &self.0;
+            match request {
+                RpcRequest::GetVersion => Ok(serde_json::json!({ "solana-core": "1.16.27", "feature-set": 0 })),
+                RpcRequest::GetSlot => Ok(serde_json::json!(rpc.finalized_slot)),
+                RpcRequest::GetSignaturesForAddress => {
+                    let config = &params[1];
+                    let finalized = config["commitment"] == "finalized";
+                    let start = match config["before"].as_str() {
+                        Some(before) => rpc.signatures.iter().position(|tx| tx.signature == before).unwrap() + 1,
+                        None => 0,
+                    };
+                    let page = rpc.signatures[start..]
+                        .iter()
+                        .take_while(|tx| Some(tx.signature.as_str()) != config["until"].as_str())
+                        .filter(|tx| !finalized || tx.slot <= rpc.finalized_slot)
+                        .take(rpc.page_size)
+                        .map(|tx| RpcConfirmedTransactionStatusWithSignature {
+                            confirmation_status: Some(if tx.slot <= rpc.finalized_slot {
+                                TransactionConfirmationStatus::Finalized
+                            } else {
+                                TransactionConfirmationStatus::Confirmed
+                            }),
+                            ..tx.clone()
+                        })
+                        .collect::<Vec<_>>();
+                    Ok(serde_json::to_value(page)?)
+                }
+                RpcRequest::GetTransaction => {
+                    let in_flight = rpc.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    rpc.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    rpc.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let transaction = &rpc.transactions[params[0].as_str().unwrap()];
+                    Ok(serde_json::to_value(transaction)?)
+                }
+                request => unimplemented!("{}", request),
+            }
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "fake".to_string()
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct FakeUserTransactionRepository {
+        mem: Arc<RwLock<Vec<Transaction>>>,
+    }
+
+    impl FakeUserTransactionRepository {
+        fn stored(&self) -> Vec<Transaction> {
+            let mut transactions = self.mem.read().unwrap().clone();
+            transactions.sort_by_key(|transaction| transaction.slot);
+            transactions
+        }
+    }
+
+    #[async_trait]
+    impl UserTransactionRepository for FakeUserTransactionRepository {
+        async fn by_transaction_id_and_instruction_index(
+            &self,
+            _transaction_id: &TransactionId,
+            _instruction_index: u8,
+            _inner_instruction_index: Option<u8>,
+        ) -> Result<Option<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_transaction_id(&self, _transaction_id: &TransactionId) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_wallet(&self, _wallet: &Pubkey, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_type(
+            &self,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_wallet_and_type(
+            &self,
+            _wallet: &Pubkey,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn all(&self, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn store_transaction(&self, transaction: &Transaction) -> Result<()> {
+            self.store_transactions(&[transaction.clone()]).await
+        }
+        async fn store_transactions(&self, transactions: &[Transaction]) -> Result<()> {
+            let mut mem = self.mem.write().unwrap();
+            for transaction in transactions {
+                mem.retain(|stored| {
+                    (
+                        &stored.transaction_id,
+                        stored.instruction_index,
+                        stored.inner_instruction_index,
+                    ) != (
+                        &transaction.transaction_id,
+                        transaction.instruction_index,
+                        transaction.inner_instruction_index,
+                    )
+                });
+                mem.push(transaction.clone());
+            }
+            Ok(())
+        }
+        async fn remove_unfinalized(&self, up_to_slot: u64) -> Result<u64> {
+            let mut mem = self.mem.write().unwrap();
+            let len = mem.len();
+            mem.retain(|transaction| transaction.finalized || transaction.slot > Some(up_to_slot));
+            Ok((len - mem.len()) as u64)
+        }
+        async fn total_deposit_by_wallet(&self, _wallet: &Pubkey) -> Result<FPUSDC> {
+            unimplemented!()
+        }
+        async fn balance_changes_by_wallet(&self, _wallet: &Pubkey, _since: DateTime<Utc>) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct FakeTransactionHistoryRepository {
+        mem: Arc<RwLock<Vec<TransactionId>>>,
+    }
+
+    #[async_trait]
+    impl TransactionHistoryRepository for FakeTransactionHistoryRepository {
+        async fn last_saved(&self) -> Result<Option<TransactionId>> {
+            Ok(self.mem.read().unwrap().last().cloned())
+        }
+        async fn save_transaction_id(&self, transaction_id: &TransactionId) -> Result<()> {
+            self.save_transaction_ids(&[transaction_id.clone()]).await
+        }
+        async fn save_transaction_ids(&self, transaction_ids: &[TransactionId]) -> Result<()> {
+            self.mem.write().unwrap().extend_from_slice(transaction_ids);
+            Ok(())
+        }
+    }
+
+    struct NoPrizes;
+
+    #[async_trait]
+    impl PrizeRepository for NoPrizes {
+        async fn by_wallet(&self, _wallet: &Pubkey, _limit: usize, _offset: usize) -> Result<Vec<Prize>> {
+            unimplemented!()
+        }
+        async fn by_wallet_epoch_and_tier(
+            &self,
+            _wallet: &Pubkey,
+            _epoch_index: u64,
+            _tier: u8,
+        ) -> Result<Option<Prize>> {
+            unimplemented!()
+        }
+        async fn total_prize_by_wallet(&self, _wallet: &Pubkey) -> Result<FPUSDC> {
+            unimplemented!()
+        }
+        async fn upsert_prizes(&self, _prizes: &[Prize]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct Setup {
+        staking_program_id: Pubkey,
+        account_keys: Vec<String>,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+        transactions: HashMap<String, EncodedConfirmedTransactionWithStatusMeta>,
+    }
+
+    impl Setup {
+        fn new() -> Self {
+            let staking_program_id = Pubkey::new_unique();
+            let account_keys = vec![
+                Pubkey::new_unique(),
+                staking_program_id,
+                spl_token::id(),
+                Pubkey::new_unique(),
+                *ac::deposit_vault(&staking_program_id),
+                Pubkey::new_unique(),
+            ];
+            Self {
+                staking_program_id,
+                account_keys: account_keys.iter().map(ToString::to_string).collect(),
+                signatures: Vec::new(),
+                transactions: HashMap::new(),
+            }
+        }
+
+        /// Adds a transaction newer than the others.
+        fn add(
+            &mut self,
+            slot: u64,
+            instructions: Vec<UiCompiledInstruction>,
+            inner_instructions: Vec<UiInnerInstructions>,
+        ) -> String {
+            let signature = Signature::new_unique().to_string();
+            let transaction = EncodedConfirmedTransactionWithStatusMeta {
+                slot,
+                transaction: EncodedTransactionWithStatusMeta {
+                    transaction: EncodedTransaction::Json(UiTransaction {
+                        signatures: vec![signature.clone()],
+                        message: UiMessage::Raw(UiRawMessage {
+                            header: MessageHeader::default(),
+                            account_keys: self.account_keys.clone(),
+                            recent_blockhash: String::new(),
+                            instructions,
+                            address_table_lookups: None,
+                        }),
+                    }),
+                    meta: Some(UiTransactionStatusMeta {
+                        inner_instructions: OptionSerializer::Some(inner_instructions),
+                        ..TransactionStatusMeta::default().into()
+                    }),
+                    version: None,
+                },
+                block_time: Some(slot as i64),
+            };
+            self.transactions.insert(signature.clone(), transaction);
+            self.signatures.insert(
+                0,
+                RpcConfirmedTransactionStatusWithSignature {
+                    signature: signature.clone(),
+                    slot,
+                    err: None,
+                    memo: None,
+                    block_time: Some(slot as i64),
+                    confirmation_status: None,
+                },
+            );
+            signature
+        }
+
+        /// Adds a transaction requesting a deposit of `slot` USDC.
+        fn add_request(&mut self, slot: u64) -> String {
+            let data = StakingInstruction::RequestStakeUpdate { amount: slot as i64 }
+                .try_to_vec()
+                .unwrap();
+            self.add(
+                slot,
+                vec![instruction(STAKING_PROGRAM, vec![WALLET], &data, None)],
+                vec![],
+            )
+        }
+
+        fn indexer(
+            self,
+            finalized_slot: u64,
+            page_size: usize,
+            fetch_concurrency: usize,
+        ) -> (
+            PollingIndexer,
+            Arc<FakeRpc>,
+            FakeUserTransactionRepository,
+            FakeTransactionHistoryRepository,
+        ) {
+            let rpc = Arc::new(FakeRpc {
+                signatures: self.signatures,
+                transactions: self.transactions,
+                finalized_slot,
+                page_size,
+                in_flight: AtomicUsize::new(0),
+                max_in_flight: AtomicUsize::new(0),
+            });
+            let rpc_client = Arc::new(RpcClient::new_sender(
+                FakeRpcSender(rpc.clone()),
+                RpcClientConfig::with_commitment(CommitmentConfig::finalized()),
+            ));
+            let context = Arc::new(SolanaProgramContext::new(
+                rpc_client.clone(),
+                self.staking_program_id,
+                Pubkey::new_unique(),
+                Arc::new(Keypair::new()),
+                Arc::new(Keypair::new()),
+            ));
+            let user_transactions = FakeUserTransactionRepository::default();
+            let history = FakeTransactionHistoryRepository::default();
+            let indexer = PollingIndexer::new(
+                rpc_client,
+                context,
+                Duration::from_millis(10),
+                2,
+                fetch_concurrency,
+                Box::new(history.clone()),
+                Box::new(user_transactions.clone()),
+                Box::new(NoPrizes),
+                Notifier::new(
+                    Box::new(InMemoryNotificationPreferencesRepository::default()),
+                    Box::new(InMemoryNotificationDeliveryRepository::default()),
+                ),
+            );
+            (indexer, rpc, user_transactions, history)
+        }
+    }
+
+    fn instruction(program: u8, accounts: Vec<u8>, data: &[u8], stack_height: Option<u32>) -> UiCompiledInstruction {
+        UiCompiledInstruction {
+            program_id_index: program,
+            accounts,
+            data: bs58::encode(data).into_string(),
+            stack_height,
+        }
+    }
+
+    fn token_transfer(destination: u8, amount: u64, stack_height: u32) -> UiInstruction {
+        let data = spl_token::instruction::TokenInstruction::Transfer { amount }.pack();
+        UiInstruction::Compiled(instruction(
+            TOKEN_PROGRAM,
+            vec![SOURCE, destination, WALLET],
+            &data,
+            Some(stack_height),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_cpi_token_transfer() -> Result<()> {
+        let mut setup = Setup::new();
+        let complete = StakingInstruction::CompleteStakeUpdate.try_to_vec().unwrap();
+        // Another program completes the stake update through CPI, and makes transfers of its own around it
+        let signature = setup.add(
+            1,
+            vec![instruction(OTHER_PROGRAM, vec![], &[], None)],
+            vec![UiInnerInstructions {
+                index: 0,
+                instructions: vec![
+                    token_transfer(SOURCE, 1, 2),
+                    UiInstruction::Compiled(instruction(STAKING_PROGRAM, vec![SOURCE, WALLET], &complete, Some(2))),
+                    UiInstruction::Compiled(instruction(OTHER_PROGRAM, vec![], &[], Some(3))),
+                    token_transfer(SOURCE, 2, 4),
+                    token_transfer(DEPOSIT_VAULT, 3, 3),
+                    token_transfer(SOURCE, 4, 2),
+                ],
+            }],
+        );
+        let wallet = Pubkey::from_str(&setup.account_keys[WALLET as usize])?;
+        let (indexer, _, user_transactions, _) = setup.indexer(1, 10, 1);
+
+        indexer.run().await?;
+
+        let transactions = user_transactions.stored();
+        assert_eq!(transactions.len(), 1);
+        let transaction = &transactions[0];
+        assert_eq!(transaction.transaction_id, TransactionId(signature));
+        assert_eq!(transaction.wallet, wallet);
+        assert_eq!(transaction.transaction_type, TransactionType::DepositCompleted);
+        assert_eq!(transaction.amount, FPUSDC::from_usdc(3));
+        assert_eq!(transaction.instruction_index, 0);
+        assert_eq!(transaction.inner_instruction_index, Some(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_confirmed() -> Result<()> {
+        let mut setup = Setup::new();
+        let finalized = setup.add_request(1);
+        let confirmed = setup.add_request(2);
+        let (indexer, _, user_transactions, history) = setup.indexer(1, 10, 1);
+
+        indexer.run_confirmed().await?;
+        let transactions = user_transactions.stored();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].transaction_id, TransactionId(confirmed.clone()));
+        assert!(!transactions[0].finalized);
+
+        // The confirmed transaction is kept until it is finalized or its slot is
+        indexer.run().await?;
+        let transactions = user_transactions.stored();
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| (transaction.transaction_id.0.as_str(), transaction.finalized))
+                .collect::<Vec<_>>(),
+            vec![(finalized.as_str(), true), (confirmed.as_str(), false)]
+        );
+        assert_eq!(history.mem.read().unwrap().clone(), vec![TransactionId(finalized)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backfill() -> Result<()> {
+        let mut setup = Setup::new();
+        let signatures = (1..=6).map(|slot| setup.add_request(slot)).collect::<Vec<_>>();
+        let (indexer, _, user_transactions, history) = setup.indexer(6, 2, 2);
+
+        indexer.backfill(2, 4, None).await?;
+        let slots = |transactions: Vec<Transaction>| transactions.iter().map(|tx| tx.slot.unwrap()).collect::<Vec<_>>();
+        assert_eq!(slots(user_transactions.stored()), vec![2, 3, 4]);
+        // Backfills don't move the live indexing forward
+        assert!(history.mem.read().unwrap().is_empty());
+
+        // Resumed below the transaction of slot 4
+        user_transactions.mem.write().unwrap().clear();
+        let before = Signature::from_str(&signatures[3])?;
+        indexer.backfill(2, 4, Some(before)).await?;
+        assert_eq!(slots(user_transactions.stored()), vec![2, 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_concurrency() -> Result<()> {
+        let mut setup = Setup::new();
+        for slot in 1..=6 {
+            setup.add_request(slot);
+        }
+        let (indexer, rpc, user_transactions, history) = setup.indexer(6, 10, 2);
+
+        indexer.run().await?;
+
+        assert_eq!(rpc.max_in_flight.load(Ordering::SeqCst), 2);
+        assert_eq!(user_transactions.stored().len(), 6);
+        // Saved oldest first, whatever order they were fetched in
+        let saved = history.mem.read().unwrap().clone();
+        let expected = rpc
+            .signatures
+            .iter()
+            .rev()
+            .map(|tx| TransactionId(tx.signature.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(saved, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use envconfig::Envconfig;
+use indexer::indexer::accounts::pubsub::AccountIndexer;
+use service::{account_mirror::AccountMirror, solana::rpc::SolanaRpcReal};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use store::{program_accounts::PostgresProgramAccountRepository, DbConfig};
+
+#[derive(Envconfig, Clone)]
+pub struct AppConfig {
+    #[envconfig(from = "SOLANA_HTTP_RPC_URL", default = "https://api.mainnet-beta.solana.com")]
+    pub solana_http_rpc_url: String,
+
+    #[envconfig(from = "SOLANA_WS_RPC_URL")]
+    pub solana_ws_rpc_url: String,
+
+    #[envconfig(from = "SOLANA_STAKING_PROGRAM_ID")]
+    pub solana_staking_program_id: String,
+
+    #[envconfig(from = "INDEXER_ACCOUNTS_CHECK_INTERVAL_SECONDS", default = "300")]
+    pub indexer_accounts_check_interval_seconds: u64,
+
+    /// How often the websocket subscription is recorded as alive, the API stops serving the mirror after
+    /// `ACCOUNT_MIRROR_MAX_SUBSCRIPTION_SILENCE_SECONDS` without one.
+    #[envconfig(from = "INDEXER_ACCOUNTS_HEARTBEAT_INTERVAL_SECONDS", default = "15")]
+    pub indexer_accounts_heartbeat_interval_seconds: u64,
+
+    #[envconfig(from = "INDEXER_ACCOUNTS_RETRY_DELAY_SECONDS", default = "5")]
+    pub indexer_accounts_retry_delay_seconds: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv::dotenv();
+    env_logger::init();
+
+    let config: AppConfig = AppConfig::init_from_env()?;
+    let db_config: DbConfig = DbConfig::init_from_env()?;
+
+    store::migrations::run(&db_config);
+    let db_pool = store::connect(&db_config).await;
+
+    let staking_program_id = Pubkey::from_str(&config.solana_staking_program_id)?;
+    let rpc_client = Arc::new(RpcClient::new_with_commitment(
+        config.solana_http_rpc_url.clone(),
+        CommitmentConfig::confirmed(),
+    ));
+    let solana_rpc = Arc::new(SolanaRpcReal::new(
+        RpcClient::new_with_commitment(config.solana_http_rpc_url, CommitmentConfig::confirmed()),
+        staking_program_id,
+    ));
+    let mirror = AccountMirror::new(
+        staking_program_id,
+        Box::new(PostgresProgramAccountRepository::new(db_pool)),
+    );
+
+    let account_indexer = AccountIndexer::new(
+        rpc_client,
+        solana_rpc,
+        config.solana_ws_rpc_url,
+        staking_program_id,
+        mirror,
+        Duration::from_secs(config.indexer_accounts_check_interval_seconds),
+        Duration::from_secs(config.indexer_accounts_heartbeat_interval_seconds),
+        Duration::from_secs(config.indexer_accounts_retry_delay_seconds),
+    );
+
+    account_indexer.run().await
+}
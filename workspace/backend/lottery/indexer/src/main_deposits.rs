@@ -16,6 +16,9 @@ pub struct AppConfig {
     #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_URL")]
     pub nezha_graphql_url: String,
 
+    #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN")]
+    pub nezha_graphql_auth_token: String,
+
     #[envconfig(from = "SOLANA_WS_RPC_URL")]
     pub solana_ws_rpc_url: String,
 
@@ -54,6 +57,7 @@ async fn main() -> Result<()> {
     let pubsub_loop = {
         let program_id = Pubkey::from_str(&config.solana_program_id).unwrap();
         let nezha_graphql_url = config.nezha_graphql_url.clone();
+        let nezha_graphql_auth_token = config.nezha_graphql_auth_token.clone();
         let pubsub_retry_interval = Duration::from_millis(config.pubsub_retry_interval_ms);
         let cancelled = cancelled.clone();
         let pubsub_cfg = SolanaPubsubConfig {
@@ -73,7 +77,7 @@ async fn main() -> Result<()> {
                     }
                 }
 
-                let nezha_api = Arc::new(nezha_api::new(&nezha_graphql_url));
+                let nezha_api = Arc::new(nezha_api::new(&nezha_graphql_url, &nezha_graphql_auth_token));
                 let mut pubsub = SolanaPubsub::new(program_id, rpc_client.clone(), nezha_api);
                 last_connected = Some(SystemTime::now());
                 match pubsub.run(cancelled.clone(), &pubsub_cfg).await {
@@ -87,7 +91,10 @@ async fn main() -> Result<()> {
 
     let accept_deposits_loop = {
         let cancelled = cancelled.clone();
-        let nezha_api = Arc::new(nezha_api::new(&config.nezha_graphql_url));
+        let nezha_api = Arc::new(nezha_api::new(
+            &config.nezha_graphql_url,
+            &config.nezha_graphql_auth_token,
+        ));
         let poll_frequency = Duration::from_millis(config.approve_deposits_poll_freq_ms);
         let accept_deposits_cfg = AcceptDepositsConfig {
             batch_gap: Duration::from_millis(config.approve_deposits_batch_gap_ms),
@@ -111,7 +118,10 @@ async fn main() -> Result<()> {
 
     let gen_tickets_loop = {
         let cancelled = cancelled.clone();
-        let nezha_api = Box::new(nezha_api::new(&config.nezha_graphql_url));
+        let nezha_api = Box::new(nezha_api::new(
+            &config.nezha_graphql_url,
+            &config.nezha_graphql_auth_token,
+        ));
         let poll_frequency = Duration::from_millis(config.generate_tickets_poll_freq_ms);
 
         let gen_tickets = GenerateTickets { nezha_api };
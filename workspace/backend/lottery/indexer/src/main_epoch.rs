@@ -1,4 +1,4 @@
-use std::{str::FromStr, sync::Arc};
+use std::{fs, str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Result};
 use envconfig::Envconfig;
@@ -7,7 +7,7 @@ use indexer::{
         epoch::{
             artkai::{ArtkaiClient, ArtkaiUpdater, FakeArtkaiClient},
             rng::SequenceGenerator,
-            EpochIndexer, EpochJobScheduler, EpochJobSchedulerConfig, WinningCombinationSource,
+            EpochIndexer, EpochJobScheduler, EpochJobSchedulerConfig, WinningCombinationSource, EPOCH_INDEXER_LOCK_KEY,
         },
         util::SolanaProgramContext,
     },
@@ -15,15 +15,21 @@ use indexer::{
 };
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
-use service::solana::SwitchboardConfiguration;
+use service::{model::scenario::Scenario, solana::SwitchboardConfiguration};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{pubkey::Pubkey, signer::keypair::read_keypair};
+use store::{
+    advisory_lock::AdvisoryLock, epoch_jobs::PostgresEpochJobRepository, tickets::PostgresTicketRepository, DbConfig,
+};
 
 #[derive(Envconfig, Clone)]
 pub struct AppConfig {
     #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_URL")]
     pub nezha_graphql_url: String,
 
+    #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN")]
+    pub nezha_graphql_auth_token: String,
+
     #[envconfig(from = "INDEXER_EPOCH_START_SCHEDULE")]
     pub epoch_start_schedule: String,
 
@@ -39,6 +45,19 @@ pub struct AppConfig {
     #[envconfig(from = "INDEXER_EPOCH_PUBLISH_WINNERS_OFFSET_SECONDS")]
     pub epoch_publish_winners_offset_seconds: i64,
 
+    #[envconfig(from = "INDEXER_EPOCH_JOB_RETRY_BASE_DELAY_SECONDS", default = "30")]
+    pub epoch_job_retry_base_delay_seconds: i64,
+
+    #[envconfig(from = "INDEXER_EPOCH_JOB_RETRY_MAX_DELAY_SECONDS", default = "1800")]
+    pub epoch_job_retry_max_delay_seconds: i64,
+
+    /// Attempts after which a failing epoch job is given up and left to an operator.
+    #[envconfig(from = "INDEXER_EPOCH_JOB_MAX_ATTEMPTS", default = "10")]
+    pub epoch_job_max_attempts: u32,
+
+    #[envconfig(from = "INDEXER_EPOCH_LEADER_CHECK_SECONDS", default = "10")]
+    pub epoch_leader_check_seconds: u64,
+
     #[envconfig(from = "SOLANA_HTTP_RPC_URL", default = "https://api.mainnet-beta.solana.com")]
     pub solana_http_rpc_url: String,
 
@@ -93,6 +112,9 @@ pub struct AppConfig {
     #[envconfig(from = "WINNING_COMBINATION_SOURCE")]
     pub winning_combination_source: String,
 
+    #[envconfig(from = "SCENARIO_FILE")]
+    pub scenario_file: Option<String>,
+
     #[envconfig(from = "INVESTOR", default = "fake")]
     pub investor: String,
 
@@ -107,8 +129,15 @@ async fn main() -> Result<()> {
     env_logger::init();
 
     let config: AppConfig = AppConfig::init_from_env()?;
+    let db_config: DbConfig = DbConfig::init_from_env()?;
 
-    let nezha_api = Box::new(nezha_api::new(&config.nezha_graphql_url));
+    store::migrations::run(&db_config);
+    let db_pool = store::connect(&db_config).await;
+
+    let nezha_api = Box::new(nezha_api::new(
+        &config.nezha_graphql_url,
+        &config.nezha_graphql_auth_token,
+    ));
 
     let rpc_client = Arc::new(RpcClient::new(config.solana_http_rpc_url.clone()));
     let staking_program_id = Pubkey::from_str(&config.solana_staking_program_id)?;
@@ -148,6 +177,9 @@ async fn main() -> Result<()> {
         exit_investment_offset_seconds: config.epoch_exit_investment_offset_seconds,
         publish_winning_combination_offset_seconds: config.epoch_publish_winning_combination_offset_seconds,
         publish_winners_offset_seconds: config.epoch_publish_winners_offset_seconds,
+        retry_base_delay_seconds: config.epoch_job_retry_base_delay_seconds,
+        retry_max_delay_seconds: config.epoch_job_retry_max_delay_seconds,
+        max_attempts: config.epoch_job_max_attempts,
     };
 
     let scheduler = EpochJobScheduler::try_from(scheduler_config)?;
@@ -173,9 +205,14 @@ async fn main() -> Result<()> {
     let sequence_generator = SequenceGenerator::new(rng);
 
     let winning_combination_source = match config.winning_combination_source.as_str() {
-        "guaranteed_jackpot" => WinningCombinationSource::GuaranteedJackpot,
-        "optimal" => WinningCombinationSource::Optimal,
         "random" => WinningCombinationSource::Random,
+        "scenario" => {
+            let scenario_file = config.scenario_file.ok_or(anyhow!("Scenario file not configured"))?;
+            WinningCombinationSource::Scenario {
+                scenario: Scenario::from_json(&fs::read_to_string(&scenario_file)?)?,
+                tickets: Box::new(PostgresTicketRepository::new(db_pool.clone())),
+            }
+        }
         _ => return Err(anyhow!("Invalid winning combination source")),
     };
     let investor = config.investor.parse::<Investor>()?;
@@ -184,6 +221,7 @@ async fn main() -> Result<()> {
 
     let mut indexer = EpochIndexer::new(
         scheduler,
+        Box::new(PostgresEpochJobRepository::new(db_pool.clone())),
         nezha_api,
         context,
         prizes,
@@ -196,5 +234,13 @@ async fn main() -> Result<()> {
         switchboard,
     );
 
-    indexer.run_loop().await
+    // Only one replica runs the jobs. The others wait here and take over when its connection goes away.
+    let leader_check = Duration::from_secs(config.epoch_leader_check_seconds);
+    let lock = AdvisoryLock::acquire(&db_pool, EPOCH_INDEXER_LOCK_KEY, leader_check).await?;
+    log::info!("Acquired the epoch indexer lock");
+
+    tokio::select! {
+        res = indexer.run_loop() => res,
+        e = lock.lost(leader_check) => Err(e),
+    }
 }
@@ -20,6 +20,9 @@ pub struct AppConfig {
     #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_URL")]
     pub nezha_graphql_url: String,
 
+    #[envconfig(from = "INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN")]
+    pub nezha_graphql_auth_token: String,
+
     #[envconfig(from = "INDEXER_RISQ_FAKE", default = "false")]
     pub risq_fake: bool,
 
@@ -66,7 +69,10 @@ async fn main() -> Result<()> {
     let (risq_draw_routes, risq_entry_routes, risq_config) = make_risq_config(&config)?;
     let epoch_store: Box<dyn EpochStore + Send + Sync> = Box::new(PostgresEpochStore::new(db_pool.clone()));
 
-    let nezha_api = Box::new(nezha_api::new(&config.nezha_graphql_url));
+    let nezha_api = Box::new(nezha_api::new(
+        &config.nezha_graphql_url,
+        &config.nezha_graphql_auth_token,
+    ));
 
     let indexer = IndexerRisq {
         epoch_store,
@@ -0,0 +1,23 @@
+//! Plays a scenario against the staking program and the fake VRF and prints its payout report.
+//!
+//! Usage: `simulate_scenario <scenario.json>`
+use std::{env, fs};
+
+use anyhow::{anyhow, Result};
+use service::{model::scenario::Scenario, scenario::ScenarioRunner};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenv::dotenv();
+
+    env_logger::init();
+
+    let scenario_file = env::args()
+        .nth(1)
+        .ok_or(anyhow!("Usage: simulate_scenario <scenario.json>"))?;
+    let scenario = Scenario::from_json(&fs::read_to_string(&scenario_file)?)?;
+    let report = ScenarioRunner::new(scenario).await?.run().await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
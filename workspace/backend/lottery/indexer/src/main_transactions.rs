@@ -1,16 +1,24 @@
 use std::{str::FromStr, sync::Arc, time::Duration};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use diesel::{Connection, PgConnection};
 use envconfig::Envconfig;
 use indexer::indexer::util::SolanaProgramContext;
 use service::{
+    notification::{
+        FileTransport, NotificationTransport, NotificationWorker, Notifier, SmtpEmailTransport, WebhookTransport,
+    },
     prize::PrizeRepository,
     transaction::{TransactionHistoryRepository, UserTransactionRepository},
 };
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::read_keypair};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair, Signature},
+};
 use store::{
+    notifications::{PostgresNotificationDeliveryRepository, PostgresNotificationPreferencesRepository},
     prizes::PostgresPrizeRepository,
     transactions::{PostgresTransactionHistoryRepository, PostgresUserTransactionRepository},
     DbConfig,
@@ -39,11 +47,99 @@ pub struct AppConfig {
     #[envconfig(from = "INDEXER_TRANSACTIONS_BATCH_SIZE", default = "50")]
     pub indexer_transactions_batch_size: usize,
 
+    #[envconfig(from = "INDEXER_TRANSACTIONS_FETCH_CONCURRENCY", default = "8")]
+    pub indexer_transactions_fetch_concurrency: usize,
+
     #[envconfig(from = "TRANSACTION_MAX_QUERY_LIMIT", default = "100")]
     pub transaction_max_query_limit: i64,
 
     #[envconfig(from = "PRIZE_MAX_QUERY_LIMIT", default = "100")]
     pub prize_max_query_limit: i64,
+
+    /// "file" writes the notifications to NOTIFICATION_FILE, "live" emails them and calls the webhooks.
+    #[envconfig(from = "NOTIFICATION_TRANSPORT", default = "file")]
+    pub notification_transport: String,
+
+    #[envconfig(from = "NOTIFICATION_FILE", default = "notifications.jsonl")]
+    pub notification_file: String,
+
+    #[envconfig(from = "NOTIFICATION_WEBHOOK_TIMEOUT_SECONDS", default = "10")]
+    pub notification_webhook_timeout_seconds: u64,
+
+    /// Notifications sent at once.
+    #[envconfig(from = "NOTIFICATION_CONCURRENCY", default = "8")]
+    pub notification_concurrency: usize,
+
+    #[envconfig(from = "NOTIFICATION_MAX_ATTEMPTS", default = "8")]
+    pub notification_max_attempts: u32,
+
+    /// Delay before the first retry, doubled for every retry after.
+    #[envconfig(from = "NOTIFICATION_RETRY_DELAY_SECONDS", default = "30")]
+    pub notification_retry_delay_seconds: i64,
+
+    #[envconfig(from = "NOTIFICATION_POLL_INTERVAL_SECONDS", default = "5")]
+    pub notification_poll_interval_seconds: u64,
+
+    #[envconfig(from = "NOTIFICATION_EMAIL_FROM")]
+    pub notification_email_from: Option<String>,
+
+    #[envconfig(from = "SMTP_HOST")]
+    pub smtp_host: Option<String>,
+
+    #[envconfig(from = "SMTP_PORT", default = "587")]
+    pub smtp_port: u16,
+
+    #[envconfig(from = "SMTP_USERNAME")]
+    pub smtp_username: Option<String>,
+
+    #[envconfig(from = "SMTP_PASSWORD")]
+    pub smtp_password: Option<String>,
+}
+
+fn notification_transports(
+    config: &AppConfig,
+) -> Result<(Box<dyn NotificationTransport>, Box<dyn NotificationTransport>)> {
+    match config.notification_transport.as_str() {
+        "file" => {
+            let file = FileTransport::new(&config.notification_file);
+            Ok((Box::new(file.clone()), Box::new(file)))
+        }
+        "live" => {
+            let email = SmtpEmailTransport::new(
+                config.smtp_host.as_deref().context("SMTP_HOST is required")?,
+                config.smtp_port,
+                config.smtp_username.clone().context("SMTP_USERNAME is required")?,
+                config.smtp_password.clone().context("SMTP_PASSWORD is required")?,
+                config
+                    .notification_email_from
+                    .as_deref()
+                    .context("NOTIFICATION_EMAIL_FROM is required")?,
+            )?;
+            let webhook = WebhookTransport::new(Duration::from_secs(config.notification_webhook_timeout_seconds))?;
+            Ok((Box::new(email), Box::new(webhook)))
+        }
+        transport => bail!("Invalid NOTIFICATION_TRANSPORT: {}", transport),
+    }
+}
+
+/// `indexer_transactions backfill <start slot> <end slot> [<before signature>]` re-indexes the slots and exits.
+fn backfill_args() -> Result<Option<(u64, u64, Option<Signature>)>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (start_slot, end_slot, before) = match args.as_slice() {
+        [] => return Ok(None),
+        [command, start_slot, end_slot] if command == "backfill" => (start_slot, end_slot, None),
+        [command, start_slot, end_slot, before] if command == "backfill" => {
+            let before = Signature::from_str(before).context("Invalid before signature")?;
+            (start_slot, end_slot, Some(before))
+        }
+        _ => bail!("Usage: indexer_transactions [backfill <start slot> <end slot> [<before signature>]]"),
+    };
+    let start_slot = start_slot.parse().context("Invalid start slot")?;
+    let end_slot = end_slot.parse().context("Invalid end slot")?;
+    if start_slot > end_slot {
+        bail!("The start slot is after the end slot");
+    }
+    Ok(Some((start_slot, end_slot, before)))
 }
 
 #[tokio::main]
@@ -51,6 +147,8 @@ async fn main() -> Result<()> {
     let _ = dotenv::dotenv();
     env_logger::init();
 
+    let backfill_args = backfill_args()?;
+
     let config: AppConfig = AppConfig::init_from_env()?;
     let db_config: DbConfig = DbConfig::init_from_env()?;
 
@@ -66,6 +164,23 @@ async fn main() -> Result<()> {
         db_pool.clone(),
         config.prize_max_query_limit,
     ));
+    let (email_transport, webhook_transport) = notification_transports(&config)?;
+    let notifier = Notifier::new(
+        Box::new(PostgresNotificationPreferencesRepository::new(db_pool.clone())),
+        Box::new(PostgresNotificationDeliveryRepository::new(db_pool.clone())),
+    );
+    let notification_worker = NotificationWorker::new(
+        Box::new(PostgresNotificationPreferencesRepository::new(db_pool.clone())),
+        Box::new(PostgresNotificationDeliveryRepository::new(db_pool.clone())),
+        email_transport,
+        webhook_transport,
+        config.notification_concurrency,
+        config.notification_max_attempts,
+        chrono::Duration::seconds(config.notification_retry_delay_seconds),
+        // Deliveries claimed by a worker that stopped are sent again after this
+        chrono::Duration::minutes(5),
+    );
+    let notification_poll_interval = Duration::from_secs(config.notification_poll_interval_seconds);
     let rpc_client = Arc::new(RpcClient::new_with_commitment(
         config.solana_http_rpc_url,
         CommitmentConfig::finalized(),
@@ -90,12 +205,24 @@ async fn main() -> Result<()> {
         context,
         Duration::from_secs(config.indexer_transactions_retry_delay_seconds),
         config.indexer_transactions_batch_size,
+        config.indexer_transactions_fetch_concurrency,
         transaction_history_repository,
         user_transaction_repository,
         prize_repository,
+        notifier,
     );
 
-    transaction_indexer.run_loop().await?;
+    match backfill_args {
+        Some((start_slot, end_slot, before)) => transaction_indexer.backfill(start_slot, end_slot, before).await?,
+        None => {
+            let notification_loop =
+                tokio::spawn(async move { notification_worker.run_loop(notification_poll_interval).await });
+            tokio::select! {
+                result = transaction_indexer.run_loop() => result?,
+                result = notification_loop => result??,
+            }
+        }
+    }
 
     Ok(())
 }
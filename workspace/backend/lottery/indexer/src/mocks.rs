@@ -33,6 +33,8 @@ pub struct NezhaApiImpl {
     /// (user_pubkey, deposit_state)
     pub wallets: Arc<Mutex<Vec<(Pubkey, StakeUpdateState)>>>,
     pub tickets: Arc<Mutex<Vec<TicketExt>>>,
+    pub return_amounts: Arc<Mutex<Vec<Option<FPUSDC>>>>,
+    pub winning_combinations: Arc<Mutex<Vec<[u8; 6]>>>,
 }
 
 #[derive(Clone)]
@@ -48,6 +50,8 @@ impl NezhaApiImpl {
             latest_epoch: Arc::new(Mutex::new(None)),
             wallets: Arc::new(Mutex::new(Vec::new())),
             tickets: Arc::new(Mutex::new(Vec::new())),
+            return_amounts: Arc::new(Mutex::new(Vec::new())),
+            winning_combinations: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -65,7 +69,8 @@ impl NezhaApiImpl {
 #[async_trait]
 impl NezhaAPI for NezhaApiImpl {
     async fn publish_winning_combination(&self, combination: [u8; 6]) -> Result<()> {
-        unimplemented!()
+        self.winning_combinations.lock().unwrap().push(combination);
+        Ok(())
     }
     async fn get_latest_epoch(&self) -> Result<Option<APIEpoch>> {
         let latest_epoch = self.latest_epoch.lock().unwrap();
@@ -174,21 +179,14 @@ impl NezhaAPI for NezhaApiImpl {
         todo!()
     }
 
-    async fn exit_investment(&self, _investor: Investor, _yield_percent: Option<FPUSDC>) -> Result<()> {
-        todo!()
+    async fn exit_investment(&self, _investor: Investor, return_amount: Option<FPUSDC>) -> Result<()> {
+        self.return_amounts.lock().unwrap().push(return_amount);
+        Ok(())
     }
 
     async fn publish_winners(&self) -> Result<()> {
         todo!()
     }
-
-    async fn calculate_optimal_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-        todo!()
-    }
-
-    async fn random_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-        todo!()
-    }
 }
 
 #[derive(Clone)]
@@ -109,6 +109,8 @@ pub enum SequenceType {
     Normal,
     SignUpBonus,
     AirdropBonus,
+    UserPicked,
+    CampaignBonus,
 }
 
 #[derive(Clone, Debug)]
@@ -134,6 +136,11 @@ impl FromStr for Investor {
     }
 }
 
+/// The stake update request of the wallet waits for an operator in the review queue.
+#[derive(Debug, thiserror::Error)]
+#[error("Stake update of {0} is flagged for review")]
+pub struct FlaggedForReview(pub Pubkey);
+
 #[async_trait]
 pub trait NezhaAPI {
     async fn get_latest_epoch(&self) -> Result<Option<Epoch>>;
@@ -142,6 +149,7 @@ pub trait NezhaAPI {
     async fn get_unsubmitted_tickets(&self, epoch_index: u64) -> Result<Vec<Ticket>>;
     async fn update_risq_ids(&self, epoch_index: u64, risq_ids: &[WalletRisqId]) -> Result<()>;
     async fn all_stake_update_requests(&self) -> Result<Vec<StakeUpdateRequest>>;
+    /// Fails with [`FlaggedForReview`] if screening flagged the request.
     async fn approve_stake_update(&self, wallet: &Pubkey) -> Result<()>;
     async fn complete_stake_update(&self, wallet: &Pubkey) -> Result<()>;
     async fn create_epoch(
@@ -153,13 +161,12 @@ pub trait NezhaAPI {
     async fn enter_investment(&self, investor: Investor) -> Result<()>;
     async fn exit_investment(&self, investor: Investor, return_amount: Option<FPUSDC>) -> Result<()>;
     async fn publish_winners(&self) -> Result<()>;
-    async fn calculate_optimal_winning_combination(&self) -> Result<Option<[u8; 6]>>;
-    async fn random_winning_combination(&self) -> Result<Option<[u8; 6]>>;
     async fn publish_winning_combination(&self, combination: [u8; 6]) -> Result<()>;
 }
 
-pub fn new(url: &str) -> impl NezhaAPI {
-    graphql_impl::NezhaAPIImpl::new(url)
+/// `auth_token` is a service token generated by the api's `gen-token` binary.
+pub fn new(url: &str, auth_token: &str) -> impl NezhaAPI {
+    graphql_impl::NezhaAPIImpl::new(url, auth_token)
 }
 
 mod graphql_impl {
@@ -171,6 +178,7 @@ mod graphql_impl {
     use graphql_client::GraphQLQuery;
     use log::info;
     use reqwest::Client as HTTPClient;
+    use service::model::stake_update_review::FLAGGED_FOR_REVIEW_CODE;
 
     type DateTime = chrono::DateTime<chrono::Utc>;
     type WalletAddr = String;
@@ -313,6 +321,8 @@ mod graphql_impl {
                 unsubmitted_tickets::SequenceType::NORMAL => SequenceType::Normal,
                 unsubmitted_tickets::SequenceType::SIGN_UP_BONUS => SequenceType::SignUpBonus,
                 unsubmitted_tickets::SequenceType::AIRDROP_BONUS => SequenceType::AirdropBonus,
+                unsubmitted_tickets::SequenceType::USER_PICKED => SequenceType::UserPicked,
+                unsubmitted_tickets::SequenceType::CAMPAIGN_BONUS => SequenceType::CampaignBonus,
                 unsubmitted_tickets::SequenceType::Other(x) => {
                     Err(anyhow::anyhow!("Unexpected value for SequenceType: {}", x))?
                 }
@@ -362,6 +372,8 @@ mod graphql_impl {
                 generate_ticket::SequenceType::NORMAL => SequenceType::Normal,
                 generate_ticket::SequenceType::SIGN_UP_BONUS => SequenceType::SignUpBonus,
                 generate_ticket::SequenceType::AIRDROP_BONUS => SequenceType::AirdropBonus,
+                generate_ticket::SequenceType::USER_PICKED => SequenceType::UserPicked,
+                generate_ticket::SequenceType::CAMPAIGN_BONUS => SequenceType::CampaignBonus,
                 generate_ticket::SequenceType::Other(x) => {
                     Err(anyhow::anyhow!("Unexpected value for SequenceType: {}", x))?
                 }
@@ -472,30 +484,24 @@ mod graphql_impl {
     )]
     struct PublishWinners;
 
-    #[derive(GraphQLQuery)]
-    #[graphql(
-        schema_path = "src/nezha_api/schema.graphql",
-        query_path = "src/nezha_api/query.graphql"
-    )]
-    struct CalculateOptimalWinningCombination;
-
-    #[derive(GraphQLQuery)]
-    #[graphql(
-        schema_path = "src/nezha_api/schema.graphql",
-        query_path = "src/nezha_api/query.graphql"
-    )]
-    struct RandomWinningCombination;
-
     pub struct NezhaAPIImpl {
         url: String,
         client: HTTPClient,
     }
 
     impl NezhaAPIImpl {
-        pub fn new(url: &str) -> Self {
+        pub fn new(url: &str, auth_token: &str) -> Self {
+            let mut headers = reqwest::header::HeaderMap::new();
+            let mut authorization =
+                reqwest::header::HeaderValue::from_str(&format!("Bearer {}", auth_token)).expect("Invalid auth token");
+            authorization.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, authorization);
             Self {
                 url: url.to_owned(),
-                client: reqwest::Client::new(),
+                client: reqwest::Client::builder()
+                    .default_headers(headers)
+                    .build()
+                    .expect("Failed to build HTTP client"),
             }
         }
     }
@@ -656,6 +662,17 @@ mod graphql_impl {
             )
             .await?;
 
+            let flagged = res.errors.iter().flatten().any(|error| {
+                error
+                    .extensions
+                    .as_ref()
+                    .and_then(|extensions| extensions.get("code"))
+                    .and_then(|code| code.as_str())
+                    == Some(FLAGGED_FOR_REVIEW_CODE)
+            });
+            if flagged {
+                return Err(FlaggedForReview(*wallet).into());
+            }
             assert_no_errors("approve_stake_update", &res.errors)?;
 
             Ok(())
@@ -746,56 +763,6 @@ mod graphql_impl {
             Ok(())
         }
 
-        async fn calculate_optimal_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-            let res = graphql_client::reqwest::post_graphql::<CalculateOptimalWinningCombination, _>(
-                &self.client,
-                &self.url,
-                calculate_optimal_winning_combination::Variables {},
-            )
-            .await?;
-
-            assert_no_errors("calculate_optimal_winning_combination", &res.errors)?;
-
-            let data = res.data.with_context(|| "Response data was null")?;
-            match data.calculate_optimal_winning_combination {
-                Some(sequence) => {
-                    let winning_combination: [u8; 6] = sequence
-                        .into_iter()
-                        .map(|n| n.try_into().map_err(|error: <u8 as TryFrom<i64>>::Error| error.into()))
-                        .collect::<Result<Vec<u8>>>()?
-                        .try_into()
-                        .map_err(|_| anyhow!("Cannot convert optimal winning combination to [u8; 6]"))?;
-                    Ok(Some(winning_combination))
-                }
-                None => Ok(None),
-            }
-        }
-
-        async fn random_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-            let res = graphql_client::reqwest::post_graphql::<RandomWinningCombination, _>(
-                &self.client,
-                &self.url,
-                random_winning_combination::Variables {},
-            )
-            .await?;
-
-            assert_no_errors("random_winning_combination", &res.errors)?;
-
-            let data = res.data.with_context(|| "Response data was null")?;
-            Ok(match data.random_winning_combination {
-                Some(sequence) => {
-                    let winning_combination: [u8; 6] = sequence
-                        .into_iter()
-                        .map(|n| n.try_into().map_err(|error: <u8 as TryFrom<i64>>::Error| error.into()))
-                        .collect::<Result<Vec<u8>>>()?
-                        .try_into()
-                        .map_err(|_| anyhow!("Cannot convert optimal winning combination to [u8; 6]"))?;
-                    Some(winning_combination)
-                }
-                None => None,
-            })
-        }
-
         async fn publish_winning_combination(&self, combination: [u8; 6]) -> Result<()> {
             let winning_combination: Vec<i64> = combination.into_iter().map(|i| i.try_into().unwrap()).collect();
             info!("attempting to publish winning combination {:?}", combination);
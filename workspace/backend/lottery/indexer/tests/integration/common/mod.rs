@@ -78,6 +78,12 @@ pub fn setup_pubsub() -> ProgramSubscription {
     solana_pubsub
 }
 
+/// Authenticates with an indexer token generated by the api's `gen-token` binary, read from
+/// `INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN`.
 pub fn setup_api() -> Box<impl NezhaAPI> {
-    Box::new(nezha_api::new(INDEXER_NEZHA_GRAPHQL_URL))
+    let _ = dotenv::dotenv();
+
+    let auth_token =
+        std::env::var("INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN").expect("INDEXER_NEZHA_GRAPHQL_AUTH_TOKEN is not set");
+    Box::new(nezha_api::new(INDEXER_NEZHA_GRAPHQL_URL, &auth_token))
 }
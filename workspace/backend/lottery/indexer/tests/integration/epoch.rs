@@ -8,6 +8,7 @@ use indexer::{
     nezha_api::{DrawEnabled, EpochStatus, Investor, TieredPrizes, YieldSplitCfg},
 };
 use rand::thread_rng;
+use service::epoch::InMemoryEpochJobRepository;
 use solana_sdk::signer::{keypair::Keypair, Signer};
 use spl_token::ui_amount_to_amount;
 
@@ -35,6 +36,9 @@ async fn test_epoch_indexer() -> Result<()> {
         exit_investment_offset_seconds: 20,
         publish_winning_combination_offset_seconds: 30,
         publish_winners_offset_seconds: 40,
+        retry_base_delay_seconds: 1,
+        retry_max_delay_seconds: 10,
+        max_attempts: 3,
     };
     let scheduler = EpochJobScheduler::try_from(config).unwrap();
     let nezha_api = setup_api();
@@ -59,6 +63,7 @@ async fn test_epoch_indexer() -> Result<()> {
 
     let mut indexer = EpochIndexer::new(
         scheduler,
+        Box::new(InMemoryEpochJobRepository::default()),
         nezha_api,
         context.clone(),
         prizes,
@@ -75,7 +80,7 @@ async fn test_epoch_indexer() -> Result<()> {
 
     // run a whole epoch cycle
     loop {
-        let job = indexer.next_job().await.unwrap();
+        let job = indexer.next_job().await.unwrap().unwrap();
 
         indexer.run_job(&job).await.unwrap();
 
@@ -144,6 +149,9 @@ async fn test_fund_prize() -> Result<()> {
         exit_investment_offset_seconds: 20,
         publish_winning_combination_offset_seconds: 30,
         publish_winners_offset_seconds: 40,
+        retry_base_delay_seconds: 1,
+        retry_max_delay_seconds: 10,
+        max_attempts: 3,
     };
     let scheduler = EpochJobScheduler::try_from(config)?;
     let nezha_api = setup_api();
@@ -168,6 +176,7 @@ async fn test_fund_prize() -> Result<()> {
 
     let mut indexer = EpochIndexer::new(
         scheduler,
+        Box::new(InMemoryEpochJobRepository::default()),
         nezha_api,
         context.clone(),
         prizes,
@@ -184,7 +193,7 @@ async fn test_fund_prize() -> Result<()> {
     let user_pubkey = user_keypair.pubkey();
 
     loop {
-        let job = indexer.next_job().await?;
+        let job = indexer.next_job().await?.unwrap();
         if let EpochCommand::EnterInvestment = job.command {
             break;
         } else {
@@ -0,0 +1,239 @@
+//! Postgres mirror of the staking program accounts, so that reads don't scan the whole program.
+//!
+//! The accounts indexer applies account updates as they are pushed over the websocket, and periodically reconciles
+//! the mirror with a full scan to catch missed updates. `SolanaRpcMirrored` serves the scans from the mirror.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use borsh::BorshDeserialize;
+use chrono::{DateTime, Utc};
+use nezha_staking::state::AccountType;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account as SolanaAccount;
+
+use crate::{
+    model::program_account::{MirroredAccountType, ProgramAccount},
+    solana::WithPubkey,
+};
+
+mod repository;
+pub use repository::*;
+
+#[async_trait]
+pub trait ProgramAccountRepository: Sync + Send {
+    async fn by_type(&self, account_type: MirroredAccountType) -> Result<Vec<ProgramAccount>>;
+    /// Accounts older than the stored ones are ignored.
+    async fn upsert(&self, accounts: &[ProgramAccount]) -> Result<()>;
+    /// Removes the account, unless it was seen after the slot.
+    async fn remove(&self, pubkey: &Pubkey, slot: u64) -> Result<()>;
+    /// Records that the mirror was reconciled with the chain.
+    async fn checked(&self, now: DateTime<Utc>) -> Result<()>;
+    async fn last_checked(&self) -> Result<Option<DateTime<Utc>>>;
+    /// Records that the account updates were still being pushed over the websocket. Only recorded once the mirror was
+    /// checked.
+    async fn subscribed(&self, now: DateTime<Utc>) -> Result<()>;
+    async fn last_subscribed(&self) -> Result<Option<DateTime<Utc>>>;
+}
+
+pub struct AccountMirror {
+    program_id: Pubkey,
+    repository: Box<dyn ProgramAccountRepository>,
+}
+
+impl AccountMirror {
+    pub fn new(program_id: Pubkey, repository: Box<dyn ProgramAccountRepository>) -> Self {
+        Self { program_id, repository }
+    }
+
+    /// Applies an account update seen at the slot. Closed accounts are removed.
+    pub async fn apply(&self, pubkey: &Pubkey, account: &SolanaAccount, slot: u64) -> Result<()> {
+        if account.owner != self.program_id || account.lamports == 0 || account.data.is_empty() {
+            return self.repository.remove(pubkey, slot).await;
+        }
+        let account_type = match AccountType::deserialize(&mut &account.data[..1])
+            .ok()
+            .and_then(|account_type| MirroredAccountType::try_from(account_type).ok())
+        {
+            Some(account_type) => account_type,
+            None => return Ok(()),
+        };
+        self.repository
+            .upsert(&[ProgramAccount {
+                pubkey: *pubkey,
+                account_type,
+                lamports: account.lamports,
+                data: account.data.clone(),
+                slot,
+            }])
+            .await
+    }
+
+    /// Makes the mirrored accounts of the type match `accounts`, the result of a scan done before `slot`.
+    /// Accounts updated after the slot are left alone. Returns how many accounts were out of sync.
+    pub async fn reconcile(
+        &self,
+        account_type: MirroredAccountType,
+        accounts: &[WithPubkey<SolanaAccount>],
+        slot: u64,
+    ) -> Result<usize> {
+        let mirrored: HashMap<Pubkey, ProgramAccount> = self
+            .repository
+            .by_type(account_type)
+            .await?
+            .into_iter()
+            .map(|account| (account.pubkey, account))
+            .collect();
+
+        let stale = accounts
+            .iter()
+            .filter(|account| match mirrored.get(&account.pubkey) {
+                Some(mirrored) => {
+                    mirrored.slot <= slot && (mirrored.data != account.data || mirrored.lamports != account.lamports)
+                }
+                None => true,
+            })
+            .map(|account| ProgramAccount {
+                pubkey: account.pubkey,
+                account_type,
+                lamports: account.lamports,
+                data: account.data.clone(),
+                slot,
+            })
+            .collect::<Vec<_>>();
+
+        let live: HashSet<Pubkey> = accounts.iter().map(|account| account.pubkey).collect();
+        let closed = mirrored
+            .values()
+            .filter(|account| account.slot <= slot && !live.contains(&account.pubkey))
+            .map(|account| account.pubkey)
+            .collect::<Vec<_>>();
+
+        self.repository.upsert(&stale).await?;
+        for pubkey in &closed {
+            self.repository.remove(pubkey, slot).await?;
+        }
+
+        let out_of_sync = stale.len() + closed.len();
+        if out_of_sync > 0 {
+            log::warn!(
+                "{} {} account(s) were out of sync with the chain",
+                out_of_sync,
+                account_type.to_string()
+            );
+        }
+        Ok(out_of_sync)
+    }
+
+    pub async fn checked(&self, now: DateTime<Utc>) -> Result<()> {
+        self.repository.checked(now).await
+    }
+
+    pub async fn subscribed(&self, now: DateTime<Utc>) -> Result<()> {
+        self.repository.subscribed(now).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+
+    use super::*;
+
+    fn account(program_id: Pubkey, account_type: AccountType, value: u8) -> SolanaAccount {
+        let mut data = account_type.try_to_vec().unwrap();
+        data.push(value);
+        SolanaAccount {
+            lamports: 1_000_000,
+            data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let program_id = Pubkey::new_unique();
+        let repository = InMemoryProgramAccountRepository::default();
+        let mirror = AccountMirror::new(program_id, Box::new(repository.clone()));
+        let stake = Pubkey::new_unique();
+
+        mirror
+            .apply(&stake, &account(program_id, AccountType::Stake, 2), 20)
+            .await
+            .unwrap();
+        // Older update delivered late
+        mirror
+            .apply(&stake, &account(program_id, AccountType::Stake, 1), 10)
+            .await
+            .unwrap();
+        // Not mirrored
+        mirror
+            .apply(
+                &Pubkey::new_unique(),
+                &account(program_id, AccountType::LatestEpoch, 1),
+                20,
+            )
+            .await
+            .unwrap();
+
+        let stakes = repository.by_type(MirroredAccountType::Stake).await.unwrap();
+        assert_eq!(stakes.len(), 1);
+        assert_eq!(stakes[0].data, account(program_id, AccountType::Stake, 2).data);
+        assert_eq!(stakes[0].slot, 20);
+
+        let closed = SolanaAccount {
+            lamports: 0,
+            data: Vec::new(),
+            ..account(Pubkey::default(), AccountType::Stake, 0)
+        };
+        mirror.apply(&stake, &closed, 30).await.unwrap();
+        assert!(repository.by_type(MirroredAccountType::Stake).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile() {
+        let program_id = Pubkey::new_unique();
+        let repository = InMemoryProgramAccountRepository::default();
+        let mirror = AccountMirror::new(program_id, Box::new(repository.clone()));
+        let (missed, changed, closed, recent) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        for (pubkey, slot) in [(changed, 10), (closed, 10), (recent, 60)] {
+            mirror
+                .apply(&pubkey, &account(program_id, AccountType::Stake, 1), slot)
+                .await
+                .unwrap();
+        }
+
+        let scanned = [missed, changed]
+            .into_iter()
+            .map(|pubkey| WithPubkey {
+                pubkey,
+                inner: account(program_id, AccountType::Stake, 2),
+            })
+            .collect::<Vec<_>>();
+        let out_of_sync = mirror
+            .reconcile(MirroredAccountType::Stake, &scanned, 50)
+            .await
+            .unwrap();
+        assert_eq!(out_of_sync, 3);
+
+        let mut stakes = repository.by_type(MirroredAccountType::Stake).await.unwrap();
+        stakes.sort_by_key(|account| account.pubkey);
+        let mut expected = vec![missed, changed, recent];
+        expected.sort();
+        assert_eq!(
+            stakes.iter().map(|account| account.pubkey).collect::<Vec<_>>(),
+            expected
+        );
+        let recent = stakes.iter().find(|account| account.pubkey == recent).unwrap();
+        assert_eq!(recent.slot, 60);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use solana_program::pubkey::Pubkey;
+
+use super::ProgramAccountRepository;
+use crate::model::program_account::{MirroredAccountType, ProgramAccount};
+
+#[derive(Clone, Default)]
+pub struct InMemoryProgramAccountRepository {
+    mem: Arc<RwLock<HashMap<Pubkey, ProgramAccount>>>,
+    last_checked: Arc<RwLock<Option<DateTime<Utc>>>>,
+    last_subscribed: Arc<RwLock<Option<DateTime<Utc>>>>,
+}
+
+#[async_trait]
+impl ProgramAccountRepository for InMemoryProgramAccountRepository {
+    async fn by_type(&self, account_type: MirroredAccountType) -> Result<Vec<ProgramAccount>> {
+        Ok(self
+            .mem
+            .read()
+            .unwrap()
+            .values()
+            .filter(|account| account.account_type == account_type)
+            .cloned()
+            .collect())
+    }
+
+    async fn upsert(&self, accounts: &[ProgramAccount]) -> Result<()> {
+        let mut mem = self.mem.write().unwrap();
+        for account in accounts {
+            if mem
+                .get(&account.pubkey)
+                .map_or(true, |existing| existing.slot <= account.slot)
+            {
+                mem.insert(account.pubkey, account.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, pubkey: &Pubkey, slot: u64) -> Result<()> {
+        let mut mem = self.mem.write().unwrap();
+        if mem.get(pubkey).map_or(false, |existing| existing.slot <= slot) {
+            mem.remove(pubkey);
+        }
+        Ok(())
+    }
+
+    async fn checked(&self, now: DateTime<Utc>) -> Result<()> {
+        *self.last_checked.write().unwrap() = Some(now);
+        Ok(())
+    }
+
+    async fn last_checked(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(*self.last_checked.read().unwrap())
+    }
+
+    async fn subscribed(&self, now: DateTime<Utc>) -> Result<()> {
+        if self.last_checked.read().unwrap().is_some() {
+            *self.last_subscribed.write().unwrap() = Some(now);
+        }
+        Ok(())
+    }
+
+    async fn last_subscribed(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(*self.last_subscribed.read().unwrap())
+    }
+}
@@ -0,0 +1,463 @@
+//! Promotional campaigns granting bonus sequences.
+//!
+//! Operators set up campaigns running over a range of epochs. Every running campaign a wallet is eligible for grants
+//! it sequences once for the epoch, up to the campaign's cap per wallet. Until the tickets are reconciled with the
+//! final balances of the epoch, tickets include what the campaigns would grant the current balance. The grants are
+//! recorded at reconciliation, so it's known which campaign granted which sequences.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use nezha_staking::fixed_point::FPUSDC;
+use solana_program::pubkey::Pubkey;
+use std::cmp::min;
+use uuid::Uuid;
+
+use crate::{
+    model::campaign::{Campaign, CampaignError, CampaignGrant, CampaignReward, NewCampaign, Referral},
+    tickets::TicketRepository,
+};
+
+mod repository;
+pub use repository::*;
+
+#[async_trait]
+pub trait CampaignRepository: Sync + Send {
+    async fn create(&self, campaign: &Campaign) -> Result<Campaign>;
+    async fn all(&self) -> Result<Vec<Campaign>>;
+    /// Active campaigns running in the epoch.
+    async fn running_by_epoch_index(&self, epoch_index: u64) -> Result<Vec<Campaign>>;
+    async fn set_active(&self, id: &Uuid, active: bool) -> Result<Option<Campaign>>;
+    /// Records the grant unless the campaign already granted the wallet sequences for the epoch. Returns `None` if it
+    /// did.
+    async fn create_grant(&self, grant: &CampaignGrant) -> Result<Option<CampaignGrant>>;
+    async fn grants_by_campaign(&self, campaign_id: &Uuid, limit: usize, offset: usize) -> Result<Vec<CampaignGrant>>;
+    async fn grants_by_wallet(&self, wallet: &Pubkey) -> Result<Vec<CampaignGrant>>;
+    async fn grants_by_wallet_and_epoch_index(&self, wallet: &Pubkey, epoch_index: u64) -> Result<Vec<CampaignGrant>>;
+    /// Sequences the campaign granted the wallet over all epochs.
+    async fn num_sequences_by_campaign_and_wallet(&self, campaign_id: &Uuid, wallet: &Pubkey) -> Result<u64>;
+    async fn referral_by_wallet(&self, wallet: &Pubkey) -> Result<Option<Referral>>;
+    /// Records the referral unless the wallet was already referred. Returns `None` if it was.
+    async fn create_referral(&self, referral: &Referral) -> Result<Option<Referral>>;
+}
+
+/// What campaigns know of a wallet when its ticket is generated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CampaignWallet {
+    pub wallet: Pubkey,
+    /// Balance the ticket is sized for.
+    pub balance: FPUSDC,
+    /// Normal sequences the balance is entitled to.
+    pub normal_sequences: u32,
+    /// Draws played before the epoch.
+    pub draws_played: u64,
+}
+
+#[async_trait]
+pub trait CampaignService: Sync + Send {
+    async fn campaigns(&self) -> Result<Vec<Campaign>>;
+    async fn create_campaign(&self, campaign: NewCampaign, created_by: &str) -> Result<Campaign>;
+    /// Pauses or resumes a campaign. Sequences it granted already stay on the tickets.
+    async fn set_active(&self, id: &Uuid, active: bool) -> Result<Campaign>;
+    async fn grants_by_campaign(&self, campaign_id: &Uuid, limit: usize, offset: usize) -> Result<Vec<CampaignGrant>>;
+    async fn grants_by_wallet(&self, wallet: &Pubkey) -> Result<Vec<CampaignGrant>>;
+    async fn referral(&self, wallet: &Pubkey) -> Result<Option<Referral>>;
+    /// Records that the wallet was referred by `referrer`, which must have played a draw. A wallet is referred at most
+    /// once.
+    async fn register_referral(&self, wallet: &Pubkey, referrer: &Pubkey) -> Result<Referral>;
+    /// Number of sequences all campaigns grant the wallet for the epoch: the recorded grants, and what the running
+    /// campaigns that didn't grant it sequences yet would grant it. Doesn't record anything.
+    async fn num_sequences(&self, epoch_index: u64, wallet: &CampaignWallet) -> Result<u32>;
+    /// Grants the wallet the sequences of every running campaign it's eligible for and didn't grant it sequences for
+    /// the epoch yet. Called with the final balance of the epoch, as grants don't change once recorded. Returns the
+    /// number of sequences all campaigns granted the wallet for the epoch.
+    async fn grant_sequences(&self, epoch_index: u64, wallet: &CampaignWallet) -> Result<u32>;
+}
+
+pub struct DefaultCampaignService {
+    repository: Box<dyn CampaignRepository>,
+    tickets: Box<dyn TicketRepository>,
+}
+
+impl DefaultCampaignService {
+    pub fn new(repository: Box<dyn CampaignRepository>, tickets: Box<dyn TicketRepository>) -> Self {
+        Self { repository, tickets }
+    }
+
+    /// Grants of the running campaigns the wallet is eligible for, that aren't `granted` yet.
+    async fn new_grants(
+        &self,
+        epoch_index: u64,
+        wallet: &CampaignWallet,
+        granted: &[CampaignGrant],
+    ) -> Result<Vec<CampaignGrant>> {
+        let mut grants = Vec::new();
+        for campaign in self.repository.running_by_epoch_index(epoch_index).await? {
+            if granted.iter().any(|grant| grant.campaign_id == campaign.id)
+                || !self.is_eligible(&campaign, wallet).await?
+            {
+                continue;
+            }
+            let mut num_sequences = campaign.reward.num_sequences(wallet.balance, wallet.normal_sequences);
+            if let Some(max_sequences) = campaign.max_sequences_per_wallet {
+                let num_granted = self
+                    .repository
+                    .num_sequences_by_campaign_and_wallet(&campaign.id, &wallet.wallet)
+                    .await?;
+                num_sequences = min(num_sequences as u64, (max_sequences as u64).saturating_sub(num_granted)) as u32;
+            }
+            if num_sequences == 0 {
+                continue;
+            }
+            grants.push(CampaignGrant {
+                campaign_id: campaign.id,
+                wallet: wallet.wallet,
+                epoch_index,
+                num_sequences,
+                granted_at: Utc::now(),
+            });
+        }
+        Ok(grants)
+    }
+
+    async fn is_eligible(&self, campaign: &Campaign, wallet: &CampaignWallet) -> Result<bool> {
+        let eligibility = &campaign.eligibility;
+        if eligibility
+            .min_stake
+            .map_or(false, |min_stake| wallet.balance < min_stake)
+            || (eligibility.new_users_only && wallet.draws_played > 0)
+            || eligibility
+                .min_draws_played
+                .map_or(false, |min_draws_played| wallet.draws_played < min_draws_played)
+        {
+            return Ok(false);
+        }
+        if eligibility.referred_only {
+            return Ok(self.repository.referral_by_wallet(&wallet.wallet).await?.is_some());
+        }
+        Ok(true)
+    }
+}
+
+fn validate(campaign: &NewCampaign) -> Result<(), CampaignError> {
+    let invalid = |reason: &str| Err(CampaignError::Invalid(reason.to_string()));
+    if campaign.name.trim().is_empty() {
+        return invalid("name is empty");
+    }
+    if campaign
+        .end_epoch
+        .map_or(false, |end_epoch| end_epoch < campaign.start_epoch)
+    {
+        return invalid("ends before it starts");
+    }
+    let no_reward = match campaign.reward {
+        CampaignReward::Constant(count) => count == 0,
+        CampaignReward::PerStake { amount } => amount == FPUSDC::zero(),
+        CampaignReward::Multiplier { percent } => percent == 0,
+    };
+    if no_reward || campaign.max_sequences_per_wallet == Some(0) {
+        return invalid("grants no sequences");
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl CampaignService for DefaultCampaignService {
+    async fn campaigns(&self) -> Result<Vec<Campaign>> {
+        self.repository.all().await
+    }
+
+    async fn create_campaign(&self, campaign: NewCampaign, created_by: &str) -> Result<Campaign> {
+        validate(&campaign)?;
+        self.repository
+            .create(&Campaign {
+                id: Uuid::new_v4(),
+                name: campaign.name.trim().to_string(),
+                start_epoch: campaign.start_epoch,
+                end_epoch: campaign.end_epoch,
+                eligibility: campaign.eligibility,
+                reward: campaign.reward,
+                max_sequences_per_wallet: campaign.max_sequences_per_wallet,
+                active: true,
+                created_by: created_by.to_string(),
+                created_at: Utc::now(),
+            })
+            .await
+    }
+
+    async fn set_active(&self, id: &Uuid, active: bool) -> Result<Campaign> {
+        Ok(self
+            .repository
+            .set_active(id, active)
+            .await?
+            .ok_or(CampaignError::NotFound(*id))?)
+    }
+
+    async fn grants_by_campaign(&self, campaign_id: &Uuid, limit: usize, offset: usize) -> Result<Vec<CampaignGrant>> {
+        self.repository.grants_by_campaign(campaign_id, limit, offset).await
+    }
+
+    async fn grants_by_wallet(&self, wallet: &Pubkey) -> Result<Vec<CampaignGrant>> {
+        self.repository.grants_by_wallet(wallet).await
+    }
+
+    async fn referral(&self, wallet: &Pubkey) -> Result<Option<Referral>> {
+        self.repository.referral_by_wallet(wallet).await
+    }
+
+    async fn register_referral(&self, wallet: &Pubkey, referrer: &Pubkey) -> Result<Referral> {
+        if wallet == referrer {
+            return Err(CampaignError::SelfReferral(*wallet).into());
+        }
+        // Anyone can claim any referrer, so only wallets that already play can refer others. A ticket of any epoch counts
+        if self.tickets.draws_played_by_wallet(referrer, u64::MAX).await? == 0 {
+            return Err(CampaignError::ReferrerNotPlaying(*referrer).into());
+        }
+        Ok(self
+            .repository
+            .create_referral(&Referral {
+                wallet: *wallet,
+                referrer: *referrer,
+                created_at: Utc::now(),
+            })
+            .await?
+            .ok_or(CampaignError::AlreadyReferred(*wallet))?)
+    }
+
+    async fn num_sequences(&self, epoch_index: u64, wallet: &CampaignWallet) -> Result<u32> {
+        let granted = self
+            .repository
+            .grants_by_wallet_and_epoch_index(&wallet.wallet, epoch_index)
+            .await?;
+        let grants = self.new_grants(epoch_index, wallet, &granted).await?;
+        Ok(granted.iter().chain(&grants).map(|grant| grant.num_sequences).sum())
+    }
+
+    async fn grant_sequences(&self, epoch_index: u64, wallet: &CampaignWallet) -> Result<u32> {
+        let granted = self
+            .repository
+            .grants_by_wallet_and_epoch_index(&wallet.wallet, epoch_index)
+            .await?;
+        for grant in self.new_grants(epoch_index, wallet, &granted).await? {
+            log::info!(
+                "Campaign {} grants {} sequences to {} in epoch {}",
+                grant.campaign_id,
+                grant.num_sequences,
+                wallet.wallet,
+                epoch_index
+            );
+            self.repository.create_grant(&grant).await?;
+        }
+
+        // Read again, grants may have been recorded concurrently
+        let granted = self
+            .repository
+            .grants_by_wallet_and_epoch_index(&wallet.wallet, epoch_index)
+            .await?;
+        Ok(granted.iter().map(|grant| grant.num_sequences).sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::campaign::CampaignEligibility,
+        tickets::{InMemoryTicketRepository, Sequence, SequenceType, Ticket},
+    };
+
+    fn fp(s: &str) -> FPUSDC {
+        s.parse().unwrap()
+    }
+
+    fn service() -> (DefaultCampaignService, InMemoryTicketRepository) {
+        let tickets = InMemoryTicketRepository::new(0);
+        let service = DefaultCampaignService::new(
+            Box::new(InMemoryCampaignRepository::default()),
+            Box::new(tickets.clone()),
+        );
+        (service, tickets)
+    }
+
+    /// A wallet that played a draw.
+    async fn player(tickets: &InMemoryTicketRepository) -> Pubkey {
+        let ticket = tickets
+            .create(&Ticket {
+                sequences: vec![Sequence {
+                    nums: [1, 2, 3, 4, 5, 6],
+                    sequence_type: SequenceType::Normal,
+                }],
+                ..Ticket::new_for_tests()
+            })
+            .await
+            .unwrap();
+        ticket.wallet
+    }
+
+    fn new_campaign(reward: CampaignReward) -> NewCampaign {
+        NewCampaign {
+            name: "Launch".to_string(),
+            start_epoch: 2,
+            end_epoch: Some(3),
+            eligibility: CampaignEligibility::default(),
+            reward,
+            max_sequences_per_wallet: None,
+        }
+    }
+
+    fn wallet(balance: &str, normal_sequences: u32, draws_played: u64) -> CampaignWallet {
+        CampaignWallet {
+            wallet: Pubkey::new_unique(),
+            balance: fp(balance),
+            normal_sequences,
+            draws_played,
+        }
+    }
+
+    #[test]
+    fn calculates_reward() {
+        let balance = fp("130");
+        assert_eq!(CampaignReward::Constant(3).num_sequences(balance, 5), 3);
+        assert_eq!(
+            CampaignReward::PerStake { amount: fp("50") }.num_sequences(balance, 5),
+            2
+        );
+        assert_eq!(CampaignReward::Multiplier { percent: 50 }.num_sequences(balance, 5), 2);
+
+        let reward = CampaignReward::Multiplier { percent: 150 };
+        assert_eq!(
+            CampaignReward::parse(reward.type_name(), &reward.value()).unwrap(),
+            reward
+        );
+    }
+
+    #[tokio::test]
+    async fn validates_campaigns() {
+        let (service, _) = service();
+        let invalid = [
+            NewCampaign {
+                name: " ".to_string(),
+                ..new_campaign(CampaignReward::Constant(1))
+            },
+            NewCampaign {
+                end_epoch: Some(1),
+                ..new_campaign(CampaignReward::Constant(1))
+            },
+            new_campaign(CampaignReward::Multiplier { percent: 0 }),
+            NewCampaign {
+                max_sequences_per_wallet: Some(0),
+                ..new_campaign(CampaignReward::Constant(1))
+            },
+        ];
+        for campaign in invalid {
+            let err = service.create_campaign(campaign, "ops").await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CampaignError>(),
+                Some(CampaignError::Invalid(_))
+            ));
+        }
+        assert!(service.campaigns().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn grants_sequences_once_per_epoch() {
+        let (service, _) = service();
+        let campaign = service
+            .create_campaign(new_campaign(CampaignReward::Constant(2)), "ops")
+            .await
+            .unwrap();
+        service
+            .create_campaign(new_campaign(CampaignReward::Multiplier { percent: 50 }), "ops")
+            .await
+            .unwrap();
+        let wallet = wallet("100", 4, 0);
+
+        // Not running yet
+        assert_eq!(service.grant_sequences(1, &wallet).await.unwrap(), 0);
+        // Follows the balance until granted
+        assert_eq!(service.num_sequences(2, &wallet).await.unwrap(), 4);
+        let doubled = CampaignWallet {
+            normal_sequences: 8,
+            ..wallet.clone()
+        };
+        assert_eq!(service.num_sequences(2, &doubled).await.unwrap(), 6);
+        assert!(service.grants_by_wallet(&wallet.wallet).await.unwrap().is_empty());
+
+        assert_eq!(service.grant_sequences(2, &wallet).await.unwrap(), 4);
+        assert_eq!(service.num_sequences(2, &doubled).await.unwrap(), 4);
+        assert_eq!(service.grant_sequences(2, &wallet).await.unwrap(), 4);
+        assert_eq!(service.grants_by_wallet(&wallet.wallet).await.unwrap().len(), 2);
+
+        service.set_active(&campaign.id, false).await.unwrap();
+        assert_eq!(service.grant_sequences(2, &wallet).await.unwrap(), 4);
+        assert_eq!(service.grant_sequences(3, &wallet).await.unwrap(), 2);
+        // Ended
+        assert_eq!(service.grant_sequences(4, &wallet).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn grants_eligible_wallets_up_to_cap() {
+        let (service, tickets) = service();
+        service
+            .create_campaign(
+                NewCampaign {
+                    eligibility: CampaignEligibility {
+                        min_stake: Some(fp("50")),
+                        min_draws_played: Some(3),
+                        referred_only: true,
+                        ..Default::default()
+                    },
+                    end_epoch: None,
+                    max_sequences_per_wallet: Some(5),
+                    ..new_campaign(CampaignReward::Constant(3))
+                },
+                "ops",
+            )
+            .await
+            .unwrap();
+
+        let low_stake = wallet("10", 0, 5);
+        let new_user = wallet("100", 4, 0);
+        let regular = wallet("100", 4, 5);
+        let referrer = player(&tickets).await;
+        for wallet in [&low_stake, &new_user, &regular] {
+            service.register_referral(&wallet.wallet, &referrer).await.unwrap();
+        }
+        let not_referred = wallet("100", 4, 5);
+
+        for wallet in [&low_stake, &new_user, &not_referred] {
+            assert_eq!(service.grant_sequences(2, wallet).await.unwrap(), 0);
+        }
+        assert_eq!(service.grant_sequences(2, &regular).await.unwrap(), 3);
+        assert_eq!(service.grant_sequences(3, &regular).await.unwrap(), 2);
+        assert_eq!(service.grant_sequences(4, &regular).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn registers_referrals_once() {
+        let (service, tickets) = service();
+        let wallet = Pubkey::new_unique();
+        let referrer = player(&tickets).await;
+
+        assert!(service.register_referral(&wallet, &wallet).await.is_err());
+        // Only players refer others
+        let err = service
+            .register_referral(&wallet, &Pubkey::new_unique())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CampaignError>(),
+            Some(CampaignError::ReferrerNotPlaying(_))
+        ));
+        service.register_referral(&wallet, &referrer).await.unwrap();
+        let err = service
+            .register_referral(&wallet, &player(&tickets).await)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CampaignError>(),
+            Some(CampaignError::AlreadyReferred(_))
+        ));
+        assert_eq!(service.referral(&wallet).await.unwrap().unwrap().referrer, referrer);
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use solana_program::pubkey::Pubkey;
+use uuid::Uuid;
+
+use super::CampaignRepository;
+use crate::model::campaign::{Campaign, CampaignGrant, Referral};
+
+#[derive(Clone, Default)]
+pub struct InMemoryCampaignRepository {
+    campaigns: Arc<RwLock<Vec<Campaign>>>,
+    grants: Arc<RwLock<Vec<CampaignGrant>>>,
+    referrals: Arc<RwLock<Vec<Referral>>>,
+}
+
+#[async_trait]
+impl CampaignRepository for InMemoryCampaignRepository {
+    async fn create(&self, campaign: &Campaign) -> Result<Campaign> {
+        self.campaigns.write().unwrap().push(campaign.clone());
+        Ok(campaign.clone())
+    }
+
+    async fn all(&self) -> Result<Vec<Campaign>> {
+        Ok(self.campaigns.read().unwrap().clone())
+    }
+
+    async fn running_by_epoch_index(&self, epoch_index: u64) -> Result<Vec<Campaign>> {
+        Ok(self
+            .campaigns
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|campaign| campaign.runs_in(epoch_index))
+            .cloned()
+            .collect())
+    }
+
+    async fn set_active(&self, id: &Uuid, active: bool) -> Result<Option<Campaign>> {
+        let mut campaigns = self.campaigns.write().unwrap();
+        Ok(campaigns
+            .iter_mut()
+            .find(|campaign| &campaign.id == id)
+            .map(|campaign| {
+                campaign.active = active;
+                campaign.clone()
+            }))
+    }
+
+    async fn create_grant(&self, grant: &CampaignGrant) -> Result<Option<CampaignGrant>> {
+        let mut grants = self.grants.write().unwrap();
+        if grants.iter().any(|existing| {
+            existing.campaign_id == grant.campaign_id
+                && existing.wallet == grant.wallet
+                && existing.epoch_index == grant.epoch_index
+        }) {
+            return Ok(None);
+        }
+        grants.push(grant.clone());
+        Ok(Some(grant.clone()))
+    }
+
+    async fn grants_by_campaign(&self, campaign_id: &Uuid, limit: usize, offset: usize) -> Result<Vec<CampaignGrant>> {
+        Ok(self
+            .grants
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|grant| &grant.campaign_id == campaign_id)
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn grants_by_wallet(&self, wallet: &Pubkey) -> Result<Vec<CampaignGrant>> {
+        Ok(self
+            .grants
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|grant| &grant.wallet == wallet)
+            .cloned()
+            .collect())
+    }
+
+    async fn grants_by_wallet_and_epoch_index(&self, wallet: &Pubkey, epoch_index: u64) -> Result<Vec<CampaignGrant>> {
+        Ok(self
+            .grants
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|grant| &grant.wallet == wallet && grant.epoch_index == epoch_index)
+            .cloned()
+            .collect())
+    }
+
+    async fn num_sequences_by_campaign_and_wallet(&self, campaign_id: &Uuid, wallet: &Pubkey) -> Result<u64> {
+        Ok(self
+            .grants
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|grant| &grant.campaign_id == campaign_id && &grant.wallet == wallet)
+            .map(|grant| grant.num_sequences as u64)
+            .sum())
+    }
+
+    async fn referral_by_wallet(&self, wallet: &Pubkey) -> Result<Option<Referral>> {
+        Ok(self
+            .referrals
+            .read()
+            .unwrap()
+            .iter()
+            .find(|referral| &referral.wallet == wallet)
+            .cloned())
+    }
+
+    async fn create_referral(&self, referral: &Referral) -> Result<Option<Referral>> {
+        let mut referrals = self.referrals.write().unwrap();
+        if referrals.iter().any(|existing| existing.wallet == referral.wallet) {
+            return Ok(None);
+        }
+        referrals.push(referral.clone());
+        Ok(Some(referral.clone()))
+    }
+}
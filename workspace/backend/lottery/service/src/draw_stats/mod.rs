@@ -0,0 +1,122 @@
+//! Per-epoch analytics of the tickets and prizes.
+//!
+//! Stats are aggregated by the repository. Once an epoch has ended its tickets don't change anymore, so its stats are
+//! cached. Indexing prizes of the epoch drops its cached stats, so they're computed again with the prizes.
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::model::{
+    draw_stats::{EpochStats, WinningCombination},
+    epoch::EpochStatus,
+};
+
+mod repository;
+pub use repository::*;
+
+#[async_trait]
+pub trait DrawStatsRepository: Sync + Send {
+    /// Aggregates the tickets and prizes of the epoch. Returns `None` if the epoch doesn't exist.
+    async fn compute_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>>;
+    async fn cached_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>>;
+    /// Caches the stats, replacing the ones cached for the epoch.
+    async fn cache(&self, stats: &EpochStats) -> Result<()>;
+    /// Winning combinations of the past epochs, the most recent first.
+    async fn winning_combinations(&self, limit: usize, offset: usize) -> Result<Vec<WinningCombination>>;
+}
+
+#[async_trait]
+pub trait DrawStatsService: Sync + Send {
+    /// Returns `None` if the epoch doesn't exist.
+    async fn epoch_stats(&self, epoch_index: u64) -> Result<Option<EpochStats>>;
+    /// Computes the stats of the epoch again, ignoring the cached ones.
+    async fn refresh_epoch_stats(&self, epoch_index: u64) -> Result<Option<EpochStats>>;
+    async fn winning_combinations(&self, limit: usize, offset: usize) -> Result<Vec<WinningCombination>>;
+}
+
+pub struct DefaultDrawStatsService {
+    repository: Box<dyn DrawStatsRepository>,
+}
+
+impl DefaultDrawStatsService {
+    pub fn new(repository: Box<dyn DrawStatsRepository>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait]
+impl DrawStatsService for DefaultDrawStatsService {
+    async fn epoch_stats(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        if let Some(stats) = self.repository.cached_by_epoch_index(epoch_index).await? {
+            return Ok(Some(stats));
+        }
+        self.refresh_epoch_stats(epoch_index).await
+    }
+
+    async fn refresh_epoch_stats(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        let stats = match self.repository.compute_by_epoch_index(epoch_index).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+        if stats.status == EpochStatus::Ended {
+            self.repository.cache(&stats).await?;
+        }
+        Ok(Some(stats))
+    }
+
+    async fn winning_combinations(&self, limit: usize, offset: usize) -> Result<Vec<WinningCombination>> {
+        self.repository.winning_combinations(limit, offset).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::draw_stats::{SequenceTypeCount, TierPrizes},
+        tickets::SequenceType,
+    };
+    use chrono::Utc;
+
+    fn stats(epoch_index: u64, status: EpochStatus, num_players: u64) -> EpochStats {
+        EpochStats {
+            epoch_index,
+            status,
+            num_players,
+            sequences_by_type: vec![SequenceTypeCount {
+                sequence_type: SequenceType::Normal,
+                num_sequences: num_players * 2,
+            }],
+            main_number_frequencies: vec![],
+            last_number_frequencies: vec![],
+            shared_prefixes: vec![],
+            prizes_by_tier: vec![],
+            computed_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_stats_of_ended_epochs() {
+        let repository = InMemoryDrawStatsRepository::default();
+        let service = DefaultDrawStatsService::new(Box::new(repository.clone()));
+        assert_eq!(service.epoch_stats(1).await.unwrap(), None);
+
+        repository.set_computed(stats(1, EpochStatus::Running, 3));
+        assert_eq!(service.epoch_stats(1).await.unwrap().unwrap().num_players, 3);
+        repository.set_computed(stats(1, EpochStatus::Ended, 4));
+        assert_eq!(service.epoch_stats(1).await.unwrap().unwrap().num_players, 4);
+
+        // Ended, so the cached stats are returned
+        let mut late_prizes = stats(1, EpochStatus::Ended, 4);
+        late_prizes.prizes_by_tier = vec![TierPrizes {
+            tier: 3,
+            num_winners: 1,
+            total_amount: "10".parse().unwrap(),
+        }];
+        repository.set_computed(late_prizes.clone());
+        assert!(service.epoch_stats(1).await.unwrap().unwrap().prizes_by_tier.is_empty());
+
+        assert_eq!(service.refresh_epoch_stats(1).await.unwrap(), Some(late_prizes.clone()));
+        assert_eq!(service.epoch_stats(1).await.unwrap(), Some(late_prizes));
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::DrawStatsRepository;
+use crate::model::draw_stats::{EpochStats, WinningCombination};
+
+/// The stats it computes are set with [`InMemoryDrawStatsRepository::set_computed`].
+#[derive(Clone, Default)]
+pub struct InMemoryDrawStatsRepository {
+    computed: Arc<RwLock<Vec<EpochStats>>>,
+    cached: Arc<RwLock<Vec<EpochStats>>>,
+    winning_combinations: Arc<RwLock<Vec<WinningCombination>>>,
+}
+
+impl InMemoryDrawStatsRepository {
+    pub fn set_computed(&self, stats: EpochStats) {
+        let mut computed = self.computed.write().unwrap();
+        computed.retain(|existing| existing.epoch_index != stats.epoch_index);
+        computed.push(stats);
+    }
+
+    pub fn add_winning_combination(&self, winning_combination: WinningCombination) {
+        self.winning_combinations.write().unwrap().push(winning_combination);
+    }
+}
+
+#[async_trait]
+impl DrawStatsRepository for InMemoryDrawStatsRepository {
+    async fn compute_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        Ok(self
+            .computed
+            .read()
+            .unwrap()
+            .iter()
+            .find(|stats| stats.epoch_index == epoch_index)
+            .cloned())
+    }
+
+    async fn cached_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        Ok(self
+            .cached
+            .read()
+            .unwrap()
+            .iter()
+            .find(|stats| stats.epoch_index == epoch_index)
+            .cloned())
+    }
+
+    async fn cache(&self, stats: &EpochStats) -> Result<()> {
+        let mut cached = self.cached.write().unwrap();
+        cached.retain(|existing| existing.epoch_index != stats.epoch_index);
+        cached.push(stats.clone());
+        Ok(())
+    }
+
+    async fn winning_combinations(&self, limit: usize, offset: usize) -> Result<Vec<WinningCombination>> {
+        let mut winning_combinations = self.winning_combinations.read().unwrap().clone();
+        winning_combinations.sort_by(|a, b| b.epoch_index.cmp(&a.epoch_index));
+        Ok(winning_combinations.into_iter().skip(offset).take(limit).collect())
+    }
+}
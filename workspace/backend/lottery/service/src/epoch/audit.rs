@@ -0,0 +1,33 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::EpochAuditRepository;
+use crate::model::epoch_audit::EpochAuditLog;
+
+#[derive(Clone, Default)]
+pub struct InMemoryEpochAuditRepository {
+    mem: Arc<RwLock<Vec<EpochAuditLog>>>,
+}
+
+#[async_trait]
+impl EpochAuditRepository for InMemoryEpochAuditRepository {
+    async fn record(&self, entry: &EpochAuditLog) -> Result<()> {
+        self.mem.write().unwrap().push(entry.clone());
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochAuditLog>> {
+        Ok(self
+            .mem
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
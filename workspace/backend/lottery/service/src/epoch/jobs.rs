@@ -0,0 +1,92 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::EpochJobRepository;
+use crate::model::epoch_job::{EpochJob, EpochJobCommand, EpochJobStatus};
+
+#[derive(Clone, Default)]
+pub struct InMemoryEpochJobRepository {
+    mem: Arc<RwLock<Vec<EpochJob>>>,
+}
+
+impl InMemoryEpochJobRepository {
+    fn update(&self, epoch_index: u64, command: EpochJobCommand, f: impl FnOnce(&mut EpochJob)) -> Result<EpochJob> {
+        let mut mem = self.mem.write().unwrap();
+        let job = mem
+            .iter_mut()
+            .find(|job| job.epoch_index == epoch_index && job.command == command)
+            .ok_or_else(|| anyhow!("Epoch job {:?} for epoch {} not found", command, epoch_index))?;
+        f(job);
+        job.updated_at = Utc::now();
+        Ok(job.clone())
+    }
+}
+
+#[async_trait]
+impl EpochJobRepository for InMemoryEpochJobRepository {
+    async fn enqueue(&self, epoch_index: u64, command: EpochJobCommand, due_at: DateTime<Utc>) -> Result<EpochJob> {
+        let mut mem = self.mem.write().unwrap();
+        if let Some(job) = mem
+            .iter()
+            .find(|job| job.epoch_index == epoch_index && job.command == command)
+        {
+            return Ok(job.clone());
+        }
+        let now = Utc::now();
+        let job = EpochJob {
+            epoch_index,
+            command,
+            status: EpochJobStatus::Pending,
+            attempts: 0,
+            last_error: None,
+            due_at,
+            next_attempt_at: due_at,
+            created_at: now,
+            updated_at: now,
+        };
+        mem.push(job.clone());
+        Ok(job)
+    }
+
+    async fn start(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob> {
+        self.update(epoch_index, command, |job| {
+            job.status = EpochJobStatus::Running;
+            job.attempts += 1;
+        })
+    }
+
+    async fn succeed(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob> {
+        self.update(epoch_index, command, |job| {
+            job.status = EpochJobStatus::Succeeded;
+            job.last_error = None;
+        })
+    }
+
+    async fn fail(
+        &self,
+        epoch_index: u64,
+        command: EpochJobCommand,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<EpochJob> {
+        self.update(epoch_index, command, |job| {
+            job.last_error = Some(error.to_string());
+            match next_attempt_at {
+                Some(next_attempt_at) => {
+                    job.status = EpochJobStatus::Pending;
+                    job.next_attempt_at = next_attempt_at;
+                }
+                None => job.status = EpochJobStatus::Failed,
+            }
+        })
+    }
+
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochJob>> {
+        let mut jobs = self.mem.read().unwrap().clone();
+        jobs.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(jobs.into_iter().skip(offset).take(limit).collect())
+    }
+}
@@ -1,5 +1,12 @@
 pub mod service;
 
+mod audit;
+mod jobs;
+mod repository;
+pub use audit::*;
+pub use jobs::*;
+pub use repository::*;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
@@ -10,9 +17,11 @@ use solana_program::pubkey::Pubkey;
 use crate::{
     model::{
         epoch::{Epoch, Investor, UseCache},
+        epoch_audit::{EpochAuditLog, EpochOperation},
+        epoch_job::{EpochJob, EpochJobCommand},
         winner::EpochWinners,
     },
-    solana::WalletPrize,
+    solana::{Simulation, WalletPrize},
     tickets::Winners,
 };
 
@@ -34,6 +43,39 @@ pub trait EpochRepository: Sync + Send {
     async fn create_or_update_epoch(&self, epoch: &Epoch) -> Result<Epoch>;
 }
 
+#[async_trait]
+pub trait EpochAuditRepository: Sync + Send {
+    async fn record(&self, entry: &EpochAuditLog) -> Result<()>;
+
+    /// Most recent entries first.
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochAuditLog>>;
+}
+
+/// Durable queue of the epoch indexer jobs, keyed by epoch index and command.
+#[async_trait]
+pub trait EpochJobRepository: Sync + Send {
+    /// Creates a pending job, unless one exists for the epoch index and command. Returns the stored job.
+    async fn enqueue(&self, epoch_index: u64, command: EpochJobCommand, due_at: DateTime<Utc>) -> Result<EpochJob>;
+
+    /// Marks the job as running and counts the attempt.
+    async fn start(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob>;
+
+    async fn succeed(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob>;
+
+    /// Records the failed attempt and reschedules the job at `next_attempt_at`, or marks it as failed for good if
+    /// there is none.
+    async fn fail(
+        &self,
+        epoch_index: u64,
+        command: EpochJobCommand,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<EpochJob>;
+
+    /// Most recently updated jobs first.
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochJob>>;
+}
+
 #[async_trait]
 pub trait EpochManager: Sync + Send {
     async fn epochs(&self) -> Result<Vec<Epoch>>;
@@ -53,13 +95,19 @@ pub trait EpochManager: Sync + Send {
 
     /// Creates a new epoch, provided the previous one has ended.
     /// The expected end date is for reference only and will be used as an identifier for the RISQ API Draw.
-    async fn create_epoch(&self, expected_end_date: DateTime<Utc>, yield_split_cfg: YieldSplitCfg) -> Result<Epoch>;
+    async fn create_epoch(
+        &self,
+        expected_end_date: DateTime<Utc>,
+        yield_split_cfg: YieldSplitCfg,
+        operator: &str,
+    ) -> Result<Epoch>;
 
     /// Withdraw yield to fake investor or Francium for the current epoch
-    async fn enter_investment(&self, investor: Investor) -> Result<Epoch>;
+    async fn enter_investment(&self, investor: Investor, operator: &str) -> Result<Epoch>;
 
     /// Return yield from fake investor or Francium back to vault for the current epoch
-    async fn exit_investment(&self, investor: Investor, return_amount: Option<FPUSDC>) -> Result<Epoch>;
+    async fn exit_investment(&self, investor: Investor, return_amount: Option<FPUSDC>, operator: &str)
+        -> Result<Epoch>;
 
     /// Used for testing, can't be called in release mode.
     async fn publish_winning_combination(&self, combination: &[u8; 6]) -> Result<Epoch>;
@@ -67,12 +115,25 @@ pub trait EpochManager: Sync + Send {
     /// Perform vrf request to generate the winning combination.
     async fn request_random_winning_combination(&self) -> Result<Epoch>;
 
+    /// Calculates the winners of the current epoch from the chain. Unlike the API's ticket service, this doesn't read
+    /// from the account mirror, so the winners are safe to publish.
+    async fn calculate_winners(&self) -> Result<Winners>;
+
     /// Publishes all the winners of the current epoch. This is called only after calculating winners
-    async fn publish_winners(&self, winners: Winners) -> Result<Epoch>;
+    async fn publish_winners(&self, winners: Winners, operator: &str) -> Result<Epoch>;
 
     /// Fetch entire history of prizes for this wallet.
     async fn wallet_prizes(&self, wallet: &Pubkey) -> Result<Vec<WalletPrize>>;
 
     /// Fund the jackpot winner
-    async fn fund_jackpot(&self) -> Result<()>;
+    async fn fund_jackpot(&self, operator: &str) -> Result<()>;
+
+    /// Simulates the operation against the current chain state without sending it.
+    async fn dry_run(&self, operation: EpochOperation, operator: &str) -> Result<Simulation>;
+
+    /// Operations sent or simulated by operators, most recent first.
+    async fn audit_log(&self, limit: usize, offset: usize) -> Result<Vec<EpochAuditLog>>;
+
+    /// Jobs of the epoch indexer, most recently updated first.
+    async fn jobs(&self, limit: usize, offset: usize) -> Result<Vec<EpochJob>>;
 }
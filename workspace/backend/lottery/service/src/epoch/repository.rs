@@ -0,0 +1,52 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use solana_program::pubkey::Pubkey;
+
+use super::EpochRepository;
+use crate::model::epoch::Epoch;
+
+#[derive(Clone, Default)]
+pub struct InMemoryEpochRepository {
+    epochs: Arc<RwLock<Vec<Epoch>>>,
+}
+
+impl InMemoryEpochRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EpochRepository for InMemoryEpochRepository {
+    async fn by_index(&self, index: u64) -> Result<Option<Epoch>> {
+        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
+        Ok(epochs.iter().find(|epoch| epoch.index == index).cloned())
+    }
+
+    async fn by_pubkey(&self, pubkey: &Pubkey) -> Result<Option<Epoch>> {
+        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
+        Ok(epochs.iter().find(|epoch| epoch.pubkey == *pubkey).cloned())
+    }
+
+    async fn all(&self) -> Result<Vec<Epoch>> {
+        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
+        Ok(epochs.clone())
+    }
+
+    async fn latest_epoch(&self) -> Result<Option<Epoch>> {
+        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
+        Ok(epochs.iter().max_by_key(|epoch| epoch.index).cloned())
+    }
+
+    async fn create_or_update_epoch(&self, epoch: &Epoch) -> Result<Epoch> {
+        let mut epochs = self.epochs.write().map_err(|_| anyhow!("cannot write epochs"))?;
+        let e = epochs.iter_mut().find(|e| e.index == epoch.index);
+        match e {
+            Some(e) => *e = epoch.clone(),
+            None => epochs.push(epoch.clone()),
+        };
+        Ok(epoch.clone())
+    }
+}
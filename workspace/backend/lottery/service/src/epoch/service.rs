@@ -8,22 +8,32 @@ use nezha_staking::{
     state::{EpochStatus, YieldSplitCfg},
 };
 use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use uuid::Uuid;
 
 use crate::{
+    events::{Event, EventBus},
     model::{
         epoch::{Epoch, EpochError, Investor, UseCache},
+        epoch_audit::{EpochAuditLog, EpochOperation},
+        epoch_job::EpochJob,
         winner::EpochWinners,
     },
-    solana::{AccountNotFound, Solana, SolanaError, WalletPrize},
-    tickets::{TicketRepository, Winners},
+    solana::{AccountNotFound, EpochInstruction, Simulation, Solana, SolanaError, WalletPrize},
+    tickets::{EpochManifest, ManifestPublisher, TicketRepository, TicketService, Winners},
 };
 
-use super::{EpochManager, EpochRepository};
+use super::{EpochAuditRepository, EpochJobRepository, EpochManager, EpochRepository};
 
 pub struct EpochService {
     pub solana: Box<dyn Solana>,
     pub repository: Box<dyn EpochRepository>,
     pub ticket_repository: Box<dyn TicketRepository>,
+    pub ticket_service: Box<dyn TicketService>,
+    pub manifest_publisher: Box<dyn ManifestPublisher>,
+    pub audit_repository: Box<dyn EpochAuditRepository>,
+    pub job_repository: Box<dyn EpochJobRepository>,
+    pub events: EventBus,
 }
 
 impl EpochService {
@@ -31,13 +41,243 @@ impl EpochService {
         solana: Box<dyn Solana>,
         repository: Box<dyn EpochRepository>,
         ticket_repository: Box<dyn TicketRepository>,
+        ticket_service: Box<dyn TicketService>,
+        manifest_publisher: Box<dyn ManifestPublisher>,
+        audit_repository: Box<dyn EpochAuditRepository>,
+        job_repository: Box<dyn EpochJobRepository>,
+        events: EventBus,
     ) -> Self {
         Self {
             solana,
             repository,
             ticket_repository,
+            ticket_service,
+            manifest_publisher,
+            audit_repository,
+            job_repository,
+            events,
         }
     }
+
+    async fn record(
+        &self,
+        operation: &EpochOperation,
+        operator: &str,
+        dry_run: bool,
+        signature: Option<String>,
+        error: Option<String>,
+    ) -> Result<()> {
+        self.audit_repository
+            .record(&EpochAuditLog {
+                id: Uuid::new_v4(),
+                operator: operator.to_owned(),
+                operation: operation.name().to_owned(),
+                params: operation.params(),
+                dry_run,
+                signature,
+                error,
+                created_at: Utc::now(),
+            })
+            .await
+    }
+
+    /// Records the outcome of the transactions sent for an operation. Failing to record it is only logged, since the
+    /// transactions were sent either way. An operation which had nothing left to send is recorded without signature.
+    async fn record_sent<S: Into<Option<Signature>> + Copy + Send>(
+        &self,
+        operation: &EpochOperation,
+        operator: &str,
+        result: Result<S, SolanaError>,
+    ) -> Result<S> {
+        let (signature, error) = match &result {
+            Ok(signature) => ((*signature).into().map(|signature| signature.to_string()), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        if let Err(e) = self.record(operation, operator, false, signature, error).await {
+            error!("Failed to record {} by {}: {:?}", operation.name(), operator, e);
+        }
+        Ok(result?)
+    }
+
+    async fn current_epoch(&self) -> Result<Epoch> {
+        Ok(self
+            .latest_epoch(UseCache::No)
+            .await?
+            .ok_or(EpochError::CouldNotReadLatestEpoch)?)
+    }
+
+    /// Manifest of the epoch's tickets, committed on chain when the epoch enters investment.
+    async fn manifest(&self, epoch_index: u64) -> Result<EpochManifest> {
+        let tickets = self.ticket_repository.by_epoch_index(epoch_index).await?;
+        EpochManifest::new(epoch_index, &tickets)
+    }
+
+    /// Generates the tickets of a newly created epoch. Derived tickets are deferred until the epoch has a seed, so
+    /// those of the stakes made in between are generated here. Failures are only logged, as the epoch was created.
+    async fn generate_tickets(&self) {
+        let tickets = match self.ticket_service.generate_tickets_for_all().await {
+            Ok(tickets) => tickets,
+            Err(e) => {
+                error!("Error generating tickets: {:?}", e);
+                return;
+            }
+        };
+        for ticket in tickets {
+            if let Err(e) = ticket {
+                error!("Error generating ticket: {:?}", e);
+            }
+        }
+    }
+
+    /// Sizes the tickets of the epoch for the balances held over it. Fails if any of them couldn't be reconciled, as
+    /// the manifest would commit them with the wrong sequences.
+    async fn reconcile_tickets(&self) -> Result<()> {
+        let mut failed = 0;
+        for ticket in self.ticket_service.reconcile_tickets().await? {
+            if let Err(e) = ticket {
+                error!("Error reconciling ticket: {:?}", e);
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            bail!("Could not reconcile {} ticket(s)", failed);
+        }
+        Ok(())
+    }
+
+    /// Resolves the operation against the current chain state.
+    async fn prepare(&self, operation: &EpochOperation) -> Result<EpochInstruction> {
+        Ok(match operation {
+            EpochOperation::CreateEpoch {
+                expected_end_date,
+                yield_split_cfg,
+            } => {
+                // If no epoch is found, we need to create the first one
+                let epoch_index = match self.latest_epoch(UseCache::No).await? {
+                    Some(epoch) => epoch.index + 1,
+                    None => 1,
+                };
+                EpochInstruction::CreateEpoch {
+                    epoch_index,
+                    expected_end_date: *expected_end_date,
+                    yield_split_cfg: yield_split_cfg.clone(),
+                }
+            }
+            EpochOperation::EnterInvestment { investor } => {
+                let epoch = self.current_epoch().await?;
+                if epoch.total_invested.is_some() {
+                    bail!("Epoch {} already entered investment", epoch.index);
+                }
+                // Dry runs don't upload the manifest, so it has no URL yet
+                let tickets_info = self.manifest(epoch.index).await?.tickets_info(String::new())?;
+                match investor {
+                    Investor::Fake => EpochInstruction::EnterInvestmentFake {
+                        epoch_index: epoch.index,
+                        tickets_info,
+                    },
+                    Investor::Francium => EpochInstruction::EnterInvestmentFrancium {
+                        epoch_index: epoch.index,
+                        tickets_info,
+                    },
+                }
+            }
+            EpochOperation::ExitInvestment {
+                investor,
+                return_amount,
+            } => {
+                let epoch = self.current_epoch().await?;
+                if epoch.returns.is_some() {
+                    bail!("Epoch {} already exited investment", epoch.index);
+                }
+                match investor {
+                    Investor::Fake => EpochInstruction::ExitInvestmentFake {
+                        epoch_index: epoch.index,
+                        amount: return_amount.ok_or(anyhow!("return amount can't be null"))?,
+                    },
+                    Investor::Francium => EpochInstruction::ExitInvestmentFrancium {
+                        epoch_index: epoch.index,
+                    },
+                }
+            }
+            EpochOperation::PublishWinners { winners } => {
+                let epoch = self.current_epoch().await?;
+                let (meta_args, winners_input) = winners_input(winners);
+                EpochInstruction::PublishWinners {
+                    epoch_index: epoch.index,
+                    draw_enabled: epoch
+                        .draw_enabled
+                        .ok_or(anyhow!("Publish winners: Draw enabled not set"))?,
+                    meta_args,
+                    winners_input,
+                }
+            }
+            EpochOperation::FundJackpot => {
+                let epoch = self.current_epoch().await?;
+                EpochInstruction::FundJackpot {
+                    epoch_index: epoch.index,
+                    amount: epoch.yield_split_cfg.jackpot,
+                }
+            }
+        })
+    }
+}
+
+/// Winner inputs of the staking program, numbered in tier order.
+pub fn winners_input(winners: &Winners) -> (CreateEpochWinnersMetaArgs, Vec<WinnerInput>) {
+    let mut index = 0;
+    let mut winners_input = Vec::new();
+    for &address in winners.tier1.iter() {
+        winners_input.push(WinnerInput {
+            index,
+            address,
+            tier: 1,
+            num_winning_tickets: 1,
+        });
+        index += 1;
+    }
+    let tier1_meta = TierWinnersMetaInput {
+        total_num_winners: winners.tier1.len() as u32,
+        total_num_winning_tickets: winners.tier1.len() as u32,
+    };
+
+    let mut tier2_total_num_winning_tickets = 0;
+    for (&address, &num_winning_tickets) in winners.tier2.iter() {
+        winners_input.push(WinnerInput {
+            index,
+            address,
+            tier: 2,
+            num_winning_tickets,
+        });
+        index += 1;
+        tier2_total_num_winning_tickets += num_winning_tickets;
+    }
+    let tier2_meta = TierWinnersMetaInput {
+        total_num_winners: winners.tier2.len() as u32,
+        total_num_winning_tickets: tier2_total_num_winning_tickets,
+    };
+
+    let mut tier3_total_num_winning_tickets = 0;
+    for (&address, &num_winning_tickets) in winners.tier3.iter() {
+        winners_input.push(WinnerInput {
+            index,
+            address,
+            tier: 3,
+            num_winning_tickets,
+        });
+        index += 1;
+        tier3_total_num_winning_tickets += num_winning_tickets;
+    }
+    let tier3_meta = TierWinnersMetaInput {
+        total_num_winners: winners.tier3.len() as u32,
+        total_num_winning_tickets: tier3_total_num_winning_tickets,
+    };
+
+    let meta_args = CreateEpochWinnersMetaArgs {
+        tier1_meta,
+        tier2_meta,
+        tier3_meta,
+    };
+    (meta_args, winners_input)
 }
 
 #[async_trait]
@@ -49,6 +289,10 @@ impl EpochManager for EpochService {
             .set_winning_combination_fake(latest_epoch.index, combination)
             .await
             .map_err(anyhow::Error::from)?;
+        self.events.publish(Event::WinningCombinationPublished {
+            epoch_index: latest_epoch.index,
+            winning_combination: *combination,
+        });
 
         self.solana
             .get_epoch_by_index(latest_epoch.index)
@@ -86,6 +330,12 @@ impl EpochManager for EpochService {
             }
             _ => {}
         }
+        if let Some(winning_combination) = combination {
+            self.events.publish(Event::WinningCombinationPublished {
+                epoch_index: latest_epoch.index,
+                winning_combination,
+            });
+        }
 
         self.solana
             .get_epoch_by_index(latest_epoch.index)
@@ -195,7 +445,16 @@ impl EpochManager for EpochService {
         Ok(Some(Epoch::from_solana(&epoch, combination)))
     }
 
-    async fn create_epoch(&self, expected_end_date: DateTime<Utc>, yield_split_cfg: YieldSplitCfg) -> Result<Epoch> {
+    async fn create_epoch(
+        &self,
+        expected_end_date: DateTime<Utc>,
+        yield_split_cfg: YieldSplitCfg,
+        operator: &str,
+    ) -> Result<Epoch> {
+        let operation = EpochOperation::CreateEpoch {
+            expected_end_date,
+            yield_split_cfg: yield_split_cfg.clone(),
+        };
         let epoch = self.latest_epoch(UseCache::No).await?;
         // If no epoch is found, we need to create the first one
         let epoch_index = match epoch {
@@ -203,50 +462,62 @@ impl EpochManager for EpochService {
             None => 1,
         };
         log::info!("creating epoch {}", epoch_index);
-        self.solana
+        let result = self
+            .solana
             .create_epoch(epoch_index, expected_end_date, yield_split_cfg)
-            .await?;
-        Ok(self
+            .await;
+        self.record_sent(&operation, operator, result).await?;
+        let epoch = self
             .latest_epoch(UseCache::No)
             .await?
-            .ok_or(EpochError::CouldNotReadLatestEpoch)?)
+            .ok_or(EpochError::CouldNotReadLatestEpoch)?;
+        self.events.publish(Event::EpochStatusChanged(epoch.clone()));
+        self.generate_tickets().await;
+        Ok(epoch)
     }
 
     async fn wallet_prizes(&self, wallet: &Pubkey) -> Result<Vec<WalletPrize>> {
         Ok(self.solana.get_prizes_by_wallet(wallet.clone()).await?)
     }
 
-    async fn enter_investment(&self, investor: Investor) -> Result<Epoch> {
+    async fn enter_investment(&self, investor: Investor, operator: &str) -> Result<Epoch> {
         let epoch = self
             .latest_epoch(UseCache::No)
             .await?
             .ok_or(EpochError::CouldNotReadLatestEpoch)?;
 
-        let num_sequences_issued: u64 = self.ticket_repository.num_sequences_by_epoch_index(epoch.index).await?;
         Ok(match epoch.total_invested {
             None => {
                 log::info!("Entering investment {:?} for epoch: {}", investor, epoch.index);
-                match investor {
-                    Investor::Fake => {
-                        self.solana
-                            .enter_investment_fake(epoch.index, num_sequences_issued)
-                            .await?;
-                    }
-                    Investor::Francium => {
-                        self.solana
-                            .enter_investment_francium(epoch.index, num_sequences_issued)
-                            .await?;
-                    }
-                }
-                self.latest_epoch(UseCache::No)
+                // Tickets are committed with the manifest, so they are sized for the final balances first
+                self.reconcile_tickets().await?;
+                let manifest = self.manifest(epoch.index).await?;
+                let tickets_url = self.manifest_publisher.publish(&manifest).await?;
+                log::info!("Published manifest of epoch {} to {}", epoch.index, tickets_url);
+                let tickets_info = manifest.tickets_info(tickets_url)?;
+                let result = match investor {
+                    Investor::Fake => self.solana.enter_investment_fake(epoch.index, tickets_info).await,
+                    Investor::Francium => self.solana.enter_investment_francium(epoch.index, tickets_info).await,
+                };
+                self.record_sent(&EpochOperation::EnterInvestment { investor }, operator, result)
+                    .await?;
+                let epoch = self
+                    .latest_epoch(UseCache::No)
                     .await?
-                    .ok_or(EpochError::CouldNotReadLatestEpoch)?
+                    .ok_or(EpochError::CouldNotReadLatestEpoch)?;
+                self.events.publish(Event::EpochStatusChanged(epoch.clone()));
+                epoch
             }
             Some(_) => epoch,
         })
     }
 
-    async fn exit_investment(&self, investor: Investor, return_amount: Option<FPUSDC>) -> Result<Epoch> {
+    async fn exit_investment(
+        &self,
+        investor: Investor,
+        return_amount: Option<FPUSDC>,
+        operator: &str,
+    ) -> Result<Epoch> {
         let epoch = self
             .latest_epoch(UseCache::No)
             .await?
@@ -254,27 +525,37 @@ impl EpochManager for EpochService {
         Ok(match epoch.returns {
             None => {
                 log::info!("Exiting investment {:?} for epoch: {}", investor, epoch.index);
-                match investor {
+                let result = match investor {
                     Investor::Fake => {
                         if let Some(return_amount) = return_amount {
-                            self.solana.exit_investment_fake(epoch.index, return_amount).await?;
+                            self.solana.exit_investment_fake(epoch.index, return_amount).await
                         } else {
                             return Err(anyhow!("return amount can't be null"));
                         }
                     }
-                    Investor::Francium => {
-                        self.solana.exit_investment_francium(epoch.index).await?;
-                    }
-                }
-                self.latest_epoch(UseCache::No)
+                    Investor::Francium => self.solana.exit_investment_francium(epoch.index).await,
+                };
+                let operation = EpochOperation::ExitInvestment {
+                    investor,
+                    return_amount,
+                };
+                self.record_sent(&operation, operator, result).await?;
+                let epoch = self
+                    .latest_epoch(UseCache::No)
                     .await?
-                    .ok_or(EpochError::CouldNotReadLatestEpoch)?
+                    .ok_or(EpochError::CouldNotReadLatestEpoch)?;
+                self.events.publish(Event::EpochStatusChanged(epoch.clone()));
+                epoch
             }
             Some(_) => epoch,
         })
     }
 
-    async fn publish_winners(&self, winners: Winners) -> Result<Epoch> {
+    async fn calculate_winners(&self) -> Result<Winners> {
+        self.ticket_service.calculate_winners().await
+    }
+
+    async fn publish_winners(&self, winners: Winners, operator: &str) -> Result<Epoch> {
         let latest_epoch = self.solana.get_latest_epoch().await?;
         let epoch_index = latest_epoch.index;
         let epoch = self
@@ -294,78 +575,56 @@ impl EpochManager for EpochService {
                 .expect("Will only be None when first epoch hasn't been created yet");
         }
 
-        let mut index = 0;
-        let mut winners_input = Vec::new();
-        for &address in winners.tier1.iter() {
-            winners_input.push(WinnerInput {
-                index,
-                address,
-                tier: 1,
-                num_winning_tickets: 1,
-            });
-            index += 1;
-        }
-        let tier1_meta = TierWinnersMetaInput {
-            total_num_winners: winners.tier1.len() as u32,
-            total_num_winning_tickets: winners.tier1.len() as u32,
-        };
-
-        let mut tier2_total_num_winning_tickets = 0;
-        for (&address, &num_winning_tickets) in winners.tier2.iter() {
-            winners_input.push(WinnerInput {
-                index,
-                address,
-                tier: 2,
-                num_winning_tickets,
-            });
-            index += 1;
-            tier2_total_num_winning_tickets += num_winning_tickets;
-        }
-        let tier2_meta = TierWinnersMetaInput {
-            total_num_winners: winners.tier2.len() as u32,
-            total_num_winning_tickets: tier2_total_num_winning_tickets,
-        };
-
-        let mut tier3_total_num_winning_tickets = 0;
-        for (&address, &num_winning_tickets) in winners.tier3.iter() {
-            winners_input.push(WinnerInput {
-                index,
-                address,
-                tier: 3,
-                num_winning_tickets,
-            });
-            index += 1;
-            tier3_total_num_winning_tickets += num_winning_tickets;
-        }
-        let tier3_meta = TierWinnersMetaInput {
-            total_num_winners: winners.tier3.len() as u32,
-            total_num_winning_tickets: tier3_total_num_winning_tickets,
-        };
-
-        let meta_args = CreateEpochWinnersMetaArgs {
-            tier1_meta,
-            tier2_meta,
-            tier3_meta,
-        };
-
-        self.solana
+        let (meta_args, winners_input) = winners_input(&winners);
+        let result = self
+            .solana
             .publish_winners(epoch_index, draw_enabled, &meta_args, &winners_input)
+            .await;
+        self.record_sent(&EpochOperation::PublishWinners { winners }, operator, result)
             .await?;
 
-        Ok(self
+        let epoch = self
             .latest_epoch(UseCache::No)
             .await?
-            .ok_or(EpochError::CouldNotReadLatestEpoch)?)
+            .ok_or(EpochError::CouldNotReadLatestEpoch)?;
+        self.events.publish(Event::EpochStatusChanged(epoch.clone()));
+        for winner in winners_input {
+            self.events.publish(Event::PrizeAvailable {
+                wallet: winner.address,
+                epoch_index,
+                tier: winner.tier,
+            });
+        }
+        Ok(epoch)
     }
 
-    async fn fund_jackpot(&self) -> Result<()> {
+    async fn fund_jackpot(&self, operator: &str) -> Result<()> {
         let epoch = self
             .latest_epoch(UseCache::No)
             .await?
             .ok_or(EpochError::CouldNotReadLatestEpoch)?;
-        self.solana
+        let result = self
+            .solana
             .fund_jackpot(epoch.index, epoch.yield_split_cfg.jackpot)
-            .await?;
+            .await;
+        self.record_sent(&EpochOperation::FundJackpot, operator, result).await?;
         Ok(())
     }
+
+    async fn dry_run(&self, operation: EpochOperation, operator: &str) -> Result<Simulation> {
+        let instruction = self.prepare(&operation).await?;
+        log::info!("Simulating {} by {}", operation.name(), operator);
+        let simulation = self.solana.simulate_epoch_instruction(&instruction).await?;
+        self.record(&operation, operator, true, None, simulation.error.clone())
+            .await?;
+        Ok(simulation)
+    }
+
+    async fn audit_log(&self, limit: usize, offset: usize) -> Result<Vec<EpochAuditLog>> {
+        self.audit_repository.recent(limit, offset).await
+    }
+
+    async fn jobs(&self, limit: usize, offset: usize) -> Result<Vec<EpochJob>> {
+        self.job_repository.recent(limit, offset).await
+    }
 }
@@ -0,0 +1,345 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+
+use crate::{
+    epoch::EpochRepository,
+    model::{epoch::Epoch, stake_update::StakeUpdate, transaction::TransactionId},
+    tickets::{Ticket, TicketRepository},
+};
+
+/// Something that happened to an epoch, a stake or a ticket.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The epoch moved to a new status.
+    EpochStatusChanged(Epoch),
+    WinningCombinationPublished {
+        epoch_index: u64,
+        winning_combination: [u8; 6],
+    },
+    StakeUpdateChanged(StakeUpdate),
+    TicketGenerated(Ticket),
+    /// The wallet won a prize in the epoch, which can be claimed now.
+    PrizeAvailable {
+        wallet: Pubkey,
+        epoch_index: u64,
+        tier: u8,
+    },
+}
+
+/// An [`Event`] as relayed to the other replicas. Notifications are small, so events of records carry their key, and
+/// the replicas read the records back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventNotice {
+    EpochStatusChanged {
+        epoch_index: u64,
+    },
+    WinningCombinationPublished {
+        epoch_index: u64,
+        winning_combination: [u8; 6],
+    },
+    StakeUpdateChanged {
+        owner: String,
+        amount: String,
+        type_: String,
+        state: String,
+        currency: String,
+        mint: String,
+        transaction_id: Option<String>,
+    },
+    TicketGenerated {
+        wallet: String,
+        epoch_index: u64,
+    },
+    PrizeAvailable {
+        wallet: String,
+        epoch_index: u64,
+        tier: u8,
+    },
+}
+
+impl From<&Event> for EventNotice {
+    fn from(event: &Event) -> Self {
+        match event {
+            Event::EpochStatusChanged(epoch) => EventNotice::EpochStatusChanged {
+                epoch_index: epoch.index,
+            },
+            Event::WinningCombinationPublished {
+                epoch_index,
+                winning_combination,
+            } => EventNotice::WinningCombinationPublished {
+                epoch_index: *epoch_index,
+                winning_combination: *winning_combination,
+            },
+            Event::StakeUpdateChanged(stake_update) => EventNotice::StakeUpdateChanged {
+                owner: stake_update.owner.to_string(),
+                amount: stake_update.amount.to_string(),
+                type_: stake_update.type_.to_string(),
+                state: stake_update.state.to_string(),
+                currency: stake_update.currency.clone(),
+                mint: stake_update.mint.to_string(),
+                transaction_id: stake_update.transaction_id.as_ref().map(|id| id.0.clone()),
+            },
+            Event::TicketGenerated(ticket) => EventNotice::TicketGenerated {
+                wallet: ticket.wallet.to_string(),
+                epoch_index: ticket.epoch_index,
+            },
+            Event::PrizeAvailable {
+                wallet,
+                epoch_index,
+                tier,
+            } => EventNotice::PrizeAvailable {
+                wallet: wallet.to_string(),
+                epoch_index: *epoch_index,
+                tier: *tier,
+            },
+        }
+    }
+}
+
+impl EventNotice {
+    /// The event of the notice. `None` if its record isn't found.
+    pub async fn load(self, epochs: &dyn EpochRepository, tickets: &dyn TicketRepository) -> Result<Option<Event>> {
+        Ok(match self {
+            EventNotice::EpochStatusChanged { epoch_index } => {
+                epochs.by_index(epoch_index).await?.map(Event::EpochStatusChanged)
+            }
+            EventNotice::WinningCombinationPublished {
+                epoch_index,
+                winning_combination,
+            } => Some(Event::WinningCombinationPublished {
+                epoch_index,
+                winning_combination,
+            }),
+            EventNotice::StakeUpdateChanged {
+                owner,
+                amount,
+                type_,
+                state,
+                currency,
+                mint,
+                transaction_id,
+            } => Some(Event::StakeUpdateChanged(StakeUpdate {
+                owner: Pubkey::from_str(&owner)?,
+                amount: amount.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+                type_: type_.parse()?,
+                state: state.parse()?,
+                currency,
+                mint: Pubkey::from_str(&mint)?,
+                transaction_id: transaction_id.map(TransactionId),
+            })),
+            EventNotice::TicketGenerated { wallet, epoch_index } => tickets
+                .by_wallet_and_epoch_index(&Pubkey::from_str(&wallet)?, epoch_index)
+                .await?
+                .map(Event::TicketGenerated),
+            EventNotice::PrizeAvailable {
+                wallet,
+                epoch_index,
+                tier,
+            } => Some(Event::PrizeAvailable {
+                wallet: Pubkey::from_str(&wallet)?,
+                epoch_index,
+                tier,
+            }),
+        })
+    }
+}
+
+/// Sends the events of a replica to the other replicas.
+#[async_trait]
+pub trait EventRelay: Sync + Send {
+    async fn send(&self, notice: &EventNotice) -> Result<()>;
+}
+
+/// In-process broadcast channel of [`Event`]s.
+///
+/// The services publish to it. Subscribers that fall behind skip the events they missed.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    relay: Option<mpsc::UnboundedSender<EventNotice>>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender, relay: None }
+    }
+
+    /// Also sends the published events to the other replicas, in order. What they send is passed to
+    /// [`EventBus::receive_relayed`].
+    pub fn with_relay(self, relay: Box<dyn EventRelay>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<EventNotice>();
+        tokio::spawn(async move {
+            while let Some(notice) = receiver.recv().await {
+                if let Err(e) = relay.send(&notice).await {
+                    log::error!("Failed to relay event {:?}: {}", notice, e);
+                }
+            }
+        });
+        Self {
+            relay: Some(sender),
+            ..self
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        if let Some(relay) = &self.relay {
+            let _ = relay.send(EventNotice::from(&event));
+        }
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+
+    /// Publishes the events the other replicas relay to the subscribers of this one, until `notices` ends.
+    pub async fn receive_relayed(
+        &self,
+        notices: impl Stream<Item = EventNotice>,
+        epochs: &dyn EpochRepository,
+        tickets: &dyn TicketRepository,
+    ) {
+        tokio::pin!(notices);
+        while let Some(notice) = notices.next().await {
+            match notice.clone().load(epochs, tickets).await {
+                Ok(Some(event)) => {
+                    let _ = self.sender.send(event);
+                }
+                Ok(None) => log::warn!("Record of relayed event {:?} not found", notice),
+                Err(e) => log::error!("Failed to load relayed event {:?}: {}", notice, e),
+            }
+        }
+    }
+
+    pub fn subscribe(&self) -> impl Stream<Item = Event> {
+        stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("Event subscriber lagged behind, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nezha_staking::fixed_point::FPUSDC;
+
+    use super::*;
+    use crate::{
+        model::stake_update::{StakeUpdateState, StakeUpdateType},
+        tickets::InMemoryTicketRepository,
+    };
+
+    struct NoEpochs;
+
+    #[async_trait]
+    impl EpochRepository for NoEpochs {
+        async fn by_index(&self, _index: u64) -> Result<Option<Epoch>> {
+            Ok(None)
+        }
+
+        async fn by_pubkey(&self, _epoch: &Pubkey) -> Result<Option<Epoch>> {
+            Ok(None)
+        }
+
+        async fn all(&self) -> Result<Vec<Epoch>> {
+            Ok(vec![])
+        }
+
+        async fn latest_epoch(&self) -> Result<Option<Epoch>> {
+            Ok(None)
+        }
+
+        async fn create_or_update_epoch(&self, _epoch: &Epoch) -> Result<Epoch> {
+            unimplemented!()
+        }
+    }
+
+    struct ChannelRelay(mpsc::UnboundedSender<EventNotice>);
+
+    #[async_trait]
+    impl EventRelay for ChannelRelay {
+        async fn send(&self, notice: &EventNotice) -> Result<()> {
+            self.0.send(notice.clone())?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let events = EventBus::new(2);
+        // No subscribers yet
+        events.publish(Event::PrizeAvailable {
+            wallet: Pubkey::new_unique(),
+            epoch_index: 1,
+            tier: 1,
+        });
+
+        let subscription = events.subscribe();
+        tokio::pin!(subscription);
+        for epoch_index in 2..=4 {
+            events.publish(Event::WinningCombinationPublished {
+                epoch_index,
+                winning_combination: [0; 6],
+            });
+        }
+
+        // The first event is dropped because the channel only holds 2
+        for expected in 3..=4 {
+            match subscription.next().await {
+                Some(Event::WinningCombinationPublished { epoch_index, .. }) => assert_eq!(epoch_index, expected),
+                event => panic!("Unexpected event {:?}", event),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receive_relayed() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let events = EventBus::default().with_relay(Box::new(ChannelRelay(sender)));
+        let replica = EventBus::default();
+        let subscription = replica.subscribe();
+        tokio::pin!(subscription);
+
+        let stake_update = StakeUpdate {
+            owner: Pubkey::new_unique(),
+            amount: FPUSDC::from_usdc(1_500_000),
+            type_: StakeUpdateType::Withdraw,
+            state: StakeUpdateState::Completed,
+            currency: "USDC".to_string(),
+            mint: Pubkey::new_unique(),
+            transaction_id: Some(TransactionId("signature".to_string())),
+        };
+        events.publish(Event::StakeUpdateChanged(stake_update.clone()));
+
+        // The epoch isn't stored, so the replica skips its event
+        let notices = stream::iter([EventNotice::EpochStatusChanged { epoch_index: 1 }])
+            .chain(stream::poll_fn(|cx| receiver.poll_recv(cx)).take(1));
+        replica
+            .receive_relayed(notices, &NoEpochs, &InMemoryTicketRepository::new(0))
+            .await;
+        match subscription.next().await {
+            Some(Event::StakeUpdateChanged(relayed)) => assert_eq!(relayed, stake_update),
+            event => panic!("Unexpected event {:?}", event),
+        }
+    }
+}
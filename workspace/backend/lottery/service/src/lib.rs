@@ -12,12 +12,20 @@ use solana_sdk::{
 };
 use thiserror::Error;
 
+pub mod account_mirror;
+pub mod campaign;
+pub mod draw_stats;
 pub mod epoch;
+pub mod events;
 pub mod faucet;
 pub mod health_check;
 pub mod model;
+pub mod notification;
 pub mod prize;
 pub mod rng;
+pub mod scenario;
+pub mod screening;
+pub mod sign_in;
 pub mod solana;
 pub mod stake;
 pub mod tickets;
@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// A promotion granting bonus sequences to the eligible wallets of every epoch from `start_epoch` to `end_epoch`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub name: String,
+    pub start_epoch: u64,
+    /// Last epoch of the campaign, inclusive. Open ended if `None`.
+    pub end_epoch: Option<u64>,
+    pub eligibility: CampaignEligibility,
+    pub reward: CampaignReward,
+    /// Most sequences a wallet is granted over the whole campaign.
+    pub max_sequences_per_wallet: Option<u32>,
+    pub active: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Campaign {
+    pub fn runs_in(&self, epoch_index: u64) -> bool {
+        self.active
+            && self.start_epoch <= epoch_index
+            && self.end_epoch.map_or(true, |end_epoch| epoch_index <= end_epoch)
+    }
+}
+
+/// A campaign as set up by an operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewCampaign {
+    pub name: String,
+    pub start_epoch: u64,
+    pub end_epoch: Option<u64>,
+    pub eligibility: CampaignEligibility,
+    pub reward: CampaignReward,
+    pub max_sequences_per_wallet: Option<u32>,
+}
+
+/// Rules a wallet must meet, all of them, to be granted sequences.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CampaignEligibility {
+    pub min_stake: Option<FPUSDC>,
+    /// Only wallets that didn't play any draw before.
+    pub new_users_only: bool,
+    /// Draws the wallet must have played before the epoch.
+    pub min_draws_played: Option<u64>,
+    /// Only wallets that were referred by another wallet.
+    pub referred_only: bool,
+}
+
+/// Number of sequences an eligible wallet is granted in an epoch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CampaignReward {
+    Constant(u32),
+    /// One sequence per `amount` staked.
+    PerStake {
+        amount: FPUSDC,
+    },
+    /// Percentage of the wallet's normal sequences, rounded down.
+    Multiplier {
+        percent: u32,
+    },
+}
+
+impl CampaignReward {
+    pub fn num_sequences(&self, balance: FPUSDC, normal_sequences: u32) -> u32 {
+        match *self {
+            CampaignReward::Constant(count) => count,
+            CampaignReward::PerStake { amount } => balance
+                .checked_div(amount)
+                .map(|count| u32::try_from(count.as_whole_number()).unwrap_or(u32::MAX))
+                .unwrap_or(0),
+            CampaignReward::Multiplier { percent } => (normal_sequences as u64 * percent as u64 / 100) as u32,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            CampaignReward::Constant(_) => "constant",
+            CampaignReward::PerStake { .. } => "per_stake",
+            CampaignReward::Multiplier { .. } => "multiplier",
+        }
+    }
+
+    pub fn value(&self) -> String {
+        match self {
+            CampaignReward::Constant(count) => count.to_string(),
+            CampaignReward::PerStake { amount } => amount.to_string(),
+            CampaignReward::Multiplier { percent } => percent.to_string(),
+        }
+    }
+
+    /// Parses a reward from its [`CampaignReward::type_name`] and [`CampaignReward::value`].
+    pub fn parse(type_name: &str, value: &str) -> Result<Self> {
+        Ok(match type_name {
+            "constant" => CampaignReward::Constant(value.parse()?),
+            "per_stake" => CampaignReward::PerStake {
+                amount: FPUSDC::from_str(value).map_err(|e| anyhow::anyhow!(e))?,
+            },
+            "multiplier" => CampaignReward::Multiplier {
+                percent: value.parse()?,
+            },
+            _ => bail!("Invalid campaign reward type: {}", type_name),
+        })
+    }
+}
+
+/// Sequences a campaign granted a wallet for an epoch. A campaign grants a wallet at most once per epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CampaignGrant {
+    pub campaign_id: Uuid,
+    pub wallet: Pubkey,
+    pub epoch_index: u64,
+    pub num_sequences: u32,
+    pub granted_at: DateTime<Utc>,
+}
+
+/// A wallet that signed up with another wallet's referral.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Referral {
+    pub wallet: Pubkey,
+    pub referrer: Pubkey,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum CampaignError {
+    #[error("Campaign {0} not found")]
+    NotFound(Uuid),
+
+    #[error("Invalid campaign: {0}")]
+    Invalid(String),
+
+    #[error("Wallet {0} can't refer itself")]
+    SelfReferral(Pubkey),
+
+    #[error("Referrer {0} didn't play any draw yet")]
+    ReferrerNotPlaying(Pubkey),
+
+    #[error("Wallet {0} was already referred")]
+    AlreadyReferred(Pubkey),
+}
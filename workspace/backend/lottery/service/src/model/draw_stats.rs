@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+
+use crate::{model::epoch::EpochStatus, tickets::SequenceType};
+
+/// Lengths of the prefixes counted in [`EpochStats::shared_prefixes`].
+pub const SHARED_PREFIX_LENGTHS: [usize; 2] = [4, 5];
+/// Number of the most shared prefixes kept in [`EpochStats::shared_prefixes`].
+pub const MAX_SHARED_PREFIXES: usize = 100;
+
+/// Aggregates of the tickets and prizes of an epoch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochStats {
+    pub epoch_index: u64,
+    /// Status of the epoch when the stats were computed. Stats are final once it's `Ended`.
+    pub status: EpochStatus,
+    pub num_players: u64,
+    pub sequences_by_type: Vec<SequenceTypeCount>,
+    /// How often each of the first 5 numbers was picked, over all positions.
+    pub main_number_frequencies: Vec<NumberFrequency>,
+    /// How often each last number was picked.
+    pub last_number_frequencies: Vec<NumberFrequency>,
+    /// Prefixes of [`SHARED_PREFIX_LENGTHS`] shared by more than one ticket, the most shared first. Up to
+    /// [`MAX_SHARED_PREFIXES`] of them.
+    pub shared_prefixes: Vec<PrefixCount>,
+    pub prizes_by_tier: Vec<TierPrizes>,
+    pub computed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceTypeCount {
+    pub sequence_type: SequenceType,
+    pub num_sequences: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFrequency {
+    pub number: u8,
+    pub num_sequences: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixCount {
+    pub prefix: Vec<u8>,
+    pub num_tickets: u64,
+    pub num_sequences: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierPrizes {
+    pub tier: u8,
+    pub num_winners: u64,
+    pub total_amount: FPUSDC,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WinningCombination {
+    pub epoch_index: u64,
+    pub combination: [u8; 6],
+}
@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use nezha_staking::{fixed_point::FPUSDC, state::YieldSplitCfg};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::epoch::Investor;
+use crate::tickets::Winners;
+
+/// Operator action on the epoch lifecycle.
+#[derive(Debug)]
+pub enum EpochOperation {
+    CreateEpoch {
+        expected_end_date: DateTime<Utc>,
+        yield_split_cfg: YieldSplitCfg,
+    },
+    EnterInvestment {
+        investor: Investor,
+    },
+    ExitInvestment {
+        investor: Investor,
+        return_amount: Option<FPUSDC>,
+    },
+    PublishWinners {
+        winners: Winners,
+    },
+    FundJackpot,
+}
+
+impl EpochOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EpochOperation::CreateEpoch { .. } => "create_epoch",
+            EpochOperation::EnterInvestment { .. } => "enter_investment",
+            EpochOperation::ExitInvestment { .. } => "exit_investment",
+            EpochOperation::PublishWinners { .. } => "publish_winners",
+            EpochOperation::FundJackpot => "fund_jackpot",
+        }
+    }
+
+    /// Parameters as recorded in the audit log. Winners are counted rather than listed.
+    pub fn params(&self) -> Value {
+        match self {
+            EpochOperation::CreateEpoch {
+                expected_end_date,
+                yield_split_cfg,
+            } => json!({
+                "expected_end_date": expected_end_date.to_rfc3339(),
+                "jackpot": yield_split_cfg.jackpot.to_string(),
+                "insurance_premium": yield_split_cfg.insurance.premium.to_string(),
+                "insurance_probability": yield_split_cfg.insurance.probability.to_string(),
+                "treasury_ratio": yield_split_cfg.treasury_ratio.to_string(),
+                "tier2_prize_share": yield_split_cfg.tier2_prize_share,
+                "tier3_prize_share": yield_split_cfg.tier3_prize_share,
+            }),
+            EpochOperation::EnterInvestment { investor } => json!({ "investor": format!("{:?}", investor) }),
+            EpochOperation::ExitInvestment {
+                investor,
+                return_amount,
+            } => json!({
+                "investor": format!("{:?}", investor),
+                "return_amount": return_amount.map(|amount| amount.to_string()),
+            }),
+            EpochOperation::PublishWinners { winners } => json!({
+                "tier1_winners": winners.tier1.len(),
+                "tier2_winners": winners.tier2.len(),
+                "tier3_winners": winners.tier3.len(),
+            }),
+            EpochOperation::FundJackpot => json!({}),
+        }
+    }
+}
+
+/// Record of an epoch operation, whether it was sent or only simulated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochAuditLog {
+    pub id: Uuid,
+    pub operator: String,
+    pub operation: String,
+    pub params: Value,
+    pub dry_run: bool,
+    /// Signature of the last transaction sent.
+    pub signature: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
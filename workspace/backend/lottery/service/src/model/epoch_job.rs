@@ -0,0 +1,96 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+/// Step of the epoch lifecycle run by the epoch indexer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochJobCommand {
+    CreateEpoch,
+    EnterInvestment,
+    ExitInvestment,
+    PublishWinningCombination,
+    PublishWinners,
+    FundJackpot,
+}
+
+impl FromStr for EpochJobCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "create_epoch" => Ok(EpochJobCommand::CreateEpoch),
+            "enter_investment" => Ok(EpochJobCommand::EnterInvestment),
+            "exit_investment" => Ok(EpochJobCommand::ExitInvestment),
+            "publish_winning_combination" => Ok(EpochJobCommand::PublishWinningCombination),
+            "publish_winners" => Ok(EpochJobCommand::PublishWinners),
+            "fund_jackpot" => Ok(EpochJobCommand::FundJackpot),
+            _ => bail!("Invalid epoch job command: {}", s),
+        }
+    }
+}
+
+impl ToString for EpochJobCommand {
+    fn to_string(&self) -> String {
+        match self {
+            EpochJobCommand::CreateEpoch => "create_epoch".to_string(),
+            EpochJobCommand::EnterInvestment => "enter_investment".to_string(),
+            EpochJobCommand::ExitInvestment => "exit_investment".to_string(),
+            EpochJobCommand::PublishWinningCombination => "publish_winning_combination".to_string(),
+            EpochJobCommand::PublishWinners => "publish_winners".to_string(),
+            EpochJobCommand::FundJackpot => "fund_jackpot".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EpochJobStatus {
+    /// Waiting for its first attempt, or for its next one at `next_attempt_at` after a failed attempt.
+    Pending,
+    Running,
+    Succeeded,
+    /// Gave up after too many failed attempts. The epoch indexer waits until an operator runs the step.
+    Failed,
+}
+
+impl FromStr for EpochJobStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(EpochJobStatus::Pending),
+            "running" => Ok(EpochJobStatus::Running),
+            "succeeded" => Ok(EpochJobStatus::Succeeded),
+            "failed" => Ok(EpochJobStatus::Failed),
+            _ => bail!("Invalid epoch job status: {}", s),
+        }
+    }
+}
+
+impl ToString for EpochJobStatus {
+    fn to_string(&self) -> String {
+        match self {
+            EpochJobStatus::Pending => "pending".to_string(),
+            EpochJobStatus::Running => "running".to_string(),
+            EpochJobStatus::Succeeded => "succeeded".to_string(),
+            EpochJobStatus::Failed => "failed".to_string(),
+        }
+    }
+}
+
+/// A persisted epoch indexer job. There is at most one job per epoch index and command.
+///
+/// The epoch index of `CreateEpoch` is the index of the epoch it creates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochJob {
+    pub epoch_index: u64,
+    pub command: EpochJobCommand,
+    pub status: EpochJobStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// When the job is scheduled to run.
+    pub due_at: DateTime<Utc>,
+    /// When the job runs next. Later than `due_at` after a failed attempt.
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
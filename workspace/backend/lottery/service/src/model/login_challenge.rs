@@ -0,0 +1,40 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+
+/// A single use nonce a wallet signs to sign in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoginChallenge {
+    pub wallet: Pubkey,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl LoginChallenge {
+    /// The message the wallet signs. Follows the Sign-In with Solana message format.
+    pub fn message(&self, domain: &str) -> String {
+        format!(
+            "{domain} wants you to sign in with your Solana account:\n\
+             {wallet}\n\
+             \n\
+             Nonce: {nonce}\n\
+             Issued At: {issued_at}\n\
+             Expiration Time: {expires_at}",
+            domain = domain,
+            wallet = self.wallet,
+            nonce = self.nonce,
+            issued_at = self.issued_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            expires_at = self.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SignInError {
+    #[error("Login challenge not found or expired")]
+    InvalidChallenge,
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+}
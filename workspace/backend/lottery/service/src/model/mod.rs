@@ -1,8 +1,17 @@
+pub mod campaign;
+pub mod draw_stats;
 pub mod epoch;
+pub mod epoch_audit;
+pub mod epoch_job;
 pub mod error;
 pub mod faucet;
+pub mod login_challenge;
+pub mod notification;
 pub mod prize;
+pub mod program_account;
+pub mod scenario;
 pub mod stake_update;
+pub mod stake_update_review;
 pub mod ticket;
 pub mod transaction;
 pub mod winner;
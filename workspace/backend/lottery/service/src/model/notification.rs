@@ -0,0 +1,322 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::transaction::TransactionId;
+
+/// Something a user can be notified about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationEvent {
+    PrizeWon {
+        wallet: Pubkey,
+        epoch_index: u64,
+        page: u32,
+        winner_index: u32,
+        tier: u8,
+        amount: FPUSDC,
+    },
+    DepositCompleted {
+        wallet: Pubkey,
+        amount: FPUSDC,
+        transaction_id: TransactionId,
+        instruction_index: u8,
+    },
+    WithdrawalCompleted {
+        wallet: Pubkey,
+        amount: FPUSDC,
+        transaction_id: TransactionId,
+        instruction_index: u8,
+    },
+    /// Sent to every user subscribed to it.
+    EpochEnded { epoch_index: u64 },
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationEvent::PrizeWon { .. } => NotificationKind::PrizeWon,
+            NotificationEvent::DepositCompleted { .. } => NotificationKind::DepositCompleted,
+            NotificationEvent::WithdrawalCompleted { .. } => NotificationKind::WithdrawalCompleted,
+            NotificationEvent::EpochEnded { .. } => NotificationKind::EpochEnded,
+        }
+    }
+
+    /// The wallet the event is about. `None` for events sent to every subscriber.
+    pub fn wallet(&self) -> Option<Pubkey> {
+        match self {
+            NotificationEvent::PrizeWon { wallet, .. }
+            | NotificationEvent::DepositCompleted { wallet, .. }
+            | NotificationEvent::WithdrawalCompleted { wallet, .. } => Some(*wallet),
+            NotificationEvent::EpochEnded { .. } => None,
+        }
+    }
+
+    /// Identifies the event, so that it's delivered once even if it's seen again.
+    pub fn key(&self) -> String {
+        match self {
+            NotificationEvent::PrizeWon {
+                epoch_index,
+                page,
+                winner_index,
+                ..
+            } => format!("prize_won:{}:{}:{}", epoch_index, page, winner_index),
+            NotificationEvent::DepositCompleted {
+                transaction_id,
+                instruction_index,
+                ..
+            } => format!("deposit_completed:{}:{}", transaction_id.0, instruction_index),
+            NotificationEvent::WithdrawalCompleted {
+                transaction_id,
+                instruction_index,
+                ..
+            } => format!("withdrawal_completed:{}:{}", transaction_id.0, instruction_index),
+            NotificationEvent::EpochEnded { epoch_index } => format!("epoch_ended:{}", epoch_index),
+        }
+    }
+
+    pub fn subject(&self) -> String {
+        match self {
+            NotificationEvent::PrizeWon { epoch_index, .. } => format!("You won a prize in draw #{}", epoch_index),
+            NotificationEvent::DepositCompleted { .. } => "Your deposit is complete".to_string(),
+            NotificationEvent::WithdrawalCompleted { .. } => "Your withdrawal is complete".to_string(),
+            NotificationEvent::EpochEnded { epoch_index } => format!("Draw #{} has ended", epoch_index),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            NotificationEvent::PrizeWon {
+                epoch_index,
+                tier,
+                amount,
+                ..
+            } => format!(
+                "Your ticket won a tier {} prize of {} USDC in draw #{}. You can claim it now.",
+                tier, amount, epoch_index
+            ),
+            NotificationEvent::DepositCompleted { amount, .. } => {
+                format!(
+                    "Your deposit of {} USDC is complete. Good luck in the next draw!",
+                    amount
+                )
+            }
+            NotificationEvent::WithdrawalCompleted { amount, .. } => {
+                format!("Your withdrawal of {} USDC is complete.", amount)
+            }
+            NotificationEvent::EpochEnded { epoch_index } => {
+                format!("The winners of draw #{} have been published.", epoch_index)
+            }
+        }
+    }
+
+    /// Details of the event sent to webhooks.
+    pub fn data(&self) -> Value {
+        match self {
+            NotificationEvent::PrizeWon {
+                wallet,
+                epoch_index,
+                tier,
+                amount,
+                ..
+            } => json!({
+                "wallet": wallet.to_string(),
+                "epoch_index": epoch_index,
+                "tier": tier,
+                "amount": amount.to_string(),
+            }),
+            NotificationEvent::DepositCompleted {
+                wallet,
+                amount,
+                transaction_id,
+                ..
+            }
+            | NotificationEvent::WithdrawalCompleted {
+                wallet,
+                amount,
+                transaction_id,
+                ..
+            } => json!({
+                "wallet": wallet.to_string(),
+                "amount": amount.to_string(),
+                "transaction_id": transaction_id.0,
+            }),
+            NotificationEvent::EpochEnded { epoch_index } => json!({ "epoch_index": epoch_index }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationKind {
+    PrizeWon,
+    DepositCompleted,
+    WithdrawalCompleted,
+    EpochEnded,
+}
+
+impl FromStr for NotificationKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "prize_won" => Ok(NotificationKind::PrizeWon),
+            "deposit_completed" => Ok(NotificationKind::DepositCompleted),
+            "withdrawal_completed" => Ok(NotificationKind::WithdrawalCompleted),
+            "epoch_ended" => Ok(NotificationKind::EpochEnded),
+            _ => bail!("Invalid notification kind: {}", s),
+        }
+    }
+}
+
+impl ToString for NotificationKind {
+    fn to_string(&self) -> String {
+        match self {
+            NotificationKind::PrizeWon => "prize_won".to_string(),
+            NotificationKind::DepositCompleted => "deposit_completed".to_string(),
+            NotificationKind::WithdrawalCompleted => "withdrawal_completed".to_string(),
+            NotificationKind::EpochEnded => "epoch_ended".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationChannel {
+    Email,
+    Webhook,
+}
+
+impl FromStr for NotificationChannel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "email" => Ok(NotificationChannel::Email),
+            "webhook" => Ok(NotificationChannel::Webhook),
+            _ => bail!("Invalid notification channel: {}", s),
+        }
+    }
+}
+
+impl ToString for NotificationChannel {
+    fn to_string(&self) -> String {
+        match self {
+            NotificationChannel::Email => "email".to_string(),
+            NotificationChannel::Webhook => "webhook".to_string(),
+        }
+    }
+}
+
+/// Where and about what a user wants to be notified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationPreferences {
+    pub wallet: Pubkey,
+    pub email: Option<String>,
+    pub webhook_url: Option<String>,
+    /// Signs the requests to the webhook. Generated for every new webhook URL.
+    pub webhook_secret: Option<String>,
+    pub kinds: Vec<NotificationKind>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NotificationPreferences {
+    /// Destinations of the channels the user set up.
+    pub fn destinations(&self) -> Vec<(NotificationChannel, String)> {
+        let email = self
+            .email
+            .as_ref()
+            .map(|email| (NotificationChannel::Email, email.clone()));
+        let webhook = self
+            .webhook_url
+            .as_ref()
+            .map(|url| (NotificationChannel::Webhook, url.clone()));
+        email.into_iter().chain(webhook).collect()
+    }
+}
+
+/// Preferences after an update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatedNotificationPreferences {
+    pub preferences: NotificationPreferences,
+    /// Set when the update generated a webhook secret. It's shown to the user this once.
+    pub new_webhook_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationDeliveryStatus {
+    /// Waiting for its first or next attempt at `next_attempt_at`.
+    Pending,
+    Sent,
+    /// Gave up after too many attempts, or the user turned the channel off.
+    Failed,
+}
+
+impl FromStr for NotificationDeliveryStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(NotificationDeliveryStatus::Pending),
+            "sent" => Ok(NotificationDeliveryStatus::Sent),
+            "failed" => Ok(NotificationDeliveryStatus::Failed),
+            _ => bail!("Invalid notification delivery status: {}", s),
+        }
+    }
+}
+
+impl ToString for NotificationDeliveryStatus {
+    fn to_string(&self) -> String {
+        match self {
+            NotificationDeliveryStatus::Pending => "pending".to_string(),
+            NotificationDeliveryStatus::Sent => "sent".to_string(),
+            NotificationDeliveryStatus::Failed => "failed".to_string(),
+        }
+    }
+}
+
+/// An event queued in the outbox for one channel of a user. There is at most one delivery per event, wallet and
+/// channel, so events seen again by the indexer aren't sent twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationDelivery {
+    pub event_key: String,
+    pub wallet: Pubkey,
+    pub channel: NotificationChannel,
+    pub subject: String,
+    pub body: String,
+    /// Body of the webhook request.
+    pub payload: Value,
+    pub status: NotificationDeliveryStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A notification on its way to one destination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub wallet: Pubkey,
+    pub channel: NotificationChannel,
+    /// Email address or webhook URL.
+    pub destination: String,
+    /// Signs webhook requests.
+    pub secret: Option<String>,
+    pub subject: String,
+    pub body: String,
+    pub payload: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Error)]
+pub enum NotificationError {
+    #[error("Invalid email address: {0}")]
+    InvalidEmail(String),
+
+    #[error("Invalid webhook URL: {0}")]
+    InvalidWebhookUrl(String),
+
+    #[error("Webhooks can't be sent to loopback, private or link-local addresses: {0}")]
+    PrivateWebhookHost(String),
+}
@@ -0,0 +1,89 @@
+use anyhow::{bail, Result};
+use nezha_staking::state::AccountType;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// Staking program accounts kept in the mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MirroredAccountType {
+    Epoch,
+    Stake,
+    StakeUpdateRequest,
+    EpochWinnersMeta,
+    EpochWinnersPage,
+}
+
+impl MirroredAccountType {
+    pub const ALL: [MirroredAccountType; 5] = [
+        MirroredAccountType::Epoch,
+        MirroredAccountType::Stake,
+        MirroredAccountType::StakeUpdateRequest,
+        MirroredAccountType::EpochWinnersMeta,
+        MirroredAccountType::EpochWinnersPage,
+    ];
+}
+
+impl From<MirroredAccountType> for AccountType {
+    fn from(account_type: MirroredAccountType) -> Self {
+        match account_type {
+            MirroredAccountType::Epoch => AccountType::Epoch,
+            MirroredAccountType::Stake => AccountType::Stake,
+            MirroredAccountType::StakeUpdateRequest => AccountType::StakeUpdateRequest,
+            MirroredAccountType::EpochWinnersMeta => AccountType::EpochWinnersMeta,
+            MirroredAccountType::EpochWinnersPage => AccountType::EpochWinnersPage,
+        }
+    }
+}
+
+impl TryFrom<AccountType> for MirroredAccountType {
+    type Error = anyhow::Error;
+
+    fn try_from(account_type: AccountType) -> Result<Self> {
+        match account_type {
+            AccountType::Epoch => Ok(MirroredAccountType::Epoch),
+            AccountType::Stake => Ok(MirroredAccountType::Stake),
+            AccountType::StakeUpdateRequest => Ok(MirroredAccountType::StakeUpdateRequest),
+            AccountType::EpochWinnersMeta => Ok(MirroredAccountType::EpochWinnersMeta),
+            AccountType::EpochWinnersPage => Ok(MirroredAccountType::EpochWinnersPage),
+            _ => bail!("{:?} accounts aren't mirrored", account_type),
+        }
+    }
+}
+
+impl FromStr for MirroredAccountType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "epoch" => Ok(MirroredAccountType::Epoch),
+            "stake" => Ok(MirroredAccountType::Stake),
+            "stake_update_request" => Ok(MirroredAccountType::StakeUpdateRequest),
+            "epoch_winners_meta" => Ok(MirroredAccountType::EpochWinnersMeta),
+            "epoch_winners_page" => Ok(MirroredAccountType::EpochWinnersPage),
+            _ => bail!("Invalid mirrored account type: {}", s),
+        }
+    }
+}
+
+impl ToString for MirroredAccountType {
+    fn to_string(&self) -> String {
+        match self {
+            MirroredAccountType::Epoch => "epoch".to_string(),
+            MirroredAccountType::Stake => "stake".to_string(),
+            MirroredAccountType::StakeUpdateRequest => "stake_update_request".to_string(),
+            MirroredAccountType::EpochWinnersMeta => "epoch_winners_meta".to_string(),
+            MirroredAccountType::EpochWinnersPage => "epoch_winners_page".to_string(),
+        }
+    }
+}
+
+/// Copy of a staking program account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramAccount {
+    pub pubkey: Pubkey,
+    pub account_type: MirroredAccountType,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    /// Slot the account was seen at.
+    pub slot: u64,
+}
@@ -0,0 +1,249 @@
+use anyhow::Result;
+use nezha_staking::{
+    fixed_point::{FPInternal, FixedPoint, FPUSDC},
+    state::{InsuranceCfg, YieldSplitCfg},
+};
+use serde::{Deserialize, Serialize};
+use solana_program::{hash::hash, pubkey::Pubkey};
+use solana_sdk::{
+    signature::Keypair,
+    signer::{keypair::keypair_from_seed, Signer},
+};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Epochs to play against the staking program and the fake VRF, read from a JSON file. Playing a scenario again gives
+/// the same report, so QA can reproduce jackpots, draws without winners and investment losses.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Scenario {
+    /// Seeds the sequences of the tickets and the draws.
+    pub seed: u64,
+    /// Index of the first epoch of `epochs` when the indexer plays them. The runner plays them from the first epoch of
+    /// a new program.
+    #[serde(default = "default_first_epoch_index")]
+    pub first_epoch_index: u64,
+    #[serde(with = "amount")]
+    pub ticket_price: FPUSDC,
+    pub yield_split_cfg: ScenarioYieldSplitCfg,
+    pub epochs: Vec<ScenarioEpoch>,
+}
+
+fn default_first_epoch_index() -> u64 {
+    1
+}
+
+impl Scenario {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn epoch(&self, epoch_index: u64) -> Option<&ScenarioEpoch> {
+        let offset = epoch_index.checked_sub(self.first_epoch_index)?;
+        self.epochs.get(usize::try_from(offset).ok()?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScenarioYieldSplitCfg {
+    #[serde(with = "amount")]
+    pub jackpot: FPUSDC,
+    #[serde(with = "amount")]
+    pub insurance_premium: FPUSDC,
+    #[serde(with = "amount")]
+    pub insurance_probability: FPInternal,
+    #[serde(with = "amount")]
+    pub treasury_ratio: FixedPoint<3>,
+    pub tier2_prize_share: u8,
+    pub tier3_prize_share: u8,
+}
+
+impl From<&ScenarioYieldSplitCfg> for YieldSplitCfg {
+    fn from(cfg: &ScenarioYieldSplitCfg) -> Self {
+        Self {
+            jackpot: cfg.jackpot,
+            insurance: InsuranceCfg {
+                premium: cfg.insurance_premium,
+                probability: cfg.insurance_probability,
+            },
+            treasury_ratio: cfg.treasury_ratio,
+            tier2_prize_share: cfg.tier2_prize_share,
+            tier3_prize_share: cfg.tier3_prize_share,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScenarioEpoch {
+    /// Applied while the epoch is running, before its tickets are generated.
+    #[serde(default)]
+    pub deposits: Vec<ScenarioTransfer>,
+    /// Applied after the deposits.
+    #[serde(default)]
+    pub withdrawals: Vec<ScenarioTransfer>,
+    /// Return of the investment, in percent of the amount invested. Negative for a loss.
+    pub yield_percent: f64,
+    #[serde(default)]
+    pub draw: ScenarioDraw,
+    /// Funds the jackpot once its winners are published, so that they can claim it. The epoch indexer funds every
+    /// jackpot, as it doesn't move on to the next epoch before.
+    #[serde(default)]
+    pub fund_jackpot: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScenarioTransfer {
+    pub wallet: ScenarioWallet,
+    #[serde(with = "amount")]
+    pub amount: FPUSDC,
+}
+
+/// A wallet address, or a label its keypair is derived from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct ScenarioWallet(pub String);
+
+impl ScenarioWallet {
+    pub fn pubkey(&self) -> Pubkey {
+        Pubkey::from_str(&self.0).unwrap_or_else(|_| derive_keypair(&self.0).pubkey())
+    }
+
+    /// Keypair of a labelled wallet, `None` for an address.
+    pub fn keypair(&self) -> Option<Keypair> {
+        match Pubkey::from_str(&self.0) {
+            Ok(_) => None,
+            Err(_) => Some(derive_keypair(&self.0)),
+        }
+    }
+}
+
+/// The same label always gives the same keypair.
+pub(crate) fn derive_keypair(label: &str) -> Keypair {
+    keypair_from_seed(&hash(label.as_bytes()).to_bytes()).expect("hashes are valid seeds")
+}
+
+/// How the winning combination of an epoch is chosen.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScenarioDraw {
+    #[default]
+    Random,
+    /// Drawn from a sequence of the wallet, or of any wallet, so that it wins the tier.
+    Winner {
+        tier: u8,
+        #[serde(default)]
+        wallet: Option<ScenarioWallet>,
+    },
+    /// Shares no 4 number prefix with any sequence of the epoch.
+    NoWinner,
+    Combination {
+        combination: [u8; 6],
+    },
+}
+
+/// What a scenario paid out, epoch by epoch.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScenarioReport {
+    pub seed: u64,
+    pub epochs: Vec<EpochReport>,
+    /// Stakes after the last epoch.
+    pub balances: Vec<WalletBalance>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EpochReport {
+    pub epoch_index: u64,
+    /// Number of sequences of all tickets.
+    pub num_tickets: u64,
+    #[serde(with = "amount")]
+    pub total_invested: FPUSDC,
+    pub yield_percent: f64,
+    #[serde(with = "amount")]
+    pub return_amount: FPUSDC,
+    #[serde(with = "amount")]
+    pub deposit_back: FPUSDC,
+    #[serde(with = "amount")]
+    pub insurance: FPUSDC,
+    #[serde(with = "amount")]
+    pub treasury: FPUSDC,
+    /// Added to the tier 2 prize pool.
+    #[serde(with = "amount")]
+    pub tier2_prize: FPUSDC,
+    /// Added to the tier 3 prize pool.
+    #[serde(with = "amount")]
+    pub tier3_prize: FPUSDC,
+    pub draw_enabled: bool,
+    pub winning_combination: Option<[u8; 6]>,
+    pub payouts: Vec<Payout>,
+    /// Funds carried over to the next epoch.
+    #[serde(with = "amount")]
+    pub pending_insurance: FPUSDC,
+    #[serde(with = "amount")]
+    pub pending_tier2_prize: FPUSDC,
+    #[serde(with = "amount")]
+    pub pending_tier3_prize: FPUSDC,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Payout {
+    pub wallet: ScenarioWallet,
+    pub address: String,
+    pub tier: u8,
+    pub num_winning_tickets: u32,
+    #[serde(with = "amount")]
+    pub prize: FPUSDC,
+    /// Whether the program lets the winner claim the prize. Jackpots are only claimable once funded.
+    pub claimable: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct WalletBalance {
+    pub wallet: ScenarioWallet,
+    pub address: String,
+    #[serde(with = "amount")]
+    pub balance: FPUSDC,
+}
+
+#[derive(Debug, Error)]
+pub enum ScenarioError {
+    #[error("Withdrawal of {amount} by {wallet} exceeds its stake of {balance}")]
+    InsufficientStake {
+        wallet: String,
+        amount: FPUSDC,
+        balance: FPUSDC,
+    },
+
+    #[error("Invalid winner tier {0}, expected 1, 2 or 3")]
+    InvalidTier(u8),
+
+    #[error("No sequences in epoch {0} to draw a winner from")]
+    NoSequences(u64),
+
+    #[error("Wallet {wallet} has no sequences in epoch {epoch_index} to draw a winner from")]
+    NoSequencesOfWallet { wallet: String, epoch_index: u64 },
+
+    #[error("Could not find a combination without winners for epoch {0}")]
+    NoCombinationWithoutWinners(u64),
+
+    #[error("Returns of epoch {0} not set after exiting the investment")]
+    ReturnsNotSet(u64),
+
+    #[error("Wallet {0} is an address, only labelled wallets can sign their stake updates")]
+    UnknownKeypair(String),
+
+    #[error("Numerical Overflow")]
+    Overflow,
+}
+
+/// Fixed point numbers as strings, so that amounts are read and reported exactly.
+mod amount {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::{fmt::Display, str::FromStr};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T: FromStr<Err = String>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
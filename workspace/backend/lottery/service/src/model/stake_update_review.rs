@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::stake_update::{StakeUpdate, StakeUpdateType};
+
+/// A stake update request that was flagged by screening and waits for an operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StakeUpdateReview {
+    pub id: Uuid,
+    pub wallet: Pubkey,
+    pub amount: FPUSDC,
+    pub type_: StakeUpdateType,
+    pub reason: String,
+    pub status: StakeUpdateReviewStatus,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+impl StakeUpdateReview {
+    /// Amount as passed to the staking program. Withdrawals are negative.
+    pub fn signed_amount(&self) -> i64 {
+        match self.type_ {
+            StakeUpdateType::Deposit => self.amount.as_usdc_i64(),
+            StakeUpdateType::Withdraw => -self.amount.as_usdc_i64(),
+        }
+    }
+
+    /// Whether the review is for `stake_update`. A wallet has one request at a time, so a request of another type or
+    /// amount means the reviewed one was cancelled and replaced.
+    pub fn is_for(&self, stake_update: &StakeUpdate) -> bool {
+        self.wallet == stake_update.owner && self.type_ == stake_update.type_ && self.amount == stake_update.amount
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakeUpdateReviewStatus {
+    Pending,
+    Approved,
+    Rejected,
+    /// The request was cancelled or replaced before it was reviewed.
+    Stale,
+}
+
+impl FromStr for StakeUpdateReviewStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(StakeUpdateReviewStatus::Pending),
+            "approved" => Ok(StakeUpdateReviewStatus::Approved),
+            "rejected" => Ok(StakeUpdateReviewStatus::Rejected),
+            "stale" => Ok(StakeUpdateReviewStatus::Stale),
+            _ => bail!("Invalid stake update review status: {}", s),
+        }
+    }
+}
+
+impl ToString for StakeUpdateReviewStatus {
+    fn to_string(&self) -> String {
+        match self {
+            StakeUpdateReviewStatus::Pending => "pending".to_string(),
+            StakeUpdateReviewStatus::Approved => "approved".to_string(),
+            StakeUpdateReviewStatus::Rejected => "rejected".to_string(),
+            StakeUpdateReviewStatus::Stale => "stale".to_string(),
+        }
+    }
+}
+
+/// Code the API gives the error of a request that was flagged for review.
+pub const FLAGGED_FOR_REVIEW_CODE: &str = "FLAGGED_FOR_REVIEW";
+
+#[derive(Debug, Error)]
+pub enum ScreeningError {
+    #[error("Stake update of {wallet} is flagged for review: {reason}")]
+    Flagged { wallet: Pubkey, reason: String },
+
+    #[error("Stake update review {0} not found")]
+    ReviewNotFound(Uuid),
+
+    #[error("Stake update review {0} was already resolved")]
+    ReviewAlreadyResolved(Uuid),
+
+    #[error("Stake update request of review {0} was cancelled or replaced")]
+    ReviewStale(Uuid),
+}
@@ -1,3 +1,4 @@
+use solana_program::pubkey::Pubkey;
 use thiserror::Error;
 
 use crate::tickets::Ticket;
@@ -11,6 +12,27 @@ pub enum TicketError {
 
     #[error("prefix length exceeded, maximum allowed = 6, supplied prefix length = {0}")]
     PrefixLengthExceeded(usize),
+
+    #[error("invalid sequence {0:?}, expected 5 distinct numbers in 1..=56 followed by 1 number in 1..=10")]
+    InvalidSequence([u8; SEQUENCE_LENGTH]),
+
+    #[error("too many picks, entitled to {entitled} sequences, picked {picked}")]
+    TooManyPicks { entitled: u32, picked: usize },
+
+    #[error("ticket for epoch {0} has already been submitted")]
+    AlreadySubmitted(u64),
+
+    #[error("seed of epoch {0} is not available, tickets are generated once the epoch is created")]
+    EpochSeedNotAvailable(u64),
+
+    #[error("tickets are not generated deterministically")]
+    NotDeterministic,
+
+    #[error("epoch {0} has already been drawn")]
+    AlreadyDrawn(u64),
+
+    #[error("stake of {0} changed in epoch {1}, but the changes are not indexed yet")]
+    StakeChangesNotIndexed(Pubkey, u64),
 }
 
 #[derive(Debug, Clone)]
@@ -18,3 +40,20 @@ pub struct TicketsWithCount {
     pub tickets: Vec<Ticket>,
     pub count: usize,
 }
+
+/// Distinct sequences of an epoch sharing a prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixStats {
+    pub prefix: Vec<u8>,
+    pub num_sequences: u64,
+    /// The smallest sequence with the prefix.
+    pub sequence: [u8; SEQUENCE_LENGTH],
+}
+
+/// Sequences of a wallet whose first `prefix_len` numbers match a combination, and not the next one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PrefixMatches {
+    pub wallet: Pubkey,
+    pub prefix_len: usize,
+    pub num_sequences: u64,
+}
@@ -94,6 +94,11 @@ pub struct Transaction {
     pub mint: Pubkey,
     pub time: Option<DateTime<Utc>>,
     pub transaction_type: TransactionType,
+    /// Position among the inner instructions of `instruction_index` when the staking program was invoked through CPI.
+    pub inner_instruction_index: Option<u8>,
+    pub slot: Option<u64>,
+    /// Transactions seen at confirmed commitment are stored unfinalized, and removed if their fork is dropped.
+    pub finalized: bool,
 }
 
 impl Ord for Transaction {
@@ -101,6 +106,7 @@ impl Ord for Transaction {
         self.transaction_id
             .cmp(&other.transaction_id)
             .then(self.instruction_index.cmp(&other.instruction_index))
+            .then(self.inner_instruction_index.cmp(&other.inner_instruction_index))
     }
 }
 
@@ -112,7 +118,9 @@ impl PartialOrd for Transaction {
 
 impl PartialEq for Transaction {
     fn eq(&self, other: &Self) -> bool {
-        self.transaction_id == other.transaction_id && self.instruction_index == other.instruction_index
+        self.transaction_id == other.transaction_id
+            && self.instruction_index == other.instruction_index
+            && self.inner_instruction_index == other.inner_instruction_index
     }
 }
 
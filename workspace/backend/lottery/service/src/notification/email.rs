@@ -0,0 +1,42 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+
+use super::NotificationTransport;
+use crate::model::notification::Notification;
+
+/// Emails notifications through an SMTP relay.
+pub struct SmtpEmailTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpEmailTransport {
+    /// Connects to the relay with STARTTLS.
+    pub fn new(host: &str, port: u16, username: String, password: String, from: &str) -> Result<Self> {
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?
+            .port(port)
+            .credentials(Credentials::new(username, password))
+            .build();
+        Ok(Self {
+            mailer,
+            from: from.parse()?,
+        })
+    }
+}
+
+#[async_trait]
+impl NotificationTransport for SmtpEmailTransport {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(notification.destination.parse()?)
+            .subject(notification.subject.clone())
+            .body(notification.body.clone())?;
+        self.mailer.send(email).await?;
+        Ok(())
+    }
+}
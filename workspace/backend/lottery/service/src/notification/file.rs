@@ -0,0 +1,52 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::NotificationTransport;
+use crate::model::notification::Notification;
+
+/// Appends notifications to a file as JSON lines instead of sending them. For local testing.
+/// Clones append to the same file.
+#[derive(Clone)]
+pub struct FileTransport {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl FileTransport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationTransport for FileTransport {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let line = json!({
+            "channel": notification.channel.to_string(),
+            "destination": notification.destination,
+            "subject": notification.subject,
+            "body": notification.body,
+            "payload": notification.payload,
+        });
+
+        let _lock = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
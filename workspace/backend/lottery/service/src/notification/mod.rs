@@ -0,0 +1,657 @@
+//! Notifies users about their prizes, stake updates and the end of epochs.
+//!
+//! Events are queued in an outbox for the email address and webhook each user set up in their preferences, and sent
+//! by the [`NotificationWorker`]. Every event is queued at most once per destination, even when the indexer sees it
+//! again, and failed deliveries are retried with backoff.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::StreamExt;
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+use crate::model::notification::{
+    Notification, NotificationChannel, NotificationDelivery, NotificationDeliveryStatus, NotificationError,
+    NotificationEvent, NotificationKind, NotificationPreferences, UpdatedNotificationPreferences,
+};
+
+mod email;
+pub use email::*;
+mod file;
+pub use file::*;
+mod repository;
+pub use repository::*;
+mod webhook;
+pub use webhook::*;
+
+#[async_trait]
+pub trait NotificationPreferencesRepository: Sync + Send {
+    async fn by_wallet(&self, wallet: &Pubkey) -> Result<Option<NotificationPreferences>>;
+    /// Preferences of every user subscribed to the kind of events.
+    async fn subscribed(&self, kind: NotificationKind) -> Result<Vec<NotificationPreferences>>;
+    async fn upsert(&self, preferences: &NotificationPreferences) -> Result<NotificationPreferences>;
+}
+
+/// The outbox of notifications.
+#[async_trait]
+pub trait NotificationDeliveryRepository: Sync + Send {
+    /// Queues the delivery. Returns `false` if the event already was queued for the wallet and channel.
+    async fn enqueue(&self, delivery: &NotificationDelivery) -> Result<bool>;
+    /// Takes up to `limit` pending deliveries due at `now` and counts an attempt for each. They're due again at
+    /// `retry_at` unless they succeed or fail first, so that the deliveries of a worker that stopped are retried.
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        retry_at: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<NotificationDelivery>>;
+    async fn succeed(&self, delivery: &NotificationDelivery, now: DateTime<Utc>) -> Result<()>;
+    /// Retries the delivery at `next_attempt_at`, or gives up on it if there's none.
+    async fn fail(
+        &self,
+        delivery: &NotificationDelivery,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+}
+
+/// Sends notifications through one channel.
+#[async_trait]
+pub trait NotificationTransport: Sync + Send {
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Queues events for the users subscribed to them.
+pub struct Notifier {
+    preferences: Box<dyn NotificationPreferencesRepository>,
+    deliveries: Box<dyn NotificationDeliveryRepository>,
+}
+
+impl Notifier {
+    pub fn new(
+        preferences: Box<dyn NotificationPreferencesRepository>,
+        deliveries: Box<dyn NotificationDeliveryRepository>,
+    ) -> Self {
+        Self {
+            preferences,
+            deliveries,
+        }
+    }
+
+    pub async fn notify(&self, event: &NotificationEvent) -> Result<()> {
+        let kind = event.kind();
+        let recipients = match event.wallet() {
+            Some(wallet) => self
+                .preferences
+                .by_wallet(&wallet)
+                .await?
+                .into_iter()
+                .filter(|preferences| preferences.kinds.contains(&kind))
+                .collect(),
+            None => self.preferences.subscribed(kind).await?,
+        };
+
+        let key = event.key();
+        for recipient in recipients {
+            for (channel, _) in recipient.destinations() {
+                let now = Utc::now();
+                let delivery = NotificationDelivery {
+                    event_key: key.clone(),
+                    wallet: recipient.wallet,
+                    channel,
+                    subject: event.subject(),
+                    body: event.body(),
+                    payload: webhook_payload(event, &recipient.wallet, now),
+                    status: NotificationDeliveryStatus::Pending,
+                    attempts: 0,
+                    last_error: None,
+                    next_attempt_at: now,
+                    created_at: now,
+                };
+                if self.deliveries.enqueue(&delivery).await? {
+                    log::info!(
+                        "Queued {} notification {} to {}",
+                        channel.to_string(),
+                        key,
+                        recipient.wallet
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sends the queued notifications, a bounded number at a time.
+///
+/// Deliveries are sent to the destinations the users have set up when they're sent. Failures are retried after
+/// `retry_delay`, doubled on every attempt, until `max_attempts` were made.
+pub struct NotificationWorker {
+    preferences: Box<dyn NotificationPreferencesRepository>,
+    deliveries: Box<dyn NotificationDeliveryRepository>,
+    email: Box<dyn NotificationTransport>,
+    webhook: Box<dyn NotificationTransport>,
+    concurrency: usize,
+    max_attempts: u32,
+    retry_delay: Duration,
+    /// How long a claimed delivery has to be sent before it's claimed again.
+    lease: Duration,
+}
+
+impl NotificationWorker {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        preferences: Box<dyn NotificationPreferencesRepository>,
+        deliveries: Box<dyn NotificationDeliveryRepository>,
+        email: Box<dyn NotificationTransport>,
+        webhook: Box<dyn NotificationTransport>,
+        concurrency: usize,
+        max_attempts: u32,
+        retry_delay: Duration,
+        lease: Duration,
+    ) -> Self {
+        Self {
+            preferences,
+            deliveries,
+            email,
+            webhook,
+            concurrency: concurrency.max(1),
+            max_attempts: max_attempts.max(1),
+            retry_delay,
+            lease,
+        }
+    }
+
+    pub async fn run_loop(&self, poll_interval: std::time::Duration) -> Result<()> {
+        loop {
+            match self.send_due().await {
+                Ok(0) => tokio::time::sleep(poll_interval).await,
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Failed to send notifications: {}", e);
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Sends the deliveries that are due. Returns how many were attempted.
+    pub async fn send_due(&self) -> Result<usize> {
+        let now = Utc::now();
+        let due = self
+            .deliveries
+            .claim_due(now, now + self.lease, self.concurrency)
+            .await?;
+        let count = due.len();
+        futures::stream::iter(due)
+            .for_each_concurrent(self.concurrency, |delivery| async move {
+                if let Err(e) = self.deliver(&delivery).await {
+                    log::error!(
+                        "Failed to record the {} notification {} to {}: {}",
+                        delivery.channel.to_string(),
+                        delivery.event_key,
+                        delivery.wallet,
+                        e
+                    );
+                }
+            })
+            .await;
+        Ok(count)
+    }
+
+    async fn deliver(&self, delivery: &NotificationDelivery) -> Result<()> {
+        let channel = delivery.channel;
+        let preferences = self.preferences.by_wallet(&delivery.wallet).await?;
+        let destination = preferences.as_ref().and_then(|preferences| {
+            preferences
+                .destinations()
+                .into_iter()
+                .find(|(destination_channel, _)| *destination_channel == channel)
+        });
+        let (preferences, destination) = match (preferences, destination) {
+            (Some(preferences), Some((_, destination))) => (preferences, destination),
+            _ => return self.deliveries.fail(delivery, "The channel was turned off", None).await,
+        };
+
+        let notification = Notification {
+            wallet: delivery.wallet,
+            channel,
+            destination,
+            secret: match channel {
+                NotificationChannel::Email => None,
+                NotificationChannel::Webhook => preferences.webhook_secret,
+            },
+            subject: delivery.subject.clone(),
+            body: delivery.body.clone(),
+            payload: delivery.payload.clone(),
+            created_at: delivery.created_at,
+        };
+        let transport = match channel {
+            NotificationChannel::Email => &self.email,
+            NotificationChannel::Webhook => &self.webhook,
+        };
+        match transport.send(&notification).await {
+            Ok(()) => {
+                log::info!(
+                    "Sent {} notification {} to {}",
+                    channel.to_string(),
+                    delivery.event_key,
+                    delivery.wallet
+                );
+                self.deliveries.succeed(delivery, Utc::now()).await
+            }
+            Err(e) if delivery.attempts >= self.max_attempts => {
+                log::error!(
+                    "Gave up on {} notification {} to {} after {} attempts: {}",
+                    channel.to_string(),
+                    delivery.event_key,
+                    delivery.wallet,
+                    delivery.attempts,
+                    e
+                );
+                self.deliveries.fail(delivery, &e.to_string(), None).await
+            }
+            Err(e) => {
+                let next_attempt_at = Utc::now() + self.backoff(delivery.attempts);
+                log::warn!(
+                    "Failed to send {} notification {} to {}, retrying at {}: {}",
+                    channel.to_string(),
+                    delivery.event_key,
+                    delivery.wallet,
+                    next_attempt_at,
+                    e
+                );
+                self.deliveries
+                    .fail(delivery, &e.to_string(), Some(next_attempt_at))
+                    .await
+            }
+        }
+    }
+
+    /// `retry_delay` doubled for every attempt after the first, up to 64 times.
+    fn backoff(&self, attempts: u32) -> Duration {
+        self.retry_delay * 2i32.pow(attempts.saturating_sub(1).min(6))
+    }
+}
+
+#[async_trait]
+pub trait NotificationPreferencesService: Sync + Send {
+    async fn preferences(&self, wallet: &Pubkey) -> Result<Option<NotificationPreferences>>;
+    async fn update_preferences(
+        &self,
+        wallet: &Pubkey,
+        email: Option<String>,
+        webhook_url: Option<String>,
+        kinds: Vec<NotificationKind>,
+        rotate_webhook_secret: bool,
+    ) -> Result<UpdatedNotificationPreferences>;
+}
+
+pub struct DefaultNotificationPreferencesService {
+    repository: Box<dyn NotificationPreferencesRepository>,
+}
+
+impl DefaultNotificationPreferencesService {
+    pub fn new(repository: Box<dyn NotificationPreferencesRepository>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait]
+impl NotificationPreferencesService for DefaultNotificationPreferencesService {
+    async fn preferences(&self, wallet: &Pubkey) -> Result<Option<NotificationPreferences>> {
+        self.repository.by_wallet(wallet).await
+    }
+
+    async fn update_preferences(
+        &self,
+        wallet: &Pubkey,
+        email: Option<String>,
+        webhook_url: Option<String>,
+        kinds: Vec<NotificationKind>,
+        rotate_webhook_secret: bool,
+    ) -> Result<UpdatedNotificationPreferences> {
+        let email = email.filter(|email| !email.trim().is_empty());
+        if let Some(email) = &email {
+            lettre::Address::from_str(email.trim()).map_err(|_| NotificationError::InvalidEmail(email.clone()))?;
+        }
+        let webhook_url = webhook_url.filter(|url| !url.trim().is_empty());
+        if let Some(url) = &webhook_url {
+            validate_webhook_url(url.trim())?;
+        }
+        let kinds = kinds.into_iter().fold(Vec::new(), |mut unique, kind| {
+            if !unique.contains(&kind) {
+                unique.push(kind);
+            }
+            unique
+        });
+
+        let webhook_url = webhook_url.map(|url| url.trim().to_owned());
+
+        // A new webhook gets a new secret, so that a secret is never shared between receivers
+        let current = self.repository.by_wallet(wallet).await?;
+        let current_secret = current
+            .filter(|current| current.webhook_url == webhook_url)
+            .and_then(|current| current.webhook_secret);
+        let (webhook_secret, new_webhook_secret) = match (&webhook_url, current_secret) {
+            (None, _) => (None, None),
+            (Some(_), Some(secret)) if !rotate_webhook_secret => (Some(secret), None),
+            (Some(_), _) => {
+                let secret = generate_webhook_secret();
+                (Some(secret.clone()), Some(secret))
+            }
+        };
+
+        let preferences = self
+            .repository
+            .upsert(&NotificationPreferences {
+                wallet: *wallet,
+                email: email.map(|email| email.trim().to_owned()),
+                webhook_url,
+                webhook_secret,
+                kinds,
+                updated_at: Utc::now(),
+            })
+            .await?;
+        Ok(UpdatedNotificationPreferences {
+            preferences,
+            new_webhook_secret,
+        })
+    }
+}
+
+fn generate_webhook_secret() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use nezha_staking::fixed_point::FPUSDC;
+
+    use super::*;
+
+    /// Keeps the notifications it's asked to send.
+    #[derive(Clone, Default)]
+    struct RecordingTransport {
+        sent: Arc<Mutex<Vec<Notification>>>,
+    }
+
+    #[async_trait]
+    impl NotificationTransport for RecordingTransport {
+        async fn send(&self, notification: &Notification) -> Result<()> {
+            self.sent.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn preferences(wallet: Pubkey, kinds: Vec<NotificationKind>) -> NotificationPreferences {
+        NotificationPreferences {
+            wallet,
+            email: Some("winner@example.com".into()),
+            webhook_url: Some("https://example.com/hook".into()),
+            webhook_secret: Some("secret".into()),
+            kinds,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Fails every notification.
+    struct FailingTransport;
+
+    #[async_trait]
+    impl NotificationTransport for FailingTransport {
+        async fn send(&self, _notification: &Notification) -> Result<()> {
+            anyhow::bail!("Connection refused")
+        }
+    }
+
+    fn worker(
+        preferences: &InMemoryNotificationPreferencesRepository,
+        deliveries: &InMemoryNotificationDeliveryRepository,
+        email: Box<dyn NotificationTransport>,
+        webhook: Box<dyn NotificationTransport>,
+    ) -> NotificationWorker {
+        NotificationWorker::new(
+            Box::new(preferences.clone()),
+            Box::new(deliveries.clone()),
+            email,
+            webhook,
+            4,
+            2,
+            Duration::zero(),
+            Duration::zero(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_notify() {
+        let preferences_repository = InMemoryNotificationPreferencesRepository::default();
+        let delivery_repository = InMemoryNotificationDeliveryRepository::default();
+        let winner = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        preferences_repository
+            .upsert(&preferences(winner, vec![NotificationKind::PrizeWon]))
+            .await
+            .unwrap();
+        preferences_repository
+            .upsert(&preferences(other, vec![NotificationKind::EpochEnded]))
+            .await
+            .unwrap();
+
+        let email = RecordingTransport::default();
+        let webhook = RecordingTransport::default();
+        let notifier = Notifier::new(
+            Box::new(preferences_repository.clone()),
+            Box::new(delivery_repository.clone()),
+        );
+        let worker = worker(
+            &preferences_repository,
+            &delivery_repository,
+            Box::new(email.clone()),
+            Box::new(webhook.clone()),
+        );
+
+        let prize_won = NotificationEvent::PrizeWon {
+            wallet: winner,
+            epoch_index: 1,
+            page: 0,
+            winner_index: 0,
+            tier: 2,
+            amount: FPUSDC::from_usdc(1_000_000),
+        };
+        notifier.notify(&prize_won).await.unwrap();
+        // Seen again by the indexer
+        notifier.notify(&prize_won).await.unwrap();
+        notifier
+            .notify(&NotificationEvent::EpochEnded { epoch_index: 1 })
+            .await
+            .unwrap();
+
+        // Only queued
+        assert_eq!(delivery_repository.all().len(), 4);
+        assert!(email.sent.lock().unwrap().is_empty());
+
+        assert_eq!(worker.send_due().await.unwrap(), 4);
+        assert_eq!(worker.send_due().await.unwrap(), 0);
+        assert!(delivery_repository
+            .all()
+            .iter()
+            .all(|delivery| delivery.status == NotificationDeliveryStatus::Sent));
+
+        let emails = email.sent.lock().unwrap();
+        assert_eq!(emails.len(), 2);
+        let winner_email = emails.iter().find(|email| email.wallet == winner).unwrap();
+        assert_eq!(winner_email.destination, "winner@example.com");
+        assert_eq!(winner_email.subject, prize_won.subject());
+        assert_eq!(winner_email.secret, None);
+        let other_email = emails.iter().find(|email| email.wallet == other).unwrap();
+        assert_eq!(
+            other_email.body,
+            NotificationEvent::EpochEnded { epoch_index: 1 }.body()
+        );
+
+        let webhooks = webhook.sent.lock().unwrap();
+        assert_eq!(webhooks.len(), 2);
+        let winner_webhook = webhooks.iter().find(|webhook| webhook.wallet == winner).unwrap();
+        assert_eq!(winner_webhook.destination, "https://example.com/hook");
+        assert_eq!(winner_webhook.secret.as_deref(), Some("secret"));
+        assert_eq!(winner_webhook.payload["id"], prize_won.key());
+    }
+
+    #[tokio::test]
+    async fn test_worker_retries() {
+        let preferences_repository = InMemoryNotificationPreferencesRepository::default();
+        let delivery_repository = InMemoryNotificationDeliveryRepository::default();
+        let wallet = Pubkey::new_unique();
+        preferences_repository
+            .upsert(&preferences(wallet, vec![NotificationKind::EpochEnded]))
+            .await
+            .unwrap();
+        let notifier = Notifier::new(
+            Box::new(preferences_repository.clone()),
+            Box::new(delivery_repository.clone()),
+        );
+        let webhook = RecordingTransport::default();
+        let worker = worker(
+            &preferences_repository,
+            &delivery_repository,
+            Box::new(FailingTransport),
+            Box::new(webhook.clone()),
+        );
+
+        notifier
+            .notify(&NotificationEvent::EpochEnded { epoch_index: 1 })
+            .await
+            .unwrap();
+        let status = |channel| {
+            delivery_repository
+                .all()
+                .into_iter()
+                .find(|delivery| delivery.channel == channel)
+                .map(|delivery| (delivery.status, delivery.attempts))
+                .unwrap()
+        };
+
+        assert_eq!(worker.send_due().await.unwrap(), 2);
+        assert_eq!(
+            status(NotificationChannel::Email),
+            (NotificationDeliveryStatus::Pending, 1)
+        );
+        assert_eq!(
+            status(NotificationChannel::Webhook),
+            (NotificationDeliveryStatus::Sent, 1)
+        );
+
+        // Gives up after the last attempt
+        assert_eq!(worker.send_due().await.unwrap(), 1);
+        assert_eq!(
+            status(NotificationChannel::Email),
+            (NotificationDeliveryStatus::Failed, 2)
+        );
+        assert_eq!(worker.send_due().await.unwrap(), 0);
+
+        // Channels turned off before the delivery are dropped
+        notifier
+            .notify(&NotificationEvent::EpochEnded { epoch_index: 2 })
+            .await
+            .unwrap();
+        preferences_repository
+            .upsert(&NotificationPreferences {
+                webhook_url: None,
+                webhook_secret: None,
+                ..preferences(wallet, vec![NotificationKind::EpochEnded])
+            })
+            .await
+            .unwrap();
+        assert_eq!(worker.send_due().await.unwrap(), 2);
+        assert_eq!(webhook.sent.lock().unwrap().len(), 1);
+        let dropped = delivery_repository
+            .all()
+            .into_iter()
+            .find(|delivery| delivery.event_key == "epoch_ended:2" && delivery.channel == NotificationChannel::Webhook)
+            .unwrap();
+        assert_eq!(dropped.status, NotificationDeliveryStatus::Failed);
+        assert_eq!(dropped.last_error.as_deref(), Some("The channel was turned off"));
+    }
+
+    #[tokio::test]
+    async fn test_update_preferences() {
+        let service =
+            DefaultNotificationPreferencesService::new(Box::new(InMemoryNotificationPreferencesRepository::default()));
+        let wallet = Pubkey::new_unique();
+
+        let result = service
+            .update_preferences(&wallet, Some("not an email".into()), None, vec![], false)
+            .await;
+        assert!(result.is_err());
+        let result = service
+            .update_preferences(&wallet, None, Some("ftp://example.com".into()), vec![], false)
+            .await;
+        assert!(result.is_err());
+
+        let updated = service
+            .update_preferences(
+                &wallet,
+                Some(" winner@example.com ".into()),
+                Some("".into()),
+                vec![NotificationKind::PrizeWon],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.preferences.email.as_deref(), Some("winner@example.com"));
+        assert_eq!(updated.preferences.webhook_url, None);
+        assert_eq!(updated.new_webhook_secret, None);
+        assert_eq!(service.preferences(&wallet).await.unwrap(), Some(updated.preferences));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_secrets() {
+        let service =
+            DefaultNotificationPreferencesService::new(Box::new(InMemoryNotificationPreferencesRepository::default()));
+        let wallet = Pubkey::new_unique();
+        let update = |webhook_url: &str, rotate_webhook_secret| {
+            service.update_preferences(
+                &wallet,
+                None,
+                Some(webhook_url.to_owned()),
+                vec![NotificationKind::PrizeWon],
+                rotate_webhook_secret,
+            )
+        };
+
+        let created = update("https://example.com/hook", false).await.unwrap();
+        let secret = created.new_webhook_secret.clone().unwrap();
+        assert_eq!(created.preferences.webhook_secret.as_ref(), Some(&secret));
+
+        // Kept while the webhook doesn't change, and not shown again
+        let kept = update("https://example.com/hook", false).await.unwrap();
+        assert_eq!(kept.new_webhook_secret, None);
+        assert_eq!(kept.preferences.webhook_secret.as_ref(), Some(&secret));
+
+        let rotated = update("https://example.com/hook", true).await.unwrap();
+        let rotated_secret = rotated.new_webhook_secret.unwrap();
+        assert_ne!(rotated_secret, secret);
+
+        let moved = update("https://example.org/hook", false).await.unwrap();
+        let moved_secret = moved.new_webhook_secret.unwrap();
+        assert_ne!(moved_secret, rotated_secret);
+        assert_eq!(moved.preferences.webhook_secret, Some(moved_secret));
+
+        // Other wallets get their own secret
+        let other = service
+            .update_preferences(
+                &Pubkey::new_unique(),
+                None,
+                Some("https://example.org/hook".into()),
+                vec![],
+                false,
+            )
+            .await
+            .unwrap();
+        assert_ne!(other.new_webhook_secret, moved.preferences.webhook_secret);
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use solana_program::pubkey::Pubkey;
+
+use super::{NotificationDeliveryRepository, NotificationPreferencesRepository};
+use crate::model::notification::{
+    NotificationDelivery, NotificationDeliveryStatus, NotificationKind, NotificationPreferences,
+};
+
+#[derive(Clone, Default)]
+pub struct InMemoryNotificationPreferencesRepository {
+    mem: Arc<RwLock<Vec<NotificationPreferences>>>,
+}
+
+#[async_trait]
+impl NotificationPreferencesRepository for InMemoryNotificationPreferencesRepository {
+    async fn by_wallet(&self, wallet: &Pubkey) -> Result<Option<NotificationPreferences>> {
+        Ok(self
+            .mem
+            .read()
+            .unwrap()
+            .iter()
+            .find(|preferences| &preferences.wallet == wallet)
+            .cloned())
+    }
+
+    async fn subscribed(&self, kind: NotificationKind) -> Result<Vec<NotificationPreferences>> {
+        Ok(self
+            .mem
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|preferences| preferences.kinds.contains(&kind))
+            .cloned()
+            .collect())
+    }
+
+    async fn upsert(&self, preferences: &NotificationPreferences) -> Result<NotificationPreferences> {
+        let mut mem = self.mem.write().unwrap();
+        mem.retain(|existing| existing.wallet != preferences.wallet);
+        mem.push(preferences.clone());
+        Ok(preferences.clone())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryNotificationDeliveryRepository {
+    mem: Arc<RwLock<Vec<NotificationDelivery>>>,
+}
+
+impl InMemoryNotificationDeliveryRepository {
+    pub fn all(&self) -> Vec<NotificationDelivery> {
+        self.mem.read().unwrap().clone()
+    }
+
+    fn update(&self, delivery: &NotificationDelivery, f: impl FnOnce(&mut NotificationDelivery)) -> Result<()> {
+        let mut mem = self.mem.write().unwrap();
+        let existing = mem
+            .iter_mut()
+            .find(|existing| is_same(existing, delivery))
+            .ok_or_else(|| anyhow!("Notification delivery {} not found", delivery.event_key))?;
+        f(existing);
+        Ok(())
+    }
+}
+
+fn is_same(a: &NotificationDelivery, b: &NotificationDelivery) -> bool {
+    a.event_key == b.event_key && a.wallet == b.wallet && a.channel == b.channel
+}
+
+#[async_trait]
+impl NotificationDeliveryRepository for InMemoryNotificationDeliveryRepository {
+    async fn enqueue(&self, delivery: &NotificationDelivery) -> Result<bool> {
+        let mut mem = self.mem.write().unwrap();
+        if mem.iter().any(|existing| is_same(existing, delivery)) {
+            return Ok(false);
+        }
+        mem.push(delivery.clone());
+        Ok(true)
+    }
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        retry_at: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let mut mem = self.mem.write().unwrap();
+        Ok(mem
+            .iter_mut()
+            .filter(|delivery| {
+                delivery.status == NotificationDeliveryStatus::Pending && delivery.next_attempt_at <= now
+            })
+            .take(limit)
+            .map(|delivery| {
+                delivery.attempts += 1;
+                delivery.next_attempt_at = retry_at;
+                delivery.clone()
+            })
+            .collect())
+    }
+
+    async fn succeed(&self, delivery: &NotificationDelivery, _now: DateTime<Utc>) -> Result<()> {
+        self.update(delivery, |existing| {
+            existing.status = NotificationDeliveryStatus::Sent;
+            existing.last_error = None;
+        })
+    }
+
+    async fn fail(
+        &self,
+        delivery: &NotificationDelivery,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        self.update(delivery, |existing| {
+            existing.last_error = Some(error.to_owned());
+            match next_attempt_at {
+                Some(next_attempt_at) => existing.next_attempt_at = next_attempt_at,
+                None => existing.status = NotificationDeliveryStatus::Failed,
+            }
+        })
+    }
+}
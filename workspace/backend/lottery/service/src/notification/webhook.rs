@@ -0,0 +1,216 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use solana_program::pubkey::Pubkey;
+
+use super::NotificationTransport;
+use crate::model::notification::{Notification, NotificationError, NotificationEvent};
+
+pub const SIGNATURE_HEADER: &str = "x-nezha-signature";
+pub const TIMESTAMP_HEADER: &str = "x-nezha-timestamp";
+
+/// Posts notifications as JSON to the users' webhooks.
+///
+/// Requests are signed with HMAC-SHA256 of `<timestamp>.<body>` using the secret generated for the webhook,
+/// sent as `sha256=<hex>` in the signature header along with the timestamp header.
+///
+/// The host is resolved before every request and the request is sent to the address that was checked, so that a
+/// webhook can't reach the internal network by changing its DNS records. Redirects aren't followed.
+pub struct WebhookTransport {
+    timeout: Duration,
+}
+
+impl WebhookTransport {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        Ok(Self { timeout })
+    }
+
+    /// A client that connects to a public address of the webhook's host.
+    async fn client(&self, url: &reqwest::Url) -> Result<reqwest::Client> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| NotificationError::InvalidWebhookUrl(url.to_string()))?;
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| NotificationError::InvalidWebhookUrl(url.to_string()))?;
+        // IPv6 literals are bracketed in URLs
+        let addrs = tokio::net::lookup_host((host.trim_start_matches('[').trim_end_matches(']'), port))
+            .await?
+            .collect::<Vec<SocketAddr>>();
+        if addrs.is_empty() {
+            bail!("{} didn't resolve to any address", host);
+        }
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+            return Err(NotificationError::PrivateWebhookHost(format!("{} ({})", host, addr.ip())).into());
+        }
+
+        Ok(reqwest::Client::builder()
+            .timeout(self.timeout)
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(host, addrs[0])
+            .build()?)
+    }
+}
+
+#[async_trait]
+impl NotificationTransport for WebhookTransport {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let secret = notification
+            .secret
+            .as_deref()
+            .context("The webhook has no secret, it needs to be set up again")?;
+        let url = reqwest::Url::parse(&notification.destination)?;
+        let body = serde_json::to_string(&notification.payload)?;
+        let timestamp = notification.created_at.timestamp();
+        let res = self
+            .client(&url)
+            .await?
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!("Webhook responded with {}", res.status());
+        }
+        Ok(())
+    }
+}
+
+/// Body of a webhook request about the event, to the wallet.
+pub fn webhook_payload(event: &NotificationEvent, wallet: &Pubkey, created_at: DateTime<Utc>) -> Value {
+    json!({
+        "id": event.key(),
+        "type": event.kind().to_string(),
+        "wallet": wallet.to_string(),
+        "created_at": created_at.to_rfc3339(),
+        "data": event.data(),
+    })
+}
+
+/// Value of the signature header for a request body.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks that a webhook URL can be registered: an http(s) URL whose host isn't an internal address.
+/// Domains are checked again when they're resolved, before every request.
+pub fn validate_webhook_url(url: &str) -> Result<(), NotificationError> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| NotificationError::InvalidWebhookUrl(url.to_owned()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(NotificationError::InvalidWebhookUrl(url.to_owned()));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| NotificationError::InvalidWebhookUrl(url.to_owned()))?;
+    let is_public = match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) => is_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+    };
+    if !is_public {
+        return Err(NotificationError::PrivateWebhookHost(url.to_owned()));
+    }
+    Ok(())
+}
+
+/// Whether webhooks may be sent to the address: not loopback, private, link-local or otherwise reserved.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Shared address space (carrier-grade NAT)
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ipv4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ipv4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local
+                || (first & 0xfe00) == 0xfc00
+                // Link-local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_webhook_url() {
+        for url in [
+            "https://example.com/hook",
+            "http://93.184.216.34:8080/hook",
+            "https://[2606:2800:220:1:248:1893:25c8:1946]/hook",
+        ] {
+            assert!(validate_webhook_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "ftp://example.com",
+            "not a url",
+            "http://localhost:8080/hook",
+            "http://api.localhost/hook",
+            "http://127.0.0.1/hook",
+            "http://10.0.0.5/hook",
+            "http://172.16.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(validate_webhook_url(url).is_err(), "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_checks_resolved_addresses() {
+        let transport = WebhookTransport::new(Duration::from_secs(1)).unwrap();
+        let url = reqwest::Url::parse("http://127.0.0.1:8080/hook").unwrap();
+        let err = transport.client(&url).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<NotificationError>(),
+            Some(NotificationError::PrivateWebhookHost(_))
+        ));
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("secret", 1_700_000_000, r#"{"type":"epoch_ended"}"#),
+            "sha256=81331b8d89674b4b188073126438954a308c2b48b1ea2a40ed07955ba06a8529"
+        );
+    }
+}
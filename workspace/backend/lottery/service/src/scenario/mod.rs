@@ -0,0 +1,250 @@
+//! Seeded scenarios played against the staking program and the fake VRF.
+//!
+//! A scenario lists the deposits, withdrawals, investment yield and draw of every epoch. Played with the same seed, it
+//! generates the same tickets and draws the same combinations, so QA can reproduce jackpots, draws without winners and
+//! losses, and compare the payouts of the report. The program runs in a program test, which loads the built programs
+//! from `SBF_OUT_DIR`.
+
+use anyhow::Result;
+use rand::{seq::SliceRandom, Rng};
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{
+    model::scenario::{ScenarioDraw, ScenarioError, ScenarioWallet},
+    tickets::{validate_sequence, Ticket},
+};
+
+mod program;
+mod runner;
+pub use program::*;
+pub use runner::*;
+
+/// Attempts at drawing a combination without winners before giving up.
+const MAX_NO_WINNER_ATTEMPTS: u32 = 10_000;
+
+/// Chooses the winning combination of a draw against the tickets of the epoch. Sequences are sorted before they're
+/// chosen from, so the same rng gives the same combination.
+pub fn resolve_draw<R: Rng>(rng: &mut R, draw: &ScenarioDraw, epoch_index: u64, tickets: &[Ticket]) -> Result<[u8; 6]> {
+    match draw {
+        ScenarioDraw::Random => Ok(random_combination(rng)),
+        ScenarioDraw::Combination { combination } => {
+            validate_sequence(combination)?;
+            Ok(*combination)
+        }
+        ScenarioDraw::NoWinner => {
+            let all_sequences = sequences(tickets, None);
+            let prefixes = all_sequences
+                .iter()
+                .map(|sequence| prefix(sequence, 4))
+                .collect::<HashSet<_>>();
+            (0..MAX_NO_WINNER_ATTEMPTS)
+                .map(|_| random_combination(rng))
+                .find(|combination| !prefixes.contains(prefix(combination, 4)))
+                .ok_or_else(|| ScenarioError::NoCombinationWithoutWinners(epoch_index).into())
+        }
+        ScenarioDraw::Winner { tier, wallet } => {
+            if !(1..=3).contains(tier) {
+                return Err(ScenarioError::InvalidTier(*tier).into());
+            }
+            let all_sequences = sequences(tickets, None);
+            let candidates = match wallet {
+                Some(wallet) => sequences(tickets, Some(wallet)),
+                None => all_sequences.clone(),
+            };
+            let sequence = *candidates.choose(rng).ok_or_else(|| match wallet {
+                Some(wallet) => ScenarioError::NoSequencesOfWallet {
+                    wallet: wallet.0.clone(),
+                    epoch_index,
+                },
+                None => ScenarioError::NoSequences(epoch_index),
+            })?;
+            Ok(match *tier {
+                1 => sequence,
+                2 => {
+                    // Another last number, avoiding the combinations that someone holds
+                    let combinations = (1..=10)
+                        .filter(|&n| n != sequence[5])
+                        .map(|n| with_number(&sequence, 5, n))
+                        .collect::<Vec<_>>();
+                    choose_avoiding(rng, &combinations, |combination| all_sequences.contains(combination))
+                }
+                _ => {
+                    // Another fifth number, avoiding the 5 number prefixes that someone holds
+                    let prefixes = all_sequences
+                        .iter()
+                        .map(|sequence| prefix(sequence, 5))
+                        .collect::<HashSet<_>>();
+                    let last = rng.gen_range(1..=10);
+                    let combinations = (1..=56)
+                        .filter(|n| !sequence[..5].contains(n))
+                        .map(|n| with_number(&with_number(&sequence, 5, last), 4, n))
+                        .collect::<Vec<_>>();
+                    choose_avoiding(rng, &combinations, |combination| {
+                        prefixes.contains(prefix(combination, 5))
+                    })
+                }
+            })
+        }
+    }
+}
+
+/// 5 distinct numbers in 1..=56 followed by 1 number in 1..=10, like the sequences of tickets.
+fn random_combination<R: Rng>(rng: &mut R) -> [u8; 6] {
+    let mut combination = [0u8; 6];
+    combination[..5].clone_from_slice(
+        &(1..=56)
+            .collect::<Vec<u8>>()
+            .choose_multiple(rng, 5)
+            .cloned()
+            .collect::<Vec<u8>>(),
+    );
+    combination[5] = rng.gen_range(1..=10);
+    combination
+}
+
+/// Distinct sequences of the tickets, of the wallet's ticket if given, in order.
+fn sequences(tickets: &[Ticket], wallet: Option<&ScenarioWallet>) -> Vec<[u8; 6]> {
+    let owner = wallet.map(ScenarioWallet::pubkey);
+    tickets
+        .iter()
+        .filter(|ticket| owner.map_or(true, |owner| ticket.wallet == owner))
+        .flat_map(|ticket| ticket.sequences.iter().map(|sequence| sequence.nums))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn prefix(sequence: &[u8; 6], len: usize) -> &[u8] {
+    &sequence[..len]
+}
+
+fn with_number(sequence: &[u8; 6], position: usize, number: u8) -> [u8; 6] {
+    let mut sequence = *sequence;
+    sequence[position] = number;
+    sequence
+}
+
+/// Chooses one of the combinations that doesn't conflict, or any of them if they all do.
+fn choose_avoiding<R: Rng>(rng: &mut R, combinations: &[[u8; 6]], conflicts: impl Fn(&[u8; 6]) -> bool) -> [u8; 6] {
+    let preferred = combinations
+        .iter()
+        .filter(|&combination| !conflicts(combination))
+        .copied()
+        .collect::<Vec<_>>();
+    let combinations = if preferred.is_empty() {
+        combinations
+    } else {
+        &preferred[..]
+    };
+    *combinations.choose(rng).expect("a sequence has other numbers to draw")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tickets::{Sequence, SequenceType};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn ticket(wallet: &ScenarioWallet, sequences: &[[u8; 6]]) -> Ticket {
+        Ticket {
+            wallet: wallet.pubkey(),
+            epoch_index: 1,
+            arweave_url: None,
+            sequences: sequences
+                .iter()
+                .map(|&nums| Sequence {
+                    nums,
+                    sequence_type: SequenceType::Normal,
+                })
+                .collect(),
+            balance: "100".into(),
+            price: "25".into(),
+            risq_id: None,
+        }
+    }
+
+    fn common_prefix_len(a: &[u8; 6], b: &[u8; 6]) -> usize {
+        a.iter().zip(b).take_while(|(a, b)| a == b).count()
+    }
+
+    #[test]
+    fn test_resolve_draw_forces_winners() -> Result<()> {
+        let alice = ScenarioWallet("alice".into());
+        let bob = ScenarioWallet("bob".into());
+        let alice_sequence = [1, 2, 3, 4, 5, 6];
+        let tickets = vec![
+            ticket(&alice, &[alice_sequence]),
+            ticket(&bob, &[[1, 2, 3, 4, 5, 7], [10, 11, 12, 13, 14, 1]]),
+        ];
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+
+        let winner = |tier| ScenarioDraw::Winner {
+            tier,
+            wallet: Some(alice.clone()),
+        };
+        let combination = resolve_draw(&mut rng, &winner(1), 1, &tickets)?;
+        assert_eq!(combination, alice_sequence);
+
+        let combination = resolve_draw(&mut rng, &winner(2), 1, &tickets)?;
+        validate_sequence(&combination)?;
+        assert_eq!(common_prefix_len(&combination, &alice_sequence), 5);
+        assert!(tickets
+            .iter()
+            .flat_map(|ticket| &ticket.sequences)
+            .all(|sequence| sequence.nums != combination));
+
+        let combination = resolve_draw(&mut rng, &winner(3), 1, &tickets)?;
+        validate_sequence(&combination)?;
+        assert_eq!(common_prefix_len(&combination, &alice_sequence), 4);
+        assert!(tickets
+            .iter()
+            .flat_map(|ticket| &ticket.sequences)
+            .all(|sequence| common_prefix_len(&sequence.nums, &combination) < 5));
+
+        assert!(resolve_draw(&mut rng, &winner(4), 1, &tickets).is_err());
+        let carol = ScenarioDraw::Winner {
+            tier: 1,
+            wallet: Some(ScenarioWallet("carol".into())),
+        };
+        assert!(resolve_draw(&mut rng, &carol, 1, &tickets).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_draw_without_winners() -> Result<()> {
+        let alice = ScenarioWallet("alice".into());
+        let tickets = vec![ticket(&alice, &[[1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 2]])];
+
+        for seed in 0..20 {
+            let combination = resolve_draw(
+                &mut ChaCha20Rng::seed_from_u64(seed),
+                &ScenarioDraw::NoWinner,
+                1,
+                &tickets,
+            )?;
+            validate_sequence(&combination)?;
+            assert!(tickets
+                .iter()
+                .flat_map(|ticket| &ticket.sequences)
+                .all(|sequence| common_prefix_len(&sequence.nums, &combination) < 4));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_draw_is_deterministic() -> Result<()> {
+        let alice = ScenarioWallet("alice".into());
+        let tickets = vec![ticket(&alice, &[[1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 2]])];
+        let mut reversed = tickets.clone();
+        reversed[0].sequences.reverse();
+
+        for draw in [ScenarioDraw::Random, ScenarioDraw::Winner { tier: 3, wallet: None }] {
+            assert_eq!(
+                resolve_draw(&mut ChaCha20Rng::seed_from_u64(1), &draw, 1, &tickets)?,
+                resolve_draw(&mut ChaCha20Rng::seed_from_u64(1), &draw, 1, &reversed)?,
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use nezha_staking::{fixed_point::FPUSDC, instruction};
+use solana_program::{native_token::LAMPORTS_PER_SOL, program_pack::Pack, system_instruction};
+use solana_sdk::signer::Signer;
+
+use crate::{
+    model::scenario::derive_keypair,
+    solana::{
+        rpc::{SolanaRpc, SolanaRpcExt, SolanaRpcTest},
+        solana_impl::SolanaImpl,
+        Solana, VrfConfiguration,
+    },
+};
+
+/// USDC the investor starts with to pay the returns of the epochs, in its smallest unit.
+const INVESTOR_USDC: u64 = 1_000_000_000_000;
+
+/// Starts the staking program and the VRF mock in a program test, with a USDC mint and the program initialized.
+/// The keys are derived from fixed labels, so that the accounts of the program, and so the order in which its stakes
+/// are read, are the same on every run.
+pub async fn start_program() -> Result<SolanaImpl> {
+    let program_id = derive_keypair("scenario/nezha_staking").pubkey();
+    let vrf_program_id = derive_keypair("scenario/nezha_vrf").pubkey();
+    let super_admin_keypair = derive_keypair("scenario/super_admin");
+    let admin_keypair = derive_keypair("scenario/admin");
+    let investor_keypair = derive_keypair("scenario/investor");
+    let usdc_mint = derive_keypair("scenario/usdc_mint");
+
+    let rpc = SolanaRpcTest::new(program_id, vrf_program_id).await;
+    for actor in [&super_admin_keypair, &admin_keypair, &investor_keypair] {
+        rpc.mint_sols(actor.pubkey(), 100 * LAMPORTS_PER_SOL).await;
+    }
+
+    let rent = rpc.get_rent().await;
+    rpc._send_and_confirm_transaction(
+        &[&admin_keypair, &usdc_mint],
+        None,
+        &[
+            system_instruction::create_account(
+                &admin_keypair.pubkey(),
+                &usdc_mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &usdc_mint.pubkey(),
+                &admin_keypair.pubkey(),
+                None,
+                6,
+            )?,
+        ],
+    )
+    .await?;
+
+    rpc.send_and_confirm_transaction(
+        &super_admin_keypair,
+        &[instruction::init(
+            &program_id,
+            &super_admin_keypair.pubkey(),
+            &admin_keypair.pubkey(),
+            &investor_keypair.pubkey(),
+            &usdc_mint.pubkey(),
+            &vrf_program_id,
+        )],
+    )
+    .await?;
+
+    let solana = SolanaImpl {
+        rpc_client: Arc::new(rpc),
+        program_id,
+        usdc_mint: usdc_mint.pubkey(),
+        nez_mint: derive_keypair("scenario/nez_mint").pubkey(),
+        admin_keypair: Arc::new(admin_keypair),
+        investor_keypair: Arc::new(investor_keypair),
+        vrf_configuration: VrfConfiguration::Fake {
+            program_id: vrf_program_id,
+        },
+    };
+    // The admin mints the USDC that funds the jackpots to its own account first
+    solana.create_usdc_ata(solana.admin_keypair.pubkey()).await?;
+    solana
+        .mint_usdc(solana.investor_keypair.pubkey(), FPUSDC::from_usdc(INVESTOR_USDC))
+        .await?;
+    Ok(solana)
+}
@@ -0,0 +1,300 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use nezha_staking::{
+    fixed_point::{test_utils::fp, FPUSDC},
+    instruction,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use solana_program::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use solana_sdk::signer::Signer;
+use spl_associated_token_account::get_associated_token_address;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use super::{resolve_draw, start_program};
+use crate::{
+    campaign::{DefaultCampaignService, InMemoryCampaignRepository},
+    epoch::{
+        service::{winners_input, EpochService},
+        EpochManager, InMemoryEpochAuditRepository, InMemoryEpochJobRepository, InMemoryEpochRepository,
+    },
+    events::EventBus,
+    model::{
+        epoch::Investor,
+        scenario::{
+            EpochReport, Payout, Scenario, ScenarioEpoch, ScenarioError, ScenarioReport, ScenarioTransfer,
+            ScenarioWallet, WalletBalance,
+        },
+    },
+    solana::{rpc::SolanaRpcExt, solana_impl::SolanaImpl, Solana},
+    tickets::{
+        bonus::{BonusInfo, BonusSequenceCount, DefaultBonusInfoService},
+        ConstantTicketPriceCalculator, DefaultTicketService, InMemoryManifestPublisher, InMemoryTicketRepository,
+        TicketAllocation, TicketGeneration, TicketRepository, TicketService, Winners,
+    },
+};
+
+/// Recorded as the operator of the epoch operations of a scenario.
+const OPERATOR: &str = "scenario";
+
+/// Plays a scenario end to end against the staking program: stakes move through stake update requests, the epoch
+/// service generates the tickets, invests and publishes the winners, and the program splits the returns and pays the
+/// prizes. The draw goes through the fake VRF.
+pub struct ScenarioRunner {
+    scenario: Scenario,
+    solana: SolanaImpl,
+    tickets: InMemoryTicketRepository,
+    /// Generates the sequences of tickets.
+    ticket_rng: Arc<Mutex<ChaCha20Rng>>,
+    /// Draws the winning combinations.
+    draw_rng: ChaCha20Rng,
+    epochs: EpochService,
+    labels: BTreeMap<Pubkey, ScenarioWallet>,
+}
+
+impl ScenarioRunner {
+    pub async fn new(scenario: Scenario) -> Result<Self> {
+        let solana = start_program().await?;
+        let tickets = InMemoryTicketRepository::new(0);
+        let ticket_rng = Arc::new(Mutex::new(ChaCha20Rng::seed_from_u64(scenario.seed)));
+        let mut draw_rng = ChaCha20Rng::seed_from_u64(scenario.seed);
+        draw_rng.set_stream(1);
+        let epochs = EpochService::new(
+            Box::new(solana.clone()),
+            Box::new(InMemoryEpochRepository::new()),
+            Box::new(tickets.clone()),
+            Box::new(ticket_service(&scenario, &solana, &tickets, &ticket_rng)),
+            Box::new(InMemoryManifestPublisher::default()),
+            Box::new(InMemoryEpochAuditRepository::default()),
+            Box::new(InMemoryEpochJobRepository::default()),
+            EventBus::default(),
+        );
+        Ok(Self {
+            scenario,
+            solana,
+            tickets,
+            ticket_rng,
+            draw_rng,
+            epochs,
+            labels: BTreeMap::new(),
+        })
+    }
+
+    pub async fn run(mut self) -> Result<ScenarioReport> {
+        let mut epochs = Vec::new();
+        for epoch in self.scenario.epochs.clone() {
+            epochs.push(self.run_epoch(&epoch).await?);
+        }
+        let mut balances = self
+            .solana
+            .get_all_stakes()
+            .await?
+            .into_iter()
+            .map(|stake| WalletBalance {
+                wallet: self.label(&stake.owner),
+                address: stake.owner.to_string(),
+                balance: stake.amount,
+            })
+            .collect::<Vec<_>>();
+        balances.sort_by(|a, b| a.wallet.cmp(&b.wallet));
+        Ok(ScenarioReport {
+            seed: self.scenario.seed,
+            epochs,
+            balances,
+        })
+    }
+
+    async fn run_epoch(&mut self, epoch: &ScenarioEpoch) -> Result<EpochReport> {
+        let created = self
+            .epochs
+            .create_epoch(
+                Utc::now() + Duration::hours(1),
+                (&self.scenario.yield_split_cfg).into(),
+                OPERATOR,
+            )
+            .await?;
+        let epoch_index = created.index;
+        log::info!("Playing epoch {epoch_index}");
+        for deposit in &epoch.deposits {
+            self.deposit(deposit).await?;
+        }
+        for withdrawal in &epoch.withdrawals {
+            self.withdraw(withdrawal).await?;
+        }
+
+        // Tickets are sized for the final stakes when the epoch enters investment
+        let invested = self.epochs.enter_investment(Investor::Fake, OPERATOR).await?;
+        let total_invested = invested.total_invested.unwrap_or_else(FPUSDC::zero);
+        let num_tickets = self.tickets.num_sequences_by_epoch_index(epoch_index).await?;
+
+        let return_amount = if total_invested == FPUSDC::zero() {
+            FPUSDC::zero()
+        } else {
+            total_invested
+                .checked_mul(fp(1.0 + epoch.yield_percent / 100.0))
+                .ok_or(ScenarioError::Overflow)?
+        };
+        let yielded = self
+            .epochs
+            .exit_investment(Investor::Fake, Some(return_amount), OPERATOR)
+            .await?;
+        let returns = yielded.returns.ok_or(ScenarioError::ReturnsNotSet(epoch_index))?;
+        let draw_enabled = yielded.draw_enabled.unwrap_or(false);
+
+        let mut winning_combination = None;
+        let winners = if draw_enabled {
+            let tickets = self.tickets.by_epoch_index(epoch_index).await?;
+            let combination = resolve_draw(&mut self.draw_rng, &epoch.draw, epoch_index, &tickets)?;
+            self.epochs.publish_winning_combination(&combination).await?;
+            winning_combination = Some(combination);
+            self.ticket_service().calculate_winners().await?
+        } else {
+            Winners {
+                tier1: BTreeSet::new(),
+                tier2: BTreeMap::new(),
+                tier3: BTreeMap::new(),
+            }
+        };
+        let (_, winners_input) = winners_input(&winners);
+        let has_jackpot_winners = !winners.tier1.is_empty();
+        self.epochs.publish_winners(winners, OPERATOR).await?;
+        if epoch.fund_jackpot && has_jackpot_winners {
+            self.epochs.fund_jackpot(OPERATOR).await?;
+        }
+
+        let num_winning_tickets = winners_input
+            .iter()
+            .map(|winner| ((winner.address, winner.tier), winner.num_winning_tickets))
+            .collect::<BTreeMap<_, _>>();
+        let payouts = match self.epochs.read_epoch_prizes(epoch_index).await? {
+            Some(prizes) => prizes
+                .winners
+                .iter()
+                .map(|winner| Payout {
+                    wallet: self.label(&winner.address),
+                    address: winner.address.to_string(),
+                    tier: winner.tier,
+                    num_winning_tickets: num_winning_tickets
+                        .get(&(winner.address, winner.tier))
+                        .copied()
+                        .unwrap_or_default(),
+                    prize: winner.prize,
+                    claimable: winner.tier != 1 || prizes.jackpot_claimable,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let pending_funds = self.solana.get_latest_epoch().await?.pending_funds.clone();
+        Ok(EpochReport {
+            epoch_index,
+            num_tickets,
+            total_invested,
+            yield_percent: epoch.yield_percent,
+            return_amount,
+            deposit_back: returns.deposit_back,
+            insurance: returns.insurance,
+            treasury: returns.treasury,
+            tier2_prize: returns.tier2_prize,
+            tier3_prize: returns.tier3_prize,
+            draw_enabled,
+            winning_combination,
+            payouts,
+            pending_insurance: pending_funds.insurance,
+            pending_tier2_prize: pending_funds.tier2_prize,
+            pending_tier3_prize: pending_funds.tier3_prize,
+        })
+    }
+
+    /// A ticket service over the tickets of the scenario and the stakes of the program.
+    fn ticket_service(&self) -> DefaultTicketService<ChaCha20Rng> {
+        ticket_service(&self.scenario, &self.solana, &self.tickets, &self.ticket_rng)
+    }
+
+    async fn deposit(&mut self, deposit: &ScenarioTransfer) -> Result<()> {
+        let owner = deposit.wallet.pubkey();
+        self.labels.insert(owner, deposit.wallet.clone());
+        self.solana.mint_usdc(owner, deposit.amount).await?;
+        self.update_stake(&deposit.wallet, deposit.amount.as_usdc_i64()).await
+    }
+
+    async fn withdraw(&mut self, withdrawal: &ScenarioTransfer) -> Result<()> {
+        let owner = withdrawal.wallet.pubkey();
+        let balance = self
+            .solana
+            .get_all_stakes()
+            .await?
+            .into_iter()
+            .find(|stake| stake.owner == owner)
+            .map_or(FPUSDC::zero(), |stake| stake.amount);
+        if balance < withdrawal.amount {
+            return Err(ScenarioError::InsufficientStake {
+                wallet: withdrawal.wallet.0.clone(),
+                amount: withdrawal.amount,
+                balance,
+            }
+            .into());
+        }
+        self.update_stake(&withdrawal.wallet, -withdrawal.amount.as_usdc_i64())
+            .await
+    }
+
+    /// Requests the stake update as the wallet, then approves and completes it as the admin.
+    async fn update_stake(&self, wallet: &ScenarioWallet, amount: i64) -> Result<()> {
+        let keypair = wallet
+            .keypair()
+            .ok_or_else(|| ScenarioError::UnknownKeypair(wallet.0.clone()))?;
+        let owner = keypair.pubkey();
+        let rpc = &self.solana.rpc_client;
+        rpc.request_airdrop(owner, LAMPORTS_PER_SOL).await?;
+        let ata = get_associated_token_address(&owner, &self.solana.usdc_mint());
+        rpc.send_and_confirm_transaction(
+            &keypair,
+            &[instruction::request_stake_update(
+                &self.solana.program_id(),
+                &owner,
+                &ata,
+                amount,
+            )],
+        )
+        .await?;
+        self.solana.approve_stake_update(owner, amount).await?;
+        self.solana.complete_stake_update(owner).await?;
+        Ok(())
+    }
+
+    fn label(&self, wallet: &Pubkey) -> ScenarioWallet {
+        self.labels
+            .get(wallet)
+            .cloned()
+            .unwrap_or_else(|| ScenarioWallet(wallet.to_string()))
+    }
+}
+
+fn ticket_service(
+    scenario: &Scenario,
+    solana: &SolanaImpl,
+    tickets: &InMemoryTicketRepository,
+    rng: &Arc<Mutex<ChaCha20Rng>>,
+) -> DefaultTicketService<ChaCha20Rng> {
+    DefaultTicketService::new(
+        rng.clone(),
+        Box::new(solana.clone()),
+        Box::new(tickets.clone()),
+        Box::new(ConstantTicketPriceCalculator::new(scenario.ticket_price)),
+        Box::new(DefaultBonusInfoService::new(BonusInfo {
+            sub_seq_count: BonusSequenceCount::Constant(0),
+            sub_seq_min_stake: FPUSDC::zero(),
+        })),
+        EventBus::default(),
+        TicketGeneration::Random,
+        TicketAllocation::Snapshot,
+        Box::new(DefaultCampaignService::new(
+            Box::new(InMemoryCampaignRepository::default()),
+            Box::new(tickets.clone()),
+        )),
+    )
+}
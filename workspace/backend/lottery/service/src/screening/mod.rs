@@ -0,0 +1,84 @@
+//! AML screening of stake update requests before they are approved on chain.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use solana_program::pubkey::Pubkey;
+use uuid::Uuid;
+
+use crate::model::{
+    stake_update::StakeUpdate,
+    stake_update_review::{StakeUpdateReview, StakeUpdateReviewStatus},
+};
+
+mod review;
+pub use review::*;
+mod sanctions;
+pub use sanctions::*;
+mod velocity;
+pub use velocity::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Screening {
+    Clear,
+    Flagged { reason: String },
+}
+
+#[async_trait]
+pub trait StakeUpdateScreener: Sync + Send {
+    async fn screen(&self, stake_update: &StakeUpdate) -> Result<Screening>;
+}
+
+/// Clears every request.
+pub struct NoScreening;
+
+#[async_trait]
+impl StakeUpdateScreener for NoScreening {
+    async fn screen(&self, _stake_update: &StakeUpdate) -> Result<Screening> {
+        Ok(Screening::Clear)
+    }
+}
+
+/// Runs every screener in order and returns the first flag.
+pub struct ScreenerChain {
+    screeners: Vec<Box<dyn StakeUpdateScreener>>,
+}
+
+impl ScreenerChain {
+    pub fn new(screeners: Vec<Box<dyn StakeUpdateScreener>>) -> Self {
+        Self { screeners }
+    }
+}
+
+#[async_trait]
+impl StakeUpdateScreener for ScreenerChain {
+    async fn screen(&self, stake_update: &StakeUpdate) -> Result<Screening> {
+        for screener in &self.screeners {
+            if let flagged @ Screening::Flagged { .. } = screener.screen(stake_update).await? {
+                return Ok(flagged);
+            }
+        }
+        Ok(Screening::Clear)
+    }
+}
+
+#[async_trait]
+pub trait StakeUpdateReviewRepository: Sync + Send {
+    async fn create(&self, review: &StakeUpdateReview) -> Result<StakeUpdateReview>;
+    async fn by_id(&self, id: &Uuid) -> Result<Option<StakeUpdateReview>>;
+    async fn pending_by_wallet(&self, wallet: &Pubkey) -> Result<Option<StakeUpdateReview>>;
+    async fn by_status(
+        &self,
+        status: StakeUpdateReviewStatus,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<StakeUpdateReview>>;
+    /// Resolves a pending review. Returns `None` if it isn't pending.
+    async fn resolve(
+        &self,
+        id: &Uuid,
+        status: StakeUpdateReviewStatus,
+        reviewed_by: &str,
+        reviewed_at: DateTime<Utc>,
+    ) -> Result<Option<StakeUpdateReview>>;
+}
@@ -0,0 +1,388 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{Screening, StakeUpdateReviewRepository, StakeUpdateScreener};
+use crate::{
+    model::{
+        stake_update::StakeUpdate,
+        stake_update_review::{ScreeningError, StakeUpdateReview, StakeUpdateReviewStatus},
+    },
+    solana::Solana,
+};
+
+/// Recorded as the reviewer of the reviews screening finds stale.
+const SCREENING_REVIEWER: &str = "screening";
+
+/// Puts the requests flagged by `screener` in the manual review queue.
+/// A request stays flagged while its review is pending. A pending review for another request of the wallet is stale,
+/// and the request is screened again.
+pub struct ManualReviewScreener {
+    screener: Box<dyn StakeUpdateScreener>,
+    repository: Box<dyn StakeUpdateReviewRepository>,
+}
+
+impl ManualReviewScreener {
+    pub fn new(screener: Box<dyn StakeUpdateScreener>, repository: Box<dyn StakeUpdateReviewRepository>) -> Self {
+        Self { screener, repository }
+    }
+}
+
+#[async_trait]
+impl StakeUpdateScreener for ManualReviewScreener {
+    async fn screen(&self, stake_update: &StakeUpdate) -> Result<Screening> {
+        if let Some(review) = self.repository.pending_by_wallet(&stake_update.owner).await? {
+            if review.is_for(stake_update) {
+                return Ok(Screening::Flagged { reason: review.reason });
+            }
+            log::info!("Stake update request of review {} was cancelled or replaced", review.id);
+            self.repository
+                .resolve(
+                    &review.id,
+                    StakeUpdateReviewStatus::Stale,
+                    SCREENING_REVIEWER,
+                    Utc::now(),
+                )
+                .await?;
+        }
+
+        let screening = self.screener.screen(stake_update).await?;
+        if let Screening::Flagged { reason } = &screening {
+            log::info!("Flagged stake update of {} for review: {}", stake_update.owner, reason);
+            self.repository
+                .create(&StakeUpdateReview {
+                    id: Uuid::new_v4(),
+                    wallet: stake_update.owner,
+                    amount: stake_update.amount,
+                    type_: stake_update.type_.clone(),
+                    reason: reason.clone(),
+                    status: StakeUpdateReviewStatus::Pending,
+                    created_at: Utc::now(),
+                    reviewed_by: None,
+                    reviewed_at: None,
+                })
+                .await?;
+        }
+        Ok(screening)
+    }
+}
+
+#[async_trait]
+pub trait StakeUpdateReviewService: Sync + Send {
+    async fn reviews(
+        &self,
+        status: StakeUpdateReviewStatus,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<StakeUpdateReview>>;
+    /// Approves the stake update request on chain. Fails if the request was cancelled or replaced since it was flagged.
+    async fn approve(&self, id: &Uuid, reviewed_by: &str) -> Result<StakeUpdateReview>;
+    /// Cancels the stake update request on chain, which returns the deposit to the wallet. Only the reviewed request is
+    /// cancelled.
+    async fn reject(&self, id: &Uuid, reviewed_by: &str) -> Result<StakeUpdateReview>;
+}
+
+pub struct DefaultStakeUpdateReviewService {
+    solana: Box<dyn Solana>,
+    repository: Box<dyn StakeUpdateReviewRepository>,
+}
+
+impl DefaultStakeUpdateReviewService {
+    pub fn new(solana: Box<dyn Solana>, repository: Box<dyn StakeUpdateReviewRepository>) -> Self {
+        Self { solana, repository }
+    }
+
+    async fn pending_review(&self, id: &Uuid) -> Result<StakeUpdateReview> {
+        let review = self
+            .repository
+            .by_id(id)
+            .await?
+            .ok_or(ScreeningError::ReviewNotFound(*id))?;
+        if review.status != StakeUpdateReviewStatus::Pending {
+            return Err(ScreeningError::ReviewAlreadyResolved(*id).into());
+        }
+        Ok(review)
+    }
+
+    /// Whether the request of the wallet on chain is the reviewed one, if it has any.
+    async fn request_is_reviewed(&self, review: &StakeUpdateReview) -> Result<Option<bool>> {
+        let request = self.solana.get_stake_update_request_by_wallet(review.wallet).await?;
+        Ok(request.map(|request| request.amount == review.signed_amount()))
+    }
+
+    async fn stale(&self, id: &Uuid, reviewed_by: &str) -> Result<StakeUpdateReview> {
+        self.resolve(id, StakeUpdateReviewStatus::Stale, reviewed_by).await?;
+        Err(ScreeningError::ReviewStale(*id).into())
+    }
+
+    async fn resolve(
+        &self,
+        id: &Uuid,
+        status: StakeUpdateReviewStatus,
+        reviewed_by: &str,
+    ) -> Result<StakeUpdateReview> {
+        Ok(self
+            .repository
+            .resolve(id, status, reviewed_by, Utc::now())
+            .await?
+            .ok_or(ScreeningError::ReviewAlreadyResolved(*id))?)
+    }
+}
+
+#[async_trait]
+impl StakeUpdateReviewService for DefaultStakeUpdateReviewService {
+    async fn reviews(
+        &self,
+        status: StakeUpdateReviewStatus,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<StakeUpdateReview>> {
+        self.repository.by_status(status, limit, offset).await
+    }
+
+    async fn approve(&self, id: &Uuid, reviewed_by: &str) -> Result<StakeUpdateReview> {
+        let review = self.pending_review(id).await?;
+        if self.request_is_reviewed(&review).await? != Some(true) {
+            return self.stale(id, reviewed_by).await;
+        }
+        self.solana
+            .approve_stake_update(review.wallet, review.signed_amount())
+            .await?;
+        self.resolve(id, StakeUpdateReviewStatus::Approved, reviewed_by).await
+    }
+
+    async fn reject(&self, id: &Uuid, reviewed_by: &str) -> Result<StakeUpdateReview> {
+        let review = self.pending_review(id).await?;
+        match self.request_is_reviewed(&review).await? {
+            Some(true) => {
+                self.solana
+                    .cancel_stake_update(review.wallet, review.signed_amount())
+                    .await?;
+            }
+            // The user cancelled the request already
+            None => {}
+            Some(false) => return self.stale(id, reviewed_by).await,
+        }
+        self.resolve(id, StakeUpdateReviewStatus::Rejected, reviewed_by).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+
+    use chrono::DateTime;
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    use crate::{
+        model::stake_update::{StakeUpdateState, StakeUpdateType},
+        screening::SanctionsListScreener,
+        solana::{mock::SolanaMock, StakeUpdateState as SolanaStakeUpdateState},
+    };
+
+    #[derive(Default, Clone)]
+    struct InMemoryStakeUpdateReviewRepository {
+        reviews: Arc<Mutex<Vec<StakeUpdateReview>>>,
+    }
+
+    #[async_trait]
+    impl StakeUpdateReviewRepository for InMemoryStakeUpdateReviewRepository {
+        async fn create(&self, review: &StakeUpdateReview) -> Result<StakeUpdateReview> {
+            self.reviews.lock().unwrap().push(review.clone());
+            Ok(review.clone())
+        }
+
+        async fn by_id(&self, id: &Uuid) -> Result<Option<StakeUpdateReview>> {
+            Ok(self.reviews.lock().unwrap().iter().find(|r| &r.id == id).cloned())
+        }
+
+        async fn pending_by_wallet(&self, wallet: &Pubkey) -> Result<Option<StakeUpdateReview>> {
+            Ok(self
+                .reviews
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|r| &r.wallet == wallet && r.status == StakeUpdateReviewStatus::Pending)
+                .cloned())
+        }
+
+        async fn by_status(
+            &self,
+            status: StakeUpdateReviewStatus,
+            limit: usize,
+            offset: usize,
+        ) -> Result<Vec<StakeUpdateReview>> {
+            Ok(self
+                .reviews
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| r.status == status)
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect())
+        }
+
+        async fn resolve(
+            &self,
+            id: &Uuid,
+            status: StakeUpdateReviewStatus,
+            reviewed_by: &str,
+            reviewed_at: DateTime<Utc>,
+        ) -> Result<Option<StakeUpdateReview>> {
+            let mut reviews = self.reviews.lock().unwrap();
+            Ok(reviews
+                .iter_mut()
+                .find(|r| &r.id == id && r.status == StakeUpdateReviewStatus::Pending)
+                .map(|review| {
+                    review.status = status;
+                    review.reviewed_by = Some(reviewed_by.to_owned());
+                    review.reviewed_at = Some(reviewed_at);
+                    review.clone()
+                }))
+        }
+    }
+
+    fn deposit(wallet: Pubkey) -> StakeUpdate {
+        deposit_of(wallet, "100")
+    }
+
+    fn deposit_of(wallet: Pubkey, amount: &str) -> StakeUpdate {
+        StakeUpdate {
+            owner: wallet,
+            amount: amount.parse().unwrap(),
+            state: StakeUpdateState::Pending,
+            type_: StakeUpdateType::Deposit,
+            currency: "USDC".into(),
+            mint: Pubkey::new_unique(),
+            transaction_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_manual_review_queue() -> Result<()> {
+        let sanctioned = Pubkey::new_unique();
+        let repository = InMemoryStakeUpdateReviewRepository::default();
+        let screener = ManualReviewScreener::new(
+            Box::new(SanctionsListScreener::new(HashSet::from([sanctioned]))),
+            Box::new(repository.clone()),
+        );
+
+        assert_eq!(screener.screen(&deposit(Pubkey::new_unique())).await?, Screening::Clear);
+
+        let screening = screener.screen(&deposit(sanctioned)).await?;
+        assert!(matches!(screening, Screening::Flagged { .. }));
+        // Flagged again without queueing a second review
+        assert_eq!(screener.screen(&deposit(sanctioned)).await?, screening);
+
+        let pending = repository.by_status(StakeUpdateReviewStatus::Pending, 10, 0).await?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].wallet, sanctioned);
+        assert_eq!(pending[0].signed_amount(), 100_000_000);
+        Ok(())
+    }
+
+    /// A review service over the requests of `solana`, with a pending review of a 100 USDC deposit of `wallet`.
+    async fn flagged_deposit(
+        solana: &SolanaMock,
+        wallet: Pubkey,
+    ) -> Result<(
+        DefaultStakeUpdateReviewService,
+        InMemoryStakeUpdateReviewRepository,
+        Uuid,
+    )> {
+        let repository = InMemoryStakeUpdateReviewRepository::default();
+        let screener = ManualReviewScreener::new(
+            Box::new(SanctionsListScreener::new(HashSet::from([wallet]))),
+            Box::new(repository.clone()),
+        );
+        solana.request_stake_update(wallet, 100_000_000);
+        screener.screen(&deposit(wallet)).await?;
+        let review = repository.pending_by_wallet(&wallet).await?.unwrap();
+        let service = DefaultStakeUpdateReviewService::new(Box::new(solana.clone()), Box::new(repository.clone()));
+        Ok((service, repository, review.id))
+    }
+
+    #[tokio::test]
+    async fn test_stale_review() -> Result<()> {
+        let sanctioned = Pubkey::new_unique();
+        let repository = InMemoryStakeUpdateReviewRepository::default();
+        let screener = ManualReviewScreener::new(
+            Box::new(SanctionsListScreener::new(HashSet::from([sanctioned]))),
+            Box::new(repository.clone()),
+        );
+
+        screener.screen(&deposit(sanctioned)).await?;
+        let first = repository.pending_by_wallet(&sanctioned).await?.unwrap();
+
+        // The request was cancelled and another one made
+        let screening = screener.screen(&deposit_of(sanctioned, "50")).await?;
+        assert!(matches!(screening, Screening::Flagged { .. }));
+        let stale = repository.by_id(&first.id).await?.unwrap();
+        assert_eq!(stale.status, StakeUpdateReviewStatus::Stale);
+        let pending = repository.pending_by_wallet(&sanctioned).await?.unwrap();
+        assert_ne!(pending.id, first.id);
+        assert_eq!(pending.signed_amount(), 50_000_000);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_approve_review() -> Result<()> {
+        let solana = SolanaMock::new();
+        let wallet = Pubkey::new_unique();
+        let (service, _, id) = flagged_deposit(&solana, wallet).await?;
+
+        let review = service.approve(&id, "operator").await?;
+        assert_eq!(review.status, StakeUpdateReviewStatus::Approved);
+        assert_eq!(review.reviewed_by.as_deref(), Some("operator"));
+        let request = solana.get_stake_update_request_by_wallet(wallet).await?.unwrap();
+        assert_eq!(request.state, SolanaStakeUpdateState::Queued);
+
+        let result = service.approve(&id, "operator").await;
+        assert!(matches!(
+            result.unwrap_err().downcast_ref(),
+            Some(ScreeningError::ReviewAlreadyResolved(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_review() -> Result<()> {
+        let solana = SolanaMock::new();
+        let wallet = Pubkey::new_unique();
+        let (service, _, id) = flagged_deposit(&solana, wallet).await?;
+
+        let review = service.reject(&id, "operator").await?;
+        assert_eq!(review.status, StakeUpdateReviewStatus::Rejected);
+        assert!(solana.get_stake_update_request_by_wallet(wallet).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_review_of_replaced_request() -> Result<()> {
+        let solana = SolanaMock::new();
+        let wallet = Pubkey::new_unique();
+        let (service, repository, id) = flagged_deposit(&solana, wallet).await?;
+
+        // The user cancelled the request and requested another amount, which neither decision applies to
+        solana.request_stake_update(wallet, 50_000_000);
+        for result in [
+            service.approve(&id, "operator").await,
+            service.reject(&id, "operator").await,
+        ] {
+            assert!(result.is_err());
+        }
+        let request = solana.get_stake_update_request_by_wallet(wallet).await?.unwrap();
+        assert_eq!(request.amount, 50_000_000);
+        assert_eq!(request.state, SolanaStakeUpdateState::PendingApproval);
+        let review = repository.by_id(&id).await?.unwrap();
+        assert_eq!(review.status, StakeUpdateReviewStatus::Stale);
+        Ok(())
+    }
+}
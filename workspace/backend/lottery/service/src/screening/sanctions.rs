@@ -0,0 +1,87 @@
+use std::{collections::HashSet, fs::File, io::BufRead, io::BufReader, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use solana_program::pubkey::Pubkey;
+
+use super::{Screening, StakeUpdateScreener};
+use crate::model::stake_update::StakeUpdate;
+
+/// Flags wallets on a sanctions list.
+pub struct SanctionsListScreener {
+    addresses: HashSet<Pubkey>,
+}
+
+impl SanctionsListScreener {
+    pub fn new(addresses: HashSet<Pubkey>) -> Self {
+        Self { addresses }
+    }
+
+    pub fn from_csv_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Failed to open sanctions list {}", path.display()))?;
+        Self::from_csv(BufReader::new(file))
+    }
+
+    /// Reads the addresses from the first column with "address" in its header.
+    /// Addresses of other chains are skipped.
+    pub fn from_csv(reader: impl BufRead) -> Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines.next().ok_or_else(|| anyhow!("Sanctions list is empty"))??;
+        let column = split_row(&header)
+            .iter()
+            .position(|name| name.to_lowercase().contains("address"))
+            .ok_or_else(|| anyhow!("Sanctions list has no address column"))?;
+
+        let mut addresses = HashSet::new();
+        for line in lines {
+            let line = line?;
+            if let Some(address) = split_row(&line).get(column) {
+                if let Ok(address) = Pubkey::from_str(address) {
+                    addresses.insert(address);
+                }
+            }
+        }
+        log::info!("Loaded {} sanctioned addresses", addresses.len());
+        Ok(Self::new(addresses))
+    }
+}
+
+fn split_row(row: &str) -> Vec<String> {
+    row.split(',')
+        .map(|field| field.trim().trim_matches('"').trim().to_owned())
+        .collect()
+}
+
+#[async_trait]
+impl StakeUpdateScreener for SanctionsListScreener {
+    async fn screen(&self, stake_update: &StakeUpdate) -> Result<Screening> {
+        Ok(if self.addresses.contains(&stake_update.owner) {
+            Screening::Flagged {
+                reason: "Wallet is on the sanctions list".to_owned(),
+            }
+        } else {
+            Screening::Clear
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_csv() {
+        let sanctioned = Pubkey::new_unique();
+        let csv = format!(
+            "ent_num,SDN_Name,Digital Currency Address\n\
+             1,\"SOME ENTITY\",\"{}\"\n\
+             2,\"OTHER ENTITY\",0x8589427373d6d84e98730d7795d8f6f8731fda16\n",
+            sanctioned
+        );
+        let screener = SanctionsListScreener::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(screener.addresses, HashSet::from([sanctioned]));
+
+        assert!(SanctionsListScreener::from_csv("name,country\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+
+use super::{Screening, StakeUpdateScreener};
+use crate::{
+    model::{
+        stake_update::{StakeUpdate, StakeUpdateType},
+        transaction::{Transaction, TransactionType},
+    },
+    transaction::UserTransactionRepository,
+};
+
+/// Most recent approvals that are looked at.
+const HISTORY_LIMIT: usize = 100;
+
+/// Flags wallets that get too many stake updates, or too much in total, approved within a window.
+/// Deposits and withdrawals are counted separately.
+pub struct VelocityScreener {
+    transactions: Box<dyn UserTransactionRepository>,
+    window: Duration,
+    max_requests: usize,
+    max_amount: FPUSDC,
+}
+
+impl VelocityScreener {
+    pub fn new(
+        transactions: Box<dyn UserTransactionRepository>,
+        window: Duration,
+        max_requests: usize,
+        max_amount: FPUSDC,
+    ) -> Self {
+        Self {
+            transactions,
+            window,
+            max_requests,
+            max_amount,
+        }
+    }
+
+    fn check(&self, stake_update: &StakeUpdate, approved: &[Transaction], now: DateTime<Utc>) -> Screening {
+        let since = now - self.window;
+        let recent: Vec<_> = approved
+            .iter()
+            .filter(|transaction| transaction.time.map_or(false, |time| time > since))
+            .collect();
+
+        if recent.len() + 1 > self.max_requests {
+            return Screening::Flagged {
+                reason: format!(
+                    "More than {} stake updates within {} hours",
+                    self.max_requests,
+                    self.window.num_hours()
+                ),
+            };
+        }
+
+        let total = recent.iter().try_fold(stake_update.amount, |total, transaction| {
+            total.checked_add(transaction.amount)
+        });
+        match total {
+            Some(total) if total <= self.max_amount => Screening::Clear,
+            _ => Screening::Flagged {
+                reason: format!(
+                    "More than {} USDC within {} hours",
+                    self.max_amount,
+                    self.window.num_hours()
+                ),
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl StakeUpdateScreener for VelocityScreener {
+    async fn screen(&self, stake_update: &StakeUpdate) -> Result<Screening> {
+        let transaction_type = match stake_update.type_ {
+            StakeUpdateType::Deposit => TransactionType::DepositApproved,
+            StakeUpdateType::Withdraw => TransactionType::WithdrawApproved,
+        };
+        let approved = self
+            .transactions
+            .by_wallet_and_type(&stake_update.owner, transaction_type, HISTORY_LIMIT, 0)
+            .await?;
+        Ok(self.check(stake_update, &approved, Utc::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    use crate::model::{
+        stake_update::StakeUpdateState,
+        transaction::{Transaction, TransactionId},
+    };
+
+    struct NoTransactions;
+
+    #[async_trait]
+    impl UserTransactionRepository for NoTransactions {
+        async fn by_transaction_id_and_instruction_index(
+            &self,
+            _transaction_id: &TransactionId,
+            _instruction_index: u8,
+            _inner_instruction_index: Option<u8>,
+        ) -> Result<Option<Transaction>> {
+            Ok(None)
+        }
+        async fn by_transaction_id(&self, _transaction_id: &TransactionId) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+        async fn by_wallet(&self, _wallet: &Pubkey, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+        async fn by_type(
+            &self,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+        async fn by_wallet_and_type(
+            &self,
+            _wallet: &Pubkey,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+        async fn all(&self, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+        async fn store_transaction(&self, _transaction: &Transaction) -> Result<()> {
+            Ok(())
+        }
+        async fn store_transactions(&self, _transactions: &[Transaction]) -> Result<()> {
+            Ok(())
+        }
+        async fn remove_unfinalized(&self, _up_to_slot: u64) -> Result<u64> {
+            Ok(0)
+        }
+        async fn total_deposit_by_wallet(&self, _wallet: &Pubkey) -> Result<FPUSDC> {
+            Ok(FPUSDC::zero())
+        }
+        async fn balance_changes_by_wallet(&self, _wallet: &Pubkey, _since: DateTime<Utc>) -> Result<Vec<Transaction>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn deposit(wallet: Pubkey, amount: &str) -> StakeUpdate {
+        StakeUpdate {
+            owner: wallet,
+            amount: amount.parse().unwrap(),
+            state: StakeUpdateState::Pending,
+            type_: StakeUpdateType::Deposit,
+            currency: "USDC".into(),
+            mint: Pubkey::new_unique(),
+            transaction_id: None,
+        }
+    }
+
+    fn approved(wallet: Pubkey, amount: &str, time: DateTime<Utc>) -> Transaction {
+        Transaction {
+            transaction_id: TransactionId(Pubkey::new_unique().to_string()),
+            instruction_index: 0,
+            wallet,
+            amount: amount.parse().unwrap(),
+            mint: Pubkey::new_unique(),
+            time: Some(time),
+            transaction_type: TransactionType::DepositApproved,
+            inner_instruction_index: None,
+            slot: None,
+            finalized: true,
+        }
+    }
+
+    #[test]
+    fn test_velocity() {
+        let screener = VelocityScreener::new(
+            Box::new(NoTransactions),
+            Duration::hours(24),
+            3,
+            "1000".parse().unwrap(),
+        );
+        let wallet = Pubkey::new_unique();
+        let now = Utc::now();
+
+        assert_eq!(screener.check(&deposit(wallet, "1000"), &[], now), Screening::Clear);
+        assert!(matches!(
+            screener.check(&deposit(wallet, "1000.01"), &[], now),
+            Screening::Flagged { .. }
+        ));
+
+        let history = [
+            approved(wallet, "400", now - Duration::hours(1)),
+            approved(wallet, "400", now - Duration::hours(2)),
+            // Outside of the window
+            approved(wallet, "400", now - Duration::hours(25)),
+        ];
+        assert_eq!(screener.check(&deposit(wallet, "200"), &history, now), Screening::Clear);
+        assert!(matches!(
+            screener.check(&deposit(wallet, "201"), &history, now),
+            Screening::Flagged { .. }
+        ));
+
+        let history = [
+            approved(wallet, "1", now - Duration::hours(1)),
+            approved(wallet, "1", now - Duration::hours(2)),
+            approved(wallet, "1", now - Duration::hours(3)),
+        ];
+        assert!(matches!(
+            screener.check(&deposit(wallet, "1"), &history, now),
+            Screening::Flagged { .. }
+        ));
+    }
+}
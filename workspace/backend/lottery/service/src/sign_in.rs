@@ -0,0 +1,146 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::sync::Mutex;
+
+use crate::model::login_challenge::{LoginChallenge, SignInError};
+
+#[async_trait]
+pub trait LoginChallengeRepository: Sync + Send {
+    async fn store(&self, challenge: &LoginChallenge) -> Result<()>;
+    /// Delete and return the challenge if it exists and hasn't expired at `now`.
+    async fn take(&self, wallet: &Pubkey, nonce: &str, now: DateTime<Utc>) -> Result<Option<LoginChallenge>>;
+}
+
+#[async_trait]
+pub trait SignInService: Sync + Send {
+    async fn challenge(&self, wallet: &Pubkey) -> Result<LoginChallenge>;
+    fn message(&self, challenge: &LoginChallenge) -> String;
+    /// Succeeds if the wallet signed the message of an outstanding challenge.
+    /// A challenge can only be used once.
+    async fn verify(&self, wallet: &Pubkey, nonce: &str, signature: &Signature) -> Result<()>;
+}
+
+pub struct DefaultSignInService {
+    repository: Box<dyn LoginChallengeRepository>,
+    domain: String,
+    challenge_ttl: Duration,
+}
+
+impl DefaultSignInService {
+    pub fn new(repository: Box<dyn LoginChallengeRepository>, domain: String, challenge_ttl: Duration) -> Self {
+        Self {
+            repository,
+            domain,
+            challenge_ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl SignInService for DefaultSignInService {
+    async fn challenge(&self, wallet: &Pubkey) -> Result<LoginChallenge> {
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let issued_at = Utc::now();
+        let challenge = LoginChallenge {
+            wallet: *wallet,
+            nonce,
+            issued_at,
+            expires_at: issued_at + self.challenge_ttl,
+        };
+        self.repository.store(&challenge).await?;
+        Ok(challenge)
+    }
+
+    fn message(&self, challenge: &LoginChallenge) -> String {
+        challenge.message(&self.domain)
+    }
+
+    async fn verify(&self, wallet: &Pubkey, nonce: &str, signature: &Signature) -> Result<()> {
+        let challenge = self
+            .repository
+            .take(wallet, nonce, Utc::now())
+            .await?
+            .ok_or(SignInError::InvalidChallenge)?;
+        if !signature.verify(wallet.as_ref(), self.message(&challenge).as_bytes()) {
+            return Err(SignInError::InvalidSignature.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryLoginChallengeRepository {
+    challenges: Mutex<Vec<LoginChallenge>>,
+}
+
+#[async_trait]
+impl LoginChallengeRepository for InMemoryLoginChallengeRepository {
+    async fn store(&self, challenge: &LoginChallenge) -> Result<()> {
+        self.challenges.lock().unwrap().push(challenge.clone());
+        Ok(())
+    }
+
+    async fn take(&self, wallet: &Pubkey, nonce: &str, now: DateTime<Utc>) -> Result<Option<LoginChallenge>> {
+        let mut challenges = self.challenges.lock().unwrap();
+        let position = challenges
+            .iter()
+            .position(|c| &c.wallet == wallet && c.nonce == nonce && c.expires_at > now);
+        Ok(position.map(|i| challenges.remove(i)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    fn service(challenge_ttl: Duration) -> DefaultSignInService {
+        DefaultSignInService::new(
+            Box::new(InMemoryLoginChallengeRepository::default()),
+            "nezha.test".to_string(),
+            challenge_ttl,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_sign_in() {
+        let service = service(Duration::minutes(5));
+        let keypair = Keypair::new();
+        let wallet = keypair.pubkey();
+
+        let challenge = service.challenge(&wallet).await.unwrap();
+        let message = service.message(&challenge);
+        assert!(message.starts_with("nezha.test wants you to sign in with your Solana account:"));
+        assert!(message.contains(&wallet.to_string()));
+
+        // Signed by another wallet
+        let signature = Keypair::new().sign_message(message.as_bytes());
+        assert!(service.verify(&wallet, &challenge.nonce, &signature).await.is_err());
+
+        // The failed attempt used up the challenge
+        let challenge = service.challenge(&wallet).await.unwrap();
+        let signature = keypair.sign_message(service.message(&challenge).as_bytes());
+        service.verify(&wallet, &challenge.nonce, &signature).await.unwrap();
+
+        // Replay
+        assert!(service.verify(&wallet, &challenge.nonce, &signature).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expired_challenge() {
+        let service = service(Duration::zero());
+        let keypair = Keypair::new();
+        let wallet = keypair.pubkey();
+
+        let challenge = service.challenge(&wallet).await.unwrap();
+        let signature = keypair.sign_message(service.message(&challenge).as_bytes());
+        assert!(service.verify(&wallet, &challenge.nonce, &signature).await.is_err());
+    }
+}
@@ -42,6 +42,8 @@ pub enum SolanaError {
     // Specific errors
     #[error("Account not found: {0}")]
     AccountNotFound(AccountNotFound),
+    #[error("Published winners of epoch {epoch_index} don't match the calculated winners: {reason}")]
+    WinnersMismatch { epoch_index: u64, reason: String },
 }
 
 pub trait ToSolanaError<T>
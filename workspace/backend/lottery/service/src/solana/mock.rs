@@ -1,10 +1,19 @@
 #![allow(unused)]
 
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 use nezha_staking::fixed_point::FPInternal;
-use nezha_staking::state::{CumulativeReturnRate, EpochStatus, InsuranceCfg, LatestEpoch, PendingFunds, Pubkeys};
+use nezha_staking::state::{
+    CreationSlot, CumulativeReturnRate, EpochStatus, InsuranceCfg, LatestEpoch, PendingFunds, Pubkeys,
+};
+use nezha_vrf_lib::{
+    accounts::AccountType as VrfAccountType,
+    state::{ContractVersion as VrfContractVersion, NezhaVrfRequestStatus},
+};
 use solana_program::pubkey::Pubkey;
 
 use super::*;
@@ -15,6 +24,16 @@ pub struct SolanaMock {
     pub stakes: Vec<Stake>,
     pub epoch_index: u64,
     pub epoch_status: EpochStatus,
+    pub pending_funds: PendingFunds,
+    pub yield_split_cfg: YieldSplitCfg,
+    pub draw_enabled: Option<bool>,
+    /// Start and expected end of the epoch, in seconds since the Unix epoch.
+    pub epoch_start_at: i64,
+    pub epoch_expected_end_at: i64,
+    /// Winning combinations set with the fake VRF, by epoch index. Clones share them.
+    pub winning_combinations: Arc<Mutex<BTreeMap<u64, [u8; 6]>>>,
+    /// Stake update requests by wallet. Clones share them.
+    pub stake_update_requests: Arc<Mutex<BTreeMap<Pubkey, StakeUpdateRequest>>>,
 }
 
 impl SolanaMock {
@@ -23,8 +42,39 @@ impl SolanaMock {
             stakes: Vec::new(),
             epoch_index: 0,
             epoch_status: EpochStatus::Ended,
+            pending_funds: PendingFunds::default(),
+            yield_split_cfg: YieldSplitCfg {
+                jackpot: "1".parse().unwrap(),
+                insurance: InsuranceCfg {
+                    premium: "1".parse().unwrap(),
+                    probability: "1".parse().unwrap(),
+                },
+                treasury_ratio: "0.5".parse().unwrap(),
+                tier2_prize_share: 1,
+                tier3_prize_share: 1,
+            },
+            draw_enabled: None,
+            epoch_start_at: 0,
+            epoch_expected_end_at: 0,
+            winning_combinations: Arc::default(),
+            stake_update_requests: Arc::default(),
         }
     }
+
+    /// Requests a stake update as `wallet` would, replacing its pending request.
+    pub fn request_stake_update(&self, wallet: Pubkey, amount: i64) {
+        self.stake_update_requests.lock().unwrap().insert(
+            wallet,
+            StakeUpdateRequest {
+                account_type: nezha_staking::state::AccountType::StakeUpdateRequest,
+                contract_version: nezha_staking::state::ContractVersion::V1,
+                is_initialized: true,
+                owner: wallet,
+                amount,
+                state: StakeUpdateState::PendingApproval,
+            },
+        );
+    }
 }
 
 #[async_trait]
@@ -33,17 +83,38 @@ impl Solana for SolanaMock {
         unimplemented!()
     }
     async fn get_epoch_vrf_request(&self, epoch_index: u64) -> Result<WithPubkey<NezhaVrfRequest>, SolanaError> {
-        unimplemented!()
+        let winning_combination = self.winning_combinations.lock().unwrap().get(&epoch_index).copied();
+        Ok(WithPubkey {
+            pubkey: Pubkey::new_unique(),
+            inner: NezhaVrfRequest {
+                account_type: VrfAccountType::NezhaVrfRequest,
+                contract_version: VrfContractVersion::V1,
+                status: match winning_combination {
+                    Some(_) => NezhaVrfRequestStatus::Success,
+                    None => NezhaVrfRequestStatus::Waiting,
+                },
+                vrf_counter: 0,
+                winning_combination,
+                request_start: 0,
+                request_end: None,
+            },
+        })
     }
     fn vrf_configuration(&self) -> VrfConfiguration {
-        unimplemented!()
+        VrfConfiguration::Fake {
+            program_id: Pubkey::default(),
+        }
     }
     async fn set_winning_combination_fake(
         &self,
         epoch_index: u64,
         winning_combination: &[u8; 6],
     ) -> Result<Signature, SolanaError> {
-        unimplemented!()
+        self.winning_combinations
+            .lock()
+            .unwrap()
+            .insert(epoch_index, *winning_combination);
+        Ok(Signature::default())
     }
     fn nezha_vrf_program_id(&self) -> Pubkey {
         unimplemented!()
@@ -82,11 +153,7 @@ impl Solana for SolanaMock {
                 status: self.epoch_status,
                 epoch: Pubkey::new_unique(),
                 cumulative_return_rate: CumulativeReturnRate::unity(),
-                pending_funds: PendingFunds {
-                    insurance: 0u8.into(),
-                    tier2_prize: 0u8.into(),
-                    tier3_prize: 0u8.into(),
-                },
+                pending_funds: self.pending_funds.clone(),
                 pubkeys: Pubkeys {
                     super_admin: Pubkey::new_unique(),
                     admin: Pubkey::new_unique(),
@@ -102,6 +169,13 @@ impl Solana for SolanaMock {
     }
 
     async fn get_epoch_by_index(&self, epoch_index: u64) -> Result<WithPubkey<Epoch>, SolanaError> {
+        // The next epoch isn't created yet
+        if epoch_index > self.epoch_index {
+            return Err(SolanaError::AccountNotFound(AccountNotFound::Epoch {
+                pubkey: Pubkey::new_unique(),
+                index: Some(epoch_index),
+            }));
+        }
         Ok(WithPubkey {
             pubkey: Pubkey::new_unique(),
             inner: Epoch {
@@ -110,23 +184,15 @@ impl Solana for SolanaMock {
                 is_initialized: true,
                 index: self.epoch_index,
                 status: self.epoch_status,
-                yield_split_cfg: YieldSplitCfg {
-                    jackpot: "1".parse().unwrap(),
-                    insurance: InsuranceCfg {
-                        premium: "1".parse().unwrap(),
-                        probability: "1".parse().unwrap(),
-                    },
-                    treasury_ratio: "0.5".parse().unwrap(),
-                    tier2_prize_share: 1,
-                    tier3_prize_share: 1,
-                },
-                start_at: 0,
-                expected_end_at: 0,
+                yield_split_cfg: self.yield_split_cfg.clone(),
+                start_at: self.epoch_start_at,
+                expected_end_at: self.epoch_expected_end_at,
                 tickets_info: None,
                 total_invested: None,
                 returns: None,
-                draw_enabled: None,
+                draw_enabled: self.draw_enabled,
                 end_at: None,
+                creation_slot: Some(CreationSlot { slot: 0, hash: [0; 32] }),
             },
         })
     }
@@ -155,13 +221,13 @@ impl Solana for SolanaMock {
         &self,
         wallet: Pubkey,
     ) -> Result<Option<StakeUpdateRequest>, SolanaError> {
-        todo!()
+        Ok(self.stake_update_requests.lock().unwrap().get(&wallet).cloned())
     }
     async fn get_all_stakes(&self) -> Result<Vec<Stake>, SolanaError> {
-        todo!()
+        Ok(self.stakes.clone())
     }
     async fn get_all_stake_update_requests(&self) -> Result<Vec<StakeUpdateRequest>, SolanaError> {
-        todo!()
+        Ok(self.stake_update_requests.lock().unwrap().values().cloned().collect())
     }
     // Epoch state progression
     async fn create_epoch(
@@ -173,15 +239,30 @@ impl Solana for SolanaMock {
         todo!()
     }
     async fn approve_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError> {
-        todo!()
+        let mut requests = self.stake_update_requests.lock().unwrap();
+        let request = requests
+            .get_mut(&wallet)
+            .filter(|request| request.amount == amount && request.state == StakeUpdateState::PendingApproval)
+            .context("No stake update request to approve")?;
+        request.state = StakeUpdateState::Queued;
+        Ok(Signature::default())
     }
     async fn complete_stake_update(&self, wallet: Pubkey) -> Result<Signature, SolanaError> {
         todo!()
     }
+    async fn cancel_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError> {
+        let mut requests = self.stake_update_requests.lock().unwrap();
+        requests
+            .get(&wallet)
+            .filter(|request| request.amount == amount)
+            .context("No stake update request to cancel")?;
+        requests.remove(&wallet);
+        Ok(Signature::default())
+    }
     async fn enter_investment_fake(
         &self,
         epoch_index: u64,
-        num_sequences_issued: u64,
+        tickets_info: TicketsInfo,
     ) -> Result<Signature, SolanaError> {
         todo!()
     }
@@ -191,7 +272,7 @@ impl Solana for SolanaMock {
     async fn enter_investment_francium(
         &self,
         epoch_index: u64,
-        num_sequences_issued: u64,
+        tickets_info: TicketsInfo,
     ) -> Result<Signature, SolanaError> {
         todo!()
     }
@@ -204,12 +285,45 @@ impl Solana for SolanaMock {
         draw_enabled: bool,
         meta_args: &CreateEpochWinnersMetaArgs,
         winners_input: &[WinnerInput],
-    ) -> Result<Signature, SolanaError> {
+    ) -> Result<Option<Signature>, SolanaError> {
         todo!()
     }
     async fn fund_jackpot(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError> {
         todo!()
     }
+    /// Fails instructions the epoch's status doesn't allow, like the program does.
+    async fn simulate_epoch_instruction(&self, instruction: &EpochInstruction) -> Result<Simulation, SolanaError> {
+        let (epoch_index, expected_status) = match instruction {
+            EpochInstruction::CreateEpoch { epoch_index, .. } => (epoch_index.saturating_sub(1), EpochStatus::Ended),
+            EpochInstruction::EnterInvestmentFake { epoch_index, .. }
+            | EpochInstruction::EnterInvestmentFrancium { epoch_index, .. } => (*epoch_index, EpochStatus::Running),
+            EpochInstruction::ExitInvestmentFake { epoch_index, .. }
+            | EpochInstruction::ExitInvestmentFrancium { epoch_index } => (*epoch_index, EpochStatus::Yielding),
+            EpochInstruction::PublishWinners { epoch_index, .. } => (*epoch_index, EpochStatus::Finalising),
+            EpochInstruction::FundJackpot { epoch_index, .. } => (*epoch_index, EpochStatus::Ended),
+        };
+        let error = if epoch_index != self.epoch_index {
+            Some(format!(
+                "Epoch index mismatch: expected {}, got {}",
+                self.epoch_index, epoch_index
+            ))
+        } else if self.epoch_status != expected_status {
+            Some(format!(
+                "Invalid epoch status: expected {:?}, got {:?}",
+                expected_status, self.epoch_status
+            ))
+        } else {
+            None
+        };
+        Ok(Simulation {
+            num_transactions: 1,
+            error,
+            logs: Vec::new(),
+            units_consumed: None,
+            account_diffs: Vec::new(),
+            vault_transfers: Vec::new(),
+        })
+    }
 
     async fn get_usdc_balance_by_wallet(&self, wallet: Pubkey) -> Result<FPUSDC, SolanaError> {
         todo!()
@@ -17,6 +17,9 @@ pub mod mock;
 pub mod models;
 pub use models::*;
 
+mod simulation;
+pub use simulation::*;
+
 use async_trait::async_trait;
 pub use error::*;
 
@@ -30,7 +33,7 @@ pub use nezha_staking::{
     fixed_point::FPUSDC,
     state::{
         Epoch, InsuranceCfg, LatestEpoch, Returns, Stake as SolanaStake, StakeUpdateRequest, StakeUpdateState,
-        YieldSplitCfg,
+        TicketsInfo, YieldSplitCfg,
     },
 };
 
@@ -182,25 +185,27 @@ pub trait Solana: Send + Sync {
     ) -> Result<Signature, SolanaError>;
     async fn approve_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError>;
     async fn complete_stake_update(&self, wallet: Pubkey) -> Result<Signature, SolanaError>;
+    async fn cancel_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError>;
     async fn enter_investment_fake(
         &self,
         epoch_index: u64,
-        num_sequences_issued: u64,
+        tickets_info: TicketsInfo,
     ) -> Result<Signature, SolanaError>;
     async fn exit_investment_fake(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError>;
     async fn enter_investment_francium(
         &self,
         epoch_index: u64,
-        num_sequences_issued: u64,
+        tickets_info: TicketsInfo,
     ) -> Result<Signature, SolanaError>;
     async fn exit_investment_francium(&self, epoch_index: u64) -> Result<Signature, SolanaError>;
+    // Returns the signature of the last transaction sent, `None` if the winners were already published.
     async fn publish_winners(
         &self,
         epoch_index: u64,
         draw_enabled: bool,
         meta_args: &CreateEpochWinnersMetaArgs,
         winners_input: &[WinnerInput],
-    ) -> Result<Signature, SolanaError>;
+    ) -> Result<Option<Signature>, SolanaError>;
     // Sets the winning combination on the mock contract.
     async fn set_winning_combination_fake(
         &self,
@@ -209,6 +214,8 @@ pub trait Solana: Send + Sync {
     ) -> Result<Signature, SolanaError>;
     async fn request_winning_combination(&self) -> Result<Signature, SolanaError>;
     async fn fund_jackpot(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError>;
+    /// Simulates the first transaction of an epoch state progression without sending it.
+    async fn simulate_epoch_instruction(&self, instruction: &EpochInstruction) -> Result<Simulation, SolanaError>;
 
     // Custom USDC
     async fn get_usdc_balance_by_wallet(&self, wallet: Pubkey) -> Result<FPUSDC, SolanaError>;
@@ -0,0 +1,259 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use nezha_staking::state::AccountType;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::account::Account as SolanaAccount;
+use solana_sdk::signature::{Keypair, Signature};
+
+use crate::{
+    account_mirror::ProgramAccountRepository,
+    model::program_account::MirroredAccountType,
+    solana::{with_pubkey::WithPubkey, SolanaError, ToSolanaError},
+};
+
+use super::{RpcSimulation, SolanaRpc};
+
+/// Serves the staking program account scans from the account mirror, for services that only read.
+/// Falls back to the RPC when the mirror wasn't reconciled with the chain within `max_staleness`, or the accounts
+/// indexer's subscription wasn't seen alive within `max_silence`, e.g. because the indexer is down. Everything else
+/// goes to the RPC.
+pub struct SolanaRpcMirrored {
+    rpc: Arc<dyn SolanaRpc>,
+    staking_program_id: Pubkey,
+    mirror: Box<dyn ProgramAccountRepository>,
+    max_staleness: Duration,
+    max_silence: Duration,
+}
+
+impl SolanaRpcMirrored {
+    pub fn new(
+        rpc: Arc<dyn SolanaRpc>,
+        staking_program_id: Pubkey,
+        mirror: Box<dyn ProgramAccountRepository>,
+        max_staleness: Duration,
+        max_silence: Duration,
+    ) -> Self {
+        Self {
+            rpc,
+            staking_program_id,
+            mirror,
+            max_staleness,
+            max_silence,
+        }
+    }
+
+    async fn is_fresh(&self) -> Result<bool, SolanaError> {
+        let last_checked = self
+            .mirror
+            .last_checked()
+            .await
+            .context("Failed to get when the account mirror was checked")?;
+        let last_subscribed = self
+            .mirror
+            .last_subscribed()
+            .await
+            .context("Failed to get when the account subscription was seen")?;
+        let now = Utc::now();
+        Ok(
+            last_checked.map_or(false, |last_checked| now - last_checked <= self.max_staleness)
+                && last_subscribed.map_or(false, |last_subscribed| now - last_subscribed <= self.max_silence),
+        )
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for SolanaRpcMirrored {
+    async fn _send_and_confirm_transaction(
+        &self,
+        signers: &[&Keypair],
+        payer: Option<&Pubkey>,
+        instructions: &[Instruction],
+    ) -> Result<Signature, SolanaError> {
+        self.rpc
+            ._send_and_confirm_transaction(signers, payer, instructions)
+            .await
+    }
+
+    async fn simulate_transaction(
+        &self,
+        signers: &[&Keypair],
+        payer: Option<&Pubkey>,
+        instructions: &[Instruction],
+        accounts: &[Pubkey],
+    ) -> Result<RpcSimulation, SolanaError> {
+        self.rpc
+            .simulate_transaction(signers, payer, instructions, accounts)
+            .await
+    }
+
+    async fn get_program_accounts_by_type(
+        &self,
+        program_id: &Pubkey,
+        account_type: AccountType,
+    ) -> Result<Vec<WithPubkey<SolanaAccount>>, SolanaError> {
+        let mirrored_account_type = match MirroredAccountType::try_from(account_type) {
+            Ok(mirrored_account_type) if *program_id == self.staking_program_id => mirrored_account_type,
+            _ => return self.rpc.get_program_accounts_by_type(program_id, account_type).await,
+        };
+        if !self.is_fresh().await? {
+            log::warn!("Account mirror is stale, scanning {:?} accounts", account_type);
+            return self.rpc.get_program_accounts_by_type(program_id, account_type).await;
+        }
+
+        let accounts = self
+            .mirror
+            .by_type(mirrored_account_type)
+            .await
+            .with_context(|| format!("Failed to get mirrored {:?} accounts", account_type))?;
+        Ok(accounts
+            .into_iter()
+            .map(|account| WithPubkey {
+                pubkey: account.pubkey,
+                inner: SolanaAccount {
+                    lamports: account.lamports,
+                    data: account.data,
+                    owner: *program_id,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            })
+            .collect())
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pks: &[Pubkey],
+    ) -> Result<Vec<Option<WithPubkey<SolanaAccount>>>, SolanaError> {
+        // Point reads often follow a transaction, so they must not lag behind the chain
+        self.rpc.get_multiple_accounts(pks).await
+    }
+
+    async fn request_airdrop(&self, pubkey: Pubkey, lamports: u64) -> Result<(), SolanaError> {
+        self.rpc.request_airdrop(pubkey, lamports).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+
+    use super::*;
+    use crate::{account_mirror::InMemoryProgramAccountRepository, model::program_account::ProgramAccount};
+
+    /// Scans return the account, nothing else is expected.
+    struct ScanningRpc(Pubkey, SolanaAccount);
+
+    #[async_trait]
+    impl SolanaRpc for ScanningRpc {
+        async fn _send_and_confirm_transaction(
+            &self,
+            _signers: &[&Keypair],
+            _payer: Option<&Pubkey>,
+            _instructions: &[Instruction],
+        ) -> Result<Signature, SolanaError> {
+            unimplemented!()
+        }
+
+        async fn simulate_transaction(
+            &self,
+            _signers: &[&Keypair],
+            _payer: Option<&Pubkey>,
+            _instructions: &[Instruction],
+            _accounts: &[Pubkey],
+        ) -> Result<RpcSimulation, SolanaError> {
+            unimplemented!()
+        }
+
+        async fn get_program_accounts_by_type(
+            &self,
+            _program_id: &Pubkey,
+            _account_type: AccountType,
+        ) -> Result<Vec<WithPubkey<SolanaAccount>>, SolanaError> {
+            Ok(vec![WithPubkey {
+                pubkey: self.0,
+                inner: self.1.clone(),
+            }])
+        }
+
+        async fn get_multiple_accounts(
+            &self,
+            _pks: &[Pubkey],
+        ) -> Result<Vec<Option<WithPubkey<SolanaAccount>>>, SolanaError> {
+            unimplemented!()
+        }
+
+        async fn request_airdrop(&self, _pubkey: Pubkey, _lamports: u64) -> Result<(), SolanaError> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_rpc() {
+        let program_id = Pubkey::new_unique();
+        let data = AccountType::Stake.try_to_vec().unwrap();
+        let (mirrored, scanned) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let repository = InMemoryProgramAccountRepository::default();
+        repository
+            .upsert(&[ProgramAccount {
+                pubkey: mirrored,
+                account_type: MirroredAccountType::Stake,
+                lamports: 1,
+                data: data.clone(),
+                slot: 10,
+            }])
+            .await
+            .unwrap();
+        let rpc = SolanaRpcMirrored::new(
+            Arc::new(ScanningRpc(
+                scanned,
+                SolanaAccount {
+                    lamports: 1,
+                    data,
+                    owner: program_id,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )),
+            program_id,
+            Box::new(repository.clone()),
+            Duration::minutes(15),
+            Duration::minutes(1),
+        );
+        let scan = || async {
+            let accounts = rpc
+                .get_program_accounts_by_type(&program_id, AccountType::Stake)
+                .await
+                .unwrap();
+            assert_eq!(accounts.len(), 1);
+            accounts[0].pubkey
+        };
+
+        // Never checked
+        repository.subscribed(Utc::now()).await.unwrap();
+        assert_eq!(scan().await, scanned);
+
+        // Checked, but the subscription wasn't seen since
+        repository.checked(Utc::now()).await.unwrap();
+        assert_eq!(scan().await, scanned);
+
+        repository.subscribed(Utc::now()).await.unwrap();
+        assert_eq!(scan().await, mirrored);
+        // Other programs aren't mirrored
+        let accounts = rpc
+            .get_program_accounts_by_type(&Pubkey::new_unique(), AccountType::Stake)
+            .await
+            .unwrap();
+        assert_eq!(accounts[0].pubkey, scanned);
+
+        // The subscription dropped
+        repository.subscribed(Utc::now() - Duration::minutes(2)).await.unwrap();
+        assert_eq!(scan().await, scanned);
+
+        // Not checked for too long
+        repository.subscribed(Utc::now()).await.unwrap();
+        repository.checked(Utc::now() - Duration::minutes(20)).await.unwrap();
+        assert_eq!(scan().await, scanned);
+    }
+}
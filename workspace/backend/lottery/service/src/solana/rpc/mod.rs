@@ -5,15 +5,28 @@ use solana_program::{instruction::Instruction, pubkey::Pubkey};
 use solana_sdk::account::Account as SolanaAccount;
 use solana_sdk::signature::{Keypair, Signature};
 use solana_sdk::signer::Signer;
+use solana_sdk::transaction::TransactionError;
 
 use super::{with_pubkey::WithPubkey, SolanaError, ToSolanaError};
 
+mod mirrored;
+pub use mirrored::*;
+
 mod real;
 pub use real::*;
 
 mod test;
 pub use test::*;
 
+/// Result of simulating a transaction.
+pub struct RpcSimulation {
+    pub error: Option<TransactionError>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Requested accounts after the transaction, if the RPC returns them.
+    pub accounts: Option<Vec<Option<SolanaAccount>>>,
+}
+
 #[async_trait]
 pub trait SolanaRpc: Send + Sync + 'static {
     async fn _send_and_confirm_transaction(
@@ -23,6 +36,15 @@ pub trait SolanaRpc: Send + Sync + 'static {
         instructions: &[Instruction],
     ) -> Result<Signature, SolanaError>;
 
+    /// Simulates the transaction without sending it, returning the state of `accounts` after it.
+    async fn simulate_transaction(
+        &self,
+        signers: &[&Keypair], // first signer will be the payer
+        payer: Option<&Pubkey>,
+        instructions: &[Instruction],
+        accounts: &[Pubkey],
+    ) -> Result<RpcSimulation, SolanaError>;
+
     async fn get_program_accounts_by_type(
         &self,
         program_id: &Pubkey,
@@ -3,7 +3,9 @@ use borsh::BorshSerialize;
 use nezha_staking::state::AccountType;
 use solana_account_decoder::UiAccountEncoding;
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
 use solana_client::rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType};
 use solana_program::{instruction::Instruction, pubkey::Pubkey};
 use solana_sdk::account::Account as SolanaAccount;
@@ -14,6 +16,8 @@ use solana_sdk::{signature::Signature, transaction::Transaction};
 use crate::solana::TransactionErrorParsed;
 use crate::solana::{with_pubkey::WithPubkey, SolanaError, ToSolanaError};
 
+use super::RpcSimulation;
+
 pub struct SolanaRpcReal {
     staking_program_id: Pubkey,
     client: RpcClient,
@@ -130,6 +134,49 @@ impl super::SolanaRpc for SolanaRpcReal {
         }
     }
 
+    async fn simulate_transaction(
+        &self,
+        signers: &[&Keypair],
+        payer: Option<&Pubkey>,
+        instructions: &[Instruction],
+        accounts: &[Pubkey],
+    ) -> Result<RpcSimulation, SolanaError> {
+        let mut transaction = Transaction::new_with_payer(instructions, payer);
+        let hash = self
+            .client
+            .get_latest_blockhash()
+            .await
+            .context("Failed to get latest blockhash")?;
+        transaction
+            .try_sign(&signers.to_owned(), hash)
+            .context("Failed to sign transaction")?;
+
+        let config = RpcSimulateTransactionConfig {
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: accounts.iter().map(Pubkey::to_string).collect(),
+            }),
+            ..RpcSimulateTransactionConfig::default()
+        };
+        let sim_resp = self
+            .client
+            .simulate_transaction_with_config(&transaction, config)
+            .await
+            .context("Failed to simulate transaction")?;
+
+        Ok(RpcSimulation {
+            error: sim_resp.value.err,
+            logs: sim_resp.value.logs.unwrap_or_default(),
+            units_consumed: sim_resp.value.units_consumed,
+            accounts: sim_resp.value.accounts.map(|accounts| {
+                accounts
+                    .into_iter()
+                    .map(|account| account.and_then(|account| account.decode::<SolanaAccount>()))
+                    .collect()
+            }),
+        })
+    }
+
     async fn get_program_accounts_by_type(
         &self,
         program_id: &Pubkey,
@@ -18,7 +18,7 @@ use solana_sdk::{
 use crate::solana::TransactionErrorParsed;
 use crate::solana::{with_pubkey::WithPubkey, SolanaError, ToSolanaError};
 
-use super::SolanaRpc;
+use super::{RpcSimulation, SolanaRpc};
 
 pub struct SolanaRpcTest {
     staking_program_id: Pubkey,
@@ -121,6 +121,87 @@ impl SolanaRpc for SolanaRpcTest {
         }
     }
 
+    /// The banks client doesn't return the accounts after a simulation, so a transaction that simulates without error
+    /// is processed, its accounts read, and then restored to what they were before.
+    async fn simulate_transaction(
+        &self,
+        signers: &[&Keypair],
+        _payer: Option<&Pubkey>,
+        instructions: &[Instruction],
+        accounts: &[Pubkey],
+    ) -> Result<RpcSimulation, SolanaError> {
+        let mut ptc_lock = self.ptc.lock().await;
+        let ptc: &mut ProgramTestContext = &mut ptc_lock;
+
+        let mut signers: Vec<_> = signers.into();
+        signers.push(&ptc.payer);
+
+        let blockhash = ptc
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .with_context(|| "Failed to get latest blockhash")?;
+        let transaction =
+            Transaction::new_signed_with_payer(instructions, Some(&ptc.payer.pubkey()), &signers, blockhash);
+
+        let result = ptc
+            .banks_client
+            .simulate_transaction(transaction.clone())
+            .await
+            .context("Failed to simulate transaction")?;
+        let details = result.simulation_details;
+        let error = result.result.and_then(Result::err);
+
+        let accounts = match error {
+            Some(_) => None,
+            None => {
+                // The payer pays the fee, so it is restored as well
+                let mut restored = accounts.to_vec();
+                restored.push(ptc.payer.pubkey());
+                let mut before = Vec::with_capacity(restored.len());
+                for pubkey in &restored {
+                    before.push(
+                        ptc.banks_client
+                            .get_account(*pubkey)
+                            .await
+                            .context("Failed to call get_account()")?,
+                    );
+                }
+
+                ptc.banks_client
+                    .process_transaction(transaction)
+                    .await
+                    .context("Failed to process simulated transaction")?;
+                let mut after = Vec::with_capacity(accounts.len());
+                for pubkey in accounts {
+                    after.push(
+                        ptc.banks_client
+                            .get_account(*pubkey)
+                            .await
+                            .context("Failed to call get_account()")?,
+                    );
+                }
+
+                for (pubkey, account) in restored.iter().zip(before) {
+                    // Accounts without lamports don't exist
+                    ptc.set_account(pubkey, &account.unwrap_or_default().into());
+                }
+                // Sending the same transaction again would be rejected as already processed
+                ptc.get_new_latest_blockhash()
+                    .await
+                    .context("Failed to advance blockhash")?;
+                Some(after)
+            }
+        };
+
+        Ok(RpcSimulation {
+            error,
+            logs: details.as_ref().map(|details| details.logs.clone()).unwrap_or_default(),
+            units_consumed: details.map(|details| details.units_consumed),
+            accounts,
+        })
+    }
+
     async fn get_program_accounts_by_type(
         &self,
         program_id: &Pubkey,
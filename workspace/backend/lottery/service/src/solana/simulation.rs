@@ -0,0 +1,249 @@
+//! Dry runs of the epoch state progression transactions.
+
+use borsh::BorshDeserialize;
+use chrono::{DateTime, Utc};
+use nezha_staking::{
+    accounts as ac,
+    fixed_point::FPUSDC,
+    instruction::{CreateEpochWinnersMetaArgs, WinnerInput},
+    state::{
+        AccountType, Epoch, EpochWinnersMeta, EpochWinnersPage, LatestEpoch, Stake as SolanaStake, StakeUpdateRequest,
+        TicketsInfo, YieldSplitCfg,
+    },
+};
+use solana_program::{borsh0_10::try_from_slice_unchecked, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::account::Account as SolanaAccount;
+
+/// Epoch state progression with its arguments resolved against the chain.
+#[derive(Debug, Clone)]
+pub enum EpochInstruction {
+    CreateEpoch {
+        epoch_index: u64,
+        expected_end_date: DateTime<Utc>,
+        yield_split_cfg: YieldSplitCfg,
+    },
+    EnterInvestmentFake {
+        epoch_index: u64,
+        tickets_info: TicketsInfo,
+    },
+    EnterInvestmentFrancium {
+        epoch_index: u64,
+        tickets_info: TicketsInfo,
+    },
+    ExitInvestmentFake {
+        epoch_index: u64,
+        amount: FPUSDC,
+    },
+    ExitInvestmentFrancium {
+        epoch_index: u64,
+    },
+    PublishWinners {
+        epoch_index: u64,
+        draw_enabled: bool,
+        meta_args: CreateEpochWinnersMetaArgs,
+        winners_input: Vec<WinnerInput>,
+    },
+    FundJackpot {
+        epoch_index: u64,
+        amount: FPUSDC,
+    },
+}
+
+/// Outcome of simulating the first transaction of an [`EpochInstruction`].
+///
+/// Later transactions, like the winner pages of `PublishWinners`, depend on the state left by the
+/// first one and can't be simulated ahead of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    pub num_transactions: usize,
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    /// Empty when the RPC doesn't return the accounts after the simulation.
+    pub account_diffs: Vec<AccountDiff>,
+    pub vault_transfers: Vec<VaultTransfer>,
+}
+
+/// Decoded state of a writable account before and after the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub pubkey: Pubkey,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Change of a token account balance, in the mint's base units.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultTransfer {
+    pub account: Pubkey,
+    /// Name of the staking program vault, if the account is one.
+    pub vault: Option<String>,
+    pub mint: Pubkey,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl VaultTransfer {
+    pub fn change(&self) -> i128 {
+        self.after as i128 - self.before as i128
+    }
+}
+
+/// Compares the accounts before and after a simulation.
+/// `before` and `after` are in the same order as `pubkeys`.
+pub fn diff_accounts(
+    program_id: &Pubkey,
+    pubkeys: &[Pubkey],
+    before: &[Option<SolanaAccount>],
+    after: &[Option<SolanaAccount>],
+) -> (Vec<AccountDiff>, Vec<VaultTransfer>) {
+    let mut diffs = Vec::new();
+    let mut transfers = Vec::new();
+    for ((pubkey, before), after) in pubkeys.iter().zip(before).zip(after) {
+        if before == after {
+            continue;
+        }
+        diffs.push(AccountDiff {
+            pubkey: *pubkey,
+            before: before.as_ref().map(|account| describe_account(program_id, account)),
+            after: after.as_ref().map(|account| describe_account(program_id, account)),
+        });
+
+        let before_token = before.as_ref().and_then(token_account);
+        let after_token = after.as_ref().and_then(token_account);
+        if let Some(mint) = after_token.or(before_token).map(|token| token.mint) {
+            let before = before_token.map_or(0, |token| token.amount);
+            let after = after_token.map_or(0, |token| token.amount);
+            if before != after {
+                transfers.push(VaultTransfer {
+                    account: *pubkey,
+                    vault: vault_name(program_id, pubkey),
+                    mint,
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    (diffs, transfers)
+}
+
+fn token_account(account: &SolanaAccount) -> Option<spl_token::state::Account> {
+    if account.owner != spl_token::id() {
+        return None;
+    }
+    spl_token::state::Account::unpack(&account.data).ok()
+}
+
+fn vault_name(program_id: &Pubkey, pubkey: &Pubkey) -> Option<String> {
+    let vaults = [
+        ("DepositVault", ac::deposit_vault(program_id).pubkey),
+        ("PendingDepositVault", ac::pending_deposit_vault(program_id).pubkey),
+        ("TreasuryVault", ac::treasury_vault(program_id).pubkey),
+        ("InsuranceVault", ac::insurance_vault(program_id).pubkey),
+        ("Tier1PrizeVault", ac::prize_vault(program_id, 1).pubkey),
+        ("Tier2PrizeVault", ac::prize_vault(program_id, 2).pubkey),
+        ("Tier3PrizeVault", ac::prize_vault(program_id, 3).pubkey),
+    ];
+    vaults
+        .iter()
+        .find(|(_, vault)| vault == pubkey)
+        .map(|(name, _)| name.to_string())
+}
+
+fn describe_account(program_id: &Pubkey, account: &SolanaAccount) -> String {
+    if let Some(token) = token_account(account) {
+        return format!("{:?}", token);
+    }
+    if account.owner != *program_id || account.data.is_empty() {
+        return format!("{} lamports", account.lamports);
+    }
+    let decoded = match AccountType::try_from_slice(&account.data[..1]) {
+        Ok(AccountType::LatestEpoch) => decode::<LatestEpoch>(&account.data),
+        Ok(AccountType::Epoch) => decode::<Epoch>(&account.data),
+        Ok(AccountType::Stake) => decode::<SolanaStake>(&account.data),
+        Ok(AccountType::StakeUpdateRequest) => decode::<StakeUpdateRequest>(&account.data),
+        Ok(AccountType::EpochWinnersMeta) => decode::<EpochWinnersMeta>(&account.data),
+        Ok(AccountType::EpochWinnersPage) => decode::<EpochWinnersPage>(&account.data),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| format!("{} bytes", account.data.len()))
+}
+
+fn decode<T: BorshDeserialize + std::fmt::Debug>(data: &[u8]) -> Option<String> {
+    try_from_slice_unchecked::<T>(data)
+        .ok()
+        .map(|account| format!("{:?}", account))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::program_option::COption;
+    use spl_token::state::AccountState;
+
+    use super::*;
+
+    fn token_account(mint: Pubkey, amount: u64) -> SolanaAccount {
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner: Pubkey::new_unique(),
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        SolanaAccount {
+            lamports: 1,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_diff_accounts() {
+        let program_id = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let deposit_vault = ac::deposit_vault(&program_id).pubkey;
+        let investor = Pubkey::new_unique();
+        let unchanged = Pubkey::new_unique();
+
+        let pubkeys = [deposit_vault, investor, unchanged];
+        let before = [
+            Some(token_account(mint, 1_000)),
+            Some(token_account(mint, 0)),
+            Some(token_account(mint, 5)),
+        ];
+        let mut after = before.clone();
+        after[0] = Some(token_account(mint, 400));
+        after[1] = Some(token_account(mint, 600));
+
+        let (diffs, transfers) = diff_accounts(&program_id, &pubkeys, &before, &after);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(
+            transfers,
+            vec![
+                VaultTransfer {
+                    account: deposit_vault,
+                    vault: Some("DepositVault".into()),
+                    mint,
+                    before: 1_000,
+                    after: 400,
+                },
+                VaultTransfer {
+                    account: investor,
+                    vault: None,
+                    mint,
+                    before: 0,
+                    after: 600,
+                },
+            ]
+        );
+        assert_eq!(transfers[0].change(), -600);
+    }
+}
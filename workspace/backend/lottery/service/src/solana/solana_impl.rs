@@ -12,6 +12,9 @@ use solana_program::{
 };
 use solana_sdk::{
     compute_budget,
+    instruction::Instruction,
+    message::Message,
+    packet::PACKET_DATA_SIZE,
     signature::{Keypair, Signature},
     signer::Signer,
 };
@@ -24,18 +27,24 @@ use nezha_staking::{
     instruction::{self, CreateEpochWinnersMetaArgs, WinnerInput},
     state::{
         Epoch, EpochWinnersMeta, EpochWinnersPage, LatestEpoch, Stake as SolanaStake, StakeUpdateRequest, TicketsInfo,
-        YieldSplitCfg, MAX_NUM_WINNERS_PER_PAGE,
+        WinnerProcessingStatus, YieldSplitCfg, MAX_NUM_WINNERS_PER_PAGE,
     },
 };
 
 use crate::{model::winner::EpochWinners, solana::AccountNotFound};
 
 use super::{
+    diff_accounts,
     rpc::{parse_account, SolanaRpc, SolanaRpcExt},
-    Stake, SwitchboardDetails, VrfConfiguration, WalletPrize, WithPubkey, WithPubkeyOption,
+    EpochInstruction, Simulation, Stake, SwitchboardDetails, TransactionErrorParsed, VrfConfiguration, WalletPrize,
+    WithPubkey, WithPubkeyOption,
 };
 use super::{Solana, SolanaError, ToSolanaError};
 
+/// Compute budget of a winners page.
+const PUBLISH_WINNERS_PAGE_COMPUTE_UNITS: u32 = 700_000;
+const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+
 #[derive(Clone)]
 pub struct SolanaImpl {
     pub rpc_client: Arc<dyn SolanaRpc>,
@@ -398,22 +407,12 @@ impl Solana for SolanaImpl {
         expected_end_date: DateTime<Utc>,
         yield_split_cfg: YieldSplitCfg,
     ) -> Result<Signature, SolanaError> {
-        let staking_program_id = self.program_id.clone();
-        let admin_pubkey = self.admin_keypair.pubkey();
-
-        let instruction = instruction::create_epoch(
-            &staking_program_id,
-            &admin_pubkey,
+        self.send_epoch_instruction(&EpochInstruction::CreateEpoch {
             epoch_index,
-            expected_end_date.timestamp(),
+            expected_end_date,
             yield_split_cfg,
-        );
-
-        let sig = self
-            .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &[instruction])
-            .await?;
-        Ok(sig)
+        })
+        .await
     }
 
     async fn approve_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError> {
@@ -439,105 +438,56 @@ impl Solana for SolanaImpl {
         Ok(sig)
     }
 
-    async fn enter_investment_fake(&self, epoch_index: u64, num_tickets: u64) -> Result<Signature, SolanaError> {
-        let staking_program_id = self.program_id.clone();
-        let admin_keypair = self.admin_keypair.clone();
-        let admin_pubkey = admin_keypair.pubkey();
-        let investor_pubkey = self.investor_keypair.pubkey();
-        let investor_usdc_token_pubkey =
-            spl_associated_token_account::get_associated_token_address(&investor_pubkey, &self.usdc_mint);
-
-        let instruction = instruction::yield_withdraw_by_investor(
-            &staking_program_id,
-            &admin_pubkey,
-            &investor_usdc_token_pubkey,
-            epoch_index,
-            TicketsInfo {
-                num_tickets,
-                tickets_url: String::from("TODO"),
-                tickets_hash: Vec::new(),
-                tickets_version: 0,
-            },
+    async fn cancel_stake_update(&self, wallet: Pubkey, amount: i64) -> Result<Signature, SolanaError> {
+        let ata = get_associated_token_address(&wallet, &self.usdc_mint);
+        let ix = instruction::cancel_stake_update(
+            &self.program_id,
+            Some(&self.admin_keypair.pubkey()),
+            &wallet,
+            &ata,
+            amount,
         );
 
         let sig = self
             .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &[instruction])
+            .send_and_confirm_transaction(&self.admin_keypair, &[ix])
             .await?;
+
         Ok(sig)
     }
 
-    async fn exit_investment_fake(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError> {
-        let admin_pubkey = self.admin_keypair.pubkey();
-        let investor_pubkey = self.investor_keypair.pubkey();
-        let investor_usdc_token_pubkey = get_associated_token_address(&investor_pubkey, &self.usdc_mint);
-        let amount = amount.as_usdc();
-
-        let mint_to_ixn = spl_token::instruction::mint_to(
-            &spl_token::id(),
-            &self.usdc_mint,
-            &investor_usdc_token_pubkey,
-            &admin_pubkey,
-            &[&admin_pubkey],
-            amount,
-        )
-        .context("Failed to create MintTo instruction for investor USDC ATA.")?;
-
-        let deposit_ixn = instruction::yield_deposit_by_investor(
-            &self.program_id,
-            &investor_pubkey,
-            &investor_usdc_token_pubkey,
+    async fn enter_investment_fake(
+        &self,
+        epoch_index: u64,
+        tickets_info: TicketsInfo,
+    ) -> Result<Signature, SolanaError> {
+        self.send_epoch_instruction(&EpochInstruction::EnterInvestmentFake {
             epoch_index,
-            amount,
-        );
-
-        let ixs = [mint_to_ixn, deposit_ixn];
-
-        let sig = self
-            .rpc_client
-            ._send_and_confirm_transaction(
-                &[&self.admin_keypair, &self.investor_keypair],
-                Some(&self.admin_keypair.pubkey()),
-                &ixs,
-            )
-            .await?;
+            tickets_info,
+        })
+        .await
+    }
 
-        Ok(sig)
+    async fn exit_investment_fake(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError> {
+        self.send_epoch_instruction(&EpochInstruction::ExitInvestmentFake { epoch_index, amount })
+            .await
     }
 
-    async fn enter_investment_francium(&self, epoch_index: u64, num_tickets: u64) -> Result<Signature, SolanaError> {
-        let ix = instruction::francium_invest(
-            &self.program_id,
-            &self.admin_keypair.pubkey(),
+    async fn enter_investment_francium(
+        &self,
+        epoch_index: u64,
+        tickets_info: TicketsInfo,
+    ) -> Result<Signature, SolanaError> {
+        self.send_epoch_instruction(&EpochInstruction::EnterInvestmentFrancium {
             epoch_index,
-            TicketsInfo {
-                num_tickets,
-                tickets_url: String::from("TODO"),
-                tickets_hash: Vec::new(),
-                tickets_version: 0,
-            },
-            &fr_consts::get_mints(),
-        );
-        let sig = self
-            .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &[ix])
-            .await?;
-        Ok(sig)
+            tickets_info,
+        })
+        .await
     }
 
     async fn exit_investment_francium(&self, epoch_index: u64) -> Result<Signature, SolanaError> {
-        let cuix = compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(300000);
-        let ix = instruction::francium_withdraw(
-            &self.program_id,
-            &self.admin_keypair.pubkey(),
-            epoch_index,
-            &fr_consts::get_mints(),
-        );
-        let sig = self
-            .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &[cuix, ix])
-            .await?;
-        Ok(sig)
+        self.send_epoch_instruction(&EpochInstruction::ExitInvestmentFrancium { epoch_index })
+            .await
     }
 
     async fn publish_winners(
@@ -546,70 +496,88 @@ impl Solana for SolanaImpl {
         draw_enabled: bool,
         meta_args: &CreateEpochWinnersMetaArgs,
         winners_input: &[WinnerInput],
-    ) -> Result<Signature, SolanaError> {
-        let cuix = compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(250_000);
-        let create_meta_instruction = instruction::create_epoch_winners_meta(
-            &self.program_id,
-            &self.admin_keypair.pubkey(),
-            epoch_index,
-            meta_args.clone(),
-            &self.nezha_vrf_program_id(),
-        );
-        let sig = self
-            .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &[cuix, create_meta_instruction])
-            .await?;
-
-        let total_num_winners = meta_args.tier1_meta.total_num_winners
-            + meta_args.tier2_meta.total_num_winners
-            + meta_args.tier3_meta.total_num_winners;
+    ) -> Result<Option<Signature>, SolanaError> {
+        // Creating the meta again would reset the progress of the pages
+        let mut signature = None;
+        if self.get_epoch_winners_meta(epoch_index).await?.is_none() {
+            let transaction = self.create_winners_meta_transaction(epoch_index, meta_args);
+            let signers = transaction.signers();
+            signature = Some(
+                self.rpc_client
+                    ._send_and_confirm_transaction(&signers, Some(&signers[0].pubkey()), &transaction.instructions)
+                    .await?,
+            );
+        }
 
-        if !draw_enabled || total_num_winners == 0 {
-            return Ok(sig);
+        if let Some(last) = self.publish_winner_pages(epoch_index, winners_input).await? {
+            signature = Some(last);
         }
 
-        let mut last = Signature::default();
-        for (page_index, chunk) in winners_input.chunks(MAX_NUM_WINNERS_PER_PAGE).enumerate() {
-            let cuix = compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(700_000);
-            let publish_instruction = instruction::publish_winners(
-                &self.program_id,
-                &self.admin_keypair.pubkey(),
+        let meta = self.get_epoch_winners_meta(epoch_index).await?.ok_or_else(|| {
+            SolanaError::AccountNotFound(AccountNotFound::EpochWinnersMeta {
                 epoch_index,
-                page_index as u32,
-                chunk.to_vec(),
-                &self.nezha_vrf_program_id(),
-            );
-            last = self
-                .rpc_client
-                .send_and_confirm_transaction(&self.admin_keypair, &[cuix, publish_instruction])
-                .await?;
-        }
-        Ok(last)
+                pubkey: ac::epoch_winners_meta(&self.program_id, epoch_index).pubkey,
+            })
+        })?;
+        verify_winners_meta(&meta, draw_enabled, meta_args, winners_input)
+            .map_err(|reason| SolanaError::WinnersMismatch { epoch_index, reason })?;
+        Ok(signature)
     }
 
     async fn fund_jackpot(&self, epoch_index: u64, amount: FPUSDC) -> Result<Signature, SolanaError> {
-        let admin_pubkey = self.admin_keypair.pubkey();
-        let admin_usdc = get_associated_token_address(&admin_pubkey, &self.usdc_mint);
+        self.send_epoch_instruction(&EpochInstruction::FundJackpot { epoch_index, amount })
+            .await
+    }
 
-        let mint_to_ixn = spl_token::instruction::mint_to(
-            &spl_token::id(),
-            &self.usdc_mint,
-            &admin_usdc,
-            &admin_pubkey,
-            &[&admin_pubkey],
-            amount.as_usdc(),
-        )
-        .context("Failed to create MintTo instruction")?;
+    async fn simulate_epoch_instruction(&self, instruction: &EpochInstruction) -> Result<Simulation, SolanaError> {
+        let transactions = self.epoch_transactions(instruction)?;
+        let transaction = &transactions[0];
 
-        let fund_ixn = instruction::fund_jackpot(&self.program_id, &admin_pubkey, &admin_usdc, epoch_index);
+        let mut writable = Vec::new();
+        for account in transaction.instructions.iter().flat_map(|ix| ix.accounts.iter()) {
+            if account.is_writable && !writable.contains(&account.pubkey) {
+                writable.push(account.pubkey);
+            }
+        }
 
-        let ixns = [mint_to_ixn, fund_ixn];
-        let sig = self
+        let before: Vec<_> = self
             .rpc_client
-            .send_and_confirm_transaction(&self.admin_keypair, &ixns)
+            .get_multiple_accounts(&writable)
+            .await?
+            .into_iter()
+            .map(|account| account.map(|account| account.inner))
+            .collect();
+
+        let signers = transaction.signers();
+        let simulation = self
+            .rpc_client
+            .simulate_transaction(
+                &signers,
+                Some(&signers[0].pubkey()),
+                &transaction.instructions,
+                &writable,
+            )
             .await?;
 
-        Ok(sig)
+        let (account_diffs, vault_transfers) = match &simulation.accounts {
+            Some(after) => diff_accounts(&self.program_id, &writable, &before, after),
+            None => (Vec::new(), Vec::new()),
+        };
+        let error = simulation.error.map(|error| {
+            match TransactionErrorParsed::from_instructions(&transaction.instructions, self.program_id, &error) {
+                Some(parsed) => parsed.to_string(),
+                None => error.to_string(),
+            }
+        });
+
+        Ok(Simulation {
+            num_transactions: transactions.len(),
+            error,
+            logs: simulation.logs,
+            units_consumed: simulation.units_consumed,
+            account_diffs,
+            vault_transfers,
+        })
     }
 
     async fn get_usdc_balance_by_wallet(&self, wallet: Pubkey) -> Result<FPUSDC, SolanaError> {
@@ -680,7 +648,269 @@ impl Solana for SolanaImpl {
     }
 }
 
+/// Instructions of a transaction. The first signer pays.
+struct PreparedTransaction {
+    instructions: Vec<Instruction>,
+    signers: Vec<Arc<Keypair>>,
+}
+
+impl PreparedTransaction {
+    fn signers(&self) -> Vec<&Keypair> {
+        self.signers.iter().map(|signer| signer.as_ref()).collect()
+    }
+}
+
 impl SolanaImpl {
+    /// Transactions of an epoch state progression, in the order they have to be sent.
+    fn epoch_transactions(&self, instruction: &EpochInstruction) -> Result<Vec<PreparedTransaction>, SolanaError> {
+        let admin_pubkey = self.admin_keypair.pubkey();
+        let by_admin = |instructions: Vec<Instruction>| PreparedTransaction {
+            instructions,
+            signers: vec![self.admin_keypair.clone()],
+        };
+
+        let transactions = match instruction {
+            EpochInstruction::CreateEpoch {
+                epoch_index,
+                expected_end_date,
+                yield_split_cfg,
+            } => vec![by_admin(vec![instruction::create_epoch(
+                &self.program_id,
+                &admin_pubkey,
+                *epoch_index,
+                expected_end_date.timestamp(),
+                yield_split_cfg.clone(),
+            )])],
+            EpochInstruction::EnterInvestmentFake {
+                epoch_index,
+                tickets_info,
+            } => {
+                let investor_usdc_token_pubkey =
+                    get_associated_token_address(&self.investor_keypair.pubkey(), &self.usdc_mint);
+                vec![by_admin(vec![instruction::yield_withdraw_by_investor(
+                    &self.program_id,
+                    &admin_pubkey,
+                    &investor_usdc_token_pubkey,
+                    *epoch_index,
+                    tickets_info.clone(),
+                )])]
+            }
+            EpochInstruction::EnterInvestmentFrancium {
+                epoch_index,
+                tickets_info,
+            } => vec![by_admin(vec![instruction::francium_invest(
+                &self.program_id,
+                &admin_pubkey,
+                *epoch_index,
+                tickets_info.clone(),
+                &fr_consts::get_mints(),
+            )])],
+            EpochInstruction::ExitInvestmentFake { epoch_index, amount } => {
+                let investor_pubkey = self.investor_keypair.pubkey();
+                let investor_usdc_token_pubkey = get_associated_token_address(&investor_pubkey, &self.usdc_mint);
+                let amount = amount.as_usdc();
+
+                let mint_to_ixn = spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &self.usdc_mint,
+                    &investor_usdc_token_pubkey,
+                    &admin_pubkey,
+                    &[&admin_pubkey],
+                    amount,
+                )
+                .context("Failed to create MintTo instruction for investor USDC ATA.")?;
+
+                let deposit_ixn = instruction::yield_deposit_by_investor(
+                    &self.program_id,
+                    &investor_pubkey,
+                    &investor_usdc_token_pubkey,
+                    *epoch_index,
+                    amount,
+                );
+
+                vec![PreparedTransaction {
+                    instructions: vec![mint_to_ixn, deposit_ixn],
+                    signers: vec![self.admin_keypair.clone(), self.investor_keypair.clone()],
+                }]
+            }
+            EpochInstruction::ExitInvestmentFrancium { epoch_index } => {
+                let cuix = compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(300000);
+                let ix = instruction::francium_withdraw(
+                    &self.program_id,
+                    &admin_pubkey,
+                    *epoch_index,
+                    &fr_consts::get_mints(),
+                );
+                vec![by_admin(vec![cuix, ix])]
+            }
+            EpochInstruction::PublishWinners {
+                epoch_index,
+                draw_enabled,
+                meta_args,
+                winners_input,
+            } => {
+                let mut transactions = vec![self.create_winners_meta_transaction(*epoch_index, meta_args)];
+
+                let total_num_winners = meta_args.tier1_meta.total_num_winners
+                    + meta_args.tier2_meta.total_num_winners
+                    + meta_args.tier3_meta.total_num_winners;
+
+                if *draw_enabled && total_num_winners > 0 {
+                    transactions.extend(self.winner_page_transactions(*epoch_index, winners_input, 0));
+                }
+                transactions
+            }
+            EpochInstruction::FundJackpot { epoch_index, amount } => {
+                let admin_usdc = get_associated_token_address(&admin_pubkey, &self.usdc_mint);
+
+                let mint_to_ixn = spl_token::instruction::mint_to(
+                    &spl_token::id(),
+                    &self.usdc_mint,
+                    &admin_usdc,
+                    &admin_pubkey,
+                    &[&admin_pubkey],
+                    amount.as_usdc(),
+                )
+                .context("Failed to create MintTo instruction")?;
+
+                let fund_ixn = instruction::fund_jackpot(&self.program_id, &admin_pubkey, &admin_usdc, *epoch_index);
+                vec![by_admin(vec![mint_to_ixn, fund_ixn])]
+            }
+        };
+        Ok(transactions)
+    }
+
+    fn create_winners_meta_transaction(
+        &self,
+        epoch_index: u64,
+        meta_args: &CreateEpochWinnersMetaArgs,
+    ) -> PreparedTransaction {
+        let cuix = compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(250_000);
+        let create_meta_instruction = instruction::create_epoch_winners_meta(
+            &self.program_id,
+            &self.admin_keypair.pubkey(),
+            epoch_index,
+            meta_args.clone(),
+            &self.nezha_vrf_program_id(),
+        );
+        PreparedTransaction {
+            instructions: vec![cuix, create_meta_instruction],
+            signers: vec![self.admin_keypair.clone()],
+        }
+    }
+
+    /// Packs the winner pages from `first_page` on into as few transactions as the packet size and compute budget
+    /// allow, keeping the pages in order.
+    fn winner_page_transactions(
+        &self,
+        epoch_index: u64,
+        winners_input: &[WinnerInput],
+        first_page: u32,
+    ) -> Vec<PreparedTransaction> {
+        let admin_pubkey = self.admin_keypair.pubkey();
+        let with_budget = |pages: &[Instruction]| {
+            let compute_units = PUBLISH_WINNERS_PAGE_COMPUTE_UNITS * pages.len() as u32;
+            let mut instructions = vec![compute_budget::ComputeBudgetInstruction::set_compute_unit_limit(
+                compute_units,
+            )];
+            instructions.extend_from_slice(pages);
+            instructions
+        };
+
+        let mut transactions = Vec::new();
+        let mut pages = Vec::new();
+        for (page_index, chunk) in winners_input
+            .chunks(MAX_NUM_WINNERS_PER_PAGE)
+            .enumerate()
+            .skip(first_page as usize)
+        {
+            let publish_instruction = instruction::publish_winners(
+                &self.program_id,
+                &admin_pubkey,
+                epoch_index,
+                page_index as u32,
+                chunk.to_vec(),
+                &self.nezha_vrf_program_id(),
+            );
+            pages.push(publish_instruction);
+            let fits = PUBLISH_WINNERS_PAGE_COMPUTE_UNITS * pages.len() as u32 <= MAX_TRANSACTION_COMPUTE_UNITS
+                && transaction_size(&with_budget(&pages), &admin_pubkey) <= PACKET_DATA_SIZE;
+            if !fits && pages.len() > 1 {
+                let page = pages.pop().expect("More than one page");
+                transactions.push(with_budget(&pages));
+                pages = vec![page];
+            }
+        }
+        if !pages.is_empty() {
+            transactions.push(with_budget(&pages));
+        }
+
+        transactions
+            .into_iter()
+            .map(|instructions| PreparedTransaction {
+                instructions,
+                signers: vec![self.admin_keypair.clone()],
+            })
+            .collect()
+    }
+
+    async fn get_epoch_winners_meta(&self, epoch_index: u64) -> Result<Option<EpochWinnersMeta>, SolanaError> {
+        let pubkey = ac::epoch_winners_meta(&self.program_id, epoch_index).pubkey;
+        let meta = self.rpc_client.get_account_parsed::<EpochWinnersMeta>(&pubkey).await?;
+        Ok(meta.map(|meta| meta.inner))
+    }
+
+    /// Publishes the pages the chain hasn't seen yet, resuming from its progress.
+    ///
+    /// Pages are only accepted in sequence, so every transaction is confirmed before the next one is sent. If one
+    /// fails, publishing again resumes from the last page that landed.
+    /// Returns the signature of the last transaction, if any was sent.
+    async fn publish_winner_pages(
+        &self,
+        epoch_index: u64,
+        winners_input: &[WinnerInput],
+    ) -> Result<Option<Signature>, SolanaError> {
+        let num_pages = match self.get_epoch_winners_meta(epoch_index).await?.map(|meta| meta.status) {
+            Some(WinnerProcessingStatus::InProgress { num_pages, .. }) => num_pages,
+            Some(WinnerProcessingStatus::Completed) | None => return Ok(None),
+        };
+        log::info!("Publishing winners of epoch {} from page {}", epoch_index, num_pages);
+
+        let transactions = self.winner_page_transactions(epoch_index, winners_input, num_pages);
+        if transactions.is_empty() {
+            return Err(SolanaError::WinnersMismatch {
+                epoch_index,
+                reason: format!("the chain expects page {} but there are no more winners", num_pages),
+            });
+        }
+
+        // Not sent in parallel: the program rejects a page which isn't the next one with `PageIndexNotInSequence`, and
+        // transactions sent together can land in any order.
+        let mut last = None;
+        for transaction in transactions {
+            let signers = transaction.signers();
+            let signature = self
+                .rpc_client
+                ._send_and_confirm_transaction(&signers, Some(&signers[0].pubkey()), &transaction.instructions)
+                .await?;
+            last = Some(signature);
+        }
+        Ok(last)
+    }
+
+    /// Sends the transactions one after the other and returns the signature of the last one.
+    async fn send_epoch_instruction(&self, instruction: &EpochInstruction) -> Result<Signature, SolanaError> {
+        let mut last = Signature::default();
+        for transaction in self.epoch_transactions(instruction)? {
+            let signers = transaction.signers();
+            last = self
+                .rpc_client
+                ._send_and_confirm_transaction(&signers, Some(&signers[0].pubkey()), &transaction.instructions)
+                .await?;
+        }
+        Ok(last)
+    }
+
     /// Check if combination can be set, if combination is already set and the status is Success,
     /// the current combination is returned. Otherwise it returns Ok(None) if the combination can
     /// still be set, that is non success status or no combination are set.
@@ -698,3 +928,51 @@ impl SolanaImpl {
         Ok(req.winning_combination)
     }
 }
+
+/// Checks the totals of the published winners against the winners they were calculated from.
+fn verify_winners_meta(
+    meta: &EpochWinnersMeta,
+    draw_enabled: bool,
+    meta_args: &CreateEpochWinnersMetaArgs,
+    winners_input: &[WinnerInput],
+) -> Result<(), String> {
+    if meta.status != WinnerProcessingStatus::Completed {
+        return Err(format!("publication is not complete: {:?}", meta.status));
+    }
+    // Without a draw the meta is created with no winners
+    let (expected_num_winners, expected_tiers) = if draw_enabled {
+        let tiers = [&meta_args.tier1_meta, &meta_args.tier2_meta, &meta_args.tier3_meta]
+            .map(|tier| (tier.total_num_winners, tier.total_num_winning_tickets));
+        (winners_input.len() as u32, tiers)
+    } else {
+        (0, [(0, 0); 3])
+    };
+    if meta.total_num_winners != expected_num_winners {
+        return Err(format!(
+            "{} winners published, expected {}",
+            meta.total_num_winners, expected_num_winners
+        ));
+    }
+    let tiers = [&meta.tier1_meta, &meta.tier2_meta, &meta.tier3_meta];
+    for (i, (tier, expected)) in tiers.iter().zip(expected_tiers).enumerate() {
+        if (tier.total_num_winners, tier.total_num_winning_tickets) != expected {
+            return Err(format!(
+                "tier {} has {} winners with {} winning tickets, expected {} with {}",
+                i + 1,
+                tier.total_num_winners,
+                tier.total_num_winning_tickets,
+                expected.0,
+                expected.1
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Size of the signed transaction on the wire.
+fn transaction_size(instructions: &[Instruction], payer: &Pubkey) -> usize {
+    let message = Message::new(instructions, Some(payer));
+    let num_signatures = message.header.num_required_signatures as usize;
+    // The signatures are prefixed with their count, a single byte for fewer than 128
+    1 + num_signatures * 64 + message.serialize().len()
+}
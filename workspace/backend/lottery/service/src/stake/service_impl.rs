@@ -9,10 +9,13 @@ use nezha_staking::{
 use solana_program::pubkey::Pubkey;
 
 use crate::{
+    events::{Event, EventBus},
     model::{
         stake_update::{StakeUpdate, StakeUpdateState, StakeUpdateType},
+        stake_update_review::ScreeningError,
         transaction::TransactionId,
     },
+    screening::{Screening, StakeUpdateScreener},
     solana::{AccountNotFound, Solana, SolanaError, Stake},
 };
 
@@ -21,13 +24,22 @@ use super::{StakeService, StakeUpdateRepository};
 pub struct DefaultStakeService {
     solana: Box<dyn Solana>,
     stake_update_repo: Box<dyn StakeUpdateRepository>,
+    screener: Box<dyn StakeUpdateScreener>,
+    events: EventBus,
 }
 
 impl DefaultStakeService {
-    pub fn new(solana: Box<dyn Solana>, stake_update_repo: Box<dyn StakeUpdateRepository>) -> Self {
+    pub fn new(
+        solana: Box<dyn Solana>,
+        stake_update_repo: Box<dyn StakeUpdateRepository>,
+        screener: Box<dyn StakeUpdateScreener>,
+        events: EventBus,
+    ) -> Self {
         Self {
             solana,
             stake_update_repo,
+            screener,
+            events,
         }
     }
 }
@@ -103,6 +115,10 @@ impl StakeService for DefaultStakeService {
             None => bail!("Account not found"),
         };
 
+        if let Screening::Flagged { reason } = self.screener.screen(&stake_update).await? {
+            return Err(ScreeningError::Flagged { wallet, reason }.into());
+        }
+
         self.solana
             .approve_stake_update(
                 wallet,
@@ -112,6 +128,7 @@ impl StakeService for DefaultStakeService {
                 },
             )
             .await?;
+        self.events.publish(Event::StakeUpdateChanged(stake_update.clone()));
         Ok(stake_update)
     }
 
@@ -127,6 +144,7 @@ impl StakeService for DefaultStakeService {
         stake_update.transaction_id = Some(transaction_id.into());
         stake_update.state = StakeUpdateState::Completed;
         let stake_update = self.stake_update_repo.store(&stake_update).await?;
+        self.events.publish(Event::StakeUpdateChanged(stake_update.clone()));
         Ok(stake_update)
     }
 
@@ -0,0 +1,337 @@
+use anyhow::{Context, Result};
+use arweave_uploader::{DataWithMediaType, Tag, UploaderClient};
+use async_trait::async_trait;
+use nezha_staking::{
+    fixed_point::FPUSDC,
+    state::{TicketsInfo, TICKETS_URL_MAX_LEN},
+};
+use serde::{Deserialize, Serialize};
+use solana_program::hash::{hash, Hash};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use super::Ticket;
+
+/// Version of the manifest format, committed on chain as `TicketsInfo::tickets_version`. Version 0 means the epoch
+/// has no manifest.
+pub const MANIFEST_VERSION: u8 = 1;
+
+/// Every ticket of an epoch in a canonical form. It's uploaded to Arweave when the epoch enters investment and its
+/// hash is committed on chain, so anyone can check the draw was run against the published tickets.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochManifest {
+    pub version: u8,
+    pub epoch_index: u64,
+    /// Number of sequences of all tickets, committed on chain as `TicketsInfo::num_tickets`.
+    pub num_sequences: u64,
+    /// Sorted by wallet address.
+    pub tickets: Vec<ManifestTicket>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestTicket {
+    pub wallet: String,
+    /// Staked amount the ticket was generated for, in USDC base units.
+    pub balance: u64,
+    /// Sorted.
+    pub sequences: Vec<[u8; 6]>,
+}
+
+impl EpochManifest {
+    pub fn new(epoch_index: u64, tickets: &[Ticket]) -> Result<Self> {
+        let mut manifest_tickets = tickets
+            .iter()
+            .filter(|ticket| ticket.epoch_index == epoch_index)
+            .map(|ticket| {
+                let balance = ticket
+                    .balance
+                    .parse::<FPUSDC>()
+                    .map_err(|e| anyhow::anyhow!(e))
+                    .with_context(|| format!("Invalid balance {} of ticket of {}", ticket.balance, ticket.wallet))?;
+                let mut sequences = ticket
+                    .sequences
+                    .iter()
+                    .map(|sequence| sequence.nums)
+                    .collect::<Vec<_>>();
+                sequences.sort();
+                Ok(ManifestTicket {
+                    wallet: ticket.wallet.to_string(),
+                    balance: balance.as_usdc(),
+                    sequences,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        manifest_tickets.sort_by(|a, b| a.wallet.cmp(&b.wallet));
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            epoch_index,
+            num_sequences: manifest_tickets
+                .iter()
+                .map(|ticket| ticket.sequences.len() as u64)
+                .sum(),
+            tickets: manifest_tickets,
+        })
+    }
+
+    /// Compact JSON, with fields in declaration order. These are the bytes uploaded and hashed.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// SHA-256 of [`EpochManifest::to_bytes`], committed on chain as `TicketsInfo::tickets_hash`.
+    pub fn hash(&self) -> Result<Hash> {
+        Ok(hash(&self.to_bytes()?))
+    }
+
+    pub fn tickets_info(&self, tickets_url: String) -> Result<TicketsInfo> {
+        anyhow::ensure!(
+            tickets_url.len() <= TICKETS_URL_MAX_LEN,
+            "Tickets url {} is longer than {} bytes",
+            tickets_url,
+            TICKETS_URL_MAX_LEN
+        );
+        Ok(TicketsInfo {
+            num_tickets: self.num_sequences,
+            tickets_url,
+            tickets_hash: self.hash()?.to_bytes().to_vec(),
+            tickets_version: self.version,
+        })
+    }
+}
+
+#[async_trait]
+pub trait ManifestPublisher: Sync + Send {
+    /// Uploads the manifest and returns its URL.
+    async fn publish(&self, manifest: &EpochManifest) -> Result<String>;
+}
+
+/// Publishes manifests to Arweave as `ar://<transaction id>` URLs, which fit in `TicketsInfo::tickets_url`.
+pub struct ArweaveManifestPublisher<U: UploaderClient> {
+    uploader: U,
+    app_name: String,
+}
+
+impl<U: UploaderClient> ArweaveManifestPublisher<U> {
+    pub fn new(uploader: U, app_name: String) -> Self {
+        Self { uploader, app_name }
+    }
+}
+
+fn tag(name: &str, value: String) -> Tag {
+    Tag {
+        name: name.to_string(),
+        value,
+    }
+}
+
+#[async_trait]
+impl<U: UploaderClient + Sync + Send> ManifestPublisher for ArweaveManifestPublisher<U> {
+    async fn publish(&self, manifest: &EpochManifest) -> Result<String> {
+        let data = manifest.to_bytes()?;
+        let tags = vec![
+            tag("App-Name", self.app_name.clone()),
+            tag("Type", "epoch-manifest".to_string()),
+            tag("Epoch-Index", manifest.epoch_index.to_string()),
+            tag("Manifest-Version", manifest.version.to_string()),
+            tag("Manifest-Hash", manifest.hash()?.to_string()),
+        ];
+        let id = self
+            .uploader
+            .upload_bytes_with_tags(
+                DataWithMediaType {
+                    data,
+                    media_type: mime::APPLICATION_JSON,
+                },
+                tags,
+            )
+            .await
+            .with_context(|| format!("Failed to upload manifest of epoch {}", manifest.epoch_index))?;
+        Ok(format!("ar://{id}"))
+    }
+}
+
+/// In memory implementation of ManifestPublisher for testing.
+#[derive(Clone, Default)]
+pub struct InMemoryManifestPublisher {
+    mem: Arc<RwLock<HashMap<String, EpochManifest>>>,
+    num_published: Arc<RwLock<usize>>,
+}
+
+impl InMemoryManifestPublisher {
+    pub fn by_url(&self, url: &str) -> Option<EpochManifest> {
+        self.mem.read().unwrap().get(url).cloned()
+    }
+
+    /// Number of times a manifest was published, including the same one again.
+    pub fn num_published(&self) -> usize {
+        *self.num_published.read().unwrap()
+    }
+}
+
+#[async_trait]
+impl ManifestPublisher for InMemoryManifestPublisher {
+    async fn publish(&self, manifest: &EpochManifest) -> Result<String> {
+        let url = format!("ar://{}", manifest.hash()?);
+        self.mem.write().unwrap().insert(url.clone(), manifest.clone());
+        *self.num_published.write().unwrap() += 1;
+        Ok(url)
+    }
+}
+
+/// URLs of the published manifests by epoch and manifest hash.
+#[async_trait]
+pub trait PublishedManifestRepository: Sync + Send {
+    async fn url_by_hash(&self, epoch_index: u64, hash: &Hash) -> Result<Option<String>>;
+    async fn create(&self, epoch_index: u64, hash: &Hash, url: &str) -> Result<()>;
+}
+
+/// Publishes each manifest once. Entering investment is retried after failures, which would otherwise pay for
+/// uploading the same manifest again.
+pub struct RecordedManifestPublisher {
+    publisher: Box<dyn ManifestPublisher>,
+    repository: Box<dyn PublishedManifestRepository>,
+}
+
+impl RecordedManifestPublisher {
+    pub fn new(publisher: Box<dyn ManifestPublisher>, repository: Box<dyn PublishedManifestRepository>) -> Self {
+        Self { publisher, repository }
+    }
+}
+
+#[async_trait]
+impl ManifestPublisher for RecordedManifestPublisher {
+    async fn publish(&self, manifest: &EpochManifest) -> Result<String> {
+        let hash = manifest.hash()?;
+        if let Some(url) = self.repository.url_by_hash(manifest.epoch_index, &hash).await? {
+            log::info!(
+                "Manifest {} of epoch {} is already published",
+                hash,
+                manifest.epoch_index
+            );
+            return Ok(url);
+        }
+        let url = self.publisher.publish(manifest).await?;
+        self.repository.create(manifest.epoch_index, &hash, &url).await?;
+        Ok(url)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct InMemoryPublishedManifestRepository {
+    mem: Arc<RwLock<HashMap<(u64, Hash), String>>>,
+}
+
+#[async_trait]
+impl PublishedManifestRepository for InMemoryPublishedManifestRepository {
+    async fn url_by_hash(&self, epoch_index: u64, hash: &Hash) -> Result<Option<String>> {
+        Ok(self.mem.read().unwrap().get(&(epoch_index, *hash)).cloned())
+    }
+
+    async fn create(&self, epoch_index: u64, hash: &Hash, url: &str) -> Result<()> {
+        self.mem
+            .write()
+            .unwrap()
+            .entry((epoch_index, *hash))
+            .or_insert_with(|| url.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arweave_uploader::FsUploaderClient;
+    use rand::distributions::{Alphanumeric, DistString};
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    use crate::tickets::{Sequence, SequenceType};
+
+    fn ticket(epoch_index: u64, balance: &str, nums: &[[u8; 6]]) -> Ticket {
+        Ticket {
+            wallet: Pubkey::new_unique(),
+            epoch_index,
+            sequences: nums
+                .iter()
+                .map(|nums| Sequence {
+                    nums: *nums,
+                    sequence_type: SequenceType::Normal,
+                })
+                .collect(),
+            balance: balance.to_string(),
+            ..Ticket::new_for_tests()
+        }
+    }
+
+    #[test]
+    fn manifest_is_canonical() {
+        let tickets = vec![
+            ticket(1, "25.0", &[[9, 8, 7, 6, 5, 4], [1, 2, 3, 4, 5, 6]]),
+            ticket(1, "12.5", &[[3, 4, 5, 6, 7, 8]]),
+            ticket(2, "10.0", &[[1, 2, 3, 4, 5, 6]]),
+        ];
+        let manifest = EpochManifest::new(1, &tickets).unwrap();
+        assert_eq!(manifest.num_sequences, 3);
+        assert_eq!(manifest.tickets.len(), 2);
+
+        let reversed = tickets.iter().rev().cloned().collect::<Vec<_>>();
+        let other = EpochManifest::new(1, &reversed).unwrap();
+        assert_eq!(manifest.to_bytes().unwrap(), other.to_bytes().unwrap());
+        assert_eq!(manifest.hash().unwrap(), other.hash().unwrap());
+
+        let ticket = manifest
+            .tickets
+            .iter()
+            .find(|ticket| ticket.wallet == tickets[0].wallet.to_string())
+            .unwrap();
+        assert_eq!(ticket.balance, 25_000_000);
+        assert_eq!(ticket.sequences, vec![[1, 2, 3, 4, 5, 6], [9, 8, 7, 6, 5, 4]]);
+    }
+
+    #[tokio::test]
+    async fn publishes_manifest() {
+        let dir = std::env::temp_dir().join(format!(
+            "manifests-{}",
+            Alphanumeric.sample_string(&mut rand::thread_rng(), 10)
+        ));
+        let publisher = ArweaveManifestPublisher::new(FsUploaderClient::new(&dir), "nezha-test".to_string());
+        let manifest = EpochManifest::new(1, &[ticket(1, "25.0", &[[1, 2, 3, 4, 5, 6]])]).unwrap();
+
+        let url = publisher.publish(&manifest).await.unwrap();
+        let tickets_info = manifest.tickets_info(url.clone()).unwrap();
+        assert_eq!(tickets_info.num_tickets, 1);
+        assert_eq!(tickets_info.tickets_version, MANIFEST_VERSION);
+
+        let uploader = FsUploaderClient::new(&dir);
+        let id = url.strip_prefix("ar://").unwrap();
+        let uploaded = uploader.read(id).unwrap();
+        assert_eq!(hash(&uploaded).to_bytes().to_vec(), tickets_info.tickets_hash);
+        assert_eq!(serde_json::from_slice::<EpochManifest>(&uploaded).unwrap(), manifest);
+
+        let tags = uploader.tags(id).unwrap();
+        let epoch_index = tags.iter().find(|tag| tag.name == "Epoch-Index").unwrap();
+        assert_eq!(epoch_index.value, "1");
+    }
+
+    #[tokio::test]
+    async fn publishes_manifest_once() {
+        let publisher = InMemoryManifestPublisher::default();
+        let recorded = RecordedManifestPublisher::new(
+            Box::new(publisher.clone()),
+            Box::new(InMemoryPublishedManifestRepository::default()),
+        );
+        let tickets = vec![ticket(1, "25.0", &[[1, 2, 3, 4, 5, 6]])];
+        let manifest = EpochManifest::new(1, &tickets).unwrap();
+
+        let url = recorded.publish(&manifest).await.unwrap();
+        assert_eq!(recorded.publish(&manifest).await.unwrap(), url);
+        assert_eq!(publisher.num_published(), 1);
+
+        // A manifest with other tickets is published again
+        let tickets = vec![ticket(1, "25.0", &[[1, 2, 3, 4, 5, 6], [6, 5, 4, 3, 2, 1]])];
+        let other = EpochManifest::new(1, &tickets).unwrap();
+        assert_ne!(recorded.publish(&other).await.unwrap(), url);
+        assert_eq!(publisher.num_published(), 2);
+    }
+}
@@ -4,17 +4,25 @@ use std::collections::HashMap;
 use std::collections::{BTreeMap, BTreeSet};
 
 pub mod bonus;
+mod manifest;
+mod odds;
 mod price_calculators;
 mod repository;
+mod seed;
 mod sequence;
 mod service;
 mod service_impl;
+mod time_weighted;
 
+pub use self::manifest::*;
+pub use self::odds::*;
 pub use self::price_calculators::*;
 pub use self::repository::*;
+pub use self::seed::*;
 pub use self::sequence::*;
 pub use self::service::*;
 pub use self::service_impl::*;
+pub use self::time_weighted::*;
 
 #[derive(Debug, Clone)]
 pub struct Ticket {
@@ -92,22 +100,31 @@ mod tests {
 
     use super::{
         bonus::{BonusInfo, BonusInfoService},
-        ConstantTicketPriceCalculator, DefaultTicketService, InMemoryTicketRepository, Sequence, Ticket,
+        verify_ticket, ConstantTicketPriceCalculator, DefaultTicketService, EpochSeedRepository,
+        InMemoryEpochSeedRepository, InMemoryTicketRepository, Sequence, Ticket, TicketAllocation, TicketGeneration,
         TicketRepository, TicketService,
     };
     use crate::{
-        model::epoch::EpochStatus,
+        campaign::{CampaignService, DefaultCampaignService, InMemoryCampaignRepository},
+        events::EventBus,
+        model::{
+            campaign::{CampaignEligibility, CampaignReward, NewCampaign},
+            epoch::EpochStatus,
+            ticket::TicketError,
+            transaction::{Transaction, TransactionId, TransactionType},
+        },
         solana::mock::SolanaMock,
         tickets::{bonus::BonusSequenceCount, generate_sequences_with_type, SequenceType},
+        transaction::UserTransactionRepository,
     };
     use anyhow::Result;
     use async_trait::async_trait;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
     use nezha_staking::fixed_point::FPUSDC;
     use pretty_assertions::assert_eq;
-    use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use rand_chacha::ChaChaRng;
     use solana_program::pubkey::Pubkey;
-    use utils::with_mutex;
 
     pub struct MockBonusInfoService {
         pub bonus_info: BonusInfo,
@@ -130,6 +147,59 @@ mod tests {
         }
     }
 
+    fn fp(s: &str) -> FPUSDC {
+        s.parse().unwrap()
+    }
+
+    /// Ticket service with a constant price. Generates random tickets for snapshot balances, without campaigns unless
+    /// set otherwise.
+    struct TestTicketService {
+        solana: SolanaMock,
+        repository: InMemoryTicketRepository,
+        price: FPUSDC,
+        sub_seq_min_stake: FPUSDC,
+        generation: TicketGeneration,
+        allocation: TicketAllocation,
+        campaigns: InMemoryCampaignRepository,
+    }
+
+    impl TestTicketService {
+        fn new(
+            solana: SolanaMock,
+            repository: InMemoryTicketRepository,
+            price: FPUSDC,
+            sub_seq_min_stake: FPUSDC,
+        ) -> Self {
+            Self {
+                solana,
+                repository,
+                price,
+                sub_seq_min_stake,
+                generation: TicketGeneration::Random,
+                allocation: TicketAllocation::Snapshot,
+                campaigns: InMemoryCampaignRepository::default(),
+            }
+        }
+
+        fn build<R: Rng + Sync + Send>(self, rng: Arc<Mutex<R>>) -> DefaultTicketService<R> {
+            let campaigns = DefaultCampaignService::new(Box::new(self.campaigns), Box::new(self.repository.clone()));
+            DefaultTicketService::new(
+                rng,
+                Box::new(self.solana),
+                Box::new(self.repository),
+                Box::new(ConstantTicketPriceCalculator::new(self.price)),
+                Box::new(MockBonusInfoService::new(BonusInfo {
+                    sub_seq_count: BonusSequenceCount::Constant(1),
+                    sub_seq_min_stake: self.sub_seq_min_stake,
+                })),
+                EventBus::default(),
+                self.generation,
+                self.allocation,
+                Box::new(campaigns),
+            )
+        }
+    }
+
     #[tokio::test]
     async fn test_ticket_service_updates_arweave_url() -> Result<()> {
         let rng = Arc::new(Mutex::new(ChaChaRng::from_entropy()));
@@ -147,19 +217,8 @@ mod tests {
 
         let mut ticket_repository = InMemoryTicketRepository::new(0);
         ticket_repository.add(ticket.clone());
-        let price_calculator = ConstantTicketPriceCalculator::new(price);
-        let solana = SolanaMock::new();
-        let bonus_info_service = MockBonusInfoService::new(BonusInfo {
-            sub_seq_count: BonusSequenceCount::Constant(1),
-            sub_seq_min_stake: balance.change_precision(),
-        });
-        let ticket_service = DefaultTicketService::new(
-            rng.clone(),
-            Box::new(solana),
-            Box::new(ticket_repository),
-            Box::new(price_calculator),
-            Box::new(bonus_info_service),
-        );
+        let ticket_service =
+            TestTicketService::new(SolanaMock::new(), ticket_repository, price, balance.change_precision()).build(rng);
 
         let actual_ticket = ticket_service
             .update_arweave_url(&wallet, epoch_index, arweave_url.clone())
@@ -191,19 +250,8 @@ mod tests {
 
         let mut ticket_repository = InMemoryTicketRepository::new(0);
         ticket_repository.add(expected_ticket.clone());
-        let price_calculator = ConstantTicketPriceCalculator::new(price);
-        let solana = SolanaMock::new();
-        let bonus_info_service = MockBonusInfoService::new(BonusInfo {
-            sub_seq_count: BonusSequenceCount::Constant(1),
-            sub_seq_min_stake: balance.change_precision(),
-        });
-        let ticket_service = DefaultTicketService::new(
-            rng.clone(),
-            Box::new(solana),
-            Box::new(ticket_repository),
-            Box::new(price_calculator),
-            Box::new(bonus_info_service),
-        );
+        let ticket_service =
+            TestTicketService::new(SolanaMock::new(), ticket_repository, price, balance.change_precision()).build(rng);
 
         let actual_ticket = ticket_service
             .read_ticket_by_wallet_and_epoch_index(&wallet, epoch_index)
@@ -215,6 +263,71 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_ticket_service_calculates_ticket_odds() -> Result<()> {
+        let wallet = Pubkey::new_unique();
+        let epoch_index = 1;
+        let sequences = |nums: &[[u8; 6]]| -> Vec<Sequence> {
+            nums.iter()
+                .map(|&nums| Sequence {
+                    nums,
+                    sequence_type: SequenceType::Normal,
+                })
+                .collect()
+        };
+
+        let ticket_repository = InMemoryTicketRepository::new(0);
+        ticket_repository
+            .create(&Ticket {
+                wallet,
+                epoch_index,
+                sequences: sequences(&[[1, 2, 3, 4, 5, 6], [9, 8, 7, 6, 5, 4]]),
+                ..Ticket::new_for_tests()
+            })
+            .await?;
+        ticket_repository
+            .create(&Ticket {
+                wallet: Pubkey::new_unique(),
+                epoch_index,
+                sequences: sequences(&[[1, 2, 3, 4, 5, 6], [9, 8, 7, 6, 5, 1]]),
+                ..Ticket::new_for_tests()
+            })
+            .await?;
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = epoch_index;
+        solana.epoch_status = EpochStatus::Finalising;
+        let ticket_service = |solana, ticket_repository| {
+            TestTicketService::new(solana, ticket_repository, fp("1.0"), fp("25.0"))
+                .build(Arc::new(Mutex::new(ChaChaRng::from_entropy())))
+        };
+
+        let ticket_service_finalising = ticket_service(solana.clone(), ticket_repository);
+        let odds = ticket_service_finalising
+            .ticket_odds(&wallet)
+            .await?
+            .expect("Ticket reported as not found");
+        assert!(odds.final_prize_pools);
+        assert_eq!(
+            odds.tiers
+                .iter()
+                .map(|tier| tier.num_winning_combinations)
+                .collect::<Vec<_>>(),
+            vec![2, 9 + 9, 2 * 51 * 10]
+        );
+        assert_eq!(odds.tiers[0].prize_pool, "1".parse().unwrap());
+        assert_eq!(
+            ticket_service_finalising.ticket_odds(&Pubkey::new_unique()).await?,
+            None
+        );
+
+        // The epoch is checked before the ticket is read
+        solana.epoch_status = EpochStatus::Ended;
+        let ticket_service_ended = ticket_service(solana, InMemoryTicketRepository::new(0));
+        assert!(ticket_service_ended.ticket_odds(&wallet).await.is_err());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_ticket_service_generates_ticket() -> Result<()> {
         let rng = Arc::new(Mutex::new(StdRng::from_seed([0u8; 32])));
@@ -227,7 +340,6 @@ mod tests {
 
         let num_airdrop_sequences = 5;
         let ticket_repository = InMemoryTicketRepository::new(num_airdrop_sequences);
-        let price_calculator = ConstantTicketPriceCalculator::new(price);
         let mut solana = SolanaMock::new();
         solana.epoch_index = epoch_index;
         solana.epoch_status = EpochStatus::Running;
@@ -236,17 +348,8 @@ mod tests {
             amount: balance,
             updated_epoch_index: epoch_index,
         });
-        let bonus_info_service = MockBonusInfoService::new(BonusInfo {
-            sub_seq_count: BonusSequenceCount::Constant(1),
-            sub_seq_min_stake: balance.change_precision(),
-        });
-        let ticket_service = DefaultTicketService::new(
-            rng,
-            Box::new(solana),
-            Box::new(ticket_repository),
-            Box::new(price_calculator),
-            Box::new(bonus_info_service),
-        );
+        let ticket_service =
+            TestTicketService::new(solana, ticket_repository, price, balance.change_precision()).build(rng);
 
         let actual_ticket = ticket_service
             .generate_ticket_for_wallet(&wallet, Some(epoch_index))
@@ -287,7 +390,6 @@ mod tests {
         let wallet = Pubkey::new_unique();
         let epoch_index = 0;
 
-        let price_calculator = ConstantTicketPriceCalculator::new("1.0".parse().unwrap());
         let mut solana = SolanaMock::new();
         solana.stakes.push(crate::solana::Stake {
             owner: wallet,
@@ -302,20 +404,11 @@ mod tests {
                 epoch_index + 1
             };
 
-            let ticket_repository = InMemoryTicketRepository::new(0);
             solana.epoch_index = epoch_index;
             solana.epoch_status = epoch_status;
-            let bonus_info_service = MockBonusInfoService::new(BonusInfo {
-                sub_seq_count: BonusSequenceCount::Constant(1),
-                sub_seq_min_stake: "25.0".parse().unwrap(),
-            });
-            let ticket_service = DefaultTicketService::new(
-                rng.clone(),
-                Box::new(solana.clone()),
-                Box::new(ticket_repository),
-                Box::new(price_calculator.clone()),
-                Box::new(bonus_info_service),
-            );
+            let ticket_service =
+                TestTicketService::new(solana.clone(), InMemoryTicketRepository::new(0), fp("1.0"), fp("25.0"))
+                    .build(rng.clone());
 
             let actual_ticket = ticket_service.generate_ticket_for_wallet(&wallet, None).await?;
             assert_eq!(expected_epoch_index, actual_ticket.epoch_index);
@@ -325,57 +418,589 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_calculate_optimal_winning_combination() -> Result<()> {
-        let mut rng = StdRng::from_seed([0u8; 32]);
-        let epoch_index = rng.gen();
+    async fn test_ticket_service_submits_user_picks() -> Result<()> {
+        let rng = Arc::new(Mutex::new(StdRng::from_seed([0u8; 32])));
+        let wallet = Pubkey::new_unique();
+        let epoch_index = 0;
+        let balance: FPUSDC = "20.0".parse().unwrap();
+        let price: FPUSDC = "4.0".parse().unwrap();
 
-        let ticket_repository = InMemoryTicketRepository::new(0);
+        let ticket_repository = InMemoryTicketRepository::new(1);
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = epoch_index;
+        solana.epoch_status = EpochStatus::Running;
+        solana.stakes.push(crate::solana::Stake {
+            owner: wallet,
+            amount: balance,
+            updated_epoch_index: epoch_index,
+        });
+        let ticket_service =
+            TestTicketService::new(solana, ticket_repository, price, balance.change_precision()).build(rng);
 
-        let mut prefix: [u8; 6] = Default::default();
-        rng.fill_bytes(&mut prefix);
+        let picks = [[1, 2, 3, 4, 5, 6], [56, 55, 54, 53, 52, 10], [1, 2, 3, 4, 5, 6]];
+        let ticket = ticket_service.submit_user_picks(&wallet, &picks).await?;
+        let count = |sequence_type| {
+            ticket
+                .sequences
+                .iter()
+                .filter(|sequence| sequence.sequence_type == sequence_type)
+                .count()
+        };
+        assert_eq!(ticket.sequences.len(), 7);
+        assert_eq!(count(SequenceType::UserPicked), 2);
+        assert_eq!(count(SequenceType::Normal), 3);
+        assert_eq!(count(SequenceType::AirdropBonus), 1);
+        assert_eq!(count(SequenceType::SignUpBonus), 1);
+        assert_eq!(
+            ticket.sequences.iter().map(|s| s.nums).collect::<HashSet<_>>().len(),
+            7,
+            "sequences must be unique"
+        );
 
-        let rng = Mutex::new(rng);
+        // Submitting again replaces the previous picks
+        let ticket = ticket_service
+            .submit_user_picks(&wallet, &[[7, 8, 9, 10, 11, 1]])
+            .await?;
+        let picked = ticket
+            .sequences
+            .iter()
+            .filter(|sequence| sequence.sequence_type == SequenceType::UserPicked)
+            .map(|sequence| sequence.nums)
+            .collect::<Vec<_>>();
+        assert_eq!(picked, vec![[7, 8, 9, 10, 11, 1]]);
+        assert_eq!(ticket.sequences.len(), 7);
 
-        for _ in 0..10 {
-            let sequences_count = with_mutex(&rng, |rng| rng.gen_range(1..=10));
-            let mut ticket = Ticket {
-                epoch_index,
-                sequences: generate_sequences_with_type(&rng, None, sequences_count, SequenceType::Normal)?,
-                ..Ticket::new_for_tests()
-            };
-            let prefix_len = with_mutex(&rng, |rng| rng.gen_range(4..=6));
-            let mut sequence: [u8; 6] = Default::default();
-            sequence[..prefix_len].clone_from_slice(&prefix[..prefix_len]);
-            for i in prefix_len..6 {
-                sequence[i] = with_mutex(&rng, |rng| rng.gen());
+        let too_many = [
+            [1, 2, 3, 4, 5, 1],
+            [1, 2, 3, 4, 5, 2],
+            [1, 2, 3, 4, 5, 3],
+            [1, 2, 3, 4, 5, 4],
+            [1, 2, 3, 4, 5, 5],
+            [1, 2, 3, 4, 5, 6],
+        ];
+        assert!(ticket_service.submit_user_picks(&wallet, &too_many).await.is_err());
+        assert!(ticket_service
+            .submit_user_picks(&wallet, &[[1, 2, 3, 4, 57, 1]])
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_derives_ticket() -> Result<()> {
+        let rng = Arc::new(Mutex::new(ChaChaRng::from_entropy()));
+        let wallet = Pubkey::new_unique();
+        let epoch_index = 0;
+        let price: FPUSDC = "4.0".parse().unwrap();
+        let mut stake = crate::solana::Stake {
+            owner: wallet,
+            amount: "20.0".parse().unwrap(),
+            updated_epoch_index: epoch_index,
+        };
+
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = epoch_index;
+        solana.epoch_status = EpochStatus::Running;
+        solana.stakes.push(stake.clone());
+        let seeds = InMemoryEpochSeedRepository::default();
+        let new_service = || {
+            TestTicketService {
+                generation: TicketGeneration::Deterministic(Box::new(seeds.clone())),
+                ..TestTicketService::new(solana.clone(), InMemoryTicketRepository::new(1), price, fp("20.0"))
             }
-            ticket.sequences.push(Sequence {
-                nums: sequence,
-                sequence_type: SequenceType::Normal,
-            });
-            ticket_repository.create(&ticket).await?;
+            .build(rng.clone())
+        };
+
+        // The same inputs derive the same ticket, whatever the service's RNG
+        let ticket = new_service()
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(ticket.sequences.len(), 7);
+        let ticket_service = new_service();
+        let again = ticket_service
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(ticket, again);
+
+        let verification = ticket_service
+            .verify_ticket(&wallet, epoch_index)
+            .await?
+            .expect("Ticket reported as not found");
+        assert!(verification.verified);
+        let epoch_seed = seeds.by_epoch_index(epoch_index).await?.unwrap();
+        assert!(verify_ticket(&ticket, &epoch_seed)?);
+
+        // Picks replace derived sequences, which are derived again around them
+        let ticket = ticket_service.submit_user_picks(&wallet, &[[1, 2, 3, 4, 5, 6]]).await?;
+        assert_eq!(ticket.sequences.len(), 7);
+        assert!(verify_ticket(&ticket, &epoch_seed)?);
+
+        // A new stake amount derives the ticket again, keeping the picks
+        stake.amount = "24.0".parse().unwrap();
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(ticket.balance, stake.amount.to_string());
+        assert_eq!(ticket.sequences.len(), 8);
+        assert!(ticket
+            .sequences
+            .iter()
+            .any(|sequence| sequence.sequence_type == SequenceType::UserPicked));
+        assert!(verify_ticket(&ticket, &epoch_seed)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_defers_derived_tickets_of_next_epoch() -> Result<()> {
+        let rng = Arc::new(Mutex::new(ChaChaRng::from_entropy()));
+        let stake = crate::solana::Stake {
+            owner: Pubkey::new_unique(),
+            amount: "20.0".parse().unwrap(),
+            updated_epoch_index: 1,
+        };
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = 1;
+        solana.epoch_status = EpochStatus::Ended;
+        solana.stakes.push(stake.clone());
+        let ticket_repository = InMemoryTicketRepository::new(1);
+        let seeds = InMemoryEpochSeedRepository::default();
+        let new_service = |solana: SolanaMock| {
+            TestTicketService {
+                generation: TicketGeneration::Deterministic(Box::new(seeds.clone())),
+                ..TestTicketService::new(solana, ticket_repository.clone(), fp("4.0"), fp("20.0"))
+            }
+            .build(rng.clone())
+        };
+
+        // Epoch 2 has no seed until it's created
+        let ticket_service = new_service(solana.clone());
+        assert!(ticket_service.generate_tickets_for_all().await?.is_empty());
+        let err = ticket_service
+            .generate_ticket_for_stake(&stake, None)
+            .await
+            .expect_err("ticket of an epoch not created yet was generated");
+        assert!(
+            matches!(
+                err.downcast_ref::<TicketError>(),
+                Some(TicketError::EpochSeedNotAvailable(2))
+            ),
+            "unexpected error: {err}"
+        );
+        assert!(ticket_repository.by_epoch_index(2).await?.is_empty());
+
+        // Generated once it is
+        solana.epoch_index = 2;
+        solana.epoch_status = EpochStatus::Running;
+        let tickets = new_service(solana).generate_tickets_for_all().await?;
+        assert_eq!(tickets.len(), 1);
+        assert_eq!(tickets[0].as_ref().unwrap().epoch_index, 2);
+        assert!(seeds.by_epoch_index(2).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_adjusts_ticket_to_balance() -> Result<()> {
+        let rng = Arc::new(Mutex::new(StdRng::from_seed([0u8; 32])));
+        let wallet = Pubkey::new_unique();
+        let epoch_index = 0;
+        let mut stake = crate::solana::Stake {
+            owner: wallet,
+            amount: "20.0".parse().unwrap(),
+            updated_epoch_index: epoch_index,
+        };
+
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = epoch_index;
+        solana.epoch_status = EpochStatus::Running;
+        let ticket_service =
+            TestTicketService::new(solana, InMemoryTicketRepository::new(1), fp("4.0"), fp("20.0")).build(rng);
+        let count = |ticket: &Ticket, sequence_type| {
+            ticket
+                .sequences
+                .iter()
+                .filter(|sequence| sequence.sequence_type == sequence_type)
+                .count()
+        };
+
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(ticket.sequences.len(), 7);
+
+        // A withdrawal removes sequences, bonus sequences are kept
+        stake.amount = "8.0".parse().unwrap();
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(ticket.balance, stake.amount.to_string());
+        assert_eq!(count(&ticket, SequenceType::Normal), 2);
+        assert_eq!(count(&ticket, SequenceType::AirdropBonus), 1);
+        assert_eq!(count(&ticket, SequenceType::SignUpBonus), 1);
+
+        stake.amount = "28.0".parse().unwrap();
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&stake, Some(epoch_index))
+            .await?;
+        assert_eq!(count(&ticket, SequenceType::Normal), 7);
+        assert_eq!(ticket.sequences.len(), 9);
+        assert_eq!(
+            ticket.sequences.iter().map(|s| s.nums).collect::<HashSet<_>>().len(),
+            9,
+            "sequences must be unique"
+        );
+
+        Ok(())
+    }
+
+    /// Deposits and withdrawals indexed for the time weighted allocation.
+    struct BalanceChanges(Vec<Transaction>);
+
+    #[async_trait]
+    impl UserTransactionRepository for BalanceChanges {
+        async fn by_transaction_id_and_instruction_index(
+            &self,
+            _transaction_id: &TransactionId,
+            _instruction_index: u8,
+            _inner_instruction_index: Option<u8>,
+        ) -> Result<Option<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_transaction_id(&self, _transaction_id: &TransactionId) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_wallet(&self, _wallet: &Pubkey, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_type(
+            &self,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn by_wallet_and_type(
+            &self,
+            _wallet: &Pubkey,
+            _transaction_type: TransactionType,
+            _limit: usize,
+            _offset: usize,
+        ) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn all(&self, _limit: usize, _offset: usize) -> Result<Vec<Transaction>> {
+            unimplemented!()
+        }
+        async fn store_transaction(&self, _transaction: &Transaction) -> Result<()> {
+            unimplemented!()
         }
-        let price_calculator = ConstantTicketPriceCalculator::new("1.0".parse().unwrap());
+        async fn store_transactions(&self, _transactions: &[Transaction]) -> Result<()> {
+            unimplemented!()
+        }
+        async fn remove_unfinalized(&self, _up_to_slot: u64) -> Result<u64> {
+            unimplemented!()
+        }
+        async fn total_deposit_by_wallet(&self, _wallet: &Pubkey) -> Result<FPUSDC> {
+            unimplemented!()
+        }
+        async fn balance_changes_by_wallet(&self, wallet: &Pubkey, since: DateTime<Utc>) -> Result<Vec<Transaction>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|transaction| transaction.wallet == *wallet && transaction.time.unwrap() > since)
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn completed(transaction_type: TransactionType, wallet: Pubkey, amount: &str, time: DateTime<Utc>) -> Transaction {
+        Transaction {
+            transaction_id: TransactionId(Pubkey::new_unique().to_string()),
+            instruction_index: 0,
+            wallet,
+            amount: amount.parse().unwrap(),
+            mint: Pubkey::new_unique(),
+            time: Some(time),
+            transaction_type,
+            inner_instruction_index: None,
+            slot: None,
+            finalized: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_allocates_time_weighted_balance() -> Result<()> {
+        let epoch_index = 3;
+        let started_at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let expected_end_at = started_at + Duration::days(10);
+        let stake = |amount: &str, updated_epoch_index| crate::solana::Stake {
+            owner: Pubkey::new_unique(),
+            amount: amount.parse().unwrap(),
+            updated_epoch_index,
+        };
+        // Deposited 100 halfway through the epoch, and 100 more after its expected end
+        let depositor = stake("200.0", epoch_index);
+        // Held 100 all along
+        let holder = stake("100.0", epoch_index - 1);
+        // Deposited 100, but the indexer didn't catch up yet
+        let unindexed = stake("100.0", epoch_index);
+
         let mut solana = SolanaMock::new();
         solana.epoch_index = epoch_index;
         solana.epoch_status = EpochStatus::Running;
-        let bonus_info_service = MockBonusInfoService::new(BonusInfo {
-            sub_seq_count: BonusSequenceCount::Constant(1),
-            sub_seq_min_stake: "25.0".parse().unwrap(),
-        });
-        let ticket_service = DefaultTicketService::new(
-            Arc::new(rng),
-            Box::new(solana),
-            Box::new(ticket_repository),
-            Box::new(price_calculator),
-            Box::new(bonus_info_service),
+        solana.epoch_start_at = started_at.timestamp();
+        solana.epoch_expected_end_at = expected_end_at.timestamp();
+        solana.stakes = vec![depositor.clone(), holder.clone(), unindexed.clone()];
+        let ticket_service = TestTicketService {
+            allocation: TicketAllocation::TimeWeighted(Box::new(BalanceChanges(vec![
+                completed(
+                    TransactionType::DepositCompleted,
+                    depositor.owner,
+                    "100",
+                    started_at + Duration::days(5),
+                ),
+                completed(
+                    TransactionType::DepositCompleted,
+                    depositor.owner,
+                    "100",
+                    expected_end_at + Duration::days(1),
+                ),
+            ]))),
+            ..TestTicketService::new(solana, InMemoryTicketRepository::new(1), fp("5.0"), fp("1000.0"))
+        }
+        .build(Arc::new(Mutex::new(StdRng::from_seed([0u8; 32]))));
+        let normal = |ticket: &Ticket| {
+            ticket
+                .sequences
+                .iter()
+                .filter(|sequence| sequence.sequence_type == SequenceType::Normal)
+                .count()
+        };
+
+        // Balances are averaged up to the expected end of the epoch, changes made after it don't count
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&depositor, Some(epoch_index))
+            .await?;
+        assert_eq!(normal(&ticket), 10);
+        let ticket = ticket_service
+            .generate_ticket_for_stake(&holder, Some(epoch_index))
+            .await?;
+        assert_eq!(normal(&ticket), 20);
+
+        let tickets = ticket_service.reconcile_tickets().await?;
+        assert_eq!(tickets.len(), 3);
+        assert_eq!(normal(tickets[0].as_ref().unwrap()), 10);
+        assert_eq!(normal(tickets[1].as_ref().unwrap()), 20);
+        // The deposit would otherwise count as held since the epoch started
+        let err = tickets[2].as_ref().unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<TicketError>(),
+                Some(TicketError::StakeChangesNotIndexed(wallet, index)) if *wallet == unindexed.owner && *index == epoch_index
+            ),
+            "unexpected error: {err}"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_verifies_picks_after_stake_change() -> Result<()> {
+        let epoch_index = 3;
+        let started_at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let expected_end_at = started_at + Duration::days(10);
+        // Deposited 100 halfway through the epoch: 150 on average
+        let stake = crate::solana::Stake {
+            owner: Pubkey::new_unique(),
+            amount: "200.0".parse().unwrap(),
+            updated_epoch_index: epoch_index,
+        };
+        let wallet = stake.owner;
+
+        let mut solana = SolanaMock::new();
+        solana.epoch_index = epoch_index;
+        solana.epoch_status = EpochStatus::Running;
+        solana.epoch_start_at = started_at.timestamp();
+        solana.epoch_expected_end_at = expected_end_at.timestamp();
+        solana.stakes.push(stake.clone());
+        let seeds = InMemoryEpochSeedRepository::default();
+        let ticket_service = TestTicketService {
+            generation: TicketGeneration::Deterministic(Box::new(seeds.clone())),
+            allocation: TicketAllocation::TimeWeighted(Box::new(BalanceChanges(vec![completed(
+                TransactionType::DepositCompleted,
+                wallet,
+                "100",
+                started_at + Duration::days(5),
+            )]))),
+            ..TestTicketService::new(solana, InMemoryTicketRepository::new(0), fp("5.0"), fp("1000.0"))
+        }
+        .build(Arc::new(Mutex::new(StdRng::from_seed([0u8; 32]))));
+
+        // Entitled to the sequences of the time weighted balance, not of the current one
+        let too_many = (1..=31).map(|i| [i, 32, 33, 34, 35, 1]).collect::<Vec<_>>();
+        let err = ticket_service
+            .submit_user_picks(&wallet, &too_many)
+            .await
+            .expect_err("Picked more sequences than entitled to");
+        assert!(
+            matches!(
+                err.downcast_ref::<TicketError>(),
+                Some(TicketError::TooManyPicks {
+                    entitled: 30,
+                    picked: 31
+                })
+            ),
+            "unexpected error: {err}"
         );
 
-        let sequence = ticket_service
-            .calculate_optimal_winning_combination()
+        let ticket = ticket_service.submit_user_picks(&wallet, &[[1, 2, 3, 4, 5, 6]]).await?;
+        assert_eq!(ticket.balance, fp("150.0").to_string());
+        assert_eq!(ticket.sequences.len(), 30);
+        let epoch_seed = seeds.by_epoch_index(epoch_index).await?.unwrap();
+        assert!(verify_ticket(&ticket, &epoch_seed)?);
+        let verification = ticket_service
+            .verify_ticket(&wallet, epoch_index)
             .await?
-            .expect("should have found a winning combination");
-        assert_eq!(sequence[..4], prefix[..4]);
+            .expect("Ticket reported as not found");
+        assert!(verification.verified);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_reconciles_running_epoch_only() -> Result<()> {
+        let epoch_index = 3;
+        for status in all_epoch_status() {
+            let mut solana = SolanaMock::new();
+            solana.epoch_index = epoch_index;
+            solana.epoch_status = status;
+            solana.stakes.push(crate::solana::Stake {
+                owner: Pubkey::new_unique(),
+                amount: "20.0".parse().unwrap(),
+                updated_epoch_index: epoch_index,
+            });
+            let ticket_repository = InMemoryTicketRepository::new(1);
+            let ticket_service = TestTicketService::new(solana, ticket_repository.clone(), fp("5.0"), fp("20.0"))
+                .build(Arc::new(Mutex::new(StdRng::from_seed([0u8; 32]))));
+
+            let tickets = ticket_service.reconcile_tickets().await?;
+            if status == EpochStatus::Running {
+                assert_eq!(tickets.len(), 1);
+                assert_eq!(tickets[0].as_ref().unwrap().epoch_index, epoch_index);
+                assert_eq!(ticket_repository.by_epoch_index(epoch_index).await?.len(), 1);
+            } else {
+                assert!(tickets.is_empty(), "{:?} epoch reconciled", status);
+                assert!(ticket_repository.by_epoch_index(epoch_index).await?.is_empty());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ticket_service_grants_campaign_bonus_sequences() -> Result<()> {
+        let epoch_index = 0;
+        for generation in [
+            TicketGeneration::Random,
+            TicketGeneration::Deterministic(Box::new(InMemoryEpochSeedRepository::default())),
+        ] {
+            let mut stake = crate::solana::Stake {
+                owner: Pubkey::new_unique(),
+                amount: "20.0".parse().unwrap(),
+                updated_epoch_index: epoch_index,
+            };
+            let mut solana = SolanaMock::new();
+            solana.epoch_index = epoch_index;
+            solana.epoch_status = EpochStatus::Running;
+            solana.stakes.push(stake.clone());
+            let campaign_repository = InMemoryCampaignRepository::default();
+            let ticket_service = TestTicketService {
+                generation,
+                campaigns: campaign_repository.clone(),
+                ..TestTicketService::new(solana, InMemoryTicketRepository::new(0), fp("4.0"), fp("100.0"))
+            }
+            .build(Arc::new(Mutex::new(ChaChaRng::from_entropy())));
+            let campaigns = DefaultCampaignService::new(
+                Box::new(campaign_repository),
+                Box::new(InMemoryTicketRepository::new(0)),
+            );
+            let count = |ticket: &Ticket| {
+                ticket
+                    .sequences
+                    .iter()
+                    .filter(|sequence| sequence.sequence_type == SequenceType::CampaignBonus)
+                    .count()
+            };
+            let assert_unique = |ticket: &Ticket| {
+                assert_eq!(
+                    ticket.sequences.iter().map(|s| s.nums).collect::<HashSet<_>>().len(),
+                    ticket.sequences.len(),
+                    "sequences must be unique"
+                );
+            };
+
+            let ticket = ticket_service
+                .generate_ticket_for_stake(&stake, Some(epoch_index))
+                .await?;
+            assert_eq!(ticket.sequences.len(), 5);
+
+            // Campaigns set up during the epoch add sequences to existing tickets
+            campaigns
+                .create_campaign(
+                    NewCampaign {
+                        name: "Launch".to_string(),
+                        start_epoch: epoch_index,
+                        end_epoch: None,
+                        eligibility: CampaignEligibility {
+                            new_users_only: true,
+                            ..Default::default()
+                        },
+                        reward: CampaignReward::Multiplier { percent: 40 },
+                        max_sequences_per_wallet: None,
+                    },
+                    "ops",
+                )
+                .await?;
+            let ticket = ticket_service
+                .generate_ticket_for_stake(&stake, Some(epoch_index))
+                .await?;
+            assert_eq!(count(&ticket), 2);
+            assert_eq!(ticket.sequences.len(), 7);
+
+            // They follow the balance until the ticket is reconciled
+            stake.amount = "40.0".parse().unwrap();
+            let ticket = ticket_service
+                .generate_ticket_for_stake(&stake, Some(epoch_index))
+                .await?;
+            assert_eq!(count(&ticket), 4);
+            assert_eq!(ticket.sequences.len(), 14);
+            assert_unique(&ticket);
+            stake.amount = "20.0".parse().unwrap();
+            let ticket = ticket_service
+                .generate_ticket_for_stake(&stake, Some(epoch_index))
+                .await?;
+            assert_eq!(count(&ticket), 2);
+            assert_eq!(ticket.sequences.len(), 7);
+            assert!(campaigns.grants_by_wallet(&stake.owner).await?.is_empty());
+
+            let tickets = ticket_service.reconcile_tickets().await?;
+            assert_eq!(count(tickets[0].as_ref().unwrap()), 2);
+            let grants = campaigns.grants_by_wallet(&stake.owner).await?;
+            assert_eq!(grants.len(), 1);
+            assert_eq!(grants[0].num_sequences, 2);
+
+            // Granted once per epoch
+            stake.amount = "40.0".parse().unwrap();
+            let ticket = ticket_service
+                .generate_ticket_for_stake(&stake, Some(epoch_index))
+                .await?;
+            assert_eq!(count(&ticket), 2);
+            assert_eq!(ticket.sequences.len(), 12);
+            assert_unique(&ticket);
+        }
 
         Ok(())
     }
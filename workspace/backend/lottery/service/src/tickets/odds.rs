@@ -0,0 +1,238 @@
+use std::collections::{BTreeSet, HashMap};
+
+use nezha_staking::fixed_point::FPUSDC;
+use solana_program::pubkey::Pubkey;
+
+use super::{validate_sequence, Ticket};
+
+/// Number of possible winning combinations: 5 distinct numbers in 1..=56 drawn in order, then 1 number in 1..=10.
+pub const NUM_WINNING_COMBINATIONS: u64 = 56 * 55 * 54 * 53 * 52 * 10;
+
+/// Chances of a ticket in the draw of its epoch, and the prize it can expect.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TicketOdds {
+    pub wallet: Pubkey,
+    pub epoch_index: u64,
+    /// Tiers 1, 2 and 3.
+    pub tiers: Vec<TierOdds>,
+    /// Sum of the expected prizes of the tiers.
+    pub expected_prize: FPUSDC,
+    /// Whether the yield of the epoch was returned. Until then, the tier 2 and 3 prize pools only hold the prizes
+    /// carried over from previous epochs.
+    pub final_prize_pools: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TierOdds {
+    pub tier: u8,
+    /// Winning combinations for which the ticket wins the tier.
+    pub num_winning_combinations: u64,
+    pub probability: f64,
+    pub prize_pool: FPUSDC,
+    /// Prize of the tier averaged over all winning combinations, after sharing it with the other winners.
+    pub expected_prize: FPUSDC,
+}
+
+/// Sequences counted by holder, the wallet's own apart.
+#[derive(Debug, Clone, Copy, Default)]
+struct Holders {
+    own: u64,
+    all: u64,
+}
+
+impl Holders {
+    fn add(&mut self, own: bool) {
+        self.all += 1;
+        self.own += own as u64;
+    }
+
+    fn without(self, excluded: Option<&Holders>) -> Holders {
+        let excluded = excluded.copied().unwrap_or_default();
+        Holders {
+            own: self.own - excluded.own,
+            all: self.all - excluded.all,
+        }
+    }
+
+    /// The wallet's share of a prize split between the sequences.
+    fn share(self) -> f64 {
+        if self.own == 0 {
+            0.0
+        } else {
+            self.own as f64 / self.all as f64
+        }
+    }
+}
+
+/// Sequences starting with a prefix, and by the number following it.
+#[derive(Debug, Default)]
+struct PrefixHolders {
+    holders: Holders,
+    by_next: HashMap<u8, Holders>,
+}
+
+impl PrefixHolders {
+    /// Counts the numbers that can follow the prefix in the winning combination for which the wallet holds a sequence
+    /// matching the prefix and not the next number, and sums the wallet's share of the prize for each.
+    fn odds(&self, next_numbers: impl Iterator<Item = u8>) -> (u64, f64) {
+        next_numbers
+            .map(|next| self.holders.without(self.by_next.get(&next)))
+            .filter(|holders| holders.own > 0)
+            .fold((0, 0.0), |(count, share), holders| (count + 1, share + holders.share()))
+    }
+}
+
+/// Groups the sequences that can win by their prefix of `prefix_len` numbers.
+fn by_prefix(wallet: &Pubkey, tickets: &[Ticket], prefix_len: usize) -> HashMap<Vec<u8>, PrefixHolders> {
+    let mut by_prefix: HashMap<_, PrefixHolders> = HashMap::new();
+    for ticket in tickets {
+        for sequence in &ticket.sequences {
+            if validate_sequence(&sequence.nums).is_err() {
+                continue;
+            }
+            let own = &ticket.wallet == wallet;
+            let prefix_holders = by_prefix.entry(sequence.nums[..prefix_len].to_vec()).or_default();
+            prefix_holders.holders.add(own);
+            prefix_holders
+                .by_next
+                .entry(sequence.nums[prefix_len])
+                .or_default()
+                .add(own);
+        }
+    }
+    by_prefix
+}
+
+/// Exact odds of the wallet's ticket, from the winning combinations starting like its sequences. `tickets` are the
+/// tickets of the epoch with sequences sharing their first 4 numbers with the wallet's, the wallet's included.
+///
+/// Like [`super::TicketService::calculate_winners`], a sequence wins the tier of the number of leading numbers it
+/// matches: 6, 5 or 4. The tier 1 prize is split between the winning wallets and the tier 2 and 3 prizes between the
+/// winning sequences.
+pub fn calculate_odds(wallet: &Pubkey, tickets: &[Ticket], prize_pools: [FPUSDC; 3]) -> Vec<TierOdds> {
+    // Wallets holding each sequence that can win
+    let mut holders: HashMap<[u8; 6], BTreeSet<Pubkey>> = HashMap::new();
+    for ticket in tickets {
+        for sequence in &ticket.sequences {
+            if validate_sequence(&sequence.nums).is_ok() {
+                holders.entry(sequence.nums).or_default().insert(ticket.wallet);
+            }
+        }
+    }
+    let tier1 = holders
+        .values()
+        .filter(|wallets| wallets.contains(wallet))
+        .fold((0, 0.0), |(count, share), wallets| {
+            (count + 1, share + 1.0 / wallets.len() as f64)
+        });
+
+    // Each prefix of 5 numbers is followed by one of 10 last numbers
+    let tier2 = by_prefix(wallet, tickets, 5)
+        .values()
+        .map(|prefix_holders| prefix_holders.odds(1..=10))
+        .fold((0, 0.0), |(count, share), odds| (count + odds.0, share + odds.1));
+
+    // Each prefix of 4 numbers is followed by one of the 52 numbers it doesn't hold, then by one of 10 last numbers
+    let tier3 = by_prefix(wallet, tickets, 4)
+        .iter()
+        .map(|(prefix, prefix_holders)| prefix_holders.odds((1..=56).filter(|n| !prefix.contains(n))))
+        .fold((0, 0.0), |(count, share), odds| {
+            (count + odds.0 * 10, share + odds.1 * 10.0)
+        });
+
+    [tier1, tier2, tier3]
+        .into_iter()
+        .zip(prize_pools)
+        .enumerate()
+        .map(|(i, ((num_winning_combinations, share), prize_pool))| TierOdds {
+            tier: i as u8 + 1,
+            num_winning_combinations,
+            probability: num_winning_combinations as f64 / NUM_WINNING_COMBINATIONS as f64,
+            prize_pool,
+            expected_prize: FPUSDC::from_usdc(
+                (prize_pool.as_usdc() as f64 * share / NUM_WINNING_COMBINATIONS as f64).round() as u64,
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tickets::{Sequence, SequenceType};
+
+    fn ticket(wallet: Pubkey, sequences: &[[u8; 6]]) -> Ticket {
+        Ticket {
+            wallet,
+            epoch_index: 1,
+            arweave_url: None,
+            sequences: sequences
+                .iter()
+                .map(|&nums| Sequence {
+                    nums,
+                    sequence_type: SequenceType::Normal,
+                })
+                .collect(),
+            balance: "100".into(),
+            price: "25".into(),
+            risq_id: None,
+        }
+    }
+
+    #[test]
+    fn calculates_odds_of_a_single_ticket() {
+        let wallet = Pubkey::new_unique();
+        let pool = FPUSDC::from_usdc(NUM_WINNING_COMBINATIONS);
+        let odds = calculate_odds(&wallet, &[ticket(wallet, &[[1, 2, 3, 4, 5, 6]])], [pool; 3]);
+
+        let counts = odds
+            .iter()
+            .map(|tier| tier.num_winning_combinations)
+            .collect::<Vec<_>>();
+        // Matches the first 5 numbers but not the last one, or the first 4 but not the 5th one
+        assert_eq!(counts, vec![1, 9, 51 * 10]);
+        assert_eq!(odds[0].probability, 1.0 / NUM_WINNING_COMBINATIONS as f64);
+        let expected_prizes = odds.iter().map(|tier| tier.expected_prize).collect::<Vec<_>>();
+        assert_eq!(
+            expected_prizes,
+            vec![FPUSDC::from_usdc(1), FPUSDC::from_usdc(9), FPUSDC::from_usdc(510)]
+        );
+    }
+
+    #[test]
+    fn shares_prizes_with_other_holders() {
+        let wallet = Pubkey::new_unique();
+        let pool = FPUSDC::from_usdc(6 * NUM_WINNING_COMBINATIONS);
+        let tickets = [
+            ticket(wallet, &[[1, 2, 3, 4, 5, 6]]),
+            ticket(Pubkey::new_unique(), &[[1, 2, 3, 4, 5, 6], [1, 2, 3, 4, 7, 1]]),
+        ];
+        let odds = calculate_odds(&wallet, &tickets, [pool; 3]);
+
+        assert_eq!(
+            odds.iter()
+                .map(|tier| tier.num_winning_combinations)
+                .collect::<Vec<_>>(),
+            vec![1, 9, 51 * 10]
+        );
+        // Tiers 1 and 2 are split in half. Tier 3 is split in half when 7 is drawn 5th, as [1, 2, 3, 4, 7, 1] doesn't
+        // win it, and in three otherwise: 6 * (10 / 2 + 50 * 10 / 3) = 1030.
+        assert_eq!(
+            odds.iter().map(|tier| tier.expected_prize).collect::<Vec<_>>(),
+            vec![FPUSDC::from_usdc(3), FPUSDC::from_usdc(27), FPUSDC::from_usdc(1030)]
+        );
+    }
+
+    #[test]
+    fn ignores_sequences_that_cant_be_drawn() {
+        let wallet = Pubkey::new_unique();
+        let odds = calculate_odds(
+            &wallet,
+            &[ticket(wallet, &[[0, 2, 3, 4, 5, 6], [1, 1, 3, 4, 5, 6]])],
+            [FPUSDC::from_usdc(1_000_000); 3],
+        );
+        assert!(odds
+            .iter()
+            .all(|tier| tier.num_winning_combinations == 0 && tier.expected_prize == FPUSDC::zero()));
+    }
+}
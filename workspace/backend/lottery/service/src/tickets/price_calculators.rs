@@ -1,5 +1,13 @@
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
-use nezha_staking::fixed_point::FPUSDC;
+use nezha_staking::{
+    fixed_point::FPUSDC,
+    state::{EpochStatus, Returns},
+};
+use solana_program::pubkey::Pubkey;
+
+use super::TicketRepository;
+use crate::solana::Solana;
 
 #[derive(Debug, Default, Clone)]
 pub struct TicketPrice {
@@ -7,10 +15,53 @@ pub struct TicketPrice {
     pub price_per_ticket: FPUSDC,
 }
 
+/// Price tier of [`TieredTicketPriceCalculator`]. Applies to the part of the balance from `from` up to the next tier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceTier {
+    pub from: FPUSDC,
+    pub price: FPUSDC,
+}
+
+/// Parameters of the active pricing policy, so that clients can work out the number of tickets of a balance.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TicketPricingPolicy {
+    Constant {
+        price: FPUSDC,
+    },
+    Tiered {
+        tiers: Vec<PriceTier>,
+    },
+    Loyalty {
+        price: FPUSDC,
+        discount_per_draw: FPUSDC,
+        max_discount: FPUSDC,
+    },
+    YieldIndexed {
+        /// Current price.
+        price: FPUSDC,
+        target_prize_per_ticket: FPUSDC,
+        min_price: FPUSDC,
+        max_price: FPUSDC,
+    },
+}
+
 #[async_trait]
 pub trait TicketPriceCalculator: Sync + Send {
-    async fn calculate(&self, balance: FPUSDC) -> TicketPrice;
-    async fn price(&self) -> FPUSDC;
+    async fn calculate(&self, wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice>;
+    /// Price of the first ticket of a wallet without discounts.
+    async fn price(&self) -> Result<FPUSDC>;
+    async fn policy(&self) -> Result<TicketPricingPolicy>;
+}
+
+fn constant_price(balance: FPUSDC, price: FPUSDC) -> Result<TicketPrice> {
+    let sequences_count = balance
+        .checked_div(price)
+        .with_context(|| format!("Invalid ticket price {}", price))?
+        .as_whole_number();
+    Ok(TicketPrice {
+        sequences_count: sequences_count as _,
+        price_per_ticket: price,
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -26,16 +77,384 @@ impl ConstantTicketPriceCalculator {
 
 #[async_trait]
 impl TicketPriceCalculator for ConstantTicketPriceCalculator {
-    async fn calculate(&self, balance: FPUSDC) -> TicketPrice {
-        let sequences_count = balance.checked_div(self.price).unwrap().as_whole_number();
-        let price_per_ticket = self.price;
-        TicketPrice {
+    async fn calculate(&self, _wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice> {
+        constant_price(balance, self.price)
+    }
+
+    async fn price(&self) -> Result<FPUSDC> {
+        Ok(self.price)
+    }
+
+    async fn policy(&self) -> Result<TicketPricingPolicy> {
+        Ok(TicketPricingPolicy::Constant { price: self.price })
+    }
+}
+
+/// Larger balances buy their marginal tickets cheaper. The part of the balance that doesn't buy a whole ticket in a
+/// tier is spent in the next one.
+#[derive(Debug, Clone)]
+pub struct TieredTicketPriceCalculator {
+    tiers: Vec<PriceTier>,
+}
+
+impl TieredTicketPriceCalculator {
+    /// The first tier must start from 0, and the tiers must be sorted by `from`.
+    pub fn new(tiers: Vec<PriceTier>) -> Result<Self> {
+        match tiers.first() {
+            Some(tier) if tier.from == FPUSDC::zero() => {}
+            _ => bail!("The first price tier must start from 0"),
+        }
+        for (tier, next) in tiers.iter().zip(tiers.iter().skip(1)) {
+            if next.from <= tier.from {
+                bail!("Price tiers must be sorted: {} after {}", next.from, tier.from);
+            }
+        }
+        if let Some(tier) = tiers.iter().find(|tier| tier.price == FPUSDC::zero()) {
+            bail!("Price of tier from {} is 0", tier.from);
+        }
+        Ok(Self { tiers })
+    }
+}
+
+#[async_trait]
+impl TicketPriceCalculator for TieredTicketPriceCalculator {
+    async fn calculate(&self, _wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice> {
+        let overflow = || anyhow!("Overflow calculating tickets of {}", balance);
+        let mut sequences_count = 0;
+        let mut carry = FPUSDC::zero();
+        for (i, tier) in self.tiers.iter().enumerate() {
+            if balance <= tier.from {
+                break;
+            }
+            let upper = match self.tiers.get(i + 1) {
+                Some(next) if next.from < balance => next.from,
+                _ => balance,
+            };
+            let amount = upper
+                .checked_sub(tier.from)
+                .ok_or_else(overflow)?
+                .checked_add(carry)
+                .ok_or_else(overflow)?;
+            let count = amount.checked_div(tier.price).ok_or_else(overflow)?.as_whole_number();
+            carry = amount
+                .checked_sub(tier.price.checked_mul(count.into()).ok_or_else(overflow)?)
+                .ok_or_else(overflow)?;
+            sequences_count += count;
+        }
+
+        let price_per_ticket = if sequences_count == 0 {
+            self.tiers[0].price
+        } else {
+            balance
+                .checked_sub(carry)
+                .and_then(|spent| spent.checked_div(sequences_count.into()))
+                .ok_or_else(overflow)?
+        };
+        Ok(TicketPrice {
             sequences_count: sequences_count as _,
             price_per_ticket,
+        })
+    }
+
+    async fn price(&self) -> Result<FPUSDC> {
+        Ok(self.tiers[0].price)
+    }
+
+    async fn policy(&self) -> Result<TicketPricingPolicy> {
+        Ok(TicketPricingPolicy::Tiered {
+            tiers: self.tiers.clone(),
+        })
+    }
+}
+
+/// Index of the first epoch that hasn't ended. The draws played are those of the epochs before it.
+pub(crate) async fn first_open_epoch_index(solana: &dyn Solana) -> Result<u64> {
+    let latest_epoch = solana.get_latest_epoch().await?;
+    Ok(if latest_epoch.status == EpochStatus::Ended {
+        latest_epoch.index + 1
+    } else {
+        latest_epoch.index
+    })
+}
+
+/// Discounts the price by `discount_per_draw` for every draw the wallet played, up to `max_discount`. Discounts are
+/// ratios of the price, e.g. 0.01 for 1%.
+pub struct LoyaltyTicketPriceCalculator {
+    solana: Box<dyn Solana>,
+    repository: Box<dyn TicketRepository>,
+    price: FPUSDC,
+    discount_per_draw: FPUSDC,
+    max_discount: FPUSDC,
+}
+
+impl LoyaltyTicketPriceCalculator {
+    pub fn new(
+        solana: Box<dyn Solana>,
+        repository: Box<dyn TicketRepository>,
+        price: FPUSDC,
+        discount_per_draw: FPUSDC,
+        max_discount: FPUSDC,
+    ) -> Result<Self> {
+        if max_discount >= FPUSDC::from(1u8) {
+            bail!("Maximum discount {} must be less than 1", max_discount);
+        }
+        Ok(Self {
+            solana,
+            repository,
+            price,
+            discount_per_draw,
+            max_discount,
+        })
+    }
+
+    fn discounted_price(&self, draws_played: u64) -> Result<FPUSDC> {
+        let discount = self
+            .discount_per_draw
+            .checked_mul(draws_played.into())
+            .map_or(self.max_discount, |discount| discount.min(self.max_discount));
+        FPUSDC::from(1u8)
+            .checked_sub(discount)
+            .and_then(|ratio| self.price.checked_mul(ratio))
+            .with_context(|| format!("Overflow discounting {} by {}", self.price, discount))
+    }
+}
+
+#[async_trait]
+impl TicketPriceCalculator for LoyaltyTicketPriceCalculator {
+    async fn calculate(&self, wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice> {
+        let before_epoch_index = first_open_epoch_index(self.solana.as_ref()).await?;
+        let draws_played = self
+            .repository
+            .draws_played_by_wallet(wallet, before_epoch_index)
+            .await?;
+        constant_price(balance, self.discounted_price(draws_played)?)
+    }
+
+    async fn price(&self) -> Result<FPUSDC> {
+        Ok(self.price)
+    }
+
+    async fn policy(&self) -> Result<TicketPricingPolicy> {
+        Ok(TicketPricingPolicy::Loyalty {
+            price: self.price,
+            discount_per_draw: self.discount_per_draw,
+            max_discount: self.max_discount,
+        })
+    }
+}
+
+/// Prices tickets so that the tier 2 and tier 3 prizes of the previous epoch would have paid
+/// `target_prize_per_ticket` per ticket, assuming the same amount is staked. Until an epoch has returns, the initial
+/// price is used.
+pub struct YieldIndexedTicketPriceCalculator {
+    solana: Box<dyn Solana>,
+    initial_price: FPUSDC,
+    target_prize_per_ticket: FPUSDC,
+    min_price: FPUSDC,
+    max_price: FPUSDC,
+}
+
+impl YieldIndexedTicketPriceCalculator {
+    pub fn new(
+        solana: Box<dyn Solana>,
+        initial_price: FPUSDC,
+        target_prize_per_ticket: FPUSDC,
+        min_price: FPUSDC,
+        max_price: FPUSDC,
+    ) -> Result<Self> {
+        if min_price == FPUSDC::zero() || min_price > max_price {
+            bail!("Invalid ticket price range {} - {}", min_price, max_price);
         }
+        Ok(Self {
+            solana,
+            initial_price,
+            target_prize_per_ticket,
+            min_price,
+            max_price,
+        })
     }
 
-    async fn price(&self) -> FPUSDC {
-        self.price
+    async fn current_price(&self) -> Result<FPUSDC> {
+        let epochs = self.solana.get_recent_epochs(2).await?;
+        let previous = epochs
+            .iter()
+            .rev()
+            .find_map(|epoch| Some((epoch.total_invested?, epoch.returns.as_ref()?)));
+        let price = match previous {
+            Some((total_invested, returns)) => {
+                yield_indexed_price(self.target_prize_per_ticket, total_invested, returns).unwrap_or(self.max_price)
+            }
+            None => self.initial_price,
+        };
+        Ok(price.clamp(self.min_price, self.max_price))
+    }
+}
+
+/// With `total_invested / price` tickets, the prizes pay `prizes * price / total_invested` per ticket. `None` if there
+/// are no prizes to reach the target with.
+pub fn yield_indexed_price(
+    target_prize_per_ticket: FPUSDC,
+    total_invested: FPUSDC,
+    returns: &Returns,
+) -> Option<FPUSDC> {
+    let prizes = returns.tier2_prize.checked_add(returns.tier3_prize)?;
+    if prizes == FPUSDC::zero() {
+        return None;
+    }
+    target_prize_per_ticket.checked_mul(total_invested)?.checked_div(prizes)
+}
+
+#[async_trait]
+impl TicketPriceCalculator for YieldIndexedTicketPriceCalculator {
+    async fn calculate(&self, _wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice> {
+        constant_price(balance, self.current_price().await?)
+    }
+
+    async fn price(&self) -> Result<FPUSDC> {
+        self.current_price().await
+    }
+
+    async fn policy(&self) -> Result<TicketPricingPolicy> {
+        Ok(TicketPricingPolicy::YieldIndexed {
+            price: self.current_price().await?,
+            target_prize_per_ticket: self.target_prize_per_ticket,
+            min_price: self.min_price,
+            max_price: self.max_price,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        solana::mock::SolanaMock,
+        tickets::{InMemoryTicketRepository, Sequence, SequenceType, Ticket},
+    };
+
+    fn fp(s: &str) -> FPUSDC {
+        s.parse().unwrap()
+    }
+
+    fn tier(from: &str, price: &str) -> PriceTier {
+        PriceTier {
+            from: fp(from),
+            price: fp(price),
+        }
+    }
+
+    #[tokio::test]
+    async fn tiered_price() {
+        let calculator =
+            TieredTicketPriceCalculator::new(vec![tier("0", "25"), tier("100", "20"), tier("200", "10")]).unwrap();
+        let wallet = Pubkey::new_unique();
+
+        let price = calculator.calculate(&wallet, fp("99")).await.unwrap();
+        assert_eq!(price.sequences_count, 3);
+        assert_eq!(price.price_per_ticket, fp("25"));
+
+        // 4 tickets in the first tier, 5 in the second and 10 in the third
+        let price = calculator.calculate(&wallet, fp("300")).await.unwrap();
+        assert_eq!(price.sequences_count, 19);
+
+        // 4 tickets use up the first tier, the 10 above it don't buy one in the second
+        let price = calculator.calculate(&wallet, fp("110")).await.unwrap();
+        assert_eq!(price.sequences_count, 4);
+        let price = calculator.calculate(&wallet, fp("135")).await.unwrap();
+        assert_eq!(price.sequences_count, 5);
+        assert_eq!(price.price_per_ticket, fp("24"));
+
+        let price = calculator.calculate(&wallet, fp("0")).await.unwrap();
+        assert_eq!(price.sequences_count, 0);
+        assert_eq!(price.price_per_ticket, fp("25"));
+
+        assert!(TieredTicketPriceCalculator::new(vec![tier("10", "25")]).is_err());
+        assert!(TieredTicketPriceCalculator::new(vec![tier("0", "25"), tier("0", "20")]).is_err());
+    }
+
+    #[tokio::test]
+    async fn loyalty_price() {
+        let repository = InMemoryTicketRepository::new(0);
+        let wallet = Pubkey::new_unique();
+        for epoch_index in 1..=3 {
+            repository
+                .create(&Ticket {
+                    wallet,
+                    epoch_index,
+                    sequences: vec![Sequence {
+                        nums: [1, 2, 3, 4, 5, epoch_index as u8],
+                        sequence_type: SequenceType::Normal,
+                    }],
+                    ..Ticket::new_for_tests()
+                })
+                .await
+                .unwrap();
+        }
+        let calculator = |epoch_index, epoch_status| {
+            let mut solana = SolanaMock::new();
+            solana.epoch_index = epoch_index;
+            solana.epoch_status = epoch_status;
+            LoyaltyTicketPriceCalculator::new(
+                Box::new(solana),
+                Box::new(repository.clone()),
+                fp("20"),
+                fp("0.05"),
+                fp("0.25"),
+            )
+            .unwrap()
+        };
+
+        let price = calculator(4, EpochStatus::Running)
+            .calculate(&Pubkey::new_unique(), fp("100"))
+            .await
+            .unwrap();
+        assert_eq!(price.sequences_count, 5);
+        assert_eq!(price.price_per_ticket, fp("20"));
+
+        // 3 draws played, 15% off
+        let price = calculator(4, EpochStatus::Running)
+            .calculate(&wallet, fp("100"))
+            .await
+            .unwrap();
+        assert_eq!(price.sequences_count, 5);
+        assert_eq!(price.price_per_ticket, fp("17"));
+
+        // The draw of a running epoch isn't played yet, that of an ended one is
+        let price = calculator(3, EpochStatus::Yielding)
+            .calculate(&wallet, fp("100"))
+            .await
+            .unwrap();
+        assert_eq!(price.price_per_ticket, fp("18"));
+        let price = calculator(3, EpochStatus::Ended)
+            .calculate(&wallet, fp("100"))
+            .await
+            .unwrap();
+        assert_eq!(price.price_per_ticket, fp("17"));
+
+        let calculator = calculator(4, EpochStatus::Running);
+
+        assert_eq!(calculator.discounted_price(100).unwrap(), fp("15"));
+    }
+
+    #[test]
+    fn yield_indexed() {
+        let returns = Returns {
+            total: fp("10100"),
+            deposit_back: fp("10000"),
+            insurance: fp("10"),
+            treasury: fp("40"),
+            tier2_prize: fp("30"),
+            tier3_prize: fp("20"),
+        };
+        // 10000 staked at 20 per ticket is 500 tickets, sharing 50 in prizes is 0.1 per ticket
+        assert_eq!(yield_indexed_price(fp("0.1"), fp("10000"), &returns), Some(fp("20")));
+        assert_eq!(yield_indexed_price(fp("0.2"), fp("10000"), &returns), Some(fp("40")));
+
+        let no_prizes = Returns {
+            tier2_prize: FPUSDC::zero(),
+            tier3_prize: FPUSDC::zero(),
+            ..returns
+        };
+        assert_eq!(yield_indexed_price(fp("0.1"), fp("10000"), &no_prizes), None);
     }
 }
@@ -1,41 +1,67 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use solana_program::pubkey::Pubkey;
-use std::{collections::HashSet, sync::RwLock};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::{Arc, RwLock},
+};
 
-use super::{Sequence, Ticket, TicketsWithCount, WalletRisqId};
-use crate::model::ticket::{TicketError, SEQUENCE_LENGTH};
+use super::{common_prefix_len, Sequence, SequenceType, Ticket, TicketsWithCount, WalletRisqId};
+use crate::model::ticket::{PrefixMatches, PrefixStats, TicketError, SEQUENCE_LENGTH};
 
 #[async_trait]
 pub trait TicketRepository: Sync + Send {
     async fn by_wallet_and_epoch_index(&self, wallet: &Pubkey, index: u64) -> Result<Option<Ticket>>;
     async fn by_wallets_and_epoch_index(&self, wallets: &[Pubkey], index: u64) -> Result<Vec<Ticket>>;
+    async fn by_epoch_index(&self, index: u64) -> Result<Vec<Ticket>>;
     async fn by_epoch_index_and_prefix(&self, index: u64, limit: Option<u8>, prefix: &[u8])
         -> Result<TicketsWithCount>;
     async fn all(&self) -> Result<Vec<Ticket>>;
     async fn distinct_sequences_by_epoch_index(&self, index: u64) -> Result<Vec<[u8; 6]>>;
     async fn random_sequence_by_epoch_index(&self, index: u64) -> Result<Option<[u8; 6]>>;
+    /// The prefix of `prefix_len` numbers shared by the most distinct sequences of the epoch, the smallest prefix on
+    /// ties.
+    async fn most_common_prefix_by_epoch_index(&self, index: u64, prefix_len: usize) -> Result<Option<PrefixStats>>;
+    /// Sequences of the epoch matching at least the first `min_prefix_len` numbers of the combination, counted by
+    /// wallet and matched prefix length.
+    async fn prefix_matches_by_epoch_index(
+        &self,
+        index: u64,
+        combination: &[u8; 6],
+        min_prefix_len: usize,
+    ) -> Result<Vec<PrefixMatches>>;
     async fn create(&self, ticket: &Ticket) -> Result<Ticket>;
     async fn add_sequences(&self, wallet: &Pubkey, index: u64, sequences: &[Sequence]) -> Result<Ticket>;
+    /// Atomically removes the ticket's sequences of the given types and adds `sequences` in their place.
+    async fn replace_sequences(
+        &self,
+        wallet: &Pubkey,
+        index: u64,
+        sequence_types: &[SequenceType],
+        sequences: &[Sequence],
+    ) -> Result<Ticket>;
     async fn update_arweave_url(&self, wallet: &Pubkey, index: u64, arweave_url: String) -> Result<Option<()>>;
+    async fn update_balance(&self, wallet: &Pubkey, index: u64, balance: String, price: String) -> Result<Option<()>>;
     async fn get_unsubmitted_tickets_in_epoch(&self, epoch_index: u64) -> Result<Vec<Ticket>>;
     async fn update_risq_ids(&self, epoch_index: u64, risq_ids: &[WalletRisqId]) -> Result<Vec<Ticket>>;
     async fn num_sequences_by_epoch_index(&self, epoch_index: u64) -> Result<u64>;
     async fn num_airdrop_sequences_by_wallet_and_epoch_index(&self, wallet: &Pubkey, epoch_index: u64) -> Result<u32>;
     async fn prior_sequences_exist_by_wallet(&self, wallet: &Pubkey) -> Result<bool>;
-    async fn draws_played_by_wallet(&self, wallet: &Pubkey) -> Result<u64>;
+    /// Number of epochs before `before_epoch_index` the wallet had sequences in.
+    async fn draws_played_by_wallet(&self, wallet: &Pubkey, before_epoch_index: u64) -> Result<u64>;
 }
 
 /// In memory implementation of TicketRepository for testing.
+#[derive(Clone)]
 pub struct InMemoryTicketRepository {
-    mem: RwLock<Vec<Ticket>>,
+    mem: Arc<RwLock<Vec<Ticket>>>,
     num_airdrop_sequences: u32,
 }
 
 impl InMemoryTicketRepository {
     pub fn new(num_airdrop_sequences: u32) -> Self {
         Self {
-            mem: RwLock::new(vec![]),
+            mem: Arc::new(RwLock::new(vec![])),
             num_airdrop_sequences,
         }
     }
@@ -68,6 +94,17 @@ impl TicketRepository for InMemoryTicketRepository {
             .collect())
     }
 
+    async fn by_epoch_index(&self, index: u64) -> Result<Vec<Ticket>> {
+        Ok(self
+            .mem
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|v| v.epoch_index == index)
+            .map(|v| v.clone())
+            .collect())
+    }
+
     async fn by_epoch_index_and_prefix(
         &self,
         index: u64,
@@ -133,6 +170,63 @@ impl TicketRepository for InMemoryTicketRepository {
             .next())
     }
 
+    async fn most_common_prefix_by_epoch_index(&self, index: u64, prefix_len: usize) -> Result<Option<PrefixStats>> {
+        if prefix_len > SEQUENCE_LENGTH {
+            return Err(TicketError::PrefixLengthExceeded(prefix_len).into());
+        } else if prefix_len == 0 {
+            return Err(TicketError::EmptyPrefix.into());
+        }
+        let mut sequences = BTreeSet::new();
+        for ticket in self.mem.read().unwrap().iter().filter(|v| v.epoch_index == index) {
+            for sequence in &ticket.sequences {
+                sequences.insert(sequence.nums);
+            }
+        }
+        let mut prefixes: BTreeMap<&[u8], PrefixStats> = BTreeMap::new();
+        for nums in &sequences {
+            prefixes
+                .entry(&nums[..prefix_len])
+                .or_insert_with(|| PrefixStats {
+                    prefix: nums[..prefix_len].to_vec(),
+                    num_sequences: 0,
+                    sequence: *nums,
+                })
+                .num_sequences += 1;
+        }
+        // max_by_key returns the last maximum, so iterating in reverse keeps the smallest prefix on ties
+        Ok(prefixes.into_values().rev().max_by_key(|stats| stats.num_sequences))
+    }
+
+    async fn prefix_matches_by_epoch_index(
+        &self,
+        index: u64,
+        combination: &[u8; 6],
+        min_prefix_len: usize,
+    ) -> Result<Vec<PrefixMatches>> {
+        if min_prefix_len > SEQUENCE_LENGTH {
+            return Err(TicketError::PrefixLengthExceeded(min_prefix_len).into());
+        } else if min_prefix_len == 0 {
+            return Err(TicketError::EmptyPrefix.into());
+        }
+        let mut matches: BTreeMap<(Pubkey, usize), u64> = BTreeMap::new();
+        for ticket in self.mem.read().unwrap().iter().filter(|v| v.epoch_index == index) {
+            for sequence in &ticket.sequences {
+                let prefix_len = common_prefix_len(&sequence.nums, combination);
+                if prefix_len >= min_prefix_len {
+                    *matches.entry((ticket.wallet, prefix_len)).or_insert(0) += 1;
+                }
+            }
+        }
+        Ok(matches
+            .into_iter()
+            .map(|((wallet, prefix_len), num_sequences)| PrefixMatches {
+                wallet,
+                prefix_len,
+                num_sequences,
+            })
+            .collect())
+    }
+
     async fn create(&self, ticket: &Ticket) -> Result<Ticket> {
         self.mem.write().unwrap().push(ticket.clone());
         Ok(ticket.clone())
@@ -148,6 +242,24 @@ impl TicketRepository for InMemoryTicketRepository {
         anyhow::bail!("Ticket not found")
     }
 
+    async fn replace_sequences(
+        &self,
+        wallet: &Pubkey,
+        epoch_index: u64,
+        sequence_types: &[SequenceType],
+        sequences: &[Sequence],
+    ) -> Result<Ticket> {
+        for t in &mut *self.mem.write().unwrap() {
+            if t.wallet == *wallet && t.epoch_index == epoch_index {
+                t.sequences
+                    .retain(|sequence| !sequence_types.contains(&sequence.sequence_type));
+                t.sequences.extend_from_slice(&sequences);
+                return Ok(t.clone());
+            }
+        }
+        anyhow::bail!("Ticket not found")
+    }
+
     async fn update_arweave_url(&self, wallet: &Pubkey, index: u64, arweave_url: String) -> Result<Option<()>> {
         Ok(self
             .mem
@@ -158,6 +270,19 @@ impl TicketRepository for InMemoryTicketRepository {
             .map(|v| v.arweave_url = Some(arweave_url)))
     }
 
+    async fn update_balance(&self, wallet: &Pubkey, index: u64, balance: String, price: String) -> Result<Option<()>> {
+        Ok(self
+            .mem
+            .write()
+            .unwrap()
+            .iter_mut()
+            .find(|v| &v.wallet == wallet && v.epoch_index == index)
+            .map(|v| {
+                v.balance = balance;
+                v.price = price;
+            }))
+    }
+
     async fn get_unsubmitted_tickets_in_epoch(&self, epoch_index: u64) -> Result<Vec<Ticket>> {
         Ok(self
             .mem
@@ -214,13 +339,13 @@ impl TicketRepository for InMemoryTicketRepository {
             .any(|v| v.wallet == *wallet && !v.sequences.is_empty()))
     }
 
-    async fn draws_played_by_wallet(&self, wallet: &Pubkey) -> Result<u64> {
+    async fn draws_played_by_wallet(&self, wallet: &Pubkey, before_epoch_index: u64) -> Result<u64> {
         Ok(self
             .mem
             .read()
             .unwrap()
             .iter()
-            .filter(|v| v.wallet == *wallet)
+            .filter(|v| v.wallet == *wallet && v.epoch_index < before_epoch_index)
             .filter(|v| !v.sequences.is_empty())
             .count() as u64)
     }
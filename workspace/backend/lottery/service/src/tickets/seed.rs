@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use nezha_staking::fixed_point::FPUSDC;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use solana_program::{
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use super::{generate_sequences_with_type, Sequence, SequenceType, Ticket};
+
+/// Sequence types derived from the ticket seed, in the order they are derived. User picked sequences are chosen by the
+/// user and aren't derived.
+pub const DERIVED_SEQUENCE_TYPES: [SequenceType; 4] = [
+    SequenceType::Normal,
+    SequenceType::AirdropBonus,
+    SequenceType::SignUpBonus,
+    SequenceType::CampaignBonus,
+];
+
+/// Most recent slot hash when the epoch was created, which the program records in the epoch account. It's public and
+/// wasn't known before the epoch existed, so sequences derived from it can't be biased by the server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochSeed {
+    pub epoch_index: u64,
+    pub epoch_pubkey: Pubkey,
+    pub slot: u64,
+    pub slot_hash: Hash,
+}
+
+#[async_trait]
+pub trait EpochSeedRepository: Sync + Send {
+    async fn by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochSeed>>;
+    /// Stores the seed unless the epoch has one already. Returns the stored seed.
+    async fn create(&self, seed: &EpochSeed) -> Result<EpochSeed>;
+}
+
+/// Public inputs the sequences of a wallet's ticket are derived from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TicketSeed {
+    pub epoch_pubkey: Pubkey,
+    pub wallet: Pubkey,
+    pub stake_amount: FPUSDC,
+    pub slot_hash: Hash,
+}
+
+impl TicketSeed {
+    pub fn new(epoch_seed: &EpochSeed, wallet: Pubkey, stake_amount: FPUSDC) -> Self {
+        Self {
+            epoch_pubkey: epoch_seed.epoch_pubkey,
+            wallet,
+            stake_amount,
+            slot_hash: epoch_seed.slot_hash,
+        }
+    }
+
+    /// SHA-256 of the epoch pubkey, the wallet, the stake amount in USDC base units (u64 little endian), the slot hash
+    /// and the name of the sequence type.
+    pub fn hash(&self, sequence_type: SequenceType) -> Hash {
+        hashv(&[
+            self.epoch_pubkey.as_ref(),
+            self.wallet.as_ref(),
+            &self.stake_amount.as_usdc().to_le_bytes(),
+            self.slot_hash.as_ref(),
+            sequence_type.to_string().as_bytes(),
+        ])
+    }
+
+    /// The sequences of each type are drawn from their own RNG, so that the number of sequences of one type doesn't
+    /// change the sequences of another.
+    pub fn rng(&self, sequence_type: SequenceType) -> ChaChaRng {
+        ChaChaRng::from_seed(self.hash(sequence_type).to_bytes())
+    }
+}
+
+/// A ticket together with its sequences reproduced from public inputs.
+#[derive(Debug, Clone)]
+pub struct TicketVerification {
+    pub ticket: Ticket,
+    pub epoch_seed: EpochSeed,
+    /// The derived sequences reproduced by [`reproduce_ticket`].
+    pub sequences: Vec<Sequence>,
+    /// Whether they are the derived sequences of the ticket.
+    pub verified: bool,
+}
+
+/// Number of sequences of each derived type on a ticket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DerivedCounts {
+    pub normal: u32,
+    pub airdrop_bonus: u32,
+    pub sign_up_bonus: u32,
+    pub campaign_bonus: u32,
+}
+
+impl DerivedCounts {
+    pub fn of(sequences: &[Sequence]) -> Self {
+        let mut counts = Self::default();
+        for sequence in sequences {
+            match sequence.sequence_type {
+                SequenceType::Normal => counts.normal += 1,
+                SequenceType::AirdropBonus => counts.airdrop_bonus += 1,
+                SequenceType::SignUpBonus => counts.sign_up_bonus += 1,
+                SequenceType::CampaignBonus => counts.campaign_bonus += 1,
+                SequenceType::UserPicked => {}
+            }
+        }
+        counts
+    }
+
+    pub fn get(&self, sequence_type: SequenceType) -> u32 {
+        match sequence_type {
+            SequenceType::Normal => self.normal,
+            SequenceType::AirdropBonus => self.airdrop_bonus,
+            SequenceType::SignUpBonus => self.sign_up_bonus,
+            SequenceType::CampaignBonus => self.campaign_bonus,
+            SequenceType::UserPicked => 0,
+        }
+    }
+}
+
+/// Derives the sequences of a ticket from its seed. The types are derived in [`DERIVED_SEQUENCE_TYPES`] order and
+/// never repeat the user's picks or a sequence derived before them.
+pub fn derive_sequences(seed: &TicketSeed, picks: &[Sequence], counts: DerivedCounts) -> Result<Vec<Sequence>> {
+    let mut prior = picks.iter().map(|sequence| sequence.nums).collect::<HashSet<_>>();
+    let mut sequences = Vec::new();
+    for sequence_type in DERIVED_SEQUENCE_TYPES {
+        let rng = Mutex::new(seed.rng(sequence_type));
+        let derived = generate_sequences_with_type(&rng, Some(&prior), counts.get(sequence_type), sequence_type)?;
+        prior.extend(derived.iter().map(|sequence| sequence.nums));
+        sequences.extend(derived);
+    }
+    Ok(sequences)
+}
+
+/// Reproduces the derived sequences of a ticket from public inputs: the epoch seed, the wallet, the staked amount the
+/// ticket was generated for, the user's picks and the number of sequences of each type on the ticket.
+pub fn reproduce_ticket(ticket: &Ticket, epoch_seed: &EpochSeed) -> Result<Vec<Sequence>> {
+    let stake_amount = ticket
+        .balance
+        .parse::<FPUSDC>()
+        .map_err(|e| anyhow::anyhow!(e))
+        .with_context(|| format!("Invalid ticket balance {}", ticket.balance))?;
+    let seed = TicketSeed::new(epoch_seed, ticket.wallet, stake_amount);
+    let picks = ticket
+        .sequences
+        .iter()
+        .filter(|sequence| sequence.sequence_type == SequenceType::UserPicked)
+        .cloned()
+        .collect::<Vec<_>>();
+    derive_sequences(&seed, &picks, DerivedCounts::of(&ticket.sequences))
+}
+
+/// Whether the derived sequences of the ticket are the ones reproduced from public inputs.
+pub fn verify_ticket(ticket: &Ticket, epoch_seed: &EpochSeed) -> Result<bool> {
+    if ticket.epoch_index != epoch_seed.epoch_index {
+        return Ok(false);
+    }
+    let mut expected = reproduce_ticket(ticket, epoch_seed)?;
+    let mut actual = ticket
+        .sequences
+        .iter()
+        .filter(|sequence| sequence.sequence_type != SequenceType::UserPicked)
+        .cloned()
+        .collect::<Vec<_>>();
+    expected.sort();
+    actual.sort();
+    Ok(expected == actual)
+}
+
+/// In memory implementation of EpochSeedRepository for testing.
+#[derive(Clone, Default)]
+pub struct InMemoryEpochSeedRepository {
+    mem: Arc<RwLock<HashMap<u64, EpochSeed>>>,
+}
+
+#[async_trait]
+impl EpochSeedRepository for InMemoryEpochSeedRepository {
+    async fn by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochSeed>> {
+        Ok(self.mem.read().unwrap().get(&epoch_index).cloned())
+    }
+
+    async fn create(&self, seed: &EpochSeed) -> Result<EpochSeed> {
+        Ok(self
+            .mem
+            .write()
+            .unwrap()
+            .entry(seed.epoch_index)
+            .or_insert_with(|| seed.clone())
+            .clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epoch_seed() -> EpochSeed {
+        EpochSeed {
+            epoch_index: 1,
+            epoch_pubkey: Pubkey::new_unique(),
+            slot: 42,
+            slot_hash: Hash::new_unique(),
+        }
+    }
+
+    fn ticket(epoch_seed: &EpochSeed, picks: &[Sequence], counts: DerivedCounts) -> Ticket {
+        let wallet = Pubkey::new_unique();
+        let stake_amount: FPUSDC = "20.0".parse().unwrap();
+        let seed = TicketSeed::new(epoch_seed, wallet, stake_amount);
+        let mut sequences = picks.to_vec();
+        sequences.extend(derive_sequences(&seed, picks, counts).unwrap());
+        Ticket {
+            wallet,
+            epoch_index: epoch_seed.epoch_index,
+            sequences,
+            balance: stake_amount.to_string(),
+            ..Ticket::new_for_tests()
+        }
+    }
+
+    #[test]
+    fn derivation_is_deterministic() {
+        let epoch_seed = epoch_seed();
+        let seed = TicketSeed::new(&epoch_seed, Pubkey::new_unique(), "20.0".parse().unwrap());
+        let counts = DerivedCounts {
+            normal: 5,
+            airdrop_bonus: 2,
+            sign_up_bonus: 1,
+            campaign_bonus: 2,
+        };
+        let mut a = derive_sequences(&seed, &[], counts).unwrap();
+        let mut b = derive_sequences(&seed, &[], counts).unwrap();
+        a.sort();
+        b.sort();
+        assert_eq!(a, b);
+        assert_eq!(DerivedCounts::of(&a), counts);
+        assert_eq!(a.iter().map(|s| s.nums).collect::<HashSet<_>>().len(), 10);
+
+        let other = TicketSeed {
+            stake_amount: "24.0".parse().unwrap(),
+            ..seed
+        };
+        let mut c = derive_sequences(&other, &[], counts).unwrap();
+        c.sort();
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn verifies_ticket() {
+        let epoch_seed = epoch_seed();
+        let picks = vec![Sequence {
+            nums: [1, 2, 3, 4, 5, 6],
+            sequence_type: SequenceType::UserPicked,
+        }];
+        let counts = DerivedCounts {
+            normal: 4,
+            airdrop_bonus: 1,
+            sign_up_bonus: 1,
+            campaign_bonus: 1,
+        };
+        let mut ticket = ticket(&epoch_seed, &picks, counts);
+        assert!(verify_ticket(&ticket, &epoch_seed).unwrap());
+
+        let other_seed = EpochSeed {
+            slot_hash: Hash::new_unique(),
+            ..epoch_seed.clone()
+        };
+        assert!(!verify_ticket(&ticket, &other_seed).unwrap());
+
+        ticket.sequences.last_mut().unwrap().nums = [7, 8, 9, 10, 11, 1];
+        assert!(!verify_ticket(&ticket, &epoch_seed).unwrap());
+    }
+}
@@ -4,11 +4,15 @@ use anyhow::{anyhow, Result};
 use rand::{prelude::SliceRandom, Rng};
 use serde::{Deserialize, Serialize};
 
+use crate::model::ticket::{TicketError, SEQUENCE_LENGTH};
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum SequenceType {
     Normal,
     SignUpBonus,
     AirdropBonus,
+    UserPicked,
+    CampaignBonus,
 }
 
 impl Display for SequenceType {
@@ -17,6 +21,8 @@ impl Display for SequenceType {
             SequenceType::Normal => write!(f, "Normal"),
             SequenceType::SignUpBonus => write!(f, "SignUpBonus"),
             SequenceType::AirdropBonus => write!(f, "AirdropBonus"),
+            SequenceType::UserPicked => write!(f, "UserPicked"),
+            SequenceType::CampaignBonus => write!(f, "CampaignBonus"),
         }
     }
 }
@@ -29,6 +35,8 @@ impl FromStr for SequenceType {
             "Normal" => Ok(SequenceType::Normal),
             "SignUpBonus" => Ok(SequenceType::SignUpBonus),
             "AirdropBonus" => Ok(SequenceType::AirdropBonus),
+            "UserPicked" => Ok(SequenceType::UserPicked),
+            "CampaignBonus" => Ok(SequenceType::CampaignBonus),
             _ => Err(anyhow!("Invalid sequence type")),
         }
     }
@@ -110,8 +118,97 @@ pub fn generate_sequences_with_type<R: Rng>(
     Ok(sequences)
 }
 
+/// Checks that a sequence could have been produced by `generate_sequences`: 5 distinct numbers in 1..=56 followed by
+/// 1 number in 1..=10.
+pub fn validate_sequence(nums: &[u8; 6]) -> Result<(), TicketError> {
+    let main = &nums[..5];
+    let distinct = main.iter().collect::<HashSet<_>>().len() == main.len();
+    if distinct && main.iter().all(|n| (1..=56).contains(n)) && (1..=10).contains(&nums[5]) {
+        Ok(())
+    } else {
+        Err(TicketError::InvalidSequence(*nums))
+    }
+}
+
+/// Packs a sequence into the low 48 bits of a u64, one byte per number with the first number in the most significant
+/// byte. Packed sequences order like the sequences, so the sequences sharing a prefix form a contiguous range.
+pub fn pack_sequence(nums: &[u8; SEQUENCE_LENGTH]) -> u64 {
+    nums.iter().fold(0, |packed, &n| packed << 8 | n as u64)
+}
+
+pub fn unpack_sequence(packed: u64) -> [u8; SEQUENCE_LENGTH] {
+    let mut nums = [0; SEQUENCE_LENGTH];
+    for (i, n) in nums.iter_mut().enumerate() {
+        *n = (packed >> (8 * (SEQUENCE_LENGTH - 1 - i))) as u8;
+    }
+    nums
+}
+
+/// Inclusive range of the packed sequences starting with `prefix`.
+pub fn packed_prefix_range(prefix: &[u8]) -> Result<(u64, u64), TicketError> {
+    let prefix_len = prefix.len();
+    if prefix_len > SEQUENCE_LENGTH {
+        return Err(TicketError::PrefixLengthExceeded(prefix_len));
+    } else if prefix_len == 0 {
+        return Err(TicketError::EmptyPrefix);
+    }
+    let mut nums = [0; SEQUENCE_LENGTH];
+    nums[..prefix_len].copy_from_slice(prefix);
+    let start = pack_sequence(&nums);
+    let rest_bits = 8 * (SEQUENCE_LENGTH - prefix_len);
+    Ok((start, start | ((1u64 << rest_bits) - 1)))
+}
+
+/// Number of leading numbers the sequences have in common.
+pub fn common_prefix_len(a: &[u8; SEQUENCE_LENGTH], b: &[u8; SEQUENCE_LENGTH]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
 #[cfg(test)]
 mod tests {
+    mod sequence_pack {
+        use super::super::{common_prefix_len, pack_sequence, packed_prefix_range, unpack_sequence};
+
+        #[test]
+        fn works() {
+            let nums = [1, 2, 3, 4, 56, 10];
+            assert_eq!(pack_sequence(&nums), 0x01_02_03_04_38_0a);
+            assert_eq!(unpack_sequence(pack_sequence(&nums)), nums);
+            assert_eq!(unpack_sequence(pack_sequence(&[255; 6])), [255; 6]);
+            assert!(pack_sequence(&[1, 2, 3, 4, 5, 6]) < pack_sequence(&[1, 2, 3, 5, 0, 0]));
+        }
+
+        #[test]
+        fn prefix_range() {
+            let (start, end) = packed_prefix_range(&[1, 2, 3, 4]).unwrap();
+            assert_eq!(start, pack_sequence(&[1, 2, 3, 4, 0, 0]));
+            assert_eq!(end, pack_sequence(&[1, 2, 3, 4, 255, 255]));
+            let (start, end) = packed_prefix_range(&[7, 8, 9, 10, 11, 12]).unwrap();
+            assert_eq!(start, end);
+            assert!(packed_prefix_range(&[]).is_err());
+            assert!(packed_prefix_range(&[1; 7]).is_err());
+            assert_eq!(common_prefix_len(&[1, 2, 3, 4, 5, 6], &[1, 2, 3, 9, 5, 6]), 3);
+        }
+    }
+
+    mod sequence_validate {
+        use super::super::validate_sequence;
+
+        #[test]
+        fn works() {
+            assert!(validate_sequence(&[1, 2, 3, 4, 56, 10]).is_ok());
+            assert!(validate_sequence(&[56, 40, 14, 1, 12, 1]).is_ok());
+            // out of range
+            assert!(validate_sequence(&[0, 2, 3, 4, 5, 6]).is_err());
+            assert!(validate_sequence(&[1, 2, 3, 4, 57, 6]).is_err());
+            assert!(validate_sequence(&[1, 2, 3, 4, 5, 0]).is_err());
+            assert!(validate_sequence(&[1, 2, 3, 4, 5, 11]).is_err());
+            // duplicated main number, the last number may repeat one of them
+            assert!(validate_sequence(&[1, 2, 3, 4, 4, 6]).is_err());
+            assert!(validate_sequence(&[1, 2, 3, 4, 5, 5]).is_ok());
+        }
+    }
+
     mod sequences_generate {
         use super::super::generate_sequences;
         use rand::{prelude::StdRng, RngCore, SeedableRng};
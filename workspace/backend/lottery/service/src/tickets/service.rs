@@ -3,7 +3,9 @@ use async_trait::async_trait;
 use nezha_staking::fixed_point::FPUSDC;
 use solana_program::pubkey::Pubkey;
 
-use super::{Ticket, TicketsWithCount, WalletRisqId, Winners};
+use super::{
+    Ticket, TicketOdds, TicketPrice, TicketPricingPolicy, TicketVerification, TicketsWithCount, WalletRisqId, Winners,
+};
 use crate::solana::Stake;
 
 #[async_trait]
@@ -18,13 +20,25 @@ pub trait TicketService: Sync + Send {
     async fn generate_ticket_for_stake(&self, stake: &Stake, epoch_index: Option<u64>) -> Result<Ticket>;
     async fn generate_ticket_for_wallet(&self, wallet: &Pubkey, epoch_index: Option<u64>) -> Result<Ticket>;
     async fn generate_tickets_for_all(&self) -> Result<Vec<Result<Ticket>>>;
+    /// Sizes the tickets of the running epoch for the balances held up to now. Run before the tickets are committed
+    /// when the epoch enters investment, does nothing once the epoch isn't running.
+    async fn reconcile_tickets(&self) -> Result<Vec<Result<Ticket>>>;
+    /// Replaces the generated sequences of the wallet's upcoming ticket with the user's own picks, up to the number of
+    /// sequences the stake entitles it to. The remainder is generated as usual, bonus sequences are kept.
+    async fn submit_user_picks(&self, wallet: &Pubkey, picks: &[[u8; 6]]) -> Result<Ticket>;
+    /// Reproduces the wallet's ticket from public inputs. Only available when tickets are generated deterministically.
+    async fn verify_ticket(&self, wallet: &Pubkey, epoch_index: u64) -> Result<Option<TicketVerification>>;
+    /// Chances of the wallet's ticket in the draw of the latest epoch, and the prize it can expect. Returns `None` if
+    /// the wallet has no ticket in the epoch.
+    async fn ticket_odds(&self, wallet: &Pubkey) -> Result<Option<TicketOdds>>;
     async fn update_arweave_url(&self, wallet: &Pubkey, index: u64, arweave_url: String) -> Result<Option<Ticket>>;
     async fn get_unsubmitted_tickets_in_epoch(&self, epoch_index: u64) -> Result<Vec<Ticket>>;
     async fn update_risq_ids(&self, epoch_index: u64, risq_ids: &[WalletRisqId]) -> Result<Vec<Ticket>>;
     async fn calculate_winners(&self) -> Result<Winners>;
-    async fn calculate_optimal_winning_combination(&self) -> Result<Option<[u8; 6]>>;
-    async fn random_winning_combination(&self) -> Result<Option<[u8; 6]>>;
     async fn ticket_price(&self) -> Result<FPUSDC>;
+    async fn ticket_pricing_policy(&self) -> Result<TicketPricingPolicy>;
+    /// Number of sequences a staked balance of the wallet is entitled to, and their price.
+    async fn calculate_ticket_price(&self, wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice>;
     async fn draws_played_by_wallet(&self, wallet: &Pubkey) -> Result<u64>;
     // Bonus info
     async fn num_signup_bonus_sequences(&self, wallet: &Pubkey, amount: FPUSDC) -> Result<u32>;
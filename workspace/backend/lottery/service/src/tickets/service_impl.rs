@@ -1,20 +1,28 @@
 use crate::{
+    campaign::{CampaignService, CampaignWallet},
+    events::{Event, EventBus},
     model::{
-        epoch::{EpochError, EpochStatus},
-        ticket::TicketsWithCount,
+        epoch::{Epoch, EpochError, EpochStatus},
+        ticket::{PrefixMatches, TicketError, TicketsWithCount},
     },
     solana::{Solana, Stake},
-    tickets::generate_sequences_with_type,
+    tickets::{
+        calculate_odds, derive_sequences, first_open_epoch_index, generate_sequences_with_type, reproduce_ticket,
+        time_weighted_balance, validate_sequence, verify_ticket, DerivedCounts, EpochSeed, EpochSeedRepository,
+        TicketOdds, TicketPrice, TicketPricingPolicy, TicketSeed, TicketVerification, DERIVED_SEQUENCE_TYPES,
+    },
+    transaction::UserTransactionRepository,
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use nezha_staking::fixed_point::FPUSDC;
+use chrono::{DateTime, Utc};
+use nezha_staking::{fixed_point::FPUSDC, state::AccountType};
 use nezha_vrf_lib::state::NezhaVrfRequestStatus;
 use rand::Rng;
-use solana_program::pubkey::Pubkey;
+use solana_program::{hash::Hash, pubkey::Pubkey};
 use std::{
     cmp::{self, max},
-    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     sync::{Arc, Mutex},
 };
 
@@ -23,21 +31,46 @@ use super::{
     WalletRisqId, Winners,
 };
 
+/// How the sequences of tickets are generated.
+pub enum TicketGeneration {
+    /// Drawn from the service's RNG.
+    Random,
+    /// Derived from public inputs with [`derive_sequences`], so that anyone can reproduce them.
+    Deterministic(Box<dyn EpochSeedRepository>),
+}
+
+/// Which balance tickets are sized for.
+pub enum TicketAllocation {
+    /// The stake balance when the ticket is generated.
+    Snapshot,
+    /// The stake balance averaged over the epoch with [`time_weighted_balance`], from the indexed deposits and
+    /// withdrawals. Until the epoch enters investment, the current balance is assumed to be held to its expected end.
+    TimeWeighted(Box<dyn UserTransactionRepository>),
+}
+
 pub struct DefaultTicketService<R: Rng> {
     rng: Arc<Mutex<R>>,
     solana: Box<dyn Solana>,
     repository: Box<dyn TicketRepository>,
     calculator: Box<dyn TicketPriceCalculator>,
     bonus_info_service: Box<dyn BonusInfoService>,
+    events: EventBus,
+    generation: TicketGeneration,
+    allocation: TicketAllocation,
+    campaigns: Box<dyn CampaignService>,
 }
 
-impl<R: Rng> DefaultTicketService<R> {
+impl<R: Rng + Sync + Send> DefaultTicketService<R> {
     pub fn new(
         rng: Arc<Mutex<R>>,
         solana: Box<dyn Solana>,
         repository: Box<dyn TicketRepository>,
         calculator: Box<dyn TicketPriceCalculator>,
         bonus_info_service: Box<dyn BonusInfoService>,
+        events: EventBus,
+        generation: TicketGeneration,
+        allocation: TicketAllocation,
+        campaigns: Box<dyn CampaignService>,
     ) -> Self {
         Self {
             rng,
@@ -45,29 +78,114 @@ impl<R: Rng> DefaultTicketService<R> {
             repository,
             calculator,
             bonus_info_service,
+            events,
+            generation,
+            allocation,
+            campaigns,
         }
     }
-}
 
-#[async_trait]
-impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
-    async fn read_ticket_by_wallet_and_epoch_index(&self, wallet: &Pubkey, index: u64) -> Result<Option<Ticket>> {
-        self.repository.by_wallet_and_epoch_index(wallet, index).await
+    /// Balance the ticket of the stake is sized for. Time weighted balances are measured up to `until`, or projected
+    /// to the expected end of the epoch, and never past it.
+    ///
+    /// When measured up to `until`, fails if the stake changed during the epoch but none of the changes are indexed
+    /// yet, as the current balance would otherwise count as held since the epoch started.
+    async fn allocated_balance(&self, stake: &Stake, epoch_index: u64, until: Option<DateTime<Utc>>) -> Result<FPUSDC> {
+        let balance: FPUSDC = stake.amount.change_precision();
+        let transactions = match &self.allocation {
+            TicketAllocation::Snapshot => return Ok(balance),
+            TicketAllocation::TimeWeighted(transactions) => transactions,
+        };
+        let epoch = match self.solana.get_epoch_by_index(epoch_index).await {
+            Ok(epoch) => Epoch::from_solana(&epoch, None),
+            // Tickets of the next epoch are generated before it starts, for the balance it will start with
+            Err(e) if e.is_account_not_found(AccountType::Epoch) => return Ok(balance),
+            Err(e) => return Err(e.into()),
+        };
+        let changes = transactions
+            .balance_changes_by_wallet(&stake.owner, epoch.started_at)
+            .await?;
+        let until = match until {
+            Some(until) => {
+                if stake.updated_epoch_index >= epoch_index && changes.is_empty() {
+                    return Err(TicketError::StakeChangesNotIndexed(stake.owner, epoch_index).into());
+                }
+                cmp::min(until, epoch.expected_end_at)
+            }
+            None => epoch.expected_end_at,
+        };
+        time_weighted_balance(balance, &changes, epoch.started_at, until)
     }
 
-    async fn read_tickets_by_epoch_index_and_prefix(
+    /// Number of campaign bonus sequences of the wallet's ticket. The campaigns it's eligible for grant them when the
+    /// ticket is `reconciled` with the final balance, until then they follow the balance.
+    async fn num_campaign_bonus_sequences(
         &self,
-        index: u64,
-        limit: u8,
-        prefix: &[u8],
-    ) -> Result<TicketsWithCount> {
-        let limit = cmp::min(limit, 40);
-        self.repository
-            .by_epoch_index_and_prefix(index, Some(limit), prefix)
+        wallet: &Pubkey,
+        epoch_index: u64,
+        balance: FPUSDC,
+        ticket_price: &TicketPrice,
+        reconciled: bool,
+    ) -> Result<u32> {
+        // Only the draws played before the ticket's epoch count
+        let before_epoch_index = cmp::min(epoch_index, first_open_epoch_index(self.solana.as_ref()).await?);
+        let draws_played = self
+            .repository
+            .draws_played_by_wallet(wallet, before_epoch_index)
+            .await?;
+        let wallet = CampaignWallet {
+            wallet: *wallet,
+            balance,
+            normal_sequences: ticket_price.sequences_count,
+            draws_played,
+        };
+        if reconciled {
+            self.campaigns.grant_sequences(epoch_index, &wallet).await
+        } else {
+            self.campaigns.num_sequences(epoch_index, &wallet).await
+        }
+    }
+
+    /// Reads the seed of the epoch, recording it from the epoch account the first time. Not available until the epoch
+    /// is created, or for epochs created before the program recorded their creation slot.
+    async fn epoch_seed(&self, seeds: &dyn EpochSeedRepository, epoch_index: u64) -> Result<EpochSeed> {
+        if let Some(seed) = seeds.by_epoch_index(epoch_index).await? {
+            return Ok(seed);
+        }
+        let epoch = match self.solana.get_epoch_by_index(epoch_index).await {
+            Ok(epoch) => epoch,
+            Err(e) if e.is_account_not_found(AccountType::Epoch) => {
+                return Err(TicketError::EpochSeedNotAvailable(epoch_index).into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (slot, slot_hash) = match &epoch.creation_slot {
+            Some(creation_slot) => (creation_slot.slot, Hash::new_from_array(creation_slot.hash)),
+            None => return Err(TicketError::EpochSeedNotAvailable(epoch_index).into()),
+        };
+        log::info!(
+            "Recording seed of epoch {}: slot {} hash {}",
+            epoch_index,
+            slot,
+            slot_hash
+        );
+        seeds
+            .create(&EpochSeed {
+                epoch_index,
+                epoch_pubkey: epoch.pubkey,
+                slot,
+                slot_hash,
+            })
             .await
     }
 
-    async fn generate_ticket_for_stake(&self, stake: &Stake, epoch_index: Option<u64>) -> Result<Ticket> {
+    /// Generates the ticket of the stake, or adjusts the existing one to its balance.
+    async fn generate_ticket(
+        &self,
+        stake: &Stake,
+        epoch_index: Option<u64>,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Ticket> {
         let wallet = stake.owner;
         log::info!("generating ticket for stake: {stake:#?}");
         let epoch_index = match epoch_index {
@@ -85,8 +203,14 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
         // if the stake has a newer epoch index, we should use that
         let epoch_index = max(stake.updated_epoch_index, epoch_index);
 
-        let balance = stake.amount.change_precision();
-        let ticket_price = self.calculator.calculate(balance).await;
+        let balance = self.allocated_balance(stake, epoch_index, until).await?;
+        let ticket_price = self.calculator.calculate(&wallet, balance).await?;
+        let reconciled = until.is_some();
+        if let TicketGeneration::Deterministic(seeds) = &self.generation {
+            return self
+                .derive_ticket(seeds.as_ref(), stake, epoch_index, balance, ticket_price, reconciled)
+                .await;
+        }
 
         log::info!("Reading existing ticket");
         if let Some(ticket) = self
@@ -96,19 +220,100 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
             .with_context(|| "Can't query existing ticket for the wallet")?
         {
             log::info!("Existing ticket found, adjusting sequences");
-            let extra = adjust_sequences(
-                &self.rng,
-                &ticket.sequences,
-                ticket_price.sequences_count,
-                SequenceType::Normal,
-            )?;
-
-            log::info!("Saving sequences");
-            let res = self
-                .repository
-                .add_sequences(&ticket.wallet, ticket.epoch_index, &extra)
-                .await
-                .with_context(|| "Error updating sequences")?;
+            let num_campaign_bonus_sequences = self
+                .num_campaign_bonus_sequences(&wallet, epoch_index, balance, &ticket_price, reconciled)
+                .await?;
+            let num_bonus_sequences = ticket
+                .sequences
+                .iter()
+                .filter(|sequence| !is_replaceable(sequence.sequence_type))
+                .count();
+            let sequences_count = ticket_price.sequences_count + num_bonus_sequences as u32;
+            let res = if ticket.sequences.len() as u32 > sequences_count {
+                // The balance went down, generated sequences are dropped before the user's picks
+                let mut sequences = ticket
+                    .sequences
+                    .iter()
+                    .filter(|sequence| sequence.sequence_type == SequenceType::UserPicked)
+                    .chain(
+                        ticket
+                            .sequences
+                            .iter()
+                            .filter(|sequence| sequence.sequence_type == SequenceType::Normal),
+                    )
+                    .cloned()
+                    .collect::<Vec<_>>();
+                sequences.truncate(ticket_price.sequences_count as usize);
+
+                log::info!("Removing sequences");
+                self.repository
+                    .replace_sequences(
+                        &wallet,
+                        epoch_index,
+                        &[SequenceType::Normal, SequenceType::UserPicked],
+                        &sequences,
+                    )
+                    .await
+                    .with_context(|| "Error replacing sequences")?
+            } else {
+                let extra = adjust_sequences(&self.rng, &ticket.sequences, sequences_count, SequenceType::Normal)?;
+
+                log::info!("Saving sequences");
+                self.repository
+                    .add_sequences(&ticket.wallet, ticket.epoch_index, &extra)
+                    .await
+                    .with_context(|| "Error updating sequences")?
+            };
+            // Campaigns may have started since the ticket was generated, and their grants follow the balance
+            let mut campaign_bonus_sequences = res
+                .sequences
+                .iter()
+                .filter(|sequence| sequence.sequence_type == SequenceType::CampaignBonus)
+                .cloned()
+                .collect::<Vec<_>>();
+            let num_existing = campaign_bonus_sequences.len() as u32;
+            let res = if num_campaign_bonus_sequences > num_existing {
+                let prior = HashSet::from_iter(res.sequences.iter().map(|sequence| sequence.nums));
+                let campaign_bonus_sequences = generate_sequences_with_type(
+                    &self.rng,
+                    Some(&prior),
+                    num_campaign_bonus_sequences - num_existing,
+                    SequenceType::CampaignBonus,
+                )?;
+
+                log::info!("Saving campaign bonus sequences");
+                self.repository
+                    .add_sequences(&wallet, epoch_index, &campaign_bonus_sequences)
+                    .await
+                    .with_context(|| "Error updating sequences")?
+            } else if num_campaign_bonus_sequences < num_existing {
+                campaign_bonus_sequences.truncate(num_campaign_bonus_sequences as usize);
+
+                log::info!("Removing campaign bonus sequences");
+                self.repository
+                    .replace_sequences(
+                        &wallet,
+                        epoch_index,
+                        &[SequenceType::CampaignBonus],
+                        &campaign_bonus_sequences,
+                    )
+                    .await
+                    .with_context(|| "Error replacing sequences")?
+            } else {
+                res
+            };
+            let res = if res.balance != balance.to_string() {
+                let balance = balance.to_string();
+                let price = ticket_price.price_per_ticket.to_string();
+                self.repository
+                    .update_balance(&wallet, epoch_index, balance.clone(), price.clone())
+                    .await
+                    .with_context(|| "Error updating balance")?;
+                Ticket { balance, price, ..res }
+            } else {
+                res
+            };
+            self.events.publish(Event::TicketGenerated(res.clone()));
             Ok(res)
         } else {
             log::info!("No existing ticket found, creating a new one");
@@ -138,8 +343,21 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
                 num_signup_bonus_sequences,
                 SequenceType::SignUpBonus,
             )?;
+            unique_sequences.extend(signup_bonus_sequences.iter().cloned().map(|s| s.nums));
             sequences.extend(signup_bonus_sequences);
 
+            // Campaign bonus sequence generation
+            let num_campaign_bonus_sequences = self
+                .num_campaign_bonus_sequences(&wallet, epoch_index, balance, &ticket_price, reconciled)
+                .await?;
+            let campaign_bonus_sequences = generate_sequences_with_type(
+                &self.rng,
+                Some(&unique_sequences),
+                num_campaign_bonus_sequences,
+                SequenceType::CampaignBonus,
+            )?;
+            sequences.extend(campaign_bonus_sequences);
+
             let ticket = Ticket {
                 wallet: wallet.clone(),
                 epoch_index,
@@ -156,10 +374,128 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
                 .create(&ticket)
                 .await
                 .with_context(|| "Error saving tickets")?;
+            self.events.publish(Event::TicketGenerated(res.clone()));
             Ok(res)
         }
     }
 
+    /// Derives the ticket of the stake from its seed. An existing ticket is derived again if the stake or its campaign
+    /// bonus sequences changed, keeping the user's picks and the number of the other bonus sequences.
+    async fn derive_ticket(
+        &self,
+        seeds: &dyn EpochSeedRepository,
+        stake: &Stake,
+        epoch_index: u64,
+        balance: FPUSDC,
+        ticket_price: TicketPrice,
+        reconciled: bool,
+    ) -> Result<Ticket> {
+        let wallet = stake.owner;
+        let epoch_seed = self.epoch_seed(seeds, epoch_index).await?;
+        let seed = TicketSeed::new(&epoch_seed, wallet, balance);
+
+        log::info!("Reading existing ticket");
+        let existing = self
+            .repository
+            .by_wallet_and_epoch_index(&wallet, epoch_index)
+            .await
+            .with_context(|| "Can't query existing ticket for the wallet")?;
+        let num_campaign_bonus_sequences = self
+            .num_campaign_bonus_sequences(&wallet, epoch_index, balance, &ticket_price, reconciled)
+            .await?;
+        let res = match existing {
+            Some(ticket)
+                if ticket.balance == balance.to_string()
+                    && DerivedCounts::of(&ticket.sequences).campaign_bonus == num_campaign_bonus_sequences =>
+            {
+                return Ok(ticket)
+            }
+            Some(ticket) => {
+                log::info!("Existing ticket found, deriving sequences for the new stake");
+                let existing_counts = DerivedCounts::of(&ticket.sequences);
+                let picks = ticket
+                    .sequences
+                    .iter()
+                    .filter(|sequence| sequence.sequence_type == SequenceType::UserPicked)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let counts = DerivedCounts {
+                    normal: ticket_price.sequences_count.saturating_sub(picks.len() as u32),
+                    campaign_bonus: num_campaign_bonus_sequences,
+                    ..existing_counts
+                };
+                let sequences = derive_sequences(&seed, &picks, counts)?;
+
+                log::info!("Saving sequences");
+                let res = self
+                    .repository
+                    .replace_sequences(&wallet, epoch_index, &DERIVED_SEQUENCE_TYPES, &sequences)
+                    .await
+                    .with_context(|| "Error replacing sequences")?;
+                // Updated last, so that the ticket is derived again if this fails
+                let balance = balance.to_string();
+                let price = ticket_price.price_per_ticket.to_string();
+                self.repository
+                    .update_balance(&wallet, epoch_index, balance.clone(), price.clone())
+                    .await
+                    .with_context(|| "Error updating balance")?;
+                Ticket { balance, price, ..res }
+            }
+            None => {
+                log::info!("No existing ticket found, deriving a new one");
+                let counts = DerivedCounts {
+                    normal: ticket_price.sequences_count,
+                    airdrop_bonus: self
+                        .repository
+                        .num_airdrop_sequences_by_wallet_and_epoch_index(&wallet, epoch_index)
+                        .await?,
+                    sign_up_bonus: self.num_signup_bonus_sequences(&wallet, stake.amount).await?,
+                    campaign_bonus: num_campaign_bonus_sequences,
+                };
+                let ticket = Ticket {
+                    wallet,
+                    epoch_index,
+                    sequences: derive_sequences(&seed, &[], counts)?,
+                    price: ticket_price.price_per_ticket.to_string(),
+                    balance: balance.to_string(),
+                    risq_id: None,
+                    arweave_url: None,
+                };
+
+                log::info!("Saving the new ticket");
+                self.repository
+                    .create(&ticket)
+                    .await
+                    .with_context(|| "Error saving tickets")?
+            }
+        };
+        self.events.publish(Event::TicketGenerated(res.clone()));
+        Ok(res)
+    }
+}
+
+#[async_trait]
+impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
+    async fn read_ticket_by_wallet_and_epoch_index(&self, wallet: &Pubkey, index: u64) -> Result<Option<Ticket>> {
+        self.repository.by_wallet_and_epoch_index(wallet, index).await
+    }
+
+    async fn read_tickets_by_epoch_index_and_prefix(
+        &self,
+        index: u64,
+        limit: u8,
+        prefix: &[u8],
+    ) -> Result<TicketsWithCount> {
+        let limit = cmp::min(limit, 40);
+        self.repository
+            .by_epoch_index_and_prefix(index, Some(limit), prefix)
+            .await
+    }
+
+    async fn generate_ticket_for_stake(&self, stake: &Stake, epoch_index: Option<u64>) -> Result<Ticket> {
+        self.generate_ticket(stake, epoch_index, None).await
+    }
+
     async fn generate_ticket_for_wallet(&self, wallet: &Pubkey, epoch_index: Option<u64>) -> Result<Ticket> {
         let stake = self.solana.get_stake_by_wallet(*wallet).await?;
         self.generate_ticket_for_stake(&stake, epoch_index).await
@@ -173,6 +509,11 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
         } else {
             latest_epoch.index + 1
         };
+        // Derived tickets need the seed of the epoch, so those of the next one are generated once it's created
+        if epoch_index > latest_epoch.index && matches!(self.generation, TicketGeneration::Deterministic(_)) {
+            log::info!("Epoch {} isn't created yet, deferring its tickets", epoch_index);
+            return Ok(vec![]);
+        }
 
         let mut tickets = vec![];
 
@@ -185,6 +526,195 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
         Ok(tickets)
     }
 
+    async fn reconcile_tickets(&self) -> Result<Vec<Result<Ticket>>> {
+        let latest_epoch = self.solana.get_latest_epoch().await?;
+        if latest_epoch.status != EpochStatus::Running {
+            return Ok(vec![]);
+        }
+        log::info!("Reconciling tickets of epoch {}", latest_epoch.index);
+        let now = Utc::now();
+        let stakes = self.solana.get_all_stakes().await?;
+
+        let mut tickets = vec![];
+        for stake in stakes {
+            let ticket = self.generate_ticket(&stake, Some(latest_epoch.index), Some(now)).await;
+            tickets.push(ticket);
+        }
+
+        Ok(tickets)
+    }
+
+    async fn submit_user_picks(&self, wallet: &Pubkey, picks: &[[u8; 6]]) -> Result<Ticket> {
+        // Keep the order in which the picks were submitted
+        let mut unique_picks = HashSet::new();
+        let mut user_picked = Vec::new();
+        for nums in picks {
+            validate_sequence(nums)?;
+            if unique_picks.insert(*nums) {
+                user_picked.push(Sequence {
+                    nums: *nums,
+                    sequence_type: SequenceType::UserPicked,
+                });
+            }
+        }
+
+        // Make sure the ticket exists and is up to date with the stake before replacing its sequences
+        let stake = self.solana.get_stake_by_wallet(*wallet).await?;
+        let ticket = self.generate_ticket_for_stake(&stake, None).await?;
+        if ticket.risq_id.is_some() {
+            return Err(TicketError::AlreadySubmitted(ticket.epoch_index).into());
+        }
+
+        // The balance the ticket was just generated for, which may be time weighted. The picks are limited by it, and
+        // verification reproduces the ticket from it
+        let balance: FPUSDC = ticket
+            .balance
+            .parse()
+            .map_err(|e| anyhow::anyhow!(e))
+            .with_context(|| format!("Invalid ticket balance {}", ticket.balance))?;
+        let sequences_count = self.calculator.calculate(wallet, balance).await?.sequences_count;
+        if user_picked.len() > sequences_count as usize {
+            return Err(TicketError::TooManyPicks {
+                entitled: sequences_count,
+                picked: user_picked.len(),
+            }
+            .into());
+        }
+
+        let (sequence_types, sequences) = match &self.generation {
+            TicketGeneration::Deterministic(seeds) => {
+                // The picks come first, everything else is derived around them
+                let epoch_seed = self.epoch_seed(seeds.as_ref(), ticket.epoch_index).await?;
+                let seed = TicketSeed::new(&epoch_seed, *wallet, balance);
+                let counts = DerivedCounts {
+                    normal: sequences_count - user_picked.len() as u32,
+                    ..DerivedCounts::of(&ticket.sequences)
+                };
+                let derived = derive_sequences(&seed, &user_picked, counts)?;
+                let mut sequences = user_picked;
+                sequences.extend(derived);
+                (
+                    vec![
+                        SequenceType::Normal,
+                        SequenceType::UserPicked,
+                        SequenceType::AirdropBonus,
+                        SequenceType::SignUpBonus,
+                        SequenceType::CampaignBonus,
+                    ],
+                    sequences,
+                )
+            }
+            TicketGeneration::Random => {
+                // Bonus sequences stay as they are, a pick that is already on the ticket as a bonus is dropped
+                let mut sequences = ticket
+                    .sequences
+                    .into_iter()
+                    .filter(|sequence| !is_replaceable(sequence.sequence_type))
+                    .collect::<Vec<_>>();
+                let num_bonus_sequences = sequences.len();
+                let bonus = sequences.iter().map(|sequence| sequence.nums).collect::<HashSet<_>>();
+                sequences.extend(user_picked.into_iter().filter(|pick| !bonus.contains(&pick.nums)));
+                let extra = adjust_sequences(
+                    &self.rng,
+                    &sequences,
+                    sequences_count + num_bonus_sequences as u32,
+                    SequenceType::Normal,
+                )?;
+                sequences.extend(extra);
+                (
+                    vec![SequenceType::Normal, SequenceType::UserPicked],
+                    sequences.split_off(num_bonus_sequences),
+                )
+            }
+        };
+
+        log::info!("Saving user picked sequences");
+        let res = self
+            .repository
+            .replace_sequences(wallet, ticket.epoch_index, &sequence_types, &sequences)
+            .await
+            .with_context(|| "Error replacing sequences")?;
+        self.events.publish(Event::TicketGenerated(res.clone()));
+        Ok(res)
+    }
+
+    async fn verify_ticket(&self, wallet: &Pubkey, epoch_index: u64) -> Result<Option<TicketVerification>> {
+        let seeds = match &self.generation {
+            TicketGeneration::Deterministic(seeds) => seeds,
+            TicketGeneration::Random => return Err(TicketError::NotDeterministic.into()),
+        };
+        let ticket = match self.repository.by_wallet_and_epoch_index(wallet, epoch_index).await? {
+            Some(ticket) => ticket,
+            None => return Ok(None),
+        };
+        let epoch_seed = seeds
+            .by_epoch_index(epoch_index)
+            .await?
+            .ok_or(TicketError::EpochSeedNotAvailable(epoch_index))?;
+        let sequences = reproduce_ticket(&ticket, &epoch_seed)?;
+        let verified = verify_ticket(&ticket, &epoch_seed)?;
+        Ok(Some(TicketVerification {
+            ticket,
+            epoch_seed,
+            sequences,
+            verified,
+        }))
+    }
+
+    async fn ticket_odds(&self, wallet: &Pubkey) -> Result<Option<TicketOdds>> {
+        let latest_epoch = self.solana.get_latest_epoch().await?;
+        if latest_epoch.status == EpochStatus::Ended {
+            return Err(TicketError::AlreadyDrawn(latest_epoch.index).into());
+        }
+        let ticket = match self
+            .repository
+            .by_wallet_and_epoch_index(wallet, latest_epoch.index)
+            .await?
+        {
+            Some(ticket) => ticket,
+            None => return Ok(None),
+        };
+
+        // Only the sequences sharing a prefix of 4 numbers with the ticket's can share its prizes. Tickets are read with
+        // the sequences matching the prefix only, so those of different prefixes are merged.
+        let prefixes: BTreeSet<_> = ticket.sequences.iter().map(|sequence| &sequence.nums[..4]).collect();
+        let mut tickets: BTreeMap<Pubkey, Ticket> = BTreeMap::new();
+        for prefix in prefixes {
+            let TicketsWithCount { tickets: holders, .. } = self
+                .repository
+                .by_epoch_index_and_prefix(latest_epoch.index, None, prefix)
+                .await?;
+            for holder in holders {
+                match tickets.get_mut(&holder.wallet) {
+                    Some(ticket) => ticket.sequences.extend(holder.sequences),
+                    None => {
+                        tickets.insert(holder.wallet, holder);
+                    }
+                }
+            }
+        }
+        let tickets: Vec<_> = tickets.into_values().collect();
+
+        let epoch = self.solana.get_epoch_by_index(latest_epoch.index).await?;
+        let prize_pools = [
+            epoch.yield_split_cfg.jackpot,
+            latest_epoch.pending_funds.tier2_prize,
+            latest_epoch.pending_funds.tier3_prize,
+        ];
+        let tiers = calculate_odds(wallet, &tickets, prize_pools);
+        let expected_prize = tiers
+            .iter()
+            .try_fold(FPUSDC::zero(), |sum, tier| sum.checked_add(tier.expected_prize))
+            .context("Error summing expected prizes")?;
+        Ok(Some(TicketOdds {
+            wallet: *wallet,
+            epoch_index: latest_epoch.index,
+            tiers,
+            expected_prize,
+            final_prize_pools: latest_epoch.status == EpochStatus::Finalising,
+        }))
+    }
+
     async fn update_arweave_url(
         &self,
         wallet: &Pubkey,
@@ -223,85 +753,60 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
             .winning_combination
             .ok_or(EpochError::WinningCombinationNotSet)?;
 
-        let mut winning_prefix = vec![0; 4];
-        winning_prefix.clone_from_slice(&winning_combination[..4]);
-        let tickets = self
+        let matches = self
             .repository
-            .by_epoch_index_and_prefix(epoch.index, None, &winning_prefix)
-            .await?
-            .tickets;
+            .prefix_matches_by_epoch_index(epoch.index, &winning_combination, 4)
+            .await?;
 
         let mut winners = Winners {
             tier1: BTreeSet::new(),
             tier2: BTreeMap::new(),
             tier3: BTreeMap::new(),
         };
-        for ticket in tickets {
-            for sequence in ticket.sequences {
-                let count = sequence
-                    .nums
-                    .into_iter()
-                    .zip(winning_combination)
-                    .take_while(|&(actual, expected)| actual == expected)
-                    .count();
-
-                match count {
-                    6 => {
-                        winners.tier1.insert(ticket.wallet);
-                    }
-                    5 => {
-                        *winners.tier2.entry(ticket.wallet).or_insert(0) += 1;
-                    }
-                    4 => {
-                        *winners.tier3.entry(ticket.wallet).or_insert(0) += 1;
-                    }
-                    _ => {}
+        for PrefixMatches {
+            wallet,
+            prefix_len,
+            num_sequences,
+        } in matches
+        {
+            let num_sequences = u32::try_from(num_sequences)?;
+            match prefix_len {
+                6 => {
+                    winners.tier1.insert(wallet);
+                }
+                5 => {
+                    *winners.tier2.entry(wallet).or_insert(0) += num_sequences;
                 }
+                4 => {
+                    *winners.tier3.entry(wallet).or_insert(0) += num_sequences;
+                }
+                _ => {}
             }
         }
         Ok(winners)
     }
 
-    async fn calculate_optimal_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-        let latest_epoch = self.solana.get_latest_epoch().await?;
-        let epoch_index = latest_epoch.index;
-
-        log::info!("getting distinct sequences for epoch {}", epoch_index);
-        let sequences = self.repository.distinct_sequences_by_epoch_index(epoch_index).await?;
-
-        log::info!("constructing prefix hash map");
-        let mut prefix_map: HashMap<[u8; 4], ([u8; 6], usize)> = HashMap::new();
-        for sequence in sequences {
-            let mut prefix: [u8; 4] = Default::default();
-            prefix.clone_from_slice(&sequence[..4]);
-            prefix_map.entry(prefix).or_insert((sequence, 0)).1 += 1;
-        }
-
-        log::info!("calculating optimal winning combination from sequences");
-        let optimal_sequence = prefix_map.into_values().max_by_key(|v| v.1).map(|v| v.0);
-        Ok(optimal_sequence)
+    async fn ticket_price(&self) -> Result<FPUSDC> {
+        self.calculator.price().await
     }
 
-    async fn random_winning_combination(&self) -> Result<Option<[u8; 6]>> {
-        let latest_epoch = self.solana.get_latest_epoch().await?;
-        let epoch_index = latest_epoch.index;
-
-        let sequence = self.repository.random_sequence_by_epoch_index(epoch_index).await?;
-        Ok(sequence)
+    async fn ticket_pricing_policy(&self) -> Result<TicketPricingPolicy> {
+        self.calculator.policy().await
     }
 
-    async fn ticket_price(&self) -> Result<FPUSDC> {
-        Ok(self.calculator.price().await)
+    async fn calculate_ticket_price(&self, wallet: &Pubkey, balance: FPUSDC) -> Result<TicketPrice> {
+        self.calculator.calculate(wallet, balance).await
     }
 
     async fn draws_played_by_wallet(&self, wallet: &Pubkey) -> Result<u64> {
-        Ok(self.repository.draws_played_by_wallet(wallet).await?)
+        let before_epoch_index = first_open_epoch_index(self.solana.as_ref()).await?;
+        self.repository.draws_played_by_wallet(wallet, before_epoch_index).await
     }
 
     async fn num_signup_bonus_sequences(&self, wallet: &Pubkey, amount: FPUSDC) -> Result<u32> {
         let staked_min_amount = amount >= self.bonus_info_service.min_stake_amount().await;
         let is_first_time_user = !self.repository.prior_sequences_exist_by_wallet(wallet).await?;
-        let normal_sequences_count = self.calculator.calculate(amount).await.sequences_count;
+        let normal_sequences_count = self.calculator.calculate(wallet, amount).await?.sequences_count;
 
         // let normal_sequence_count = ;
         if staked_min_amount && is_first_time_user {
@@ -314,6 +819,10 @@ impl<R: Rng + Sync + Send> TicketService for DefaultTicketService<R> {
     }
 }
 
+fn is_replaceable(sequence_type: SequenceType) -> bool {
+    matches!(sequence_type, SequenceType::Normal | SequenceType::UserPicked)
+}
+
 fn adjust_sequences<R: Rng>(
     rng: &Mutex<R>,
     sequences: &[Sequence],
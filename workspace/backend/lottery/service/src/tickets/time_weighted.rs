@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use nezha_staking::fixed_point::FPUSDC;
+
+use crate::model::transaction::{Transaction, TransactionType};
+
+/// Average of a wallet's stake balance over `[from, to]`, weighted by how long each balance was held.
+///
+/// `balance` is the current balance, and `changes` the completed deposits and withdrawals of the wallet since `from`.
+/// The balance before each change is worked out backwards from it, so every change made since `from` has to be indexed
+/// for the result to be right. Other transactions, and changes without a time, are ignored.
+pub fn time_weighted_balance(
+    balance: FPUSDC,
+    changes: &[Transaction],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<FPUSDC> {
+    let overflow = || anyhow!("Overflow calculating the time weighted balance from {}", balance);
+    let before = |balance: FPUSDC, transaction: &Transaction| -> Result<FPUSDC> {
+        Ok(match transaction.transaction_type {
+            TransactionType::DepositCompleted => balance.checked_sub(transaction.amount).unwrap_or_else(FPUSDC::zero),
+            TransactionType::WithdrawCompleted => balance.checked_add(transaction.amount).ok_or_else(overflow)?,
+            _ => balance,
+        })
+    };
+
+    let mut changes = changes
+        .iter()
+        .filter_map(|transaction| Some((transaction.time?, transaction)))
+        .filter(|(time, _)| *time > from)
+        .collect::<Vec<_>>();
+    changes.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    // Changes made after the window don't count towards it
+    let mut balance = balance;
+    for (_, transaction) in changes.iter().filter(|(time, _)| *time > to) {
+        balance = before(balance, transaction)?;
+    }
+    let window = (to - from).num_seconds();
+    if window <= 0 {
+        return Ok(balance);
+    }
+
+    let mut until = to;
+    let mut weighted = FPUSDC::zero();
+    for (time, transaction) in changes.into_iter().filter(|(time, _)| *time <= to) {
+        let held = FPUSDC::from((until - time).num_seconds() as u64);
+        weighted = weighted
+            .checked_add(balance.checked_mul(held).ok_or_else(overflow)?)
+            .ok_or_else(overflow)?;
+        balance = before(balance, transaction)?;
+        until = time;
+    }
+    let held = FPUSDC::from((until - from).num_seconds() as u64);
+    weighted = weighted
+        .checked_add(balance.checked_mul(held).ok_or_else(overflow)?)
+        .ok_or_else(overflow)?;
+
+    weighted.checked_div(FPUSDC::from(window as u64)).ok_or_else(overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use solana_program::pubkey::Pubkey;
+
+    use super::*;
+    use crate::model::transaction::TransactionId;
+
+    fn fp(s: &str) -> FPUSDC {
+        s.parse().unwrap()
+    }
+
+    fn transaction(transaction_type: TransactionType, amount: &str, time: DateTime<Utc>) -> Transaction {
+        Transaction {
+            transaction_id: TransactionId(Pubkey::new_unique().to_string()),
+            instruction_index: 0,
+            wallet: Pubkey::new_unique(),
+            amount: fp(amount),
+            mint: Pubkey::new_unique(),
+            time: Some(time),
+            transaction_type,
+            inner_instruction_index: None,
+            slot: None,
+            finalized: true,
+        }
+    }
+
+    #[test]
+    fn weighs_balance_by_time_held() {
+        let from = Utc::now() - Duration::days(30);
+        let to = from + Duration::days(10);
+
+        // Held all along
+        assert_eq!(time_weighted_balance(fp("100"), &[], from, to).unwrap(), fp("100"));
+
+        // Deposited a day before the end
+        let changes = [transaction(
+            TransactionType::DepositCompleted,
+            "100",
+            to - Duration::days(1),
+        )];
+        assert_eq!(time_weighted_balance(fp("100"), &changes, from, to).unwrap(), fp("10"));
+
+        // 100 held all along, 50 more for half of the window, then 120 withdrawn for the last 2 days
+        let changes = [
+            transaction(TransactionType::DepositCompleted, "50", from + Duration::days(5)),
+            transaction(TransactionType::WithdrawCompleted, "120", from + Duration::days(8)),
+            transaction(TransactionType::DepositApproved, "1000", from + Duration::days(9)),
+            transaction(TransactionType::DepositCompleted, "1000", to + Duration::days(1)),
+        ];
+        // (100 * 5 + 150 * 3 + 30 * 2) / 10, the deposit after the window is taken off the current balance
+        assert_eq!(
+            time_weighted_balance(fp("1030"), &changes, from, to).unwrap(),
+            fp("101")
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use nezha_staking::fixed_point::FPUSDC;
 use solana_sdk::pubkey::Pubkey;
 
@@ -7,10 +8,13 @@ use crate::model::transaction::{Transaction, TransactionId, TransactionType};
 
 #[async_trait]
 pub trait UserTransactionRepository: Send + Sync {
+    /// `inner_instruction_index` is `None` for the top level instruction, and the position among its inner
+    /// instructions for one invoked through CPI.
     async fn by_transaction_id_and_instruction_index(
         &self,
         transaction_id: &TransactionId,
         instruction_index: u8,
+        inner_instruction_index: Option<u8>,
     ) -> Result<Option<Transaction>>;
     async fn by_transaction_id(&self, transaction_id: &TransactionId) -> Result<Vec<Transaction>>;
     async fn by_wallet(&self, wallet: &Pubkey, limit: usize, offset: usize) -> Result<Vec<Transaction>>;
@@ -25,8 +29,14 @@ pub trait UserTransactionRepository: Send + Sync {
     ) -> Result<Vec<Transaction>>;
     async fn all(&self, limit: usize, offset: usize) -> Result<Vec<Transaction>>;
     async fn store_transaction(&self, transaction: &Transaction) -> Result<()>;
+    /// Stores the transactions, and marks those already stored as finalized when they are.
     async fn store_transactions(&self, transactions: &[Transaction]) -> Result<()>;
+    /// Removes the unfinalized transactions up to the slot, which didn't make it into the finalized chain.
+    /// Returns how many were removed.
+    async fn remove_unfinalized(&self, up_to_slot: u64) -> Result<u64>;
     async fn total_deposit_by_wallet(&self, wallet: &Pubkey) -> Result<FPUSDC>;
+    /// Completed deposits and withdrawals of the wallet after `since`, oldest first.
+    async fn balance_changes_by_wallet(&self, wallet: &Pubkey, since: DateTime<Utc>) -> Result<Vec<Transaction>>;
 }
 
 #[async_trait]
@@ -42,6 +52,7 @@ pub trait UserTransactionService: Send + Sync {
         &self,
         transaction_id: &TransactionId,
         instruction_index: u8,
+        inner_instruction_index: Option<u8>,
     ) -> Result<Option<Transaction>>;
     async fn by_transaction_id(&self, transaction_id: &TransactionId) -> Result<Vec<Transaction>>;
     async fn by_wallet(&self, wallet: &Pubkey, limit: usize, offset: usize) -> Result<Vec<Transaction>>;
@@ -76,9 +87,10 @@ impl UserTransactionService for UserTransactionServiceImpl {
         &self,
         transaction_id: &TransactionId,
         instruction_index: u8,
+        inner_instruction_index: Option<u8>,
     ) -> Result<Option<Transaction>> {
         self.user_transaction_repository
-            .by_transaction_id_and_instruction_index(transaction_id, instruction_index)
+            .by_transaction_id_and_instruction_index(transaction_id, instruction_index, inner_instruction_index)
             .await
     }
 
@@ -3,7 +3,7 @@ use chrono::{DateTime, Duration, Utc};
 use nezha_staking::{
     fixed_point::test_utils::fp,
     instruction::{self, CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput},
-    state::YieldSplitCfg,
+    state::{TicketsInfo, YieldSplitCfg},
 };
 use solana_program::native_token::LAMPORTS_PER_SOL;
 use solana_sdk::{signature::Keypair, signer::Signer};
@@ -112,7 +112,13 @@ pub async fn attempt_deposit(solana: &SolanaImpl, user_keypair: &Keypair, amount
 
 pub async fn withdraw_yield(solana: &dyn Solana, epoch_index: u64) -> Result<()> {
     println!("Withdrawing yield");
-    solana.enter_investment_fake(epoch_index, 1).await?;
+    let tickets_info = TicketsInfo {
+        num_tickets: 1,
+        tickets_url: "ar://test".to_string(),
+        tickets_hash: Vec::new(),
+        tickets_version: 0,
+    };
+    solana.enter_investment_fake(epoch_index, tickets_info).await?;
     Ok(())
 }
 
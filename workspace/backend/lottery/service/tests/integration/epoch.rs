@@ -1,9 +1,9 @@
 use crate::common::{
     self,
-    util::{deposit_yield, progress_latest_epoch_to_status, publish_winning_combination},
+    util::{deposit_yield, perform_deposit, progress_latest_epoch_to_status, publish_winning_combination},
     SolanaContext,
 };
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use async_trait::async_trait;
 use pretty_assertions::assert_eq;
 use rand::{
@@ -11,31 +11,87 @@ use rand::{
     thread_rng, Rng, SeedableRng,
 };
 use service::{
-    epoch::{service::EpochService, EpochManager, EpochRepository, FPUSDC},
-    model::epoch::{Epoch, EpochStatus, Investor, UseCache},
+    campaign::{DefaultCampaignService, InMemoryCampaignRepository},
+    epoch::{
+        service::EpochService, EpochManager, InMemoryEpochAuditRepository, InMemoryEpochJobRepository,
+        InMemoryEpochRepository, FPUSDC,
+    },
+    events::EventBus,
+    model::{
+        epoch::{Epoch, EpochStatus, Investor, UseCache},
+        epoch_audit::EpochOperation,
+    },
     solana::{
         rpc::{SolanaRpc, SolanaRpcExt},
-        Solana, ToSolanaError,
+        solana_impl::SolanaImpl,
+        Solana, SolanaError, ToSolanaError,
     },
     tickets::{
-        bonus::BonusInfoService, DefaultTicketService, Sequence, SequenceType, Ticket, TicketPrice,
-        TicketPriceCalculator, TicketRepository, TicketService,
+        bonus::{BonusInfo, BonusInfoService, BonusSequenceCount, DefaultBonusInfoService},
+        ConstantTicketPriceCalculator, DefaultTicketService, Sequence, SequenceType, Ticket, TicketAllocation,
+        TicketGeneration, TicketPrice, TicketPriceCalculator, TicketPricingPolicy, TicketRepository, TicketService,
     },
 };
 use solana_program::{program_pack::Pack, pubkey::Pubkey};
-use solana_sdk::signer::Signer;
-use std::sync::{Arc, Mutex, RwLock};
+use solana_sdk::{compute_budget::ComputeBudgetInstruction, signer::Signer};
+use std::sync::{Arc, Mutex};
 use utils::with_mutex;
 
 use nezha_staking::accounts as ac;
 use nezha_staking::fixed_point::test_utils::fp;
-use service::tickets::InMemoryTicketRepository;
+use nezha_staking::instruction::{self, CreateEpochWinnersMetaArgs, TierWinnersMetaInput, WinnerInput};
+use service::tickets::{EpochManifest, InMemoryManifestPublisher, InMemoryTicketRepository};
+
+/// Ticket service generating random tickets for snapshot balances, without campaigns.
+fn new_ticket_svc(
+    rng: Arc<Mutex<StdRng>>,
+    solana: &SolanaImpl,
+    ticket_repo: InMemoryTicketRepository,
+    calculator: Box<dyn TicketPriceCalculator>,
+    bonus_info_service: Box<dyn BonusInfoService>,
+) -> DefaultTicketService<StdRng> {
+    let campaign_svc = DefaultCampaignService::new(
+        Box::new(InMemoryCampaignRepository::default()),
+        Box::new(ticket_repo.clone()),
+    );
+    DefaultTicketService::new(
+        rng,
+        Box::new(solana.clone()),
+        Box::new(ticket_repo),
+        calculator,
+        bonus_info_service,
+        EventBus::default(),
+        TicketGeneration::Random,
+        TicketAllocation::Snapshot,
+        Box::new(campaign_svc),
+    )
+}
 
-fn new_epoch_svc(solana: Box<dyn Solana>) -> EpochService {
-    EpochService::new(
+/// Ticket service of the epoch service, reconciling tickets when the epoch enters investment.
+fn new_reconciling_ticket_svc(solana: &SolanaImpl, ticket_repo: InMemoryTicketRepository) -> Box<dyn TicketService> {
+    Box::new(new_ticket_svc(
+        Arc::new(Mutex::new(StdRng::from_entropy())),
         solana,
+        ticket_repo,
+        Box::new(ConstantTicketPriceCalculator::new(fp("25"))),
+        Box::new(DefaultBonusInfoService::new(BonusInfo {
+            sub_seq_count: BonusSequenceCount::Constant(1),
+            sub_seq_min_stake: fp("25"),
+        })),
+    ))
+}
+
+fn new_epoch_svc(solana: &SolanaImpl) -> EpochService {
+    let ticket_repo = InMemoryTicketRepository::new(0);
+    EpochService::new(
+        Box::new(solana.clone()),
         Box::new(InMemoryEpochRepository::new()),
-        Box::new(InMemoryTicketRepository::new(0)),
+        Box::new(ticket_repo.clone()),
+        new_reconciling_ticket_svc(solana, ticket_repo),
+        Box::new(InMemoryManifestPublisher::default()),
+        Box::new(InMemoryEpochAuditRepository::default()),
+        Box::new(InMemoryEpochJobRepository::default()),
+        EventBus::default(),
     )
 }
 
@@ -87,7 +143,7 @@ async fn test_latest_epoch_from_service() -> Result<()> {
     // make sure there's at least 1 epoch
     progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
 
-    let svc = new_epoch_svc(Box::new(solana.clone()));
+    let svc = new_epoch_svc(solana);
 
     let latest_epoch = svc.latest_epoch(UseCache::No).await?.unwrap();
     assert!(latest_epoch.index >= 1);
@@ -109,7 +165,7 @@ async fn test_prize_decoding() -> Result<()> {
     progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
     progress_latest_epoch_to_status(&ctx, EpochStatus::Ended).await?;
 
-    let svc = new_epoch_svc(Box::new(solana.clone()));
+    let svc = new_epoch_svc(solana);
 
     let prizes = svc.wallet_prizes(&ctx.user_keypair.pubkey()).await?;
     assert_eq!(false, prizes.is_empty());
@@ -124,7 +180,7 @@ async fn test_withdraw_yield() -> Result<()> {
 
     let investor_pubkey = ctx.investor_keypair.pubkey();
 
-    let svc = new_epoch_svc(Box::new(solana.clone()));
+    let svc = new_epoch_svc(solana);
 
     progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
 
@@ -136,7 +192,7 @@ async fn test_withdraw_yield() -> Result<()> {
     let deposit_vault = ac::deposit_vault(&solana.program_id()).pubkey;
     let old_vault_balance = get_usdc_token_account_balance(solana.rpc_client.as_ref(), deposit_vault).await?;
 
-    let epoch = svc.enter_investment(Investor::Fake).await?;
+    let epoch = svc.enter_investment(Investor::Fake, "test").await?;
     assert_eq!(epoch.status, EpochStatus::Yielding);
 
     let new_investor_balance = solana.get_usdc_balance_by_wallet(investor_pubkey).await?;
@@ -156,12 +212,119 @@ async fn test_withdraw_yield() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_enter_investment_commits_manifest() -> Result<()> {
+    let ctx = common::setup_solana().await;
+    let solana = &ctx.solana;
+
+    let ticket_repo = InMemoryTicketRepository::new(0);
+    let publisher = InMemoryManifestPublisher::default();
+    let svc = EpochService::new(
+        Box::new(solana.clone()),
+        Box::new(InMemoryEpochRepository::new()),
+        Box::new(ticket_repo.clone()),
+        new_reconciling_ticket_svc(solana, ticket_repo.clone()),
+        Box::new(publisher.clone()),
+        Box::new(InMemoryEpochAuditRepository::default()),
+        Box::new(InMemoryEpochJobRepository::default()),
+        EventBus::default(),
+    );
+
+    progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
+    perform_deposit(solana, &ctx.user_keypair, 25_000_000).await?;
+    let epoch = svc.enter_investment(Investor::Fake, "operator").await?;
+
+    let tickets_info = solana
+        .get_epoch_by_index(epoch.index)
+        .await?
+        .inner
+        .tickets_info
+        .expect("tickets info not committed");
+    let manifest = publisher
+        .by_url(&tickets_info.tickets_url)
+        .expect("manifest not uploaded");
+    assert_eq!(tickets_info.tickets_hash, manifest.hash()?.to_bytes().to_vec());
+    assert_eq!(tickets_info.num_tickets, manifest.num_sequences);
+    assert_eq!(tickets_info.tickets_version, manifest.version);
+
+    // It's the manifest of the reconciled tickets
+    let tickets = ticket_repo.by_epoch_index(epoch.index).await?;
+    assert!(!tickets.is_empty());
+    assert_eq!(manifest, EpochManifest::new(epoch.index, &tickets)?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dry_run_withdraw_yield() -> Result<()> {
+    let ctx = common::setup_solana().await;
+    let solana = &ctx.solana;
+
+    let audit_repository = InMemoryEpochAuditRepository::default();
+    let ticket_repo = InMemoryTicketRepository::new(0);
+    let svc = EpochService::new(
+        Box::new(solana.clone()),
+        Box::new(InMemoryEpochRepository::new()),
+        Box::new(ticket_repo.clone()),
+        new_reconciling_ticket_svc(solana, ticket_repo),
+        Box::new(InMemoryManifestPublisher::default()),
+        Box::new(audit_repository.clone()),
+        Box::new(InMemoryEpochJobRepository::default()),
+        EventBus::default(),
+    );
+
+    progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
+
+    let simulation = svc
+        .dry_run(
+            EpochOperation::EnterInvestment {
+                investor: Investor::Fake,
+            },
+            "operator",
+        )
+        .await?;
+    assert_eq!(simulation.error, None);
+    assert_eq!(simulation.num_transactions, 1);
+    assert!(!simulation.logs.is_empty());
+    assert!(!simulation.account_diffs.is_empty());
+    assert!(!simulation.vault_transfers.is_empty());
+
+    // Nothing was sent
+    let latest_epoch = svc.latest_epoch(UseCache::No).await?.unwrap();
+    assert_eq!(latest_epoch.status, EpochStatus::Running);
+    assert_eq!(latest_epoch.total_invested, None);
+
+    svc.enter_investment(Investor::Fake, "operator").await?;
+
+    // Entering investment again is a no-op, so its dry run fails instead of simulating
+    let result = svc
+        .dry_run(
+            EpochOperation::EnterInvestment {
+                investor: Investor::Fake,
+            },
+            "operator",
+        )
+        .await;
+    assert!(result.unwrap_err().to_string().contains("already entered investment"));
+
+    let audit_log = svc.audit_log(10, 0).await?;
+    assert_eq!(audit_log.len(), 2);
+    assert_eq!(audit_log[0].operation, "enter_investment");
+    assert!(!audit_log[0].dry_run);
+    assert!(audit_log[0].signature.is_some());
+    assert!(audit_log[1].dry_run);
+    assert_eq!(audit_log[1].signature, None);
+    assert_eq!(audit_log[1].operator, "operator");
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_deposit_yield() -> Result<()> {
     let ctx = common::setup_solana().await;
     let solana = &ctx.solana;
 
-    let svc = new_epoch_svc(Box::new(solana.clone()));
+    let svc = new_epoch_svc(solana);
 
     progress_latest_epoch_to_status(&ctx, EpochStatus::Running).await?;
 
@@ -180,7 +343,7 @@ async fn test_deposit_yield() -> Result<()> {
         .checked_mul(fp(1.0 + yield_percent / 100.0))
         .unwrap();
 
-    svc.exit_investment(Investor::Fake, Some(return_amount)).await?;
+    svc.exit_investment(Investor::Fake, Some(return_amount), "test").await?;
     let latest_epoch_chain = {
         let latest_epoch = solana.get_latest_epoch().await?;
         let epoch = solana.get_epoch_by_index(latest_epoch.index).await?;
@@ -199,7 +362,7 @@ async fn test_deposit_yield() -> Result<()> {
 //     let ctx = common::setup_solana().await;
 //     let solana = &ctx.solana;
 
-//     let svc = new_epoch_svc(Box::new(solana.clone()));
+//     let svc = new_epoch_svc(solana);
 
 //     // start with a fresh epoch
 //     progress_latest_epoch_to_status(&ctx, EpochStatus::Ended).await?;
@@ -229,9 +392,9 @@ async fn setup_service_with_tier(tier: u8) -> (SolanaContext, Box<dyn TicketServ
     let solana = &ctx.solana;
 
     let user_pubkey = ctx.user_keypair.pubkey();
-    let epoch_service = new_epoch_svc(Box::new(solana.clone()));
+    let epoch_service = new_epoch_svc(solana);
 
-    let ticket_repo = Box::new(InMemoryTicketRepository::new(0));
+    let ticket_repo = InMemoryTicketRepository::new(0);
     let rng = Arc::new(Mutex::new(StdRng::from_entropy()));
 
     // start with a fresh epoch
@@ -259,9 +422,9 @@ async fn setup_service_with_tier(tier: u8) -> (SolanaContext, Box<dyn TicketServ
         ..Ticket::new_for_tests()
     };
     ticket_repo.create(&ticket).await.expect("could not create ticket");
-    let ticket_service = DefaultTicketService::new(
+    let ticket_service = new_ticket_svc(
         rng.clone(),
-        Box::new(solana.clone()),
+        solana,
         ticket_repo,
         Box::new(MockTicketPriceCalculator {}),
         Box::new(MockBonusInfoService {}),
@@ -316,7 +479,7 @@ async fn test_publish_winners_tier_1() -> Result<()> {
     let user_pubkey = ctx.user_keypair.pubkey();
 
     let winners = ticket_service.calculate_winners().await?;
-    let _epoch = epoch_service.publish_winners(winners).await?;
+    let _epoch = epoch_service.publish_winners(winners, "test").await?;
 
     let latest_epoch = {
         let latest_epoch = solana.get_latest_epoch().await?;
@@ -340,7 +503,7 @@ async fn test_publish_winners_tier_2() -> Result<()> {
     let user_pubkey = ctx.user_keypair.pubkey();
 
     let winners = ticket_service.calculate_winners().await?;
-    let _epoch = epoch_service.publish_winners(winners).await?;
+    let _epoch = epoch_service.publish_winners(winners, "test").await?;
 
     let latest_epoch = {
         let latest_epoch = solana.get_latest_epoch().await?;
@@ -357,15 +520,157 @@ async fn test_publish_winners_tier_2() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_republish_winners_verifies_meta() -> Result<()> {
+    let (ctx, ticket_service, epoch_service) = setup_service_with_tier(1).await;
+    let solana = ctx.solana;
+
+    let winners = ticket_service.calculate_winners().await?;
+    epoch_service.publish_winners(winners, "test").await?;
+    let epoch_index = solana.get_latest_epoch().await?.index;
+
+    // The meta is complete, so nothing is sent again and the totals are checked against the arguments
+    let meta_args = CreateEpochWinnersMetaArgs {
+        tier1_meta: TierWinnersMetaInput {
+            total_num_winners: 2,
+            total_num_winning_tickets: 2,
+        },
+        tier2_meta: TierWinnersMetaInput {
+            total_num_winners: 0,
+            total_num_winning_tickets: 0,
+        },
+        tier3_meta: TierWinnersMetaInput {
+            total_num_winners: 0,
+            total_num_winning_tickets: 0,
+        },
+    };
+    let result = solana.publish_winners(epoch_index, true, &meta_args, &[]).await;
+    assert!(
+        matches!(result, Err(SolanaError::WinnersMismatch { epoch_index: index, .. }) if index == epoch_index),
+        "{:?}",
+        result
+    );
+
+    Ok(())
+}
+
+/// Tier 3 winners with a winning ticket each.
+fn tier3_winners(num_winners: u32) -> (CreateEpochWinnersMetaArgs, Vec<WinnerInput>) {
+    let no_winners = TierWinnersMetaInput {
+        total_num_winners: 0,
+        total_num_winning_tickets: 0,
+    };
+    let meta_args = CreateEpochWinnersMetaArgs {
+        tier1_meta: no_winners.clone(),
+        tier2_meta: no_winners,
+        tier3_meta: TierWinnersMetaInput {
+            total_num_winners: num_winners,
+            total_num_winning_tickets: num_winners,
+        },
+    };
+    let winners_input = (0..num_winners)
+        .map(|index| WinnerInput {
+            index,
+            address: Pubkey::new_unique(),
+            tier: 3,
+            num_winning_tickets: 1,
+        })
+        .collect();
+    (meta_args, winners_input)
+}
+
+#[tokio::test]
+async fn test_publish_winners_in_multiple_pages() -> Result<()> {
+    let (ctx, _, epoch_service) = setup_service_with_tier(3).await;
+    let solana = ctx.solana;
+    let epoch_index = solana.get_latest_epoch().await?.index;
+
+    // 3 pages, more than fit in a transaction
+    let (meta_args, winners_input) = tier3_winners(25);
+    let signature = solana
+        .publish_winners(epoch_index, true, &meta_args, &winners_input)
+        .await?;
+    assert!(signature.is_some());
+
+    let epoch_winners = epoch_service.read_epoch_prizes(epoch_index).await?.unwrap();
+    let addresses = epoch_winners
+        .winners
+        .iter()
+        .map(|winner| winner.address)
+        .collect::<Vec<_>>();
+    let expected = winners_input.iter().map(|winner| winner.address).collect::<Vec<_>>();
+    assert_eq!(addresses, expected);
+
+    // Nothing left to send
+    let signature = solana
+        .publish_winners(epoch_index, true, &meta_args, &winners_input)
+        .await?;
+    assert_eq!(signature, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_publish_winners_resumes_from_published_pages() -> Result<()> {
+    let (ctx, _, epoch_service) = setup_service_with_tier(3).await;
+    let solana = ctx.solana;
+    let epoch_index = solana.get_latest_epoch().await?.index;
+    let admin_pubkey = ctx.admin_keypair.pubkey();
+
+    // A previous attempt created the meta and published the first page only
+    let (meta_args, winners_input) = tier3_winners(25);
+    solana
+        .rpc_client
+        .send_and_confirm_transaction(
+            &ctx.admin_keypair,
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(250_000),
+                instruction::create_epoch_winners_meta(
+                    &solana.program_id,
+                    &admin_pubkey,
+                    epoch_index,
+                    meta_args.clone(),
+                    &solana.nezha_vrf_program_id(),
+                ),
+            ],
+        )
+        .await?;
+    solana
+        .rpc_client
+        .send_and_confirm_transaction(
+            &ctx.admin_keypair,
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(700_000),
+                instruction::publish_winners(
+                    &solana.program_id,
+                    &admin_pubkey,
+                    epoch_index,
+                    0,
+                    winners_input[..10].to_vec(),
+                    &solana.nezha_vrf_program_id(),
+                ),
+            ],
+        )
+        .await?;
+
+    // The meta isn't created again and the first page isn't sent again, which the program would both refuse
+    solana
+        .publish_winners(epoch_index, true, &meta_args, &winners_input)
+        .await?;
+
+    let epoch_winners = epoch_service.read_epoch_prizes(epoch_index).await?.unwrap();
+    assert_eq!(epoch_winners.winners.len(), winners_input.len());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_multiple_winning_tickets() -> Result<()> {
     let ctx = common::setup_solana().await;
     let solana = &ctx.solana;
 
     let user_pubkey = ctx.user_keypair.pubkey();
-    let epoch_service = new_epoch_svc(Box::new(solana.clone()));
+    let epoch_service = new_epoch_svc(solana);
 
-    let ticket_repo = Box::new(InMemoryTicketRepository::new(0));
+    let ticket_repo = InMemoryTicketRepository::new(0);
     let rng = Arc::new(Mutex::new(StdRng::from_entropy()));
 
     // start with a fresh epoch
@@ -446,9 +751,9 @@ async fn test_multiple_winning_tickets() -> Result<()> {
     }
     let tier3_winning_tickets_count = (n_winners + 2) as u64;
 
-    let ticket_service = DefaultTicketService::new(
+    let ticket_service = new_ticket_svc(
         rng.clone(),
-        Box::new(solana.clone()),
+        solana,
         ticket_repo,
         Box::new(MockTicketPriceCalculator {}),
         Box::new(MockBonusInfoService {}),
@@ -465,7 +770,7 @@ async fn test_multiple_winning_tickets() -> Result<()> {
     publish_winning_combination(solana, latest_epoch.index, winning_combination).await?;
 
     let winners = ticket_service.calculate_winners().await?;
-    let _epoch = epoch_service.publish_winners(winners).await?;
+    let _epoch = epoch_service.publish_winners(winners, "test").await?;
     let epoch_winners = epoch_service.read_epoch_prizes(latest_epoch.index).await?.unwrap();
 
     assert!(
@@ -521,57 +826,14 @@ struct MockTicketPriceCalculator {}
 
 #[async_trait]
 impl TicketPriceCalculator for MockTicketPriceCalculator {
-    async fn calculate(&self, _balance: FPUSDC) -> TicketPrice {
+    async fn calculate(&self, _wallet: &Pubkey, _balance: FPUSDC) -> Result<TicketPrice> {
         todo!()
     }
-    async fn price(&self) -> FPUSDC {
+    async fn price(&self) -> Result<FPUSDC> {
         todo!()
     }
-}
-
-#[derive(Clone)]
-struct InMemoryEpochRepository {
-    epochs: Arc<RwLock<Vec<Epoch>>>,
-}
-
-impl InMemoryEpochRepository {
-    pub fn new() -> Self {
-        Self {
-            epochs: Arc::new(RwLock::new(vec![])),
-        }
-    }
-}
-
-#[async_trait]
-impl EpochRepository for InMemoryEpochRepository {
-    async fn by_index(&self, index: u64) -> Result<Option<Epoch>> {
-        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
-        Ok(epochs.iter().find(|epoch| epoch.index == index).cloned())
-    }
-
-    async fn by_pubkey(&self, pubkey: &Pubkey) -> Result<Option<Epoch>> {
-        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
-        Ok(epochs.iter().find(|epoch| epoch.pubkey == *pubkey).cloned())
-    }
-
-    async fn all(&self) -> Result<Vec<Epoch>> {
-        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
-        Ok(epochs.clone())
-    }
-
-    async fn latest_epoch(&self) -> Result<Option<Epoch>> {
-        let epochs = self.epochs.read().map_err(|_| anyhow!("cannot read epochs"))?;
-        Ok(epochs.iter().max_by_key(|epoch| epoch.index).cloned())
-    }
-
-    async fn create_or_update_epoch(&self, epoch: &Epoch) -> Result<Epoch> {
-        let mut epochs = self.epochs.write().map_err(|_| anyhow!("cannot write epochs"))?;
-        let e = epochs.iter_mut().find(|e| e.index == epoch.index);
-        match e {
-            Some(e) => *e = epoch.clone(),
-            None => epochs.push(epoch.clone()),
-        };
-        Ok(epoch.clone())
+    async fn policy(&self) -> Result<TicketPricingPolicy> {
+        todo!()
     }
 }
 
@@ -3,5 +3,6 @@ mod common;
 mod epoch;
 mod faucet;
 mod prize;
+mod scenario;
 mod stake;
 mod transaction;
@@ -0,0 +1,85 @@
+use anyhow::Result;
+use nezha_staking::fixed_point::test_utils::usdc;
+use service::{
+    model::scenario::{Payout, Scenario, ScenarioWallet},
+    scenario::ScenarioRunner,
+};
+
+#[tokio::test]
+async fn test_scenario_runner_reports_payouts() -> Result<()> {
+    let scenario = Scenario::from_json(
+        r#"{
+            "seed": 42,
+            "ticket_price": "25",
+            "yield_split_cfg": {
+                "jackpot": "1000",
+                "insurance_premium": "1",
+                "insurance_probability": "0.0001",
+                "treasury_ratio": "0.5",
+                "tier2_prize_share": 1,
+                "tier3_prize_share": 1
+            },
+            "epochs": [
+                {
+                    "deposits": [{ "wallet": "alice", "amount": "100" }, { "wallet": "bob", "amount": "100" }],
+                    "yield_percent": 10,
+                    "draw": { "kind": "winner", "tier": 1, "wallet": "alice" },
+                    "fund_jackpot": true
+                },
+                { "yield_percent": 10, "draw": { "kind": "no_winner" } },
+                { "yield_percent": 10, "draw": { "kind": "winner", "tier": 1, "wallet": "bob" } },
+                { "withdrawals": [{ "wallet": "bob", "amount": "50" }], "yield_percent": -20 }
+            ]
+        }"#,
+    )?;
+
+    let report = ScenarioRunner::new(scenario.clone()).await?.run().await?;
+    assert_eq!(report, ScenarioRunner::new(scenario).await?.run().await?);
+
+    let alice = ScenarioWallet("alice".into());
+    let bob = ScenarioWallet("bob".into());
+    let funded_jackpot = &report.epochs[0];
+    assert_eq!(funded_jackpot.epoch_index, 1);
+    assert_eq!(funded_jackpot.num_tickets, 8);
+    assert_eq!(funded_jackpot.insurance, usdc("0.8"));
+    assert_eq!(funded_jackpot.treasury, usdc("9.6"));
+    assert!(funded_jackpot.draw_enabled);
+    assert!(funded_jackpot.payouts.contains(&Payout {
+        wallet: alice.clone(),
+        address: alice.pubkey().to_string(),
+        tier: 1,
+        num_winning_tickets: 1,
+        prize: usdc("1000"),
+        claimable: true,
+    }));
+
+    let no_winner = &report.epochs[1];
+    assert!(no_winner.draw_enabled);
+    assert!(no_winner.payouts.is_empty());
+    assert!(no_winner.pending_tier2_prize >= no_winner.tier2_prize);
+
+    // The jackpot isn't funded, so the program doesn't let bob claim it
+    let unfunded_jackpot = &report.epochs[2];
+    assert!(unfunded_jackpot.payouts.contains(&Payout {
+        wallet: bob.clone(),
+        address: bob.pubkey().to_string(),
+        tier: 1,
+        num_winning_tickets: 1,
+        prize: usdc("1000"),
+        claimable: false,
+    }));
+
+    let loss = &report.epochs[3];
+    assert_eq!(loss.num_tickets, 6);
+    assert_eq!(loss.return_amount, usdc("120"));
+    assert_eq!(loss.deposit_back, usdc("120"));
+    assert!(!loss.draw_enabled);
+    assert_eq!(loss.winning_combination, None);
+    let balances = report
+        .balances
+        .iter()
+        .map(|balance| (balance.wallet.0.as_str(), balance.balance))
+        .collect::<Vec<_>>();
+    assert_eq!(balances, vec![("alice", usdc("80")), ("bob", usdc("40"))]);
+    Ok(())
+}
@@ -3,10 +3,12 @@ use anyhow::Result;
 use async_trait::async_trait;
 use rand::Rng;
 use service::{
+    events::EventBus,
     model::{
         stake_update::StakeUpdate,
         transaction::{Transaction, TransactionId},
     },
+    screening::NoScreening,
     stake::{DefaultStakeService, StakeService, StakeUpdateRepository, TransactionDecoder},
 };
 use solana_sdk::pubkey::Pubkey;
@@ -68,6 +70,8 @@ async fn all_stakes() {
     let stake_svc = DefaultStakeService::new(
         Box::new(solana.clone()),
         Box::new(InMemoryStakeUpdateRepository::default()),
+        Box::new(NoScreening),
+        EventBus::default(),
     );
 
     let user_keypair = ctx.user_keypair;
@@ -97,6 +101,8 @@ async fn all_stake_update_requests() -> Result<()> {
     let stake_svc = DefaultStakeService::new(
         Box::new(solana.clone()),
         Box::new(InMemoryStakeUpdateRepository::default()),
+        Box::new(NoScreening),
+        EventBus::default(),
     );
 
     let user_keypair = ctx.user_keypair;
@@ -2,6 +2,7 @@ use std::sync::RwLock;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use service::{
     epoch::FPUSDC,
     model::transaction::{Transaction, TransactionId, TransactionType},
@@ -20,11 +21,16 @@ impl UserTransactionRepository for InMemoryTransactionRepository {
         &self,
         transaction_id: &TransactionId,
         instruction_index: u8,
+        inner_instruction_index: Option<u8>,
     ) -> Result<Option<Transaction>> {
         let transactions = self.mem.read().unwrap();
         Ok(transactions
             .iter()
-            .find(|t| t.transaction_id == *transaction_id && t.instruction_index == instruction_index)
+            .find(|t| {
+                t.transaction_id == *transaction_id
+                    && t.instruction_index == instruction_index
+                    && t.inner_instruction_index == inner_instruction_index
+            })
             .cloned())
     }
 
@@ -96,6 +102,13 @@ impl UserTransactionRepository for InMemoryTransactionRepository {
         Ok(())
     }
 
+    async fn remove_unfinalized(&self, up_to_slot: u64) -> Result<u64> {
+        let mut transactions = self.mem.write().unwrap();
+        let len = transactions.len();
+        transactions.retain(|t| t.finalized || t.slot.map_or(true, |slot| slot > up_to_slot));
+        Ok((len - transactions.len()) as u64)
+    }
+
     async fn total_deposit_by_wallet(&self, wallet: &Pubkey) -> Result<FPUSDC> {
         let transactions = self.mem.read().unwrap();
         let total = transactions
@@ -108,4 +121,19 @@ impl UserTransactionRepository for InMemoryTransactionRepository {
             })?;
         Ok(total)
     }
+
+    async fn balance_changes_by_wallet(&self, wallet: &Pubkey, since: DateTime<Utc>) -> Result<Vec<Transaction>> {
+        let transactions = self.mem.read().unwrap();
+        let mut changes = transactions
+            .iter()
+            .filter(|t| &t.wallet == wallet && t.time.map_or(false, |time| time > since))
+            .filter(|t| {
+                t.transaction_type == TransactionType::DepositCompleted
+                    || t.transaction_type == TransactionType::WithdrawCompleted
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|t| t.time);
+        Ok(changes)
+    }
 }
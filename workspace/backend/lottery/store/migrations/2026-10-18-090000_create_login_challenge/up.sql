@@ -0,0 +1,7 @@
+CREATE TABLE login_challenge (
+    wallet VARCHAR NOT NULL,
+    nonce VARCHAR NOT NULL,
+    issued_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (wallet, nonce)
+);
@@ -0,0 +1 @@
+DROP TABLE stake_update_review;
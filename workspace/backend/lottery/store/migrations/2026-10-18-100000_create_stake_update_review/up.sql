@@ -0,0 +1,14 @@
+CREATE TABLE stake_update_review(
+    id UUID PRIMARY KEY,
+    wallet VARCHAR NOT NULL,
+    amount VARCHAR NOT NULL,
+    type VARCHAR NOT NULL,
+    reason VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    reviewed_by VARCHAR,
+    reviewed_at TIMESTAMPTZ
+);
+
+-- A wallet has at most one request on chain, so at most one pending review.
+CREATE UNIQUE INDEX stake_update_review_pending_wallet ON stake_update_review(wallet) WHERE status = 'pending';
@@ -0,0 +1,12 @@
+CREATE TABLE epoch_audit_log(
+    id UUID PRIMARY KEY,
+    operator VARCHAR NOT NULL,
+    operation VARCHAR NOT NULL,
+    params JSONB NOT NULL,
+    dry_run BOOLEAN NOT NULL,
+    signature VARCHAR,
+    error VARCHAR,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX epoch_audit_log_created_at ON epoch_audit_log(created_at);
@@ -0,0 +1,14 @@
+CREATE TABLE epoch_job(
+    epoch_index NUMERIC(20, 0) NOT NULL, -- 20 digits, 0 decimals, to accommodate u64.
+    command VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error VARCHAR,
+    due_at TIMESTAMPTZ NOT NULL,
+    next_attempt_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (epoch_index, command)
+);
+
+CREATE INDEX epoch_job_updated_at ON epoch_job(updated_at);
@@ -0,0 +1,17 @@
+CREATE TABLE notification_preference(
+    wallet VARCHAR PRIMARY KEY,
+    email VARCHAR,
+    webhook_url VARCHAR,
+    kinds VARCHAR[] NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX notification_preference_kinds ON notification_preference USING GIN (kinds);
+
+CREATE TABLE notification_delivery(
+    event_key VARCHAR NOT NULL,
+    wallet VARCHAR NOT NULL,
+    channel VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (event_key, wallet, channel)
+);
@@ -0,0 +1,9 @@
+DROP INDEX user_transaction_unfinalized;
+
+DELETE FROM user_transaction WHERE inner_instruction_index IS NOT NULL;
+DROP INDEX user_transaction_instruction;
+ALTER TABLE user_transaction ADD PRIMARY KEY (transaction_id, instruction_index);
+
+ALTER TABLE user_transaction DROP COLUMN finalized;
+ALTER TABLE user_transaction DROP COLUMN slot;
+ALTER TABLE user_transaction DROP COLUMN inner_instruction_index;
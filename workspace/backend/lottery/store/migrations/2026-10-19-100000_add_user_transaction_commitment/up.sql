@@ -0,0 +1,9 @@
+ALTER TABLE user_transaction ADD COLUMN inner_instruction_index SMALLINT;
+ALTER TABLE user_transaction ADD COLUMN slot BIGINT;
+ALTER TABLE user_transaction ADD COLUMN finalized BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- Staking instructions invoked through CPI share the index of the top level instruction
+ALTER TABLE user_transaction DROP CONSTRAINT user_transaction_pkey;
+CREATE UNIQUE INDEX user_transaction_instruction ON user_transaction (transaction_id, instruction_index, (COALESCE(inner_instruction_index, -1)));
+
+CREATE INDEX user_transaction_unfinalized ON user_transaction (slot) WHERE NOT finalized;
@@ -0,0 +1,2 @@
+DROP TABLE program_account_check;
+DROP TABLE program_account;
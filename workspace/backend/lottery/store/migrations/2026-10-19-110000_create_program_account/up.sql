@@ -0,0 +1,15 @@
+CREATE TABLE program_account(
+    pubkey VARCHAR PRIMARY KEY,
+    account_type VARCHAR NOT NULL,
+    lamports NUMERIC(20, 0) NOT NULL, -- 20 digits, 0 decimals, to accommodate u64.
+    data BYTEA NOT NULL,
+    slot BIGINT NOT NULL
+);
+
+CREATE INDEX program_account_account_type ON program_account(account_type);
+
+-- Single row: when the mirror was last reconciled with the chain
+CREATE TABLE program_account_check(
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    checked_at TIMESTAMPTZ NOT NULL
+);
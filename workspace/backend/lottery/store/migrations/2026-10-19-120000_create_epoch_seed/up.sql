@@ -0,0 +1,8 @@
+-- Block hash of the slot each epoch account was created in, tickets are derived from it
+CREATE TABLE epoch_seed(
+    epoch_index NUMERIC(20, 0) PRIMARY KEY, -- 20 digits, 0 decimals, to accommodate u64.
+    epoch_pubkey VARCHAR NOT NULL,
+    slot BIGINT NOT NULL,
+    slot_hash VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,17 @@
+DROP INDEX idx_sequences_wallet_epoch_index;
+DROP INDEX idx_sequences_epoch_index_packed;
+
+ALTER TABLE sequences
+    ADD COLUMN _1 SMALLINT, ADD COLUMN _2 SMALLINT, ADD COLUMN _3 SMALLINT,
+    ADD COLUMN _4 SMALLINT, ADD COLUMN _5 SMALLINT, ADD COLUMN _6 SMALLINT;
+UPDATE sequences
+SET _1 = (packed >> 40) & 255, _2 = (packed >> 32) & 255, _3 = (packed >> 24) & 255,
+    _4 = (packed >> 16) & 255, _5 = (packed >> 8) & 255, _6 = packed & 255;
+ALTER TABLE sequences
+    ALTER COLUMN _1 SET NOT NULL, ALTER COLUMN _2 SET NOT NULL, ALTER COLUMN _3 SET NOT NULL,
+    ALTER COLUMN _4 SET NOT NULL, ALTER COLUMN _5 SET NOT NULL, ALTER COLUMN _6 SET NOT NULL;
+ALTER TABLE sequences DROP COLUMN packed;
+
+CREATE INDEX idx_epoch_index_4_prefix ON sequences (epoch_index, _1, _2, _3, _4);
+
+DROP FUNCTION unpack_sequence;
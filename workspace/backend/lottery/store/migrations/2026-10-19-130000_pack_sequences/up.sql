@@ -0,0 +1,21 @@
+-- Sequences are packed into the low 48 bits of a BIGINT, one byte per number with the first number in the most
+-- significant byte, so the sequences sharing a prefix are a contiguous range of the (epoch_index, packed) index.
+CREATE FUNCTION unpack_sequence(packed BIGINT) RETURNS SMALLINT[]
+    LANGUAGE SQL IMMUTABLE STRICT PARALLEL SAFE
+    AS $$
+        SELECT ARRAY[
+            (packed >> 40) & 255, (packed >> 32) & 255, (packed >> 24) & 255,
+            (packed >> 16) & 255, (packed >> 8) & 255, packed & 255
+        ]::SMALLINT[]
+    $$;
+
+ALTER TABLE sequences ADD COLUMN packed BIGINT;
+UPDATE sequences
+SET packed = (_1::BIGINT << 40) | (_2::BIGINT << 32) | (_3::BIGINT << 24) | (_4::BIGINT << 16) | (_5::BIGINT << 8) | _6::BIGINT;
+ALTER TABLE sequences ALTER COLUMN packed SET NOT NULL;
+
+DROP INDEX idx_epoch_index_4_prefix;
+ALTER TABLE sequences DROP COLUMN _1, DROP COLUMN _2, DROP COLUMN _3, DROP COLUMN _4, DROP COLUMN _5, DROP COLUMN _6;
+
+CREATE INDEX idx_sequences_epoch_index_packed ON sequences (epoch_index, packed);
+CREATE INDEX idx_sequences_wallet_epoch_index ON sequences (wallet, epoch_index);
@@ -0,0 +1,3 @@
+DROP TABLE referral;
+DROP TABLE campaign_grant;
+DROP TABLE campaign;
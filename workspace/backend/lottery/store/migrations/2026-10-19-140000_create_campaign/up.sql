@@ -0,0 +1,34 @@
+CREATE TABLE campaign(
+    id UUID PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    start_epoch NUMERIC(20, 0) NOT NULL, -- 20 digits, 0 decimals, to accommodate u64.
+    end_epoch NUMERIC(20, 0), -- Inclusive, open ended if NULL.
+    min_stake VARCHAR,
+    new_users_only BOOLEAN NOT NULL,
+    min_draws_played BIGINT,
+    referred_only BOOLEAN NOT NULL,
+    reward_type VARCHAR NOT NULL,
+    reward_value VARCHAR NOT NULL,
+    max_sequences_per_wallet BIGINT,
+    active BOOLEAN NOT NULL,
+    created_by VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Sequences each campaign granted each wallet, at most once per epoch.
+CREATE TABLE campaign_grant(
+    campaign_id UUID NOT NULL REFERENCES campaign(id),
+    wallet VARCHAR NOT NULL,
+    epoch_index NUMERIC(20, 0) NOT NULL,
+    num_sequences BIGINT NOT NULL,
+    granted_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (campaign_id, wallet, epoch_index)
+);
+
+CREATE INDEX campaign_grant_wallet ON campaign_grant(wallet, epoch_index);
+
+CREATE TABLE referral(
+    wallet VARCHAR PRIMARY KEY,
+    referrer VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
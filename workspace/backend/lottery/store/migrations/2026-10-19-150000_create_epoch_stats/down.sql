@@ -0,0 +1 @@
+DROP TABLE epoch_stats;
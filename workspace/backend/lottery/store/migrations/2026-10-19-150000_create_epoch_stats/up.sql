@@ -0,0 +1,6 @@
+-- Stats of the ended epochs, computed from their tickets and prizes.
+CREATE TABLE epoch_stats(
+    epoch_index NUMERIC(20, 0) PRIMARY KEY, -- 20 digits, 0 decimals, to accommodate u64.
+    stats JSONB NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE notification_preference DROP COLUMN webhook_secret;
@@ -0,0 +1,2 @@
+-- Signs the requests to the webhook. Webhooks set up before have none and need to be set up again.
+ALTER TABLE notification_preference ADD COLUMN webhook_secret VARCHAR;
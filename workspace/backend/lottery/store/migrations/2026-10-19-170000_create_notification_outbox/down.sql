@@ -0,0 +1,11 @@
+DROP INDEX notification_delivery_due;
+
+ALTER TABLE notification_delivery
+    DROP COLUMN subject,
+    DROP COLUMN body,
+    DROP COLUMN payload,
+    DROP COLUMN status,
+    DROP COLUMN attempts,
+    DROP COLUMN last_error,
+    DROP COLUMN next_attempt_at,
+    DROP COLUMN updated_at;
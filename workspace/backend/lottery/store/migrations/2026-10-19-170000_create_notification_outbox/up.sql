@@ -0,0 +1,21 @@
+-- Deliveries are queued and sent by the notification worker. The deliveries recorded before were sent.
+ALTER TABLE notification_delivery
+    ADD COLUMN subject VARCHAR NOT NULL DEFAULT '',
+    ADD COLUMN body VARCHAR NOT NULL DEFAULT '',
+    ADD COLUMN payload JSONB NOT NULL DEFAULT '{}',
+    ADD COLUMN status VARCHAR NOT NULL DEFAULT 'sent',
+    ADD COLUMN attempts INT NOT NULL DEFAULT 1,
+    ADD COLUMN last_error VARCHAR,
+    ADD COLUMN next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+ALTER TABLE notification_delivery
+    ALTER COLUMN subject DROP DEFAULT,
+    ALTER COLUMN body DROP DEFAULT,
+    ALTER COLUMN payload DROP DEFAULT,
+    ALTER COLUMN status DROP DEFAULT,
+    ALTER COLUMN attempts DROP DEFAULT,
+    ALTER COLUMN next_attempt_at DROP DEFAULT,
+    ALTER COLUMN updated_at DROP DEFAULT;
+
+CREATE INDEX notification_delivery_due ON notification_delivery (next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1 @@
+ALTER TABLE program_account_check DROP COLUMN subscribed_at;
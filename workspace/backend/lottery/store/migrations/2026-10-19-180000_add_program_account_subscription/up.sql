@@ -0,0 +1,2 @@
+-- When the accounts indexer last saw its websocket subscription alive
+ALTER TABLE program_account_check ADD COLUMN subscribed_at TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- URLs of the uploaded epoch manifests, reused when entering investment is retried
+CREATE TABLE published_manifest(
+    epoch_index NUMERIC(20, 0) NOT NULL, -- 20 digits, 0 decimals, to accommodate u64.
+    manifest_hash VARCHAR NOT NULL,
+    url VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (epoch_index, manifest_hash)
+);
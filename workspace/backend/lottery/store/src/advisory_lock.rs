@@ -0,0 +1,57 @@
+//! Leader election between replicas with Postgres session level advisory locks.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use deadpool_postgres::{Client, ClientWrapper, Pool};
+
+use crate::get_client;
+
+/// A session level advisory lock, held for as long as its connection lives.
+///
+/// The connection is detached from the pool, so dropping the lock closes it and releases the lock.
+pub struct AdvisoryLock {
+    key: i64,
+    client: ClientWrapper,
+}
+
+impl AdvisoryLock {
+    /// Takes the lock if no other session holds it.
+    pub async fn try_acquire(pool: &Pool, key: i64) -> Result<Option<Self>> {
+        let client = Client::take(get_client(pool).await?);
+        let row = client.query_one("SELECT pg_try_advisory_lock($1)", &[&key]).await?;
+        if row.get::<_, bool>(0) {
+            Ok(Some(Self { key, client }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Waits until the lock is taken, trying every `retry_interval`.
+    pub async fn acquire(pool: &Pool, key: i64, retry_interval: Duration) -> Result<Self> {
+        loop {
+            if let Some(lock) = Self::try_acquire(pool, key).await? {
+                return Ok(lock);
+            }
+            log::info!("Advisory lock {} is held by another session, waiting", key);
+            tokio::time::sleep(retry_interval).await;
+        }
+    }
+
+    /// Resolves when the connection holding the lock fails, since the lock is released with it.
+    pub async fn lost(&self, check_interval: Duration) -> anyhow::Error {
+        loop {
+            tokio::time::sleep(check_interval).await;
+            if let Err(e) = self.client.simple_query("SELECT 1").await {
+                return anyhow::anyhow!("Lost advisory lock {}: {}", self.key, e);
+            }
+        }
+    }
+
+    pub async fn release(self) -> Result<()> {
+        self.client
+            .query_one("SELECT pg_advisory_unlock($1)", &[&self.key])
+            .await?;
+        Ok(())
+    }
+}
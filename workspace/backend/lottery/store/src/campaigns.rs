@@ -0,0 +1,249 @@
+use crate::get_client;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use deadpool_postgres::Pool;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use service::{
+    campaign::CampaignRepository,
+    model::campaign::{Campaign, CampaignEligibility, CampaignGrant, CampaignReward, Referral},
+    solana::FPUSDC,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PostgresCampaignRepository {
+    pool: Pool,
+}
+
+impl PostgresCampaignRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CampaignRepository for PostgresCampaignRepository {
+    async fn create(&self, campaign: &Campaign) -> Result<Campaign> {
+        let client = get_client(&self.pool).await?;
+        let eligibility = &campaign.eligibility;
+        let row = client
+            .query_one(
+                r#"
+            INSERT INTO campaign(
+                id,
+                name,
+                start_epoch,
+                end_epoch,
+                min_stake,
+                new_users_only,
+                min_draws_played,
+                referred_only,
+                reward_type,
+                reward_value,
+                max_sequences_per_wallet,
+                active,
+                created_by,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14) RETURNING *
+            "#,
+                &[
+                    &campaign.id,
+                    &campaign.name,
+                    &Decimal::from(campaign.start_epoch),
+                    &campaign.end_epoch.map(Decimal::from),
+                    &eligibility.min_stake.map(|min_stake| min_stake.to_string()),
+                    &eligibility.new_users_only,
+                    &eligibility.min_draws_played.map(|count| count as i64),
+                    &eligibility.referred_only,
+                    &campaign.reward.type_name(),
+                    &campaign.reward.value(),
+                    &campaign.max_sequences_per_wallet.map(|count| count as i64),
+                    &campaign.active,
+                    &campaign.created_by,
+                    &campaign.created_at,
+                ],
+            )
+            .await?;
+        parse_campaign(row)
+    }
+
+    async fn all(&self) -> Result<Vec<Campaign>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client.query("SELECT * FROM campaign ORDER BY created_at", &[]).await?;
+        rows.into_iter().map(parse_campaign).collect()
+    }
+
+    async fn running_by_epoch_index(&self, epoch_index: u64) -> Result<Vec<Campaign>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                r#"
+            SELECT * FROM campaign
+            WHERE active AND start_epoch <= $1 AND (end_epoch IS NULL OR end_epoch >= $1)
+            ORDER BY created_at
+            "#,
+                &[&Decimal::from(epoch_index)],
+            )
+            .await?;
+        rows.into_iter().map(parse_campaign).collect()
+    }
+
+    async fn set_active(&self, id: &Uuid, active: bool) -> Result<Option<Campaign>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "UPDATE campaign SET active = $2 WHERE id = $1 RETURNING *",
+                &[id, &active],
+            )
+            .await?;
+        row.map(parse_campaign).transpose()
+    }
+
+    async fn create_grant(&self, grant: &CampaignGrant) -> Result<Option<CampaignGrant>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                r#"
+            INSERT INTO campaign_grant(campaign_id, wallet, epoch_index, num_sequences, granted_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (campaign_id, wallet, epoch_index) DO NOTHING
+            RETURNING *
+            "#,
+                &[
+                    &grant.campaign_id,
+                    &grant.wallet.to_string(),
+                    &Decimal::from(grant.epoch_index),
+                    &(grant.num_sequences as i64),
+                    &grant.granted_at,
+                ],
+            )
+            .await?;
+        row.map(parse_grant).transpose()
+    }
+
+    async fn grants_by_campaign(&self, campaign_id: &Uuid, limit: usize, offset: usize) -> Result<Vec<CampaignGrant>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM campaign_grant WHERE campaign_id = $1 ORDER BY granted_at LIMIT $2 OFFSET $3",
+                &[campaign_id, &(limit as i64), &(offset as i64)],
+            )
+            .await?;
+        rows.into_iter().map(parse_grant).collect()
+    }
+
+    async fn grants_by_wallet(&self, wallet: &Pubkey) -> Result<Vec<CampaignGrant>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM campaign_grant WHERE wallet = $1 ORDER BY epoch_index, granted_at",
+                &[&wallet.to_string()],
+            )
+            .await?;
+        rows.into_iter().map(parse_grant).collect()
+    }
+
+    async fn grants_by_wallet_and_epoch_index(&self, wallet: &Pubkey, epoch_index: u64) -> Result<Vec<CampaignGrant>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM campaign_grant WHERE wallet = $1 AND epoch_index = $2 ORDER BY granted_at",
+                &[&wallet.to_string(), &Decimal::from(epoch_index)],
+            )
+            .await?;
+        rows.into_iter().map(parse_grant).collect()
+    }
+
+    async fn num_sequences_by_campaign_and_wallet(&self, campaign_id: &Uuid, wallet: &Pubkey) -> Result<u64> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_one(
+                "SELECT COALESCE(SUM(num_sequences), 0)::BIGINT FROM campaign_grant WHERE campaign_id = $1 AND wallet = $2",
+                &[campaign_id, &wallet.to_string()],
+            )
+            .await?;
+        Ok(row.get::<_, i64>(0) as u64)
+    }
+
+    async fn referral_by_wallet(&self, wallet: &Pubkey) -> Result<Option<Referral>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt("SELECT * FROM referral WHERE wallet = $1", &[&wallet.to_string()])
+            .await?;
+        row.map(parse_referral).transpose()
+    }
+
+    async fn create_referral(&self, referral: &Referral) -> Result<Option<Referral>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                r#"
+            INSERT INTO referral(wallet, referrer, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (wallet) DO NOTHING
+            RETURNING *
+            "#,
+                &[
+                    &referral.wallet.to_string(),
+                    &referral.referrer.to_string(),
+                    &referral.created_at,
+                ],
+            )
+            .await?;
+        row.map(parse_referral).transpose()
+    }
+}
+
+fn to_u64(decimal: Decimal, column: &str) -> Result<u64> {
+    decimal.to_u64().ok_or(anyhow!("cannot convert {} to u64", column))
+}
+
+fn parse_campaign(row: Row) -> Result<Campaign> {
+    Ok(Campaign {
+        id: row.get("id"),
+        name: row.get("name"),
+        start_epoch: to_u64(row.get("start_epoch"), "start_epoch")?,
+        end_epoch: row
+            .get::<_, Option<Decimal>>("end_epoch")
+            .map(|end_epoch| to_u64(end_epoch, "end_epoch"))
+            .transpose()?,
+        eligibility: CampaignEligibility {
+            min_stake: row
+                .get::<_, Option<&str>>("min_stake")
+                .map(|min_stake| FPUSDC::from_str(min_stake).map_err(|s| anyhow!(s)))
+                .transpose()?,
+            new_users_only: row.get("new_users_only"),
+            min_draws_played: row.get::<_, Option<i64>>("min_draws_played").map(|count| count as u64),
+            referred_only: row.get("referred_only"),
+        },
+        reward: CampaignReward::parse(row.get("reward_type"), row.get("reward_value"))?,
+        max_sequences_per_wallet: row
+            .get::<_, Option<i64>>("max_sequences_per_wallet")
+            .map(|count| count as u32),
+        active: row.get("active"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn parse_grant(row: Row) -> Result<CampaignGrant> {
+    Ok(CampaignGrant {
+        campaign_id: row.get("campaign_id"),
+        wallet: Pubkey::from_str(row.get("wallet"))?,
+        epoch_index: to_u64(row.get("epoch_index"), "epoch_index")?,
+        num_sequences: row.get::<_, i64>("num_sequences") as u32,
+        granted_at: row.get("granted_at"),
+    })
+}
+
+fn parse_referral(row: Row) -> Result<Referral> {
+    Ok(Referral {
+        wallet: Pubkey::from_str(row.get("wallet"))?,
+        referrer: Pubkey::from_str(row.get("referrer"))?,
+        created_at: row.get("created_at"),
+    })
+}
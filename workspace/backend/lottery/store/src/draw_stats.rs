@@ -0,0 +1,330 @@
+use crate::{
+    epochs::{epoch_status_from_str, epoch_status_to_str},
+    get_client,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+use service::{
+    draw_stats::DrawStatsRepository,
+    model::{
+        draw_stats::{
+            EpochStats, NumberFrequency, PrefixCount, SequenceTypeCount, TierPrizes, WinningCombination,
+            MAX_SHARED_PREFIXES, SHARED_PREFIX_LENGTHS,
+        },
+        ticket::SEQUENCE_LENGTH,
+    },
+    tickets::{unpack_sequence, SequenceType},
+};
+use std::str::FromStr;
+use tokio_postgres::Row;
+
+/// Cached stats, as stored in the `stats` column.
+#[derive(Serialize, Deserialize)]
+struct CachedStats {
+    status: String,
+    num_players: u64,
+    sequences_by_type: Vec<(SequenceType, u64)>,
+    main_number_frequencies: Vec<(u8, u64)>,
+    last_number_frequencies: Vec<(u8, u64)>,
+    shared_prefixes: Vec<(Vec<u8>, u64, u64)>,
+    prizes_by_tier: Vec<(u8, u64, String)>,
+}
+
+impl From<&EpochStats> for CachedStats {
+    fn from(stats: &EpochStats) -> Self {
+        Self {
+            status: epoch_status_to_str(stats.status).to_string(),
+            num_players: stats.num_players,
+            sequences_by_type: stats
+                .sequences_by_type
+                .iter()
+                .map(|count| (count.sequence_type, count.num_sequences))
+                .collect(),
+            main_number_frequencies: stats
+                .main_number_frequencies
+                .iter()
+                .map(|frequency| (frequency.number, frequency.num_sequences))
+                .collect(),
+            last_number_frequencies: stats
+                .last_number_frequencies
+                .iter()
+                .map(|frequency| (frequency.number, frequency.num_sequences))
+                .collect(),
+            shared_prefixes: stats
+                .shared_prefixes
+                .iter()
+                .map(|count| (count.prefix.clone(), count.num_tickets, count.num_sequences))
+                .collect(),
+            prizes_by_tier: stats
+                .prizes_by_tier
+                .iter()
+                .map(|prizes| (prizes.tier, prizes.num_winners, prizes.total_amount.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PostgresDrawStatsRepository {
+    pool: Pool,
+}
+
+impl PostgresDrawStatsRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DrawStatsRepository for PostgresDrawStatsRepository {
+    async fn compute_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        let client = get_client(&self.pool).await?;
+        let index = Decimal::from(epoch_index);
+
+        let status = match client
+            .query_opt("SELECT epoch_status FROM epoch WHERE epoch_index = $1", &[&index])
+            .await?
+        {
+            Some(row) => epoch_status_from_str(row.get("epoch_status"))?,
+            None => return Ok(None),
+        };
+
+        // Aggregated by the database, the sequences of the epoch are never loaded into memory
+        let row = client
+            .query_one(
+                "SELECT COUNT(DISTINCT wallet) FROM sequences WHERE epoch_index = $1",
+                &[&index],
+            )
+            .await?;
+        let num_players = row.get::<_, i64>(0) as u64;
+
+        let rows = client
+            .query(
+                "SELECT sequence_type, COUNT(*) AS num_sequences FROM sequences WHERE epoch_index = $1 GROUP BY sequence_type",
+                &[&index],
+            )
+            .await?;
+        let mut sequences_by_type = rows
+            .into_iter()
+            .map(|row| {
+                Ok(SequenceTypeCount {
+                    sequence_type: SequenceType::from_str(row.get("sequence_type"))?,
+                    num_sequences: row.get::<_, i64>("num_sequences") as u64,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        sequences_by_type.sort_by_key(|count| count.sequence_type);
+
+        // The first 5 numbers are the 5 most significant bytes of the packed sequence
+        let rows = client
+            .query(
+                "
+                SELECT (packed >> shift) & 255 AS number, COUNT(*) AS num_sequences
+                FROM sequences CROSS JOIN unnest(ARRAY[40, 32, 24, 16, 8]) AS shift
+                WHERE epoch_index = $1
+                GROUP BY number
+                ORDER BY number",
+                &[&index],
+            )
+            .await?;
+        let main_number_frequencies = rows.into_iter().map(parse_number_frequency).collect();
+
+        let rows = client
+            .query(
+                "
+                SELECT packed & 255 AS number, COUNT(*) AS num_sequences
+                FROM sequences
+                WHERE epoch_index = $1
+                GROUP BY number
+                ORDER BY number",
+                &[&index],
+            )
+            .await?;
+        let last_number_frequencies = rows.into_iter().map(parse_number_frequency).collect();
+
+        let mut shared_prefixes = Vec::new();
+        for prefix_len in SHARED_PREFIX_LENGTHS {
+            let shift = (8 * (SEQUENCE_LENGTH - prefix_len)) as i32;
+            let rows = client
+                .query(
+                    "
+                    SELECT packed >> $2 AS prefix, COUNT(DISTINCT wallet) AS num_tickets, COUNT(*) AS num_sequences
+                    FROM sequences
+                    WHERE epoch_index = $1
+                    GROUP BY prefix
+                    HAVING COUNT(DISTINCT wallet) > 1
+                    ORDER BY num_tickets DESC, prefix
+                    LIMIT $3",
+                    &[&index, &shift, &(MAX_SHARED_PREFIXES as i64)],
+                )
+                .await?;
+            shared_prefixes.extend(rows.into_iter().map(|row| PrefixCount {
+                prefix: unpack_sequence((row.get::<_, i64>("prefix") as u64) << shift)[..prefix_len].to_vec(),
+                num_tickets: row.get::<_, i64>("num_tickets") as u64,
+                num_sequences: row.get::<_, i64>("num_sequences") as u64,
+            }));
+        }
+        shared_prefixes.sort_by(|a, b| b.num_tickets.cmp(&a.num_tickets).then_with(|| a.prefix.cmp(&b.prefix)));
+        shared_prefixes.truncate(MAX_SHARED_PREFIXES);
+
+        let rows = client
+            .query(
+                "
+                SELECT tier, COUNT(*) AS num_winners, SUM(amount::NUMERIC)::VARCHAR AS total_amount
+                FROM prize
+                WHERE epoch_index = $1
+                GROUP BY tier
+                ORDER BY tier",
+                &[&index],
+            )
+            .await?;
+        let prizes_by_tier = rows
+            .into_iter()
+            .map(|row| {
+                Ok(TierPrizes {
+                    tier: u8::try_from(row.get::<_, i16>("tier"))?,
+                    num_winners: row.get::<_, i64>("num_winners") as u64,
+                    total_amount: row
+                        .get::<_, &str>("total_amount")
+                        .parse()
+                        .map_err(|e: String| anyhow!(e))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(EpochStats {
+            epoch_index,
+            status,
+            num_players,
+            sequences_by_type,
+            main_number_frequencies,
+            last_number_frequencies,
+            shared_prefixes,
+            prizes_by_tier,
+            computed_at: Utc::now(),
+        }))
+    }
+
+    async fn cached_by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochStats>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "SELECT * FROM epoch_stats WHERE epoch_index = $1",
+                &[&Decimal::from(epoch_index)],
+            )
+            .await?;
+        row.map(parse_epoch_stats).transpose()
+    }
+
+    async fn cache(&self, stats: &EpochStats) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                r#"
+            INSERT INTO epoch_stats(epoch_index, stats, computed_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (epoch_index) DO UPDATE SET stats = $2, computed_at = $3
+            "#,
+                &[
+                    &Decimal::from(stats.epoch_index),
+                    &serde_json::to_value(CachedStats::from(stats))?,
+                    &stats.computed_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn winning_combinations(&self, limit: usize, offset: usize) -> Result<Vec<WinningCombination>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                r#"
+            SELECT epoch_index, winning_combination FROM epoch
+            WHERE winning_combination IS NOT NULL
+            ORDER BY epoch_index DESC
+            LIMIT $1 OFFSET $2
+            "#,
+                &[&(limit as i64), &(offset as i64)],
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                let mut combination = [0; 6];
+                for (n, num) in combination
+                    .iter_mut()
+                    .zip(row.get::<_, [i16; 6]>("winning_combination"))
+                {
+                    *n = u8::try_from(num)?;
+                }
+                Ok(WinningCombination {
+                    epoch_index: to_u64(row.get("epoch_index"))?,
+                    combination,
+                })
+            })
+            .collect()
+    }
+}
+
+fn to_u64(decimal: Decimal) -> Result<u64> {
+    decimal.to_u64().ok_or(anyhow!("cannot convert epoch_index to u64"))
+}
+
+fn parse_number_frequency(row: Row) -> NumberFrequency {
+    NumberFrequency {
+        number: row.get::<_, i64>("number") as u8,
+        num_sequences: row.get::<_, i64>("num_sequences") as u64,
+    }
+}
+
+fn parse_epoch_stats(row: Row) -> Result<EpochStats> {
+    let stats: CachedStats = serde_json::from_value(row.get("stats"))?;
+    Ok(EpochStats {
+        epoch_index: to_u64(row.get("epoch_index"))?,
+        status: epoch_status_from_str(&stats.status)?,
+        num_players: stats.num_players,
+        sequences_by_type: stats
+            .sequences_by_type
+            .into_iter()
+            .map(|(sequence_type, num_sequences)| SequenceTypeCount {
+                sequence_type,
+                num_sequences,
+            })
+            .collect(),
+        main_number_frequencies: stats
+            .main_number_frequencies
+            .into_iter()
+            .map(|(number, num_sequences)| NumberFrequency { number, num_sequences })
+            .collect(),
+        last_number_frequencies: stats
+            .last_number_frequencies
+            .into_iter()
+            .map(|(number, num_sequences)| NumberFrequency { number, num_sequences })
+            .collect(),
+        shared_prefixes: stats
+            .shared_prefixes
+            .into_iter()
+            .map(|(prefix, num_tickets, num_sequences)| PrefixCount {
+                prefix,
+                num_tickets,
+                num_sequences,
+            })
+            .collect(),
+        prizes_by_tier: stats
+            .prizes_by_tier
+            .into_iter()
+            .map(|(tier, num_winners, total_amount)| {
+                Ok(TierPrizes {
+                    tier,
+                    num_winners,
+                    total_amount: total_amount.parse().map_err(|e: String| anyhow!(e))?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+        computed_at: row.get("computed_at"),
+    })
+}
@@ -0,0 +1,76 @@
+use crate::get_client;
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::Pool;
+use service::{epoch::EpochAuditRepository, model::epoch_audit::EpochAuditLog};
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct PostgresEpochAuditRepository {
+    pool: Pool,
+}
+
+impl PostgresEpochAuditRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EpochAuditRepository for PostgresEpochAuditRepository {
+    async fn record(&self, entry: &EpochAuditLog) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                r#"
+            INSERT INTO epoch_audit_log(
+                id,
+                operator,
+                operation,
+                params,
+                dry_run,
+                signature,
+                error,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+                &[
+                    &entry.id,
+                    &entry.operator,
+                    &entry.operation,
+                    &entry.params,
+                    &entry.dry_run,
+                    &entry.signature,
+                    &entry.error,
+                    &entry.created_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochAuditLog>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM epoch_audit_log ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+                &[&(limit as i64), &(offset as i64)],
+            )
+            .await?;
+        Ok(rows.into_iter().map(parse_row).collect())
+    }
+}
+
+fn parse_row(row: Row) -> EpochAuditLog {
+    EpochAuditLog {
+        id: row.get("id"),
+        operator: row.get("operator"),
+        operation: row.get("operation"),
+        params: row.get("params"),
+        dry_run: row.get("dry_run"),
+        signature: row.get("signature"),
+        error: row.get("error"),
+        created_at: row.get("created_at"),
+    }
+}
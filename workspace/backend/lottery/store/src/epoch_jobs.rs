@@ -0,0 +1,160 @@
+use crate::get_client;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use service::{
+    epoch::EpochJobRepository,
+    model::epoch_job::{EpochJob, EpochJobCommand, EpochJobStatus},
+};
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct PostgresEpochJobRepository {
+    pool: Pool,
+}
+
+impl PostgresEpochJobRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EpochJobRepository for PostgresEpochJobRepository {
+    async fn enqueue(&self, epoch_index: u64, command: EpochJobCommand, due_at: DateTime<Utc>) -> Result<EpochJob> {
+        let client = get_client(&self.pool).await?;
+        let now = Utc::now();
+        client
+            .execute(
+                r#"
+            INSERT INTO epoch_job(
+                epoch_index,
+                command,
+                status,
+                attempts,
+                last_error,
+                due_at,
+                next_attempt_at,
+                created_at,
+                updated_at
+            )
+            VALUES ($1, $2, 'pending', 0, NULL, $3, $3, $4, $4)
+            ON CONFLICT (epoch_index, command) DO NOTHING
+            "#,
+                &[&Decimal::from(epoch_index), &command.to_string(), &due_at, &now],
+            )
+            .await?;
+        let row = client
+            .query_one(
+                "SELECT * FROM epoch_job WHERE epoch_index = $1 AND command = $2",
+                &[&Decimal::from(epoch_index), &command.to_string()],
+            )
+            .await?;
+        parse_row(row)
+    }
+
+    async fn start(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                r#"
+            UPDATE epoch_job
+            SET status = 'running', attempts = attempts + 1, updated_at = $3
+            WHERE epoch_index = $1 AND command = $2
+            RETURNING *
+            "#,
+                &[&Decimal::from(epoch_index), &command.to_string(), &Utc::now()],
+            )
+            .await?;
+        row.map(parse_row)
+            .transpose()?
+            .ok_or_else(|| not_found(epoch_index, command))
+    }
+
+    async fn succeed(&self, epoch_index: u64, command: EpochJobCommand) -> Result<EpochJob> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                r#"
+            UPDATE epoch_job
+            SET status = 'succeeded', last_error = NULL, updated_at = $3
+            WHERE epoch_index = $1 AND command = $2
+            RETURNING *
+            "#,
+                &[&Decimal::from(epoch_index), &command.to_string(), &Utc::now()],
+            )
+            .await?;
+        row.map(parse_row)
+            .transpose()?
+            .ok_or_else(|| not_found(epoch_index, command))
+    }
+
+    async fn fail(
+        &self,
+        epoch_index: u64,
+        command: EpochJobCommand,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<EpochJob> {
+        let client = get_client(&self.pool).await?;
+        let status = match next_attempt_at {
+            Some(_) => EpochJobStatus::Pending,
+            None => EpochJobStatus::Failed,
+        };
+        let row = client
+            .query_opt(
+                r#"
+            UPDATE epoch_job
+            SET status = $3, last_error = $4, next_attempt_at = COALESCE($5, next_attempt_at), updated_at = $6
+            WHERE epoch_index = $1 AND command = $2
+            RETURNING *
+            "#,
+                &[
+                    &Decimal::from(epoch_index),
+                    &command.to_string(),
+                    &status.to_string(),
+                    &error,
+                    &next_attempt_at,
+                    &Utc::now(),
+                ],
+            )
+            .await?;
+        row.map(parse_row)
+            .transpose()?
+            .ok_or_else(|| not_found(epoch_index, command))
+    }
+
+    async fn recent(&self, limit: usize, offset: usize) -> Result<Vec<EpochJob>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM epoch_job ORDER BY updated_at DESC LIMIT $1 OFFSET $2",
+                &[&(limit as i64), &(offset as i64)],
+            )
+            .await?;
+        rows.into_iter().map(parse_row).collect()
+    }
+}
+
+fn not_found(epoch_index: u64, command: EpochJobCommand) -> anyhow::Error {
+    anyhow!("Epoch job {} for epoch {} not found", command.to_string(), epoch_index)
+}
+
+fn parse_row(row: Row) -> Result<EpochJob> {
+    Ok(EpochJob {
+        epoch_index: row
+            .get::<_, Decimal>("epoch_index")
+            .to_u64()
+            .ok_or(anyhow!("cannot convert epoch index to u64"))?,
+        command: row.get::<_, &str>("command").parse()?,
+        status: row.get::<_, &str>("status").parse()?,
+        attempts: row.get::<_, i32>("attempts") as u32,
+        last_error: row.get("last_error"),
+        due_at: row.get("due_at"),
+        next_attempt_at: row.get("next_attempt_at"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
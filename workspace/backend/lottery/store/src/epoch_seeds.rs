@@ -0,0 +1,65 @@
+use crate::get_client;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use deadpool_postgres::Pool;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use service::tickets::{EpochSeed, EpochSeedRepository};
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+use std::str::FromStr;
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct PostgresEpochSeedRepository {
+    pool: Pool,
+}
+
+impl PostgresEpochSeedRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EpochSeedRepository for PostgresEpochSeedRepository {
+    async fn by_epoch_index(&self, epoch_index: u64) -> Result<Option<EpochSeed>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "SELECT * FROM epoch_seed WHERE epoch_index = $1",
+                &[&Decimal::from(epoch_index)],
+            )
+            .await?;
+        row.map(parse_row).transpose()
+    }
+
+    async fn create(&self, seed: &EpochSeed) -> Result<EpochSeed> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                "INSERT INTO epoch_seed(epoch_index, epoch_pubkey, slot, slot_hash) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (epoch_index) DO NOTHING",
+                &[
+                    &Decimal::from(seed.epoch_index),
+                    &seed.epoch_pubkey.to_string(),
+                    &(seed.slot as i64),
+                    &seed.slot_hash.to_string(),
+                ],
+            )
+            .await?;
+        self.by_epoch_index(seed.epoch_index)
+            .await?
+            .ok_or(anyhow!("epoch seed {} not found after insert", seed.epoch_index))
+    }
+}
+
+fn parse_row(row: Row) -> Result<EpochSeed> {
+    Ok(EpochSeed {
+        epoch_index: row
+            .get::<_, Decimal>("epoch_index")
+            .to_u64()
+            .ok_or(anyhow!("cannot convert epoch_index to u64"))?,
+        epoch_pubkey: Pubkey::from_str(row.get("epoch_pubkey"))?,
+        slot: row.get::<_, i64>("slot") as u64,
+        slot_hash: Hash::from_str(row.get("slot_hash"))?,
+    })
+}
@@ -209,7 +209,7 @@ fn returns_from_json(json: &Value) -> Returns {
     }
 }
 
-fn epoch_status_from_str(s: &str) -> Result<EpochStatus, anyhow::Error> {
+pub(crate) fn epoch_status_from_str(s: &str) -> Result<EpochStatus, anyhow::Error> {
     match s {
         "running" => Ok(EpochStatus::Running),
         "yielding" => Ok(EpochStatus::Yielding),
@@ -219,7 +219,7 @@ fn epoch_status_from_str(s: &str) -> Result<EpochStatus, anyhow::Error> {
     }
 }
 
-fn epoch_status_to_str(s: EpochStatus) -> &'static str {
+pub(crate) fn epoch_status_to_str(s: EpochStatus) -> &'static str {
     match s {
         EpochStatus::Running => "running",
         EpochStatus::Yielding => "yielding",
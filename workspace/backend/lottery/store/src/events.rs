@@ -0,0 +1,102 @@
+//! Fan-out of service events between replicas with Postgres `NOTIFY`/`LISTEN`.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{channel::mpsc, stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use service::events::{EventNotice, EventRelay};
+use tokio_postgres::{AsyncMessage, NoTls};
+
+use crate::{DbConfig, Pool};
+
+const CHANNEL: &str = "events";
+
+#[derive(Serialize, Deserialize)]
+struct Notification {
+    origin: String,
+    notice: EventNotice,
+}
+
+/// Notifies the other replicas of the events of this one.
+///
+/// Notifications carry the id of the replica that sent them, so that [`PostgresEventRelay::listen`] skips its own.
+#[derive(Clone)]
+pub struct PostgresEventRelay {
+    pool: Pool,
+    origin: String,
+}
+
+impl PostgresEventRelay {
+    pub fn new(pool: Pool) -> Self {
+        Self {
+            pool,
+            origin: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// The events the other replicas send. Listens on a dedicated connection, reconnecting every `retry_interval`
+    /// after it fails. Events sent while reconnecting are lost.
+    pub fn listen(&self, db_config: DbConfig, retry_interval: Duration) -> impl Stream<Item = EventNotice> {
+        let (sender, receiver) = mpsc::unbounded();
+        let origin = self.origin.clone();
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                if let Err(e) = listen(&db_config, &origin, &sender).await {
+                    log::error!("Listening to relayed events failed: {}", e);
+                }
+                tokio::time::sleep(retry_interval).await;
+            }
+        });
+        receiver
+    }
+}
+
+async fn listen(db_config: &DbConfig, origin: &str, sender: &mpsc::UnboundedSender<EventNotice>) -> Result<()> {
+    let (client, mut connection) = tokio_postgres::connect(&db_config.connection_string(), NoTls).await?;
+    // Notifications are only delivered while the connection is polled
+    let (messages_sender, mut messages) = mpsc::unbounded();
+    tokio::spawn(
+        stream::poll_fn(move |cx| connection.poll_message(cx))
+            .map(Ok::<_, mpsc::SendError>)
+            .forward(messages_sender),
+    );
+    client.batch_execute(&format!("LISTEN {}", CHANNEL)).await?;
+
+    while let Some(message) = messages.next().await {
+        let notification = match message? {
+            AsyncMessage::Notification(notification) => notification,
+            _ => continue,
+        };
+        let notification: Notification = match serde_json::from_str(notification.payload()) {
+            Ok(notification) => notification,
+            Err(e) => {
+                log::error!("Invalid relayed event {}: {}", notification.payload(), e);
+                continue;
+            }
+        };
+        if notification.origin == origin {
+            continue;
+        }
+        if sender.unbounded_send(notification.notice).is_err() {
+            return Ok(());
+        }
+    }
+    Err(anyhow!("Connection closed"))
+}
+
+#[async_trait]
+impl EventRelay for PostgresEventRelay {
+    async fn send(&self, notice: &EventNotice) -> Result<()> {
+        let payload = serde_json::to_string(&Notification {
+            origin: self.origin.clone(),
+            notice: notice.clone(),
+        })?;
+        let client = self.pool.get().await?;
+        client
+            .execute("SELECT pg_notify($1, $2)", &[&CHANNEL, &payload])
+            .await?;
+        Ok(())
+    }
+}
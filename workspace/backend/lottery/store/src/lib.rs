@@ -1,12 +1,24 @@
 #[macro_use]
 extern crate diesel_migrations;
 
+pub mod advisory_lock;
+pub mod campaigns;
+pub mod draw_stats;
+pub mod epoch_audit;
+pub mod epoch_jobs;
+pub mod epoch_seeds;
 pub mod epochs;
+pub mod events;
 pub mod faucet;
 pub mod health_check;
+pub mod login_challenges;
 pub mod migrations;
+pub mod notifications;
 pub mod prizes;
+pub mod program_accounts;
+pub mod published_manifests;
 pub mod stake_update;
+pub mod stake_update_reviews;
 pub mod tickets;
 pub mod transactions;
 
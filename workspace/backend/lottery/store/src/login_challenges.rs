@@ -0,0 +1,70 @@
+use crate::Pool;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use service::{model::login_challenge::LoginChallenge, sign_in::LoginChallengeRepository};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio_postgres::Row;
+
+fn login_challenge_from_row(row: Row) -> Result<LoginChallenge> {
+    Ok(LoginChallenge {
+        wallet: Pubkey::from_str(&row.get::<_, String>("wallet"))?,
+        nonce: row.get("nonce"),
+        issued_at: row.get("issued_at"),
+        expires_at: row.get("expires_at"),
+    })
+}
+
+#[derive(Clone)]
+pub struct PostgresLoginChallengeRepository {
+    pool: Pool,
+}
+
+impl PostgresLoginChallengeRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl LoginChallengeRepository for PostgresLoginChallengeRepository {
+    async fn store(&self, challenge: &LoginChallenge) -> Result<()> {
+        let client = self.pool.get().await?;
+        // Challenges that were never used pile up otherwise
+        client
+            .execute("DELETE FROM login_challenge WHERE expires_at < $1", &[&challenge.issued_at])
+            .await?;
+        client
+            .execute(
+                "INSERT INTO login_challenge (wallet, nonce, issued_at, expires_at) VALUES ($1, $2, $3, $4)",
+                &[
+                    &challenge.wallet.to_string(),
+                    &challenge.nonce,
+                    &challenge.issued_at,
+                    &challenge.expires_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn take(&self, wallet: &Pubkey, nonce: &str, now: DateTime<Utc>) -> Result<Option<LoginChallenge>> {
+        let client = self.pool.get().await?;
+        let row = client
+            .query_opt(
+                "DELETE FROM login_challenge
+                WHERE wallet = $1 AND nonce = $2
+                RETURNING *",
+                &[&wallet.to_string(), &nonce],
+            )
+            .await?;
+        match row {
+            Some(row) => {
+                let challenge = login_challenge_from_row(row)?;
+                Ok(if challenge.expires_at > now { Some(challenge) } else { None })
+            }
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::get_client;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use service::{
+    model::notification::{
+        NotificationDelivery, NotificationDeliveryStatus, NotificationKind, NotificationPreferences,
+    },
+    notification::{NotificationDeliveryRepository, NotificationPreferencesRepository},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct PostgresNotificationPreferencesRepository {
+    pool: Pool,
+}
+
+impl PostgresNotificationPreferencesRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl NotificationPreferencesRepository for PostgresNotificationPreferencesRepository {
+    async fn by_wallet(&self, wallet: &Pubkey) -> Result<Option<NotificationPreferences>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "SELECT * FROM notification_preference WHERE wallet = $1",
+                &[&wallet.to_string()],
+            )
+            .await?;
+        row.map(parse_row).transpose()
+    }
+
+    async fn subscribed(&self, kind: NotificationKind) -> Result<Vec<NotificationPreferences>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM notification_preference WHERE kinds @> ARRAY[$1]::VARCHAR[]",
+                &[&kind.to_string()],
+            )
+            .await?;
+        rows.into_iter().map(parse_row).collect()
+    }
+
+    async fn upsert(&self, preferences: &NotificationPreferences) -> Result<NotificationPreferences> {
+        let client = get_client(&self.pool).await?;
+        let kinds: Vec<String> = preferences.kinds.iter().map(NotificationKind::to_string).collect();
+        let row = client
+            .query_one(
+                r#"
+            INSERT INTO notification_preference(wallet, email, webhook_url, webhook_secret, kinds, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (wallet) DO UPDATE
+            SET email = $2, webhook_url = $3, webhook_secret = $4, kinds = $5, updated_at = $6
+            RETURNING *
+            "#,
+                &[
+                    &preferences.wallet.to_string(),
+                    &preferences.email,
+                    &preferences.webhook_url,
+                    &preferences.webhook_secret,
+                    &kinds,
+                    &preferences.updated_at,
+                ],
+            )
+            .await?;
+        parse_row(row)
+    }
+}
+
+#[derive(Clone)]
+pub struct PostgresNotificationDeliveryRepository {
+    pool: Pool,
+}
+
+impl PostgresNotificationDeliveryRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl NotificationDeliveryRepository for PostgresNotificationDeliveryRepository {
+    async fn enqueue(&self, delivery: &NotificationDelivery) -> Result<bool> {
+        let client = get_client(&self.pool).await?;
+        let inserted = client
+            .execute(
+                r#"
+            INSERT INTO notification_delivery(
+                event_key,
+                wallet,
+                channel,
+                subject,
+                body,
+                payload,
+                status,
+                attempts,
+                last_error,
+                next_attempt_at,
+                created_at,
+                updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11)
+            ON CONFLICT (event_key, wallet, channel) DO NOTHING
+            "#,
+                &[
+                    &delivery.event_key,
+                    &delivery.wallet.to_string(),
+                    &delivery.channel.to_string(),
+                    &delivery.subject,
+                    &delivery.body,
+                    &delivery.payload,
+                    &delivery.status.to_string(),
+                    &(delivery.attempts as i32),
+                    &delivery.last_error,
+                    &delivery.next_attempt_at,
+                    &delivery.created_at,
+                ],
+            )
+            .await?;
+        Ok(inserted == 1)
+    }
+
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        retry_at: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let client = get_client(&self.pool).await?;
+        // Skipping locked rows lets several workers claim deliveries at once
+        let rows = client
+            .query(
+                r#"
+            WITH due AS (
+                SELECT event_key, wallet, channel
+                FROM notification_delivery
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE notification_delivery d
+            SET attempts = d.attempts + 1, next_attempt_at = $2, updated_at = $1
+            FROM due
+            WHERE d.event_key = due.event_key AND d.wallet = due.wallet AND d.channel = due.channel
+            RETURNING d.*
+            "#,
+                &[&now, &retry_at, &(limit as i64)],
+            )
+            .await?;
+        rows.into_iter().map(parse_delivery_row).collect()
+    }
+
+    async fn succeed(&self, delivery: &NotificationDelivery, now: DateTime<Utc>) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                r#"
+            UPDATE notification_delivery
+            SET status = 'sent', last_error = NULL, updated_at = $4
+            WHERE event_key = $1 AND wallet = $2 AND channel = $3
+            "#,
+                &[
+                    &delivery.event_key,
+                    &delivery.wallet.to_string(),
+                    &delivery.channel.to_string(),
+                    &now,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        delivery: &NotificationDelivery,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        let status = match next_attempt_at {
+            Some(_) => NotificationDeliveryStatus::Pending,
+            None => NotificationDeliveryStatus::Failed,
+        };
+        client
+            .execute(
+                r#"
+            UPDATE notification_delivery
+            SET status = $4, last_error = $5, next_attempt_at = COALESCE($6, next_attempt_at), updated_at = $7
+            WHERE event_key = $1 AND wallet = $2 AND channel = $3
+            "#,
+                &[
+                    &delivery.event_key,
+                    &delivery.wallet.to_string(),
+                    &delivery.channel.to_string(),
+                    &status.to_string(),
+                    &error,
+                    &next_attempt_at,
+                    &Utc::now(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+fn parse_delivery_row(row: Row) -> Result<NotificationDelivery> {
+    Ok(NotificationDelivery {
+        event_key: row.get("event_key"),
+        wallet: Pubkey::from_str(row.get::<_, &str>("wallet"))?,
+        channel: row.get::<_, &str>("channel").parse()?,
+        subject: row.get("subject"),
+        body: row.get("body"),
+        payload: row.get("payload"),
+        status: row.get::<_, &str>("status").parse()?,
+        attempts: row.get::<_, i32>("attempts").try_into()?,
+        last_error: row.get("last_error"),
+        next_attempt_at: row.get("next_attempt_at"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn parse_row(row: Row) -> Result<NotificationPreferences> {
+    Ok(NotificationPreferences {
+        wallet: Pubkey::from_str(row.get::<_, &str>("wallet"))?,
+        email: row.get("email"),
+        webhook_url: row.get("webhook_url"),
+        webhook_secret: row.get("webhook_secret"),
+        kinds: row
+            .get::<_, Vec<String>>("kinds")
+            .iter()
+            .map(|kind| kind.parse())
+            .collect::<Result<_>>()?,
+        updated_at: row.get("updated_at"),
+    })
+}
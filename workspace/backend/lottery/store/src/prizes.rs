@@ -182,7 +182,7 @@ impl PrizeRepository for PostgresPrizeRepository {
         if prizes.is_empty() {
             return Ok(());
         }
-        let client = self.pool.get().await?;
+        let mut client = self.pool.get().await?;
         let prizes = prizes.iter().cloned().map(Prize::from).collect::<Vec<_>>();
         let mut query = "
             INSERT INTO 
@@ -236,7 +236,16 @@ impl PrizeRepository for PostgresPrizeRepository {
                 claimable = EXCLUDED.claimable, 
                 claimed = EXCLUDED.claimed
         ";
-        client.execute(&query, &params).await?;
+        let mut epoch_indexes = prizes.iter().map(|prize| prize.epoch_index).collect::<Vec<_>>();
+        epoch_indexes.dedup();
+
+        let transaction = client.transaction().await?;
+        transaction.execute(&query, &params).await?;
+        // The cached stats of the epochs don't include these prizes anymore
+        transaction
+            .execute("DELETE FROM epoch_stats WHERE epoch_index = ANY($1)", &[&epoch_indexes])
+            .await?;
+        transaction.commit().await?;
         Ok(())
     }
 }
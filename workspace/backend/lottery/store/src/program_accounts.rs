@@ -0,0 +1,133 @@
+use crate::get_client;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use service::{
+    account_mirror::ProgramAccountRepository,
+    model::program_account::{MirroredAccountType, ProgramAccount},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio_postgres::Row;
+
+#[derive(Clone)]
+pub struct PostgresProgramAccountRepository {
+    pool: Pool,
+}
+
+impl PostgresProgramAccountRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ProgramAccountRepository for PostgresProgramAccountRepository {
+    async fn by_type(&self, account_type: MirroredAccountType) -> Result<Vec<ProgramAccount>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM program_account WHERE account_type = $1",
+                &[&account_type.to_string()],
+            )
+            .await?;
+        rows.into_iter().map(parse_row).collect()
+    }
+
+    async fn upsert(&self, accounts: &[ProgramAccount]) -> Result<()> {
+        if accounts.is_empty() {
+            return Ok(());
+        }
+        let mut client = get_client(&self.pool).await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction
+            .prepare(
+                r#"
+            INSERT INTO program_account(pubkey, account_type, lamports, data, slot)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (pubkey) DO UPDATE
+            SET account_type = $2, lamports = $3, data = $4, slot = $5
+            WHERE program_account.slot <= $5
+            "#,
+            )
+            .await?;
+        for account in accounts {
+            transaction
+                .execute(
+                    &statement,
+                    &[
+                        &account.pubkey.to_string(),
+                        &account.account_type.to_string(),
+                        &Decimal::from(account.lamports),
+                        &account.data,
+                        &(account.slot as i64),
+                    ],
+                )
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn remove(&self, pubkey: &Pubkey, slot: u64) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                "DELETE FROM program_account WHERE pubkey = $1 AND slot <= $2",
+                &[&pubkey.to_string(), &(slot as i64)],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn checked(&self, now: DateTime<Utc>) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                "INSERT INTO program_account_check(checked_at) VALUES ($1)
+                ON CONFLICT (id) DO UPDATE SET checked_at = $1",
+                &[&now],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn last_checked(&self) -> Result<Option<DateTime<Utc>>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt("SELECT checked_at FROM program_account_check", &[])
+            .await?;
+        Ok(row.map(|row| row.get("checked_at")))
+    }
+
+    async fn subscribed(&self, now: DateTime<Utc>) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute("UPDATE program_account_check SET subscribed_at = $1", &[&now])
+            .await?;
+        Ok(())
+    }
+
+    async fn last_subscribed(&self) -> Result<Option<DateTime<Utc>>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt("SELECT subscribed_at FROM program_account_check", &[])
+            .await?;
+        Ok(row.and_then(|row| row.get("subscribed_at")))
+    }
+}
+
+fn parse_row(row: Row) -> Result<ProgramAccount> {
+    Ok(ProgramAccount {
+        pubkey: Pubkey::from_str(row.get("pubkey"))?,
+        account_type: row.get::<_, &str>("account_type").parse()?,
+        lamports: row
+            .get::<_, Decimal>("lamports")
+            .to_u64()
+            .ok_or(anyhow!("cannot convert lamports to u64"))?,
+        data: row.get("data"),
+        slot: row.get::<_, i64>("slot") as u64,
+    })
+}
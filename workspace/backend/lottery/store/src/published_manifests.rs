@@ -0,0 +1,44 @@
+use crate::get_client;
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::Pool;
+use rust_decimal::Decimal;
+use service::tickets::PublishedManifestRepository;
+use solana_sdk::hash::Hash;
+
+#[derive(Clone)]
+pub struct PostgresPublishedManifestRepository {
+    pool: Pool,
+}
+
+impl PostgresPublishedManifestRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PublishedManifestRepository for PostgresPublishedManifestRepository {
+    async fn url_by_hash(&self, epoch_index: u64, hash: &Hash) -> Result<Option<String>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "SELECT url FROM published_manifest WHERE epoch_index = $1 AND manifest_hash = $2",
+                &[&Decimal::from(epoch_index), &hash.to_string()],
+            )
+            .await?;
+        Ok(row.map(|row| row.get("url")))
+    }
+
+    async fn create(&self, epoch_index: u64, hash: &Hash, url: &str) -> Result<()> {
+        let client = get_client(&self.pool).await?;
+        client
+            .execute(
+                "INSERT INTO published_manifest(epoch_index, manifest_hash, url) VALUES ($1, $2, $3)
+                ON CONFLICT (epoch_index, manifest_hash) DO NOTHING",
+                &[&Decimal::from(epoch_index), &hash.to_string(), &url],
+            )
+            .await?;
+        Ok(())
+    }
+}
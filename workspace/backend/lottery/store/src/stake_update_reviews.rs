@@ -0,0 +1,142 @@
+use crate::get_client;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use service::{
+    model::{
+        stake_update::StakeUpdateType,
+        stake_update_review::{StakeUpdateReview, StakeUpdateReviewStatus},
+    },
+    screening::StakeUpdateReviewRepository,
+    solana::FPUSDC,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use tokio_postgres::Row;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PostgresStakeUpdateReviewRepository {
+    pool: Pool,
+}
+
+impl PostgresStakeUpdateReviewRepository {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl StakeUpdateReviewRepository for PostgresStakeUpdateReviewRepository {
+    async fn create(&self, review: &StakeUpdateReview) -> Result<StakeUpdateReview> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_one(
+                r#"
+            INSERT INTO stake_update_review(
+                id,
+                wallet,
+                amount,
+                type,
+                reason,
+                status,
+                created_at,
+                reviewed_by,
+                reviewed_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *
+            "#,
+                &[
+                    &review.id,
+                    &review.wallet.to_string(),
+                    &review.amount.to_string(),
+                    &review.type_.to_string(),
+                    &review.reason,
+                    &review.status.to_string(),
+                    &review.created_at,
+                    &review.reviewed_by,
+                    &review.reviewed_at,
+                ],
+            )
+            .await?;
+        parse_row(row)
+    }
+
+    async fn by_id(&self, id: &Uuid) -> Result<Option<StakeUpdateReview>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt("SELECT * FROM stake_update_review WHERE id = $1", &[id])
+            .await?;
+        row.map(parse_row).transpose()
+    }
+
+    async fn pending_by_wallet(&self, wallet: &Pubkey) -> Result<Option<StakeUpdateReview>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                "SELECT * FROM stake_update_review WHERE wallet = $1 AND status = $2",
+                &[&wallet.to_string(), &StakeUpdateReviewStatus::Pending.to_string()],
+            )
+            .await?;
+        row.map(parse_row).transpose()
+    }
+
+    async fn by_status(
+        &self,
+        status: StakeUpdateReviewStatus,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<StakeUpdateReview>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM stake_update_review WHERE status = $1 ORDER BY created_at LIMIT $2 OFFSET $3",
+                &[&status.to_string(), &(limit as i64), &(offset as i64)],
+            )
+            .await?;
+        rows.into_iter().map(parse_row).collect()
+    }
+
+    async fn resolve(
+        &self,
+        id: &Uuid,
+        status: StakeUpdateReviewStatus,
+        reviewed_by: &str,
+        reviewed_at: DateTime<Utc>,
+    ) -> Result<Option<StakeUpdateReview>> {
+        let client = get_client(&self.pool).await?;
+        let row = client
+            .query_opt(
+                r#"
+            UPDATE stake_update_review
+            SET status = $2, reviewed_by = $3, reviewed_at = $4
+            WHERE id = $1 AND status = $5
+            RETURNING *
+            "#,
+                &[
+                    id,
+                    &status.to_string(),
+                    &reviewed_by,
+                    &reviewed_at,
+                    &StakeUpdateReviewStatus::Pending.to_string(),
+                ],
+            )
+            .await?;
+        row.map(parse_row).transpose()
+    }
+}
+
+fn parse_row(row: Row) -> Result<StakeUpdateReview> {
+    Ok(StakeUpdateReview {
+        id: row.get("id"),
+        wallet: Pubkey::from_str(row.get::<_, &str>("wallet"))?,
+        amount: FPUSDC::from_str(row.get::<_, &str>("amount")).map_err(|s| anyhow::anyhow!(s))?,
+        type_: StakeUpdateType::from_str(row.get::<_, &str>("type"))?,
+        reason: row.get("reason"),
+        status: StakeUpdateReviewStatus::from_str(row.get::<_, &str>("status"))?,
+        created_at: row.get("created_at"),
+        reviewed_by: row.get("reviewed_by"),
+        reviewed_at: row.get("reviewed_at"),
+    })
+}
@@ -5,12 +5,12 @@ use postgres_types::ToSql;
 use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::Deserialize;
 use service::{
-    model::ticket::{TicketError, TicketsWithCount, SEQUENCE_LENGTH},
-    tickets::{SequenceType, TicketRepository, WalletRisqId},
+    model::ticket::{PrefixMatches, PrefixStats, TicketError, TicketsWithCount, SEQUENCE_LENGTH},
+    tickets::{pack_sequence, packed_prefix_range, unpack_sequence, SequenceType, TicketRepository, WalletRisqId},
 };
 use solana_sdk::pubkey::Pubkey;
 use std::str::FromStr;
-use tokio_postgres::Row;
+use tokio_postgres::{binary_copy::BinaryCopyInWriter, types::Type, Row, Transaction};
 
 #[derive(Deserialize, Debug, Clone)]
 struct Sequence {
@@ -18,23 +18,6 @@ struct Sequence {
     sequence_type: String,
 }
 
-impl TryFrom<service::tickets::Sequence> for Sequence {
-    type Error = anyhow::Error;
-    fn try_from(sequence: service::tickets::Sequence) -> Result<Self> {
-        if sequence.nums.len() != SEQUENCE_LENGTH {
-            return Err(anyhow!("Invalid sequence length"));
-        }
-        let mut nums = [0; SEQUENCE_LENGTH];
-        for (i, num) in sequence.nums.into_iter().enumerate() {
-            nums[i] = num as i16;
-        }
-        Ok(Self {
-            nums,
-            sequence_type: sequence.sequence_type.to_string(),
-        })
-    }
-}
-
 impl TryFrom<Sequence> for service::tickets::Sequence {
     type Error = anyhow::Error;
     fn try_from(sequence: Sequence) -> Result<Self> {
@@ -122,8 +105,9 @@ impl TicketRepository for PostgresTicketRepository {
                 WITH
                     sequences_sel AS (
                         SELECT wallet, epoch_index,
-                            to_jsonb(array_agg(jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type))) AS sequences
+                            to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
                         FROM sequences
+                        WHERE wallet = $1 AND epoch_index = $2
                         GROUP BY wallet, epoch_index
                     )
                 SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id,
@@ -154,8 +138,9 @@ impl TicketRepository for PostgresTicketRepository {
                 WITH
                     sequences_sel AS (
                         SELECT wallet, epoch_index,
-                            to_jsonb(array_agg(jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type))) AS sequences
+                            to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
                         FROM sequences
+                        WHERE wallet = ANY($1) AND epoch_index = $2
                         GROUP BY wallet, epoch_index
                     )
                 SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id,
@@ -174,6 +159,36 @@ impl TicketRepository for PostgresTicketRepository {
             .collect::<Result<Result<Vec<_>>>>()?
     }
 
+    async fn by_epoch_index(&self, index: u64) -> Result<Vec<service::tickets::Ticket>> {
+        let client = get_client(&self.pool).await?;
+
+        let rows = client
+            .query(
+                "
+                WITH
+                    sequences_sel AS (
+                        SELECT wallet, epoch_index,
+                            to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
+                        FROM sequences
+                        WHERE epoch_index = $1
+                        GROUP BY wallet, epoch_index
+                    )
+                SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id,
+                    COALESCE(sequences_sel.sequences, jsonb_build_array()) AS sequences
+                FROM ticket
+                LEFT JOIN sequences_sel
+                ON ticket.wallet=sequences_sel.wallet and ticket.epoch_index=sequences_sel.epoch_index
+                WHERE ticket.epoch_index = $1",
+                &[&(Decimal::from(index))],
+            )
+            .await?;
+
+        rows.into_iter()
+            .map(Ticket::try_from)
+            .map(|ticket| ticket.map(service::tickets::Ticket::try_from))
+            .collect::<Result<Result<Vec<_>>>>()?
+    }
+
     async fn by_epoch_index_and_prefix(
         &self,
         index: u64,
@@ -181,31 +196,19 @@ impl TicketRepository for PostgresTicketRepository {
         prefix: &[u8],
     ) -> Result<TicketsWithCount> {
         let client = get_client(&self.pool).await?;
-        let prefix_len = prefix.len();
-        if prefix_len > SEQUENCE_LENGTH {
-            return Err(TicketError::PrefixLengthExceeded(prefix_len).into());
-        } else if prefix_len == 0 {
-            return Err(TicketError::EmptyPrefix.into());
-        }
+        let (start, end) = packed_prefix_range(prefix)?;
+        let (start, end) = (start as i64, end as i64);
         let mut query = String::new();
         query += "
         WITH
             sequences_sel AS (
                 SELECT wallet, epoch_index,
-                    jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type) AS sequences
+                    jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type) AS sequences
                 FROM sequences
-                WHERE epoch_index=$1";
-        let epoch_index = Decimal::from(index);
-        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&epoch_index];
-
-        let prefix: Vec<i16> = prefix.into_iter().cloned().map(|x| x as i16).collect();
-        prefix.iter().enumerate().for_each(|(i, n)| {
-            query += &format!(" AND _{} = ${}", i + 1, i + 2);
-            params.push(n);
-        });
-
-        query += "
+                WHERE epoch_index=$1 AND packed BETWEEN $2 AND $3
             )";
+        let epoch_index = Decimal::from(index);
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&epoch_index, &start, &end];
 
         query += "
         SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id, to_jsonb(array_agg(sequences_sel.sequences)) AS sequences, count(*) OVER() AS count
@@ -246,7 +249,7 @@ impl TicketRepository for PostgresTicketRepository {
         WITH
             sequences_sel AS (
                 SELECT wallet, epoch_index,
-                    to_jsonb(array_agg(jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type))) AS sequences
+                    to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
                 FROM sequences
                 GROUP BY wallet, epoch_index
             )
@@ -271,21 +274,14 @@ impl TicketRepository for PostgresTicketRepository {
 
         let rows = client
             .query(
-                "SELECT DISTINCT ARRAY[_1, _2, _3, _4, _5, _6] AS sequence FROM sequences WHERE epoch_index=$1",
+                "SELECT DISTINCT packed FROM sequences WHERE epoch_index=$1",
                 &[&(Decimal::from(index))],
             )
             .await?;
 
         Ok(rows
             .into_iter()
-            .map(|row| {
-                let seq = row.get::<_, [i16; 6]>("sequence");
-                let mut nums = [0u8; 6];
-                seq.iter().enumerate().for_each(|(i, n)| {
-                    nums[i] = *n as u8;
-                });
-                nums
-            })
+            .map(|row| unpack_sequence(row.get::<_, i64>("packed") as u64))
             .collect::<Vec<[u8; 6]>>())
     }
 
@@ -295,7 +291,7 @@ impl TicketRepository for PostgresTicketRepository {
         let row = client
             .query_opt(
                 "
-                SELECT ARRAY[_1, _2, _3, _4, _5, _6] AS sequence
+                SELECT packed
                 FROM sequences
                 WHERE epoch_index=$1
                 ORDER BY random()
@@ -304,99 +300,116 @@ impl TicketRepository for PostgresTicketRepository {
             )
             .await?;
 
+        Ok(row.map(|row| unpack_sequence(row.get::<_, i64>("packed") as u64)))
+    }
+
+    async fn most_common_prefix_by_epoch_index(&self, index: u64, prefix_len: usize) -> Result<Option<PrefixStats>> {
+        if prefix_len > SEQUENCE_LENGTH {
+            return Err(TicketError::PrefixLengthExceeded(prefix_len).into());
+        } else if prefix_len == 0 {
+            return Err(TicketError::EmptyPrefix.into());
+        }
+        let client = get_client(&self.pool).await?;
+
+        // Aggregated by the database, the sequences of the epoch are never loaded into memory
+        let shift = (8 * (SEQUENCE_LENGTH - prefix_len)) as i32;
+        let row = client
+            .query_opt(
+                "
+                SELECT packed >> $2 AS prefix, COUNT(DISTINCT packed) AS num_sequences, MIN(packed) AS sequence
+                FROM sequences
+                WHERE epoch_index = $1
+                GROUP BY prefix
+                ORDER BY num_sequences DESC, prefix
+                LIMIT 1",
+                &[&(Decimal::from(index)), &shift],
+            )
+            .await?;
+
         Ok(row.map(|row| {
-            let seq = row.get::<_, [i16; 6]>("sequence");
-            let mut nums = [0u8; 6];
-            seq.into_iter().enumerate().for_each(|(i, n)| {
-                nums[i] = n as u8;
-            });
-            nums
+            let sequence = unpack_sequence(row.get::<_, i64>("sequence") as u64);
+            PrefixStats {
+                prefix: sequence[..prefix_len].to_vec(),
+                num_sequences: row.get::<_, i64>("num_sequences") as u64,
+                sequence,
+            }
         }))
     }
 
-    async fn create(&self, ticket: &service::tickets::Ticket) -> Result<service::tickets::Ticket> {
+    async fn prefix_matches_by_epoch_index(
+        &self,
+        index: u64,
+        combination: &[u8; 6],
+        min_prefix_len: usize,
+    ) -> Result<Vec<PrefixMatches>> {
+        let (start, end) = packed_prefix_range(
+            combination
+                .get(..min_prefix_len)
+                .ok_or(TicketError::PrefixLengthExceeded(min_prefix_len))?,
+        )?;
         let client = get_client(&self.pool).await?;
 
-        let mut query = String::new();
-        query += "
-        WITH
-            ticket_ins AS (
-                INSERT INTO ticket (wallet, epoch_index, arweave_url, balance, price, risq_id)
-                VALUES ($1, $2, $3, $4, $5, $6)
-                RETURNING *
-            ),";
+        // The matched prefix length of each sequence, longest first. The shifts and packed prefixes are numbers
+        // computed here, so they can be inlined.
+        let packed = pack_sequence(combination);
+        let prefix_len = (min_prefix_len + 1..=SEQUENCE_LENGTH)
+            .rev()
+            .map(|len| {
+                let shift = 8 * (SEQUENCE_LENGTH - len);
+                format!("WHEN packed >> {shift} = {} THEN {len}", packed >> shift)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let query = format!(
+            "
+            SELECT wallet, CASE {prefix_len} ELSE {min_prefix_len} END AS prefix_len, COUNT(*) AS num_sequences
+            FROM sequences
+            WHERE epoch_index = $1 AND packed BETWEEN $2 AND $3
+            GROUP BY wallet, prefix_len
+            ORDER BY wallet, prefix_len"
+        );
+        let rows = client
+            .query(&query, &[&(Decimal::from(index)), &(start as i64), &(end as i64)])
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PrefixMatches {
+                    wallet: Pubkey::from_str(&row.get::<_, String>("wallet"))?,
+                    prefix_len: row.get::<_, i32>("prefix_len") as usize,
+                    num_sequences: row.get::<_, i64>("num_sequences") as u64,
+                })
+            })
+            .collect()
+    }
+
+    async fn create(&self, ticket: &service::tickets::Ticket) -> Result<service::tickets::Ticket> {
+        let mut client = get_client(&self.pool).await?;
+
         let wallet = ticket.wallet.to_string();
         let epoch_index = Decimal::from(ticket.epoch_index);
-        let sequences = ticket
-            .sequences
-            .clone()
-            .into_iter()
-            .map(Sequence::try_from)
-            .collect::<Result<Vec<Sequence>>>()?;
-        let mut params: Vec<&(dyn ToSql + Sync)> = vec![
-            &wallet,
-            &epoch_index,
-            &ticket.arweave_url,
-            &ticket.balance,
-            &ticket.price,
-            &ticket.risq_id,
-        ];
 
-        query += "
-            seq (_1, _2, _3, _4, _5, _6, sequence_type) AS (";
-        if sequences.is_empty() {
-            query += "
-                SELECT 1, 2, 3, 4, 5, 6, 'Normal' WHERE false";
-        } else {
-            query += "
-                VALUES ";
-            for i in 0..sequences.len() {
-                query += &format!(
-                    "(${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::varchar)",
-                    i * 7 + 7,
-                    i * 7 + 8,
-                    i * 7 + 9,
-                    i * 7 + 10,
-                    i * 7 + 11,
-                    i * 7 + 12,
-                    i * 7 + 13,
-                );
-                params.push(&sequences[i].nums[0]);
-                params.push(&sequences[i].nums[1]);
-                params.push(&sequences[i].nums[2]);
-                params.push(&sequences[i].nums[3]);
-                params.push(&sequences[i].nums[4]);
-                params.push(&sequences[i].nums[5]);
-                params.push(&sequences[i].sequence_type);
-                if i < ticket.sequences.len() - 1 {
-                    query += ", ";
-                }
-            }
-        }
-        query += "
-            ),";
-        query += "
-            sequences_ins AS (
-                INSERT INTO sequences
-                SELECT ticket_ins.wallet, ticket_ins.epoch_index, seq._1, seq._2, seq._3, seq._4, seq._5, seq._6, seq.sequence_type
-                FROM ticket_ins, seq
-                returning *
-            ),
-            sequences_sel AS (
-                SELECT sequences_ins.wallet, sequences_ins.epoch_index,
-                    to_jsonb(array_agg(jsonb_build_object('nums', array[sequences_ins._1, sequences_ins._2, sequences_ins._3, sequences_ins._4, sequences_ins._5, sequences_ins._6], 'sequence_type', sequences_ins.sequence_type))) AS sequences
-                FROM sequences_ins
-                GROUP BY sequences_ins.wallet, sequences_ins.epoch_index
+        let transaction = client.transaction().await?;
+        transaction
+            .execute(
+                "INSERT INTO ticket (wallet, epoch_index, arweave_url, balance, price, risq_id)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                &[
+                    &wallet,
+                    &epoch_index,
+                    &ticket.arweave_url,
+                    &ticket.balance,
+                    &ticket.price,
+                    &ticket.risq_id,
+                ],
             )
-        SELECT ticket_ins.wallet, ticket_ins.epoch_index, ticket_ins.arweave_url, ticket_ins.balance, ticket_ins.price, ticket_ins.risq_id,
-            COALESCE(sequences_sel.sequences, jsonb_build_array()) AS sequences
-        FROM ticket_ins
-        LEFT JOIN sequences_sel
-        ON ticket_ins.wallet = sequences_sel.wallet AND ticket_ins.epoch_index=sequences_sel.epoch_index;
-        ";
+            .await?;
+        copy_sequences(&transaction, &wallet, &epoch_index, &ticket.sequences).await?;
+        transaction.commit().await?;
 
-        let row = client.query_one(&query, &params).await?;
-        service::tickets::Ticket::try_from(Ticket::try_from(row)?)
+        self.by_wallet_and_epoch_index(&ticket.wallet, ticket.epoch_index)
+            .await?
+            .context("Ticket not found")
     }
 
     async fn add_sequences(
@@ -405,78 +418,49 @@ impl TicketRepository for PostgresTicketRepository {
         epoch_index: u64,
         sequences: &[service::tickets::Sequence],
     ) -> Result<service::tickets::Ticket> {
-        let client = get_client(&self.pool).await?;
+        let mut client = get_client(&self.pool).await?;
+
+        let transaction = client.transaction().await?;
+        copy_sequences(
+            &transaction,
+            &wallet.to_string(),
+            &Decimal::from(epoch_index),
+            sequences,
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.by_wallet_and_epoch_index(wallet, epoch_index)
+            .await?
+            .context("Ticket not found")
+    }
 
-        let mut query = String::new();
-        let wallet = wallet.to_string();
-        let epoch_index = Decimal::from(epoch_index);
-        let sequences = sequences
-            .iter()
-            .cloned()
-            .map(Sequence::try_from)
-            .collect::<Result<Vec<_>>>()?;
-        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&wallet, &epoch_index];
-        query += "
-        WITH
-            sequences_ins AS (
-                INSERT INTO sequences";
-        if sequences.is_empty() {
-            query += "
-                SELECT $1, $2, 1, 2, 3, 4, 5, 6, 'Normal' WHERE false";
-        } else {
-            query += "
-                VALUES ";
-            for i in 0..sequences.len() {
-                query += &format!(
-                    "($1, $2, ${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::smallint, ${}::varchar)",
-                    i * 7 + 3,
-                    i * 7 + 4,
-                    i * 7 + 5,
-                    i * 7 + 6,
-                    i * 7 + 7,
-                    i * 7 + 8,
-                    i * 7 + 9,
-                );
-                params.push(&sequences[i].nums[0]);
-                params.push(&sequences[i].nums[1]);
-                params.push(&sequences[i].nums[2]);
-                params.push(&sequences[i].nums[3]);
-                params.push(&sequences[i].nums[4]);
-                params.push(&sequences[i].nums[5]);
-                params.push(&sequences[i].sequence_type);
-                if i < sequences.len() - 1 {
-                    query += ", ";
-                }
-            }
-        }
-        query += "
-                RETURNING *
-            ),";
+    async fn replace_sequences(
+        &self,
+        wallet: &Pubkey,
+        epoch_index: u64,
+        sequence_types: &[SequenceType],
+        sequences: &[service::tickets::Sequence],
+    ) -> Result<service::tickets::Ticket> {
+        let mut client = get_client(&self.pool).await?;
 
-        query += "
-            sequences_sel AS (
-                SELECT sequences.wallet, sequences.epoch_index, jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type) AS sequences
-                FROM sequences
-                UNION
-                SELECT sequences_ins.wallet, sequences_ins.epoch_index, jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type) AS sequences
-                FROM sequences_ins
-            ),
-            sequences_agg AS (
-                SELECT sequences_sel.wallet, sequences_sel.epoch_index, to_jsonb(array_agg(sequences_sel.sequences)) AS sequences
-                FROM sequences_sel
-                GROUP BY sequences_sel.wallet, sequences_sel.epoch_index
-            )
-        SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id,
-            COALESCE(sequences_agg.sequences, jsonb_build_array()) AS sequences
-        FROM ticket
-        LEFT JOIN sequences_agg
-        ON ticket.wallet = sequences_agg.wallet AND ticket.epoch_index=sequences_agg.epoch_index
-        WHERE ticket.wallet = $1 AND ticket.epoch_index = $2";
+        let wallet_str = wallet.to_string();
+        let epoch_index_dec = Decimal::from(epoch_index);
+        let sequence_types = sequence_types.iter().map(|t| t.to_string()).collect::<Vec<_>>();
 
-        let row = client.query_one(&query, &params).await?;
-        let postgres_ticket: Ticket = row.try_into()?;
+        let transaction = client.transaction().await?;
+        transaction
+            .execute(
+                "DELETE FROM sequences WHERE wallet = $1 AND epoch_index = $2 AND sequence_type = ANY($3)",
+                &[&wallet_str, &epoch_index_dec, &sequence_types],
+            )
+            .await?;
+        copy_sequences(&transaction, &wallet_str, &epoch_index_dec, sequences).await?;
+        transaction.commit().await?;
 
-        Ok(postgres_ticket.try_into()?)
+        self.by_wallet_and_epoch_index(wallet, epoch_index)
+            .await?
+            .context("Ticket not found")
     }
 
     async fn update_arweave_url(&self, wallet: &Pubkey, index: u64, arweave_url: String) -> Result<Option<()>> {
@@ -492,6 +476,19 @@ impl TicketRepository for PostgresTicketRepository {
         Ok((row > 0).then(|| ()))
     }
 
+    async fn update_balance(&self, wallet: &Pubkey, index: u64, balance: String, price: String) -> Result<Option<()>> {
+        let client = get_client(&self.pool).await?;
+
+        let row = client
+            .execute(
+                "UPDATE ticket SET balance = $1, price = $2 WHERE wallet = $3 AND epoch_index = $4",
+                &[&balance, &price, &wallet.to_string(), &(Decimal::from(index))],
+            )
+            .await?;
+
+        Ok((row > 0).then(|| ()))
+    }
+
     async fn get_unsubmitted_tickets_in_epoch(&self, epoch_index: u64) -> Result<Vec<service::tickets::Ticket>> {
         let client = get_client(&self.pool).await?;
 
@@ -501,8 +498,9 @@ impl TicketRepository for PostgresTicketRepository {
                 WITH
                     sequences_sel AS (
                         SELECT wallet, epoch_index,
-                            to_jsonb(array_agg(jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type))) AS sequences
+                            to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
                         FROM sequences
+                        WHERE epoch_index = $1
                         GROUP BY wallet, epoch_index
                     )
                 SELECT ticket.wallet, ticket.epoch_index, ticket.arweave_url, ticket.balance, ticket.price, ticket.risq_id,
@@ -561,8 +559,9 @@ impl TicketRepository for PostgresTicketRepository {
                 ),
                 sequences_sel AS (
                     SELECT wallet, epoch_index,
-                        to_jsonb(array_agg(jsonb_build_object('nums', array[_1, _2, _3, _4, _5, _6], 'sequence_type', sequence_type))) AS sequences
+                        to_jsonb(array_agg(jsonb_build_object('nums', unpack_sequence(packed), 'sequence_type', sequence_type))) AS sequences
                     FROM sequences
+                    WHERE epoch_index = $1
                     GROUP BY wallet, epoch_index
                 )
             SELECT risq_upd.wallet, risq_upd.epoch_index, risq_upd.arweave_url, risq_upd.balance, risq_upd.price, risq_upd.risq_id,
@@ -632,7 +631,7 @@ impl TicketRepository for PostgresTicketRepository {
     }
 
     // This is using the assumption that a wallet participates in an epoch if it has a sequence in that epoch
-    async fn draws_played_by_wallet(&self, wallet: &Pubkey) -> Result<u64> {
+    async fn draws_played_by_wallet(&self, wallet: &Pubkey, before_epoch_index: u64) -> Result<u64> {
         let client = get_client(&self.pool).await?;
 
         let query = format!(
@@ -641,19 +640,50 @@ impl TicketRepository for PostgresTicketRepository {
             sequences_sel AS (
                 SELECT epoch_index
                 FROM sequences
-                WHERE wallet=$1
+                WHERE wallet=$1 AND epoch_index < $2
                 GROUP BY wallet, epoch_index
             )
         SELECT COUNT(*) FROM sequences_sel"#
         );
 
-        let row = client.query_one(&query, &[&wallet.to_string()]).await?;
+        let row = client
+            .query_one(&query, &[&wallet.to_string(), &Decimal::from(before_epoch_index)])
+            .await?;
 
         let count: i64 = row.get(0);
         Ok(count as u64)
     }
 }
 
+/// Bulk inserts sequences with a binary COPY, which is much faster than multi-row INSERTs for tickets with many
+/// sequences and isn't bounded by the number of query parameters.
+async fn copy_sequences(
+    transaction: &Transaction<'_>,
+    wallet: &str,
+    epoch_index: &Decimal,
+    sequences: &[service::tickets::Sequence],
+) -> Result<u64> {
+    if sequences.is_empty() {
+        return Ok(0);
+    }
+    let sink = transaction
+        .copy_in("COPY sequences (wallet, epoch_index, packed, sequence_type) FROM STDIN BINARY")
+        .await?;
+    let mut writer = Box::pin(BinaryCopyInWriter::new(
+        sink,
+        &[Type::VARCHAR, Type::NUMERIC, Type::INT8, Type::VARCHAR],
+    ));
+    for sequence in sequences {
+        let packed = pack_sequence(&sequence.nums) as i64;
+        let sequence_type = sequence.sequence_type.to_string();
+        writer
+            .as_mut()
+            .write(&[&wallet, epoch_index, &packed, &sequence_type])
+            .await?;
+    }
+    Ok(writer.as_mut().finish().await?)
+}
+
 fn params_push_get_idx<T>(params: &mut Vec<T>, val: T) -> usize {
     params.push(val);
     params.len()
@@ -21,6 +21,9 @@ struct Transaction {
     mint: String,
     time: Option<DateTime<Utc>>,
     transaction_type: String,
+    inner_instruction_index: Option<i16>,
+    slot: Option<i64>,
+    finalized: bool,
 }
 
 impl From<service::model::transaction::Transaction> for Transaction {
@@ -33,6 +36,9 @@ impl From<service::model::transaction::Transaction> for Transaction {
             mint: transaction.mint.to_string(),
             time: transaction.time,
             transaction_type: transaction.transaction_type.to_string(),
+            inner_instruction_index: transaction.inner_instruction_index.map(|index| index as i16),
+            slot: transaction.slot.map(|slot| slot as i64),
+            finalized: transaction.finalized,
         }
     }
 }
@@ -49,6 +55,9 @@ impl TryFrom<Transaction> for service::model::transaction::Transaction {
             mint: Pubkey::from_str(&transaction.mint)?,
             time: transaction.time,
             transaction_type: transaction.transaction_type.parse()?,
+            inner_instruction_index: transaction.inner_instruction_index.map(u8::try_from).transpose()?,
+            slot: transaction.slot.map(u64::try_from).transpose()?,
+            finalized: transaction.finalized,
         })
     }
 }
@@ -63,10 +72,19 @@ impl From<Row> for Transaction {
             mint: row.get("mint"),
             time: row.get("transaction_time"),
             transaction_type: row.get("transaction_type"),
+            inner_instruction_index: row.get("inner_instruction_index"),
+            slot: row.get("slot"),
+            finalized: row.get("finalized"),
         }
     }
 }
 
+/// Transactions seen again are only updated when they get finalized.
+const ON_CONFLICT: &str =
+    "ON CONFLICT (transaction_id, instruction_index, (COALESCE(inner_instruction_index, -1))) DO UPDATE
+        SET slot = CASE WHEN user_transaction.finalized THEN user_transaction.slot ELSE EXCLUDED.slot END,
+            finalized = user_transaction.finalized OR EXCLUDED.finalized";
+
 #[derive(Clone)]
 pub struct PostgresUserTransactionRepository {
     pool: Pool,
@@ -85,12 +103,18 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
         &self,
         transaction_id: &TransactionId,
         instruction_index: u8,
+        inner_instruction_index: Option<u8>,
     ) -> Result<Option<service::model::transaction::Transaction>> {
         let client = get_client(&self.pool).await?;
         let row = client
             .query_opt(
-                "SELECT * FROM user_transaction WHERE transaction_id = $1 AND instruction_index = $2",
-                &[&transaction_id.0, &(instruction_index as i16)],
+                "SELECT * FROM user_transaction WHERE transaction_id = $1 AND instruction_index = $2
+                AND COALESCE(inner_instruction_index, -1) = $3",
+                &[
+                    &transaction_id.0,
+                    &(instruction_index as i16),
+                    &inner_instruction_index.map_or(-1, |index| index as i16),
+                ],
             )
             .await?;
         row.map(Transaction::from)
@@ -194,9 +218,12 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
     async fn store_transaction(&self, transaction: &service::model::transaction::Transaction) -> Result<()> {
         let client = get_client(&self.pool).await?;
         let transaction = Transaction::from(transaction.clone());
-        let query = "INSERT INTO user_transaction (transaction_id, instruction_index, wallet, amount, mint, transaction_time, transaction_type)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        ON CONFLICT (transaction_id, instruction_index) DO NOTHING";
+        let query = format!(
+            "INSERT INTO user_transaction (transaction_id, instruction_index, wallet, amount, mint, transaction_time, transaction_type, inner_instruction_index, slot, finalized)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        {}",
+            ON_CONFLICT
+        );
         let params: Vec<&(dyn ToSql + Sync)> = vec![
             &transaction.transaction_id,
             &transaction.instruction_index,
@@ -205,9 +232,12 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
             &transaction.mint,
             &transaction.time,
             &transaction.transaction_type,
+            &transaction.inner_instruction_index,
+            &transaction.slot,
+            &transaction.finalized,
         ];
 
-        client.execute(query, &params).await?;
+        client.execute(&query, &params).await?;
         Ok(())
     }
 
@@ -218,19 +248,22 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
         let client = get_client(&self.pool).await?;
         let transactions = transactions.iter().cloned().map(Transaction::from).collect::<Vec<_>>();
         let mut query =
-            "INSERT INTO user_transaction (transaction_id, instruction_index, wallet, amount, mint, transaction_time, transaction_type) VALUES "
+            "INSERT INTO user_transaction (transaction_id, instruction_index, wallet, amount, mint, transaction_time, transaction_type, inner_instruction_index, slot, finalized) VALUES "
                 .to_string();
         let mut params: Vec<&(dyn ToSql + Sync)> = vec![];
         for (index, transaction) in transactions.iter().enumerate() {
             query += &format!(
-                "(${}, ${}, ${}, ${}, ${}, ${}, ${})",
-                index * 7 + 1,
-                index * 7 + 2,
-                index * 7 + 3,
-                index * 7 + 4,
-                index * 7 + 5,
-                index * 7 + 6,
-                index * 7 + 7
+                "(${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${})",
+                index * 10 + 1,
+                index * 10 + 2,
+                index * 10 + 3,
+                index * 10 + 4,
+                index * 10 + 5,
+                index * 10 + 6,
+                index * 10 + 7,
+                index * 10 + 8,
+                index * 10 + 9,
+                index * 10 + 10
             );
             params.push(&transaction.transaction_id);
             params.push(&transaction.instruction_index);
@@ -239,17 +272,32 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
             params.push(&transaction.mint);
             params.push(&transaction.time);
             params.push(&transaction.transaction_type);
+            params.push(&transaction.inner_instruction_index);
+            params.push(&transaction.slot);
+            params.push(&transaction.finalized);
 
             if index < transactions.len() - 1 {
                 query += ", ";
             }
         }
-        query += " ON CONFLICT (transaction_id, instruction_index) DO NOTHING";
+        query += " ";
+        query += ON_CONFLICT;
 
         client.execute(&query, &params).await?;
         Ok(())
     }
 
+    async fn remove_unfinalized(&self, up_to_slot: u64) -> Result<u64> {
+        let client = get_client(&self.pool).await?;
+        let removed = client
+            .execute(
+                "DELETE FROM user_transaction WHERE NOT finalized AND slot <= $1",
+                &[&(up_to_slot as i64)],
+            )
+            .await?;
+        Ok(removed)
+    }
+
     async fn total_deposit_by_wallet(&self, wallet: &Pubkey) -> Result<FPUSDC> {
         let client = get_client(&self.pool).await?;
         let rows = client
@@ -270,6 +318,31 @@ impl UserTransactionRepository for PostgresUserTransactionRepository {
             })?;
         Ok(total)
     }
+
+    async fn balance_changes_by_wallet(
+        &self,
+        wallet: &Pubkey,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<service::model::transaction::Transaction>> {
+        let client = get_client(&self.pool).await?;
+        let rows = client
+            .query(
+                "SELECT * FROM user_transaction
+                WHERE wallet = $1 AND transaction_type IN ($2, $3) AND transaction_time > $4
+                ORDER BY transaction_time, sl_no",
+                &[
+                    &wallet.to_string(),
+                    &TransactionType::DepositCompleted.to_string(),
+                    &TransactionType::WithdrawCompleted.to_string(),
+                    &since,
+                ],
+            )
+            .await?;
+        rows.into_iter()
+            .map(Transaction::from)
+            .map(service::model::transaction::Transaction::try_from)
+            .collect()
+    }
 }
 
 pub struct PostgresTransactionHistoryRepository {
@@ -0,0 +1,18 @@
+use anyhow::Result;
+use store::advisory_lock::AdvisoryLock;
+
+use crate::common;
+
+#[tokio::test]
+async fn test_advisory_lock() -> Result<()> {
+    let pool = common::setup().await;
+    let key = rand::random::<i64>();
+
+    let lock = AdvisoryLock::try_acquire(&pool, key).await?.expect("lock is free");
+    assert!(AdvisoryLock::try_acquire(&pool, key).await?.is_none());
+
+    lock.release().await?;
+    let lock = AdvisoryLock::try_acquire(&pool, key).await?;
+    assert!(lock.is_some());
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use chrono::Utc;
+use rand::{thread_rng, Rng};
+use service::{
+    campaign::CampaignRepository,
+    model::campaign::{Campaign, CampaignEligibility, CampaignGrant, CampaignReward, Referral},
+};
+use solana_sdk::pubkey::Pubkey;
+use store::campaigns::PostgresCampaignRepository;
+use uuid::Uuid;
+
+use crate::common;
+
+async fn get_repo() -> PostgresCampaignRepository {
+    let pool = common::setup().await;
+    PostgresCampaignRepository::new(pool)
+}
+
+fn new_campaign(start_epoch: u64, end_epoch: Option<u64>) -> Campaign {
+    Campaign {
+        id: Uuid::new_v4(),
+        name: "Launch".into(),
+        start_epoch,
+        end_epoch,
+        eligibility: CampaignEligibility {
+            min_stake: Some("50".parse().unwrap()),
+            new_users_only: false,
+            min_draws_played: Some(3),
+            referred_only: true,
+        },
+        reward: CampaignReward::PerStake {
+            amount: "25".parse().unwrap(),
+        },
+        max_sequences_per_wallet: Some(10),
+        active: true,
+        created_by: "ops".into(),
+        created_at: common::db_time(Utc::now()),
+    }
+}
+
+fn new_grant(campaign_id: Uuid, wallet: Pubkey, epoch_index: u64, num_sequences: u32) -> CampaignGrant {
+    CampaignGrant {
+        campaign_id,
+        wallet,
+        epoch_index,
+        num_sequences,
+        granted_at: common::db_time(Utc::now()),
+    }
+}
+
+#[tokio::test]
+async fn test_create_campaign() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = thread_rng().gen_range(1_000_000..u32::MAX as u64);
+    let campaign = new_campaign(epoch_index, Some(epoch_index + 1));
+    assert_eq!(repo.create(&campaign).await?, campaign);
+    let open_ended = repo.create(&new_campaign(epoch_index + 1, None)).await?;
+
+    let running = |campaigns: Vec<Campaign>| {
+        campaigns
+            .into_iter()
+            .map(|campaign| campaign.id)
+            .filter(|id| [campaign.id, open_ended.id].contains(id))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(running(repo.running_by_epoch_index(epoch_index - 1).await?), vec![]);
+    assert_eq!(
+        running(repo.running_by_epoch_index(epoch_index).await?),
+        vec![campaign.id]
+    );
+    assert_eq!(
+        running(repo.running_by_epoch_index(epoch_index + 1).await?),
+        vec![campaign.id, open_ended.id]
+    );
+    assert_eq!(
+        running(repo.running_by_epoch_index(epoch_index + 2).await?),
+        vec![open_ended.id]
+    );
+
+    let paused = repo.set_active(&open_ended.id, false).await?.unwrap();
+    assert!(!paused.active);
+    assert_eq!(running(repo.running_by_epoch_index(epoch_index + 2).await?), vec![]);
+    assert_eq!(repo.set_active(&Uuid::new_v4(), false).await?, None);
+    assert!(repo.all().await?.contains(&paused));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_campaign_grant() -> Result<()> {
+    let repo = get_repo().await;
+    let campaign = repo.create(&new_campaign(1, None)).await?;
+    let wallet = Pubkey::new_unique();
+
+    let grant = new_grant(campaign.id, wallet, 1, 3);
+    assert_eq!(repo.create_grant(&grant).await?, Some(grant.clone()));
+    // Once per epoch
+    assert_eq!(repo.create_grant(&new_grant(campaign.id, wallet, 1, 5)).await?, None);
+    let next = new_grant(campaign.id, wallet, 2, 4);
+    repo.create_grant(&next).await?;
+    repo.create_grant(&new_grant(campaign.id, Pubkey::new_unique(), 2, 1))
+        .await?;
+
+    assert_eq!(
+        repo.grants_by_wallet_and_epoch_index(&wallet, 1).await?,
+        vec![grant.clone()]
+    );
+    assert_eq!(repo.grants_by_wallet(&wallet).await?, vec![grant.clone(), next]);
+    assert_eq!(
+        repo.num_sequences_by_campaign_and_wallet(&campaign.id, &wallet).await?,
+        7
+    );
+    assert_eq!(
+        repo.num_sequences_by_campaign_and_wallet(&campaign.id, &Pubkey::new_unique())
+            .await?,
+        0
+    );
+    assert_eq!(repo.grants_by_campaign(&campaign.id, 10, 0).await?.len(), 3);
+    assert_eq!(repo.grants_by_campaign(&campaign.id, 10, 1).await?.len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_referral() -> Result<()> {
+    let repo = get_repo().await;
+    let referral = Referral {
+        wallet: Pubkey::new_unique(),
+        referrer: Pubkey::new_unique(),
+        created_at: common::db_time(Utc::now()),
+    };
+    assert_eq!(repo.create_referral(&referral).await?, Some(referral.clone()));
+    let other = Referral {
+        referrer: Pubkey::new_unique(),
+        ..referral.clone()
+    };
+    assert_eq!(repo.create_referral(&other).await?, None);
+    assert_eq!(repo.referral_by_wallet(&referral.wallet).await?, Some(referral));
+    assert_eq!(repo.referral_by_wallet(&Pubkey::new_unique()).await?, None);
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
 use envconfig::Envconfig;
 use store::{connect, DbConfig};
@@ -9,3 +10,10 @@ pub async fn setup() -> Pool {
     let pool = connect(&db_config).await;
     pool
 }
+
+/// Truncates the time to the microseconds Postgres stores.
+pub fn db_time(time: DateTime<Utc>) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(&time.to_rfc3339_opts(chrono::SecondsFormat::Micros, true))
+        .unwrap()
+        .with_timezone(&Utc)
+}
@@ -0,0 +1,222 @@
+use anyhow::Result;
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use rand::Rng;
+use service::{
+    draw_stats::DrawStatsRepository,
+    epoch::{EpochRepository, FPUSDC},
+    model::{
+        draw_stats::{NumberFrequency, PrefixCount, SequenceTypeCount, TierPrizes},
+        epoch::{Epoch, EpochStatus},
+        prize::Prize,
+    },
+    prize::PrizeRepository,
+    solana::{InsuranceCfg, YieldSplitCfg},
+    tickets::{Sequence, SequenceType, Ticket, TicketRepository},
+};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use store::{
+    draw_stats::PostgresDrawStatsRepository, epochs::PostgresEpochRepository, prizes::PostgresPrizeRepository,
+    tickets::PostgresTicketRepository,
+};
+
+use crate::common;
+
+fn create_epoch(status: EpochStatus, winning_combination: Option<[u8; 6]>) -> Epoch {
+    let now = common::db_time(Utc::now());
+    Epoch {
+        pubkey: Keypair::new().pubkey(),
+        index: rand::thread_rng().gen_range(2_000_000..3_000_000),
+        status,
+        winning_combination,
+        yield_split_cfg: YieldSplitCfg {
+            jackpot: "1_000_000".parse().unwrap(),
+            insurance: InsuranceCfg {
+                premium: 0u8.into(),
+                probability: 0u8.into(),
+            },
+            treasury_ratio: "0.5".parse().unwrap(),
+            tier2_prize_share: 1,
+            tier3_prize_share: 1,
+        },
+        total_invested: None,
+        returns: None,
+        started_at: now,
+        expected_end_at: now,
+        ended_at: Some(now),
+        draw_enabled: None,
+    }
+}
+
+fn create_ticket(epoch_index: u64, sequences: &[([u8; 6], SequenceType)]) -> Ticket {
+    Ticket {
+        wallet: Pubkey::new_unique(),
+        epoch_index,
+        arweave_url: None,
+        sequences: sequences
+            .iter()
+            .map(|&(nums, sequence_type)| Sequence { nums, sequence_type })
+            .collect(),
+        balance: "100".into(),
+        price: "25".into(),
+        risq_id: None,
+    }
+}
+
+fn create_prize(epoch_index: u64, winner_index: u32, tier: u8, amount: &str) -> Prize {
+    Prize {
+        wallet: Pubkey::new_unique(),
+        epoch_index,
+        page: 0,
+        winner_index,
+        tier,
+        amount: amount.parse::<FPUSDC>().unwrap(),
+        claimable: true,
+        claimed: false,
+    }
+}
+
+#[tokio::test]
+async fn test_compute_by_epoch_index() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresDrawStatsRepository::new(pool.clone());
+    let epoch = create_epoch(EpochStatus::Ended, Some([1, 2, 3, 4, 5, 6]));
+    PostgresEpochRepository::new(pool.clone())
+        .create_or_update_epoch(&epoch)
+        .await?;
+
+    let tickets = PostgresTicketRepository::new(pool.clone());
+    tickets
+        .create(&create_ticket(
+            epoch.index,
+            &[
+                ([1, 2, 3, 4, 5, 6], SequenceType::Normal),
+                ([7, 8, 9, 10, 11, 1], SequenceType::SignUpBonus),
+            ],
+        ))
+        .await?;
+    tickets
+        .create(&create_ticket(
+            epoch.index,
+            &[([1, 2, 3, 4, 6, 1], SequenceType::Normal)],
+        ))
+        .await?;
+    PostgresPrizeRepository::new(pool, 100)
+        .upsert_prizes(&[
+            create_prize(epoch.index, 0, 1, "1000"),
+            create_prize(epoch.index, 1, 3, "2.5"),
+            create_prize(epoch.index, 2, 3, "2.5"),
+        ])
+        .await?;
+
+    let stats = repo.compute_by_epoch_index(epoch.index).await?.unwrap();
+    assert_eq!(stats.status, EpochStatus::Ended);
+    assert_eq!(stats.num_players, 2);
+    assert_eq!(
+        stats.sequences_by_type,
+        vec![
+            SequenceTypeCount {
+                sequence_type: SequenceType::Normal,
+                num_sequences: 2,
+            },
+            SequenceTypeCount {
+                sequence_type: SequenceType::SignUpBonus,
+                num_sequences: 1,
+            },
+        ]
+    );
+    let frequency = |number, num_sequences| NumberFrequency { number, num_sequences };
+    assert_eq!(stats.main_number_frequencies.len(), 11);
+    assert_eq!(stats.main_number_frequencies[0], frequency(1, 2));
+    assert_eq!(stats.main_number_frequencies[5], frequency(6, 1));
+    assert_eq!(stats.last_number_frequencies, vec![frequency(1, 2), frequency(6, 1)]);
+    assert_eq!(
+        stats.shared_prefixes,
+        vec![PrefixCount {
+            prefix: vec![1, 2, 3, 4],
+            num_tickets: 2,
+            num_sequences: 2,
+        }]
+    );
+    assert_eq!(
+        stats.prizes_by_tier,
+        vec![
+            TierPrizes {
+                tier: 1,
+                num_winners: 1,
+                total_amount: "1000".parse().unwrap(),
+            },
+            TierPrizes {
+                tier: 3,
+                num_winners: 2,
+                total_amount: "5".parse().unwrap(),
+            },
+        ]
+    );
+
+    assert_eq!(repo.compute_by_epoch_index(u64::MAX).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresDrawStatsRepository::new(pool.clone());
+    let epoch = create_epoch(EpochStatus::Ended, None);
+    PostgresEpochRepository::new(pool.clone())
+        .create_or_update_epoch(&epoch)
+        .await?;
+    PostgresTicketRepository::new(pool.clone())
+        .create(&create_ticket(
+            epoch.index,
+            &[([1, 2, 3, 4, 5, 6], SequenceType::UserPicked)],
+        ))
+        .await?;
+
+    assert_eq!(repo.cached_by_epoch_index(epoch.index).await?, None);
+    let mut stats = repo.compute_by_epoch_index(epoch.index).await?.unwrap();
+    stats.computed_at = common::db_time(stats.computed_at);
+    stats.prizes_by_tier = vec![TierPrizes {
+        tier: 2,
+        num_winners: 3,
+        total_amount: "12.345678".parse().unwrap(),
+    }];
+    repo.cache(&stats).await?;
+    assert_eq!(repo.cached_by_epoch_index(epoch.index).await?, Some(stats.clone()));
+
+    stats.num_players = 3;
+    repo.cache(&stats).await?;
+    assert_eq!(repo.cached_by_epoch_index(epoch.index).await?, Some(stats));
+
+    // Indexing prizes of the epoch drops its cached stats
+    PostgresPrizeRepository::new(pool, 100)
+        .upsert_prizes(&[create_prize(epoch.index, 0, 2, "4")])
+        .await?;
+    assert_eq!(repo.cached_by_epoch_index(epoch.index).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_winning_combinations() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresDrawStatsRepository::new(pool.clone());
+    let epochs = PostgresEpochRepository::new(pool);
+    let drawn = create_epoch(EpochStatus::Ended, Some([10, 20, 30, 40, 50, 5]));
+    let running = create_epoch(EpochStatus::Running, None);
+    epochs.create_or_update_epoch(&drawn).await?;
+    epochs.create_or_update_epoch(&running).await?;
+
+    let winning_combinations = repo.winning_combinations(i64::MAX as usize, 0).await?;
+    assert!(winning_combinations
+        .windows(2)
+        .all(|pair| pair[0].epoch_index > pair[1].epoch_index));
+    let winning_combination = winning_combinations
+        .iter()
+        .find(|winning_combination| winning_combination.epoch_index == drawn.index)
+        .unwrap();
+    assert_eq!(winning_combination.combination, [10, 20, 30, 40, 50, 5]);
+    assert!(winning_combinations
+        .iter()
+        .all(|winning_combination| winning_combination.epoch_index != running.index));
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde_json::json;
+use service::{epoch::EpochAuditRepository, model::epoch_audit::EpochAuditLog};
+use store::epoch_audit::PostgresEpochAuditRepository;
+use uuid::Uuid;
+
+use crate::common;
+
+async fn get_repo() -> PostgresEpochAuditRepository {
+    let pool = common::setup().await;
+    PostgresEpochAuditRepository::new(pool)
+}
+
+#[tokio::test]
+async fn test_record_epoch_audit_log() -> Result<()> {
+    let repo = get_repo().await;
+    // Newer than anything recorded by other tests
+    let created_at = common::db_time(Utc::now() + Duration::days(365));
+    let dry_run = EpochAuditLog {
+        id: Uuid::new_v4(),
+        operator: "operator".into(),
+        operation: "fund_jackpot".into(),
+        params: json!({}),
+        dry_run: true,
+        signature: None,
+        error: Some("Staking Error: InvalidEpochStatus".into()),
+        created_at,
+    };
+    let sent = EpochAuditLog {
+        id: Uuid::new_v4(),
+        operation: "exit_investment".into(),
+        params: json!({ "investor": "Fake", "return_amount": "100" }),
+        dry_run: false,
+        signature: Some("signature".into()),
+        error: None,
+        created_at: created_at + Duration::seconds(1),
+        ..dry_run.clone()
+    };
+    repo.record(&dry_run).await?;
+    repo.record(&sent).await?;
+
+    let recent = repo.recent(2, 0).await?;
+    assert_eq!(recent, vec![sent, dry_run]);
+    Ok(())
+}
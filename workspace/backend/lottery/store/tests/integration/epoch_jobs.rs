@@ -0,0 +1,104 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use service::{
+    epoch::EpochJobRepository,
+    model::epoch_job::{EpochJobCommand, EpochJobStatus},
+};
+use store::epoch_jobs::PostgresEpochJobRepository;
+
+use crate::common;
+
+async fn get_repo() -> PostgresEpochJobRepository {
+    let pool = common::setup().await;
+    PostgresEpochJobRepository::new(pool)
+}
+
+#[tokio::test]
+async fn test_enqueue_epoch_job() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = rand::random::<u32>() as u64;
+    let due_at = common::db_time(Utc::now());
+
+    let job = repo
+        .enqueue(epoch_index, EpochJobCommand::EnterInvestment, due_at)
+        .await?;
+    assert_eq!(job.status, EpochJobStatus::Pending);
+    assert_eq!(job.attempts, 0);
+    assert_eq!(job.due_at, due_at);
+    assert_eq!(job.next_attempt_at, due_at);
+
+    // Enqueueing again keeps the existing job
+    let again = repo
+        .enqueue(
+            epoch_index,
+            EpochJobCommand::EnterInvestment,
+            due_at + Duration::hours(1),
+        )
+        .await?;
+    assert_eq!(again, job);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_epoch_job() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = rand::random::<u32>() as u64;
+    let command = EpochJobCommand::PublishWinners;
+    let due_at = common::db_time(Utc::now());
+    repo.enqueue(epoch_index, command, due_at).await?;
+
+    let job = repo.start(epoch_index, command).await?;
+    assert_eq!(job.status, EpochJobStatus::Running);
+    assert_eq!(job.attempts, 1);
+
+    let next_attempt_at = common::db_time(Utc::now() + Duration::minutes(1));
+    let job = repo
+        .fail(epoch_index, command, "RPC error", Some(next_attempt_at))
+        .await?;
+    assert_eq!(job.status, EpochJobStatus::Pending);
+    assert_eq!(job.last_error.as_deref(), Some("RPC error"));
+    assert_eq!(job.next_attempt_at, next_attempt_at);
+
+    // The backoff survives a restart
+    let job = repo.enqueue(epoch_index, command, due_at).await?;
+    assert_eq!(job.next_attempt_at, next_attempt_at);
+
+    repo.start(epoch_index, command).await?;
+    let job = repo.succeed(epoch_index, command).await?;
+    assert_eq!(job.status, EpochJobStatus::Succeeded);
+    assert_eq!(job.attempts, 2);
+    assert_eq!(job.last_error, None);
+
+    let recent = repo.recent(1, 0).await?;
+    assert_eq!(recent.len(), 1);
+    assert!(recent[0].updated_at >= job.updated_at);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_start_missing_epoch_job() -> Result<()> {
+    let repo = get_repo().await;
+    let res = repo.start(u64::MAX, EpochJobCommand::FundJackpot).await;
+    assert!(res.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_give_up_epoch_job() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = rand::random::<u32>() as u64;
+    let command = EpochJobCommand::FundJackpot;
+    let due_at = common::db_time(Utc::now());
+    repo.enqueue(epoch_index, command, due_at).await?;
+
+    repo.start(epoch_index, command).await?;
+    let job = repo.fail(epoch_index, command, "Winners not set", None).await?;
+    assert_eq!(job.status, EpochJobStatus::Failed);
+    assert_eq!(job.last_error.as_deref(), Some("Winners not set"));
+    assert_eq!(job.next_attempt_at, due_at);
+
+    // Still failed when the indexer looks it up again
+    let job = repo.enqueue(epoch_index, command, due_at).await?;
+    assert_eq!(job.status, EpochJobStatus::Failed);
+    Ok(())
+}
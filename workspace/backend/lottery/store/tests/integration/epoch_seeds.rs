@@ -0,0 +1,34 @@
+use anyhow::Result;
+use rand::{thread_rng, Rng};
+use service::tickets::{EpochSeed, EpochSeedRepository};
+use solana_sdk::{hash::Hash, pubkey::Pubkey};
+use store::epoch_seeds::PostgresEpochSeedRepository;
+
+use crate::common;
+
+#[tokio::test]
+async fn test_create_epoch_seed() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresEpochSeedRepository::new(pool);
+    // Random so that we don't have to reset the DB between re-runs of the tests.
+    let epoch_index = thread_rng().gen();
+    assert_eq!(repo.by_epoch_index(epoch_index).await?, None);
+
+    let seed = EpochSeed {
+        epoch_index,
+        epoch_pubkey: Pubkey::new_unique(),
+        slot: 42,
+        slot_hash: Hash::new_unique(),
+    };
+    assert_eq!(repo.create(&seed).await?, seed);
+    assert_eq!(repo.by_epoch_index(epoch_index).await?, Some(seed.clone()));
+
+    // The first seed recorded for an epoch is kept
+    let other = EpochSeed {
+        slot_hash: Hash::new_unique(),
+        ..seed.clone()
+    };
+    assert_eq!(repo.create(&other).await?, seed);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use service::{model::login_challenge::LoginChallenge, sign_in::LoginChallengeRepository};
+use solana_sdk::pubkey::Pubkey;
+use store::login_challenges::PostgresLoginChallengeRepository;
+
+use crate::common;
+
+async fn get_repo() -> PostgresLoginChallengeRepository {
+    let pool = common::setup().await;
+    PostgresLoginChallengeRepository::new(pool)
+}
+
+fn new_challenge(ttl: Duration) -> LoginChallenge {
+    let issued_at = common::db_time(Utc::now());
+    LoginChallenge {
+        wallet: Pubkey::new_unique(),
+        nonce: format!("nonce-{}", rand::random::<u64>()),
+        issued_at,
+        expires_at: issued_at + ttl,
+    }
+}
+
+#[tokio::test]
+async fn test_take_login_challenge() -> Result<()> {
+    let repo = get_repo().await;
+    let challenge = new_challenge(Duration::minutes(5));
+    repo.store(&challenge).await?;
+
+    let wrong_nonce = repo.take(&challenge.wallet, "wrong", Utc::now()).await?;
+    assert_eq!(wrong_nonce, None);
+
+    let taken = repo.take(&challenge.wallet, &challenge.nonce, Utc::now()).await?;
+    assert_eq!(taken, Some(challenge.clone()));
+
+    // Single use
+    let taken = repo.take(&challenge.wallet, &challenge.nonce, Utc::now()).await?;
+    assert_eq!(taken, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_login_challenge() -> Result<()> {
+    let repo = get_repo().await;
+    let challenge = new_challenge(Duration::minutes(5));
+    repo.store(&challenge).await?;
+
+    let later = challenge.expires_at + Duration::seconds(1);
+    let taken = repo.take(&challenge.wallet, &challenge.nonce, later).await?;
+    assert_eq!(taken, None);
+
+    Ok(())
+}
@@ -1,9 +1,21 @@
 mod common;
 
+mod advisory_lock;
+mod campaigns;
+mod draw_stats;
+mod epoch_audit;
+mod epoch_jobs;
+mod epoch_seeds;
 mod epochs;
 mod faucet;
 mod health_check;
+mod login_challenges;
+mod notifications;
 mod prizes;
+mod program_accounts;
+mod published_manifests;
+mod sequence_layout;
 mod stake_update;
+mod stake_update_reviews;
 mod tickets;
 mod transactions;
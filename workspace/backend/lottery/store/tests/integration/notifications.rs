@@ -0,0 +1,125 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde_json::json;
+use service::{
+    model::notification::{
+        NotificationChannel, NotificationDelivery, NotificationDeliveryStatus, NotificationKind,
+        NotificationPreferences,
+    },
+    notification::{NotificationDeliveryRepository, NotificationPreferencesRepository},
+};
+use solana_sdk::pubkey::Pubkey;
+use store::notifications::{PostgresNotificationDeliveryRepository, PostgresNotificationPreferencesRepository};
+
+use crate::common;
+
+#[tokio::test]
+async fn test_upsert_notification_preferences() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresNotificationPreferencesRepository::new(pool);
+    let wallet = Pubkey::new_unique();
+    assert_eq!(repo.by_wallet(&wallet).await?, None);
+
+    let preferences = NotificationPreferences {
+        wallet,
+        email: Some("winner@example.com".into()),
+        webhook_url: None,
+        webhook_secret: None,
+        kinds: vec![NotificationKind::PrizeWon, NotificationKind::EpochEnded],
+        updated_at: common::db_time(Utc::now()),
+    };
+    assert_eq!(repo.upsert(&preferences).await?, preferences);
+    assert!(repo
+        .subscribed(NotificationKind::EpochEnded)
+        .await?
+        .contains(&preferences));
+
+    let updated = NotificationPreferences {
+        webhook_url: Some("https://example.com/hook".into()),
+        webhook_secret: Some("secret".into()),
+        kinds: vec![NotificationKind::DepositCompleted],
+        ..preferences.clone()
+    };
+    repo.upsert(&updated).await?;
+    assert_eq!(repo.by_wallet(&wallet).await?, Some(updated.clone()));
+    assert!(!repo.subscribed(NotificationKind::EpochEnded).await?.contains(&updated));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_notification_outbox() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresNotificationDeliveryRepository::new(pool);
+    let key = format!("epoch_ended:{}", rand::random::<u32>());
+    // Due before the deliveries queued by other tests
+    let queued_at = common::db_time(Utc.timestamp(946_684_800 + rand::random::<u32>() as i64 % 1_000_000, 0));
+    let delivery = NotificationDelivery {
+        event_key: key.clone(),
+        wallet: Pubkey::new_unique(),
+        channel: NotificationChannel::Email,
+        subject: "Draw #1 has ended".into(),
+        body: "The winners of draw #1 have been published.".into(),
+        payload: json!({ "id": key }),
+        status: NotificationDeliveryStatus::Pending,
+        attempts: 0,
+        last_error: None,
+        next_attempt_at: queued_at,
+        created_at: queued_at,
+    };
+
+    assert!(repo.enqueue(&delivery).await?);
+    assert!(!repo.enqueue(&delivery).await?);
+    let webhook = NotificationDelivery {
+        channel: NotificationChannel::Webhook,
+        ..delivery.clone()
+    };
+    assert!(repo.enqueue(&webhook).await?);
+
+    let claim = |now: DateTime<Utc>| {
+        let repo = repo.clone();
+        let key = key.clone();
+        async move {
+            let claimed = repo.claim_due(now, now + Duration::minutes(5), 1000).await?;
+            Ok::<_, anyhow::Error>(
+                claimed
+                    .into_iter()
+                    .filter(|claimed| claimed.event_key == key)
+                    .collect::<Vec<_>>(),
+            )
+        }
+    };
+    let claimed = claim(queued_at).await?;
+    assert_eq!(claimed.len(), 2);
+    assert!(claimed.iter().all(|claimed| claimed.attempts == 1));
+    assert!(claimed
+        .iter()
+        .all(|claimed| claimed.next_attempt_at == queued_at + Duration::minutes(5)));
+    // Leased to the worker that claimed them
+    assert!(claim(queued_at).await?.is_empty());
+
+    let email = claimed
+        .iter()
+        .find(|claimed| claimed.channel == NotificationChannel::Email)
+        .unwrap();
+    repo.succeed(email, Utc::now()).await?;
+    let webhook = claimed
+        .iter()
+        .find(|claimed| claimed.channel == NotificationChannel::Webhook)
+        .unwrap();
+    repo.fail(
+        webhook,
+        "Webhook responded with 500",
+        Some(queued_at + Duration::minutes(1)),
+    )
+    .await?;
+
+    let retried = claim(queued_at + Duration::minutes(1)).await?;
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].channel, NotificationChannel::Webhook);
+    assert_eq!(retried[0].attempts, 2);
+    assert_eq!(retried[0].last_error.as_deref(), Some("Webhook responded with 500"));
+
+    repo.fail(&retried[0], "Webhook responded with 500", None).await?;
+    assert!(claim(queued_at + Duration::days(1)).await?.is_empty());
+    Ok(())
+}
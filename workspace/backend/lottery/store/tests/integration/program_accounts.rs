@@ -0,0 +1,75 @@
+use anyhow::Result;
+use chrono::Utc;
+use service::{
+    account_mirror::ProgramAccountRepository,
+    model::program_account::{MirroredAccountType, ProgramAccount},
+};
+use solana_sdk::pubkey::Pubkey;
+use store::program_accounts::PostgresProgramAccountRepository;
+
+use crate::common;
+
+fn program_account(pubkey: Pubkey, data: &[u8], slot: u64) -> ProgramAccount {
+    ProgramAccount {
+        pubkey,
+        account_type: MirroredAccountType::StakeUpdateRequest,
+        lamports: u64::MAX,
+        data: data.to_vec(),
+        slot,
+    }
+}
+
+#[tokio::test]
+async fn test_upsert_program_accounts() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresProgramAccountRepository::new(pool);
+    let pubkey = Pubkey::new_unique();
+
+    let account = program_account(pubkey, &[3, 1], 20);
+    repo.upsert(&[account.clone()]).await?;
+    // Older update delivered late
+    repo.upsert(&[program_account(pubkey, &[3, 0], 10)]).await?;
+    let accounts = repo.by_type(MirroredAccountType::StakeUpdateRequest).await?;
+    assert!(accounts.contains(&account));
+
+    let updated = program_account(pubkey, &[3, 2], 30);
+    repo.upsert(&[updated.clone()]).await?;
+    let accounts = repo.by_type(MirroredAccountType::StakeUpdateRequest).await?;
+    assert!(accounts.contains(&updated));
+    assert!(!accounts.contains(&account));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_remove_program_account() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresProgramAccountRepository::new(pool);
+    let pubkey = Pubkey::new_unique();
+    let account = program_account(pubkey, &[3, 1], 20);
+    repo.upsert(&[account.clone()]).await?;
+
+    // Closed before the last update
+    repo.remove(&pubkey, 10).await?;
+    let accounts = repo.by_type(MirroredAccountType::StakeUpdateRequest).await?;
+    assert!(accounts.contains(&account));
+
+    repo.remove(&pubkey, 20).await?;
+    let accounts = repo.by_type(MirroredAccountType::StakeUpdateRequest).await?;
+    assert!(!accounts.iter().any(|account| account.pubkey == pubkey));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_program_accounts_checked() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresProgramAccountRepository::new(pool);
+    let now = common::db_time(Utc::now());
+    repo.checked(now).await?;
+    let last_checked = repo.last_checked().await?;
+    assert!(last_checked >= Some(now), "{:?}", last_checked);
+
+    repo.subscribed(now).await?;
+    let last_subscribed = repo.last_subscribed().await?;
+    assert!(last_subscribed >= Some(now), "{:?}", last_subscribed);
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use rand::{thread_rng, Rng};
+use service::tickets::PublishedManifestRepository;
+use solana_sdk::hash::Hash;
+use store::published_manifests::PostgresPublishedManifestRepository;
+
+use crate::common;
+
+#[tokio::test]
+async fn test_create_published_manifest() -> Result<()> {
+    let pool = common::setup().await;
+    let repo = PostgresPublishedManifestRepository::new(pool);
+    // Random so that we don't have to reset the DB between re-runs of the tests.
+    let epoch_index = thread_rng().gen();
+    let hash = Hash::new_unique();
+    assert_eq!(repo.url_by_hash(epoch_index, &hash).await?, None);
+
+    repo.create(epoch_index, &hash, "ar://first").await?;
+    assert_eq!(
+        repo.url_by_hash(epoch_index, &hash).await?,
+        Some("ar://first".to_string())
+    );
+    assert_eq!(repo.url_by_hash(epoch_index, &Hash::new_unique()).await?, None);
+
+    // The first URL recorded for a manifest is kept
+    repo.create(epoch_index, &hash, "ar://second").await?;
+    assert_eq!(
+        repo.url_by_hash(epoch_index, &hash).await?,
+        Some("ar://first".to_string())
+    );
+
+    Ok(())
+}
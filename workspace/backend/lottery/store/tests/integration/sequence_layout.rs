@@ -0,0 +1,220 @@
+//! Compares the packed sequence layout against the previous one, a SMALLINT column per number with a 4-prefix index,
+//! written with multi-row INSERTs and aggregated in memory. Set `SEQUENCE_LAYOUT_BENCH_SIZE` to benchmark with more
+//! sequences, e.g. `SEQUENCE_LAYOUT_BENCH_SIZE=1000000 cargo test -p store sequence_layout -- --nocapture`.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use postgres_types::ToSql;
+use rand::{thread_rng, Rng};
+use rust_decimal::Decimal;
+use service::tickets::{pack_sequence, packed_prefix_range, unpack_sequence};
+use store::get_client;
+use tokio_postgres::{binary_copy::BinaryCopyInWriter, types::Type};
+
+use crate::common;
+
+// Bounded by the 65535 query parameters of a statement
+const INSERT_CHUNK: usize = 5000;
+
+fn bench_size() -> usize {
+    std::env::var("SEQUENCE_LAYOUT_BENCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(20_000)
+}
+
+fn report(name: &str, legacy: Duration, packed: Duration) {
+    println!(
+        "{name}: legacy {legacy:?}, packed {packed:?} ({:.1}x)",
+        legacy.as_secs_f64() / packed.as_secs_f64()
+    );
+}
+
+#[tokio::test]
+async fn test_sequence_layout() -> Result<()> {
+    let pool = common::setup().await;
+    let client = get_client(&pool).await?;
+    let mut rng = thread_rng();
+
+    // Temporary tables live as long as the connection, and aren't constrained by the ticket foreign key
+    client
+        .batch_execute(
+            "
+            CREATE TEMPORARY TABLE legacy_sequences (
+                wallet VARCHAR NOT NULL,
+                epoch_index NUMERIC(20,0) NOT NULL,
+                _1 SMALLINT NOT NULL,
+                _2 SMALLINT NOT NULL,
+                _3 SMALLINT NOT NULL,
+                _4 SMALLINT NOT NULL,
+                _5 SMALLINT NOT NULL,
+                _6 SMALLINT NOT NULL,
+                sequence_type VARCHAR NOT NULL
+            );
+            CREATE INDEX ON legacy_sequences (epoch_index, _1, _2, _3, _4);
+            CREATE TEMPORARY TABLE packed_sequences (LIKE sequences INCLUDING INDEXES);",
+        )
+        .await?;
+
+    let size = bench_size();
+    let epoch_index = Decimal::from(rng.gen::<u64>());
+    let wallets = (0..100).map(|i| format!("wallet{i}")).collect::<Vec<_>>();
+    // Few distinct prefixes so that prefix lookups match many sequences
+    let sequences = (0..size)
+        .map(|i| {
+            let nums = [
+                rng.gen_range(1..=4),
+                rng.gen_range(1..=4),
+                1,
+                rng.gen_range(1..=4),
+                rng.gen(),
+                rng.gen(),
+            ];
+            (wallets[i % wallets.len()].as_str(), nums)
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for chunk in sequences.chunks(INSERT_CHUNK) {
+        let nums = chunk.iter().map(|(_, nums)| nums.map(|n| n as i16)).collect::<Vec<_>>();
+        let mut query = "INSERT INTO legacy_sequences VALUES ".to_string();
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&epoch_index];
+        for (i, ((wallet, _), nums)) in chunk.iter().zip(&nums).enumerate() {
+            if i > 0 {
+                query += ", ";
+            }
+            let base = params.len();
+            query += &format!(
+                "(${}, $1, ${}, ${}, ${}, ${}, ${}, ${}, 'Normal')",
+                base + 1,
+                base + 2,
+                base + 3,
+                base + 4,
+                base + 5,
+                base + 6,
+                base + 7
+            );
+            params.push(wallet);
+            for n in nums {
+                params.push(n);
+            }
+        }
+        client.execute(&query, &params).await?;
+    }
+    let legacy_insert = start.elapsed();
+
+    let start = Instant::now();
+    let sink = client
+        .copy_in("COPY packed_sequences (wallet, epoch_index, packed, sequence_type) FROM STDIN BINARY")
+        .await?;
+    let mut writer = Box::pin(BinaryCopyInWriter::new(
+        sink,
+        &[Type::VARCHAR, Type::NUMERIC, Type::INT8, Type::VARCHAR],
+    ));
+    for (wallet, nums) in &sequences {
+        let packed = pack_sequence(nums) as i64;
+        writer
+            .as_mut()
+            .write(&[wallet, &epoch_index, &packed, &"Normal"])
+            .await?;
+    }
+    writer.as_mut().finish().await?;
+    let packed_insert = start.elapsed();
+    report("insert", legacy_insert, packed_insert);
+
+    client
+        .batch_execute("ANALYZE legacy_sequences; ANALYZE packed_sequences;")
+        .await?;
+
+    // Sequences matching a 4-prefix, as when calculating winners
+    let prefix = sequences[0].1[..4].to_vec();
+    let start = Instant::now();
+    let legacy_prefix = prefix.iter().map(|&n| n as i16).collect::<Vec<_>>();
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM legacy_sequences WHERE epoch_index = $1 AND _1 = $2 AND _2 = $3 AND _3 = $4 AND _4 = $5",
+            &[
+                &epoch_index,
+                &legacy_prefix[0],
+                &legacy_prefix[1],
+                &legacy_prefix[2],
+                &legacy_prefix[3],
+            ],
+        )
+        .await?;
+    let legacy_count: i64 = row.get(0);
+    let legacy_lookup = start.elapsed();
+
+    let start = Instant::now();
+    let (from, to) = packed_prefix_range(&prefix)?;
+    let row = client
+        .query_one(
+            "SELECT COUNT(*) FROM packed_sequences WHERE epoch_index = $1 AND packed BETWEEN $2 AND $3",
+            &[&epoch_index, &(from as i64), &(to as i64)],
+        )
+        .await?;
+    let packed_count: i64 = row.get(0);
+    let packed_lookup = start.elapsed();
+    report("prefix lookup", legacy_lookup, packed_lookup);
+
+    let expected_count = sequences.iter().filter(|(_, nums)| nums[..4] == prefix[..]).count();
+    assert_eq!(legacy_count as usize, expected_count);
+    assert_eq!(packed_count as usize, expected_count);
+
+    // Most common 4-prefix among distinct sequences, as when calculating the optimal winning combination
+    let start = Instant::now();
+    let rows = client
+        .query(
+            "SELECT DISTINCT ARRAY[_1, _2, _3, _4, _5, _6] AS sequence FROM legacy_sequences WHERE epoch_index = $1",
+            &[&epoch_index],
+        )
+        .await?;
+    let mut prefix_counts: HashMap<[i16; 4], usize> = HashMap::new();
+    for row in rows {
+        let sequence = row.get::<_, [i16; 6]>("sequence");
+        *prefix_counts
+            .entry([sequence[0], sequence[1], sequence[2], sequence[3]])
+            .or_insert(0) += 1;
+    }
+    let legacy_max = prefix_counts.into_values().max().unwrap_or(0);
+    let legacy_aggregate = start.elapsed();
+
+    let start = Instant::now();
+    let row = client
+        .query_one(
+            "
+            SELECT packed >> 16 AS prefix, COUNT(DISTINCT packed) AS num_sequences, MIN(packed) AS sequence
+            FROM packed_sequences
+            WHERE epoch_index = $1
+            GROUP BY prefix
+            ORDER BY num_sequences DESC, prefix
+            LIMIT 1",
+            &[&epoch_index],
+        )
+        .await?;
+    let packed_max: i64 = row.get("num_sequences");
+    let packed_sequence = unpack_sequence(row.get::<_, i64>("sequence") as u64);
+    let packed_aggregate = start.elapsed();
+    report("most common prefix", legacy_aggregate, packed_aggregate);
+
+    assert_eq!(packed_max as usize, legacy_max);
+    assert!(sequences.iter().any(|(_, nums)| *nums == packed_sequence));
+
+    let sizes = client
+        .query_one(
+            "SELECT pg_total_relation_size('legacy_sequences') AS legacy, pg_total_relation_size('packed_sequences') AS packed",
+            &[],
+        )
+        .await?;
+    println!(
+        "size: legacy {} bytes, packed {} bytes",
+        sizes.get::<_, i64>("legacy"),
+        sizes.get::<_, i64>("packed")
+    );
+
+    Ok(())
+}
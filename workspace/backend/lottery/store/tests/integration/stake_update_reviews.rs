@@ -0,0 +1,72 @@
+use anyhow::Result;
+use chrono::Utc;
+use service::{
+    model::{
+        stake_update::StakeUpdateType,
+        stake_update_review::{StakeUpdateReview, StakeUpdateReviewStatus},
+    },
+    screening::StakeUpdateReviewRepository,
+};
+use solana_sdk::pubkey::Pubkey;
+use store::stake_update_reviews::PostgresStakeUpdateReviewRepository;
+use uuid::Uuid;
+
+use crate::common;
+
+async fn get_repo() -> PostgresStakeUpdateReviewRepository {
+    let pool = common::setup().await;
+    PostgresStakeUpdateReviewRepository::new(pool)
+}
+
+fn new_review(wallet: Pubkey) -> StakeUpdateReview {
+    StakeUpdateReview {
+        id: Uuid::new_v4(),
+        wallet,
+        amount: "100".parse().unwrap(),
+        type_: StakeUpdateType::Deposit,
+        reason: "Wallet is on the sanctions list".into(),
+        status: StakeUpdateReviewStatus::Pending,
+        created_at: common::db_time(Utc::now()),
+        reviewed_by: None,
+        reviewed_at: None,
+    }
+}
+
+#[tokio::test]
+async fn test_create_stake_update_review() -> Result<()> {
+    let repo = get_repo().await;
+    let review = new_review(Pubkey::new_unique());
+    let created = repo.create(&review).await?;
+    assert_eq!(created, review);
+
+    assert_eq!(repo.by_id(&review.id).await?, Some(review.clone()));
+    assert_eq!(repo.pending_by_wallet(&review.wallet).await?, Some(review.clone()));
+    assert_eq!(repo.pending_by_wallet(&Pubkey::new_unique()).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resolve_stake_update_review() -> Result<()> {
+    let repo = get_repo().await;
+    let review = repo.create(&new_review(Pubkey::new_unique())).await?;
+
+    let reviewed_at = common::db_time(Utc::now());
+    let resolved = repo
+        .resolve(&review.id, StakeUpdateReviewStatus::Rejected, "operator", reviewed_at)
+        .await?
+        .unwrap();
+    assert_eq!(resolved.status, StakeUpdateReviewStatus::Rejected);
+    assert_eq!(resolved.reviewed_by, Some("operator".into()));
+    assert_eq!(resolved.reviewed_at, Some(reviewed_at));
+    assert_eq!(repo.pending_by_wallet(&review.wallet).await?, None);
+
+    // Only pending reviews can be resolved
+    let resolved_again = repo
+        .resolve(&review.id, StakeUpdateReviewStatus::Approved, "operator", reviewed_at)
+        .await?;
+    assert_eq!(resolved_again, None);
+
+    let rejected = repo.by_status(StakeUpdateReviewStatus::Rejected, 1000, 0).await?;
+    assert!(rejected.contains(&resolved));
+    Ok(())
+}
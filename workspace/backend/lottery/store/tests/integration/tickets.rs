@@ -5,7 +5,7 @@ use pretty_assertions::{assert_eq, assert_ne};
 use rand::{distributions::Standard, prelude::SliceRandom, thread_rng, Rng};
 use rust_decimal::Decimal;
 use service::{
-    model::ticket::TicketError,
+    model::ticket::{PrefixMatches, TicketError},
     tickets::{Sequence, SequenceType, Ticket, TicketRepository, WalletRisqId},
 };
 use solana_sdk::pubkey::{self, Pubkey};
@@ -19,10 +19,11 @@ fn create_ticket() -> Ticket {
     let mut sequences = Vec::new();
     let sequences_count: usize = rng.gen_range(0..=5);
     for _ in 0..sequences_count {
-        let sequence_type = match rng.gen_range(0..3) {
+        let sequence_type = match rng.gen_range(0..4) {
             0 => SequenceType::Normal,
             1 => SequenceType::SignUpBonus,
             2 => SequenceType::AirdropBonus,
+            3 => SequenceType::UserPicked,
             _ => unreachable!(),
         };
         let mut sequence = Sequence {
@@ -115,6 +116,43 @@ async fn test_add_sequences() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_replace_sequences() -> Result<()> {
+    let repo = get_repo().await;
+
+    let unchanged_ticket = create_ticket();
+    repo.create(&unchanged_ticket).await?;
+
+    let mut ticket = create_ticket();
+    repo.create(&ticket).await?;
+
+    let replaced_types = [SequenceType::Normal, SequenceType::UserPicked];
+    let new_sequences = create_ticket()
+        .sequences
+        .into_iter()
+        .map(|sequence| Sequence {
+            sequence_type: SequenceType::UserPicked,
+            ..sequence
+        })
+        .collect::<Vec<_>>();
+    ticket
+        .sequences
+        .retain(|sequence| !replaced_types.contains(&sequence.sequence_type));
+    ticket.sequences.extend_from_slice(&new_sequences);
+
+    let res = repo
+        .replace_sequences(&ticket.wallet, ticket.epoch_index, &replaced_types, &new_sequences)
+        .await?;
+    assert_eq!(res, ticket);
+
+    let res = repo
+        .by_wallet_and_epoch_index(&unchanged_ticket.wallet, unchanged_ticket.epoch_index)
+        .await?;
+    assert_eq!(res, Some(unchanged_ticket));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_update_arweave_url() -> Result<()> {
     let repo = get_repo().await;
@@ -150,6 +188,41 @@ async fn test_update_arweave_url() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_update_balance() -> Result<()> {
+    let repo = get_repo().await;
+
+    let ticket_unchanged = create_ticket();
+    repo.create(&ticket_unchanged).await?;
+
+    let ticket = create_ticket();
+    repo.create(&ticket).await?;
+
+    let res = repo
+        .update_balance(&ticket.wallet, ticket.epoch_index, "24".to_string(), "4".to_string())
+        .await?;
+    assert_eq!(res, Some(()));
+
+    let ticket_found = repo
+        .by_wallet_and_epoch_index(&ticket.wallet, ticket.epoch_index)
+        .await?;
+    assert_eq!(
+        ticket_found,
+        Some(Ticket {
+            balance: "24".to_string(),
+            price: "4".to_string(),
+            ..ticket
+        })
+    );
+
+    let ticket_found = repo
+        .by_wallet_and_epoch_index(&ticket_unchanged.wallet, ticket_unchanged.epoch_index)
+        .await?;
+    assert_eq!(ticket_found, Some(ticket_unchanged));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_by_wallet_and_epoch_index() -> Result<()> {
     let repo = get_repo().await;
@@ -244,6 +317,33 @@ async fn test_by_wallets_and_epoch_index() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_by_epoch_index() -> Result<()> {
+    let repo = get_repo().await;
+
+    let epoch_index = thread_rng().gen();
+
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        let mut ticket = create_ticket();
+        ticket.epoch_index = epoch_index;
+        repo.create(&ticket).await?;
+        expected.push(ticket);
+    }
+
+    let mut other_epoch = create_ticket();
+    other_epoch.epoch_index = epoch_index.wrapping_add(1);
+    repo.create(&other_epoch).await?;
+
+    let mut tickets_found = repo.by_epoch_index(epoch_index).await?;
+    tickets_found.sort_by_key(|t| t.wallet);
+    expected.sort_by_key(|t| t.wallet);
+
+    assert_eq!(tickets_found, expected);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_by_epoch_index_and_prefix() -> Result<()> {
     let mut rng = thread_rng();
@@ -419,6 +519,119 @@ async fn test_distinct_sequences_by_epoch_index() -> Result<()> {
     Ok(())
 }
 
+fn sequence(nums: [u8; 6]) -> Sequence {
+    Sequence {
+        nums,
+        sequence_type: SequenceType::Normal,
+    }
+}
+
+#[tokio::test]
+async fn test_most_common_prefix_by_epoch_index() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = thread_rng().gen();
+
+    for sequences in [
+        vec![sequence([1, 2, 3, 4, 5, 6]), sequence([1, 2, 3, 4, 9, 9])],
+        // The same sequence on two tickets is counted once
+        vec![sequence([1, 2, 3, 4, 5, 6]), sequence([7, 7, 7, 7, 1, 1])],
+        vec![sequence([7, 7, 7, 7, 2, 2]), sequence([200, 0, 0, 0, 0, 0])],
+    ] {
+        let ticket = Ticket {
+            epoch_index,
+            sequences,
+            ..create_ticket()
+        };
+        repo.create(&ticket).await?;
+    }
+
+    // [1, 2, 3, 4] and [7, 7, 7, 7] both have 2 distinct sequences, the smallest prefix wins
+    let stats = repo
+        .most_common_prefix_by_epoch_index(epoch_index, 4)
+        .await?
+        .expect("should have found a prefix");
+    assert_eq!(stats.prefix, vec![1, 2, 3, 4]);
+    assert_eq!(stats.num_sequences, 2);
+    assert_eq!(stats.sequence, [1, 2, 3, 4, 5, 6]);
+
+    let stats = repo
+        .most_common_prefix_by_epoch_index(epoch_index, 1)
+        .await?
+        .expect("should have found a prefix");
+    assert_eq!(stats.prefix, vec![1]);
+
+    assert!(repo
+        .most_common_prefix_by_epoch_index(epoch_index.wrapping_add(1), 4)
+        .await?
+        .is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_prefix_matches_by_epoch_index() -> Result<()> {
+    let repo = get_repo().await;
+    let epoch_index = thread_rng().gen();
+    let combination = [10, 20, 30, 40, 50, 6];
+
+    let ticket1 = Ticket {
+        epoch_index,
+        sequences: vec![
+            sequence([10, 20, 30, 40, 50, 6]),
+            sequence([10, 20, 30, 40, 50, 7]),
+            sequence([10, 20, 30, 40, 1, 1]),
+            sequence([10, 20, 30, 41, 50, 6]),
+        ],
+        ..create_ticket()
+    };
+    let ticket2 = Ticket {
+        epoch_index,
+        sequences: vec![sequence([10, 20, 30, 40, 2, 2]), sequence([10, 20, 30, 40, 3, 3])],
+        ..create_ticket()
+    };
+    let ticket3 = Ticket {
+        epoch_index,
+        sequences: vec![sequence([1, 20, 30, 40, 50, 6])],
+        ..create_ticket()
+    };
+    for ticket in [&ticket1, &ticket2, &ticket3] {
+        repo.create(ticket).await?;
+    }
+
+    let mut matches = repo.prefix_matches_by_epoch_index(epoch_index, &combination, 4).await?;
+    matches.sort();
+    let mut expected = vec![
+        PrefixMatches {
+            wallet: ticket1.wallet,
+            prefix_len: 6,
+            num_sequences: 1,
+        },
+        PrefixMatches {
+            wallet: ticket1.wallet,
+            prefix_len: 5,
+            num_sequences: 1,
+        },
+        PrefixMatches {
+            wallet: ticket1.wallet,
+            prefix_len: 4,
+            num_sequences: 1,
+        },
+        PrefixMatches {
+            wallet: ticket2.wallet,
+            prefix_len: 4,
+            num_sequences: 2,
+        },
+    ];
+    expected.sort();
+    assert_eq!(matches, expected);
+
+    let res = repo.prefix_matches_by_epoch_index(epoch_index, &combination, 0).await;
+    assert!(matches!(
+        res.expect_err("could not get error").downcast_ref::<TicketError>(),
+        Some(TicketError::EmptyPrefix)
+    ));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_random_sequence_by_epoch_index() -> Result<()> {
     let pool = common::setup().await;
@@ -770,13 +983,14 @@ async fn test_draws_played_by_wallet() -> Result<()> {
     client
         .execute("DELETE FROM sequences WHERE wallet = $1", &[&wallet.to_string()])
         .await?;
-    let draws_played = repo.draws_played_by_wallet(&wallet).await?;
+    let draws_played = repo.draws_played_by_wallet(&wallet, u64::MAX).await?;
     assert_eq!(draws_played, 0);
 
     let expected_draws_played = 10;
-    for _ in 1..=expected_draws_played {
+    for epoch_index in 1..=expected_draws_played {
         let ticket = Ticket {
             wallet,
+            epoch_index,
             sequences: vec![Sequence {
                 nums: [1, 2, 3, 4, 5, 6],
                 sequence_type: SequenceType::Normal,
@@ -785,8 +999,14 @@ async fn test_draws_played_by_wallet() -> Result<()> {
         };
         repo.create(&ticket).await?;
     }
-    let draws_played = repo.draws_played_by_wallet(&wallet).await?;
+    let draws_played = repo.draws_played_by_wallet(&wallet, u64::MAX).await?;
     assert_eq!(draws_played, expected_draws_played);
 
+    // Epochs from the given one on aren't counted
+    let draws_played = repo.draws_played_by_wallet(&wallet, expected_draws_played).await?;
+    assert_eq!(draws_played, expected_draws_played - 1);
+    let draws_played = repo.draws_played_by_wallet(&wallet, 1).await?;
+    assert_eq!(draws_played, 0);
+
     Ok(())
 }
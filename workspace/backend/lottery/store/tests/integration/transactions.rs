@@ -31,6 +31,9 @@ fn create_transaction() -> Transaction {
                 .with_timezone(&Utc),
         ),
         transaction_type,
+        inner_instruction_index: None,
+        slot: Some(rng.gen_range(1..u32::MAX as u64)),
+        finalized: true,
     }
 }
 
@@ -45,7 +48,11 @@ async fn test_by_transaction_id_and_instruction_index() -> Result<()> {
     let transaction = create_transaction();
     repo.store_transaction(&transaction).await?;
     let stored_transaction = repo
-        .by_transaction_id_and_instruction_index(&transaction.transaction_id, transaction.instruction_index)
+        .by_transaction_id_and_instruction_index(
+            &transaction.transaction_id,
+            transaction.instruction_index,
+            transaction.inner_instruction_index,
+        )
         .await?;
     assert!(stored_transaction.is_some(), "{:?}", stored_transaction);
     assert_eq!(stored_transaction, Some(transaction));
@@ -213,7 +220,11 @@ async fn test_store_transaction() -> Result<()> {
     let transaction = create_transaction();
     repo.store_transaction(&transaction).await?;
     let stored_transaction = repo
-        .by_transaction_id_and_instruction_index(&transaction.transaction_id, transaction.instruction_index)
+        .by_transaction_id_and_instruction_index(
+            &transaction.transaction_id,
+            transaction.instruction_index,
+            transaction.inner_instruction_index,
+        )
         .await
         .expect("Failed to get transaction by transaction_id");
     assert!(stored_transaction.is_some(), "{:?}", stored_transaction);
@@ -231,7 +242,11 @@ async fn test_store_transactions() -> Result<()> {
     repo.store_transactions(&transactions).await?;
     for transaction in transactions {
         let stored_transaction = repo
-            .by_transaction_id_and_instruction_index(&transaction.transaction_id, transaction.instruction_index)
+            .by_transaction_id_and_instruction_index(
+                &transaction.transaction_id,
+                transaction.instruction_index,
+                transaction.inner_instruction_index,
+            )
             .await
             .expect("Failed to get transaction by transaction_id");
         assert!(stored_transaction.is_some(), "{:?}", stored_transaction);
@@ -263,6 +278,102 @@ async fn test_store_duplicate_transactions() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_store_inner_instructions() -> Result<()> {
+    let repo = get_user_transaction_repo().await;
+    let transaction = create_transaction();
+    let inner_transactions = (0..3)
+        .map(|inner_instruction_index| Transaction {
+            inner_instruction_index: Some(inner_instruction_index),
+            ..transaction.clone()
+        })
+        .collect::<Vec<_>>();
+    repo.store_transaction(&transaction).await?;
+    repo.store_transactions(&inner_transactions).await?;
+
+    let mut stored_transactions = repo.by_transaction_id(&transaction.transaction_id).await?;
+    stored_transactions.sort();
+    let mut expected = vec![transaction];
+    expected.extend(inner_transactions);
+    expected.sort();
+    assert_eq!(stored_transactions, expected);
+
+    for expected in expected {
+        let stored = repo
+            .by_transaction_id_and_instruction_index(
+                &expected.transaction_id,
+                expected.instruction_index,
+                expected.inner_instruction_index,
+            )
+            .await?;
+        assert_eq!(stored, Some(expected));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_finalize_transactions() -> Result<()> {
+    let repo = get_user_transaction_repo().await;
+    let mut rng = rand::thread_rng();
+    let slot = rng.gen_range(u32::MAX as u64..i64::MAX as u64 / 2);
+
+    let finalized = Transaction {
+        slot: Some(slot),
+        finalized: false,
+        ..create_transaction()
+    };
+    let dropped = Transaction {
+        slot: Some(slot),
+        finalized: false,
+        ..create_transaction()
+    };
+    let pending = Transaction {
+        slot: Some(slot + 1),
+        finalized: false,
+        ..create_transaction()
+    };
+    repo.store_transactions(&[finalized.clone(), dropped.clone(), pending.clone()])
+        .await?;
+    repo.store_transactions(&[Transaction {
+        finalized: true,
+        ..finalized.clone()
+    }])
+    .await?;
+    // Seen again at confirmed commitment
+    repo.store_transaction(&finalized).await?;
+
+    let removed = repo.remove_unfinalized(slot).await?;
+    assert!(removed >= 1);
+
+    let stored = repo
+        .by_transaction_id_and_instruction_index(
+            &finalized.transaction_id,
+            finalized.instruction_index,
+            finalized.inner_instruction_index,
+        )
+        .await?
+        .expect("Finalized transaction should be kept");
+    assert!(stored.finalized);
+    let stored = repo
+        .by_transaction_id_and_instruction_index(
+            &dropped.transaction_id,
+            dropped.instruction_index,
+            dropped.inner_instruction_index,
+        )
+        .await?;
+    assert_eq!(stored, None);
+    let stored = repo
+        .by_transaction_id_and_instruction_index(
+            &pending.transaction_id,
+            pending.instruction_index,
+            pending.inner_instruction_index,
+        )
+        .await?
+        .expect("Transaction after the finalized slot should be kept");
+    assert!(!stored.finalized);
+    Ok(())
+}
+
 async fn get_transaction_history_repo() -> PostgresTransactionHistoryRepository {
     let pool = common::setup().await;
     PostgresTransactionHistoryRepository::new(pool)
@@ -330,3 +441,37 @@ async fn test_total_deposit_by_wallet() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_balance_changes_by_wallet() -> Result<()> {
+    let repo = get_user_transaction_repo().await;
+    let wallet = Pubkey::new_unique();
+    let since = Utc::now() - chrono::Duration::days(7);
+
+    let at = |days: i64, transaction_type: TransactionType| {
+        let transaction = create_transaction();
+        Transaction {
+            wallet,
+            transaction_type,
+            time: transaction.time.map(|time| time - chrono::Duration::days(days)),
+            ..transaction
+        }
+    };
+    let withdraw = at(1, TransactionType::WithdrawCompleted);
+    let deposit = at(3, TransactionType::DepositCompleted);
+    repo.store_transactions(&[
+        withdraw.clone(),
+        deposit.clone(),
+        at(2, TransactionType::DepositApproved),
+        at(8, TransactionType::DepositCompleted),
+        Transaction {
+            wallet: Pubkey::new_unique(),
+            ..at(1, TransactionType::DepositCompleted)
+        },
+    ])
+    .await?;
+
+    let changes = repo.balance_changes_by_wallet(&wallet, since).await?;
+    assert_eq!(changes, vec![deposit, withdraw]);
+    Ok(())
+}